@@ -1,18 +1,86 @@
+use crate::cache::{BrowseCache, StatementCache};
+use crate::circuit_breaker::CircuitBreaker;
+use crate::credentials::CredentialStore;
+use crate::datafusion_handler::ConnectionContext;
 use crate::graphql::{GraphQLClient, Session};
+use crate::graphql::types::ActiveAlarm;
+use crate::graphql::client::RetryPolicy;
+use crate::jwt::JwtValidator;
+use crate::AuthMode;
+use crate::metrics::Metrics;
+use crate::query_handler::QueryResult;
+use crate::rate_limiter::RateLimiter;
+use crate::session_recovery::{RecoverableSession, SessionRecoveryFile, DEFAULT_SESSION_MAX_LIFETIME_SECS};
+use crate::sql_log::SqlLog;
 use anyhow::Result;
 use chrono::{DateTime, Utc};
 use std::collections::HashMap;
 use std::net::SocketAddr;
 use std::sync::Arc;
-use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering};
 use tokio::sync::RwLock;
 use tokio::task::JoinHandle;
 use tokio::time::{interval, Duration};
+use tokio_util::sync::CancellationToken;
 use tracing::{debug, error, info, warn};
 use uuid::Uuid;
 
 static CONNECTION_ID_COUNTER: AtomicU32 = AtomicU32::new(1);
 
+/// Address of a connected client
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClientAddr {
+    Tcp(SocketAddr),
+    // Unix domain socket peer, identified by its process credentials (SO_PEERCRED);
+    // socket_id keeps several connections of one process apart
+    Unix { pid: Option<i32>, uid: Option<u32>, socket_id: u64 },
+}
+
+impl ClientAddr {
+    /// Client address as shown in pg_stat_activity and traces
+    pub fn host(&self) -> String {
+        match self {
+            ClientAddr::Tcp(addr) => addr.ip().to_string(),
+            ClientAddr::Unix { .. } => "local".to_string(),
+        }
+    }
+
+    /// Client port, -1 for Unix domain sockets like in PostgreSQL
+    pub fn port(&self) -> i64 {
+        match self {
+            ClientAddr::Tcp(addr) => addr.port() as i64,
+            ClientAddr::Unix { .. } => -1,
+        }
+    }
+}
+
+impl std::fmt::Display for ClientAddr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ClientAddr::Tcp(addr) => write!(f, "{}", addr),
+            ClientAddr::Unix { pid, uid, .. } => {
+                write!(f, "local")?;
+                if let Some(pid) = pid {
+                    write!(f, " pid={}", pid)?;
+                }
+                if let Some(uid) = uid {
+                    write!(f, " uid={}", uid)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl From<SocketAddr> for ClientAddr {
+    fn from(addr: SocketAddr) -> Self {
+        ClientAddr::Tcp(addr)
+    }
+}
+
+/// Database reported for connections whose startup message names none
+pub const DEFAULT_DATABASE_NAME: &str = "winccua";
+
 #[derive(Debug, Clone)]
 #[allow(dead_code)]
 pub struct ConnectionInfo {
@@ -20,19 +88,74 @@ pub struct ConnectionInfo {
     pub session_id: Option<String>,         // Links to AuthenticatedSession (None if not authenticated)
     pub username: Option<String>,           // Username (None if not authenticated)
     pub database_name: Option<String>,      // Database name (None if not specified)
-    pub client_addr: SocketAddr,            // Client IP and port
+    pub client_addr: ClientAddr,            // Client IP and port, or local socket peer
     pub application_name: Option<String>,   // Client application name (None if not provided)
+    pub startup_application_name: Option<String>, // application_name of the startup message, restored by RESET
+    pub startup_timezone: Option<String>,   // TimeZone of the startup message, used until SET TimeZone
     pub backend_start: DateTime<Utc>,       // Connection start time
     pub query_start: Option<DateTime<Utc>>, // Current query start time
     pub query_stop: Option<DateTime<Utc>>,  // Query completion time
     pub state: ConnectionState,             // Connection state
+    pub waiting_on_graphql: bool,           // The running query waits for GraphQL responses
     pub last_query: String,                 // Last or current query
     pub graphql_time_ms: Option<u64>,       // GraphQL execution time in milliseconds
     pub datafusion_time_ms: Option<u64>,    // DataFusion execution time in milliseconds
     pub overall_time_ms: Option<u64>,       // Overall query execution time in milliseconds
+    pub retry_count: Option<u32>,           // GraphQL requests retried by the last query
     pub last_alive_sent: Option<DateTime<Utc>>, // Last time a keep-alive was successfully sent
+    pub session_variables: HashMap<String, String>, // Variables assigned with SET, by lowercase name
+    pub stats: ConnectionStats,             // Totals of all queries run on the connection
+}
+
+impl ConnectionInfo {
+    /// wait_event_type and wait_event of pg_stat_activity: waiting for the client while idle,
+    /// for the GraphQL server while fetching, none while DataFusion runs or in a transaction
+    pub fn wait_event(&self) -> (Option<&'static str>, Option<&'static str>) {
+        match self.state {
+            ConnectionState::Idle => (Some("Client"), Some("ClientRead")),
+            ConnectionState::Active if self.waiting_on_graphql => (Some("Activity"), Some("DataFileRead")),
+            _ => (None, None),
+        }
+    }
+}
+
+/// Query totals of a connection, shown in winccua_connection_stats
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ConnectionStats {
+    pub total_queries: u64,
+    pub total_duration: Duration,
+    pub max_duration: Duration,
+    pub graphql_errors: u64,
+    pub cache_hits: u64,
+    pub slow_queries: u64, // Queries that took at least --slow-query-threshold
 }
 
+/// A cursor opened with DECLARE: the rows of its query, evaluated when it was declared, and the
+/// position of the next row FETCH returns
+#[derive(Debug)]
+pub struct CursorState {
+    pub result: QueryResult,
+    pub position: usize,
+}
+
+/// Last value of a tag returned to a connection by tagvaluechanges
+#[derive(Debug, Clone, PartialEq)]
+pub struct TagValueSnapshot {
+    pub numeric_value: Option<f64>,
+    pub string_value: Option<String>,
+    #[allow(dead_code)]
+    pub changed_at: DateTime<Utc>, // When the value last changed
+}
+
+/// Newest timestamp returned by loggedtagvalues_incr, by tag_name filter
+type LoggedValueCursors = HashMap<String, DateTime<Utc>>;
+
+/// Active alarms returned to a connection by activealarms_changes, by name and instance_id
+pub type ActiveAlarmSnapshot = HashMap<(String, i32), ActiveAlarm>;
+
+/// (tag_name, error) of the tags the last tagvalues query could not read
+type TagErrors = Vec<(String, String)>;
+
 #[derive(Debug, Clone, PartialEq)]
 #[allow(dead_code)]
 pub enum ConnectionState {
@@ -63,6 +186,10 @@ pub struct AuthenticatedSession {
     #[allow(dead_code)]
     pub expires: String,
     pub client: Arc<GraphQLClient>,
+    pub graphql_url: String, // Backend the session was authenticated on, its queries go there too
+    pub datafusion_context: Arc<ConnectionContext>, // Reused by the queries of the connection
+    pub external_token: bool, // JWT sent by the client (--auth-mode jwt), used as is and never extended
+    pub created_at: std::time::Instant, // GraphQL login, the session is renewed after --session-max-lifetime
 }
 
 impl AuthenticatedSession {
@@ -72,7 +199,11 @@ impl AuthenticatedSession {
             username,
             token: session.token,
             expires: session.expires,
+            graphql_url: client.url().to_string(),
             client,
+            datafusion_context: Arc::new(ConnectionContext::default()),
+            external_token: false,
+            created_at: std::time::Instant::now(),
         }
     }
 
@@ -95,14 +226,119 @@ impl AuthenticatedSession {
     }
 }
 
+// Tags per GraphQL request unless overridden with --graphql-batch-size
+pub const DEFAULT_GRAPHQL_BATCH_SIZE: usize = 50;
+
+// Statement timeout in milliseconds unless overridden with --query-timeout (0 = disabled)
+pub const DEFAULT_QUERY_TIMEOUT_MS: u64 = 30_000;
+
+// LISTEN polling interval in seconds unless overridden with --listen-poll-interval
+pub const DEFAULT_LISTEN_POLL_INTERVAL_SECS: u64 = 5;
+
+// Connection limits unless overridden with --max-connections / --max-connections-per-user (0 = unlimited)
+pub const DEFAULT_MAX_CONNECTIONS: usize = 100;
+pub const DEFAULT_MAX_CONNECTIONS_PER_USER: usize = 10;
+
+/// Open client connections, counted from the startup message until the connection closes
+#[derive(Debug, Default)]
+struct ConnectionCounter {
+    total: AtomicUsize,
+    per_user: std::sync::Mutex<HashMap<String, usize>>,
+}
+
+/// Which connection limit rejected a new connection
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionLimitExceeded {
+    Global,
+    PerUser,
+}
+
+/// Slot of an open connection; dropping it when the connection ends frees the slot
+#[derive(Debug)]
+pub struct ConnectionSlot {
+    counter: Arc<ConnectionCounter>,
+    username: String,
+}
+
+impl Drop for ConnectionSlot {
+    fn drop(&mut self) {
+        self.counter.total.fetch_sub(1, Ordering::SeqCst);
+        let mut per_user = self.counter.per_user.lock().unwrap();
+        if let Some(count) = per_user.get_mut(&self.username) {
+            *count -= 1;
+            if *count == 0 {
+                per_user.remove(&self.username);
+            }
+        }
+    }
+}
+
+/// Write the GraphQL sessions to the recovery file; JWT sessions are the client's token and
+/// not listed. Errors are logged, the sessions keep working without the file.
+fn save_sessions(recovery_file: &SessionRecoveryFile, sessions: &HashMap<String, AuthenticatedSession>) {
+    let listed: Vec<RecoverableSession> = sessions
+        .values()
+        .filter(|session| !session.external_token)
+        .map(|session| RecoverableSession {
+            token: session.token.clone(),
+            username: session.username.clone(),
+            expires: session.expires.clone(),
+            graphql_url: session.graphql_url.clone(),
+        })
+        .collect();
+    if let Err(e) = recovery_file.save(&listed) {
+        warn!("⚠️  Failed to write session recovery file {}: {:#}", recovery_file.path().display(), e);
+    }
+}
+
+/// Set the drain flag, shared by SIGTERM handling and GET /drain of the health endpoint
+pub fn start_draining(draining: &AtomicBool) {
+    if !draining.swap(true, Ordering::SeqCst) {
+        info!("🚰 Entering drain mode, new connections are rejected");
+    }
+}
+
 #[derive(Debug)]
 pub struct SessionManager {
     sessions: Arc<RwLock<HashMap<String, AuthenticatedSession>>>,
     connections: Arc<RwLock<HashMap<u32, ConnectionInfo>>>,
-    graphql_url: String,
+    graphql_urls: Vec<String>, // Redundant GraphQL servers, at least one
+    graphql_primary_only: bool, // Authenticate on the first URL, the others only on failover
+    next_graphql_url: AtomicUsize, // Round-robin position for the next authentication
     extension_task_handle: Arc<RwLock<Option<JoinHandle<()>>>>,
     extension_interval_secs: u64,
     quiet_connections: bool,
+    metrics: Arc<Metrics>,
+    cancel_keys: Arc<RwLock<HashMap<(u32, u32), CancellationToken>>>, // (process_id, secret_key) -> token
+    graphql_batch_size: usize,
+    query_timeout_ms: u64,
+    listen_poll_interval: Duration,
+    shutdown: CancellationToken, // Cancelled once the server starts shutting down
+    draining: Arc<AtomicBool>, // Set in drain mode, shared with the health endpoint
+    connection_counter: Arc<ConnectionCounter>,
+    max_connections: usize,
+    max_connections_per_user: usize,
+    graphql_clients: Vec<Arc<GraphQLClient>>, // One per URL, shared by all sessions, owns the HTTP connection pool
+    graphql_pool_size: usize,
+    graphql_pool_idle_timeout: Duration,
+    graphql_retry_policy: RetryPolicy,
+    circuit_breaker: Arc<CircuitBreaker>,
+    rate_limiter: Arc<RateLimiter>, // Query token bucket per username
+    browse_cache: Arc<BrowseCache>, // Shared by all sessions, browse results of LIKE patterns
+    statement_cache: Arc<StatementCache>, // Shared by all connections, unnamed statements that passed Parse
+    tag_value_cache: Arc<RwLock<HashMap<u32, HashMap<String, TagValueSnapshot>>>>, // connection_id -> tag_name -> last value
+    logged_value_cursors: Arc<RwLock<HashMap<u32, LoggedValueCursors>>>, // connection_id -> cursors of loggedtagvalues_incr
+    active_alarm_snapshots: Arc<RwLock<HashMap<u32, ActiveAlarmSnapshot>>>, // connection_id -> alarms of the last activealarms_changes query
+    last_tag_errors: Arc<RwLock<HashMap<u32, TagErrors>>>, // connection_id -> errors of the last tagvalues query
+    cursors: Arc<RwLock<HashMap<u32, HashMap<String, CursorState>>>>, // connection_id -> cursor name -> cursor
+    scram_credentials: Option<Arc<CredentialStore>>, // Enables SCRAM-SHA-256 for the users it lists
+    jwt_validator: Option<Arc<JwtValidator>>, // Set with --auth-mode jwt, clients send a JWT instead of a password
+    auth_mode: AuthMode,
+    default_credentials: Option<(String, String)>, // --default-user and --default-password of --auth-mode trust
+    sql_log: Option<Arc<SqlLog>>, // --sql-log-file, every received query is appended
+    session_recovery: Option<Arc<SessionRecoveryFile>>, // --session-recovery-file, tokens of the open sessions
+    session_max_lifetime: Duration, // Sessions are renewed with a new login after this age, zero disables it
+    parameter_status: Arc<Vec<(String, String)>>, // ParameterStatus sent after authentication, with the --pg-param-status overrides
 }
 
 impl SessionManager {
@@ -112,14 +348,52 @@ impl SessionManager {
     }
 
     pub fn with_extension_interval(graphql_url: String, extension_interval_secs: u64) -> Self {
-        Self {
+        let mut session_manager = Self {
             sessions: Arc::new(RwLock::new(HashMap::new())),
             connections: Arc::new(RwLock::new(HashMap::new())),
-            graphql_url,
+            graphql_clients: Vec::new(),
+            graphql_pool_size: crate::graphql::client::DEFAULT_POOL_SIZE,
+            graphql_pool_idle_timeout: Duration::from_secs(crate::graphql::client::DEFAULT_POOL_IDLE_TIMEOUT_SECS),
+            graphql_retry_policy: RetryPolicy::default(),
+            circuit_breaker: Arc::new(CircuitBreaker::new(
+                crate::circuit_breaker::DEFAULT_FAILURE_THRESHOLD,
+                Duration::from_secs(crate::circuit_breaker::DEFAULT_RESET_TIMEOUT_SECS),
+            )),
+            graphql_urls: vec![graphql_url],
+            graphql_primary_only: false,
+            next_graphql_url: AtomicUsize::new(0),
             extension_task_handle: Arc::new(RwLock::new(None)),
             extension_interval_secs,
             quiet_connections: false,
-        }
+            metrics: Arc::new(Metrics::new().expect("Failed to create metrics registry")),
+            cancel_keys: Arc::new(RwLock::new(HashMap::new())),
+            graphql_batch_size: DEFAULT_GRAPHQL_BATCH_SIZE,
+            query_timeout_ms: DEFAULT_QUERY_TIMEOUT_MS,
+            listen_poll_interval: Duration::from_secs(DEFAULT_LISTEN_POLL_INTERVAL_SECS),
+            shutdown: CancellationToken::new(),
+            draining: Arc::new(AtomicBool::new(false)),
+            connection_counter: Arc::new(ConnectionCounter::default()),
+            max_connections: DEFAULT_MAX_CONNECTIONS,
+            max_connections_per_user: DEFAULT_MAX_CONNECTIONS_PER_USER,
+            rate_limiter: Arc::new(RateLimiter::default()),
+            browse_cache: Arc::new(BrowseCache::default()),
+            statement_cache: Arc::new(StatementCache::default()),
+            tag_value_cache: Arc::new(RwLock::new(HashMap::new())),
+            logged_value_cursors: Arc::new(RwLock::new(HashMap::new())),
+            active_alarm_snapshots: Arc::new(RwLock::new(HashMap::new())),
+            last_tag_errors: Arc::new(RwLock::new(HashMap::new())),
+            cursors: Arc::new(RwLock::new(HashMap::new())),
+            scram_credentials: None,
+            jwt_validator: None,
+            auth_mode: AuthMode::Md5,
+            default_credentials: None,
+            sql_log: None,
+            session_recovery: None,
+            session_max_lifetime: Duration::from_secs(DEFAULT_SESSION_MAX_LIFETIME_SECS),
+            parameter_status: Arc::new(crate::pg_protocol::settings::parameter_status_list(&[])),
+        };
+        session_manager.rebuild_graphql_clients();
+        session_manager
     }
 
     pub fn with_quiet_connections(mut self, quiet: bool) -> Self {
@@ -127,8 +401,330 @@ impl SessionManager {
         self
     }
 
+    pub fn with_metrics(mut self, metrics: Arc<Metrics>) -> Self {
+        self.metrics = metrics;
+        self.rebuild_graphql_clients();
+        self
+    }
+
+    pub fn metrics(&self) -> Arc<Metrics> {
+        self.metrics.clone()
+    }
+
+    pub fn with_graphql_batch_size(mut self, batch_size: usize) -> Self {
+        self.graphql_batch_size = batch_size.max(1);
+        self
+    }
+
+    /// Number of tag names sent per GraphQL request when fetching tag values
+    pub fn graphql_batch_size(&self) -> usize {
+        self.graphql_batch_size
+    }
+
+    pub fn with_query_timeout_ms(mut self, timeout_ms: u64) -> Self {
+        self.query_timeout_ms = timeout_ms;
+        self
+    }
+
+    /// Maximum execution time of a single query, None if the timeout is disabled
+    pub fn query_timeout(&self) -> Option<std::time::Duration> {
+        (self.query_timeout_ms > 0).then(|| std::time::Duration::from_millis(self.query_timeout_ms))
+    }
+
+    pub fn with_listen_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.listen_poll_interval = poll_interval.max(Duration::from_millis(10));
+        self
+    }
+
+    /// How often the tag of a LISTEN channel is polled for changes
+    pub fn listen_poll_interval(&self) -> Duration {
+        self.listen_poll_interval
+    }
+
+    pub fn with_shutdown_token(mut self, shutdown: CancellationToken) -> Self {
+        self.shutdown = shutdown;
+        self
+    }
+
+    /// Token cancelled on server shutdown; idle connections watch it to close themselves
+    pub fn shutdown_token(&self) -> CancellationToken {
+        self.shutdown.clone()
+    }
+
+    pub fn with_draining_flag(mut self, draining: Arc<AtomicBool>) -> Self {
+        self.draining = draining;
+        self
+    }
+
+    /// Enter drain mode: new connections are rejected while existing ones keep working
+    pub fn start_draining(&self) {
+        start_draining(&self.draining);
+    }
+
+    pub fn is_draining(&self) -> bool {
+        self.draining.load(Ordering::SeqCst)
+    }
+
+    pub fn with_connection_limits(mut self, max_connections: usize, max_connections_per_user: usize) -> Self {
+        self.max_connections = max_connections;
+        self.max_connections_per_user = max_connections_per_user;
+        self
+    }
+
+    /// Reserve a slot for a new connection of `username`, checked before authentication starts
+    pub fn try_acquire_connection_slot(&self, username: &str) -> Result<ConnectionSlot, ConnectionLimitExceeded> {
+        let mut per_user = self.connection_counter.per_user.lock().unwrap();
+        let total = self.connection_counter.total.load(Ordering::SeqCst);
+        if self.max_connections > 0 && total >= self.max_connections {
+            return Err(ConnectionLimitExceeded::Global);
+        }
+        let user_count = per_user.entry(username.to_string()).or_insert(0);
+        if self.max_connections_per_user > 0 && *user_count >= self.max_connections_per_user {
+            return Err(ConnectionLimitExceeded::PerUser);
+        }
+        *user_count += 1;
+        self.connection_counter.total.fetch_add(1, Ordering::SeqCst);
+        Ok(ConnectionSlot {
+            counter: self.connection_counter.clone(),
+            username: username.to_string(),
+        })
+    }
+
+    /// Number of open connections holding a connection slot
+    pub fn open_connection_count(&self) -> usize {
+        self.connection_counter.total.load(Ordering::SeqCst)
+    }
+
+    /// Authenticate round-robin on `urls`, or always on the first one with `primary_only`.
+    /// When the selected URL fails the next ones are tried in order.
+    pub fn with_graphql_urls(mut self, urls: Vec<String>, primary_only: bool) -> Self {
+        if !urls.is_empty() {
+            self.graphql_urls = urls;
+        }
+        self.graphql_primary_only = primary_only;
+        self.rebuild_graphql_clients();
+        self
+    }
+
+    pub fn with_graphql_pool(mut self, pool_size: usize, pool_idle_timeout: std::time::Duration) -> Self {
+        self.graphql_pool_size = pool_size;
+        self.graphql_pool_idle_timeout = pool_idle_timeout;
+        self.rebuild_graphql_clients();
+        self
+    }
+
+    pub fn with_graphql_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.graphql_retry_policy = retry_policy;
+        self.rebuild_graphql_clients();
+        self
+    }
+
+    fn rebuild_graphql_clients(&mut self) {
+        self.graphql_clients = self
+            .graphql_urls
+            .iter()
+            .map(|url| {
+                let client = GraphQLClient::with_pool(url.clone(), self.graphql_pool_size, self.graphql_pool_idle_timeout)
+                    .with_backend_requests(self.metrics.graphql_backend_requests_total.clone());
+                Arc::new(client.with_retry_policy(self.graphql_retry_policy.clone()))
+            })
+            .collect();
+    }
+
+    pub fn with_circuit_breaker(mut self, failure_threshold: u32, reset_timeout: Duration) -> Self {
+        self.circuit_breaker = Arc::new(CircuitBreaker::new(failure_threshold, reset_timeout));
+        self
+    }
+
+    /// Breaker guarding the GraphQL data requests of all sessions
+    pub fn circuit_breaker(&self) -> &CircuitBreaker {
+        &self.circuit_breaker
+    }
+
+    pub fn with_rate_limit(mut self, queries_per_second: u32, burst: u32) -> Self {
+        self.rate_limiter = Arc::new(RateLimiter::new(queries_per_second, burst));
+        self
+    }
+
+    /// Query rate limit of each user, shared by all of its connections
+    pub fn rate_limiter(&self) -> &RateLimiter {
+        &self.rate_limiter
+    }
+
+    pub fn with_browse_cache(mut self, ttl: Duration, max_entries: usize) -> Self {
+        self.browse_cache = Arc::new(BrowseCache::new(ttl, max_entries));
+        self
+    }
+
+    pub fn browse_cache(&self) -> &BrowseCache {
+        &self.browse_cache
+    }
+
+    /// Share up to `max_entries` validated unnamed statements between connections, 0 disables it
+    pub fn with_statement_cache(mut self, max_entries: usize) -> Self {
+        self.statement_cache = Arc::new(StatementCache::new(max_entries));
+        self
+    }
+
+    pub(crate) fn statement_cache(&self) -> &StatementCache {
+        &self.statement_cache
+    }
+
+    pub fn with_scram_credentials(mut self, credentials: Option<Arc<CredentialStore>>) -> Self {
+        self.scram_credentials = credentials;
+        self
+    }
+
+    /// MD5 and SCRAM-SHA-256 are verified against the password file once it is loaded
+    pub fn scram_enabled(&self) -> bool {
+        self.scram_credentials.is_some()
+    }
+
+    /// Password of a user from the SCRAM password file
+    pub fn scram_password(&self, username: &str) -> Option<&str> {
+        self.scram_credentials.as_ref()?.password(username)
+    }
+
+    pub fn with_jwt_validator(mut self, validator: Option<Arc<JwtValidator>>) -> Self {
+        self.jwt_validator = validator;
+        self
+    }
+
+    /// `default_credentials` are the GraphQL login of every connection with AuthMode::Trust
+    pub fn with_auth_mode(mut self, auth_mode: AuthMode, default_credentials: Option<(String, String)>) -> Self {
+        self.auth_mode = auth_mode;
+        self.default_credentials = default_credentials;
+        self
+    }
+
+    pub fn auth_mode(&self) -> AuthMode {
+        self.auth_mode
+    }
+
+    pub fn default_credentials(&self) -> Option<&(String, String)> {
+        self.default_credentials.as_ref()
+    }
+
+    pub fn with_sql_log(mut self, sql_log: Option<Arc<SqlLog>>) -> Self {
+        self.sql_log = sql_log;
+        self
+    }
+
+    pub fn sql_log(&self) -> Option<&SqlLog> {
+        self.sql_log.as_deref()
+    }
+
+    pub fn with_session_recovery_file(mut self, session_recovery: Option<Arc<SessionRecoveryFile>>) -> Self {
+        self.session_recovery = session_recovery;
+        self
+    }
+
+    pub fn with_session_max_lifetime(mut self, max_lifetime: Duration) -> Self {
+        self.session_max_lifetime = max_lifetime;
+        self
+    }
+
+    pub fn with_parameter_status(mut self, parameter_status: Arc<Vec<(String, String)>>) -> Self {
+        self.parameter_status = parameter_status;
+        self
+    }
+
+    /// ParameterStatus values reported to a new connection; application_name and the settings of
+    /// the startup options replace the configured values
+    pub async fn connection_parameter_status(&self, connection_id: Option<u32>) -> Vec<(String, String)> {
+        let session_variables = match connection_id {
+            Some(conn_id) => self.session_variables(conn_id).await,
+            None => HashMap::new(),
+        };
+        self.parameter_status
+            .iter()
+            .map(|(name, value)| {
+                let value = session_variables.get(&name.to_lowercase()).unwrap_or(value);
+                (name.clone(), value.clone())
+            })
+            .collect()
+    }
+
+    /// Log out the sessions a crashed previous run left in the recovery file (best-effort) and
+    /// start the file over. Returns the number of sessions that were logged out.
+    pub async fn recover_orphaned_sessions(&self) -> usize {
+        let Some(recovery_file) = &self.session_recovery else {
+            return 0;
+        };
+        let orphaned = match recovery_file.load() {
+            Ok(orphaned) => orphaned,
+            Err(e) => {
+                warn!("⚠️  Ignoring session recovery file {}: {:#}", recovery_file.path().display(), e);
+                Vec::new()
+            }
+        };
+        if !orphaned.is_empty() {
+            info!("🧹 Closing {} GraphQL session(s) left open by the previous run", orphaned.len());
+        }
+
+        let mut closed = 0;
+        for session in orphaned {
+            let client = self
+                .graphql_clients
+                .iter()
+                .find(|client| client.url() == session.graphql_url)
+                .unwrap_or(&self.graphql_clients[0]);
+            match client.close_session(&session.token).await {
+                Ok(()) => {
+                    debug!("🧹 Closed orphaned session of user {} on {}", session.username, session.graphql_url);
+                    closed += 1;
+                }
+                Err(e) => warn!("⚠️  Failed to close orphaned session of user {} on {}: {}", session.username, session.graphql_url, e),
+            }
+        }
+        self.persist_sessions().await;
+        closed
+    }
+
+    /// Write the open sessions to the recovery file, if there is one
+    async fn persist_sessions(&self) {
+        if let Some(recovery_file) = &self.session_recovery {
+            save_sessions(recovery_file, &*self.sessions.read().await);
+        }
+    }
+
+    /// Whether the GraphQL session is older than --session-max-lifetime and has to be renewed.
+    /// JWT sessions use the client's token and end when it expires.
+    pub fn session_lifetime_exceeded(&self, session: &AuthenticatedSession) -> bool {
+        !session.external_token && !self.session_max_lifetime.is_zero() && session.created_at.elapsed() >= self.session_max_lifetime
+    }
+
+    /// Replace the session of a connection with a new GraphQL login and log out the old one
+    pub async fn renew_session(&self, connection_id: Option<u32>, session: &AuthenticatedSession, password: &str) -> Result<AuthenticatedSession> {
+        let renewed = self.authenticate(&session.username, password).await?;
+        if let Some(conn_id) = connection_id {
+            if let Some(conn) = self.connections.write().await.get_mut(&conn_id) {
+                conn.session_id = Some(renewed.session_id.clone());
+            }
+        }
+        self.remove_session(&session.session_id).await;
+        if let Err(e) = session.client.close_session(&session.token).await {
+            debug!("⚠️  Failed to log out the replaced session {}: {}", session.session_id, e);
+        }
+        if !self.quiet_connections {
+            info!("🔑 Renewed the GraphQL session of user {} after {}s", session.username, session.created_at.elapsed().as_secs());
+        }
+        Ok(renewed)
+    }
+
+    /// The first (primary) GraphQL URL
     pub fn graphql_url(&self) -> &str {
-        &self.graphql_url
+        &self.graphql_urls[0]
+    }
+
+    /// Clients in the order they are tried for the next authentication
+    fn graphql_clients_for_login(&self) -> Vec<Arc<GraphQLClient>> {
+        let start = if self.graphql_primary_only {
+            0
+        } else {
+            self.next_graphql_url.fetch_add(1, Ordering::Relaxed) % self.graphql_clients.len()
+        };
+        self.graphql_clients.iter().cycle().skip(start).take(self.graphql_clients.len()).cloned().collect()
     }
 
     pub fn extension_interval_secs(&self) -> u64 {
@@ -138,22 +734,72 @@ impl SessionManager {
     pub async fn authenticate(&self, username: &str, password: &str) -> Result<AuthenticatedSession> {
         debug!("Authenticating user: {}", username);
         
-        let client = Arc::new(GraphQLClient::new(self.graphql_url.clone()));
-        let session = client.login(username, password).await?;
-        
+        let mut last_error = None;
+        let mut authenticated = None;
+        for client in self.graphql_clients_for_login() {
+            match client.login(username, password).await {
+                Ok(session) => {
+                    authenticated = Some((session, client));
+                    break;
+                }
+                // A server that answered and rejected the credentials is not failed over
+                Err(e) if e.to_string().starts_with("Login failed") => {
+                    last_error = Some(e);
+                    break;
+                }
+                Err(e) => {
+                    if self.graphql_clients.len() > 1 {
+                        warn!("⚠️  Login on GraphQL server {} failed, trying the next one: {}", client.url(), e);
+                    }
+                    last_error = Some(e);
+                }
+            }
+        }
+        let Some((session, client)) = authenticated else {
+            self.metrics.auth_failures_total.inc();
+            return Err(last_error.unwrap_or_else(|| anyhow::anyhow!("No GraphQL server configured")));
+        };
+
         let auth_session = AuthenticatedSession::new(username.to_string(), session, client);
+        self.rate_limiter.add_user(username);
         
         // Store the session
         let mut sessions = self.sessions.write().await;
         sessions.insert(auth_session.session_id.clone(), auth_session.clone());
         
-        // Start the session extension task if this is the first session
-        if sessions.len() == 1 {
-            drop(sessions); // Release the lock before starting the task
-            self.start_session_extension_task().await;
-        }
+        // Start the session extension task unless it is already running (JWT sessions don't start it)
+        drop(sessions); // Release the lock before starting the task
+        self.persist_sessions().await;
+        self.start_session_extension_task().await;
         
-        debug!("User {} authenticated successfully with session {}", username, auth_session.session_id);
+        debug!("User {} authenticated successfully with session {} on {}", username, auth_session.session_id, auth_session.graphql_url);
+        Ok(auth_session)
+    }
+
+    /// Create a session for a JWT sent as password. The token is validated locally and then
+    /// used as the GraphQL bearer token, there is no GraphQL login.
+    pub async fn authenticate_jwt(&self, token: &str) -> Result<AuthenticatedSession> {
+        let validator = self.jwt_validator.as_ref().ok_or_else(|| anyhow::anyhow!("JWT authentication is not enabled"))?;
+        let claims = match validator.validate(token) {
+            Ok(claims) => claims,
+            Err(e) => {
+                self.metrics.auth_failures_total.inc();
+                return Err(e);
+            }
+        };
+        let client = self.graphql_clients_for_login().remove(0);
+        let session = Session {
+            token: token.trim().to_string(),
+            expires: claims.expires_at.to_rfc3339(),
+            user: None,
+            error: None,
+        };
+        let mut auth_session = AuthenticatedSession::new(claims.subject, session, client);
+        auth_session.external_token = true;
+        self.rate_limiter.add_user(&auth_session.username);
+        self.sessions.write().await.insert(auth_session.session_id.clone(), auth_session.clone());
+
+        debug!("User {} authenticated with a JWT valid until {}, session {}", auth_session.username, auth_session.expires, auth_session.session_id);
         Ok(auth_session)
     }
 
@@ -170,6 +816,12 @@ impl SessionManager {
             if !self.quiet_connections {
                 info!("🛑 Removed session {} for user {}", session_id, session.username);
             }
+            if !sessions.values().any(|other| other.username == session.username) {
+                self.rate_limiter.remove_user(&session.username);
+            }
+            if let Some(recovery_file) = &self.session_recovery {
+                save_sessions(recovery_file, &sessions);
+            }
         }
         
         // Stop the extension task if no sessions remain
@@ -180,6 +832,28 @@ impl SessionManager {
     }
 
 
+    /// Drop all sessions and connection records, used as the last step of a shutdown
+    pub async fn close_all_sessions(&self) {
+        let remaining_connections = {
+            let mut connections = self.connections.write().await;
+            let count = connections.len();
+            connections.clear();
+            count
+        };
+        self.cancel_keys.write().await.clear();
+        self.metrics.connections_active.sub(remaining_connections as i64);
+
+        let closed_sessions = {
+            let mut sessions = self.sessions.write().await;
+            let count = sessions.len();
+            sessions.clear();
+            count
+        };
+        self.persist_sessions().await;
+        self.stop_session_extension_task().await;
+        info!("🛑 Closed {} sessions and {} remaining connections", closed_sessions, remaining_connections);
+    }
+
     #[allow(dead_code)]
     pub async fn session_count(&self) -> usize {
         self.sessions.read().await.len()
@@ -191,9 +865,11 @@ impl SessionManager {
         let mut handle_guard = self.extension_task_handle.write().await;
         let extension_interval_secs = self.extension_interval_secs;
         let quiet_connections = self.quiet_connections;
+        let session_recovery = self.session_recovery.clone();
+        let session_max_lifetime = self.session_max_lifetime;
         
         // Don't start a new task if one is already running
-        if handle_guard.is_some() {
+        if handle_guard.as_ref().is_some_and(|handle| !handle.is_finished()) {
             return;
         }
         
@@ -214,7 +890,14 @@ impl SessionManager {
                 // Get all current sessions
                 let sessions_to_extend = {
                     let sessions = sessions_clone.read().await;
-                    sessions.values().cloned().collect::<Vec<_>>()
+                    // JWT sessions cannot be extended, they end when the token expires. Sessions beyond
+                    // --session-max-lifetime are left to expire, their connection logs in again.
+                    sessions
+                        .values()
+                        .filter(|session| !session.external_token)
+                        .filter(|session| session_max_lifetime.is_zero() || session.created_at.elapsed() < session_max_lifetime)
+                        .cloned()
+                        .collect::<Vec<_>>()
                 };
                 
                 if sessions_to_extend.is_empty() {
@@ -243,6 +926,10 @@ impl SessionManager {
                     }
                 }
                 
+                if let Some(recovery_file) = &session_recovery {
+                    save_sessions(recovery_file, &*sessions_clone.read().await);
+                }
+
                 // Check if we still have sessions after extension attempts
                 let remaining_sessions = sessions_clone.read().await.len();
                 if remaining_sessions == 0 {
@@ -274,8 +961,10 @@ impl SessionManager {
     pub async fn register_connection(
         &self,
         session_id: &str,
-        client_addr: SocketAddr,
+        client_addr: ClientAddr,
         application_name: String,
+        database_name: String,
+        timezone: Option<String>,
     ) -> Result<u32> {
         let sessions = self.sessions.read().await;
         let session = sessions.get(session_id)
@@ -287,22 +976,29 @@ impl SessionManager {
             connection_id,
             session_id: Some(session_id.to_string()),
             username: Some(session.username.clone()),
-            database_name: Some("winccua".to_string()),
+            database_name: Some(database_name),
             client_addr,
-            application_name: Some(application_name),
+            application_name: Some(application_name.clone()),
+            startup_application_name: Some(application_name),
+            startup_timezone: timezone,
             backend_start: Utc::now(),
             query_start: None,
             query_stop: None,
             state: ConnectionState::Idle,
+            waiting_on_graphql: false,
             last_query: String::new(),
             graphql_time_ms: None,
             datafusion_time_ms: None,
             overall_time_ms: None,
+            retry_count: None,
             last_alive_sent: None,
+            session_variables: HashMap::new(),
+            stats: ConnectionStats::default(),
         };
         
         let mut connections = self.connections.write().await;
         connections.insert(connection_id, connection_info);
+        self.metrics.connections_active.inc();
         
         if !self.quiet_connections {
             info!("📊 Registered connection {} for user {} from {}", 
@@ -312,11 +1008,81 @@ impl SessionManager {
         Ok(connection_id)
     }
     
+    /// Assign a random secret key to a connection for BackendKeyData / CancelRequest.
+    /// The connection ID is used as the process ID.
+    pub async fn register_cancel_key(&self, connection_id: u32) -> u32 {
+        let secret_key = rand::random::<u32>();
+        self.cancel_keys
+            .write()
+            .await
+            .insert((connection_id, secret_key), CancellationToken::new());
+        secret_key
+    }
+
+    /// Token for the next query on a connection. A token cancelled by an earlier
+    /// CancelRequest is replaced so it does not abort later queries.
+    pub async fn query_cancellation_token(&self, connection_id: u32) -> Option<CancellationToken> {
+        let mut cancel_keys = self.cancel_keys.write().await;
+        let (_, token) = cancel_keys
+            .iter_mut()
+            .find(|((process_id, _), _)| *process_id == connection_id)?;
+        if token.is_cancelled() {
+            *token = CancellationToken::new();
+        }
+        Some(token.clone())
+    }
+
+    /// Cancel the running query of the connection identified by a CancelRequest.
+    /// Returns false if the process ID / secret key pair is unknown.
+    pub async fn cancel_query(&self, process_id: u32, secret_key: u32) -> bool {
+        match self.cancel_keys.read().await.get(&(process_id, secret_key)) {
+            Some(token) => {
+                token.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Cancel the running query of every connection; returns the number of cancelled tokens
+    pub async fn cancel_all_queries(&self) -> usize {
+        let cancel_keys = self.cancel_keys.read().await;
+        for token in cancel_keys.values() {
+            token.cancel();
+        }
+        cancel_keys.len()
+    }
+
+    /// Number of connections currently executing a query
+    pub async fn active_query_count(&self) -> usize {
+        self.connections
+            .read()
+            .await
+            .values()
+            .filter(|conn| conn.state == ConnectionState::Active)
+            .count()
+    }
+
+    /// Number of registered (authenticated) connections
+    pub async fn connection_count(&self) -> usize {
+        self.connections.read().await.len()
+    }
+
     /// Unregister a connection and remove the session if no other connections are using it
     pub async fn unregister_connection(&self, connection_id: u32) {
         let session_id_to_check = {
             let mut connections = self.connections.write().await;
+            self.cancel_keys
+                .write()
+                .await
+                .retain(|(process_id, _), _| *process_id != connection_id);
+            self.tag_value_cache.write().await.remove(&connection_id);
+            self.logged_value_cursors.write().await.remove(&connection_id);
+            self.active_alarm_snapshots.write().await.remove(&connection_id);
+            self.last_tag_errors.write().await.remove(&connection_id);
+            self.cursors.write().await.remove(&connection_id);
             if let Some(conn) = connections.remove(&connection_id) {
+                self.metrics.connections_active.dec();
                 if !self.quiet_connections {
                     info!("📊 Unregistered connection {} for user {:?} from {}", 
                         connection_id, conn.username, conn.client_addr);
@@ -353,6 +1119,7 @@ impl SessionManager {
             conn.graphql_time_ms = None;
             conn.datafusion_time_ms = None;
             conn.overall_time_ms = None;
+            conn.retry_count = None;
             debug!("📊 Connection {} started query: {}", connection_id, query);
         }
     }
@@ -363,6 +1130,7 @@ impl SessionManager {
         let mut connections = self.connections.write().await;
         if let Some(conn) = connections.get_mut(&connection_id) {
             conn.state = ConnectionState::Idle;
+            conn.waiting_on_graphql = false;
             conn.query_stop = Some(Utc::now());
             
             // Calculate overall time if query_start is available
@@ -377,6 +1145,13 @@ impl SessionManager {
         }
     }
 
+    /// Mark the running query of a connection as waiting for GraphQL, shown in wait_event
+    pub async fn set_waiting_on_graphql(&self, connection_id: u32, waiting: bool) {
+        if let Some(conn) = self.connections.write().await.get_mut(&connection_id) {
+            conn.waiting_on_graphql = waiting;
+        }
+    }
+
     /// Update query timing metrics
     #[allow(dead_code)]
     pub async fn set_query_timings(&self, connection_id: u32, graphql_time_ms: Option<u64>, datafusion_time_ms: Option<u64>) {
@@ -393,21 +1168,39 @@ impl SessionManager {
 
     /// Update query timing metrics including overall time
     #[allow(dead_code)]
-    pub async fn set_all_query_timings(&self, connection_id: u32, graphql_time_ms: Option<u64>, datafusion_time_ms: Option<u64>, overall_time_ms: Option<u64>) {
+    pub async fn set_all_query_timings(&self, connection_id: u32, graphql_time_ms: Option<u64>, datafusion_time_ms: Option<u64>, overall_time_ms: Option<u64>, retry_count: u32) {
         let mut connections = self.connections.write().await;
         if let Some(conn) = connections.get_mut(&connection_id) {
             conn.graphql_time_ms = graphql_time_ms;
             conn.datafusion_time_ms = datafusion_time_ms;
             conn.overall_time_ms = overall_time_ms;
-            debug!("📊 Updated connection {} timing - GraphQL: {:?}ms, DataFusion: {:?}ms, Overall: {:?}ms", 
-                connection_id, graphql_time_ms, datafusion_time_ms, overall_time_ms);
+            conn.retry_count = Some(retry_count);
+            debug!("📊 Updated connection {} timing - GraphQL: {:?}ms, DataFusion: {:?}ms, Overall: {:?}ms, Retries: {}", 
+                connection_id, graphql_time_ms, datafusion_time_ms, overall_time_ms, retry_count);
         } else {
             warn!("📊 Could not find connection {} to update timing", connection_id);
         }
     }
     
+    /// Add a finished query, successful or not, to the statistics of the connection
+    pub async fn record_query_stats(&self, connection_id: u32, duration: Duration, graphql_errors: u64, cache_hits: u64) {
+        let mut connections = self.connections.write().await;
+        if let Some(conn) = connections.get_mut(&connection_id) {
+            let stats = &mut conn.stats;
+            stats.total_queries += 1;
+            stats.total_duration += duration;
+            stats.max_duration = stats.max_duration.max(duration);
+            stats.graphql_errors += graphql_errors;
+            stats.cache_hits += cache_hits;
+            let slow_query_threshold_ms = crate::SLOW_QUERY_THRESHOLD_MS.load(Ordering::Relaxed);
+            if slow_query_threshold_ms > 0 && duration.as_millis() >= slow_query_threshold_ms as u128 {
+                stats.slow_queries += 1;
+            }
+        }
+    }
+
     /// Clean up connections and sessions for a specific client address (used for abrupt disconnections)
-    pub async fn cleanup_connections_by_address(&self, client_addr: SocketAddr) {
+    pub async fn cleanup_connections_by_address(&self, client_addr: ClientAddr) {
         let mut connections_to_remove = Vec::new();
         
         // Find all connections from this client address
@@ -436,6 +1229,191 @@ impl SessionManager {
         connections.values().cloned().collect()
     }
     
+    /// Client address of a registered connection
+    pub async fn connection_client_addr(&self, connection_id: u32) -> Option<ClientAddr> {
+        let connections = self.connections.read().await;
+        connections.get(&connection_id).map(|conn| conn.client_addr)
+    }
+
+    /// Store a variable assigned with SET for the rest of the connection
+    pub async fn set_session_variable(&self, connection_id: u32, name: &str, value: &str) {
+        let mut connections = self.connections.write().await;
+        if let Some(conn) = connections.get_mut(&connection_id) {
+            let name = name.to_lowercase();
+            if name == "application_name" {
+                conn.application_name = Some(value.to_string());
+            }
+            debug!("🔧 Connection {} set {} = '{}'", connection_id, name, value);
+            conn.session_variables.insert(name, value.to_string());
+        }
+    }
+
+    /// Drop a variable assigned with SET, or all of them without a name, so the server setting
+    /// or the application_name of the startup message applies again
+    pub async fn reset_session_variable(&self, connection_id: u32, name: Option<&str>) {
+        let mut connections = self.connections.write().await;
+        if let Some(conn) = connections.get_mut(&connection_id) {
+            match name.map(str::to_lowercase) {
+                Some(name) => {
+                    if name == "application_name" {
+                        conn.application_name = conn.startup_application_name.clone();
+                    }
+                    debug!("🔧 Connection {} reset {}", connection_id, name);
+                    conn.session_variables.remove(&name);
+                }
+                None => {
+                    conn.application_name = conn.startup_application_name.clone();
+                    debug!("🔧 Connection {} reset all {} variables", connection_id, conn.session_variables.len());
+                    conn.session_variables.clear();
+                }
+            }
+        }
+    }
+
+    /// Variables assigned on a connection, including the application_name and TimeZone from the
+    /// startup message
+    pub async fn session_variables(&self, connection_id: u32) -> HashMap<String, String> {
+        let connections = self.connections.read().await;
+        let Some(conn) = connections.get(&connection_id) else {
+            return HashMap::new();
+        };
+        let mut variables = conn.session_variables.clone();
+        if let Some(application_name) = &conn.application_name {
+            variables.insert("application_name".to_string(), application_name.clone());
+        }
+        if let Some(timezone) = &conn.startup_timezone {
+            variables.entry("timezone".to_string()).or_insert_with(|| timezone.clone());
+        }
+        variables
+    }
+
+    /// Database named in the connection's startup message
+    pub async fn database_name(&self, connection_id: u32) -> String {
+        self.connections
+            .read()
+            .await
+            .get(&connection_id)
+            .and_then(|conn| conn.database_name.clone())
+            .unwrap_or_else(|| DEFAULT_DATABASE_NAME.to_string())
+    }
+
+    pub async fn set_last_tag_errors(&self, connection_id: u32, errors: TagErrors) {
+        self.last_tag_errors.write().await.insert(connection_id, errors);
+    }
+
+    /// (tag_name, error) of the tags the last tagvalues query of the connection could not read
+    pub async fn last_tag_errors(&self, connection_id: u32) -> TagErrors {
+        self.last_tag_errors.read().await.get(&connection_id).cloned().unwrap_or_default()
+    }
+
+    pub async fn has_cursor(&self, connection_id: u32, name: &str) -> bool {
+        self.cursors.read().await.get(&connection_id).is_some_and(|cursors| cursors.contains_key(name))
+    }
+
+    /// Open a cursor on the rows of `result`; false if the connection already has one named `name`
+    pub async fn declare_cursor(&self, connection_id: u32, name: &str, result: QueryResult) -> bool {
+        let mut cursors = self.cursors.write().await;
+        let cursors = cursors.entry(connection_id).or_default();
+        if cursors.contains_key(name) {
+            return false;
+        }
+        cursors.insert(name.to_string(), CursorState { result, position: 0 });
+        true
+    }
+
+    /// The next `count` rows of a cursor, all remaining ones for None, and advance it past them.
+    /// None if the connection has no cursor named `name`.
+    pub async fn fetch_from_cursor(&self, connection_id: u32, name: &str, count: Option<usize>) -> Option<QueryResult> {
+        let mut cursors = self.cursors.write().await;
+        let cursor = cursors.get_mut(&connection_id)?.get_mut(name)?;
+        let start = cursor.position.min(cursor.result.rows.len());
+        let end = count.map_or(cursor.result.rows.len(), |count| start.saturating_add(count).min(cursor.result.rows.len()));
+        cursor.position = end;
+
+        let mut fetched = QueryResult::new(cursor.result.columns.clone(), cursor.result.column_types.clone());
+        fetched.rows = cursor.result.rows[start..end].to_vec();
+        Some(fetched)
+    }
+
+    /// Columns of a cursor without fetching from it
+    pub async fn cursor_columns(&self, connection_id: u32, name: &str) -> Option<QueryResult> {
+        let cursors = self.cursors.read().await;
+        let cursor = cursors.get(&connection_id)?.get(name)?;
+        Some(QueryResult::new(cursor.result.columns.clone(), cursor.result.column_types.clone()))
+    }
+
+    /// Close a cursor, or all cursors of the connection for None; false if there is no such cursor
+    pub async fn close_cursor(&self, connection_id: u32, name: Option<&str>) -> bool {
+        let mut cursors = self.cursors.write().await;
+        match name {
+            Some(name) => cursors.get_mut(&connection_id).is_some_and(|cursors| cursors.remove(name).is_some()),
+            None => {
+                cursors.remove(&connection_id);
+                true
+            }
+        }
+    }
+
+    /// Compare tag values with the ones this connection saw last and remember the new ones.
+    /// Returns one flag per value, true for tags seen for the first time or with a changed value.
+    pub async fn detect_tag_value_changes(
+        &self,
+        connection_id: u32,
+        values: &[(String, Option<f64>, Option<String>)],
+    ) -> Vec<bool> {
+        let mut cache = self.tag_value_cache.write().await;
+        let snapshots = cache.entry(connection_id).or_default();
+        values
+            .iter()
+            .map(|(tag_name, numeric_value, string_value)| {
+                let unchanged = snapshots.get(tag_name).is_some_and(|snapshot| {
+                    snapshot.numeric_value == *numeric_value && snapshot.string_value == *string_value
+                });
+                if !unchanged {
+                    snapshots.insert(
+                        tag_name.clone(),
+                        TagValueSnapshot {
+                            numeric_value: *numeric_value,
+                            string_value: string_value.clone(),
+                            changed_at: Utc::now(),
+                        },
+                    );
+                }
+                !unchanged
+            })
+            .collect()
+    }
+
+    /// Remember the active alarms returned to this connection and hand back the ones of the
+    /// previous activealarms_changes query, empty on the first query
+    pub async fn replace_active_alarm_snapshot(&self, connection_id: u32, alarms: &[ActiveAlarm]) -> ActiveAlarmSnapshot {
+        let snapshot = alarms
+            .iter()
+            .map(|alarm| ((alarm.name.clone(), alarm.instance_id), alarm.clone()))
+            .collect();
+        self.active_alarm_snapshots
+            .write()
+            .await
+            .insert(connection_id, snapshot)
+            .unwrap_or_default()
+    }
+
+    /// Newest timestamp loggedtagvalues_incr returned to this connection for the tag_name filter `key`
+    pub async fn logged_value_cursor(&self, connection_id: u32, key: &str) -> Option<DateTime<Utc>> {
+        self.logged_value_cursors
+            .read()
+            .await
+            .get(&connection_id)
+            .and_then(|cursors| cursors.get(key).copied())
+    }
+
+    /// Move the cursor of `key` forward to `timestamp`; it never moves back
+    pub async fn advance_logged_value_cursor(&self, connection_id: u32, key: &str, timestamp: DateTime<Utc>) {
+        let mut cursors = self.logged_value_cursors.write().await;
+        let cursor = cursors.entry(connection_id).or_default().entry(key.to_string()).or_insert(timestamp);
+        *cursor = (*cursor).max(timestamp);
+    }
+
     /// Update last keep-alive sent time for a connection
     pub async fn update_last_alive_sent(&self, connection_id: u32) {
         let mut connections = self.connections.write().await;
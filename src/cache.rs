@@ -0,0 +1,271 @@
+use crate::graphql::types::BrowseResult;
+use crate::pg_protocol::PreparedStatement;
+use anyhow::Result;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::debug;
+
+// Defaults unless overridden with --browse-cache-ttl / --browse-cache-max-entries
+pub const DEFAULT_BROWSE_CACHE_TTL_SECS: u64 = 60;
+pub const DEFAULT_BROWSE_CACHE_MAX_ENTRIES: usize = 1000;
+
+// Default unless overridden with --statement-cache-size
+pub const DEFAULT_STATEMENT_CACHE_SIZE: usize = 200;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hit_count: u64,
+    pub miss_count: u64,
+    pub eviction_count: u64,
+    pub entry_count: u64,
+}
+
+#[derive(Debug)]
+struct CacheEntry {
+    results: Vec<BrowseResult>,
+    fetched_at: Instant,
+    last_used: u64, // Value of the access counter when the entry was last read or written
+}
+
+#[derive(Debug, Default)]
+struct LruCache {
+    entries: HashMap<String, CacheEntry>,
+    access_counter: u64,
+    stats: CacheStats,
+}
+
+/// Browse results by browse pattern, so repeated LIKE queries skip the expensive browse of the
+/// tag namespace. Entries expire after the TTL; when full, the least recently used one is evicted.
+#[derive(Debug)]
+pub struct BrowseCache {
+    ttl: Duration, // Zero disables the cache
+    max_entries: usize,
+    cache: Mutex<LruCache>,
+}
+
+impl BrowseCache {
+    pub fn new(ttl: Duration, max_entries: usize) -> Self {
+        Self {
+            ttl,
+            max_entries,
+            cache: Mutex::new(LruCache::default()),
+        }
+    }
+
+    fn is_enabled(&self) -> bool {
+        !self.ttl.is_zero() && self.max_entries > 0
+    }
+
+    /// Return the cached results for `pattern`, or run `fetch` and cache its results
+    pub async fn get_or_fetch<F>(&self, pattern: &str, fetch: F) -> Result<Vec<BrowseResult>>
+    where
+        F: Future<Output = Result<Vec<BrowseResult>>>,
+    {
+        if !self.is_enabled() {
+            return fetch.await;
+        }
+        if let Some(results) = self.get(pattern) {
+            debug!("📦 Browse cache hit for pattern '{}'", pattern);
+            crate::metrics::record_cache_hit();
+            return Ok(results);
+        }
+
+        let results = fetch.await?;
+        self.insert(pattern, results.clone());
+        Ok(results)
+    }
+
+    fn get(&self, pattern: &str) -> Option<Vec<BrowseResult>> {
+        let mut cache = self.cache.lock().unwrap();
+        cache.access_counter += 1;
+        let access_counter = cache.access_counter;
+
+        let fresh = match cache.entries.get_mut(pattern) {
+            Some(entry) if entry.fetched_at.elapsed() < self.ttl => {
+                entry.last_used = access_counter;
+                Some(entry.results.clone())
+            }
+            _ => None,
+        };
+        match fresh {
+            Some(_) => cache.stats.hit_count += 1,
+            None => cache.stats.miss_count += 1,
+        }
+        fresh
+    }
+
+    fn insert(&self, pattern: &str, results: Vec<BrowseResult>) {
+        let mut cache = self.cache.lock().unwrap();
+        cache.access_counter += 1;
+        let access_counter = cache.access_counter;
+
+        if !cache.entries.contains_key(pattern) && cache.entries.len() >= self.max_entries {
+            let least_recently_used = cache
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(pattern, _)| pattern.clone());
+            if let Some(evicted) = least_recently_used {
+                cache.entries.remove(&evicted);
+                cache.stats.eviction_count += 1;
+            }
+        }
+        cache.entries.insert(
+            pattern.to_string(),
+            CacheEntry {
+                results,
+                fetched_at: Instant::now(),
+                last_used: access_counter,
+            },
+        );
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        let cache = self.cache.lock().unwrap();
+        CacheStats {
+            entry_count: cache.entries.len() as u64,
+            ..cache.stats
+        }
+    }
+}
+
+impl Default for BrowseCache {
+    fn default() -> Self {
+        Self::new(
+            Duration::from_secs(DEFAULT_BROWSE_CACHE_TTL_SECS),
+            DEFAULT_BROWSE_CACHE_MAX_ENTRIES,
+        )
+    }
+}
+
+#[derive(Debug)]
+struct StatementEntry {
+    statement: Arc<PreparedStatement>,
+    last_used: u64,
+}
+
+#[derive(Debug, Default)]
+struct StatementLru {
+    entries: HashMap<String, StatementEntry>,
+    access_counter: u64,
+}
+
+/// Unnamed statements that passed Parse, by SHA-256 of the query text. Shared by all
+/// connections, so dashboards opening a connection per panel validate each query once.
+/// When full, the least recently used statement is evicted.
+#[derive(Debug)]
+pub(crate) struct StatementCache {
+    max_entries: usize, // Zero disables the cache
+    cache: Mutex<StatementLru>,
+}
+
+impl StatementCache {
+    pub(crate) fn new(max_entries: usize) -> Self {
+        Self {
+            max_entries,
+            cache: Mutex::new(StatementLru::default()),
+        }
+    }
+
+    fn key(query: &str) -> String {
+        hex::encode(Sha256::digest(query.as_bytes()))
+    }
+
+    pub(crate) fn get(&self, query: &str) -> Option<Arc<PreparedStatement>> {
+        if self.max_entries == 0 {
+            return None;
+        }
+        let mut cache = self.cache.lock().unwrap();
+        cache.access_counter += 1;
+        let access_counter = cache.access_counter;
+        cache.entries.get_mut(&Self::key(query)).map(|entry| {
+            entry.last_used = access_counter;
+            entry.statement.clone()
+        })
+    }
+
+    pub(crate) fn insert(&self, query: &str, statement: Arc<PreparedStatement>) {
+        if self.max_entries == 0 {
+            return;
+        }
+        let key = Self::key(query);
+        let mut cache = self.cache.lock().unwrap();
+        cache.access_counter += 1;
+        let access_counter = cache.access_counter;
+
+        if !cache.entries.contains_key(&key) && cache.entries.len() >= self.max_entries {
+            let least_recently_used = cache
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| key.clone());
+            if let Some(evicted) = least_recently_used {
+                cache.entries.remove(&evicted);
+            }
+        }
+        cache.entries.insert(key, StatementEntry { statement, last_used: access_counter });
+    }
+}
+
+impl Default for StatementCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_STATEMENT_CACHE_SIZE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn browse_result(name: &str) -> BrowseResult {
+        BrowseResult {
+            name: name.to_string(),
+            display_name: None,
+            object_type: Some("TAG".to_string()),
+            data_type: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_expired_and_least_recently_used_entries_are_refetched() {
+        let cache = BrowseCache::new(Duration::from_millis(50), 2);
+        let fetch = |name: &'static str| async move { Ok(vec![browse_result(name)]) };
+
+        cache.get_or_fetch("A*", fetch("A1")).await.unwrap();
+        cache.get_or_fetch("B*", fetch("B1")).await.unwrap();
+        // A* is used more recently than B*, so B* is evicted for C*
+        assert_eq!(cache.get_or_fetch("A*", fetch("A2")).await.unwrap()[0].name, "A1");
+        cache.get_or_fetch("C*", fetch("C1")).await.unwrap();
+        assert_eq!(cache.get_or_fetch("B*", fetch("B2")).await.unwrap()[0].name, "B2");
+        assert_eq!(
+            cache.stats(),
+            CacheStats { hit_count: 1, miss_count: 4, eviction_count: 2, entry_count: 2 }
+        );
+
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        assert_eq!(cache.get_or_fetch("B*", fetch("B3")).await.unwrap()[0].name, "B3");
+    }
+
+    #[test]
+    fn test_least_recently_used_statement_is_evicted() {
+        let cache = StatementCache::new(2);
+        let statement = |query: &str| {
+            Arc::new(PreparedStatement { name: String::new(), query: query.to_string(), parameter_types: Vec::new() })
+        };
+
+        cache.insert("SELECT 1", statement("SELECT 1"));
+        cache.insert("SELECT 2", statement("SELECT 2"));
+        assert!(cache.get("SELECT 1").is_some());
+        cache.insert("SELECT 3", statement("SELECT 3"));
+        assert!(cache.get("SELECT 2").is_none());
+        assert_eq!(cache.get("SELECT 1").unwrap().query, "SELECT 1");
+        assert!(cache.get("SELECT 3").is_some());
+
+        let disabled = StatementCache::new(0);
+        disabled.insert("SELECT 1", statement("SELECT 1"));
+        assert!(disabled.get("SELECT 1").is_none());
+    }
+}
@@ -0,0 +1,232 @@
+use crate::query_handler::SqlStateError;
+use anyhow::Result;
+use std::future::Future;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+use tracing::{info, warn};
+
+// Defaults unless overridden with --cb-failure-threshold / --cb-reset-timeout
+pub const DEFAULT_FAILURE_THRESHOLD: u32 = 5;
+pub const DEFAULT_RESET_TIMEOUT_SECS: u64 = 30;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitState {
+    Closed,   // Requests pass, consecutive failures are counted
+    Open,     // Requests fail immediately until the reset timeout expires
+    HalfOpen, // A single probe request decides whether to close or re-open
+}
+
+#[derive(Debug)]
+struct BreakerState {
+    state: CircuitState,
+    opened_at: Option<Instant>,
+    probe_started: Option<Instant>,
+}
+
+/// Stops sending GraphQL requests while the backend is unreachable, so queries fail fast
+/// with SQLSTATE 08006 instead of each one waiting for its own network error
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    failure_threshold: u32, // 0 disables the breaker
+    reset_timeout: Duration,
+    consecutive_failures: AtomicU32,
+    rejected_requests: AtomicU32,
+    state: Mutex<BreakerState>,
+}
+
+impl CircuitBreaker {
+    pub fn new(failure_threshold: u32, reset_timeout: Duration) -> Self {
+        Self {
+            failure_threshold,
+            reset_timeout,
+            consecutive_failures: AtomicU32::new(0),
+            rejected_requests: AtomicU32::new(0),
+            state: Mutex::new(BreakerState {
+                state: CircuitState::Closed,
+                opened_at: None,
+                probe_started: None,
+            }),
+        }
+    }
+
+    #[allow(dead_code)]
+    pub async fn state(&self) -> CircuitState {
+        self.state.lock().await.state
+    }
+
+    /// Run a GraphQL request through the breaker. Only transport failures (connection errors,
+    /// timeouts) count; a GraphQL error response proves the backend is reachable.
+    pub async fn call<T, F>(&self, request: F) -> Result<T>
+    where
+        F: Future<Output = Result<T>>,
+    {
+        if self.failure_threshold == 0 {
+            return request.await;
+        }
+        self.acquire().await?;
+
+        let result = request.await;
+        match &result {
+            Err(e) if is_transport_failure(e) => self.record_failure().await,
+            _ => self.record_success().await,
+        }
+        result
+    }
+
+    async fn acquire(&self) -> Result<()> {
+        let mut state = self.state.lock().await;
+        match state.state {
+            CircuitState::Closed => Ok(()),
+            CircuitState::Open => {
+                let elapsed = state.opened_at.map(|t| t.elapsed()).unwrap_or_default();
+                if elapsed >= self.reset_timeout {
+                    info!("🔌 Circuit breaker half-open, sending a probe request to the GraphQL server");
+                    state.state = CircuitState::HalfOpen;
+                    state.probe_started = Some(Instant::now());
+                    Ok(())
+                } else {
+                    Err(self.reject(self.reset_timeout - elapsed))
+                }
+            }
+            CircuitState::HalfOpen => {
+                // A probe that was dropped (cancelled query) never reports back; allow a new one
+                let stale_probe = state.probe_started.is_none_or(|t| t.elapsed() >= self.reset_timeout);
+                if stale_probe {
+                    state.probe_started = Some(Instant::now());
+                    Ok(())
+                } else {
+                    Err(self.reject(Duration::ZERO))
+                }
+            }
+        }
+    }
+
+    fn reject(&self, retry_in: Duration) -> anyhow::Error {
+        self.rejected_requests.fetch_add(1, Ordering::Relaxed);
+        SqlStateError::new(
+            "08006",
+            format!(
+                "GraphQL server unavailable, circuit breaker is open (retry in {} s)",
+                retry_in.as_secs()
+            ),
+        )
+        .into()
+    }
+
+    async fn record_success(&self) {
+        self.consecutive_failures.store(0, Ordering::SeqCst);
+        let mut state = self.state.lock().await;
+        if state.state != CircuitState::Closed {
+            info!(
+                "✅ Circuit breaker closed, GraphQL server reachable again ({} requests rejected while open)",
+                self.rejected_requests.swap(0, Ordering::Relaxed)
+            );
+            state.state = CircuitState::Closed;
+            state.opened_at = None;
+            state.probe_started = None;
+        }
+    }
+
+    async fn record_failure(&self) {
+        let failures = self.consecutive_failures.fetch_add(1, Ordering::SeqCst) + 1;
+        let mut state = self.state.lock().await;
+        let open = match state.state {
+            CircuitState::HalfOpen => true,
+            CircuitState::Closed => failures >= self.failure_threshold,
+            CircuitState::Open => false,
+        };
+        if open {
+            warn!(
+                "🚧 Circuit breaker open after {} consecutive GraphQL failures, failing queries for {} s",
+                failures,
+                self.reset_timeout.as_secs()
+            );
+            state.state = CircuitState::Open;
+            state.opened_at = Some(Instant::now());
+            state.probe_started = None;
+        }
+    }
+}
+
+fn is_transport_failure(e: &anyhow::Error) -> bool {
+    e.chain().any(|cause| {
+        cause
+            .downcast_ref::<reqwest::Error>()
+            .is_some_and(|e| e.is_connect() || e.is_timeout() || e.is_request())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicBool;
+
+    async fn transport_failure() -> Result<()> {
+        reqwest::get("http://127.0.0.1:1/graphql").await?;
+        Ok(())
+    }
+
+    fn sql_state(e: &anyhow::Error) -> Option<&'static str> {
+        e.downcast_ref::<SqlStateError>().map(|e| e.code)
+    }
+
+    #[tokio::test]
+    async fn test_breaker_opens_after_consecutive_failures() {
+        let breaker = CircuitBreaker::new(3, Duration::from_secs(60));
+
+        // Errors from a reachable backend do not count
+        let _ = breaker.call(async { Err::<(), _>(anyhow::anyhow!("tag not found")) }).await;
+        for _ in 0..2 {
+            assert!(breaker.call(transport_failure()).await.is_err());
+        }
+        assert_eq!(breaker.state().await, CircuitState::Closed);
+        assert!(breaker.call(transport_failure()).await.is_err());
+        assert_eq!(breaker.state().await, CircuitState::Open);
+
+        let attempted = AtomicBool::new(false);
+        let err = breaker
+            .call(async {
+                attempted.store(true, Ordering::SeqCst);
+                Ok(())
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(sql_state(&err), Some("08006"));
+        assert!(!attempted.load(Ordering::SeqCst), "no request is sent while the breaker is open");
+    }
+
+    #[tokio::test]
+    async fn test_half_open_probe_closes_or_reopens() {
+        let breaker = CircuitBreaker::new(1, Duration::from_millis(100));
+        assert!(breaker.call(transport_failure()).await.is_err());
+        assert_eq!(breaker.state().await, CircuitState::Open);
+
+        // A failed probe re-opens the breaker for another reset timeout
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        assert!(breaker.call(transport_failure()).await.is_err());
+        assert_eq!(breaker.state().await, CircuitState::Open);
+        let err = breaker.call(async { Ok(()) }).await.unwrap_err();
+        assert_eq!(sql_state(&err), Some("08006"));
+
+        // Only one probe is let through; concurrent requests are rejected until it completes
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        let (release_probe, probe_released) = tokio::sync::oneshot::channel::<()>();
+        let probe = breaker.call(async {
+            probe_released.await.unwrap();
+            Ok(())
+        });
+        let concurrent = async {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            assert_eq!(breaker.state().await, CircuitState::HalfOpen);
+            let err = breaker.call(async { Ok(()) }).await.unwrap_err();
+            assert_eq!(sql_state(&err), Some("08006"));
+            release_probe.send(()).unwrap();
+        };
+        let (probe_result, _) = tokio::join!(probe, concurrent);
+        probe_result.unwrap();
+
+        assert_eq!(breaker.state().await, CircuitState::Closed);
+        breaker.call(async { Ok(()) }).await.unwrap();
+    }
+}
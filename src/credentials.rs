@@ -0,0 +1,79 @@
+use anyhow::{anyhow, Context, Result};
+use std::collections::HashMap;
+use tracing::{info, warn};
+
+/// Local passwords for SCRAM-SHA-256, loaded from --scram-password-file.
+///
+/// SCRAM never sends the password over the wire, but the GraphQL login after the SCRAM
+/// exchange needs it in cleartext, so the file keeps one `username:password` per line.
+/// Hashes (bcrypt or SCRAM verifiers) would verify the client but not log it in to GraphQL.
+#[derive(Debug, Default)]
+pub struct CredentialStore {
+    passwords: HashMap<String, String>,
+}
+
+impl CredentialStore {
+    pub fn load(path: &str) -> Result<Self> {
+        let content = std::fs::read_to_string(path).with_context(|| format!("Failed to read SCRAM password file {}", path))?;
+        warn_if_readable_by_others(path);
+        let store = Self::parse(&content).with_context(|| format!("Invalid SCRAM password file {}", path))?;
+        info!("🔐 Loaded SCRAM credentials for {} users from {}", store.passwords.len(), path);
+        Ok(store)
+    }
+
+    /// Lines are `username:password`; empty lines and lines starting with # are skipped
+    pub(crate) fn parse(content: &str) -> Result<Self> {
+        let mut passwords = HashMap::new();
+        for (index, line) in content.lines().enumerate() {
+            let line = line.trim_end_matches('\r');
+            if line.trim().is_empty() || line.trim_start().starts_with('#') {
+                continue;
+            }
+            let (username, password) = line
+                .split_once(':')
+                .ok_or_else(|| anyhow!("line {}: expected username:password", index + 1))?;
+            let username = username.trim();
+            if username.is_empty() || password.is_empty() {
+                return Err(anyhow!("line {}: username and password must not be empty", index + 1));
+            }
+            if passwords.insert(username.to_string(), password.to_string()).is_some() {
+                return Err(anyhow!("line {}: duplicate user '{}'", index + 1, username));
+            }
+        }
+        Ok(Self { passwords })
+    }
+
+    pub fn password(&self, username: &str) -> Option<&str> {
+        self.passwords.get(username).map(String::as_str)
+    }
+}
+
+#[cfg(unix)]
+fn warn_if_readable_by_others(path: &str) {
+    use std::os::unix::fs::PermissionsExt;
+    if let Ok(metadata) = std::fs::metadata(path) {
+        if metadata.permissions().mode() & 0o077 != 0 {
+            warn!("⚠️  SCRAM password file {} is accessible by other users, restrict it with chmod 600", path);
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn warn_if_readable_by_others(_path: &str) {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_credentials() {
+        let store = CredentialStore::parse("# operators\ngrafana:s3cr:et\n\nadmin:admin pw\n").unwrap();
+        assert_eq!(store.password("grafana"), Some("s3cr:et"));
+        assert_eq!(store.password("admin"), Some("admin pw"));
+        assert_eq!(store.password("unknown"), None);
+
+        assert!(CredentialStore::parse("grafana").unwrap_err().to_string().contains("line 1"));
+        assert!(CredentialStore::parse("a:1\na:2").unwrap_err().to_string().contains("duplicate"));
+        assert!(CredentialStore::parse(":pw").is_err());
+    }
+}
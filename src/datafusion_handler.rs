@@ -1,23 +1,399 @@
-use anyhow::Result;
+use anyhow::{anyhow, Result};
+use arrow::array::{AsArray, StringArray};
+use arrow::datatypes::{DataType, Int32Type};
 use arrow::record_batch::RecordBatch;
+use datafusion::catalog::MemorySchemaProvider;
+use datafusion::common::ScalarValue;
+use datafusion::datasource::MemTable;
+use datafusion::error::DataFusionError;
+use datafusion::execution::SessionStateBuilder;
+use datafusion::logical_expr::planner::TypePlanner;
+use datafusion::logical_expr::{create_udf, ColumnarValue, ScalarUDF, Volatility};
+use datafusion::sql::sqlparser::ast;
+use datafusion::sql::TableReference;
 use datafusion::prelude::*;
+use std::collections::HashMap;
+use std::sync::Arc;
 use std::time::Instant;
 use tracing::debug;
 
+#[allow(dead_code)]
 pub async fn execute_query(
     sql: &str,
     batch: RecordBatch,
     table_name: &str,
+) -> Result<(Vec<RecordBatch>, u64)> {
+    execute_query_with_tables(sql, vec![(table_name.to_string(), batch)]).await
+}
+
+pub async fn execute_query_with_tables(
+    sql: &str,
+    tables: Vec<(String, RecordBatch)>,
+) -> Result<(Vec<RecordBatch>, u64)> {
+    execute_query_with_udfs(sql, tables, Vec::new()).await
+}
+
+/// Like `execute_query_with_tables`, with additional scalar functions registered
+pub async fn execute_query_with_udfs(
+    sql: &str,
+    tables: Vec<(String, RecordBatch)>,
+    udfs: Vec<ScalarUDF>,
 ) -> Result<(Vec<RecordBatch>, u64)> {
     let start_time = Instant::now();
     
-    let ctx = SessionContext::new();
-    ctx.register_batch(table_name, batch)?;
-    let df = ctx.sql(sql).await?;
-    let results = df.collect().await?;
+    let ctx = new_session_context();
+    for udf in udfs {
+        ctx.register_udf(udf);
+    }
+    for (table_name, batch) in tables {
+        register_table(&ctx, &table_name, batch)?;
+    }
+    let results = collect_query(&ctx, sql).await?;
     
     let elapsed_ms = start_time.elapsed().as_millis() as u64;
     debug!("⚡ DataFusion query execution completed in {} ms", elapsed_ms);
     
     Ok((results, elapsed_ms))
 }
+
+/// DataFusion context of one connection. It is created on the first query and reused by the
+/// following ones, which only replace the tables and the per-query functions.
+#[derive(Default)]
+pub struct ConnectionContext {
+    ctx: tokio::sync::Mutex<Option<SessionContext>>,
+}
+
+impl std::fmt::Debug for ConnectionContext {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ConnectionContext").finish_non_exhaustive()
+    }
+}
+
+impl ConnectionContext {
+    /// Same as `execute_query_with_udfs`, on the context of the connection
+    pub async fn execute_query_with_udfs(
+        &self,
+        sql: &str,
+        tables: Vec<(String, RecordBatch)>,
+        udfs: Vec<ScalarUDF>,
+    ) -> Result<(Vec<RecordBatch>, u64)> {
+        let start_time = Instant::now();
+
+        // Queries of a connection run one after the other, the lock only guards against overlapping tables
+        let mut guard = self.ctx.lock().await;
+        let ctx = guard.get_or_insert_with(new_session_context);
+        for udf in udfs {
+            ctx.register_udf(udf);
+        }
+        let table_names: Vec<String> = tables.iter().map(|(table_name, _)| table_name.clone()).collect();
+        let registered = tables.into_iter().try_for_each(|(table_name, batch)| {
+            // A cancelled query may have left its tables behind
+            deregister_table(ctx, &table_name)?;
+            register_table(ctx, &table_name, batch)
+        });
+        let results = match registered {
+            Ok(()) => collect_query(ctx, sql).await,
+            Err(e) => Err(e),
+        };
+        // Release the batches until the next query brings fresh ones
+        for table_name in &table_names {
+            deregister_table(ctx, table_name)?;
+        }
+        let results = results?;
+
+        let elapsed_ms = start_time.elapsed().as_millis() as u64;
+        debug!("⚡ DataFusion query execution completed in {} ms (connection context)", elapsed_ms);
+
+        Ok((results, elapsed_ms))
+    }
+}
+
+/// The step of the DataFusion query that failed, reported as WHERE of the ErrorResponse
+pub fn failed_step(error: &DataFusionError) -> &'static str {
+    match error.find_root() {
+        DataFusionError::SQL(..) => "DataFusion SQL parsing",
+        DataFusionError::Plan(_) | DataFusionError::SchemaError(..) | DataFusionError::NotImplemented(_) => "DataFusion query planning",
+        DataFusionError::Configuration(_) => "DataFusion configuration",
+        DataFusionError::Internal(_) => "DataFusion internal error",
+        _ => "DataFusion query execution",
+    }
+}
+
+fn new_session_context() -> SessionContext {
+    let state = SessionStateBuilder::new()
+        .with_default_features()
+        .with_type_planner(Arc::new(JsonTypePlanner))
+        .build();
+    let ctx = SessionContext::new_with_state(state);
+    register_winccua_udfs(&ctx);
+    ctx
+}
+
+async fn collect_query(ctx: &SessionContext, sql: &str) -> Result<Vec<RecordBatch>> {
+    let df = ctx.sql(sql).await?;
+    let schema = Arc::new(df.schema().as_arrow().clone());
+    let mut results = df.collect().await?;
+    // Keep the column definitions for queries that return no rows
+    if results.is_empty() {
+        results.push(RecordBatch::new_empty(schema));
+    }
+    Ok(results)
+}
+
+/// Plans `::json` and `::jsonb` casts as LargeUtf8, which marks a column as JSON in the results
+#[derive(Debug)]
+struct JsonTypePlanner;
+
+impl TypePlanner for JsonTypePlanner {
+    fn plan_type(&self, sql_type: &ast::DataType) -> datafusion::error::Result<Option<DataType>> {
+        match sql_type {
+            ast::DataType::JSON | ast::DataType::JSONB => Ok(Some(DataType::LargeUtf8)),
+            _ => Ok(None),
+        }
+    }
+}
+
+/// `current_setting(name)`: a variable assigned with SET on the connection, or the server setting
+pub fn current_setting_udf(session_variables: HashMap<String, String>) -> ScalarUDF {
+    let lookup = move |name: &str| -> datafusion::error::Result<String> {
+        session_variables
+            .get(&name.to_lowercase())
+            .cloned()
+            .or_else(|| crate::pg_protocol::settings::get_postgresql_setting(name).map(|setting| setting.value.to_string()))
+            .ok_or_else(|| DataFusionError::Execution(format!("unrecognized configuration parameter \"{}\"", name)))
+    };
+
+    create_udf(
+        "current_setting",
+        vec![DataType::Utf8],
+        DataType::Utf8,
+        Volatility::Stable,
+        Arc::new(move |args: &[ColumnarValue]| match &args[0] {
+            ColumnarValue::Scalar(ScalarValue::Utf8(Some(name))) => {
+                Ok(ColumnarValue::Scalar(ScalarValue::Utf8(Some(lookup(name)?))))
+            }
+            ColumnarValue::Scalar(_) => Ok(ColumnarValue::Scalar(ScalarValue::Utf8(None))),
+            ColumnarValue::Array(names) => {
+                let names = names.as_string::<i32>();
+                let values = names
+                    .iter()
+                    .map(|name| name.map(&lookup).transpose())
+                    .collect::<datafusion::error::Result<StringArray>>()?;
+                Ok(ColumnarValue::Array(Arc::new(values)))
+            }
+        }),
+    )
+}
+
+/// WinCC-specific scalar functions as (name, argument type, return type, description)
+pub const WINCCUA_FUNCTIONS: &[(&str, &str, &str, &str)] = &[
+    ("winccua_quality_code", "text", "bigint", "OPC UA status code of a quality: 0 for GOOD, 1073741824 for UNCERTAIN, 2147483648 for BAD"),
+    ("winccua_is_good", "text", "boolean", "True when the quality starts with GOOD"),
+    ("winccua_quality_description", "integer", "text", "Main quality of a quality_code: GOOD for 0, UNCERTAIN for 64, BAD for 128"),
+    ("winccua_tag_system", "text", "text", "System of a tag name, the part before the first ::"),
+    ("winccua_tag_path", "text", "text", "Tag name without its :value_name suffix"),
+    ("winccua_parse_duration", "text", "bigint", "Seconds of an ISO 8601 duration like PT1H30M45S, as in loggedalarms.duration"),
+];
+
+/// Register the functions of `WINCCUA_FUNCTIONS`, available in every query
+pub fn register_winccua_udfs(ctx: &SessionContext) {
+    ctx.register_udf(text_udf("winccua_quality_code", DataType::Int64, |quality| {
+        ScalarValue::Int64(winccua_quality_code(quality))
+    }));
+    ctx.register_udf(text_udf("winccua_is_good", DataType::Boolean, |quality| {
+        ScalarValue::Boolean(Some(quality.starts_with("GOOD")))
+    }));
+    ctx.register_udf(integer_udf("winccua_quality_description", DataType::Utf8, |code| {
+        ScalarValue::Utf8(crate::quality::quality_description(code).map(str::to_string))
+    }));
+    ctx.register_udf(text_udf("winccua_tag_system", DataType::Utf8, |tag_name| {
+        ScalarValue::Utf8(tag_name.split("::").next().map(str::to_string))
+    }));
+    ctx.register_udf(text_udf("winccua_tag_path", DataType::Utf8, |tag_name| {
+        ScalarValue::Utf8(Some(winccua_tag_path(tag_name).to_string()))
+    }));
+    ctx.register_udf(text_udf("winccua_parse_duration", DataType::Int64, |duration| {
+        ScalarValue::Int64(crate::query_handler::QueryHandler::parse_iso_duration_to_seconds(duration))
+    }));
+}
+
+/// Severity bits of the OPC UA status code for a WinCC quality like GOOD_CASCADE or BAD_COMM_FAILURE
+fn winccua_quality_code(quality: &str) -> Option<i64> {
+    crate::quality::parse_quality_code(quality).map(|code| i64::from(code) << 24)
+}
+
+/// Strip the `:value_name` suffix, e.g. `Server::Pump1::Speed:PV` -> `Server::Pump1::Speed`;
+/// the `::` path separators are not mistaken for it
+fn winccua_tag_path(tag_name: &str) -> &str {
+    let bytes = tag_name.as_bytes();
+    let suffix_start = (0..bytes.len()).rev().find(|&i| {
+        bytes[i] == b':' && (i == 0 || bytes[i - 1] != b':') && bytes.get(i + 1) != Some(&b':')
+    });
+    match suffix_start {
+        Some(i) => &tag_name[..i],
+        None => tag_name,
+    }
+}
+
+/// A function of one text argument; NULL in gives NULL out
+fn text_udf(
+    name: &str,
+    return_type: DataType,
+    function: impl Fn(&str) -> ScalarValue + Send + Sync + 'static,
+) -> ScalarUDF {
+    let null = ScalarValue::try_from(&return_type).unwrap_or(ScalarValue::Null);
+    let result_type = return_type.clone();
+    create_udf(
+        name,
+        vec![DataType::Utf8],
+        return_type,
+        Volatility::Immutable,
+        Arc::new(move |args: &[ColumnarValue]| {
+            let arrays = ColumnarValue::values_to_arrays(args)?;
+            let values = arrow::compute::cast(&arrays[0], &DataType::Utf8)?;
+            let results = values
+                .as_string::<i32>()
+                .iter()
+                .map(|value| value.map_or_else(|| null.clone(), &function))
+                .collect::<Vec<_>>();
+            if results.is_empty() {
+                return Ok(ColumnarValue::Array(arrow::array::new_empty_array(&result_type)));
+            }
+            Ok(ColumnarValue::Array(ScalarValue::iter_to_array(results)?))
+        }),
+    )
+}
+
+/// A function of one integer argument; NULL in gives NULL out
+fn integer_udf(
+    name: &str,
+    return_type: DataType,
+    function: impl Fn(i32) -> ScalarValue + Send + Sync + 'static,
+) -> ScalarUDF {
+    let null = ScalarValue::try_from(&return_type).unwrap_or(ScalarValue::Null);
+    let result_type = return_type.clone();
+    create_udf(
+        name,
+        vec![DataType::Int32],
+        return_type,
+        Volatility::Immutable,
+        Arc::new(move |args: &[ColumnarValue]| {
+            let arrays = ColumnarValue::values_to_arrays(args)?;
+            let values = arrow::compute::cast(&arrays[0], &DataType::Int32)?;
+            let results = values
+                .as_primitive::<Int32Type>()
+                .iter()
+                .map(|value| value.map_or_else(|| null.clone(), &function))
+                .collect::<Vec<_>>();
+            if results.is_empty() {
+                return Ok(ColumnarValue::Array(arrow::array::new_empty_array(&result_type)));
+            }
+            Ok(ColumnarValue::Array(ScalarValue::iter_to_array(results)?))
+        }),
+    )
+}
+
+/// `current_database()`: the database named in the connection's startup message
+pub fn current_database_udf(database_name: String) -> ScalarUDF {
+    create_udf(
+        "current_database",
+        vec![],
+        DataType::Utf8,
+        Volatility::Stable,
+        Arc::new(move |_: &[ColumnarValue]| Ok(ColumnarValue::Scalar(ScalarValue::Utf8(Some(database_name.clone()))))),
+    )
+}
+
+/// The connection a query runs on, as reported by the session functions
+#[derive(Debug, Clone)]
+pub struct SessionInfo {
+    pub username: String,
+    pub database_name: String,
+    pub connection_id: Option<u32>, // The backend process ID sent in BackendKeyData
+}
+
+/// `current_user`, `session_user`, `current_database()` and `pg_backend_pid()` of the
+/// connection. DataFusion would otherwise answer current_user with the OS user of the server.
+pub fn session_udfs(session_info: &SessionInfo) -> Vec<ScalarUDF> {
+    let username = Arc::new(session_info.username.clone());
+    let user_udf = |name: &str| {
+        let username = username.clone();
+        create_udf(
+            name,
+            vec![],
+            DataType::Utf8,
+            Volatility::Stable,
+            Arc::new(move |_: &[ColumnarValue]| Ok(ColumnarValue::Scalar(ScalarValue::Utf8(Some(username.to_string()))))),
+        )
+    };
+    let backend_pid = session_info.connection_id.map(|id| id as i32);
+    vec![
+        user_udf("current_user"),
+        user_udf("session_user"),
+        current_database_udf(session_info.database_name.clone()),
+        create_udf(
+            "pg_backend_pid",
+            vec![],
+            DataType::Int32,
+            Volatility::Stable,
+            Arc::new(move |_: &[ColumnarValue]| Ok(ColumnarValue::Scalar(ScalarValue::Int32(backend_pid)))),
+        ),
+    ]
+}
+
+/// `winccua_last_errors()`: the tags the last tagvalues query of the connection could not read,
+/// as a JSON array of {"tag_name", "error"} objects
+pub fn last_errors_udf(errors: Vec<(String, String)>) -> ScalarUDF {
+    let errors: Vec<serde_json::Value> = errors
+        .into_iter()
+        .map(|(tag_name, error)| serde_json::json!({"tag_name": tag_name, "error": error}))
+        .collect();
+    let json = serde_json::Value::Array(errors).to_string();
+    create_udf(
+        "winccua_last_errors",
+        vec![],
+        DataType::LargeUtf8,
+        Volatility::Stable,
+        Arc::new(move |_: &[ColumnarValue]| Ok(ColumnarValue::Scalar(ScalarValue::LargeUtf8(Some(json.clone()))))),
+    )
+}
+
+/// Register a batch under its table name. Schema-qualified names get their schema created
+/// on demand; pg_catalog tables are also registered unqualified, as pg_catalog is always
+/// on the PostgreSQL search path, and so are information_schema tables, which clients
+/// sometimes query without the schema.
+fn register_table(ctx: &SessionContext, table_name: &str, batch: RecordBatch) -> Result<()> {
+    let Some((schema_name, bare_name)) = table_name.split_once('.') else {
+        ctx.register_batch(table_name, batch)?;
+        return Ok(());
+    };
+
+    let catalog = ctx
+        .catalog("datafusion")
+        .ok_or_else(|| anyhow!("DataFusion default catalog not found"))?;
+    if catalog.schema(schema_name).is_none() {
+        catalog.register_schema(schema_name, Arc::new(MemorySchemaProvider::new()))?;
+    }
+    let table = MemTable::try_new(batch.schema(), vec![vec![batch.clone()]])?;
+    ctx.register_table(TableReference::partial(schema_name, bare_name), Arc::new(table))?;
+
+    if schema_name == "pg_catalog" || schema_name == "information_schema" {
+        ctx.register_batch(bare_name, batch)?;
+    }
+    Ok(())
+}
+
+/// Remove a table registered by `register_table`, including its unqualified alias
+fn deregister_table(ctx: &SessionContext, table_name: &str) -> Result<()> {
+    let Some((schema_name, bare_name)) = table_name.split_once('.') else {
+        ctx.deregister_table(table_name)?;
+        return Ok(());
+    };
+    if ctx.catalog("datafusion").and_then(|catalog| catalog.schema(schema_name)).is_some() {
+        ctx.deregister_table(TableReference::partial(schema_name, bare_name))?;
+    }
+    if schema_name == "pg_catalog" || schema_name == "information_schema" {
+        ctx.deregister_table(bare_name)?;
+    }
+    Ok(())
+}
@@ -1,19 +1,337 @@
 use super::types::*;
 use anyhow::{anyhow, Result};
-use reqwest::Client;
-use tracing::{debug, error};
+use prometheus::IntCounterVec;
+use rand::Rng;
+use reqwest::{Client, RequestBuilder, Response, StatusCode};
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, error, warn, Instrument};
+
+trait TracedSend {
+    async fn send_traced(self, retry_policy: &RetryPolicy) -> reqwest::Result<Response>;
+    async fn send_observed(self, client: &GraphQLClient) -> reqwest::Result<Response>;
+    async fn send_mutation(self, client: &GraphQLClient) -> reqwest::Result<Response>;
+}
+
+impl TracedSend for RequestBuilder {
+    /// Send the request inside a graphql.request span recording the URL and response status,
+    /// retrying transient failures according to `retry_policy`
+    async fn send_traced(self, retry_policy: &RetryPolicy) -> reqwest::Result<Response> {
+        send_with_retries(self, retry_policy, true).await
+    }
+
+    /// send_traced with the retry policy of `client`, counting the outcome for its backend URL
+    async fn send_observed(self, client: &GraphQLClient) -> reqwest::Result<Response> {
+        let result = send_with_retries(self, &client.retry_policy, true).await;
+        client.count_backend_request(&result);
+        result
+    }
+
+    /// send_observed for mutations that change the plant (tag writes, alarm acknowledgments).
+    /// A timed out or interrupted request may have been executed already, so only requests
+    /// the server certainly did not process are repeated: connect errors, 429 and 503.
+    async fn send_mutation(self, client: &GraphQLClient) -> reqwest::Result<Response> {
+        let result = send_with_retries(self, &client.retry_policy, false).await;
+        client.count_backend_request(&result);
+        result
+    }
+}
+
+// `retry_sent` also retries timeouts and errors after the request was sent
+async fn send_with_retries(builder: RequestBuilder, retry_policy: &RetryPolicy, retry_sent: bool) -> reqwest::Result<Response> {
+    let mut builder = builder;
+    let mut attempt = 0;
+    loop {
+        // Bodies are always JSON, so the request can be cloned for another attempt
+        let next_attempt = builder.try_clone();
+        let result = send_once(builder).await;
+        let retryable = match &result {
+            Ok(response) => is_retryable_status(response.status()),
+            Err(e) => e.is_connect() || (retry_sent && (e.is_timeout() || e.is_request())),
+        };
+        match next_attempt {
+            Some(next) if retryable && attempt < retry_policy.max_retries => {
+                let backoff = retry_policy.backoff(attempt);
+                match &result {
+                    Ok(response) => warn!("🔁 GraphQL request returned {}, retrying in {} ms", response.status(), backoff.as_millis()),
+                    Err(e) => warn!("🔁 GraphQL request failed ({}), retrying in {} ms", e, backoff.as_millis()),
+                }
+                let _ = RETRY_COUNTER.try_with(|counter| counter.fetch_add(1, Ordering::Relaxed));
+                tokio::time::sleep(backoff).await;
+                attempt += 1;
+                builder = next;
+            }
+            _ => return result,
+        }
+    }
+}
+
+async fn send_once(builder: RequestBuilder) -> reqwest::Result<Response> {
+    let (client, request) = builder.build_split();
+    let request = request?;
+    let span = tracing::info_span!(
+        "graphql.request",
+        http.method = %request.method(),
+        http.url = %request.url(),
+        http.status_code = tracing::field::Empty
+    );
+    let response = client.execute(request).instrument(span.clone()).await;
+    if let Ok(response) = &response {
+        span.record("http.status_code", response.status().as_u16());
+    }
+    response
+}
+
+// Only overload responses are retried; 400/401/403 and other errors are permanent
+fn is_retryable_status(status: StatusCode) -> bool {
+    matches!(status, StatusCode::TOO_MANY_REQUESTS | StatusCode::SERVICE_UNAVAILABLE)
+}
+
+// Retry defaults unless overridden with --graphql-max-retries / --graphql-retry-initial-backoff
+pub const DEFAULT_MAX_RETRIES: u32 = 3;
+pub const DEFAULT_RETRY_INITIAL_BACKOFF_MS: u64 = 100;
+pub const DEFAULT_RETRY_MAX_BACKOFF_MS: u64 = 5_000;
+
+/// How often and how long to wait before repeating a GraphQL request that failed transiently
+/// (network error, HTTP 429 or 503)
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    pub max_retries: u32,        // 0 disables retries
+    pub initial_backoff_ms: u64, // Wait before the first retry, doubled for every further one
+    pub max_backoff_ms: u64,
+    pub jitter: bool, // Randomize each wait between half and the full backoff
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: DEFAULT_MAX_RETRIES,
+            initial_backoff_ms: DEFAULT_RETRY_INITIAL_BACKOFF_MS,
+            max_backoff_ms: DEFAULT_RETRY_MAX_BACKOFF_MS,
+            jitter: true,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Fail on the first error, for startup and readiness checks that report the state as is
+    pub fn none() -> Self {
+        Self { max_retries: 0, ..Self::default() }
+    }
+
+    /// Wait before retry number `attempt + 1`
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let backoff_ms = self
+            .initial_backoff_ms
+            .saturating_mul(1u64 << attempt.min(32))
+            .min(self.max_backoff_ms);
+        if self.jitter && backoff_ms > 1 {
+            Duration::from_millis(rand::rng().random_range(backoff_ms / 2..=backoff_ms))
+        } else {
+            Duration::from_millis(backoff_ms)
+        }
+    }
+}
+
+tokio::task_local! {
+    // Retries made for the query running on this task, see count_retries
+    static RETRY_COUNTER: Arc<AtomicU32>;
+}
+
+/// Run `future` and count the GraphQL request retries it caused
+pub async fn count_retries<F: Future>(future: F) -> (F::Output, u32) {
+    let counter = Arc::new(AtomicU32::new(0));
+    let output = RETRY_COUNTER.scope(counter.clone(), future).await;
+    (output, counter.load(Ordering::Relaxed))
+}
+
+/// Retry counter of the current task, to be carried into spawned tasks with `with_retry_counter`
+pub fn retry_counter() -> Option<Arc<AtomicU32>> {
+    RETRY_COUNTER.try_with(Arc::clone).ok()
+}
+
+/// Run `future` counting its retries into `counter`
+pub async fn with_retry_counter<F: Future>(counter: Option<Arc<AtomicU32>>, future: F) -> F::Output {
+    match counter {
+        Some(counter) => RETRY_COUNTER.scope(counter, future).await,
+        None => future.await,
+    }
+}
+
+// Connection pool defaults unless overridden with --graphql-pool-size / --graphql-pool-idle-timeout
+pub const DEFAULT_POOL_SIZE: usize = 20;
+pub const DEFAULT_POOL_IDLE_TIMEOUT_SECS: u64 = 90;
+
+// TCP keep-alive for pooled connections, so idle connections survive stateful firewalls
+const TCP_KEEPALIVE: Duration = Duration::from_secs(60);
+
+// Browse paging defaults unless overridden with --browse-page-size / --browse-max-pages / --browse-max-tags
+pub const DEFAULT_BROWSE_PAGE_SIZE: u32 = 500;
+pub const DEFAULT_BROWSE_MAX_PAGES: u32 = 100;
+pub const DEFAULT_BROWSE_MAX_TAGS: u32 = 10_000;
+
+/// Progress of a paged browse. Pages are requested at the offset of the results collected so
+/// far until a short page arrives, `max_pages` pages were fetched or `max_tags` is reached.
+#[derive(Debug)]
+pub struct BrowsePaginator {
+    page_size: usize,
+    max_pages: usize,
+    max_tags: usize,
+    max_tags_option: &'static str, // Option named in the truncation warnings
+    pages: usize,
+    results: Vec<BrowseResult>,
+    exhausted: bool,
+}
+
+impl BrowsePaginator {
+    pub fn new(page_size: u32, max_pages: u32, max_tags: u32) -> Self {
+        Self {
+            page_size: page_size.max(1) as usize,
+            max_pages: max_pages.max(1) as usize,
+            max_tags: max_tags as usize,
+            max_tags_option: "--browse-max-tags",
+            pages: 0,
+            results: Vec::new(),
+            exhausted: false,
+        }
+    }
+
+    /// Name the option `max_tags` comes from in the truncation warnings, when a caller lowers
+    /// the --browse-max-tags limit
+    pub fn with_max_tags_option(mut self, option: &'static str) -> Self {
+        self.max_tags_option = option;
+        self
+    }
+
+    /// Offset and limit of the next page, None when browsing is done or the offset no longer
+    /// fits the Int argument of GraphQL
+    pub fn next_page(&self) -> Option<(i32, i32)> {
+        if self.exhausted || self.pages >= self.max_pages || self.results.len() >= self.max_tags {
+            return None;
+        }
+        let offset = i32::try_from(self.results.len()).ok()?;
+        let limit = self.page_size.min(self.max_tags - self.results.len());
+        Some((offset, i32::try_from(limit).unwrap_or(i32::MAX)))
+    }
+
+    pub fn add_page(&mut self, page: Vec<BrowseResult>) {
+        let requested = self.next_page().map_or(0, |(_, limit)| limit as usize);
+        self.pages += 1;
+        self.exhausted = page.len() < requested;
+        self.results.extend(page);
+    }
+
+    /// Results of a single unpaged browse, for servers without offset/limit
+    pub fn add_unpaged(&mut self, results: Vec<BrowseResult>) {
+        self.pages += 1;
+        self.exhausted = true;
+        self.results.extend(results);
+    }
+
+    pub fn pages(&self) -> usize {
+        self.pages
+    }
+
+    pub fn result_count(&self) -> usize {
+        self.results.len()
+    }
+
+    /// The collected results, cut to `max_tags`
+    pub fn finish(mut self, label: &str) -> Vec<BrowseResult> {
+        if self.results.len() > self.max_tags {
+            warn!("⚠️  {} returned {} tags, truncating to {} {}", label, self.results.len(), self.max_tags_option, self.max_tags);
+            self.results.truncate(self.max_tags);
+        } else if !self.exhausted && self.results.len() == self.max_tags {
+            warn!("⚠️  {} stopped at {} {}, results are incomplete", label, self.max_tags_option, self.max_tags);
+        } else if !self.exhausted {
+            warn!("⚠️  {} stopped after --browse-max-pages {} pages, results may be incomplete", label, self.max_pages);
+        }
+        self.results
+    }
+}
+
+/// Errors listed in the `errors` of a GraphQL response. The server's messages are sent to
+/// the client as DETAIL of the ErrorResponse.
+#[derive(Debug)]
+pub struct GraphQLResponseError {
+    pub operation: String,
+    pub detail: String,
+}
+
+impl GraphQLResponseError {
+    pub fn new(operation: impl Into<String>, detail: impl Into<String>) -> Self {
+        Self { operation: operation.into(), detail: detail.into() }
+    }
+}
+
+impl std::fmt::Display for GraphQLResponseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.operation, self.detail)
+    }
+}
+
+impl std::error::Error for GraphQLResponseError {}
 
 #[derive(Debug)]
 pub struct GraphQLClient {
     client: Client,
     url: String,
+    retry_policy: RetryPolicy,
+    backend_requests: Option<IntCounterVec>, // Requests by URL and outcome, see with_backend_requests
+    browse_paging_unsupported: Arc<AtomicBool>, // Set once the server rejected offset/limit, see browse_pages
 }
 
 impl GraphQLClient {
+    #[allow(dead_code)]
     pub fn new(url: String) -> Self {
+        Self::with_pool(url, DEFAULT_POOL_SIZE, Duration::from_secs(DEFAULT_POOL_IDLE_TIMEOUT_SECS))
+    }
+
+    /// Client keeping up to `pool_size` idle keep-alive connections to the GraphQL server.
+    /// One client is shared by all sessions, so queries reuse connections instead of reconnecting.
+    pub fn with_pool(url: String, pool_size: usize, pool_idle_timeout: Duration) -> Self {
+        let client = Client::builder()
+            .connection_verbose(true)
+            .pool_max_idle_per_host(pool_size)
+            .pool_idle_timeout(pool_idle_timeout)
+            .tcp_keepalive(TCP_KEEPALIVE)
+            .build()
+            .unwrap_or_else(|e| {
+                warn!("⚠️  Failed to build pooled HTTP client, using defaults: {}", e);
+                Client::new()
+            });
+        Self { client, url, retry_policy: RetryPolicy::default(), backend_requests: None, browse_paging_unsupported: Arc::default() }
+    }
+
+    /// Copy of this client sharing its connection pool, retrying with `retry_policy`
+    pub fn with_retry_policy(&self, retry_policy: RetryPolicy) -> Self {
         Self {
-            client: Client::new(),
-            url,
+            client: self.client.clone(),
+            url: self.url.clone(),
+            retry_policy,
+            backend_requests: self.backend_requests.clone(),
+            browse_paging_unsupported: self.browse_paging_unsupported.clone(),
+        }
+    }
+
+    /// Count every request as success or failure of this client's URL in `backend_requests`
+    pub fn with_backend_requests(mut self, backend_requests: IntCounterVec) -> Self {
+        self.backend_requests = Some(backend_requests);
+        self
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    // Outcome of a request for the backend_requests counter
+    fn count_backend_request(&self, result: &reqwest::Result<Response>) {
+        if let Some(backend_requests) = &self.backend_requests {
+            let success = matches!(result, Ok(response) if response.status().is_success());
+            let outcome = if success { "success" } else { "failure" };
+            backend_requests.with_label_values(&[self.url.as_str(), outcome]).inc();
         }
     }
 
@@ -58,7 +376,7 @@ impl GraphQLClient {
             .client
             .post(&self.url)
             .json(&request)
-            .send()
+            .send_observed(self)
             .await?;
 
         if !response.status().is_success() {
@@ -82,7 +400,7 @@ impl GraphQLClient {
                 .map(|e| e.description.as_deref().unwrap_or("Unknown error"))
                 .collect::<Vec<_>>()
                 .join(", ");
-            return Err(anyhow!("Login failed: {}", error_msg));
+            return Err(GraphQLResponseError::new("Login failed", error_msg).into());
         }
 
         let session = login_response
@@ -111,7 +429,7 @@ impl GraphQLClient {
         {
             // Error field is None, which is unexpected, not logged on!
             error!("Login response did not contain expected data!");
-            return Err(anyhow!("Login response did not contain expected data!"));
+            Err(anyhow!("Login response did not contain expected data!"))
         }
     }
 
@@ -125,6 +443,7 @@ impl GraphQLClient {
                         timestamp
                         quality {
                             quality
+                            subStatus
                         }
                     }
                     error {
@@ -152,7 +471,7 @@ impl GraphQLClient {
             .post(&self.url)
             .header("Authorization", format!("Bearer {}", token))
             .json(&request)
-            .send()
+            .send_observed(self)
             .await?;
 
         if !response.status().is_success() {
@@ -183,6 +502,114 @@ impl GraphQLClient {
             .unwrap_or_default())
     }
 
+    pub async fn write_tag_values(&self, token: &str, input: Vec<TagValueInput>) -> Result<Vec<WriteTagValuesResult>> {
+        let query = r#"
+            mutation WriteTagValues($input: [TagValueInput]!) {
+                writeTagValues(input: $input) {
+                    name
+                    error {
+                        code
+                        description
+                    }
+                }
+            }
+        "#;
+
+        let request = WriteTagValuesRequest {
+            query: query.to_string(),
+            variables: WriteTagValuesVariables { input },
+        };
+
+        debug!("🚀 Executing GraphQL mutation: write_tag_values");
+        debug!("🔧 Variables (JSON): {}", serde_json::to_string_pretty(&request.variables).unwrap_or_else(|_| "Failed to serialize variables".to_string()));
+
+        let response = self
+            .client
+            .post(&self.url)
+            .header("Authorization", format!("Bearer {}", token))
+            .json(&request)
+            .send_mutation(self)
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(anyhow!("GraphQL request failed with status: {}", response.status()));
+        }
+
+        let response_text = response.text().await?;
+        debug!("📥 GraphQL response: {}", response_text);
+
+        let write_response: WriteTagValuesResponse = serde_json::from_str(&response_text)
+            .map_err(|e| anyhow!("Failed to decode WriteTagValuesResponse: {}", e))?;
+
+        if let Some(errors) = write_response.errors {
+            let error_msg = errors.iter()
+                .map(|e| e.description.as_deref().or(e.message.as_deref()).unwrap_or("Unknown error"))
+                .collect::<Vec<_>>()
+                .join(", ");
+            error!("WriteTagValues mutation errors: {}", error_msg);
+            return Err(GraphQLResponseError::new("WriteTagValues mutation failed", error_msg).into());
+        }
+
+        Ok(write_response
+            .data
+            .and_then(|d| d.write_tag_values)
+            .unwrap_or_default())
+    }
+
+    pub async fn acknowledge_alarms(&self, token: &str, alarms: Vec<AlarmIdentifierInput>) -> Result<Vec<AcknowledgeAlarmsResult>> {
+        let query = r#"
+            mutation AcknowledgeAlarms($input: [AlarmIdentifierInput]!) {
+                acknowledgeAlarms(input: $input) {
+                    alarmName
+                    alarmInstanceID
+                    error {
+                        code
+                        description
+                    }
+                }
+            }
+        "#;
+
+        let request = AcknowledgeAlarmsRequest {
+            query: query.to_string(),
+            variables: AcknowledgeAlarmsVariables { input: alarms },
+        };
+
+        debug!("🚀 Executing GraphQL mutation: acknowledge_alarms");
+        debug!("🔧 Variables (JSON): {}", serde_json::to_string_pretty(&request.variables).unwrap_or_else(|_| "Failed to serialize variables".to_string()));
+
+        let response = self
+            .client
+            .post(&self.url)
+            .header("Authorization", format!("Bearer {}", token))
+            .json(&request)
+            .send_mutation(self)
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(anyhow!("GraphQL request failed with status: {}", response.status()));
+        }
+
+        let response_text = response.text().await?;
+        debug!("📥 GraphQL response: {}", response_text);
+
+        let ack_response: AcknowledgeAlarmsResponse = serde_json::from_str(&response_text)
+            .map_err(|e| anyhow!("Failed to decode AcknowledgeAlarmsResponse: {}", e))?;
+
+        // Errors of single alarms are reported per result; top-level errors without data fail the mutation
+        let results = ack_response.data.and_then(|d| d.acknowledge_alarms);
+        if let (None, Some(errors)) = (&results, ack_response.errors) {
+            let error_msg = errors.iter()
+                .map(|e| e.description.as_deref().or(e.message.as_deref()).unwrap_or("Unknown error"))
+                .collect::<Vec<_>>()
+                .join(", ");
+            error!("AcknowledgeAlarms mutation errors: {}", error_msg);
+            return Err(GraphQLResponseError::new("AcknowledgeAlarms mutation failed", error_msg).into());
+        }
+
+        Ok(results.unwrap_or_default())
+    }
+
     pub async fn get_logged_tag_values(
         &self,
         token: &str,
@@ -202,6 +629,7 @@ impl GraphQLClient {
                             timestamp
                             quality {
                                 quality
+                                subStatus
                             }
                         }
                         flags
@@ -240,7 +668,7 @@ impl GraphQLClient {
             .post(&self.url)
             .header("Authorization", format!("Bearer {}", token))
             .json(&request)
-            .send()
+            .send_observed(self)
             .await?;
 
         if !response.status().is_success() {
@@ -313,7 +741,11 @@ impl GraphQLClient {
                         serde_json::to_string_pretty(&request).unwrap_or_else(|_| "Failed to serialize request".to_string())
                     );
                     error!("{}", error_msg);
-                    return Err(anyhow!("{}", error_msg));
+                    return Err(GraphQLResponseError::new(
+                        format!("LoggedTagValues query failed with {} error(s)", error_details.len()),
+                        error_details.join("\n"),
+                    )
+                    .into());
                 }
             }
             return Err(anyhow!("LoggedTagValues query failed with unknown error. Response: {}", response_text));
@@ -332,7 +764,7 @@ impl GraphQLClient {
                 .collect::<Vec<_>>()
                 .join(", ");
             if !error_msg.is_empty() {
-                return Err(anyhow!("LoggedTagValues query errors: {}", error_msg));
+                return Err(GraphQLResponseError::new("LoggedTagValues query errors", error_msg).into());
             }
         }
 
@@ -347,10 +779,11 @@ impl GraphQLClient {
         token: &str,
         system_names: Vec<String>,
         filter_string: String,
+        languages: Vec<String>,
     ) -> Result<Vec<ActiveAlarm>> {
         let query = r#"
-            query ActiveAlarms($systemNames: [String!], $filterString: String!) {
-                activeAlarms(systemNames: $systemNames, filterString: $filterString) {
+            query ActiveAlarms($systemNames: [String!], $filterString: String!, $languages: [String]) {
+                activeAlarms(systemNames: $systemNames, filterString: $filterString, languages: $languages) {
                     name
                     instanceID
                     alarmGroupID
@@ -378,7 +811,7 @@ impl GraphQLClient {
                 system_names,
                 filter_string,
                 filter_language: "en-US".to_string(),
-                languages: vec!["en-US".to_string()],
+                languages,
             },
         };
 
@@ -391,7 +824,7 @@ impl GraphQLClient {
             .post(&self.url)
             .header("Authorization", format!("Bearer {}", token))
             .json(&request)
-            .send()
+            .send_observed(self)
             .await?;
 
         if !response.status().is_success() {
@@ -422,6 +855,7 @@ impl GraphQLClient {
             .unwrap_or_default())
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub async fn get_logged_alarms(
         &self,
         token: &str,
@@ -431,6 +865,7 @@ impl GraphQLClient {
         end_time: Option<String>,
         max_results: Option<i32>,
         filter_language: Option<String>,
+        languages: Vec<String>,
     ) -> Result<Vec<LoggedAlarm>> {
         let query = r#"
             query LoggedAlarms($systemNames: [String], $filterString: String, $filterLanguage: String, $languages: [String], $startTime: Timestamp, $endTime: Timestamp, $maxNumberOfResults: Int) {
@@ -463,7 +898,7 @@ impl GraphQLClient {
                 system_names: if system_names.is_empty() { None } else { Some(system_names) },
                 filter_string: if filter_string.is_empty() { None } else { Some(filter_string) },
                 filter_language,
-                languages: None, // Set to None since we don't set it from SQL queries
+                languages: Some(languages),
                 start_time,
                 end_time,
                 max_number_of_results: max_results,
@@ -479,7 +914,7 @@ impl GraphQLClient {
             .post(&self.url)
             .header("Authorization", format!("Bearer {}", token))
             .json(&request)
-            .send()
+            .send_observed(self)
             .await?;
 
         if !response.status().is_success() {
@@ -510,7 +945,34 @@ impl GraphQLClient {
             .unwrap_or_default())
     }
 
-    pub async fn browse_tags(&self, token: &str, name_filters: Vec<String>) -> Result<Vec<BrowseResult>> {
+    /// Browse tags page by page, see `browse_pages`. `object_type_filter` is passed as the
+    /// objectTypeFilters argument, so GraphQL only returns tags of these types.
+    pub async fn browse_tags(&self, token: &str, name_filters: Vec<String>, object_type_filter: Option<&[String]>, paginator: BrowsePaginator) -> Result<Vec<BrowseResult>> {
+        self.browse_tags_in_language(token, name_filters, object_type_filter, "en-US", paginator).await
+    }
+
+    /// Same as `browse_tags`, with the display names in `language`
+    pub async fn browse_tags_in_language(
+        &self,
+        token: &str,
+        name_filters: Vec<String>,
+        object_type_filter: Option<&[String]>,
+        language: &str,
+        mut paginator: BrowsePaginator,
+    ) -> Result<Vec<BrowseResult>> {
+        let variables = BrowseVariables {
+            name_filters,
+            object_type_filters: object_type_filter.map(<[String]>::to_vec).unwrap_or_default(),
+            base_type_filters: vec![],
+            language: language.to_string(),
+        };
+        if !self.browse_paging_supported() || !self.browse_pages("browse_tags", token, &variables, &mut paginator).await? {
+            paginator.add_unpaged(self.browse_tags_unpaged(token, variables).await?);
+        }
+        Ok(paginator.finish("browse_tags"))
+    }
+
+    async fn browse_tags_unpaged(&self, token: &str, variables: BrowseVariables) -> Result<Vec<BrowseResult>> {
         let query = r#"
             query Browse($nameFilters: [String!]!, $objectTypeFilters: [ObjectTypesEnum!]!, $baseTypeFilters: [String!]!, $language: String!) {
                 browse(nameFilters: $nameFilters, objectTypeFilters: $objectTypeFilters, baseTypeFilters: $baseTypeFilters, language: $language) {
@@ -524,12 +986,7 @@ impl GraphQLClient {
 
         let request = BrowseRequest {
             query: query.to_string(),
-            variables: BrowseVariables {
-                name_filters,
-                object_type_filters: vec![],
-                base_type_filters: vec![],
-                language: "en-US".to_string(),
-            },
+            variables,
         };
 
         debug!("🚀 Executing GraphQL query: browse_tags");
@@ -541,7 +998,7 @@ impl GraphQLClient {
             .post(&self.url)
             .header("Authorization", format!("Bearer {}", token))
             .json(&request)
-            .send()
+            .send_observed(self)
             .await?;
 
         if !response.status().is_success() {
@@ -577,10 +1034,19 @@ impl GraphQLClient {
             .unwrap_or_default())
     }
 
-    pub async fn browse_tags_with_object_type(&self, token: &str, name_filters: Vec<String>, object_type_filters: Vec<String>, language: String) -> Result<Vec<BrowseResult>> {
+    /// False once the server rejected the paging arguments; the schema doesn't change while the
+    /// client lives, so the failed request and its warning happen only once
+    fn browse_paging_supported(&self) -> bool {
+        !self.browse_paging_unsupported.load(Ordering::Relaxed)
+    }
+
+    /// Request the pages of `paginator` using `offset`/`limit`, so large namespaces don't time
+    /// out. Returns false if the server rejects the paging arguments on the first page; the
+    /// caller then browses with a single unpaged request, as do all later browses of this client.
+    async fn browse_pages(&self, label: &str, token: &str, variables: &BrowseVariables, paginator: &mut BrowsePaginator) -> Result<bool> {
         let query = r#"
-            query Browse($nameFilters: [String!]!, $objectTypeFilters: [ObjectTypesEnum!]!, $baseTypeFilters: [String!]!, $language: String!) {
-                browse(nameFilters: $nameFilters, objectTypeFilters: $objectTypeFilters, baseTypeFilters: $baseTypeFilters, language: $language) {
+            query Browse($nameFilters: [String!]!, $objectTypeFilters: [ObjectTypesEnum!]!, $baseTypeFilters: [String!]!, $language: String!, $offset: Int, $limit: Int) {
+                browse(nameFilters: $nameFilters, objectTypeFilters: $objectTypeFilters, baseTypeFilters: $baseTypeFilters, language: $language, offset: $offset, limit: $limit) {
                     name
                     displayName
                     objectType
@@ -589,63 +1055,90 @@ impl GraphQLClient {
             }
         "#;
 
-        let request = BrowseRequest {
-            query: query.to_string(),
-            variables: BrowseVariables {
-                name_filters: name_filters.clone(),
-                object_type_filters: object_type_filters.clone(),
-                base_type_filters: vec![],
-                language: language.clone(),
-            },
-        };
-
-        debug!("🚀 Executing GraphQL query: browse_tags_with_object_type");
-        debug!("📄 Query: {}", query);
-        debug!("🔧 Variables (JSON): {}", serde_json::to_string_pretty(&request.variables).unwrap_or_else(|_| "Failed to serialize variables".to_string()));
+        while let Some((offset, limit)) = paginator.next_page() {
+            let first_page = paginator.pages() == 0;
+            let request = BrowsePagedRequest {
+                query: query.to_string(),
+                variables: BrowsePagedVariables {
+                    name_filters: variables.name_filters.clone(),
+                    object_type_filters: variables.object_type_filters.clone(),
+                    base_type_filters: variables.base_type_filters.clone(),
+                    language: variables.language.clone(),
+                    offset,
+                    limit,
+                },
+            };
+
+            debug!("🚀 Executing GraphQL query: {} (page {}, offset {})", label, paginator.pages() + 1, offset);
+            debug!("📄 Query: {}", query);
+            debug!("🔧 Variables (JSON): {}", serde_json::to_string_pretty(&request.variables).unwrap_or_else(|_| "Failed to serialize variables".to_string()));
+
+            let response = self
+                .client
+                .post(&self.url)
+                .header("Authorization", format!("Bearer {}", token))
+                .json(&request)
+                .send_observed(self)
+                .await?;
+
+            if !response.status().is_success() {
+                let status = response.status();
+                if first_page && status.as_u16() == 400 {
+                    warn!("⚠️  Paged {} rejected with status {}, server may not support paging - falling back to single requests", label, status);
+                    self.browse_paging_unsupported.store(true, Ordering::Relaxed);
+                    return Ok(false);
+                }
+                return Err(anyhow!("GraphQL request failed with status: {}", status));
+            }
 
-        let response = self
-            .client
-            .post(&self.url)
-            .header("Authorization", format!("Bearer {}", token))
-            .json(&request)
-            .send()
-            .await?;
+            let response_text = response.text().await?;
+            debug!("📥 GraphQL response: {}", response_text);
+
+            let browse_response: BrowseResponse = serde_json::from_str(&response_text)
+                .map_err(|e| {
+                    error!("❌ Failed to decode BrowseResponse: {}", e);
+                    error!("📥 Raw response was: {}", response_text);
+                    anyhow!("Failed to decode BrowseResponse: {}", e)
+                })?;
+
+            if let Some(errors) = browse_response.errors {
+                let error_msg = errors.iter()
+                    .map(|e| e.message.as_deref().or(e.description.as_deref()).unwrap_or("Unknown error"))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                if first_page && browse_response.data.is_none() {
+                    warn!("⚠️  Paged {} failed ({}), server may not support paging - falling back to single requests", label, error_msg);
+                    self.browse_paging_unsupported.store(true, Ordering::Relaxed);
+                    return Ok(false);
+                }
+                error!("Browse paged query errors: {}", error_msg);
+            }
 
-        if !response.status().is_success() {
-            let status = response.status();
-            let response_text = response.text().await.unwrap_or_else(|_| "Failed to read response".to_string());
-            error!("GraphQL browse_tags_with_object_type request failed with status: {}", status);
-            error!("GraphQL request body: {}", serde_json::to_string_pretty(&request).unwrap_or_else(|_| "Failed to serialize request".to_string()));
-            error!("GraphQL response body: {}", response_text);
-            return Err(anyhow!("GraphQL request failed with status: {}", status));
+            let page_results = browse_response
+                .data
+                .map(|d| d.browse)
+                .unwrap_or_default();
+            paginator.add_page(page_results);
+            debug!("📄 {} page {} done, {} results so far", label, paginator.pages(), paginator.result_count());
         }
+        Ok(true)
+    }
 
-        let response_text = response.text().await?;
-        debug!("📥 GraphQL response: {}", response_text);
-        
-        let browse_response: BrowseResponse = serde_json::from_str(&response_text)
-            .map_err(|e| {
-                error!("❌ Failed to decode BrowseResponse: {}", e);
-                error!("📥 Raw response was: {}", response_text);
-                anyhow!("Failed to decode BrowseResponse: {}", e)
-            })?;
-
-        if let Some(errors) = browse_response.errors {
-            let error_msg = errors.iter()
-                .map(|e| e.description.as_deref().unwrap_or("Unknown error"))
-                .collect::<Vec<_>>()
-                .join(", ");
-            error!("Browse tags with object type query errors: {}", error_msg);
-            return Err(anyhow!("Browse query failed: {}", error_msg));
+    /// Browse logging tags page by page, see `browse_pages`. The object types default to LOGGINGTAG.
+    pub async fn browse_logging_tags(&self, token: &str, name_filters: Vec<String>, object_type_filter: Option<&[String]>, mut paginator: BrowsePaginator) -> Result<Vec<BrowseResult>> {
+        let variables = BrowseVariables {
+            name_filters,
+            object_type_filters: object_type_filter.map_or_else(|| vec!["LOGGINGTAG".to_string()], <[String]>::to_vec),
+            base_type_filters: vec![],
+            language: "en-US".to_string(),
+        };
+        if !self.browse_paging_supported() || !self.browse_pages("browse_logging_tags", token, &variables, &mut paginator).await? {
+            paginator.add_unpaged(self.browse_logging_tags_unpaged(token, variables).await?);
         }
-
-        Ok(browse_response
-            .data
-            .map(|d| d.browse)
-            .unwrap_or_default())
+        Ok(paginator.finish("browse_logging_tags"))
     }
 
-    pub async fn browse_logging_tags(&self, token: &str, name_filters: Vec<String>) -> Result<Vec<BrowseResult>> {
+    async fn browse_logging_tags_unpaged(&self, token: &str, variables: BrowseVariables) -> Result<Vec<BrowseResult>> {
         // Try first with objectTypeFilters (newer API)
         let query_with_filters = r#"
             query Browse($nameFilters: [String!]!, $objectTypeFilters: [ObjectTypesEnum!]!) {
@@ -660,12 +1153,7 @@ impl GraphQLClient {
 
         let request_with_filters = BrowseRequest {
             query: query_with_filters.to_string(),
-            variables: BrowseVariables {
-                name_filters: name_filters.clone(),
-                object_type_filters: vec!["LOGGINGTAG".to_string()],
-                base_type_filters: vec![],
-                language: "en-US".to_string(),
-            },
+            variables,
         };
 
         debug!("🚀 Executing GraphQL query: browse_logging_tags");
@@ -677,7 +1165,7 @@ impl GraphQLClient {
             .post(&self.url)
             .header("Authorization", format!("Bearer {}", token))
             .json(&request_with_filters)
-            .send()
+            .send_observed(self)
             .await?;
 
         if !response.status().is_success() {
@@ -705,7 +1193,7 @@ impl GraphQLClient {
                 .collect::<Vec<_>>()
                 .join(", ");
             error!("Browse logging tags query errors: {}", error_msg);
-            return Err(anyhow!("Browse query failed: {}", error_msg));
+            return Err(GraphQLResponseError::new("Browse query failed", error_msg).into());
         }
 
         Ok(browse_response
@@ -741,7 +1229,7 @@ impl GraphQLClient {
             .post(&self.url)
             .header("Authorization", format!("Bearer {}", token))
             .json(&request)
-            .send()
+            .send_observed(self)
             .await?;
 
         if !response.status().is_success() {
@@ -767,7 +1255,7 @@ impl GraphQLClient {
                 .map(|e| e.description.as_deref().unwrap_or("Unknown error"))
                 .collect::<Vec<_>>()
                 .join(", ");
-            return Err(anyhow!("extendSession failed: {}", error_msg));
+            return Err(GraphQLResponseError::new("extendSession failed", error_msg).into());
         }
 
         let session = extend_session_response
@@ -793,6 +1281,57 @@ impl GraphQLClient {
 
         Ok(session)
     }
+
+    /// End the session of `token` on the WinCC server, releasing its license
+    pub async fn close_session(&self, token: &str) -> Result<()> {
+        let query = r#"
+            mutation Logout {
+                logout(allSessions: false)
+            }
+        "#;
+
+        let request = serde_json::json!({
+            "query": query,
+        });
+
+        debug!("🚀 Executing GraphQL mutation: logout");
+        debug!("📄 Query: {}", query);
+
+        let response = self
+            .client
+            .post(&self.url)
+            .header("Authorization", format!("Bearer {}", token))
+            .json(&request)
+            .send_observed(self)
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(anyhow!(
+                "GraphQL request failed with status: {}",
+                response.status()
+            ));
+        }
+
+        let response_text = response.text().await?;
+        debug!("📥 GraphQL response: {}", response_text);
+
+        let logout_response: LogoutResponse = serde_json::from_str(&response_text)
+            .map_err(|e| anyhow!("Failed to decode LogoutResponse: {}", e))?;
+
+        if let Some(errors) = logout_response.errors {
+            let error_msg = errors
+                .iter()
+                .map(|e| e.description.as_deref().unwrap_or("Unknown error"))
+                .collect::<Vec<_>>()
+                .join(", ");
+            return Err(GraphQLResponseError::new("logout failed", error_msg).into());
+        }
+
+        match logout_response.data.and_then(|data| data.logout) {
+            Some(false) => Err(anyhow!("logout was rejected")),
+            _ => Ok(()),
+        }
+    }
 }
 
 pub async fn validate_connection(url: &str) -> Result<()> {
@@ -809,7 +1348,7 @@ pub async fn validate_connection(url: &str) -> Result<()> {
     let response = client
         .post(url)
         .json(&introspection_query)
-        .send()
+        .send_traced(&RetryPolicy::none())
         .await?;
     
     if response.status().is_success() {
@@ -818,13 +1357,13 @@ pub async fn validate_connection(url: &str) -> Result<()> {
         
         // Check if it's a valid GraphQL response
         if response_text.contains("\"data\"") || response_text.contains("\"__schema\"") {
-            return Ok(());
+            Ok(())
         } else if response_text.contains("\"errors\"") {
             // GraphQL endpoint is working but introspection might be disabled
             debug!("Introspection disabled, trying fallback validation");
-            return validate_with_simple_query(&client, url).await;
+            validate_with_simple_query(&client, url).await
         } else {
-            return Err(anyhow!("Invalid GraphQL response format: {}", response_text));
+            Err(anyhow!("Invalid GraphQL response format: {}", response_text))
         }
     } else {
         let status = response.status();
@@ -836,7 +1375,7 @@ pub async fn validate_connection(url: &str) -> Result<()> {
             return validate_with_simple_query(&client, url).await;
         }
         
-        return Err(anyhow!("GraphQL server returned status: {} - {}", status, error_text));
+        Err(anyhow!("GraphQL server returned status: {} - {}", status, error_text))
     }
 }
 
@@ -852,7 +1391,7 @@ async fn validate_with_simple_query(client: &Client, url: &str) -> Result<()> {
     let response = client
         .post(url)
         .json(&simple_query)
-        .send()
+        .send_traced(&RetryPolicy::none())
         .await?;
     
     if response.status().is_success() {
@@ -874,4 +1413,126 @@ async fn validate_with_simple_query(client: &Client, url: &str) -> Result<()> {
         let error_text = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
         Err(anyhow!("GraphQL validation failed with status: {} - {}", status, error_text))
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{spawn_counting_graphql_server, spawn_graphql_server, MockResponse};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[tokio::test]
+    async fn test_requests_reuse_pooled_connections() {
+        let (url, connections) = spawn_counting_graphql_server(|_| MockResponse::Json(r#"{"data":{"tagValues":[]}}"#.to_string())).await;
+        let pool_size = 4;
+        let client = GraphQLClient::with_pool(url, pool_size, Duration::from_secs(90));
+
+        for i in 0..50 {
+            let values = client.get_tag_values("token", vec![format!("Tag_{}", i)], false).await.unwrap();
+            assert!(values.is_empty());
+        }
+        let opened = connections.load(Ordering::SeqCst);
+        assert!((1..=pool_size).contains(&opened), "50 requests opened {} connections", opened);
+    }
+
+    #[tokio::test]
+    async fn test_timed_out_mutations_are_not_sent_again() {
+        let requests = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let seen = requests.clone();
+        let url = spawn_graphql_server(move |request| {
+            let query = request.query();
+            let operation = ["WriteTagValues", "AcknowledgeAlarms", "TagValues"].into_iter().find(|name| query.contains(name)).unwrap();
+            seen.lock().unwrap().push(operation);
+            MockResponse::Delayed(Duration::from_secs(5), r#"{"data":{}}"#.to_string())
+        })
+        .await;
+        let retry_policy = RetryPolicy { max_retries: 2, initial_backoff_ms: 1, max_backoff_ms: 1, jitter: false };
+        let client = GraphQLClient {
+            client: Client::builder().timeout(Duration::from_millis(100)).build().unwrap(),
+            ..GraphQLClient::new(url).with_retry_policy(retry_policy)
+        };
+
+        let input = vec![TagValueInput { name: "Tag_1".to_string(), value: serde_json::json!(1) }];
+        let (result, retries) = count_retries(client.write_tag_values("token", input)).await;
+        assert!(result.is_err());
+        let alarms = vec![AlarmIdentifierInput { name: "Alarm_1".to_string(), instance_id: 1 }];
+        let (result, retries_ack) = count_retries(client.acknowledge_alarms("token", alarms)).await;
+        assert!(result.is_err());
+        assert_eq!((retries, retries_ack), (0, 0));
+        assert_eq!(*requests.lock().unwrap(), vec!["WriteTagValues", "AcknowledgeAlarms"]);
+
+        // Reads are repeated after a timeout
+        let (result, retries) = count_retries(client.get_tag_values("token", vec!["Tag_1".to_string()], false)).await;
+        assert!(result.is_err());
+        assert_eq!(retries, 2);
+        assert_eq!(requests.lock().unwrap().iter().filter(|operation| **operation == "TagValues").count(), 3);
+    }
+
+    // Answers browse requests from 5 tags by offset/limit; without offset it returns all of them
+    fn browse_five_tags(body: &str) -> String {
+        let request: serde_json::Value = serde_json::from_str(body).unwrap();
+        let variables = &request["variables"];
+        let (offset, limit) = match (variables["offset"].as_u64(), variables["limit"].as_u64()) {
+            (Some(offset), Some(limit)) => (offset as usize, limit as usize),
+            _ => (0, usize::MAX),
+        };
+        let tags: Vec<serde_json::Value> = (1..=5)
+            .skip(offset)
+            .take(limit)
+            .map(|i| serde_json::json!({"name": format!("Tag_{}", i), "displayName": null, "objectType": "TAG", "dataType": "Int"}))
+            .collect();
+        serde_json::json!({"data": {"browse": tags}}).to_string()
+    }
+
+    fn names(results: &[BrowseResult]) -> Vec<&str> {
+        results.iter().map(|result| result.name.as_str()).collect()
+    }
+
+    #[test]
+    fn test_browse_page_limit_fits_graphql_int() {
+        let paginator = BrowsePaginator::new(u32::MAX, u32::MAX, u32::MAX);
+        assert_eq!(paginator.next_page(), Some((0, i32::MAX)));
+    }
+
+    #[tokio::test]
+    async fn test_browse_collects_pages_until_a_short_page() {
+        let url = spawn_graphql_server(|request| MockResponse::Json(browse_five_tags(&request.body))).await;
+        let client = GraphQLClient::new(url);
+
+        let variables = BrowseVariables {
+            name_filters: vec!["*".to_string()],
+            object_type_filters: vec![],
+            base_type_filters: vec![],
+            language: "en-US".to_string(),
+        };
+        let mut paginator = BrowsePaginator::new(2, 100, 10_000);
+        assert!(client.browse_pages("browse_tags", "token", &variables, &mut paginator).await.unwrap());
+        assert_eq!(paginator.pages(), 3);
+        assert_eq!(names(&paginator.finish("browse_tags")), vec!["Tag_1", "Tag_2", "Tag_3", "Tag_4", "Tag_5"]);
+
+        // --browse-max-tags stops paging early
+        let results = client.browse_tags("token", vec!["*".to_string()], None, BrowsePaginator::new(2, 100, 3)).await.unwrap();
+        assert_eq!(names(&results), vec!["Tag_1", "Tag_2", "Tag_3"]);
+
+        // Servers without offset/limit get a single unpaged browse; the paged request is only
+        // tried once per client, also by copies sharing its pool
+        let paged_requests = Arc::new(AtomicUsize::new(0));
+        let counted = paged_requests.clone();
+        let url = spawn_graphql_server(move |request| {
+            if request.body.contains("$offset") {
+                counted.fetch_add(1, Ordering::SeqCst);
+                MockResponse::Json(r#"{"errors":[{"message":"Unknown argument \"offset\" on field \"browse\""}]}"#.to_string())
+            } else {
+                MockResponse::Json(browse_five_tags(&request.body))
+            }
+        })
+        .await;
+        let client = GraphQLClient::new(url);
+        let results = client.browse_tags("token", vec!["*".to_string()], None, BrowsePaginator::new(2, 100, 4)).await.unwrap();
+        assert_eq!(names(&results), vec!["Tag_1", "Tag_2", "Tag_3", "Tag_4"]);
+        let copy = client.with_retry_policy(RetryPolicy::none());
+        let results = copy.browse_logging_tags("token", vec!["*".to_string()], None, BrowsePaginator::new(2, 100, 10)).await.unwrap();
+        assert_eq!(results.len(), 5);
+        assert_eq!(paged_requests.load(Ordering::SeqCst), 1);
+    }
+}
@@ -78,6 +78,8 @@ pub struct TagValueResult {
     pub name: String,
     pub value: Option<Value>,
     pub error: Option<GraphQLError>,
+    #[serde(skip)]
+    pub warning: Option<String>, // Set by --tag-name-autocomplete, shown in __warning
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -90,6 +92,84 @@ pub struct Value {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Quality {
     pub quality: String,
+    #[serde(rename = "subStatus", default, skip_serializing_if = "Option::is_none")]
+    pub sub_status: Option<String>,
+}
+
+// Write Tag Values
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WriteTagValuesRequest {
+    pub query: String,
+    pub variables: WriteTagValuesVariables,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WriteTagValuesVariables {
+    pub input: Vec<TagValueInput>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TagValueInput {
+    pub name: String,
+    pub value: serde_json::Value,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WriteTagValuesResponse {
+    pub data: Option<WriteTagValuesData>,
+    pub errors: Option<Vec<GraphQLError>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WriteTagValuesData {
+    #[serde(rename = "writeTagValues")]
+    pub write_tag_values: Option<Vec<WriteTagValuesResult>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WriteTagValuesResult {
+    pub name: Option<String>,
+    pub error: Option<GraphQLError>,
+}
+
+// Acknowledge Alarms
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AcknowledgeAlarmsRequest {
+    pub query: String,
+    pub variables: AcknowledgeAlarmsVariables,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AcknowledgeAlarmsVariables {
+    pub input: Vec<AlarmIdentifierInput>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlarmIdentifierInput {
+    pub name: String,
+    #[serde(rename = "instanceID")]
+    pub instance_id: i32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AcknowledgeAlarmsResponse {
+    pub data: Option<AcknowledgeAlarmsData>,
+    pub errors: Option<Vec<GraphQLError>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AcknowledgeAlarmsData {
+    #[serde(rename = "acknowledgeAlarms")]
+    pub acknowledge_alarms: Option<Vec<AcknowledgeAlarmsResult>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AcknowledgeAlarmsResult {
+    #[serde(rename = "alarmName")]
+    pub alarm_name: Option<String>,
+    #[serde(rename = "alarmInstanceID")]
+    pub instance_id: Option<i32>,
+    pub error: Option<GraphQLError>,
 }
 
 // Logged Tag Values
@@ -297,6 +377,24 @@ pub struct BrowseVariables {
     pub language: String,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BrowsePagedRequest {
+    pub query: String,
+    pub variables: BrowsePagedVariables,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BrowsePagedVariables {
+    #[serde(rename = "nameFilters")]
+    pub name_filters: Vec<String>,
+    #[serde(rename = "objectTypeFilters")]
+    pub object_type_filters: Vec<String>,
+    #[serde(rename = "baseTypeFilters")]
+    pub base_type_filters: Vec<String>,
+    pub language: String,
+    pub offset: i32,
+    pub limit: i32,
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BrowseResponse {
@@ -331,4 +429,16 @@ pub struct ExtendSessionResponse {
 pub struct ExtendSessionData {
     #[serde(rename = "extendSession")]
     pub extend_session: Session,
+}
+
+// Logout
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogoutResponse {
+    pub data: Option<LogoutData>,
+    pub errors: Option<Vec<GraphQLError>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogoutData {
+    pub logout: Option<bool>,
 }
\ No newline at end of file
@@ -0,0 +1,204 @@
+use crate::metrics::{read_request_line, write_response, Metrics, PROMETHEUS_CONTENT_TYPE};
+use anyhow::Result;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::{TcpListener, TcpStream};
+use tracing::{debug, info, warn};
+
+// Upper bound for the GraphQL check of a readiness probe
+const READINESS_TIMEOUT: Duration = Duration::from_secs(5);
+
+const JSON_CONTENT_TYPE: &str = "application/json";
+
+/// State shared by the liveness/readiness endpoints
+pub struct HealthState {
+    // All --graphql-url servers; connections fail over between them
+    pub graphql_urls: Vec<String>,
+    // Served on GET /metrics when no dedicated --metrics-addr is configured
+    pub metrics: Option<Arc<Metrics>>,
+    // Drain mode of the PostgreSQL server, set by GET /drain or SIGTERM
+    pub draining: Arc<AtomicBool>,
+}
+
+pub async fn serve(addr: SocketAddr, state: Arc<HealthState>) -> Result<()> {
+    let listener = TcpListener::bind(addr).await?;
+    info!("🩺 Health probes available at http://{}/healthz and /readyz", addr);
+    if state.metrics.is_some() {
+        info!("📈 Prometheus metrics available at http://{}/metrics", addr);
+    }
+    serve_on(listener, state).await
+}
+
+pub async fn serve_on(listener: TcpListener, state: Arc<HealthState>) -> Result<()> {
+    loop {
+        let (socket, peer_addr) = listener.accept().await?;
+        let state = state.clone();
+        tokio::spawn(async move {
+            if let Err(e) = handle_request(socket, &state).await {
+                warn!("⚠️  Health request from {} failed: {}", peer_addr, e);
+            }
+        });
+    }
+}
+
+async fn handle_request(mut socket: TcpStream, state: &HealthState) -> Result<()> {
+    let request_line = read_request_line(&mut socket).await?;
+    debug!("🩺 Health request: {}", request_line);
+
+    let mut parts = request_line.split_whitespace();
+    let (status, content_type, body) = match (parts.next(), parts.next(), &state.metrics) {
+        (Some("GET"), Some("/healthz"), _) => ("200 OK", JSON_CONTENT_TYPE, serde_json::json!({ "status": "ok" }).to_string()),
+        (Some("GET"), Some("/drain"), _) => {
+            crate::auth::start_draining(&state.draining);
+            ("200 OK", JSON_CONTENT_TYPE, serde_json::json!({ "status": "draining" }).to_string())
+        }
+        // A draining instance is taken out of the load balancer before it stops
+        (Some("GET"), Some("/readyz"), _) if state.draining.load(Ordering::SeqCst) => (
+            "503 Service Unavailable",
+            JSON_CONTENT_TYPE,
+            serde_json::json!({ "status": "draining" }).to_string(),
+        ),
+        (Some("GET"), Some("/readyz"), _) => match check_graphql(&state.graphql_urls).await {
+            Ok(()) => ("200 OK", JSON_CONTENT_TYPE, serde_json::json!({ "status": "ok" }).to_string()),
+            Err(e) => {
+                warn!("⚠️  Readiness check failed: {}", e);
+                (
+                    "503 Service Unavailable",
+                    JSON_CONTENT_TYPE,
+                    serde_json::json!({ "status": "unavailable", "error": e.to_string() }).to_string(),
+                )
+            }
+        },
+        (Some("GET"), Some("/metrics"), Some(metrics)) => ("200 OK", PROMETHEUS_CONTENT_TYPE, metrics.encode()?),
+        _ => ("404 Not Found", "text/plain; charset=utf-8", "Not Found\n".to_string()),
+    };
+
+    write_response(&mut socket, status, content_type, &body).await
+}
+
+// Ready as long as one GraphQL server answers, new sessions fail over to it. The servers are
+// checked concurrently, so a hanging primary doesn't delay the answer of a live secondary.
+async fn check_graphql(graphql_urls: &[String]) -> Result<()> {
+    let mut checks = tokio::task::JoinSet::new();
+    for graphql_url in graphql_urls {
+        let graphql_url = graphql_url.clone();
+        checks.spawn(async move {
+            tokio::time::timeout(READINESS_TIMEOUT, crate::graphql::client::validate_connection(&graphql_url))
+                .await
+                .map_err(|_| anyhow::anyhow!("GraphQL server did not respond within {} s", READINESS_TIMEOUT.as_secs()))
+                .and_then(|result| result)
+                .map_err(|e| format!("{}: {}", graphql_url, e))
+        });
+    }
+    let mut errors = Vec::new();
+    while let Some(result) = checks.join_next().await {
+        match result? {
+            Ok(()) => return Ok(()),
+            Err(e) => errors.push(e),
+        }
+    }
+    Err(anyhow::anyhow!("No GraphQL server is reachable: {}", errors.join("; ")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{spawn_graphql_server, MockResponse};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    // GraphQL server answering every request with an introspection result
+    async fn spawn_graphql_mock() -> String {
+        spawn_graphql_server(|_| MockResponse::Json(r#"{"data":{"__schema":{"queryType":{"name":"Query"}}}}"#.to_string())).await
+    }
+
+    async fn spawn_health_server(graphql_url: String, metrics: Option<Arc<Metrics>>) -> SocketAddr {
+        spawn_health_server_with_drain_flag(vec![graphql_url], metrics, Arc::new(AtomicBool::new(false))).await
+    }
+
+    async fn spawn_health_server_with_drain_flag(graphql_urls: Vec<String>, metrics: Option<Arc<Metrics>>, draining: Arc<AtomicBool>) -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(serve_on(listener, Arc::new(HealthState { graphql_urls, metrics, draining })));
+        addr
+    }
+
+    async fn get(addr: SocketAddr, path: &str) -> (String, String) {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(format!("GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path).as_bytes())
+            .await
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        (head.lines().next().unwrap().to_string(), body.to_string())
+    }
+
+    #[tokio::test]
+    async fn test_health_probes() {
+        let addr = spawn_health_server(spawn_graphql_mock().await, None).await;
+
+        let (status, body) = get(addr, "/healthz").await;
+        assert_eq!(status, "HTTP/1.1 200 OK");
+        assert_eq!(serde_json::from_str::<serde_json::Value>(&body).unwrap()["status"], "ok");
+
+        let (status, body) = get(addr, "/readyz").await;
+        assert_eq!(status, "HTTP/1.1 200 OK");
+        assert_eq!(serde_json::from_str::<serde_json::Value>(&body).unwrap()["status"], "ok");
+
+        // Without a shared registry /metrics belongs to the dedicated metrics endpoint
+        let (status, _) = get(addr, "/metrics").await;
+        assert_eq!(status, "HTTP/1.1 404 Not Found");
+    }
+
+    #[tokio::test]
+    async fn test_readyz_reports_unreachable_graphql() {
+        let metrics = Arc::new(Metrics::new().unwrap());
+        let addr = spawn_health_server("http://127.0.0.1:1/graphql".to_string(), Some(metrics)).await;
+
+        let (status, body) = get(addr, "/readyz").await;
+        assert_eq!(status, "HTTP/1.1 503 Service Unavailable");
+        let body: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(body["status"], "unavailable");
+        assert!(!body["error"].as_str().unwrap().is_empty());
+
+        let (status, body) = get(addr, "/metrics").await;
+        assert_eq!(status, "HTTP/1.1 200 OK");
+        assert!(body.contains("pgwire_connections_active"));
+    }
+
+    #[tokio::test]
+    async fn test_drain_endpoint_fails_readiness() {
+        let draining = Arc::new(AtomicBool::new(false));
+        let addr = spawn_health_server_with_drain_flag(vec![spawn_graphql_mock().await], None, draining.clone()).await;
+        assert_eq!(get(addr, "/readyz").await.0, "HTTP/1.1 200 OK");
+
+        let (status, body) = get(addr, "/drain").await;
+        assert_eq!(status, "HTTP/1.1 200 OK");
+        assert_eq!(serde_json::from_str::<serde_json::Value>(&body).unwrap()["status"], "draining");
+        assert!(draining.load(Ordering::SeqCst));
+
+        // Readiness fails even though GraphQL is reachable, liveness is unaffected
+        let (status, body) = get(addr, "/readyz").await;
+        assert_eq!(status, "HTTP/1.1 503 Service Unavailable");
+        assert_eq!(serde_json::from_str::<serde_json::Value>(&body).unwrap()["status"], "draining");
+        assert_eq!(get(addr, "/healthz").await.0, "HTTP/1.1 200 OK");
+    }
+
+    #[tokio::test]
+    async fn test_readyz_is_ready_when_a_secondary_graphql_server_answers() {
+        let dead_primary = "http://127.0.0.1:1/graphql".to_string();
+        let draining = Arc::new(AtomicBool::new(false));
+        let addr = spawn_health_server_with_drain_flag(vec![dead_primary.clone(), spawn_graphql_mock().await], None, draining.clone()).await;
+        assert_eq!(get(addr, "/readyz").await.0, "HTTP/1.1 200 OK");
+
+        // Not ready once no server answers; the error names every server
+        let addr = spawn_health_server_with_drain_flag(vec![dead_primary.clone(), "http://127.0.0.1:2/graphql".to_string()], None, draining).await;
+        let (status, body) = get(addr, "/readyz").await;
+        assert_eq!(status, "HTTP/1.1 503 Service Unavailable");
+        let error = serde_json::from_str::<serde_json::Value>(&body).unwrap()["error"].as_str().unwrap().to_string();
+        assert!(error.contains(&dead_primary) && error.contains("127.0.0.1:2"), "{}", error);
+    }
+}
@@ -0,0 +1,64 @@
+use ipnet::IpNet;
+use std::net::IpAddr;
+
+/// Subnets allowed to connect over TCP, from --allow-ip and --deny-ip. The denylist is checked
+/// first; with an allowlist, addresses outside of it are rejected. Empty lists allow everyone.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct IpFilter {
+    allow: Vec<IpNet>,
+    deny: Vec<IpNet>,
+}
+
+impl IpFilter {
+    pub fn new(allow: Vec<IpNet>, deny: Vec<IpNet>) -> Self {
+        Self { allow, deny }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.allow.is_empty() && self.deny.is_empty()
+    }
+
+    pub fn is_allowed(&self, addr: IpAddr) -> bool {
+        // Dual-stack listeners report IPv4 clients as ::ffff:a.b.c.d
+        let addr = addr.to_canonical();
+        if self.deny.iter().any(|net| net.contains(&addr)) {
+            return false;
+        }
+        self.allow.is_empty() || self.allow.iter().any(|net| net.contains(&addr))
+    }
+}
+
+/// Parse a CIDR subnet such as 192.168.1.0/24; a plain address stands for itself
+pub fn parse_ip_net(value: &str) -> Result<IpNet, String> {
+    value
+        .parse::<IpNet>()
+        .or_else(|_| value.parse::<IpAddr>().map(IpNet::from))
+        .map_err(|_| format!("'{}' is not an IP address or CIDR subnet", value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn nets(values: &[&str]) -> Vec<IpNet> {
+        values.iter().map(|value| parse_ip_net(value).unwrap()).collect()
+    }
+
+    #[test]
+    fn test_denylist_is_checked_before_the_allowlist() {
+        let filter = IpFilter::new(nets(&["192.168.1.0/24", "fd00::/8"]), nets(&["192.168.1.13"]));
+        assert!(filter.is_allowed("192.168.1.20".parse().unwrap()));
+        assert!(filter.is_allowed("::ffff:192.168.1.20".parse().unwrap()));
+        assert!(filter.is_allowed("fd00::1".parse().unwrap()));
+        assert!(!filter.is_allowed("192.168.1.13".parse().unwrap()));
+        assert!(!filter.is_allowed("10.0.0.1".parse().unwrap()));
+
+        // Without an allowlist everything but the denylist passes
+        let filter = IpFilter::new(Vec::new(), nets(&["10.0.0.0/8"]));
+        assert!(!filter.is_allowed("10.1.2.3".parse().unwrap()));
+        assert!(filter.is_allowed("127.0.0.1".parse().unwrap()));
+        assert!(IpFilter::default().is_allowed("10.1.2.3".parse().unwrap()));
+
+        assert!(parse_ip_net("192.168.1.0/33").is_err());
+    }
+}
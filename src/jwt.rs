@@ -0,0 +1,271 @@
+use anyhow::{anyhow, bail, Context, Result};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, Utc};
+use ring::signature::{self, UnparsedPublicKey, VerificationAlgorithm};
+use serde::Deserialize;
+use std::io::BufReader;
+use tracing::info;
+
+// Allowed difference between our clock and the token issuer's for exp and nbf
+const CLOCK_SKEW_LEEWAY_SECS: i64 = 60;
+
+// Algorithm OIDs of a SubjectPublicKeyInfo (DER content bytes)
+const OID_RSA_ENCRYPTION: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x01];
+const OID_EC_PUBLIC_KEY: &[u8] = &[0x2a, 0x86, 0x48, 0xce, 0x3d, 0x02, 0x01];
+const OID_PRIME256V1: &[u8] = &[0x2a, 0x86, 0x48, 0xce, 0x3d, 0x03, 0x01, 0x07];
+const OID_ED25519: &[u8] = &[0x2b, 0x65, 0x70];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum KeyType {
+    Rsa,
+    EcP256,
+    Ed25519,
+}
+
+impl KeyType {
+    /// Signature algorithm for the `alg` of a token header, None if it does not fit the key
+    fn verification_algorithm(self, alg: &str) -> Option<&'static dyn VerificationAlgorithm> {
+        match (self, alg) {
+            (KeyType::Rsa, "RS256") => Some(&signature::RSA_PKCS1_2048_8192_SHA256),
+            (KeyType::Rsa, "RS384") => Some(&signature::RSA_PKCS1_2048_8192_SHA384),
+            (KeyType::Rsa, "RS512") => Some(&signature::RSA_PKCS1_2048_8192_SHA512),
+            (KeyType::EcP256, "ES256") => Some(&signature::ECDSA_P256_SHA256_FIXED),
+            (KeyType::Ed25519, "EdDSA") => Some(&signature::ED25519),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct Header {
+    alg: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Claims {
+    sub: Option<String>,
+    exp: Option<i64>,
+    nbf: Option<i64>,
+    aud: Option<Audience>,
+}
+
+// aud is either a single string or a list of them
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum Audience {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl Audience {
+    fn contains(&self, audience: &str) -> bool {
+        match self {
+            Audience::One(value) => value == audience,
+            Audience::Many(values) => values.iter().any(|value| value == audience),
+        }
+    }
+}
+
+/// The claims of a validated token the session is built from
+#[derive(Debug, Clone, PartialEq)]
+pub struct JwtClaims {
+    pub subject: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Validates the JWTs clients send as password with --auth-mode jwt, against the public key
+/// of --jwt-public-key. RSA (RS256/384/512), P-256 (ES256) and Ed25519 (EdDSA) keys are
+/// supported; the token has to be signed with the algorithm of the key.
+#[derive(Debug)]
+pub struct JwtValidator {
+    key_type: KeyType,
+    public_key: Vec<u8>,
+    audience: Option<String>,
+}
+
+impl JwtValidator {
+    pub fn load(path: &str, audience: Option<String>) -> Result<Self> {
+        let pem = std::fs::read(path).with_context(|| format!("Failed to read JWT public key {}", path))?;
+        let validator = Self::from_pem(&pem, audience).with_context(|| format!("Invalid JWT public key {}", path))?;
+        info!("🔐 Loaded {:?} public key for JWT validation from {}", validator.key_type, path);
+        Ok(validator)
+    }
+
+    /// Parse a PEM "PUBLIC KEY" (SubjectPublicKeyInfo)
+    pub fn from_pem(pem: &[u8], audience: Option<String>) -> Result<Self> {
+        let spki = rustls_pemfile::public_keys(&mut BufReader::new(pem))
+            .next()
+            .ok_or_else(|| anyhow!("no PUBLIC KEY block found"))??;
+        let (key_type, public_key) = parse_subject_public_key_info(spki.as_ref())?;
+        Ok(Self {
+            key_type,
+            public_key,
+            audience,
+        })
+    }
+
+    /// Check signature, exp, nbf and aud of `token` and return its claims
+    pub fn validate(&self, token: &str) -> Result<JwtClaims> {
+        self.validate_at(token, Utc::now())
+    }
+
+    fn validate_at(&self, token: &str, now: DateTime<Utc>) -> Result<JwtClaims> {
+        let token = token.trim();
+        let mut parts = token.split('.');
+        let (Some(header), Some(payload), Some(signature), None) = (parts.next(), parts.next(), parts.next(), parts.next()) else {
+            bail!("token is not a JWT");
+        };
+        // The signature covers "header.payload"
+        let signed_part = &token[..header.len() + 1 + payload.len()];
+
+        let header: Header = decode_json_part(header).context("invalid JWT header")?;
+        let algorithm = self
+            .key_type
+            .verification_algorithm(&header.alg)
+            .ok_or_else(|| anyhow!("JWT algorithm {} does not match the {:?} public key", header.alg, self.key_type))?;
+        let signature = URL_SAFE_NO_PAD.decode(signature).context("invalid JWT signature encoding")?;
+        UnparsedPublicKey::new(algorithm, &self.public_key)
+            .verify(signed_part.as_bytes(), &signature)
+            .map_err(|_| anyhow!("JWT signature is invalid"))?;
+
+        let claims: Claims = decode_json_part(payload).context("invalid JWT claims")?;
+        let now_secs = now.timestamp();
+        let exp = claims.exp.ok_or_else(|| anyhow!("JWT has no exp claim"))?;
+        if exp + CLOCK_SKEW_LEEWAY_SECS <= now_secs {
+            bail!("JWT has expired");
+        }
+        if claims.nbf.is_some_and(|nbf| nbf - CLOCK_SKEW_LEEWAY_SECS > now_secs) {
+            bail!("JWT is not valid yet");
+        }
+        if let Some(audience) = &self.audience {
+            if !claims.aud.as_ref().is_some_and(|aud| aud.contains(audience)) {
+                bail!("JWT audience does not include {}", audience);
+            }
+        }
+        let subject = claims.sub.filter(|sub| !sub.is_empty()).ok_or_else(|| anyhow!("JWT has no sub claim"))?;
+        let expires_at = DateTime::from_timestamp(exp, 0).ok_or_else(|| anyhow!("JWT exp claim is out of range"))?;
+        Ok(JwtClaims { subject, expires_at })
+    }
+}
+
+fn decode_json_part<T: serde::de::DeserializeOwned>(part: &str) -> Result<T> {
+    let json = URL_SAFE_NO_PAD.decode(part)?;
+    Ok(serde_json::from_slice(&json)?)
+}
+
+/// Key type and the key bytes ring verifies with: the subjectPublicKey BIT STRING, which is
+/// the PKCS#1 RSAPublicKey, the uncompressed EC point or the raw Ed25519 key
+fn parse_subject_public_key_info(der: &[u8]) -> Result<(KeyType, Vec<u8>)> {
+    let (spki, _) = der_element(der, 0x30)?;
+    let (algorithm, rest) = der_element(spki, 0x30)?;
+    let (bit_string, _) = der_element(rest, 0x03)?;
+    let (oid, parameters) = der_element(algorithm, 0x06)?;
+
+    let key_type = match oid {
+        OID_RSA_ENCRYPTION => KeyType::Rsa,
+        OID_ED25519 => KeyType::Ed25519,
+        OID_EC_PUBLIC_KEY => match der_element(parameters, 0x06) {
+            Ok((OID_PRIME256V1, _)) => KeyType::EcP256,
+            _ => bail!("only P-256 EC keys are supported"),
+        },
+        _ => bail!("unsupported public key algorithm, expected RSA, P-256 or Ed25519"),
+    };
+    // The first byte counts the unused bits of the last byte, always 0 for keys
+    match bit_string.split_first() {
+        Some((0, key)) if !key.is_empty() => Ok((key_type, key.to_vec())),
+        _ => bail!("invalid public key bit string"),
+    }
+}
+
+/// Content of the DER element with `tag` at the start of `input`, and the bytes after it
+fn der_element(input: &[u8], tag: u8) -> Result<(&[u8], &[u8])> {
+    let invalid = || anyhow!("invalid DER encoding of the public key");
+    let (&actual_tag, rest) = input.split_first().ok_or_else(invalid)?;
+    if actual_tag != tag {
+        return Err(invalid());
+    }
+    let (&first, mut rest) = rest.split_first().ok_or_else(invalid)?;
+    let length = if first < 0x80 {
+        first as usize
+    } else {
+        let count = (first & 0x7f) as usize;
+        if count == 0 || count > 4 || rest.len() < count {
+            return Err(invalid());
+        }
+        let length = rest[..count].iter().fold(0usize, |length, &byte| (length << 8) | byte as usize);
+        rest = &rest[count..];
+        length
+    };
+    if rest.len() < length {
+        return Err(invalid());
+    }
+    Ok(rest.split_at(length))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use base64::engine::general_purpose::STANDARD;
+    use ring::rand::SystemRandom;
+    use ring::signature::{EcdsaKeyPair, KeyPair, ECDSA_P256_SHA256_FIXED_SIGNING};
+
+    // SubjectPublicKeyInfo of a P-256 key up to the 65 byte uncompressed point
+    const P256_SPKI_PREFIX: [u8; 26] = [
+        0x30, 0x59, 0x30, 0x13, 0x06, 0x07, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x02, 0x01, 0x06, 0x08, 0x2a, 0x86, 0x48, 0xce,
+        0x3d, 0x03, 0x01, 0x07, 0x03, 0x42, 0x00,
+    ];
+
+    /// ES256 signing key and the PEM of its public key, for tests that need real tokens
+    pub(crate) fn es256_key_pair() -> (EcdsaKeyPair, String) {
+        let rng = SystemRandom::new();
+        let pkcs8 = EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, &rng).unwrap();
+        let key_pair = EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, pkcs8.as_ref(), &rng).unwrap();
+        let mut spki = P256_SPKI_PREFIX.to_vec();
+        spki.extend_from_slice(key_pair.public_key().as_ref());
+        let pem = format!("-----BEGIN PUBLIC KEY-----\n{}\n-----END PUBLIC KEY-----\n", STANDARD.encode(spki));
+        (key_pair, pem)
+    }
+
+    pub(crate) fn sign_es256(key_pair: &EcdsaKeyPair, claims: serde_json::Value) -> String {
+        let header = URL_SAFE_NO_PAD.encode(r#"{"alg":"ES256","typ":"JWT"}"#);
+        let payload = URL_SAFE_NO_PAD.encode(claims.to_string());
+        let signed_part = format!("{}.{}", header, payload);
+        let signature = key_pair.sign(&SystemRandom::new(), signed_part.as_bytes()).unwrap();
+        format!("{}.{}", signed_part, URL_SAFE_NO_PAD.encode(signature.as_ref()))
+    }
+
+    #[test]
+    fn test_tokens_are_checked_for_signature_expiry_and_audience() {
+        let (key_pair, pem) = es256_key_pair();
+        let validator = JwtValidator::from_pem(pem.as_bytes(), Some("winccua".to_string())).unwrap();
+        let now = Utc::now();
+        let exp = now.timestamp() + 3600;
+
+        let token = sign_es256(&key_pair, serde_json::json!({"sub": "grafana", "exp": exp, "aud": ["other", "winccua"]}));
+        let claims = validator.validate_at(&token, now).unwrap();
+        assert_eq!(claims.subject, "grafana");
+        assert_eq!(claims.expires_at.timestamp(), exp);
+
+        // Expired beyond the leeway
+        let error = validator.validate_at(&token, now + chrono::Duration::seconds(3600 + CLOCK_SKEW_LEEWAY_SECS)).unwrap_err();
+        assert_eq!(error.to_string(), "JWT has expired");
+
+        let token = sign_es256(&key_pair, serde_json::json!({"sub": "grafana", "exp": exp, "aud": "other"}));
+        assert!(validator.validate_at(&token, now).unwrap_err().to_string().contains("audience"));
+
+        // A token signed by another key, or with its claims swapped, fails the signature check
+        let (other_key_pair, _) = es256_key_pair();
+        let token = sign_es256(&other_key_pair, serde_json::json!({"sub": "grafana", "exp": exp, "aud": "winccua"}));
+        assert_eq!(validator.validate_at(&token, now).unwrap_err().to_string(), "JWT signature is invalid");
+        let admin = URL_SAFE_NO_PAD.encode(serde_json::json!({"sub": "admin", "exp": exp, "aud": "winccua"}).to_string());
+        let parts: Vec<&str> = token.split('.').collect();
+        let tampered = format!("{}.{}.{}", parts[0], admin, parts[2]);
+        assert!(validator.validate_at(&tampered, now).is_err());
+
+        // The algorithm has to match the key, "none" in particular is never accepted
+        let unsigned = format!("{}.{}.", URL_SAFE_NO_PAD.encode(r#"{"alg":"none"}"#), admin);
+        assert!(validator.validate_at(&unsigned, now).unwrap_err().to_string().contains("algorithm none"));
+
+        assert!(JwtValidator::from_pem(b"-----BEGIN CERTIFICATE-----\nAAAA\n-----END CERTIFICATE-----\n", None).is_err());
+    }
+}
@@ -1,24 +1,87 @@
 use anyhow::Result;
-use clap::Parser;
+use clap::{CommandFactory, FromArgMatches, Parser};
 use std::fmt;
 use std::net::SocketAddr;
-use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicU8, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
 use tracing::{info, warn};
-use tracing_subscriber::fmt::format::Writer;
-use tracing_subscriber::fmt::{FmtContext, FormatEvent, FormatFields};
+use tracing_subscriber::fmt::format::{JsonFields, Writer};
+use tracing_subscriber::fmt::{FmtContext, FormatEvent, FormatFields, FormattedFields};
+use tracing_subscriber::layer::SubscriberExt;
 use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::EnvFilter;
 
 // Global setting for SQL logging (0 = disabled, >0 = enabled with row count limit)
 pub static LOG_SQL_ROWS: AtomicU32 = AtomicU32::new(0);
 
+// Global settings for TagValues queries without a tag_name filter (full scan via browse)
+pub static ALLOW_FULL_TAGVALUES_SCAN: AtomicBool = AtomicBool::new(false);
+pub static FULL_SCAN_LIMIT: AtomicU32 = AtomicU32::new(1000);
+
+// Global setting for UPDATE tagvalues SET ... (writes are rejected with SQLSTATE 42501 unless enabled)
+pub static ENABLE_WRITES: AtomicBool = AtomicBool::new(false);
+
+// Global setting for UPDATE activealarms SET state = 'ACKNOWLEDGED' (independent of tag writes)
+pub static ENABLE_ALARM_WRITES: AtomicBool = AtomicBool::new(false);
+
+// Global settings for the languages of alarm texts (requested languages, default locale)
+pub static ALARM_LANGUAGES: OnceLock<Vec<String>> = OnceLock::new();
+pub static ALARM_LOCALE: OnceLock<String> = OnceLock::new();
+
+// Global setting for the columns whose changes activealarms_changes reports as CHANGED
+pub static ALARM_DIFF_COLUMNS: OnceLock<Vec<String>> = OnceLock::new();
+
+// Global setting for counting slow queries in winccua_connection_stats (0 = disabled)
+pub static SLOW_QUERY_THRESHOLD_MS: AtomicU64 = AtomicU64::new(1000);
+
+// Global setting for reporting text columns holding JSON objects or arrays as jsonb
+pub static JSONB_AUTO_DETECT: AtomicBool = AtomicBool::new(false);
+
+// Global setting for sending NaN and Infinity float values as NULL
+pub static NAN_AS_NULL: AtomicBool = AtomicBool::new(false);
+
+// Global setting for the __error column of tagvalues, rows of unreadable tags carry their error
+pub static INCLUDE_ERRORS_COLUMN: AtomicBool = AtomicBool::new(false);
+
+// Global settings for paged browse requests
+pub static BROWSE_PAGE_SIZE: AtomicU32 = AtomicU32::new(graphql::client::DEFAULT_BROWSE_PAGE_SIZE);
+pub static BROWSE_MAX_PAGES: AtomicU32 = AtomicU32::new(graphql::client::DEFAULT_BROWSE_MAX_PAGES);
+pub static BROWSE_MAX_TAGS: AtomicU32 = AtomicU32::new(graphql::client::DEFAULT_BROWSE_MAX_TAGS);
+
+// Global settings for tag_name filter values (case folding, case-insensitive retry of unknown tags)
+pub static TAG_NAME_CASE_FOLD: AtomicU8 = AtomicU8::new(TagNameCaseFold::None as u8);
+pub static TAG_NAME_FALLBACK_BROWSE: AtomicBool = AtomicBool::new(false);
+pub static TAG_NAME_AUTOCOMPLETE: AtomicBool = AtomicBool::new(false);
+
+// Global configuration shown in winccua_config and winccua_version
+pub static GLOBAL_CONFIG: OnceLock<server_config::GlobalConfig> = OnceLock::new();
+
 mod auth;
+mod cache;
+mod circuit_breaker;
+mod credentials;
 mod datafusion_handler;
 mod graphql;
+mod health;
+mod ip_filter;
+mod jwt;
 mod keep_alive;
+mod metrics;
 mod pg_protocol;
+mod quality;
 mod query_handler;
+mod rate_limiter;
+mod server_config;
+mod session_recovery;
+mod sql_log;
 mod sql_handler;
 mod tables;
+mod telemetry;
+#[cfg(test)]
+mod test_support;
 mod tls;
 
 // Custom formatter for consistent module name width
@@ -67,6 +130,124 @@ where
     }
 }
 
+/// Newline-delimited JSON log lines. Span fields (connection_id, query_hash) are expected to be
+/// recorded with `JsonFields` and are merged into every event logged inside the span.
+struct JsonFormatter;
+
+impl<S, N> FormatEvent<S, N> for JsonFormatter
+where
+    S: tracing::Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    fn format_event(
+        &self,
+        ctx: &FmtContext<'_, S, N>,
+        mut writer: Writer<'_>,
+        event: &tracing::Event<'_>,
+    ) -> fmt::Result {
+        let metadata = event.metadata();
+        let target = metadata.target();
+        let module = target.strip_prefix("winccua_pgwire_protocol::").unwrap_or(target);
+
+        let mut fields = serde_json::Map::new();
+        fields.insert(
+            "timestamp".to_string(),
+            chrono::Utc::now().format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string().into(),
+        );
+        fields.insert("level".to_string(), metadata.level().as_str().into());
+        fields.insert("module".to_string(), module.into());
+
+        // Outermost span first so fields of inner spans take precedence
+        if let Some(scope) = ctx.event_scope() {
+            for span in scope.from_root() {
+                let extensions = span.extensions();
+                if let Some(span_fields) = extensions.get::<FormattedFields<N>>() {
+                    if let Ok(serde_json::Value::Object(span_fields)) = serde_json::from_str(span_fields) {
+                        fields.extend(span_fields);
+                    }
+                }
+            }
+        }
+
+        event.record(&mut JsonFieldVisitor(&mut fields));
+        writeln!(writer, "{}", serde_json::Value::Object(fields))
+    }
+}
+
+struct JsonFieldVisitor<'a>(&'a mut serde_json::Map<String, serde_json::Value>);
+
+impl tracing::field::Visit for JsonFieldVisitor<'_> {
+    fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn fmt::Debug) {
+        self.0.insert(field.name().to_string(), format!("{:?}", value).into());
+    }
+
+    fn record_str(&mut self, field: &tracing::field::Field, value: &str) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_i64(&mut self, field: &tracing::field::Field, value: i64) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_u64(&mut self, field: &tracing::field::Field, value: u64) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_f64(&mut self, field: &tracing::field::Field, value: f64) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_bool(&mut self, field: &tracing::field::Field, value: bool) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LogFormat {
+    Text,
+    Json,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AuthMode {
+    /// MD5 password, verified against the known passwords or used for the GraphQL login as is
+    Md5,
+    /// SCRAM-SHA-256 against the passwords of --scram-password-file
+    #[value(name = "scram-sha-256")]
+    ScramSha256,
+    /// Cleartext password, used for the GraphQL login
+    Password,
+    /// No authentication, every connection logs in to GraphQL with --default-user and --default-password
+    Trust,
+    /// A JWT sent as cleartext password is validated locally and used as GraphQL bearer token
+    Jwt,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TagNameCaseFold {
+    None = 0,
+    Lower = 1,
+    Upper = 2,
+}
+
+impl TagNameCaseFold {
+    pub fn current() -> Self {
+        match TAG_NAME_CASE_FOLD.load(Ordering::Relaxed) {
+            1 => Self::Lower,
+            2 => Self::Upper,
+            _ => Self::None,
+        }
+    }
+
+    pub fn apply(self, tag_name: &str) -> String {
+        match self {
+            Self::None => tag_name.to_string(),
+            Self::Lower => tag_name.to_lowercase(),
+            Self::Upper => tag_name.to_uppercase(),
+        }
+    }
+}
+
 #[derive(Parser, Debug)]
 #[command(name = "winccua-pgwire-protocol")]
 #[command(about = "PostgreSQL wire protocol server for WinCC UA GraphQL backend")]
@@ -75,9 +256,13 @@ pub struct Args {
     #[arg(long, default_value = "127.0.0.1:5432")]
     pub bind_addr: SocketAddr,
 
-    /// GraphQL server URL (also reads from GRAPHQL_HTTP_URL env var)
+    /// GraphQL server URL, repeat for redundant servers (also reads from GRAPHQL_HTTP_URL env var, comma-separated)
+    #[arg(long, action = clap::ArgAction::Append)]
+    pub graphql_url: Vec<String>,
+
+    /// Authenticate on the first --graphql-url and use the others only when it fails (default: round-robin)
     #[arg(long)]
-    pub graphql_url: Option<String>,
+    pub graphql_primary_only: bool,
 
     /// Enable debug logging
     #[arg(long)]
@@ -103,10 +288,54 @@ pub struct Args {
     #[arg(long)]
     pub tls_require_client_cert: bool,
 
+    /// File with username:password lines; the passwords SCRAM-SHA-256 and MD5 are verified against
+    #[arg(long, value_name = "PATH", required_if_eq("auth_mode", "scram-sha-256"))]
+    pub scram_password_file: Option<String>,
+
+    /// How clients authenticate: md5, scram-sha-256, password (cleartext), trust or jwt (default: md5, scram-sha-256 with --scram-password-file)
+    #[arg(long, value_enum)]
+    pub auth_mode: Option<AuthMode>,
+
+    /// GraphQL user every connection logs in as with --auth-mode trust
+    #[arg(long, required_if_eq("auth_mode", "trust"))]
+    pub default_user: Option<String>,
+
+    /// GraphQL password of --default-user for --auth-mode trust
+    #[arg(long, required_if_eq("auth_mode", "trust"))]
+    pub default_password: Option<String>,
+
+    /// PEM public key (RSA, P-256 or Ed25519) that signs the JWTs of --auth-mode jwt
+    #[arg(long, value_name = "PATH", required_if_eq("auth_mode", "jwt"))]
+    pub jwt_public_key: Option<String>,
+
+    /// Audience the JWTs of --auth-mode jwt must contain in their aud claim (default: not checked)
+    #[arg(long)]
+    pub jwt_audience: Option<String>,
+
+    /// Subnet allowed to connect over TCP in CIDR notation, repeatable; others are rejected (default: all)
+    #[arg(long, value_name = "CIDR", value_parser = ip_filter::parse_ip_net)]
+    pub allow_ip: Vec<ipnet::IpNet>,
+
+    /// Subnet rejected before --allow-ip is checked, in CIDR notation, repeatable (default: none)
+    #[arg(long, value_name = "CIDR", value_parser = ip_filter::parse_ip_net)]
+    pub deny_ip: Vec<ipnet::IpNet>,
+
+    /// ParameterStatus value reported to clients after authentication as NAME=VALUE, repeatable, e.g. integer_datetimes=off (default: built-in values)
+    #[arg(long, value_name = "NAME=VALUE", value_parser = pg_protocol::settings::parse_parameter_status)]
+    pub pg_param_status: Vec<(String, String)>,
+
     /// Session extension interval in seconds (default: 600 = 10 minutes)
     #[arg(long, default_value_t = 600)]
     pub session_extension_interval: u64,
 
+    /// Keep the tokens of open GraphQL sessions in this JSON file and log out the ones left by a crash at startup (default: disabled)
+    #[arg(long, value_name = "PATH")]
+    pub session_recovery_file: Option<String>,
+
+    /// Seconds after which a connection logs in to GraphQL again instead of extending its session, 0 disables it (default: 3600)
+    #[arg(long, value_name = "SECONDS", default_value_t = session_recovery::DEFAULT_SESSION_MAX_LIFETIME_SECS)]
+    pub session_max_lifetime: u64,
+
     /// Keep-alive interval in seconds (default: 30 seconds)
     #[arg(long, default_value_t = 30)]
     pub keep_alive_interval: u64,
@@ -115,38 +344,275 @@ pub struct Args {
     #[arg(long, value_name = "ROWS")]
     pub log_sql: Option<u32>,
 
+    /// Append every received query to this file for replay, one tab-separated line per query (default: disabled)
+    #[arg(long, value_name = "PATH")]
+    pub sql_log_file: Option<String>,
+
+    /// Size in bytes at which --sql-log-file is renamed to <path>.1 and a new file started, 0 disables it (default: 104857600)
+    #[arg(long, value_name = "BYTES", default_value_t = 104857600)]
+    pub sql_log_rotate_size: u64,
+
     /// Suppress connection and authentication log messages
     #[arg(long)]
     pub quiet_connections: bool,
+
+    /// Allow TagValues queries without a tag_name filter by browsing all tags (can be slow)
+    #[arg(long)]
+    pub allow_full_tagvalues_scan: bool,
+
+    /// Allow writing tag values with UPDATE tagvalues SET ... (default: disabled)
+    #[arg(long)]
+    pub enable_writes: bool,
+
+    /// Allow acknowledging alarms with UPDATE activealarms SET state = 'ACKNOWLEDGED' (default: disabled)
+    #[arg(long)]
+    pub enable_alarm_writes: bool,
+
+    /// Languages requested for alarm event and info texts, comma-separated (default: en-US)
+    #[arg(long, value_delimiter = ',', default_value = "en-US")]
+    pub alarm_languages: Vec<String>,
+
+    /// Language of the alarm texts shown in event_text and info_text, empty for the first
+    /// requested language; connections can override it with SET winccua.alarm_locale (default: "")
+    #[arg(long, default_value = "")]
+    pub alarm_locale: String,
+
+    /// Columns compared by activealarms_changes to report an alarm as CHANGED, comma-separated (default: state,priority)
+    #[arg(long, value_delimiter = ',', default_value = "state,priority", value_parser = query_handler::active_alarms_handler::parse_alarm_diff_column)]
+    pub alarm_diff_columns: Vec<String>,
+
+    /// Report text result columns whose values are all JSON objects or arrays as jsonb (default: disabled)
+    #[arg(long)]
+    pub jsonb_auto_detect: bool,
+
+    /// Send NaN and Infinity float values as NULL instead of 'NaN' and 'Infinity' (default: disabled)
+    #[arg(long)]
+    pub nan_as_null: bool,
+
+    /// Add an __error column to tagvalues with the GraphQL error of tags that could not be read (disabled by default)
+    #[arg(long)]
+    pub include_errors_column: bool,
+
+    /// Case folding applied to tag_name filter values before they are sent to GraphQL (default: none)
+    #[arg(long, value_enum, default_value_t = TagNameCaseFold::None)]
+    pub tag_name_case_fold: TagNameCaseFold,
+
+    /// Retry tags that are not found with a case-insensitive browse, only without case folding (default: disabled)
+    #[arg(long)]
+    pub tag_name_fallback_browse: bool,
+
+    /// Complete tags of a tag_name = or IN filter that are not found to the single tag starting with the name (disabled by default)
+    #[arg(long)]
+    pub tag_name_autocomplete: bool,
+
+    /// Maximum number of tags returned by a TagValues full scan (default: 1000)
+    #[arg(long, default_value_t = 1000)]
+    pub full_scan_limit: u32,
+
+    /// Number of tags requested per page when browsing large namespaces (default: 500)
+    #[arg(long, default_value_t = 500)]
+    pub browse_page_size: u32,
+
+    /// Maximum number of pages fetched when browsing large namespaces (default: 100)
+    #[arg(long, default_value_t = 100)]
+    pub browse_max_pages: u32,
+
+    /// Maximum number of tags returned by a browse, larger results are cut off (default: 10000)
+    #[arg(long, default_value_t = 10000)]
+    pub browse_max_tags: u32,
+
+    /// Seconds browse results for LIKE patterns are cached, 0 disables the cache (default: 60)
+    #[arg(long, default_value_t = 60)]
+    pub browse_cache_ttl: u64,
+
+    /// Maximum number of cached browse patterns (default: 1000)
+    #[arg(long, default_value_t = 1000)]
+    pub browse_cache_max_entries: usize,
+
+    /// Maximum number of unnamed prepared statements shared between connections, 0 disables the cache (default: 200)
+    #[arg(long, default_value_t = 200)]
+    pub statement_cache_size: usize,
+
+    /// Dedicated address for Prometheus metrics (GET /metrics); served on --http-addr if not set
+    #[arg(long)]
+    pub metrics_addr: Option<SocketAddr>,
+
+    /// Address of the HTTP server for liveness (/healthz) and readiness (/readyz) probes
+    #[arg(long, default_value = "127.0.0.1:8080")]
+    pub http_addr: SocketAddr,
+
+    /// Log output format: human-readable text or newline-delimited JSON
+    #[arg(long, value_enum, default_value_t = LogFormat::Text)]
+    pub log_format: LogFormat,
+
+    /// Number of tags per GraphQL request; batches are fetched concurrently (default: 50)
+    #[arg(long, default_value_t = 50, value_parser = clap::value_parser!(u32).range(1..))]
+    pub graphql_batch_size: u32,
+
+    /// Maximum query execution time in milliseconds, 0 disables the timeout (default: 30000)
+    #[arg(long, default_value_t = 30000)]
+    pub query_timeout: u64,
+
+    /// Seconds between two polls of the tag of a LISTEN channel (default: 5)
+    #[arg(long, default_value_t = 5, value_parser = clap::value_parser!(u64).range(1..))]
+    pub listen_poll_interval: u64,
+
+    /// Queries taking at least this many milliseconds count as slow_queries in winccua_connection_stats, 0 disables it (default: 1000)
+    #[arg(long, default_value_t = 1000)]
+    pub slow_query_threshold: u64,
+
+    /// Queries per second allowed for each user, 0 disables the rate limit (default: 0)
+    #[arg(long, default_value_t = 0)]
+    pub rate_limit_queries_per_second: u32,
+
+    /// Queries a user may run at once before --rate-limit-queries-per-second applies (default: 10)
+    #[arg(long, default_value_t = 10)]
+    pub rate_limit_burst: u32,
+
+    /// Unix domain socket to listen on in addition to TCP; a directory gets the socket file .s.PGSQL.<port>
+    #[arg(long)]
+    pub unix_socket_path: Option<String>,
+
+    /// OTLP gRPC endpoint to export traces to (e.g., http://localhost:4317)
+    #[arg(long)]
+    pub otel_endpoint: Option<String>,
+
+    /// Seconds to wait for in-flight queries on SIGTERM/SIGINT before cancelling them (default: 30)
+    #[arg(long, default_value_t = 30)]
+    pub shutdown_timeout: u64,
+
+    /// Seconds in-flight queries get to complete in drain mode on SIGTERM/SIGINT before idle connections are closed (default: 30)
+    #[arg(long, default_value_t = 30)]
+    pub drain_timeout: u64,
+
+    /// Maximum number of simultaneous client connections, 0 for no limit (default: 100)
+    #[arg(long, default_value_t = 100)]
+    pub max_connections: usize,
+
+    /// Maximum number of simultaneous connections per user, 0 for no limit (default: 10)
+    #[arg(long, default_value_t = 10)]
+    pub max_connections_per_user: usize,
+
+    /// Maximum idle HTTP connections kept open to the GraphQL server (default: 20)
+    #[arg(long, default_value_t = 20)]
+    pub graphql_pool_size: usize,
+
+    /// Seconds an idle pooled GraphQL connection is kept open (default: 90)
+    #[arg(long, default_value_t = 90)]
+    pub graphql_pool_idle_timeout: u64,
+
+    /// Retries of a GraphQL request after a network error, HTTP 429 or HTTP 503, 0 disables retries (default: 3)
+    #[arg(long, default_value_t = 3)]
+    pub graphql_max_retries: u32,
+
+    /// Milliseconds before the first GraphQL retry, doubled for every further retry (default: 100)
+    #[arg(long, default_value_t = 100)]
+    pub graphql_retry_initial_backoff: u64,
+
+    /// Consecutive GraphQL connection failures that open the circuit breaker, 0 disables it (default: 5)
+    #[arg(long, default_value_t = 5)]
+    pub cb_failure_threshold: u32,
+
+    /// Seconds the circuit breaker stays open before a probe request is allowed (default: 30)
+    #[arg(long, default_value_t = 30)]
+    pub cb_reset_timeout: u64,
+}
+
+/// Cancel the token on SIGINT (Ctrl+C) or, on Unix, SIGTERM. The SIGTERM handler is
+/// installed before returning, so the default action no longer terminates the process.
+fn spawn_shutdown_signal_handler(shutdown: CancellationToken) -> Result<()> {
+    #[cfg(unix)]
+    let mut sigterm = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())?;
+
+    tokio::spawn(async move {
+        #[cfg(unix)]
+        let terminate = sigterm.recv();
+        #[cfg(not(unix))]
+        let terminate = std::future::pending::<Option<()>>();
+
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => info!("🛑 Received SIGINT, shutting down"),
+            _ = terminate => info!("🛑 Received SIGTERM, shutting down"),
+        }
+        shutdown.cancel();
+    });
+    Ok(())
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    let args = Args::parse();
+    let command = Args::command();
+    let matches = command.clone().get_matches();
+    let args = Args::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
 
-    // Get GraphQL URL from args or environment
-    let graphql_url = args
-        .graphql_url
-        .or_else(|| std::env::var("GRAPHQL_HTTP_URL").ok())
+    // Get GraphQL URLs from args or environment
+    let graphql_urls = if args.graphql_url.is_empty() {
+        std::env::var("GRAPHQL_HTTP_URL")
+            .map(|urls| urls.split(',').map(|url| url.trim().to_string()).filter(|url| !url.is_empty()).collect())
+            .unwrap_or_default()
+    } else {
+        args.graphql_url.clone()
+    };
+    let graphql_url = graphql_urls
+        .first()
+        .cloned()
         .expect("GraphQL URL must be provided via --graphql-url or GRAPHQL_HTTP_URL environment variable");
+    let _ = GLOBAL_CONFIG.set(server_config::GlobalConfig::from_matches(&command, &matches, &graphql_urls));
 
     // Initialize logging with custom formatter for consistent module name width
     let log_level = if args.debug { "debug" } else { "info" };
-    tracing_subscriber::fmt()
-        .with_env_filter(format!(
-            "{}={},winccua_pgwire_protocol={}",
-            env!("CARGO_PKG_NAME").replace('-', "_"),
-            log_level,
-            log_level
-        ))
-        .event_format(CustomFormatter)
-        .init();
+    let env_filter = format!(
+        "{}={},winccua_pgwire_protocol={}",
+        env!("CARGO_PKG_NAME").replace('-', "_"),
+        log_level,
+        log_level
+    );
+    let otel_provider = match &args.otel_endpoint {
+        Some(endpoint) => Some(telemetry::init_tracer_provider(endpoint)?),
+        None => None,
+    };
+    let registry = tracing_subscriber::registry()
+        .with(EnvFilter::new(env_filter))
+        .with(otel_provider.as_ref().map(telemetry::layer));
+    match args.log_format {
+        LogFormat::Text => registry
+            .with(tracing_subscriber::fmt::layer().event_format(CustomFormatter))
+            .init(),
+        LogFormat::Json => registry
+            .with(tracing_subscriber::fmt::layer().fmt_fields(JsonFields::new()).event_format(JsonFormatter))
+            .init(),
+    }
 
     info!("Starting WinCC UA PostgreSQL Wire Protocol Server");
     info!("Binding to: {}", args.bind_addr);
-    info!("GraphQL URL: {}", graphql_url);
+    info!("GraphQL URL: {}", graphql_urls.join(", "));
+    if graphql_urls.len() > 1 {
+        let mode = if args.graphql_primary_only { "primary with failover" } else { "round-robin with failover" };
+        info!("GraphQL servers: {} ({})", graphql_urls.len(), mode);
+    }
     info!("Session extension interval: {} seconds", args.session_extension_interval);
     info!("Keep-alive interval: {} seconds", args.keep_alive_interval);
+    if args.query_timeout > 0 {
+        info!("Query timeout: {} ms", args.query_timeout);
+    } else {
+        info!("Query timeout: disabled");
+    }
+    SLOW_QUERY_THRESHOLD_MS.store(args.slow_query_threshold, Ordering::Relaxed);
+    if args.slow_query_threshold > 0 {
+        info!("Slow query threshold: {} ms", args.slow_query_threshold);
+    }
+    if args.rate_limit_queries_per_second > 0 {
+        info!("Rate limit: {} queries per second per user, burst of {}", args.rate_limit_queries_per_second, args.rate_limit_burst);
+    }
+    if let Some(endpoint) = &args.otel_endpoint {
+        info!("OpenTelemetry trace export: {}", endpoint);
+    }
+    info!("Shutdown timeout: {} seconds", args.shutdown_timeout);
+    info!("Drain timeout: {} seconds", args.drain_timeout);
+    info!("Connection limits: {} total, {} per user (0 = unlimited)", args.max_connections, args.max_connections_per_user);
+    info!("GraphQL connection pool: {} idle connections, {} s idle timeout", args.graphql_pool_size, args.graphql_pool_idle_timeout);
+    info!("GraphQL retries: up to {}, {} ms initial backoff", args.graphql_max_retries, args.graphql_retry_initial_backoff);
+    info!("Circuit breaker: opens after {} failures, {} s reset timeout", args.cb_failure_threshold, args.cb_reset_timeout);
     
     // Set global SQL logging setting
     let log_sql_rows = args.log_sql.unwrap_or(0);
@@ -157,20 +623,65 @@ async fn main() -> Result<()> {
         info!("SQL query logging: DEBUG level only");
     }
 
-    // Validate GraphQL connection
-    info!("Validating GraphQL connection to: {}", graphql_url);
-    match graphql::client::validate_connection(&graphql_url).await {
-        Ok(()) => {
-            info!("✅ GraphQL connection validated successfully");
+    // Set global TagValues full scan settings
+    ALLOW_FULL_TAGVALUES_SCAN.store(args.allow_full_tagvalues_scan, Ordering::Relaxed);
+    FULL_SCAN_LIMIT.store(args.full_scan_limit, Ordering::Relaxed);
+    BROWSE_PAGE_SIZE.store(args.browse_page_size, Ordering::Relaxed);
+    BROWSE_MAX_PAGES.store(args.browse_max_pages, Ordering::Relaxed);
+    BROWSE_MAX_TAGS.store(args.browse_max_tags, Ordering::Relaxed);
+    ENABLE_WRITES.store(args.enable_writes, Ordering::Relaxed);
+    ENABLE_ALARM_WRITES.store(args.enable_alarm_writes, Ordering::Relaxed);
+    let _ = ALARM_LANGUAGES.set(
+        args.alarm_languages
+            .iter()
+            .map(|language| language.trim().to_string())
+            .filter(|language| !language.is_empty())
+            .collect(),
+    );
+    let _ = ALARM_LOCALE.set(args.alarm_locale.clone());
+    let _ = ALARM_DIFF_COLUMNS.set(args.alarm_diff_columns.clone());
+    JSONB_AUTO_DETECT.store(args.jsonb_auto_detect, Ordering::Relaxed);
+    NAN_AS_NULL.store(args.nan_as_null, Ordering::Relaxed);
+    INCLUDE_ERRORS_COLUMN.store(args.include_errors_column, Ordering::Relaxed);
+    TAG_NAME_CASE_FOLD.store(args.tag_name_case_fold as u8, Ordering::Relaxed);
+    TAG_NAME_FALLBACK_BROWSE.store(args.tag_name_fallback_browse, Ordering::Relaxed);
+    TAG_NAME_AUTOCOMPLETE.store(args.tag_name_autocomplete, Ordering::Relaxed);
+    if args.tag_name_case_fold != TagNameCaseFold::None {
+        info!("Tag name case folding: {:?}", args.tag_name_case_fold);
+        if args.tag_name_fallback_browse {
+            warn!("⚠️  --tag-name-fallback-browse has no effect together with --tag-name-case-fold");
         }
-        Err(e) => {
-            warn!("⚠️  GraphQL connection validation failed: {}", e);
-            warn!("This could mean:");
-            warn!("  - GraphQL server is not running");
-            warn!("  - URL is incorrect (current: {})", graphql_url);
-            warn!("  - Network connectivity issues");
-            warn!("  - Server doesn't support introspection queries");
-            warn!("Server will start anyway, but authentication will likely fail.");
+    }
+    if args.jsonb_auto_detect {
+        info!("JSON auto-detection: text columns holding JSON are reported as jsonb");
+    }
+    if args.enable_writes {
+        warn!("⚠️  Tag writes: ENABLED (UPDATE tagvalues SET ... writes to WinCC Unified)");
+    }
+    if args.enable_alarm_writes {
+        warn!("⚠️  Alarm acknowledgment: ENABLED (UPDATE activealarms SET state = 'ACKNOWLEDGED')");
+    }
+    if args.allow_full_tagvalues_scan {
+        warn!("⚠️  TagValues full scan: ENABLED (max {} tags)", args.full_scan_limit);
+        warn!("   Queries without a tag_name filter browse all tags, which can be slow on large systems");
+    }
+
+    // Validate GraphQL connection
+    for graphql_url in &graphql_urls {
+        info!("Validating GraphQL connection to: {}", graphql_url);
+        match graphql::client::validate_connection(graphql_url).await {
+            Ok(()) => {
+                info!("✅ GraphQL connection validated successfully");
+            }
+            Err(e) => {
+                warn!("⚠️  GraphQL connection validation failed: {}", e);
+                warn!("This could mean:");
+                warn!("  - GraphQL server is not running");
+                warn!("  - URL is incorrect (current: {})", graphql_url);
+                warn!("  - Network connectivity issues");
+                warn!("  - Server doesn't support introspection queries");
+                warn!("Server will start anyway, but authentication will likely fail.");
+            }
         }
     }
 
@@ -199,6 +710,43 @@ async fn main() -> Result<()> {
         None
     };
 
+    // Passwords to verify SCRAM-SHA-256 against before they are used for the GraphQL login
+    let scram_credentials = match &args.scram_password_file {
+        Some(path) => Some(Arc::new(credentials::CredentialStore::load(path)?)),
+        None => None,
+    };
+
+    let sql_log = match &args.sql_log_file {
+        Some(path) => Some(Arc::new(sql_log::SqlLog::open(path, args.sql_log_rotate_size).await?)),
+        None => None,
+    };
+
+    let auth_mode = args.auth_mode.unwrap_or(if scram_credentials.is_some() { AuthMode::ScramSha256 } else { AuthMode::Md5 });
+    let default_credentials = match (&args.default_user, &args.default_password) {
+        (Some(user), Some(password)) if auth_mode == AuthMode::Trust => {
+            warn!("⚠️  ================================================================");
+            warn!("⚠️  WARNING: --auth-mode trust accepts every client WITHOUT a password");
+            warn!("⚠️  All connections log in to GraphQL as '{}'", user);
+            warn!("⚠️  ================================================================");
+            Some((user.clone(), password.clone()))
+        }
+        _ => None,
+    };
+
+    // Public key to validate the JWTs clients send as password with --auth-mode jwt
+    let jwt_validator = match (auth_mode, &args.jwt_public_key) {
+        (AuthMode::Jwt, Some(path)) => {
+            info!("Authentication mode: JWT{}", args.jwt_audience.as_ref().map(|aud| format!(" (audience {})", aud)).unwrap_or_default());
+            Some(Arc::new(jwt::JwtValidator::load(path, args.jwt_audience.clone())?))
+        }
+        _ => None,
+    };
+
+    let ip_filter = ip_filter::IpFilter::new(args.allow_ip.clone(), args.deny_ip.clone());
+    if !ip_filter.is_empty() {
+        info!("IP filter: {} allowed, {} denied subnets", args.allow_ip.len(), args.deny_ip.len());
+    }
+
     // For now, always use the simple server with improved PostgreSQL compatibility
     // The pgwire library API is too complex and has changed significantly
     if tls_config.is_some() {
@@ -207,14 +755,157 @@ async fn main() -> Result<()> {
         info!("🐘 Starting PostgreSQL-compatible server");
     }
     
+    // Start the Prometheus metrics endpoint, on its own address if configured
+    let metrics = Arc::new(metrics::Metrics::new()?);
+    if let Some(metrics_addr) = args.metrics_addr {
+        let metrics_for_endpoint = metrics.clone();
+        tokio::spawn(async move {
+            if let Err(e) = metrics::serve(metrics_addr, metrics_for_endpoint).await {
+                warn!("⚠️  Metrics endpoint on {} failed: {}", metrics_addr, e);
+            }
+        });
+    }
+
+    // Start the liveness/readiness probe endpoint; GET /drain puts the server in drain mode
+    let http_addr = args.http_addr;
+    let draining = Arc::new(AtomicBool::new(false));
+    let health_state = Arc::new(health::HealthState {
+        graphql_urls: graphql_urls.clone(),
+        metrics: args.metrics_addr.is_none().then(|| metrics.clone()),
+        draining: draining.clone(),
+    });
+    tokio::spawn(async move {
+        if let Err(e) = health::serve(http_addr, health_state).await {
+            warn!("⚠️  Health endpoint on {} failed: {}", http_addr, e);
+        }
+    });
+
     let server = pg_protocol::PgProtocolServer::with_keep_alive(
         graphql_url, 
         tls_config, 
         args.session_extension_interval,
         args.keep_alive_interval
     )
-    .with_quiet_connections(args.quiet_connections);
-    server.start(args.bind_addr).await?;
+    .with_quiet_connections(args.quiet_connections)
+    .with_metrics(metrics)
+    .with_graphql_urls(graphql_urls, args.graphql_primary_only)
+    .with_graphql_batch_size(args.graphql_batch_size as usize)
+    .with_query_timeout(args.query_timeout)
+    .with_listen_poll_interval(Duration::from_secs(args.listen_poll_interval))
+    .with_rate_limit(args.rate_limit_queries_per_second, args.rate_limit_burst)
+    .with_unix_socket_path(args.unix_socket_path)
+    .with_connection_limits(args.max_connections, args.max_connections_per_user)
+    .with_graphql_pool(args.graphql_pool_size, Duration::from_secs(args.graphql_pool_idle_timeout))
+    .with_graphql_retry(graphql::client::RetryPolicy {
+        max_retries: args.graphql_max_retries,
+        initial_backoff_ms: args.graphql_retry_initial_backoff,
+        ..graphql::client::RetryPolicy::default()
+    })
+    .with_circuit_breaker(args.cb_failure_threshold, Duration::from_secs(args.cb_reset_timeout))
+    .with_browse_cache(Duration::from_secs(args.browse_cache_ttl), args.browse_cache_max_entries)
+    .with_statement_cache(args.statement_cache_size)
+    .with_scram_credentials(scram_credentials)
+    .with_jwt_validator(jwt_validator)
+    .with_auth_mode(auth_mode, default_credentials)
+    .with_sql_log(sql_log)
+    .with_session_recovery_file(args.session_recovery_file.as_ref().map(|path| Arc::new(session_recovery::SessionRecoveryFile::new(path))))
+    .with_session_max_lifetime(Duration::from_secs(args.session_max_lifetime))
+    .with_parameter_status(&args.pg_param_status)
+    .with_ip_filter(ip_filter)
+    .with_drain(draining, Duration::from_secs(args.drain_timeout));
 
-    Ok(())
+    let shutdown = CancellationToken::new();
+    spawn_shutdown_signal_handler(shutdown.clone())?;
+    let server = server.with_shutdown(shutdown, Duration::from_secs(args.shutdown_timeout));
+    let result = server.start(args.bind_addr).await;
+
+    if let Some(provider) = otel_provider {
+        if let Err(e) = provider.shutdown() {
+            warn!("⚠️  Failed to flush OpenTelemetry spans: {}", e);
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use std::sync::Mutex;
+
+    #[derive(Clone, Default)]
+    struct CapturedLogs(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for CapturedLogs {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_sigterm_cancels_shutdown_token() {
+        let shutdown = CancellationToken::new();
+        spawn_shutdown_signal_handler(shutdown.clone()).unwrap();
+
+        let status = std::process::Command::new("kill")
+            .args(["-TERM", &std::process::id().to_string()])
+            .status()
+            .unwrap();
+        assert!(status.success());
+
+        tokio::time::timeout(Duration::from_secs(5), shutdown.cancelled())
+            .await
+            .expect("SIGTERM cancels the shutdown token");
+    }
+
+    #[tokio::test]
+    async fn test_json_logs_carry_query_span_fields() {
+        let logs = CapturedLogs::default();
+        let writer = logs.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_max_level(tracing::Level::DEBUG)
+            .fmt_fields(JsonFields::new())
+            .event_format(JsonFormatter)
+            .with_writer(move || writer.clone())
+            .finish();
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let url = "http://127.0.0.1:1/graphql".to_string();
+        let session = auth::AuthenticatedSession {
+            session_id: "test".to_string(),
+            username: "test".to_string(),
+            token: "token".to_string(),
+            expires: String::new(),
+            client: Arc::new(graphql::client::GraphQLClient::new(url.clone())),
+            graphql_url: url.clone(),
+            datafusion_context: Default::default(),
+            external_token: false,
+            created_at: std::time::Instant::now(),
+        };
+        let session_manager = Arc::new(auth::SessionManager::new(url));
+        let sql = "SELECT 42 AS answer";
+        query_handler::QueryHandler::execute_query_with_connection(sql, &session, session_manager, Some(7))
+            .await
+            .unwrap();
+
+        let output = String::from_utf8(logs.0.lock().unwrap().clone()).unwrap();
+        let lines: Vec<serde_json::Value> = output
+            .lines()
+            .map(|line| serde_json::from_str(line).expect("every log line is valid JSON"))
+            .collect();
+        assert!(!lines.is_empty());
+        let expected_hash = query_handler::QueryHandler::query_hash(sql);
+        for line in &lines {
+            assert_eq!(line["connection_id"], 7, "{}", line);
+            assert_eq!(line["query_hash"], expected_hash.as_str(), "{}", line);
+            assert!(line["timestamp"].is_string() && line["level"].is_string() && line["module"].is_string());
+        }
+    }
 }
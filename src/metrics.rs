@@ -0,0 +1,291 @@
+use anyhow::Result;
+use prometheus::{
+    Encoder, Histogram, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGauge, Opts,
+    Registry, TextEncoder,
+};
+use std::future::Future;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tracing::{debug, info, warn};
+
+// Upper bound for an HTTP request head; metrics and probe requests are tiny
+const MAX_REQUEST_SIZE: usize = 8192;
+
+/// Prometheus metrics shared by the PostgreSQL server, session manager and query handler
+pub struct Metrics {
+    registry: Registry,
+    pub queries_total: IntCounterVec,
+    pub connections_active: IntGauge,
+    pub graphql_errors_total: IntCounter,
+    pub auth_failures_total: IntCounter,
+    pub tag_name_case_folds_total: IntCounter,
+    pub query_duration_seconds: HistogramVec,
+    pub graphql_request_duration_seconds: Histogram,
+    pub graphql_backend_requests_total: IntCounterVec,
+    pub statement_cache_hits_total: IntCounter,
+    pub statement_cache_misses_total: IntCounter,
+}
+
+impl std::fmt::Debug for Metrics {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Metrics").finish_non_exhaustive()
+    }
+}
+
+impl Metrics {
+    pub fn new() -> Result<Self> {
+        let registry = Registry::new();
+
+        let queries_total = IntCounterVec::new(
+            Opts::new("pgwire_queries_total", "Number of executed SQL queries"),
+            &["table", "status"],
+        )?;
+        let connections_active = IntGauge::new(
+            "pgwire_connections_active",
+            "Number of currently registered client connections",
+        )?;
+        let graphql_errors_total = IntCounter::new(
+            "pgwire_graphql_errors_total",
+            "Number of failed GraphQL requests",
+        )?;
+        let auth_failures_total = IntCounter::new(
+            "pgwire_auth_failures_total",
+            "Number of failed client authentications",
+        )?;
+        let tag_name_case_folds_total = IntCounter::new(
+            "pgwire_tag_name_case_folds_total",
+            "Number of tag names looked up with a different case than written in the query",
+        )?;
+        let query_duration_seconds = HistogramVec::new(
+            HistogramOpts::new(
+                "pgwire_query_duration_seconds",
+                "Query duration in seconds by execution phase",
+            ),
+            &["phase"],
+        )?;
+        let graphql_request_duration_seconds = Histogram::with_opts(HistogramOpts::new(
+            "pgwire_graphql_request_duration_seconds",
+            "Duration of GraphQL data requests in seconds",
+        ))?;
+
+        let graphql_backend_requests_total = IntCounterVec::new(
+            Opts::new(
+                "pgwire_graphql_backend_requests_total",
+                "Number of GraphQL requests by backend URL and result",
+            ),
+            &["url", "result"],
+        )?;
+
+        let statement_cache_hits_total = IntCounter::new(
+            "pgwire_statement_cache_hits_total",
+            "Number of Parse messages answered from the shared statement cache",
+        )?;
+        let statement_cache_misses_total = IntCounter::new(
+            "pgwire_statement_cache_misses_total",
+            "Number of Parse messages of unnamed statements that had to be parsed",
+        )?;
+
+        registry.register(Box::new(queries_total.clone()))?;
+        registry.register(Box::new(connections_active.clone()))?;
+        registry.register(Box::new(graphql_errors_total.clone()))?;
+        registry.register(Box::new(auth_failures_total.clone()))?;
+        registry.register(Box::new(tag_name_case_folds_total.clone()))?;
+        registry.register(Box::new(query_duration_seconds.clone()))?;
+        registry.register(Box::new(graphql_request_duration_seconds.clone()))?;
+        registry.register(Box::new(graphql_backend_requests_total.clone()))?;
+        registry.register(Box::new(statement_cache_hits_total.clone()))?;
+        registry.register(Box::new(statement_cache_misses_total.clone()))?;
+
+        // Create the phase series up front so they are exported before the first query
+        for phase in ["graphql", "datafusion", "overall"] {
+            query_duration_seconds.with_label_values(&[phase]);
+        }
+
+        Ok(Self {
+            registry,
+            queries_total,
+            connections_active,
+            graphql_errors_total,
+            auth_failures_total,
+            tag_name_case_folds_total,
+            query_duration_seconds,
+            graphql_request_duration_seconds,
+            graphql_backend_requests_total,
+            statement_cache_hits_total,
+            statement_cache_misses_total,
+        })
+    }
+
+    pub fn record_query(&self, table: &str, success: bool) {
+        let status = if success { "success" } else { "error" };
+        self.queries_total.with_label_values(&[table, status]).inc();
+    }
+
+    pub fn observe_query_phase(&self, phase: &str, duration_ms: u64) {
+        self.query_duration_seconds
+            .with_label_values(&[phase])
+            .observe(duration_ms as f64 / 1000.0);
+    }
+
+    /// Time a GraphQL-backed fetch and count it as a GraphQL error if it fails
+    pub async fn observe_graphql<T, F>(&self, request: F) -> Result<T>
+    where
+        F: Future<Output = Result<T>>,
+    {
+        let start = Instant::now();
+        let result = request.await;
+        self.graphql_request_duration_seconds
+            .observe(start.elapsed().as_secs_f64());
+        if result.is_err() {
+            self.graphql_errors_total.inc();
+            let _ = QUERY_EVENTS.try_with(|events| events.graphql_errors.fetch_add(1, Ordering::Relaxed));
+        }
+        result
+    }
+
+    /// Render all metrics in the Prometheus text exposition format
+    pub fn encode(&self) -> Result<String> {
+        let mut buffer = Vec::new();
+        TextEncoder::new().encode(&self.registry.gather(), &mut buffer)?;
+        Ok(String::from_utf8(buffer)?)
+    }
+}
+
+/// GraphQL errors and browse cache hits of one query, for the per-connection statistics
+#[derive(Debug, Default)]
+pub struct QueryEvents {
+    pub graphql_errors: AtomicU64,
+    pub cache_hits: AtomicU64,
+}
+
+tokio::task_local! {
+    // Events of the query running on this task, see count_query_events
+    static QUERY_EVENTS: Arc<QueryEvents>;
+}
+
+/// Run `future` and count the GraphQL errors and browse cache hits it caused
+pub async fn count_query_events<F: Future>(future: F) -> (F::Output, Arc<QueryEvents>) {
+    let events = Arc::new(QueryEvents::default());
+    let output = QUERY_EVENTS.scope(events.clone(), future).await;
+    (output, events)
+}
+
+/// Count a browse cache hit for the query running on this task
+pub fn record_cache_hit() {
+    let _ = QUERY_EVENTS.try_with(|events| events.cache_hits.fetch_add(1, Ordering::Relaxed));
+}
+
+pub async fn serve(addr: SocketAddr, metrics: Arc<Metrics>) -> Result<()> {
+    let listener = TcpListener::bind(addr).await?;
+    info!("📈 Prometheus metrics available at http://{}/metrics", addr);
+    serve_on(listener, metrics).await
+}
+
+pub async fn serve_on(listener: TcpListener, metrics: Arc<Metrics>) -> Result<()> {
+    loop {
+        let (socket, peer_addr) = listener.accept().await?;
+        let metrics = metrics.clone();
+        tokio::spawn(async move {
+            if let Err(e) = handle_request(socket, &metrics).await {
+                warn!("⚠️  Metrics request from {} failed: {}", peer_addr, e);
+            }
+        });
+    }
+}
+
+async fn handle_request(mut socket: TcpStream, metrics: &Metrics) -> Result<()> {
+    let request_line = read_request_line(&mut socket).await?;
+    debug!("📈 Metrics request: {}", request_line);
+
+    let mut parts = request_line.split_whitespace();
+    let (status, content_type, body) = match (parts.next(), parts.next()) {
+        (Some("GET"), Some("/metrics")) => (
+            "200 OK",
+            PROMETHEUS_CONTENT_TYPE,
+            metrics.encode()?,
+        ),
+        _ => ("404 Not Found", "text/plain; charset=utf-8", "Not Found\n".to_string()),
+    };
+
+    write_response(&mut socket, status, content_type, &body).await
+}
+
+pub const PROMETHEUS_CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// Read an HTTP request head and return its request line (e.g. "GET /metrics HTTP/1.1")
+pub async fn read_request_line(socket: &mut TcpStream) -> Result<String> {
+    let mut request = Vec::new();
+    let mut buffer = [0u8; 1024];
+    while !request.windows(4).any(|w| w == b"\r\n\r\n") && request.len() < MAX_REQUEST_SIZE {
+        let n = socket.read(&mut buffer).await?;
+        if n == 0 {
+            break;
+        }
+        request.extend_from_slice(&buffer[..n]);
+    }
+
+    Ok(String::from_utf8_lossy(&request)
+        .lines()
+        .next()
+        .unwrap_or_default()
+        .to_string())
+}
+
+/// Write a complete HTTP/1.1 response and close the connection
+pub async fn write_response(socket: &mut TcpStream, status: &str, content_type: &str, body: &str) -> Result<()> {
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    );
+    socket.write_all(response.as_bytes()).await?;
+    socket.shutdown().await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_metrics_endpoint_serves_prometheus_text() {
+        let metrics = Arc::new(Metrics::new().unwrap());
+        metrics.record_query("tagvalues", true);
+        metrics.connections_active.inc();
+        metrics.graphql_errors_total.inc();
+        metrics.auth_failures_total.inc();
+        metrics.graphql_request_duration_seconds.observe(0.05);
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(serve_on(listener, metrics));
+
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(b"GET /metrics HTTP/1.1\r\nHost: localhost\r\n\r\n")
+            .await
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        for name in [
+            "pgwire_queries_total",
+            "pgwire_connections_active",
+            "pgwire_graphql_errors_total",
+            "pgwire_auth_failures_total",
+            "pgwire_query_duration_seconds",
+            "pgwire_graphql_request_duration_seconds",
+        ] {
+            assert!(response.contains(name), "missing metric {}", name);
+        }
+        assert!(response.contains("pgwire_queries_total{status=\"success\",table=\"tagvalues\"} 1"));
+        assert!(response.contains("phase=\"datafusion\""));
+    }
+}
@@ -12,6 +12,9 @@ use super::{ScramSha256Context, ScramStage};
 pub(super) enum AuthContext {
     Md5([u8; 4]), // MD5 with salt
     Scram,        // SCRAM-SHA-256 (placeholder for now)
+    Jwt,          // Cleartext password that carries a JWT
+    Password,     // Cleartext password for the GraphQL login
+    Trust,        // No password exchange, the --default-user logs in to GraphQL
 }
 
 pub(super) fn create_postgres_md5_request() -> (Vec<u8>, [u8; 4]) {
@@ -49,6 +52,14 @@ fn create_postgres_md5_request_with_salt(salt: [u8; 4]) -> Vec<u8> {
     response
 }
 
+pub(super) fn create_postgres_cleartext_password_request() -> Vec<u8> {
+    // Message type 'R' (Authentication) + length (4 bytes) + auth type (4 bytes, 3 = cleartext password)
+    let mut response = vec![b'R'];
+    response.extend_from_slice(&8u32.to_be_bytes());
+    response.extend_from_slice(&3u32.to_be_bytes());
+    response
+}
+
 pub(super) fn compute_postgres_md5_hash(username: &str, password: &str, salt: &[u8; 4]) -> String {
     // PostgreSQL MD5 authentication: MD5(MD5(password + username) + salt)
 
@@ -80,20 +91,30 @@ pub(super) fn verify_postgres_md5_auth(
     client_response == expected_hash
 }
 
-pub(super) fn create_postgres_scram_sha256_request() -> Vec<u8> {
+pub(super) const SCRAM_SHA_256: &str = "SCRAM-SHA-256";
+pub(super) const SCRAM_SHA_256_PLUS: &str = "SCRAM-SHA-256-PLUS";
+
+pub(super) fn create_postgres_scram_sha256_request(channel_binding: bool) -> Vec<u8> {
     let mut response = Vec::new();
 
     // AuthenticationSASL message
     // Message type 'R' + length + auth type (10 = SASL) + mechanism list
     response.push(b'R');
 
-    // SASL mechanism: "SCRAM-SHA-256" + null terminator + empty string + null terminator
-    let mechanism = b"SCRAM-SHA-256\0\0";
+    // SASL mechanisms, each null-terminated, followed by an empty string terminator.
+    // SCRAM-SHA-256-PLUS is only offered on TLS connections and listed first as preferred.
+    let mut mechanism = Vec::new();
+    if channel_binding {
+        mechanism.extend_from_slice(SCRAM_SHA_256_PLUS.as_bytes());
+        mechanism.push(0);
+    }
+    mechanism.extend_from_slice(SCRAM_SHA_256.as_bytes());
+    mechanism.extend_from_slice(b"\0\0");
     let total_length = 4 + 4 + mechanism.len(); // length field + auth type + mechanism
 
     response.extend_from_slice(&(total_length as u32).to_be_bytes());
     response.extend_from_slice(&10u32.to_be_bytes()); // Auth type 10 = SASL
-    response.extend_from_slice(mechanism);
+    response.extend_from_slice(&mechanism);
 
     response
 }
@@ -141,15 +162,12 @@ pub(super) fn parse_sasl_initial_response(buffer: &[u8]) -> Result<(String, Stri
     Ok((mechanism, initial_response))
 }
 
-pub(super) fn parse_scram_client_first(client_first: &str) -> Result<(String, String), String> {
-    // Format: "n,,n=username,r=client_nonce"
-    // or: "n=username,r=client_nonce" (without GS2 header)
-
-    let client_first_bare = if client_first.starts_with("n,,") {
-        &client_first[3..] // Remove GS2 header "n,,"
-    } else {
-        client_first
-    };
+pub(super) fn parse_scram_client_first(client_first: &str) -> Result<(String, String, String, String), String> {
+    // Format: "<gs2-header>n=username,r=client_nonce" with a GS2 header of "n,,", "y,,"
+    // or "p=<cb-name>,," (channel binding), or "n=username,r=client_nonce" without header.
+    // libpq sends an empty "n=", the user of the startup message is authoritative like in PostgreSQL.
+    // Returns the username, the client nonce, the GS2 header and the client-first-bare message
+    let (gs2_header, client_first_bare) = split_gs2_header(client_first)?;
 
     let mut username = String::new();
     let mut client_nonce = String::new();
@@ -164,11 +182,50 @@ pub(super) fn parse_scram_client_first(client_first: &str) -> Result<(String, St
         }
     }
 
-    if username.is_empty() || client_nonce.is_empty() {
-        return Err("Missing username or client nonce in SCRAM client-first".to_string());
+    if client_nonce.is_empty() {
+        return Err("Missing client nonce in SCRAM client-first".to_string());
     }
 
-    Ok((username, client_nonce))
+    Ok((username, client_nonce, gs2_header.to_string(), client_first_bare.to_string()))
+}
+
+// Split client-first into GS2 header (flag, optional authzid, trailing comma) and bare message
+fn split_gs2_header(client_first: &str) -> Result<(&str, &str), String> {
+    if !(client_first.starts_with("n,") || client_first.starts_with("y,") || client_first.starts_with("p=")) {
+        // Clients that omit the header are treated as not supporting channel binding
+        return Ok(("n,,", client_first));
+    }
+    let flag_end = client_first.find(',').ok_or("Invalid GS2 header in SCRAM client-first")?;
+    let authzid_end = client_first[flag_end + 1..]
+        .find(',')
+        .map(|i| flag_end + 1 + i)
+        .ok_or("Invalid GS2 header in SCRAM client-first")?;
+    Ok(client_first.split_at(authzid_end + 1))
+}
+
+/// Check the GS2 channel binding flag against the selected mechanism and return the
+/// channel binding data the client must echo in client-final
+pub(super) fn scram_channel_binding(
+    mechanism: &str,
+    gs2_header: &str,
+    tls_server_end_point: Option<&[u8]>,
+) -> Result<Vec<u8>, String> {
+    let cb_flag = gs2_header.split(',').next().unwrap_or_default();
+    match (mechanism, cb_flag) {
+        (SCRAM_SHA_256_PLUS, "p=tls-server-end-point") => tls_server_end_point
+            .map(|data| data.to_vec())
+            .ok_or_else(|| "Channel binding requested on a connection without TLS".to_string()),
+        (SCRAM_SHA_256_PLUS, flag) => Err(format!("Unsupported SCRAM channel binding type '{}'", flag)),
+        (_, flag) if flag.starts_with("p=") => {
+            Err("Channel binding requested but SCRAM-SHA-256-PLUS was not selected".to_string())
+        }
+        // "y" means the client supports channel binding but believes the server does not;
+        // the server did offer it, so the mechanism list was tampered with
+        (_, "y") if tls_server_end_point.is_some() => {
+            Err("SCRAM channel binding downgrade detected".to_string())
+        }
+        _ => Ok(Vec::new()),
+    }
 }
 
 pub(super) fn scram_sha256_server_first_message(
@@ -198,6 +255,8 @@ pub(super) fn scram_sha256_server_first_message(
         stored_key: Vec::new(), // Will be computed later
         server_key: Vec::new(), // Will be computed later
         stage: ScramStage::Initial,
+        gs2_header: "n,,".to_string(),
+        channel_binding: Vec::new(),
     };
 
     (server_first, context)
@@ -277,6 +336,13 @@ pub(super) fn scram_sha256_verify_client_proof(
 ) -> Result<String, String> {
     type HmacSha256 = Hmac<Sha256>;
 
+    // The c= attribute must be the GS2 header followed by the channel binding data
+    let expected_binding = STANDARD.encode([context.gs2_header.as_bytes(), &context.channel_binding].concat());
+    let client_binding = client_final_without_proof.split(',').find_map(|part| part.strip_prefix("c="));
+    if client_binding != Some(expected_binding.as_str()) {
+        return Err("SCRAM channel binding mismatch".to_string());
+    }
+
     // Derive keys from password
     let (stored_key, server_key) =
         scram_sha256_derive_keys(password, &context.salt, context.iteration_count);
@@ -342,7 +408,7 @@ fn scram_sha256_derive_keys(password: &str, salt: &[u8], iterations: u32) -> (Ve
     let server_key = server_key_hmac.finalize().into_bytes();
 
     // Stored Key = SHA256(Client Key)
-    let stored_key = Sha256::digest(&client_key);
+    let stored_key = Sha256::digest(client_key);
 
     (stored_key.to_vec(), server_key.to_vec())
 }
@@ -373,7 +439,7 @@ pub(super) fn parse_postgres_password(data: &[u8]) -> Option<String> {
     if data[0] == b'p' {
         // Password message: 'p' + length (4 bytes) + password string + null terminator
         let length = u32::from_be_bytes([data[1], data[2], data[3], data[4]]) as usize;
-        if data.len() >= length + 1 && length > 4 {
+        if data.len() > length && length > 4 {
             let password_bytes = &data[5..5 + length - 4]; // Exclude length and null terminator
             if let Ok(password) = std::str::from_utf8(password_bytes) {
                 return Some(password.trim_end_matches('\0').to_string());
@@ -383,3 +449,93 @@ pub(super) fn parse_postgres_password(data: &[u8]) -> Option<String> {
 
     None
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tls::tests::{parse_cert, SHA256_SIGNED_CERT};
+
+    // Client side of SCRAM: client-final with proof for the given channel binding attribute
+    fn client_final(context: &ScramSha256Context, cbind: &str, password: &str) -> String {
+        type HmacSha256 = Hmac<Sha256>;
+        let (stored_key, _) = scram_sha256_derive_keys(password, &context.salt, context.iteration_count);
+        let nonce = format!("{}{}", context.client_nonce, context.server_nonce);
+        let without_proof = format!("c={},r={}", cbind, nonce);
+        let auth_message = format!("{},{},{}", context.client_first_bare, context.server_first, without_proof);
+
+        let mut salted_password = [0u8; 32];
+        pbkdf2::pbkdf2::<HmacSha256>(password.as_bytes(), &context.salt, context.iteration_count, &mut salted_password).unwrap();
+        let mut client_key_hmac = HmacSha256::new_from_slice(&salted_password).unwrap();
+        client_key_hmac.update(b"Client Key");
+        let client_key = client_key_hmac.finalize().into_bytes();
+        let mut signature_hmac = HmacSha256::new_from_slice(&stored_key).unwrap();
+        signature_hmac.update(auth_message.as_bytes());
+        let signature = signature_hmac.finalize().into_bytes();
+        let proof: Vec<u8> = client_key.iter().zip(signature.iter()).map(|(k, s)| k ^ s).collect();
+
+        format!("{},p={}", without_proof, STANDARD.encode(proof))
+    }
+
+    fn verify(context: &ScramSha256Context, client_final_message: &str) -> Result<String, String> {
+        let (without_proof, proof) = parse_scram_client_final(client_final_message)?;
+        scram_sha256_verify_client_proof(context, &without_proof, &proof, "password1")
+    }
+
+    #[test]
+    fn test_scram_plus_mechanism_offered_only_with_tls() {
+        let with_tls = create_postgres_scram_sha256_request(true);
+        assert_eq!(&with_tls[9..], b"SCRAM-SHA-256-PLUS\0SCRAM-SHA-256\0\0");
+        let without_tls = create_postgres_scram_sha256_request(false);
+        assert_eq!(&without_tls[9..], b"SCRAM-SHA-256\0\0");
+    }
+
+    #[test]
+    fn test_scram_plus_channel_binding_round_trip() {
+        let end_point = crate::tls::tls_server_end_point(&parse_cert(SHA256_SIGNED_CERT));
+        let client_first = "p=tls-server-end-point,,n=grafana,r=clientnonce";
+        let (username, client_nonce, gs2_header, client_first_bare) = parse_scram_client_first(client_first).unwrap();
+        assert_eq!((username.as_str(), gs2_header.as_str()), ("grafana", "p=tls-server-end-point,,"));
+        assert_eq!(client_first_bare, "n=grafana,r=clientnonce");
+
+        let channel_binding = scram_channel_binding(SCRAM_SHA_256_PLUS, &gs2_header, Some(&end_point)).unwrap();
+        let (_, mut context) = scram_sha256_server_first_message(&client_nonce, &username);
+        context.client_first_bare = client_first_bare;
+        context.gs2_header = gs2_header.clone();
+        context.channel_binding = channel_binding;
+
+        // The client hashes the certificate it received during the TLS handshake
+        let cbind = STANDARD.encode([gs2_header.as_bytes(), &end_point].concat());
+        let message = client_final(&context, &cbind, "password1");
+        assert!(verify(&context, &message).unwrap().starts_with("v="));
+
+        // A different certificate (e.g. a MITM proxy) produces different binding data
+        let mut other_cert = end_point.clone();
+        other_cert[0] ^= 0xff;
+        let forged = STANDARD.encode([gs2_header.as_bytes(), &other_cert].concat());
+        let message = client_final(&context, &forged, "password1");
+        assert_eq!(verify(&context, &message).unwrap_err(), "SCRAM channel binding mismatch");
+    }
+
+    #[test]
+    fn test_scram_client_first_with_empty_username() {
+        // libpq leaves the username empty, the one of the startup message is used instead
+        let (username, client_nonce, gs2_header, client_first_bare) = parse_scram_client_first("n,,n=,r=rOprNGfwEbeRWgbNEkqO").unwrap();
+        assert_eq!(username, "");
+        assert_eq!(client_nonce, "rOprNGfwEbeRWgbNEkqO");
+        assert_eq!(gs2_header, "n,,");
+        assert_eq!(client_first_bare, "n=,r=rOprNGfwEbeRWgbNEkqO");
+        assert!(parse_scram_client_first("n,,n=grafana").is_err());
+    }
+
+    #[test]
+    fn test_scram_channel_binding_flags() {
+        let end_point = [1u8; 32];
+        assert!(scram_channel_binding(SCRAM_SHA_256, "n,,", Some(&end_point)).unwrap().is_empty());
+        assert!(scram_channel_binding(SCRAM_SHA_256, "y,,", None).unwrap().is_empty());
+        // "y" while the server offered SCRAM-SHA-256-PLUS indicates a stripped mechanism list
+        assert!(scram_channel_binding(SCRAM_SHA_256, "y,,", Some(&end_point)).is_err());
+        assert!(scram_channel_binding(SCRAM_SHA_256, "p=tls-server-end-point,,", Some(&end_point)).is_err());
+        assert!(scram_channel_binding(SCRAM_SHA_256_PLUS, "p=tls-unique,,", Some(&end_point)).is_err());
+        assert!(scram_channel_binding(SCRAM_SHA_256_PLUS, "p=tls-server-end-point,,", None).is_err());
+    }
+}
@@ -1,26 +1,118 @@
-use crate::auth::SessionManager;
-use anyhow::Result;
-use std::net::SocketAddr;
+use crate::auth::{ClientAddr, SessionManager};
+use anyhow::{anyhow, Result};
 use std::sync::Arc;
 use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
-use tokio::net::TcpStream;
 use tokio_rustls::TlsAcceptor;
-use tracing::{debug, error, info, trace, warn};
+use tracing::{debug, error, info, trace, warn, Instrument};
 
 use super::startup::handle_postgres_startup;
+use super::transport::Transport;
+
+// Upper bound for a single frontend message; anything larger is treated as a protocol violation
+const MAX_MESSAGE_LENGTH: usize = 64 * 1024 * 1024;
+
+// Protocol code sent in place of the version number by a CancelRequest
+pub(super) const CANCEL_REQUEST_CODE: u32 = 80877102;
+
+/// Accumulates bytes across multiple socket reads and yields complete
+/// PostgreSQL frontend messages (type byte + 4-byte length + payload) in order.
+pub(super) struct MessageReader {
+    buffer: Vec<u8>,
+    max_message_length: usize,
+}
+
+impl MessageReader {
+    pub(super) fn new() -> Self {
+        Self::with_max_message_length(MAX_MESSAGE_LENGTH)
+    }
+
+    pub(super) fn with_max_message_length(max_message_length: usize) -> Self {
+        Self {
+            buffer: Vec::new(),
+            max_message_length,
+        }
+    }
+
+    /// Append bytes received from the socket
+    pub(super) fn push(&mut self, data: &[u8]) {
+        self.buffer.extend_from_slice(data);
+    }
+
+    /// Number of bytes buffered that don't yet form a complete message
+    pub(super) fn buffered_len(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// Return the next complete message, or `None` if more bytes are needed.
+    /// Fails if the header announces an invalid or oversized length.
+    pub(super) fn next_message(&mut self) -> Result<Option<Vec<u8>>> {
+        if self.buffer.len() < 5 {
+            return Ok(None);
+        }
+
+        let message_len = u32::from_be_bytes([
+            self.buffer[1],
+            self.buffer[2],
+            self.buffer[3],
+            self.buffer[4],
+        ]) as usize;
+
+        if message_len < 4 {
+            return Err(anyhow!(
+                "Invalid message length {} for message type '{}'",
+                message_len,
+                self.buffer[0] as char
+            ));
+        }
+        if message_len > self.max_message_length {
+            return Err(anyhow!(
+                "Message length {} exceeds maximum of {} bytes",
+                message_len,
+                self.max_message_length
+            ));
+        }
+
+        let total_message_len = 1 + message_len;
+        if self.buffer.len() < total_message_len {
+            trace!(
+                "🧩 Waiting for more data: have {} of {} bytes",
+                self.buffer.len(),
+                total_message_len
+            );
+            return Ok(None);
+        }
+
+        let message: Vec<u8> = self.buffer.drain(..total_message_len).collect();
+        Ok(Some(message))
+    }
+}
 
 pub(super) async fn handle_connection(
-    mut socket: TcpStream,
+    socket: Transport,
+    session_manager: Arc<SessionManager>,
+    client_addr: ClientAddr,
+    tls_acceptor: Option<TlsAcceptor>,
+    tls_server_end_point: Option<Vec<u8>>,
+    quiet_connections: bool,
+    keep_alive_interval: u64,
+) -> Result<()> {
+    // Root span of all work done for this client; queries and GraphQL requests are children
+    let span = tracing::info_span!("pg.connection", net.peer.ip = %client_addr.host(), net.peer.port = client_addr.port());
+    serve_connection(socket, session_manager, client_addr, tls_acceptor, tls_server_end_point, quiet_connections, keep_alive_interval)
+        .instrument(span)
+        .await
+}
+
+async fn serve_connection(
+    mut socket: Transport,
     session_manager: Arc<SessionManager>,
-    client_addr: SocketAddr,
+    client_addr: ClientAddr,
     tls_acceptor: Option<TlsAcceptor>,
+    tls_server_end_point: Option<Vec<u8>>,
     quiet_connections: bool,
     keep_alive_interval: u64,
 ) -> Result<()> {
     let peer_addr = client_addr;
-    if !quiet_connections {
-        info!("🔌 New connection established from {}", peer_addr);
-    }
 
     // Read first few bytes to see what kind of connection this is
     let mut peek_buffer = [0; 32];
@@ -35,6 +127,16 @@ pub(super) async fn handle_connection(
         return Ok(());
     }
 
+    // A CancelRequest arrives on its own connection and gets no response
+    if let Some((process_id, secret_key)) = parse_cancel_request(&peek_buffer[..n]) {
+        handle_cancel_request(&session_manager, process_id, secret_key, &peer_addr.to_string()).await;
+        return Ok(());
+    }
+
+    if !quiet_connections {
+        info!("🔌 New connection established from {}", peer_addr);
+    }
+
     debug!("📊 Received {} bytes from {}", n, peer_addr);
     trace!("🔍 Raw bytes: {:02x?}", &peek_buffer[..n]);
 
@@ -76,7 +178,7 @@ pub(super) async fn handle_connection(
             };
             
             // Now handle the startup message over the encrypted connection
-            return handle_postgres_startup_tls(tls_stream, session_manager, peer_addr, quiet_connections, keep_alive_interval).await;
+            return handle_postgres_startup_tls(tls_stream, session_manager, peer_addr, tls_server_end_point, quiet_connections, keep_alive_interval).await;
             
         } else {
             if !quiet_connections {
@@ -148,6 +250,7 @@ pub(super) async fn handle_connection(
         return handle_simple_text_protocol(
             socket,
             session_manager,
+            peer_addr,
             initial_data.to_string(),
         )
         .await;
@@ -174,7 +277,7 @@ pub(super) async fn handle_connection(
                         debug!("📄 Full data as text: {:?}", full_data);
 
                         if full_data.contains(':') {
-                            return handle_simple_text_protocol(socket, session_manager, full_data.to_string()).await;
+                            return handle_simple_text_protocol(socket, session_manager, peer_addr, full_data.to_string()).await;
                         }
                     }
                 }
@@ -221,10 +324,41 @@ fn is_postgres_wire_protocol(data: &[u8]) -> bool {
 
     version == 196608
         || version == 80877103
-        || version == 80877102
+        || version == CANCEL_REQUEST_CODE
         || (length > 8 && length < 10000 && version > 0)
 }
 
+/// Parse a CancelRequest (length 16, code 80877102) into (process_id, secret_key)
+pub(super) fn parse_cancel_request(data: &[u8]) -> Option<(u32, u32)> {
+    if data.len() < 16 {
+        return None;
+    }
+
+    let length = u32::from_be_bytes([data[0], data[1], data[2], data[3]]);
+    let code = u32::from_be_bytes([data[4], data[5], data[6], data[7]]);
+    if length != 16 || code != CANCEL_REQUEST_CODE {
+        return None;
+    }
+
+    let process_id = u32::from_be_bytes([data[8], data[9], data[10], data[11]]);
+    let secret_key = u32::from_be_bytes([data[12], data[13], data[14], data[15]]);
+    Some((process_id, secret_key))
+}
+
+/// Cancel the query of the connection matching the key. The server never replies to a CancelRequest.
+pub(super) async fn handle_cancel_request(
+    session_manager: &SessionManager,
+    process_id: u32,
+    secret_key: u32,
+    peer_addr: &str,
+) {
+    if session_manager.cancel_query(process_id, secret_key).await {
+        info!("🛑 CancelRequest from {} for connection {}", peer_addr, process_id);
+    } else {
+        warn!("⚠️  Ignoring CancelRequest from {} with unknown key for connection {}", peer_addr, process_id);
+    }
+}
+
 fn is_ssl_request(data: &[u8]) -> bool {
     if data.len() < 8 {
         return false;
@@ -238,11 +372,11 @@ fn is_ssl_request(data: &[u8]) -> bool {
 }
 
 async fn handle_simple_text_protocol(
-    mut socket: TcpStream,
+    mut socket: Transport,
     session_manager: Arc<SessionManager>,
+    peer_addr: ClientAddr,
     initial_data: String,
 ) -> Result<()> {
-    let peer_addr = socket.peer_addr().unwrap_or_else(|_| "unknown".parse().unwrap());
     info!("📝 Using simple text protocol with {}", peer_addr);
 
     // Parse authentication from initial data
@@ -337,7 +471,8 @@ async fn handle_simple_text_protocol(
 async fn handle_postgres_startup_tls<T>(
     mut stream: T,
     session_manager: Arc<SessionManager>,
-    peer_addr: SocketAddr,
+    peer_addr: ClientAddr,
+    tls_server_end_point: Option<Vec<u8>>,
     quiet_connections: bool,
     keep_alive_interval: u64,
 ) -> Result<()> 
@@ -368,6 +503,102 @@ where
         Some(peer_addr),
         quiet_connections,
         keep_alive_interval,
+        tls_server_end_point,
     )
     .await;
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(message_type: u8, payload: &[u8]) -> Vec<u8> {
+        let mut message = vec![message_type];
+        message.extend_from_slice(&((payload.len() + 4) as u32).to_be_bytes());
+        message.extend_from_slice(payload);
+        message
+    }
+
+    #[test]
+    fn test_message_reader_one_byte_at_a_time() {
+        let message = frame(b'Q', b"SELECT 1\0");
+        let mut reader = MessageReader::new();
+
+        for (i, byte) in message.iter().enumerate() {
+            assert!(reader.next_message().unwrap().is_none());
+            reader.push(&[*byte]);
+            if i + 1 < message.len() {
+                assert!(reader.next_message().unwrap().is_none());
+            }
+        }
+
+        assert_eq!(reader.next_message().unwrap(), Some(message));
+        assert_eq!(reader.buffered_len(), 0);
+    }
+
+    #[test]
+    fn test_message_reader_multiple_messages_in_one_read() {
+        let parse = frame(b'P', b"\0SELECT 1\0\0\0");
+        let sync = frame(b'S', b"");
+        let partial = frame(b'Q', b"SELECT 2\0");
+
+        let mut data = Vec::new();
+        data.extend_from_slice(&parse);
+        data.extend_from_slice(&sync);
+        data.extend_from_slice(&partial[..3]);
+
+        let mut reader = MessageReader::new();
+        reader.push(&data);
+
+        assert_eq!(reader.next_message().unwrap(), Some(parse));
+        assert_eq!(reader.next_message().unwrap(), Some(sync));
+        assert!(reader.next_message().unwrap().is_none());
+
+        reader.push(&partial[3..]);
+        assert_eq!(reader.next_message().unwrap(), Some(partial));
+    }
+
+    #[test]
+    fn test_message_reader_large_and_invalid_lengths() {
+        let large = frame(b'Q', &vec![b'x'; 10_000]);
+        let mut reader = MessageReader::new();
+        reader.push(&large[..4096]);
+        assert!(reader.next_message().unwrap().is_none());
+        reader.push(&large[4096..]);
+        assert_eq!(reader.next_message().unwrap(), Some(large));
+
+        let mut reader = MessageReader::with_max_message_length(1024);
+        reader.push(&frame(b'Q', &vec![b'x'; 2048])[..5]);
+        assert!(reader.next_message().is_err());
+
+        let mut reader = MessageReader::new();
+        reader.push(&[b'Q', 0, 0, 0, 2]);
+        assert!(reader.next_message().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_cancel_request_cancels_matching_connection() {
+        let mut request = Vec::new();
+        request.extend_from_slice(&16u32.to_be_bytes());
+        request.extend_from_slice(&CANCEL_REQUEST_CODE.to_be_bytes());
+        request.extend_from_slice(&7u32.to_be_bytes());
+        request.extend_from_slice(&42u32.to_be_bytes());
+        assert_eq!(parse_cancel_request(&request), Some((7, 42)));
+        assert_eq!(parse_cancel_request(&request[..12]), None);
+
+        let session_manager = SessionManager::new("http://127.0.0.1:1/graphql".to_string());
+        let secret_key = session_manager.register_cancel_key(7).await;
+        let token = session_manager.query_cancellation_token(7).await.unwrap();
+
+        // A wrong secret key must not cancel another client's query
+        assert!(!session_manager.cancel_query(7, secret_key.wrapping_add(1)).await);
+        assert!(!token.is_cancelled());
+
+        assert!(session_manager.cancel_query(7, secret_key).await);
+        assert!(token.is_cancelled());
+
+        // The next query on the connection gets a fresh token
+        let next_token = session_manager.query_cancellation_token(7).await.unwrap();
+        assert!(!next_token.is_cancelled());
+    }
+}
@@ -7,13 +7,14 @@ use tracing::{debug, info, warn};
 
 use super::{
     response::{
-        create_bind_complete_response, create_close_complete_response,
+        create_bind_complete_response, create_close_complete_response, create_command_complete_response,
         create_empty_row_description_response,
         create_parameter_description_response, create_parse_complete_response,
         create_ready_for_query_response, create_row_description_response,
         create_row_description_response_with_types,
     },
-    ConnectionState, Portal, PreparedStatement,
+    notifications::{parse_listen_command, Listeners},
+    ColumnFormat, ConnectionState, Portal, PreparedStatement,
 };
 
 pub(super) async fn handle_postgres_message(
@@ -50,13 +51,30 @@ pub(super) async fn handle_postgres_message(
     );
 
     let result = match message_type {
-        b'Q' => handle_simple_query_message(payload, session, session_manager.clone(), connection_id).await,
-        b'P' => handle_parse_message(payload, connection_state).await,
+        b'Q' => handle_simple_query_message(payload, &mut connection_state.listeners, session, session_manager.clone(), connection_id).await,
+        b'P' => {
+            let response = handle_parse_message(payload, connection_state, &session_manager).await;
+            if response.is_ok() {
+                connection_state.pipeline_depth += 1;
+            }
+            response
+        }
         b'B' => handle_bind_message(payload, connection_state).await,
-        b'E' => handle_execute_message(payload, connection_state, session, session_manager.clone(), connection_id).await,
+        b'E' => {
+            let response = handle_execute_message(payload, connection_state, session, session_manager.clone(), connection_id).await;
+            if response.is_ok() {
+                // Execute ignores max_rows, so every successful Execute ends with CommandComplete
+                connection_state.pipeline_depth = connection_state.pipeline_depth.saturating_sub(1);
+            }
+            response
+        }
         b'D' => handle_describe_message(payload, connection_state, session, session_manager.clone(), connection_id).await,
         b'C' => handle_close_message(payload, connection_state).await,
-        b'S' => handle_sync_message().await,
+        b'H' => handle_flush_message(connection_state.pipeline_depth).await,
+        b'S' => {
+            connection_state.pipeline_depth = 0;
+            handle_sync_message().await
+        }
         b'X' => handle_terminate_message(quiet_connections).await,
         _ => {
             warn!(
@@ -87,6 +105,7 @@ pub(super) async fn handle_postgres_message(
 
 async fn handle_simple_query_message(
     payload: &[u8],
+    listeners: &mut Listeners,
     session: &crate::auth::AuthenticatedSession,
     session_manager: Arc<SessionManager>,
     connection_id: Option<u32>,
@@ -108,11 +127,66 @@ async fn handle_simple_query_message(
         return Ok(response);
     }
 
+    log_received_query(query_str, session, &session_manager, connection_id).await;
+
+    // Several statements in one message run one after another, each with its own result and
+    // a single ReadyForQuery at the end. Like PostgreSQL, the first failing statement ends it.
+    let statements = SqlHandler::split_statements(query_str);
+    if statements.len() > 1 {
+        debug!("📥 Simple query with {} statements", statements.len());
+        let mut response = Vec::new();
+        for statement in statements {
+            let result = Box::pin(execute_simple_statement(statement, listeners, session, session_manager.clone(), connection_id)).await;
+            match result {
+                Ok(statement_response) => {
+                    let ready_for_query = create_ready_for_query_response();
+                    response.extend_from_slice(statement_response.strip_suffix(ready_for_query.as_slice()).unwrap_or(&statement_response));
+                }
+                Err(e) => {
+                    warn!("❌ Statement of a multi-statement query failed: {}", e);
+                    response.extend_from_slice(&super::startup::query_error_response(&e));
+                    return Ok(response);
+                }
+            }
+        }
+        response.extend_from_slice(&create_ready_for_query_response());
+        return Ok(response);
+    }
+
+    execute_simple_statement(query_str, listeners, session, session_manager, connection_id).await
+}
+
+/// Log a query as received from the client, at INFO level with --log-sql and to the --sql-log-file
+async fn log_received_query(
+    query_str: &str,
+    session: &crate::auth::AuthenticatedSession,
+    session_manager: &SessionManager,
+    connection_id: Option<u32>,
+) {
     if crate::LOG_SQL_ROWS.load(std::sync::atomic::Ordering::Relaxed) > 0 {
         info!("📥 SQL Query: {}", query_str.trim().replace('\n', " ").replace('\r', ""));
     } else {
         debug!("📥 SQL Query: {}", query_str.trim().replace('\n', " ").replace('\r', ""));
     }
+    if let Some(sql_log) = session_manager.sql_log() {
+        sql_log.write(connection_id, &session.username, query_str).await;
+    }
+}
+
+async fn execute_simple_statement(
+    query_str: &str,
+    listeners: &mut Listeners,
+    session: &crate::auth::AuthenticatedSession,
+    session_manager: Arc<SessionManager>,
+    connection_id: Option<u32>,
+) -> Result<Vec<u8>> {
+    // LISTEN / UNLISTEN start and stop polling tasks of this connection
+    if let Some(command) = parse_listen_command(query_str) {
+        let tag = listeners.execute(command, &session.session_id, session_manager, connection_id);
+        let mut response = create_command_complete_response(tag);
+        response.extend_from_slice(&create_ready_for_query_response());
+        return Ok(response);
+    }
 
     // Start query tracking
     if let Some(conn_id) = connection_id {
@@ -142,6 +216,7 @@ async fn handle_simple_query_message(
 async fn handle_parse_message(
     payload: &[u8],
     connection_state: &mut ConnectionState,
+    session_manager: &SessionManager,
 ) -> Result<Vec<u8>> {
     let mut pos = 0;
 
@@ -234,6 +309,22 @@ async fn handle_parse_message(
         return Ok(create_parse_complete_response());
     }
 
+    // Unnamed statements already parsed by any connection are not validated again; named
+    // statements stay local to their connection
+    let shared = statement_name.is_empty();
+    if shared {
+        if let Some(cached) = session_manager.statement_cache().get(&query) {
+            debug!("📦 Parse: statement cache hit for query '{}'", query.trim());
+            session_manager.metrics().statement_cache_hits_total.inc();
+            let prepared_stmt = PreparedStatement { parameter_types, ..(*cached).clone() };
+            connection_state
+                .prepared_statements
+                .insert(statement_name, prepared_stmt);
+            return Ok(create_parse_complete_response());
+        }
+        session_manager.metrics().statement_cache_misses_total.inc();
+    }
+
     // Validate the SQL query using the same parser as the query handler
     match crate::sql_handler::SqlHandler::parse_query(&query) {
         Ok(_sql_result) => {
@@ -243,6 +334,9 @@ async fn handle_parse_message(
                 query: query.clone(),
                 parameter_types,
             };
+            if shared {
+                session_manager.statement_cache().insert(&query, Arc::new(prepared_stmt.clone()));
+            }
             connection_state
                 .prepared_statements
                 .insert(statement_name, prepared_stmt);
@@ -279,15 +373,28 @@ async fn handle_bind_message(
     // Extract statement name
     let statement_name = extract_null_terminated_string(payload, &mut pos)?;
 
-    // Parameter format codes count
+    // Parameter format codes (0 = text, 1 = binary)
     if pos + 2 > payload.len() {
         return Err(anyhow!("Incomplete bind message"));
     }
     let format_count = u16::from_be_bytes([payload[pos], payload[pos + 1]]) as usize;
     pos += 2;
 
-    // Skip format codes for now (we'll assume text format)
-    pos += format_count * 2;
+    let mut param_formats = Vec::with_capacity(format_count);
+    for _ in 0..format_count {
+        if pos + 2 > payload.len() {
+            return Err(anyhow!("Incomplete parameter format codes"));
+        }
+        param_formats.push(ColumnFormat::from_code(i16::from_be_bytes([payload[pos], payload[pos + 1]]))?);
+        pos += 2;
+    }
+
+    // Parameter type OIDs from Parse are needed to decode binary parameters
+    let parameter_types = connection_state
+        .prepared_statements
+        .get(&statement_name)
+        .map(|stmt| stmt.parameter_types.clone())
+        .unwrap_or_default();
 
     // Parameter values count
     if pos + 2 > payload.len() {
@@ -298,7 +405,7 @@ async fn handle_bind_message(
 
     // Extract parameter values
     let mut parameters = Vec::new();
-    for _ in 0..param_count {
+    for param_idx in 0..param_count {
         if pos + 4 > payload.len() {
             return Err(anyhow!("Incomplete parameter length"));
         }
@@ -318,17 +425,38 @@ async fn handle_bind_message(
             if pos + param_length > payload.len() {
                 return Err(anyhow!("Incomplete parameter value"));
             }
-            let param_value = std::str::from_utf8(&payload[pos..pos + param_length])
-                .map_err(|_| anyhow!("Invalid UTF-8 in parameter"))?
-                .to_string();
+            let raw_value = &payload[pos..pos + param_length];
+            let param_value = match ColumnFormat::for_column(&param_formats, param_idx) {
+                ColumnFormat::Text => std::str::from_utf8(raw_value)
+                    .map_err(|_| anyhow!("Invalid UTF-8 in parameter"))?
+                    .to_string(),
+                ColumnFormat::Binary => {
+                    let type_oid = parameter_types.get(param_idx).copied().unwrap_or(0);
+                    decode_binary_parameter(raw_value, type_oid)?
+                }
+            };
             parameters.push(Some(param_value));
             pos += param_length;
         }
     }
 
+    // Result column format codes
+    let mut result_formats = Vec::new();
+    if pos + 2 <= payload.len() {
+        let result_format_count = u16::from_be_bytes([payload[pos], payload[pos + 1]]) as usize;
+        pos += 2;
+        for _ in 0..result_format_count {
+            if pos + 2 > payload.len() {
+                return Err(anyhow!("Incomplete result format codes"));
+            }
+            result_formats.push(ColumnFormat::from_code(i16::from_be_bytes([payload[pos], payload[pos + 1]]))?);
+            pos += 2;
+        }
+    }
+
     debug!(
-        "🔗 Bind: portal='{}', statement='{}', params={:?}",
-        portal_name, statement_name, parameters
+        "🔗 Bind: portal='{}', statement='{}', params={:?}, result_formats={:?}",
+        portal_name, statement_name, parameters, result_formats
     );
 
     // Store the portal
@@ -336,6 +464,7 @@ async fn handle_bind_message(
         name: portal_name.clone(),
         statement_name: statement_name.clone(),
         parameters,
+        result_formats,
     };
     connection_state.portals.insert(portal_name, portal);
 
@@ -345,7 +474,7 @@ async fn handle_bind_message(
 
 async fn handle_execute_message(
     payload: &[u8],
-    connection_state: &ConnectionState,
+    connection_state: &mut ConnectionState,
     session: &crate::auth::AuthenticatedSession,
     session_manager: Arc<SessionManager>,
     connection_id: Option<u32>,
@@ -395,6 +524,12 @@ async fn handle_execute_message(
     }
 
     debug!("🔍 Executing parameterized query: {}", final_query.trim());
+    log_received_query(&final_query, session, &session_manager, connection_id).await;
+
+    if let Some(command) = parse_listen_command(&final_query) {
+        let tag = connection_state.listeners.execute(command, &session.session_id, session_manager, connection_id);
+        return Ok(create_command_complete_response(tag));
+    }
 
     // Start query tracking with timing
     let query_start = std::time::Instant::now();
@@ -403,7 +538,7 @@ async fn handle_execute_message(
     }
 
     // Execute the query - for Extended Query protocol, we need a different response format
-    let result = match super::query_execution::handle_extended_query_with_connection(&final_query, session, session_manager.clone(), connection_id).await {
+    let result = match super::query_execution::handle_extended_query_with_connection(&final_query, session, session_manager.clone(), connection_id, &portal.result_formats).await {
         Ok(response) => {
             debug!("📤 Extended query result: {} bytes", response.len());
             // Log the message types in the response
@@ -465,25 +600,37 @@ async fn handle_describe_message(
                 // First send ParameterDescription
                 response.extend_from_slice(&create_parameter_description_response(&statement.parameter_types));
                 
-                // For SELECT queries, also send RowDescription
+                // For SELECT queries, also send RowDescription; writes are not executed before Execute
                 let trimmed_query = statement.query.trim().to_uppercase();
-                if !trimmed_query.starts_with("SET")
+                if super::query_execution::is_show_statement(&trimmed_query) {
+                    let session_variables = super::query_execution::connection_session_variables(&session_manager, connection_id).await;
+                    let show_result = super::query_execution::execute_show_statement(&statement.query, &session_variables)?;
+                    response.extend_from_slice(&create_row_description_response_with_types(&show_result, &[]));
+                } else if super::query_execution::is_fetch_statement(&trimmed_query) {
+                    let fetch_result = crate::query_handler::QueryHandler::describe_fetch(&statement.query, &session_manager, connection_id).await?;
+                    response.extend_from_slice(&create_row_description_response_with_types(&fetch_result, &[]));
+                } else if !trimmed_query.starts_with("SET")
+                    && !super::query_execution::is_write_statement(&trimmed_query)
+                    && !super::query_execution::is_copy_statement(&trimmed_query)
+                    && !super::query_execution::is_cursor_statement(&trimmed_query)
                     && !super::query_execution::is_transaction_control_statement(&trimmed_query)
                     && !super::query_execution::is_utility_statement(&trimmed_query)
                 {
                     // Execute the query to get proper column types (like Execute message does)
                     tracing::debug!("🚀 Describe message: Executing query to get proper column types");
-                    match crate::query_handler::QueryHandler::execute_query_with_connection(&statement.query, session, session_manager.clone(), connection_id).await {
+                    let describe_connection_id = if super::query_execution::is_change_detection_query(&trimmed_query) { None } else { connection_id };
+                    match crate::query_handler::QueryHandler::execute_query_with_connection(&statement.query, session, session_manager.clone(), describe_connection_id).await {
                         Ok(query_result) => {
                             tracing::debug!("🚀 Describe message: Generated QueryResult with {} columns and proper OIDs", query_result.columns.len());
-                            response.extend_from_slice(&create_row_description_response_with_types(&query_result));
+                            // Result formats are not known until Bind, so a statement is always described as text
+                            response.extend_from_slice(&create_row_description_response_with_types(&query_result, &[]));
                         }
                         Err(e) => {
                             tracing::warn!("🚀 Describe message: Query execution failed, falling back to schema-based types: {}", e);
                             // Fallback to parsing only if execution fails
                             match SqlHandler::parse_query(&statement.query) {
                                 Ok(SqlResult::Query(query_info)) => {
-                                    response.extend_from_slice(&create_row_description_response(&query_info));
+                                    response.extend_from_slice(&create_row_description_response(&query_info, &[]));
                                 }
                                 _ => {
                                     // For non-SELECT statements, send NoData
@@ -510,28 +657,42 @@ async fn handle_describe_message(
                 if let Some(statement) = connection_state.prepared_statements.get(&portal.statement_name) {
                     // For SET statements and utility statements, return empty row description
                     let trimmed_query = statement.query.trim().to_uppercase();
+                    if super::query_execution::is_show_statement(&trimmed_query) {
+                        let session_variables = super::query_execution::connection_session_variables(&session_manager, connection_id).await;
+                        let show_result = super::query_execution::execute_show_statement(&statement.query, &session_variables)?;
+                        return Ok(create_row_description_response_with_types(&show_result, &portal.result_formats));
+                    }
+                    if super::query_execution::is_fetch_statement(&trimmed_query) {
+                        let fetch_result = crate::query_handler::QueryHandler::describe_fetch(&statement.query, &session_manager, connection_id).await?;
+                        return Ok(create_row_description_response_with_types(&fetch_result, &portal.result_formats));
+                    }
                     if trimmed_query.starts_with("SET")
+                        || super::query_execution::is_write_statement(&trimmed_query)
+                        || super::query_execution::is_copy_statement(&trimmed_query)
+                        || super::query_execution::is_cursor_statement(&trimmed_query)
                         || super::query_execution::is_transaction_control_statement(&trimmed_query)
                         || super::query_execution::is_utility_statement(&trimmed_query)
                     {
                         return Ok(create_empty_row_description_response());
                     }
                     
-                    // For SELECT queries, execute the query to get proper column types
+                    // For SELECT queries, execute the query with the bound parameters to get proper column types
                     tracing::debug!("🚀 Portal Describe: Executing query to get proper column types");
-                    match crate::query_handler::QueryHandler::execute_query_with_connection(&statement.query, session, session_manager.clone(), connection_id).await {
+                    let final_query = substitute_parameters(&statement.query, &portal.parameters)?;
+                    let describe_connection_id = if super::query_execution::is_change_detection_query(&trimmed_query) { None } else { connection_id };
+                    match crate::query_handler::QueryHandler::execute_query_with_connection(&final_query, session, session_manager.clone(), describe_connection_id).await {
                         Ok(query_result) => {
                             tracing::debug!("🚀 Portal Describe: Generated QueryResult with {} columns and proper OIDs", query_result.columns.len());
-                            Ok(create_row_description_response_with_types(&query_result))
+                            Ok(create_row_description_response_with_types(&query_result, &portal.result_formats))
                         }
                         Err(e) => {
                             tracing::warn!("🚀 Portal Describe: Query execution failed, falling back to schema-based types: {}", e);
                             // Fallback to parsing only if execution fails
-                            match SqlHandler::parse_query(&statement.query) {
+                            match SqlHandler::parse_query(&final_query) {
                                 Ok(SqlResult::Query(query_info)) => {
-                                    Ok(create_row_description_response(&query_info))
+                                    Ok(create_row_description_response(&query_info, &portal.result_formats))
                                 }
-                                Ok(SqlResult::SetStatement(_)) => {
+                                Ok(SqlResult::SetStatement(_)) | Ok(SqlResult::UnionQuery(_)) | Ok(SqlResult::ExplainPlan(_)) | Ok(SqlResult::WriteTagValues(_)) | Ok(SqlResult::AcknowledgeAlarms(_)) | Ok(SqlResult::CopyTo(_)) | Ok(SqlResult::Cursor(_)) => {
                                     Ok(create_empty_row_description_response())
                                 }
                                 Err(_) => {
@@ -585,6 +746,17 @@ async fn handle_close_message(
     Ok(create_close_complete_response())
 }
 
+/// The responses of all messages of a read are written together, so there is nothing to flush.
+/// Once every parsed statement has completed, the pipeline is idle and ReadyForQuery is sent.
+async fn handle_flush_message(pipeline_depth: u32) -> Result<Vec<u8>> {
+    debug!("🚿 Flush (pipeline depth {})", pipeline_depth);
+    if pipeline_depth == 0 {
+        Ok(create_ready_for_query_response())
+    } else {
+        Ok(Vec::new())
+    }
+}
+
 async fn handle_sync_message() -> Result<Vec<u8>> {
     debug!("🔄 Sync");
     Ok(create_ready_for_query_response())
@@ -599,6 +771,47 @@ async fn handle_terminate_message(quiet_connections: bool) -> Result<Vec<u8>> {
     Err(anyhow!("TERMINATE_CONNECTION"))
 }
 
+/// Decode a binary-format Bind parameter into its text representation for substitution.
+/// Unspecified types (OID 0) are inferred from the value length.
+fn decode_binary_parameter(data: &[u8], type_oid: u32) -> Result<String> {
+    let type_oid = match (type_oid, data.len()) {
+        (0, 1) => 16,
+        (0, 2) => 21,
+        (0, 4) => 23,
+        (0, 8) => 20,
+        (oid, _) => oid,
+    };
+
+    let fixed = |len: usize| -> Result<&[u8]> {
+        if data.len() != len {
+            return Err(anyhow!("Invalid binary parameter length {} for type OID {}", data.len(), type_oid));
+        }
+        Ok(data)
+    };
+
+    Ok(match type_oid {
+        16 => (fixed(1)?[0] != 0).to_string(),
+        21 => i16::from_be_bytes(fixed(2)?.try_into()?).to_string(),
+        23 => i32::from_be_bytes(fixed(4)?.try_into()?).to_string(),
+        20 => i64::from_be_bytes(fixed(8)?.try_into()?).to_string(),
+        700 => f32::from_be_bytes(fixed(4)?.try_into()?).to_string(),
+        701 => f64::from_be_bytes(fixed(8)?.try_into()?).to_string(),
+        1114 | 1184 => {
+            // Microseconds since 2000-01-01 00:00:00
+            let micros = i64::from_be_bytes(fixed(8)?.try_into()?);
+            let pg_epoch = chrono::NaiveDate::from_ymd_opt(2000, 1, 1)
+                .and_then(|d| d.and_hms_opt(0, 0, 0))
+                .ok_or_else(|| anyhow!("Invalid PostgreSQL epoch"))?;
+            (pg_epoch + chrono::Duration::microseconds(micros))
+                .format("%Y-%m-%d %H:%M:%S%.6f")
+                .to_string()
+        }
+        _ => std::str::from_utf8(data)
+            .map_err(|_| anyhow!("Unsupported binary parameter for type OID {}", type_oid))?
+            .to_string(),
+    })
+}
+
 fn substitute_parameters(query: &str, params: &[Option<String>]) -> Result<String> {
     let mut final_query = query.to_string();
     for (i, param) in params.iter().enumerate() {
@@ -612,7 +825,7 @@ fn substitute_parameters(query: &str, params: &[Option<String>]) -> Result<Strin
     Ok(final_query)
 }
 
-fn extract_null_terminated_string<'a>(payload: &'a [u8], pos: &mut usize) -> Result<String> {
+fn extract_null_terminated_string(payload: &[u8], pos: &mut usize) -> Result<String> {
     let start = *pos;
     while *pos < payload.len() && payload[*pos] != 0 {
         *pos += 1;
@@ -627,3 +840,206 @@ fn extract_null_terminated_string<'a>(payload: &'a [u8], pos: &mut usize) -> Res
     Ok(result)
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn frame(message_type: u8, payload: &[u8]) -> Vec<u8> {
+        let mut message = vec![message_type];
+        message.extend_from_slice(&((payload.len() + 4) as u32).to_be_bytes());
+        message.extend_from_slice(payload);
+        message
+    }
+
+    fn split_messages(response: &[u8]) -> Vec<(char, Vec<u8>)> {
+        let mut messages = Vec::new();
+        let mut pos = 0;
+        while pos + 5 <= response.len() {
+            let len = u32::from_be_bytes([response[pos + 1], response[pos + 2], response[pos + 3], response[pos + 4]]) as usize;
+            messages.push((response[pos] as char, response[pos + 5..pos + 1 + len].to_vec()));
+            pos += 1 + len;
+        }
+        messages
+    }
+
+    fn test_session() -> (crate::auth::AuthenticatedSession, Arc<SessionManager>) {
+        let client = Arc::new(crate::graphql::client::GraphQLClient::new("http://127.0.0.1:1/graphql".to_string()));
+        let session = crate::auth::AuthenticatedSession {
+            session_id: "test".to_string(),
+            username: "test".to_string(),
+            token: "token".to_string(),
+            expires: String::new(),
+            client,
+            graphql_url: "http://127.0.0.1:1/graphql".to_string(),
+            datafusion_context: Default::default(),
+            external_token: false,
+            created_at: std::time::Instant::now(),
+        };
+        let session_manager = Arc::new(SessionManager::new("http://127.0.0.1:1/graphql".to_string()));
+        (session, session_manager)
+    }
+
+    async fn run_pipeline(messages: &[Vec<u8>]) -> Vec<(char, Vec<u8>)> {
+        let (session, session_manager) = test_session();
+        let mut connection_state = ConnectionState {
+            prepared_statements: HashMap::new(),
+            portals: HashMap::new(),
+            scram_context: None,
+            listeners: Listeners::new().0,
+            pipeline_depth: 0,
+        };
+
+        let mut response = Vec::new();
+        for message in messages {
+            let result = handle_postgres_message(message, &mut connection_state, &session, session_manager.clone(), None, true)
+                .await
+                .expect("message should be handled");
+            response.extend(result);
+        }
+        split_messages(&response)
+    }
+
+    #[tokio::test]
+    async fn test_pipelined_extended_set_statement() {
+        // Parse + Bind + Execute + Sync as sent by a client in a single TCP write
+        let messages = run_pipeline(&[
+            frame(b'P', b"\0SET extra_float_digits = 3\0\0\0"),
+            frame(b'B', b"\0\0\0\0\0\0\0\0"),
+            frame(b'E', b"\0\0\0\0\0"),
+            frame(b'S', b""),
+        ])
+        .await;
+
+        let types: Vec<char> = messages.iter().map(|(t, _)| *t).collect();
+        assert_eq!(types, vec!['1', '2', 'C', 'Z'], "expected ParseComplete, BindComplete, CommandComplete, ReadyForQuery");
+        assert_eq!(messages[2].1, b"SET\0".to_vec());
+    }
+
+    #[tokio::test]
+    async fn test_flush_sends_ready_for_query_only_when_pipeline_is_idle() {
+        let messages = run_pipeline(&[
+            frame(b'P', b"\0SET extra_float_digits = 3\0\0\0"),
+            frame(b'B', b"\0\0\0\0\0\0\0\0"),
+            frame(b'H', b""),
+            frame(b'E', b"\0\0\0\0\0"),
+            frame(b'H', b""),
+            frame(b'P', b"\0SET application_name = 'pgbench'\0\0\0"),
+            frame(b'H', b""),
+            frame(b'S', b""),
+        ])
+        .await;
+
+        // The first Flush waits for the Execute, the last one for the second statement
+        let types: Vec<char> = messages.iter().map(|(t, _)| *t).collect();
+        assert_eq!(types, vec!['1', '2', 'C', 'Z', '1', 'Z']);
+    }
+
+    #[tokio::test]
+    async fn test_unnamed_statements_are_parsed_once_for_all_connections() {
+        let (session, session_manager) = test_session();
+        let query = "SELECT tag_name, numeric_value FROM tagvalues WHERE tag_name = 'Tag_1'";
+        let parse = |name: &str| frame(b'P', format!("{}\0{}\0\0\0", name, query).as_bytes());
+        let metrics = session_manager.metrics();
+        let counters = || (metrics.statement_cache_hits_total.get(), metrics.statement_cache_misses_total.get());
+
+        // Each panel of a dashboard parses the same statement on its own connection
+        for connection in [1, 2] {
+            let mut connection_state = ConnectionState {
+                prepared_statements: HashMap::new(),
+                portals: HashMap::new(),
+                scram_context: None,
+                listeners: Listeners::new().0,
+                pipeline_depth: 0,
+            };
+            let response = handle_postgres_message(&parse(""), &mut connection_state, &session, session_manager.clone(), Some(connection), true)
+                .await
+                .unwrap();
+            assert_eq!(split_messages(&response)[0].0, '1');
+            assert_eq!(connection_state.prepared_statements[""].query, query);
+        }
+        assert_eq!(counters(), (1, 1), "the second connection must not parse the query again");
+
+        // Named statements are neither looked up nor shared
+        let mut connection_state = ConnectionState {
+            prepared_statements: HashMap::new(),
+            portals: HashMap::new(),
+            scram_context: None,
+            listeners: Listeners::new().0,
+            pipeline_depth: 0,
+        };
+        handle_postgres_message(&parse("panel_1"), &mut connection_state, &session, session_manager.clone(), Some(3), true)
+            .await
+            .unwrap();
+        assert!(connection_state.prepared_statements.contains_key("panel_1"));
+        assert_eq!(counters(), (1, 1));
+    }
+
+    #[tokio::test]
+    async fn test_binary_format_round_trip() {
+        let query = "SELECT CAST(7 AS SMALLINT) AS i2, CAST(42 AS INT) AS i4, CAST($1 AS BIGINT) AS i8, \
+                     CAST(1.5 AS REAL) AS f4, CAST(2.25 AS DOUBLE) AS f8, true AS b, \
+                     CAST('2000-01-02 00:00:01' AS TIMESTAMP) AS ts";
+
+        let mut parse = vec![0u8];
+        parse.extend_from_slice(query.as_bytes());
+        parse.push(0);
+        parse.extend_from_slice(&1u16.to_be_bytes());
+        parse.extend_from_slice(&20u32.to_be_bytes()); // $1 is int8
+
+        let mut bind = vec![0u8, 0u8];
+        bind.extend_from_slice(&1u16.to_be_bytes());
+        bind.extend_from_slice(&1i16.to_be_bytes()); // binary parameters
+        bind.extend_from_slice(&1u16.to_be_bytes());
+        bind.extend_from_slice(&8i32.to_be_bytes());
+        bind.extend_from_slice(&9_000_000_000i64.to_be_bytes());
+        bind.extend_from_slice(&1u16.to_be_bytes());
+        bind.extend_from_slice(&1i16.to_be_bytes()); // binary results for all columns
+
+        let messages = run_pipeline(&[
+            frame(b'P', &parse),
+            frame(b'B', &bind),
+            frame(b'D', b"P\0"),
+            frame(b'E', b"\0\0\0\0\0"),
+            frame(b'S', b""),
+        ])
+        .await;
+
+        let types: Vec<char> = messages.iter().map(|(t, _)| *t).collect();
+        assert_eq!(types, vec!['1', '2', 'T', 'D', 'C', 'Z']);
+
+        // Every RowDescription field must announce binary format (last 2 bytes of each field)
+        let row_description = &messages[2].1;
+        let field_count = u16::from_be_bytes([row_description[0], row_description[1]]) as usize;
+        assert_eq!(field_count, 7);
+        let mut pos = 2;
+        for _ in 0..field_count {
+            while row_description[pos] != 0 {
+                pos += 1;
+            }
+            pos += 1 + 4 + 2 + 4 + 2 + 4;
+            assert_eq!(i16::from_be_bytes([row_description[pos], row_description[pos + 1]]), 1);
+            pos += 2;
+        }
+
+        let data_row = &messages[3].1;
+        let mut pos = 2;
+        let mut fields = Vec::new();
+        for _ in 0..u16::from_be_bytes([data_row[0], data_row[1]]) {
+            let len = i32::from_be_bytes(data_row[pos..pos + 4].try_into().unwrap()) as usize;
+            pos += 4;
+            fields.push(data_row[pos..pos + len].to_vec());
+            pos += len;
+        }
+
+        assert_eq!(fields[0], 7i16.to_be_bytes().to_vec());
+        assert_eq!(fields[1], 42i32.to_be_bytes().to_vec());
+        assert_eq!(fields[2], 9_000_000_000i64.to_be_bytes().to_vec());
+        assert_eq!(fields[3], 1.5f32.to_be_bytes().to_vec());
+        assert_eq!(fields[4], 2.25f64.to_be_bytes().to_vec());
+        assert_eq!(fields[5], vec![1u8]);
+        // One day and one second after the PostgreSQL epoch, in microseconds
+        assert_eq!(fields[6], (86_401_000_000i64).to_be_bytes().to_vec());
+    }
+}
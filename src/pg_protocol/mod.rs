@@ -1,27 +1,68 @@
 mod authentication;
 mod connection_handler;
 mod message_handler;
+mod notifications;
 mod query_execution;
 pub(crate) mod response;
+pub(crate) mod settings;
 mod startup;
+mod transport;
 
-use crate::auth::SessionManager;
+use crate::auth::{ClientAddr, SessionManager};
+use crate::credentials::CredentialStore;
+use crate::graphql::client::RetryPolicy;
+use crate::ip_filter::IpFilter;
+use crate::jwt::JwtValidator;
+use crate::AuthMode;
+use crate::metrics::Metrics;
+use crate::session_recovery::SessionRecoveryFile;
+use crate::sql_log::SqlLog;
 use crate::tls::TlsConfig;
 use anyhow::Result;
 use std::collections::HashMap;
 use std::net::SocketAddr;
+use std::sync::atomic::AtomicBool;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::net::TcpListener;
-use tracing::{debug, error, info};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, error, info, warn};
+use transport::{Transport, UnixSocketListener};
 
 // Extended Query Protocol structures
 #[derive(Debug, Clone)]
-struct PreparedStatement {
+pub(crate) struct PreparedStatement {
     #[allow(dead_code)]
-    name: String,
-    query: String,
+    pub(crate) name: String,
+    pub(crate) query: String,
     #[allow(dead_code)]
-    parameter_types: Vec<u32>, // PostgreSQL OID types
+    pub(crate) parameter_types: Vec<u32>, // PostgreSQL OID types
+}
+
+// Format code for parameters and result columns in Bind
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum ColumnFormat {
+    Text = 0,
+    Binary = 1,
+}
+
+impl ColumnFormat {
+    fn from_code(code: i16) -> Result<Self> {
+        match code {
+            0 => Ok(ColumnFormat::Text),
+            1 => Ok(ColumnFormat::Binary),
+            _ => Err(anyhow::anyhow!("Invalid format code: {}", code)),
+        }
+    }
+
+    /// Resolve the format for a column: no codes means text, a single code applies to all columns
+    pub(super) fn for_column(formats: &[ColumnFormat], index: usize) -> ColumnFormat {
+        match formats.len() {
+            0 => ColumnFormat::Text,
+            1 => formats[0],
+            _ => formats.get(index).copied().unwrap_or(ColumnFormat::Text),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -29,7 +70,8 @@ struct Portal {
     #[allow(dead_code)]
     name: String,
     statement_name: String,
-    parameters: Vec<Option<String>>, // Parameter values
+    parameters: Vec<Option<String>>, // Parameter values (binary parameters are decoded to text)
+    result_formats: Vec<ColumnFormat>, // Result column format codes from Bind
 }
 
 // Connection state for Extended Query Protocol
@@ -39,6 +81,8 @@ struct ConnectionState {
     portals: HashMap<String, Portal>,
     #[allow(dead_code)]
     scram_context: Option<ScramSha256Context>, // SCRAM authentication state
+    listeners: notifications::Listeners, // LISTEN channels, aborted when the connection closes
+    pipeline_depth: u32, // Parsed statements without CommandComplete yet, reset by Sync
 }
 
 // SCRAM authentication stages
@@ -63,60 +107,341 @@ pub(super) struct ScramSha256Context {
     stored_key: Vec<u8>,
     server_key: Vec<u8>,
     stage: ScramStage,
+    gs2_header: String,       // GS2 header of client-first, e.g. "n,," or "p=tls-server-end-point,,"
+    channel_binding: Vec<u8>, // Channel binding data expected in client-final (empty without binding)
 }
 
 pub struct PgProtocolServer {
     session_manager: Arc<SessionManager>,
     tls_config: Option<TlsConfig>,
+    graphql_urls: Vec<String>,
+    graphql_primary_only: bool,
     quiet_connections: bool,
     keep_alive_interval: u64,
+    graphql_batch_size: usize,
+    query_timeout_ms: u64,
+    listen_poll_interval: Duration,
+    unix_socket_path: Option<String>,
+    shutdown: CancellationToken,
+    shutdown_timeout: Duration,
+    close_connections: CancellationToken, // Cancelled after the drain phase, closes idle connections
+    draining: Arc<AtomicBool>,
+    drain_timeout: Duration,
+    max_connections: usize,
+    max_connections_per_user: usize,
+    graphql_pool_size: usize,
+    graphql_pool_idle_timeout: Duration,
+    graphql_retry_policy: RetryPolicy,
+    cb_failure_threshold: u32,
+    cb_reset_timeout: Duration,
+    browse_cache_ttl: Duration,
+    browse_cache_max_entries: usize,
+    statement_cache_size: usize,
+    rate_limit_queries_per_second: u32,
+    rate_limit_burst: u32,
+    scram_credentials: Option<Arc<CredentialStore>>,
+    jwt_validator: Option<Arc<JwtValidator>>,
+    auth_mode: AuthMode,
+    default_credentials: Option<(String, String)>,
+    sql_log: Option<Arc<SqlLog>>,
+    session_recovery: Option<Arc<SessionRecoveryFile>>,
+    session_max_lifetime: Duration,
+    parameter_status: Arc<Vec<(String, String)>>,
+    ip_filter: IpFilter,
 }
 
+// How long connections get to send their termination notice once queries were cancelled
+const SHUTDOWN_DRAIN_GRACE: Duration = Duration::from_secs(2);
+
 impl PgProtocolServer {
     pub fn with_keep_alive(graphql_url: String, tls_config: Option<TlsConfig>, session_extension_interval: u64, keep_alive_interval: u64) -> Self {
         Self {
-            session_manager: Arc::new(SessionManager::with_extension_interval(graphql_url, session_extension_interval)),
+            session_manager: Arc::new(SessionManager::with_extension_interval(graphql_url.clone(), session_extension_interval)),
             tls_config,
+            graphql_urls: vec![graphql_url],
+            graphql_primary_only: false,
             quiet_connections: false,
             keep_alive_interval,
+            graphql_batch_size: crate::auth::DEFAULT_GRAPHQL_BATCH_SIZE,
+            query_timeout_ms: crate::auth::DEFAULT_QUERY_TIMEOUT_MS,
+            listen_poll_interval: Duration::from_secs(crate::auth::DEFAULT_LISTEN_POLL_INTERVAL_SECS),
+            unix_socket_path: None,
+            shutdown: CancellationToken::new(),
+            shutdown_timeout: Duration::from_secs(30),
+            close_connections: CancellationToken::new(),
+            draining: Arc::new(AtomicBool::new(false)),
+            drain_timeout: Duration::ZERO,
+            max_connections: crate::auth::DEFAULT_MAX_CONNECTIONS,
+            max_connections_per_user: crate::auth::DEFAULT_MAX_CONNECTIONS_PER_USER,
+            graphql_pool_size: crate::graphql::client::DEFAULT_POOL_SIZE,
+            graphql_pool_idle_timeout: Duration::from_secs(crate::graphql::client::DEFAULT_POOL_IDLE_TIMEOUT_SECS),
+            graphql_retry_policy: RetryPolicy::default(),
+            cb_failure_threshold: crate::circuit_breaker::DEFAULT_FAILURE_THRESHOLD,
+            cb_reset_timeout: Duration::from_secs(crate::circuit_breaker::DEFAULT_RESET_TIMEOUT_SECS),
+            browse_cache_ttl: Duration::from_secs(crate::cache::DEFAULT_BROWSE_CACHE_TTL_SECS),
+            browse_cache_max_entries: crate::cache::DEFAULT_BROWSE_CACHE_MAX_ENTRIES,
+            statement_cache_size: crate::cache::DEFAULT_STATEMENT_CACHE_SIZE,
+            rate_limit_queries_per_second: crate::rate_limiter::DEFAULT_RATE_LIMIT_QUERIES_PER_SECOND,
+            rate_limit_burst: crate::rate_limiter::DEFAULT_RATE_LIMIT_BURST,
+            scram_credentials: None,
+            jwt_validator: None,
+            auth_mode: AuthMode::Md5,
+            default_credentials: None,
+            sql_log: None,
+            session_recovery: None,
+            session_max_lifetime: Duration::from_secs(crate::session_recovery::DEFAULT_SESSION_MAX_LIFETIME_SECS),
+            parameter_status: Arc::new(settings::parameter_status_list(&[])),
+            ip_filter: IpFilter::default(),
         }
     }
 
     pub fn with_quiet_connections(mut self, quiet: bool) -> Self {
         self.quiet_connections = quiet;
         // Also update the session manager
-        let session_manager = Arc::new(
-            SessionManager::with_extension_interval(
-                self.session_manager.graphql_url().to_string(), 
-                self.session_manager.extension_interval_secs()
-            ).with_quiet_connections(quiet)
-        );
-        self.session_manager = session_manager;
+        self.session_manager = Arc::new(self.rebuild_session_manager(self.session_manager.metrics()));
+        self
+    }
+
+    pub fn with_metrics(mut self, metrics: Arc<Metrics>) -> Self {
+        self.session_manager = Arc::new(self.rebuild_session_manager(metrics));
+        self
+    }
+
+    pub fn with_graphql_batch_size(mut self, batch_size: usize) -> Self {
+        self.graphql_batch_size = batch_size;
+        self.session_manager = Arc::new(self.rebuild_session_manager(self.session_manager.metrics()));
+        self
+    }
+
+    pub fn with_query_timeout(mut self, timeout_ms: u64) -> Self {
+        self.query_timeout_ms = timeout_ms;
+        self.session_manager = Arc::new(self.rebuild_session_manager(self.session_manager.metrics()));
+        self
+    }
+
+    /// How often LISTEN polls the tag of each channel for changes
+    pub fn with_listen_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.listen_poll_interval = poll_interval;
+        self.session_manager = Arc::new(self.rebuild_session_manager(self.session_manager.metrics()));
+        self
+    }
+
+    pub fn with_unix_socket_path(mut self, path: Option<String>) -> Self {
+        self.unix_socket_path = path;
+        self
+    }
+
+    pub fn with_connection_limits(mut self, max_connections: usize, max_connections_per_user: usize) -> Self {
+        self.max_connections = max_connections;
+        self.max_connections_per_user = max_connections_per_user;
+        self.session_manager = Arc::new(self.rebuild_session_manager(self.session_manager.metrics()));
+        self
+    }
+
+    /// Redundant GraphQL servers, see SessionManager::with_graphql_urls
+    pub fn with_graphql_urls(mut self, urls: Vec<String>, primary_only: bool) -> Self {
+        if !urls.is_empty() {
+            self.graphql_urls = urls;
+        }
+        self.graphql_primary_only = primary_only;
+        self.session_manager = Arc::new(self.rebuild_session_manager(self.session_manager.metrics()));
+        self
+    }
+
+    pub fn with_graphql_pool(mut self, pool_size: usize, pool_idle_timeout: Duration) -> Self {
+        self.graphql_pool_size = pool_size;
+        self.graphql_pool_idle_timeout = pool_idle_timeout;
+        self.session_manager = Arc::new(self.rebuild_session_manager(self.session_manager.metrics()));
+        self
+    }
+
+    pub fn with_graphql_retry(mut self, retry_policy: RetryPolicy) -> Self {
+        self.graphql_retry_policy = retry_policy;
+        self.session_manager = Arc::new(self.rebuild_session_manager(self.session_manager.metrics()));
+        self
+    }
+
+    pub fn with_circuit_breaker(mut self, failure_threshold: u32, reset_timeout: Duration) -> Self {
+        self.cb_failure_threshold = failure_threshold;
+        self.cb_reset_timeout = reset_timeout;
+        self.session_manager = Arc::new(self.rebuild_session_manager(self.session_manager.metrics()));
+        self
+    }
+
+    pub fn with_browse_cache(mut self, ttl: Duration, max_entries: usize) -> Self {
+        self.browse_cache_ttl = ttl;
+        self.browse_cache_max_entries = max_entries;
+        self.session_manager = Arc::new(self.rebuild_session_manager(self.session_manager.metrics()));
+        self
+    }
+
+    /// Number of unnamed statements whose Parse is shared between connections, 0 disables it
+    pub fn with_statement_cache(mut self, max_entries: usize) -> Self {
+        self.statement_cache_size = max_entries;
+        self.session_manager = Arc::new(self.rebuild_session_manager(self.session_manager.metrics()));
+        self
+    }
+
+    /// Queries per second and burst allowed for each user, 0 queries per second disables the limit
+    pub fn with_rate_limit(mut self, queries_per_second: u32, burst: u32) -> Self {
+        self.rate_limit_queries_per_second = queries_per_second;
+        self.rate_limit_burst = burst;
+        self.session_manager = Arc::new(self.rebuild_session_manager(self.session_manager.metrics()));
+        self
+    }
+
+    /// Authenticate with SCRAM-SHA-256 against the passwords of --scram-password-file
+    pub fn with_scram_credentials(mut self, credentials: Option<Arc<CredentialStore>>) -> Self {
+        self.scram_credentials = credentials;
+        self.session_manager = Arc::new(self.rebuild_session_manager(self.session_manager.metrics()));
+        self
+    }
+
+    /// Authenticate with a JWT sent as cleartext password instead of a GraphQL login (--auth-mode jwt)
+    pub fn with_jwt_validator(mut self, validator: Option<Arc<JwtValidator>>) -> Self {
+        self.jwt_validator = validator;
+        self.session_manager = Arc::new(self.rebuild_session_manager(self.session_manager.metrics()));
+        self
+    }
+
+    /// How clients authenticate (--auth-mode); with AuthMode::Trust every connection logs in to
+    /// GraphQL with `default_credentials`
+    pub fn with_auth_mode(mut self, auth_mode: AuthMode, default_credentials: Option<(String, String)>) -> Self {
+        self.auth_mode = auth_mode;
+        self.default_credentials = default_credentials;
+        self.session_manager = Arc::new(self.rebuild_session_manager(self.session_manager.metrics()));
+        self
+    }
+
+    /// Append every received query to the --sql-log-file
+    pub fn with_sql_log(mut self, sql_log: Option<Arc<SqlLog>>) -> Self {
+        self.sql_log = sql_log;
+        self.session_manager = Arc::new(self.rebuild_session_manager(self.session_manager.metrics()));
+        self
+    }
+
+    /// Keep the tokens of the open GraphQL sessions in a file and log out the ones a crash left behind
+    pub fn with_session_recovery_file(mut self, session_recovery: Option<Arc<SessionRecoveryFile>>) -> Self {
+        self.session_recovery = session_recovery;
+        self.session_manager = Arc::new(self.rebuild_session_manager(self.session_manager.metrics()));
+        self
+    }
+
+    /// Age after which a connection logs in to GraphQL again, zero keeps sessions forever
+    pub fn with_session_max_lifetime(mut self, max_lifetime: Duration) -> Self {
+        self.session_max_lifetime = max_lifetime;
+        self.session_manager = Arc::new(self.rebuild_session_manager(self.session_manager.metrics()));
+        self
+    }
+
+    /// Override or add ParameterStatus values reported after authentication (--pg-param-status)
+    pub fn with_parameter_status(mut self, overrides: &[(String, String)]) -> Self {
+        self.parameter_status = Arc::new(settings::parameter_status_list(overrides));
+        self.session_manager = Arc::new(self.rebuild_session_manager(self.session_manager.metrics()));
+        self
+    }
+
+    /// Close TCP connections from addresses rejected by `ip_filter` right after accepting them
+    pub fn with_ip_filter(mut self, ip_filter: IpFilter) -> Self {
+        self.ip_filter = ip_filter;
+        self
+    }
+
+    /// Stop accepting connections once `shutdown` is cancelled and give in-flight queries
+    /// up to `timeout` to finish before they are cancelled
+    pub fn with_shutdown(mut self, shutdown: CancellationToken, timeout: Duration) -> Self {
+        self.shutdown = shutdown;
+        self.shutdown_timeout = timeout;
+        self.session_manager = Arc::new(self.rebuild_session_manager(self.session_manager.metrics()));
+        self
+    }
+
+    /// Drain mode before a shutdown: `draining` is shared with the health endpoint, and on
+    /// shutdown in-flight queries get up to `timeout` to finish while idle connections stay open
+    pub fn with_drain(mut self, draining: Arc<AtomicBool>, timeout: Duration) -> Self {
+        self.draining = draining;
+        self.drain_timeout = timeout;
+        self.session_manager = Arc::new(self.rebuild_session_manager(self.session_manager.metrics()));
         self
     }
 
+    // The session manager is shared behind an Arc, so settings are applied by rebuilding it
+    fn rebuild_session_manager(&self, metrics: Arc<Metrics>) -> SessionManager {
+        SessionManager::with_extension_interval(
+            self.session_manager.graphql_url().to_string(),
+            self.session_manager.extension_interval_secs()
+        )
+        .with_quiet_connections(self.quiet_connections)
+        .with_metrics(metrics)
+        .with_graphql_batch_size(self.graphql_batch_size)
+        .with_query_timeout_ms(self.query_timeout_ms)
+        .with_listen_poll_interval(self.listen_poll_interval)
+        .with_shutdown_token(self.close_connections.clone())
+        .with_draining_flag(self.draining.clone())
+        .with_connection_limits(self.max_connections, self.max_connections_per_user)
+        .with_graphql_urls(self.graphql_urls.clone(), self.graphql_primary_only)
+        .with_graphql_pool(self.graphql_pool_size, self.graphql_pool_idle_timeout)
+        .with_graphql_retry_policy(self.graphql_retry_policy.clone())
+        .with_circuit_breaker(self.cb_failure_threshold, self.cb_reset_timeout)
+        .with_browse_cache(self.browse_cache_ttl, self.browse_cache_max_entries)
+        .with_statement_cache(self.statement_cache_size)
+        .with_rate_limit(self.rate_limit_queries_per_second, self.rate_limit_burst)
+        .with_scram_credentials(self.scram_credentials.clone())
+        .with_jwt_validator(self.jwt_validator.clone())
+        .with_auth_mode(self.auth_mode, self.default_credentials.clone())
+        .with_sql_log(self.sql_log.clone())
+        .with_session_recovery_file(self.session_recovery.clone())
+        .with_session_max_lifetime(self.session_max_lifetime)
+        .with_parameter_status(self.parameter_status.clone())
+    }
+
     pub async fn start(&self, addr: SocketAddr) -> Result<()> {
         let listener = TcpListener::bind(addr).await?;
         info!("🐘 PostgreSQL-like server listening on {}", addr);
+        self.serve_on(listener).await
+    }
 
-        // Create TLS acceptor if TLS is configured
-        let tls_acceptor = if let Some(ref tls_config) = self.tls_config {
+    /// Accept connections on an already bound listener until shutdown is requested
+    pub async fn serve_on(&self, listener: TcpListener) -> Result<()> {
+        self.session_manager.recover_orphaned_sessions().await;
+        let unix_listener = UnixSocketListener::bind(self.unix_socket_path.as_deref(), listener.local_addr()?.port())?;
+
+        // Create TLS acceptor if TLS is configured, together with the SCRAM channel binding data
+        let (tls_acceptor, tls_server_end_point) = if let Some(ref tls_config) = self.tls_config {
             let server_config = crate::tls::create_server_config(tls_config)?;
-            Some(tokio_rustls::TlsAcceptor::from(server_config))
+            (
+                Some(tokio_rustls::TlsAcceptor::from(server_config)),
+                Some(crate::tls::load_server_end_point(tls_config)?),
+            )
         } else {
-            None
+            (None, None)
         };
 
         loop {
             debug!("🎧 Waiting for new connections...");
 
-            let (socket, client_addr) = listener.accept().await?;
+            let (socket, client_addr) = tokio::select! {
+                accepted = listener.accept() => {
+                    let (socket, client_addr) = accepted?;
+                    (Transport::Tcp(socket), ClientAddr::Tcp(client_addr))
+                }
+                accepted = unix_listener.accept() => accepted?,
+                _ = self.shutdown.cancelled() => break,
+            };
+            // Closed without an answer, so the client cannot tell that a PostgreSQL server is listening
+            if let ClientAddr::Tcp(peer_addr) = client_addr {
+                if !self.ip_filter.is_allowed(peer_addr.ip()) {
+                    warn!("🚫 Rejected connection from {} by the IP filter", peer_addr.ip());
+                    continue;
+                }
+            }
             if !self.quiet_connections {
                 info!("🌟 Accepted new connection from {}", client_addr);
             }
 
             let session_manager = self.session_manager.clone();
             let tls_acceptor = tls_acceptor.clone();
+            let tls_server_end_point = tls_server_end_point.clone();
             let quiet_connections = self.quiet_connections;
             let keep_alive_interval = self.keep_alive_interval;
             
@@ -128,6 +453,7 @@ impl PgProtocolServer {
                     session_manager.clone(), 
                     client_addr,
                     tls_acceptor,
+                    tls_server_end_point,
                     quiet_connections,
                     keep_alive_interval
                 ).await
@@ -161,5 +487,1197 @@ impl PgProtocolServer {
                 }
             });
         }
+
+        // Closing the listeners refuses new connections while existing ones drain
+        self.session_manager.start_draining();
+        drop(listener);
+        drop(unix_listener);
+        self.wait_for_queries_drained().await;
+        self.close_connections.cancel();
+        self.drain_connections().await;
+        Ok(())
+    }
+
+    /// Let in-flight queries complete naturally, up to the drain timeout, before idle connections are closed
+    async fn wait_for_queries_drained(&self) {
+        if self.drain_timeout.is_zero() {
+            return;
+        }
+        let session_manager = &self.session_manager;
+        info!(
+            "🚰 Draining: waiting up to {}s for {} in-flight queries to complete",
+            self.drain_timeout.as_secs(),
+            session_manager.active_query_count().await
+        );
+        let deadline = tokio::time::Instant::now() + self.drain_timeout;
+        loop {
+            let active_queries = session_manager.active_query_count().await;
+            if active_queries == 0 {
+                return;
+            }
+            if tokio::time::Instant::now() >= deadline {
+                warn!("⏰ Drain timeout reached with {} queries still running", active_queries);
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+    }
+
+    /// Wait for in-flight queries, cancel those exceeding the shutdown timeout and close all sessions.
+    /// Idle connections notice the shutdown token themselves and terminate right away.
+    async fn drain_connections(&self) {
+        let session_manager = &self.session_manager;
+        info!(
+            "🛑 Shutting down: waiting up to {}s for {} in-flight queries",
+            self.shutdown_timeout.as_secs(),
+            session_manager.active_query_count().await
+        );
+
+        if !Self::wait_for_connections_closed(session_manager, self.shutdown_timeout).await {
+            let cancelled = session_manager.cancel_all_queries().await;
+            warn!("⏰ Shutdown timeout reached, cancelling {} running queries", cancelled);
+            if !Self::wait_for_connections_closed(session_manager, SHUTDOWN_DRAIN_GRACE).await {
+                warn!(
+                    "⚠️  {} connections did not close in time",
+                    session_manager.connection_count().await
+                );
+            }
+        }
+
+        session_manager.close_all_sessions().await;
+        info!("👋 Server shutdown complete");
+    }
+
+    // Returns true once no registered connection is left, false if the timeout expired first
+    async fn wait_for_connections_closed(session_manager: &SessionManager, timeout: Duration) -> bool {
+        let deadline = tokio::time::Instant::now() + timeout;
+        loop {
+            if session_manager.connection_count().await == 0 {
+                return true;
+            }
+            if tokio::time::Instant::now() >= deadline {
+                return false;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{login_response, MockRequest, MockResponse};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpStream;
+
+    // GraphQL server that accepts every login but never answers data queries
+    async fn spawn_login_only_graphql_server() -> String {
+        spawn_graphql_server(|_| None).await
+    }
+
+    // GraphQL server that accepts every login; other requests get the body returned by
+    // `respond`, or are never answered when it returns None
+    async fn spawn_graphql_server(respond: impl Fn(&MockRequest) -> Option<String> + Send + Sync + 'static) -> String {
+        crate::test_support::spawn_graphql_server(move |request| {
+            if request.body.contains("mutation Login") {
+                return login_response();
+            }
+            respond(request).map_or(MockResponse::Hang, MockResponse::Json)
+        })
+        .await
+    }
+
+    async fn read_message(stream: &mut TcpStream) -> Option<(u8, Vec<u8>)> {
+        let mut header = [0u8; 5];
+        stream.read_exact(&mut header).await.ok()?;
+        let length = u32::from_be_bytes([header[1], header[2], header[3], header[4]]) as usize;
+        let mut body = vec![0u8; length - 4];
+        stream.read_exact(&mut body).await.ok()?;
+        Some((header[0], body))
+    }
+
+    async fn read_until(stream: &mut TcpStream, message_type: u8) -> Vec<u8> {
+        loop {
+            let (tag, body) = read_message(stream).await.expect("connection closed early");
+            if tag == message_type {
+                return body;
+            }
+        }
+    }
+
+    async fn send_startup(addr: SocketAddr, user: &str) -> TcpStream {
+        send_startup_with_parameters(addr, &[("user", user), ("database", "system")]).await
+    }
+
+    async fn send_startup_with_parameters(addr: SocketAddr, startup_parameters: &[(&str, &str)]) -> TcpStream {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        let mut parameters = Vec::new();
+        parameters.extend_from_slice(&196608u32.to_be_bytes());
+        for (name, value) in startup_parameters {
+            parameters.extend_from_slice(format!("{}\0{}\0", name, value).as_bytes());
+        }
+        parameters.push(0);
+        let mut startup = ((parameters.len() + 4) as u32).to_be_bytes().to_vec();
+        startup.extend_from_slice(&parameters);
+        stream.write_all(&startup).await.unwrap();
+        stream
+    }
+
+    // Connect with a cleartext password and wait for the first ReadyForQuery
+    async fn connect_client(addr: SocketAddr, user: &str) -> TcpStream {
+        authenticate(send_startup(addr, user).await).await
+    }
+
+    async fn authenticate(mut stream: TcpStream) -> TcpStream {
+        read_until(&mut stream, b'R').await;
+        let password = b"password1\0";
+        let mut message = vec![b'p'];
+        message.extend_from_slice(&((password.len() + 4) as u32).to_be_bytes());
+        message.extend_from_slice(password);
+        stream.write_all(&message).await.unwrap();
+        read_until(&mut stream, b'Z').await;
+        stream
+    }
+
+    async fn send_query(stream: &mut TcpStream, sql: &str) {
+        let mut message = vec![b'Q'];
+        message.extend_from_slice(&((sql.len() + 5) as u32).to_be_bytes());
+        message.extend_from_slice(sql.as_bytes());
+        message.push(0);
+        stream.write_all(&message).await.unwrap();
+    }
+
+    async fn spawn_server(server: Arc<PgProtocolServer>) -> (SocketAddr, tokio::task::JoinHandle<Result<()>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server_task = tokio::spawn(async move { server.serve_on(listener).await });
+        (addr, server_task)
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_drains_idle_and_cancels_running_queries() {
+        let url = spawn_login_only_graphql_server().await;
+        let shutdown = CancellationToken::new();
+        let server = Arc::new(
+            PgProtocolServer::with_keep_alive(url, None, 600, 600)
+                .with_query_timeout(0)
+                .with_shutdown(shutdown.clone(), Duration::from_millis(500)),
+        );
+        let (addr, server_task) = spawn_server(server.clone()).await;
+
+        let mut idle_client = connect_client(addr, "grafana").await;
+        let mut busy_client = connect_client(addr, "grafana").await;
+        send_query(&mut busy_client, "SELECT * FROM tagvalues WHERE tag_name = 'Tag_1'").await;
+        while server.session_manager.active_query_count().await == 0 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        shutdown.cancel();
+
+        // The idle connection is told right away why it is being closed
+        let notice = String::from_utf8_lossy(&read_until(&mut idle_client, b'E').await).to_string();
+        assert!(notice.contains("SFATAL") && notice.contains("C57P01"), "{}", notice);
+        assert!(read_message(&mut idle_client).await.is_none());
+
+        // New connections are refused while draining
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(TcpStream::connect(addr).await.is_err());
+
+        // The running query outlives the shutdown timeout and is cancelled
+        let error = String::from_utf8_lossy(&read_until(&mut busy_client, b'E').await).to_string();
+        assert!(error.contains("C57014"), "{}", error);
+        let notice = String::from_utf8_lossy(&read_until(&mut busy_client, b'E').await).to_string();
+        assert!(notice.contains("C57P01"), "{}", notice);
+        assert!(read_message(&mut busy_client).await.is_none());
+
+        tokio::time::timeout(Duration::from_secs(5), server_task)
+            .await
+            .expect("server stops after draining")
+            .unwrap()
+            .unwrap();
+        assert_eq!(server.session_manager.session_count().await, 0);
+        assert_eq!(server.session_manager.connection_count().await, 0);
+    }
+
+    // The mock answers tagValues after blocking its task, so the test needs a second worker
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_drain_mode_rejects_new_connections_and_lets_queries_complete() {
+        let url = spawn_graphql_server(|request| {
+            request.body.contains("tagValues").then(|| {
+                std::thread::sleep(Duration::from_millis(500));
+                r#"{"data":{"tagValues":[{"name":"Tag_1","value":{"value":1.5,"timestamp":"2025-01-01T10:00:00.000Z","quality":null},"error":null}]}}"#.to_string()
+            })
+        })
+        .await;
+        let shutdown = CancellationToken::new();
+        let draining = Arc::new(AtomicBool::new(false));
+        let server = Arc::new(
+            PgProtocolServer::with_keep_alive(url, None, 600, 600)
+                .with_query_timeout(0)
+                .with_shutdown(shutdown.clone(), Duration::from_secs(5))
+                .with_drain(draining.clone(), Duration::from_secs(5)),
+        );
+        let (addr, server_task) = spawn_server(server.clone()).await;
+        let mut busy_client = connect_client(addr, "grafana").await;
+
+        // GET /drain of the health endpoint: new connections are sent elsewhere
+        crate::auth::start_draining(&draining);
+        let mut rejected = send_startup(addr, "grafana").await;
+        let error = String::from_utf8_lossy(&read_until(&mut rejected, b'E').await).to_string();
+        assert!(error.contains("SFATAL") && error.contains("C57P03"), "{}", error);
+        assert!(read_message(&mut rejected).await.is_none());
+
+        // SIGTERM while a query runs: it completes before the connection is closed
+        send_query(&mut busy_client, "SELECT numeric_value FROM tagvalues WHERE tag_name = 'Tag_1'").await;
+        while server.session_manager.active_query_count().await == 0 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        shutdown.cancel();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(TcpStream::connect(addr).await.is_err());
+
+        let row = String::from_utf8_lossy(&read_until(&mut busy_client, b'D').await).to_string();
+        assert!(row.contains("1.5"), "{}", row);
+        let command_tag = String::from_utf8_lossy(&read_until(&mut busy_client, b'C').await).to_string();
+        assert!(command_tag.starts_with("SELECT 1"), "{}", command_tag);
+        read_until(&mut busy_client, b'Z').await;
+        let notice = String::from_utf8_lossy(&read_until(&mut busy_client, b'E').await).to_string();
+        assert!(notice.contains("C57P01"), "{}", notice);
+        assert!(read_message(&mut busy_client).await.is_none());
+
+        tokio::time::timeout(Duration::from_secs(5), server_task)
+            .await
+            .expect("server stops once the query completed")
+            .unwrap()
+            .unwrap();
+        assert_eq!(server.session_manager.connection_count().await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_connection_limits_reject_with_53300() {
+        let url = spawn_login_only_graphql_server().await;
+        let server = Arc::new(PgProtocolServer::with_keep_alive(url, None, 600, 600).with_connection_limits(2, 1));
+        let (addr, _server_task) = spawn_server(server.clone()).await;
+
+        let grafana = connect_client(addr, "grafana").await;
+
+        let mut rejected = send_startup(addr, "grafana").await;
+        let error = String::from_utf8_lossy(&read_until(&mut rejected, b'E').await).to_string();
+        assert!(error.contains("SFATAL") && error.contains("C53300"), "{}", error);
+        assert!(error.contains("too many connections for user"), "{}", error);
+        assert!(read_message(&mut rejected).await.is_none());
+
+        let _testuser = connect_client(addr, "testuser").await;
+        let mut rejected = send_startup(addr, "username1").await;
+        let error = String::from_utf8_lossy(&read_until(&mut rejected, b'E').await).to_string();
+        assert!(error.contains("C53300") && error.contains("too many clients"), "{}", error);
+        assert_eq!(server.session_manager.open_connection_count(), 2);
+
+        // Closing a connection frees its slot for the same user
+        drop(grafana);
+        while server.session_manager.open_connection_count() > 1 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        let _grafana = connect_client(addr, "grafana").await;
+        assert_eq!(server.session_manager.open_connection_count(), 2);
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_replication_connections_and_commands_are_rejected_with_0a000() {
+        let url = spawn_login_only_graphql_server().await;
+        let server = Arc::new(PgProtocolServer::with_keep_alive(url, None, 600, 600));
+        let (addr, _server_task) = spawn_server(server.clone()).await;
+
+        let mut replication = send_startup_with_parameters(addr, &[("user", "grafana"), ("database", "system"), ("replication", "database")]).await;
+        let (tag, body) = read_message(&mut replication).await.expect("error response");
+        assert_eq!(tag, b'E');
+        let error = String::from_utf8_lossy(&body).to_string();
+        assert!(error.contains("SFATAL") && error.contains("C0A000"), "{}", error);
+        assert!(error.contains("Mlogical replication is not supported by this server"), "{}", error);
+        assert!(read_message(&mut replication).await.is_none());
+        assert_eq!(server.session_manager.open_connection_count(), 0);
+
+        // replication=false is a regular connection
+        let mut client = authenticate(send_startup_with_parameters(addr, &[("user", "grafana"), ("database", "system"), ("replication", "false")]).await).await;
+        for command in ["START_REPLICATION SLOT debezium LOGICAL 0/0", "CREATE_REPLICATION_SLOT debezium LOGICAL pgoutput", "START TRANSACTION REPLICATION"] {
+            send_query(&mut client, command).await;
+            let error = String::from_utf8_lossy(&read_until(&mut client, b'E').await).to_string();
+            assert!(error.contains("C0A000") && error.contains("logical replication is not supported"), "{}", error);
+            read_until(&mut client, b'Z').await;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_update_writes_tag_values_only_when_enabled() {
+        let write_requests = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let recorded = write_requests.clone();
+        let url = spawn_graphql_server(move |request| {
+            if !request.body.contains("writeTagValues") {
+                return None;
+            }
+            recorded.lock().unwrap().push(request.body.clone());
+            Some(r#"{"data":{"writeTagValues":[{"name":"Setpoint_1","error":{"code":"0"}},{"name":"Setpoint_2","error":{"code":"0"}}]}}"#.to_string())
+        })
+        .await;
+        let server = Arc::new(PgProtocolServer::with_keep_alive(url, None, 600, 600));
+        let (addr, _server_task) = spawn_server(server).await;
+        let mut client = connect_client(addr, "operator").await;
+
+        crate::ENABLE_WRITES.store(false, std::sync::atomic::Ordering::Relaxed);
+        send_query(&mut client, "UPDATE tagvalues SET numeric_value = 3.14 WHERE tag_name = 'Setpoint_1'").await;
+        let error = String::from_utf8_lossy(&read_until(&mut client, b'E').await).to_string();
+        assert!(error.contains("C42501"), "{}", error);
+        read_until(&mut client, b'Z').await;
+        assert!(write_requests.lock().unwrap().is_empty());
+
+        crate::ENABLE_WRITES.store(true, std::sync::atomic::Ordering::Relaxed);
+        send_query(
+            &mut client,
+            "UPDATE tagvalues SET numeric_value = 3.14 WHERE tag_name IN ('Setpoint_1', 'Setpoint_2')",
+        )
+        .await;
+        let (tag, body) = read_message(&mut client).await.unwrap();
+        crate::ENABLE_WRITES.store(false, std::sync::atomic::Ordering::Relaxed);
+        assert_eq!(tag, b'C', "no RowDescription precedes the command tag");
+        assert_eq!(body, b"UPDATE 2\0");
+        read_until(&mut client, b'Z').await;
+
+        let write_requests = write_requests.lock().unwrap();
+        assert_eq!(write_requests.len(), 1);
+        assert!(write_requests[0].contains(r#"{"name":"Setpoint_1","value":3.14}"#), "{}", write_requests[0]);
+        assert!(write_requests[0].contains(r#"{"name":"Setpoint_2","value":3.14}"#), "{}", write_requests[0]);
+    }
+
+    #[tokio::test]
+    async fn test_taglist_object_type_filter_is_sent_to_browse() {
+        let browse_requests = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let recorded = browse_requests.clone();
+        let url = spawn_graphql_server(move |request| {
+            if !request.body.contains("browse(") {
+                return None;
+            }
+            recorded.lock().unwrap().push(request.json());
+            Some(r#"{"data":{"browse":[{"name":"Motor:Speed:PV","displayName":null,"objectType":"LOGGINGTAG","dataType":null}]}}"#.to_string())
+        })
+        .await;
+        let server = Arc::new(PgProtocolServer::with_keep_alive(url, None, 600, 600));
+        let (addr, _server_task) = spawn_server(server).await;
+        let mut client = connect_client(addr, "grafana").await;
+
+        send_query(&mut client, "SELECT tag_name FROM taglist WHERE object_type = 'LOGGINGTAG'").await;
+        let row = read_until(&mut client, b'D').await;
+        read_until(&mut client, b'Z').await;
+        assert_eq!(String::from_utf8_lossy(&row[6..]), "Motor:Speed:PV");
+
+        let browse_requests = browse_requests.lock().unwrap();
+        assert_eq!(browse_requests.len(), 1);
+        assert_eq!(browse_requests[0]["variables"]["objectTypeFilters"], serde_json::json!(["LOGGINGTAG"]));
+    }
+
+    #[tokio::test]
+    async fn test_set_variables_round_trip_through_show_and_current_setting() {
+        let url = spawn_login_only_graphql_server().await;
+        let server = Arc::new(PgProtocolServer::with_keep_alive(url, None, 600, 600));
+        let (addr, _server_task) = spawn_server(server).await;
+        let mut client = connect_client(addr, "grafana").await;
+        let mut other_client = connect_client(addr, "grafana").await;
+
+        let query_value = async |client: &mut TcpStream, sql: &str| {
+            send_query(client, sql).await;
+            let row = read_until(client, b'D').await;
+            read_until(client, b'Z').await;
+            String::from_utf8_lossy(&row[6..]).to_string()
+        };
+
+        for sql in ["SET foo = 'bar'", "SET \"grafana.user\" = 'admin'", "SET application_name = 'Grafana'"] {
+            send_query(&mut client, sql).await;
+            assert_eq!(read_until(&mut client, b'C').await, b"SET\0");
+            read_until(&mut client, b'Z').await;
+        }
+
+        assert_eq!(query_value(&mut client, "SHOW foo").await, "bar");
+        assert_eq!(query_value(&mut client, "SHOW grafana.user").await, "admin");
+        assert_eq!(query_value(&mut client, "SHOW application_name").await, "Grafana");
+        assert_eq!(query_value(&mut client, "SELECT current_setting('grafana.user')").await, "admin");
+        assert_eq!(query_value(&mut client, "SELECT current_setting('TimeZone')").await, "UTC");
+
+        // Variables belong to the connection that set them
+        send_query(&mut other_client, "SHOW foo").await;
+        let error = String::from_utf8_lossy(&read_until(&mut other_client, b'E').await).to_string();
+        assert!(error.contains("unrecognized configuration parameter"), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn test_startup_options_apply_as_set() {
+        let url = spawn_login_only_graphql_server().await;
+        let server = Arc::new(PgProtocolServer::with_keep_alive(url, None, 600, 600));
+        let (addr, _server_task) = spawn_server(server).await;
+        let stream = send_startup_with_parameters(
+            addr,
+            &[("user", "grafana"), ("database", "system"), ("options", "-c application_name=MyApp -c search_path=public")],
+        )
+        .await;
+        let mut client = authenticate(stream).await;
+
+        for (sql, expected) in [("SHOW application_name", "MyApp"), ("SHOW search_path", "public")] {
+            send_query(&mut client, sql).await;
+            let row = read_until(&mut client, b'D').await;
+            read_until(&mut client, b'Z').await;
+            assert_eq!(String::from_utf8_lossy(&row[6..]), expected, "{}", sql);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_parameter_status_overrides_and_application_name() {
+        let url = spawn_login_only_graphql_server().await;
+        let overrides = ["integer_datetimes=off", "is_superuser=on"].map(|arg| settings::parse_parameter_status(arg).unwrap());
+        let server = Arc::new(PgProtocolServer::with_keep_alive(url, None, 600, 600).with_parameter_status(&overrides));
+        let (addr, _server_task) = spawn_server(server).await;
+        let mut stream = send_startup_with_parameters(addr, &[("user", "grafana"), ("database", "system"), ("application_name", "psql")]).await;
+
+        read_until(&mut stream, b'R').await;
+        let password = b"password1\0";
+        let mut message = vec![b'p'];
+        message.extend_from_slice(&((password.len() + 4) as u32).to_be_bytes());
+        message.extend_from_slice(password);
+        stream.write_all(&message).await.unwrap();
+        let mut parameters = HashMap::new();
+        loop {
+            let (tag, body) = read_message(&mut stream).await.unwrap();
+            match tag {
+                b'S' => {
+                    let fields: Vec<String> = body.split(|b| *b == 0).map(|field| String::from_utf8_lossy(field).to_string()).collect();
+                    parameters.insert(fields[0].clone(), fields[1].clone());
+                }
+                b'Z' => break,
+                _ => {}
+            }
+        }
+
+        assert_eq!(parameters["integer_datetimes"], "off");
+        assert_eq!(parameters["is_superuser"], "on");
+        assert_eq!(parameters["application_name"], "psql");
+        assert_eq!(parameters["server_version"], "15.0");
+        assert!(settings::parse_parameter_status("integer_datetimes").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_multiple_statements_in_one_simple_query() {
+        let url = spawn_login_only_graphql_server().await;
+        let server = Arc::new(PgProtocolServer::with_keep_alive(url, None, 600, 600));
+        let (addr, _server_task) = spawn_server(server).await;
+        let mut client = connect_client(addr, "grafana").await;
+
+        let responses = async |client: &mut TcpStream, sql: &str| {
+            send_query(client, sql).await;
+            let mut messages = Vec::new();
+            loop {
+                let (tag, body) = read_message(client).await.unwrap();
+                messages.push((tag as char, body));
+                if tag == b'Z' {
+                    return messages;
+                }
+            }
+        };
+
+        let messages = responses(&mut client, "SELECT 2 AS a; SELECT 'x;y' AS b; SELECT 3 AS c;").await;
+        let types: String = messages.iter().map(|(tag, _)| *tag).collect();
+        assert_eq!(types, "TDCTDCTDCZ");
+        let values: Vec<String> = messages
+            .iter()
+            .filter(|(tag, _)| *tag == 'D')
+            .map(|(_, body)| String::from_utf8_lossy(&body[6..]).to_string())
+            .collect();
+        assert_eq!(values, vec!["2", "x;y", "3"]);
+
+        // The first failing statement ends the query, the statements after it are not run
+        let messages = responses(&mut client, "BEGIN; SELECT 2 AS a; SELECT * FROM no_such_table; SELECT 3 AS c").await;
+        let types: String = messages.iter().map(|(tag, _)| *tag).collect();
+        assert_eq!(types, "CTDCEZ");
+        assert_eq!(messages[0].1, b"BEGIN\0");
+
+        // The connection stays usable
+        let types: String = responses(&mut client, "SELECT 4 AS d").await.iter().map(|(tag, _)| *tag).collect();
+        assert_eq!(types, "TDCZ");
+    }
+
+    #[tokio::test]
+    async fn test_reset_drops_session_variables() {
+        let url = spawn_login_only_graphql_server().await;
+        let server = Arc::new(PgProtocolServer::with_keep_alive(url, None, 600, 600));
+        let (addr, _server_task) = spawn_server(server).await;
+        let mut client = connect_client(addr, "grafana").await;
+
+        let execute = async |client: &mut TcpStream, sql: &str| {
+            send_query(client, sql).await;
+            let tag = read_until(client, b'C').await;
+            read_until(client, b'Z').await;
+            tag
+        };
+        let show = async |client: &mut TcpStream, name: &str| {
+            send_query(client, &format!("SHOW {}", name)).await;
+            let (tag, body) = loop {
+                let (tag, body) = read_message(client).await.unwrap();
+                if tag == b'D' || tag == b'E' {
+                    break (tag, body);
+                }
+            };
+            read_until(client, b'Z').await;
+            match tag {
+                b'D' => Ok(String::from_utf8_lossy(&body[6..]).to_string()),
+                _ => Err(String::from_utf8_lossy(&body).to_string()),
+            }
+        };
+
+        for sql in ["SET foo = 'bar'", "SET search_path = winccua", "SET application_name = 'Grafana'", "SET extra_float_digits = 3"] {
+            assert_eq!(execute(&mut client, sql).await, b"SET\0");
+        }
+        assert_eq!(execute(&mut client, "RESET foo").await, b"RESET\0");
+        let error = show(&mut client, "foo").await.unwrap_err();
+        assert!(error.contains("unrecognized configuration parameter"), "{}", error);
+        assert_eq!(show(&mut client, "search_path").await.unwrap(), "winccua");
+
+        assert_eq!(execute(&mut client, "RESET search_path").await, b"RESET\0");
+        assert_eq!(show(&mut client, "search_path").await.unwrap(), "\"$user\", public");
+
+        // RESET ALL restores the server settings and the application_name of the startup message
+        assert_eq!(execute(&mut client, "RESET ALL").await, b"RESET\0");
+        assert_eq!(show(&mut client, "extra_float_digits").await.unwrap(), "1");
+        assert_eq!(show(&mut client, "application_name").await.unwrap(), "unknown");
+    }
+
+    #[tokio::test]
+    async fn test_timestamps_are_shown_in_the_session_timezone() {
+        let url = spawn_login_only_graphql_server().await;
+        let server = Arc::new(PgProtocolServer::with_keep_alive(url, None, 600, 600));
+        let (addr, _server_task) = spawn_server(server).await;
+        let mut client = connect_client(addr, "grafana").await;
+
+        // Type OID of the first column and the first row
+        let query = async |client: &mut TcpStream, sql: &str| {
+            send_query(client, sql).await;
+            let description = read_until(client, b'T').await;
+            let name_end = description[2..].iter().position(|&b| b == 0).unwrap() + 3;
+            let type_oid = u32::from_be_bytes(description[name_end + 6..name_end + 10].try_into().unwrap());
+            let row = read_until(client, b'D').await;
+            read_until(client, b'Z').await;
+            (type_oid, String::from_utf8_lossy(&row[6..]).to_string())
+        };
+        let winter = "SELECT CAST('2025-01-15 12:00:00' AS TIMESTAMP) AS ts";
+        let summer = "SELECT CAST('2025-07-15 12:00:00' AS TIMESTAMP) AS ts";
+
+        assert_eq!(query(&mut client, winter).await, (1114, "2025-01-15 12:00:00.000000".to_string()));
+
+        send_query(&mut client, "SET TimeZone = 'America/New_York'").await;
+        assert_eq!(read_until(&mut client, b'C').await, b"SET\0");
+        read_until(&mut client, b'Z').await;
+        assert_eq!(query(&mut client, winter).await, (1184, "2025-01-15 07:00:00.000000-05:00".to_string()));
+        assert_eq!(query(&mut client, summer).await, (1184, "2025-07-15 08:00:00.000000-04:00".to_string()));
+
+        send_query(&mut client, "RESET TimeZone").await;
+        read_until(&mut client, b'Z').await;
+        assert_eq!(query(&mut client, winter).await, (1114, "2025-01-15 12:00:00.000000".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_alarm_locale_session_variable_selects_alarm_texts() {
+        let _ = crate::ALARM_LANGUAGES.set(["en-US", "de-DE", "fr-FR"].map(String::from).to_vec());
+        // Alarm texts in the order of the requested languages
+        let url = spawn_graphql_server(|request| {
+            request.body.contains("activeAlarms").then(|| {
+                let variables = request.json();
+                let texts: Vec<&str> = variables["variables"]["languages"]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .map(|language| match language.as_str().unwrap() {
+                        "de-DE" => "Motor überlastet",
+                        "fr-FR" => "Surcharge moteur",
+                        _ => "Motor overload",
+                    })
+                    .collect();
+                serde_json::json!({ "data": { "activeAlarms": [{
+                    "name": "Motor_1", "instanceID": 1, "alarmGroupID": null, "raiseTime": "2025-01-01T10:00:00.000Z",
+                    "acknowledgmentTime": null, "clearTime": null, "resetTime": null, "modificationTime": "2025-01-01T10:00:00.000Z",
+                    "state": "RAISED", "priority": 10, "eventText": texts, "infoText": null, "origin": null, "area": null,
+                    "value": null, "hostName": null, "userName": null
+                }] } })
+                .to_string()
+            })
+        })
+        .await;
+        let server = Arc::new(PgProtocolServer::with_keep_alive(url, None, 600, 600));
+        let (addr, _server_task) = spawn_server(server).await;
+        let mut client = connect_client(addr, "grafana").await;
+        let mut other_client = connect_client(addr, "grafana").await;
+
+        let event_text = async |client: &mut TcpStream| {
+            send_query(client, "SELECT event_text FROM activealarms").await;
+            let row = read_until(client, b'D').await;
+            read_until(client, b'Z').await;
+            String::from_utf8_lossy(&row[6..]).to_string()
+        };
+
+        assert_eq!(event_text(&mut client).await, "Motor overload");
+        for (locale, expected) in [("de", "Motor überlastet"), ("fr-FR", "Surcharge moteur")] {
+            send_query(&mut client, &format!("SET winccua.alarm_locale = '{}'", locale)).await;
+            read_until(&mut client, b'Z').await;
+            assert_eq!(event_text(&mut client).await, expected);
+        }
+
+        // The locale belongs to the connection that set it
+        assert_eq!(event_text(&mut other_client).await, "Motor overload");
+    }
+
+    #[tokio::test]
+    async fn test_failed_graphql_url_fails_over_to_the_next_one() {
+        let dead_url = "http://127.0.0.1:1/graphql".to_string();
+        let live_url = spawn_graphql_server(|request| {
+            request.body.contains("tagValues").then(|| {
+                r#"{"data":{"tagValues":[
+                    {"name":"Tag_1","value":{"value":42,"timestamp":"2025-01-01T10:00:00.000Z","quality":{"quality":"GOOD"}},"error":null}
+                ]}}"#
+                    .to_string()
+            })
+        })
+        .await;
+        let server = Arc::new(
+            PgProtocolServer::with_keep_alive(dead_url.clone(), None, 600, 600)
+                .with_graphql_urls(vec![dead_url.clone(), live_url.clone()], false)
+                .with_graphql_retry(RetryPolicy::none()),
+        );
+        let (addr, _server_task) = spawn_server(server.clone()).await;
+
+        // Round-robin starts on the dead URL for one of the two logins, both end up on the live one
+        for _ in 0..2 {
+            let mut client = connect_client(addr, "grafana").await;
+            send_query(&mut client, "SELECT tag_name, numeric_value FROM tagvalues WHERE tag_name = 'Tag_1'").await;
+            let row = read_until(&mut client, b'D').await;
+            assert!(String::from_utf8_lossy(&row).contains("Tag_1"));
+            assert_eq!(read_until(&mut client, b'C').await, b"SELECT 1\0");
+        }
+
+        let metrics = server.session_manager.metrics().encode().unwrap();
+        let count = |url: &str, result: &str| {
+            let series = format!("pgwire_graphql_backend_requests_total{{result=\"{}\",url=\"{}\"}} ", result, url);
+            metrics.lines().find_map(|line| line.strip_prefix(series.as_str())).unwrap_or("0").to_string()
+        };
+        assert_eq!(count(&dead_url, "failure"), "1");
+        assert_eq!(count(&live_url, "success"), "4", "two logins and two tagValues requests");
+    }
+
+    #[tokio::test]
+    async fn test_denied_ip_is_disconnected_without_an_answer() {
+        let url = spawn_login_only_graphql_server().await;
+        let deny = vec![crate::ip_filter::parse_ip_net("127.0.0.1/32").unwrap()];
+        let server = Arc::new(PgProtocolServer::with_keep_alive(url, None, 600, 600).with_ip_filter(IpFilter::new(Vec::new(), deny)));
+        let (addr, _server_task) = spawn_server(server).await;
+
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        let mut buffer = [0u8; 16];
+        let read = tokio::time::timeout(Duration::from_secs(5), stream.read(&mut buffer)).await.expect("connection was not closed");
+        assert!(matches!(read, Ok(0) | Err(_)), "server answered {:?}", read);
+    }
+
+    #[tokio::test]
+    async fn test_connection_stats_count_the_queries_of_a_connection() {
+        let url = spawn_graphql_server(|request| {
+            request.body.contains("tagValues").then(|| {
+                if request.body.contains("Broken") {
+                    return "not a GraphQL response".to_string();
+                }
+                r#"{"data":{"tagValues":[
+                    {"name":"Tag_1","value":{"value":42,"timestamp":"2025-01-01T10:00:00.000Z","quality":{"quality":"GOOD"}},"error":null}
+                ]}}"#
+                    .to_string()
+            })
+        })
+        .await;
+        let server = Arc::new(PgProtocolServer::with_keep_alive(url, None, 600, 600));
+        let (addr, _server_task) = spawn_server(server).await;
+        let mut client = connect_client(addr, "grafana").await;
+        let mut other_client = connect_client(addr, "grafana").await;
+
+        for tag in ["Tag_1", "Tag_1", "Tag_1", "Tag_1", "Broken"] {
+            send_query(&mut client, &format!("SELECT numeric_value FROM tagvalues WHERE tag_name = '{}'", tag)).await;
+            read_until(&mut client, b'Z').await;
+        }
+
+        // Totals of the querying connection, counted before the statistics query itself
+        let sql = "SELECT total_queries, total_duration_ms, graphql_errors, cache_hits, slow_queries, avg_duration_ms \
+                   FROM winccua_connection_stats ORDER BY total_queries DESC";
+        send_query(&mut other_client, sql).await;
+        let row = read_until(&mut other_client, b'D').await;
+        read_until(&mut other_client, b'Z').await;
+        let mut values = Vec::new();
+        let mut offset = 2;
+        while offset < row.len() {
+            let length = i32::from_be_bytes(row[offset..offset + 4].try_into().unwrap());
+            offset += 4;
+            values.push(String::from_utf8_lossy(&row[offset..offset + length.max(0) as usize]).to_string());
+            offset += length.max(0) as usize;
+        }
+        assert_eq!(values[0], "5");
+        assert!(values[1].parse::<i64>().unwrap() > 0, "total_duration_ms: {}", values[1]);
+        assert_eq!(&values[2..5], ["1", "0", "0"]);
+        assert!(values[5].parse::<f64>().unwrap() > 0.0);
+    }
+
+    // Client side of a SCRAM-SHA-256 exchange: the client-final message and the server
+    // signature the server has to answer with
+    fn scram_client_final(password: &str, client_first_bare: &str, server_first: &str) -> (String, String) {
+        use base64::{engine::general_purpose::STANDARD, Engine};
+        use hmac::{Hmac, Mac};
+        use sha2::{Digest, Sha256};
+        type HmacSha256 = Hmac<Sha256>;
+        let hmac = |key: &[u8], data: &[u8]| {
+            let mut mac = HmacSha256::new_from_slice(key).unwrap();
+            mac.update(data);
+            mac.finalize().into_bytes().to_vec()
+        };
+
+        let attribute = |name: &str| {
+            server_first.split(',').find_map(|part| part.strip_prefix(name)).unwrap().to_string()
+        };
+        let salt = STANDARD.decode(attribute("s=")).unwrap();
+        let iterations: u32 = attribute("i=").parse().unwrap();
+        let mut salted_password = [0u8; 32];
+        pbkdf2::pbkdf2::<HmacSha256>(password.as_bytes(), &salt, iterations, &mut salted_password).unwrap();
+
+        let without_proof = format!("c=biws,r={}", attribute("r="));
+        let auth_message = format!("{},{},{}", client_first_bare, server_first, without_proof);
+        let client_key = hmac(&salted_password, b"Client Key");
+        let client_signature = hmac(&Sha256::digest(&client_key), auth_message.as_bytes());
+        let proof: Vec<u8> = client_key.iter().zip(&client_signature).map(|(k, s)| k ^ s).collect();
+        let server_signature = hmac(&hmac(&salted_password, b"Server Key"), auth_message.as_bytes());
+        (
+            format!("{},p={}", without_proof, STANDARD.encode(proof)),
+            format!("v={}", STANDARD.encode(server_signature)),
+        )
+    }
+
+    // Run the SCRAM exchange and return the first message after the client-final; `scram_user`
+    // is the username of the client-first message, which libpq leaves empty
+    async fn scram_login(addr: SocketAddr, user: &str, scram_user: &str, password: &str) -> (TcpStream, u8, Vec<u8>) {
+        let mut stream = send_startup(addr, user).await;
+        let request = read_until(&mut stream, b'R').await;
+        assert_eq!(&request[..4], &10u32.to_be_bytes(), "AuthenticationSASL expected");
+        assert_eq!(&request[4..], b"SCRAM-SHA-256\0\0");
+
+        let client_first_bare = format!("n={},r=rOprNGfwEbeRWgbNEkqO", scram_user);
+        let client_first = format!("n,,{}", client_first_bare);
+        let mut body = b"SCRAM-SHA-256\0".to_vec();
+        body.extend_from_slice(&(client_first.len() as u32).to_be_bytes());
+        body.extend_from_slice(client_first.as_bytes());
+        let mut message = vec![b'p'];
+        message.extend_from_slice(&((body.len() + 4) as u32).to_be_bytes());
+        message.extend_from_slice(&body);
+        stream.write_all(&message).await.unwrap();
+
+        let server_first = read_until(&mut stream, b'R').await;
+        assert_eq!(&server_first[..4], &11u32.to_be_bytes(), "AuthenticationSASLContinue expected");
+        let server_first = String::from_utf8(server_first[4..].to_vec()).unwrap();
+        let (client_final, server_signature) = scram_client_final(password, &client_first_bare, &server_first);
+        let mut message = vec![b'p'];
+        message.extend_from_slice(&((client_final.len() + 4) as u32).to_be_bytes());
+        message.extend_from_slice(client_final.as_bytes());
+        stream.write_all(&message).await.unwrap();
+
+        let (tag, body) = read_message(&mut stream).await.unwrap();
+        if tag == b'R' {
+            assert_eq!(&body[..4], &12u32.to_be_bytes(), "AuthenticationSASLFinal expected");
+            assert_eq!(String::from_utf8_lossy(&body[4..]), server_signature, "server signature");
+        }
+        (stream, tag, body)
+    }
+
+    #[tokio::test]
+    async fn test_scram_authentication_against_password_file() {
+        let url = spawn_login_only_graphql_server().await;
+        let credentials = crate::credentials::CredentialStore::parse("grafana:s3cret").unwrap();
+        let server = Arc::new(
+            PgProtocolServer::with_keep_alive(url, None, 600, 600)
+                .with_scram_credentials(Some(Arc::new(credentials)))
+                .with_auth_mode(AuthMode::ScramSha256, None),
+        );
+        let (addr, _server_task) = spawn_server(server.clone()).await;
+
+        // libpq sends "n,,n=,r=<nonce>", the password belongs to the user of the startup message
+        for scram_user in ["grafana", ""] {
+            let (mut client, tag, _) = scram_login(addr, "grafana", scram_user, "s3cret").await;
+            assert_eq!(tag, b'R');
+            let auth_ok = read_until(&mut client, b'R').await;
+            assert_eq!(auth_ok, 0u32.to_be_bytes(), "AuthenticationOk expected");
+            read_until(&mut client, b'Z').await;
+            send_query(&mut client, "SELECT 42").await;
+            read_until(&mut client, b'D').await;
+        }
+
+        // A wrong password and an unknown user are both rejected after the client-final
+        for (user, password) in [("grafana", "password1"), ("operator", "s3cret")] {
+            let (_, tag, body) = scram_login(addr, user, "", password).await;
+            assert_eq!(tag, b'E');
+            let error = String::from_utf8_lossy(&body).to_string();
+            assert!(error.contains("28P01"), "{}", error);
+            assert!(error.contains(&format!("password authentication failed for user \"{}\"", user)), "{}", error);
+        }
+        assert_eq!(server.session_manager.metrics().auth_failures_total.get(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_jwt_authentication_uses_the_token_for_graphql() {
+        let (key_pair, pem) = crate::jwt::tests::es256_key_pair();
+        let exp = chrono::Utc::now().timestamp() + 3600;
+        let token = crate::jwt::tests::sign_es256(&key_pair, serde_json::json!({"sub": "grafana", "exp": exp, "aud": "winccua"}));
+
+        let requests = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let seen = requests.clone();
+        let url = spawn_graphql_server(move |request| {
+            seen.lock().unwrap().push((request.header("authorization").unwrap_or_default().to_string(), request.body.clone()));
+            request.body.contains("tagValues").then(|| {
+                r#"{"data":{"tagValues":[{"name":"Tag_1","value":{"value":1.5,"timestamp":"2025-01-01T10:00:00.000Z","quality":null},"error":null}]}}"#.to_string()
+            })
+        })
+        .await;
+        let validator = crate::jwt::JwtValidator::from_pem(pem.as_bytes(), Some("winccua".to_string())).unwrap();
+        let server = Arc::new(
+            PgProtocolServer::with_keep_alive(url, None, 600, 600)
+                .with_jwt_validator(Some(Arc::new(validator)))
+                .with_auth_mode(AuthMode::Jwt, None),
+        );
+        let (addr, _server_task) = spawn_server(server.clone()).await;
+
+        let login = |password: String| async move {
+            let mut stream = send_startup(addr, "ignored").await;
+            let request = read_until(&mut stream, b'R').await;
+            assert_eq!(request, 3u32.to_be_bytes(), "AuthenticationCleartextPassword expected");
+            let mut message = vec![b'p'];
+            message.extend_from_slice(&((password.len() + 5) as u32).to_be_bytes());
+            message.extend_from_slice(password.as_bytes());
+            message.push(0);
+            stream.write_all(&message).await.unwrap();
+            let (tag, body) = read_message(&mut stream).await.unwrap();
+            (stream, tag, body)
+        };
+
+        let (mut client, tag, body) = login(token.clone()).await;
+        assert_eq!((tag, body), (b'R', 0u32.to_be_bytes().to_vec()), "AuthenticationOk expected");
+        read_until(&mut client, b'Z').await;
+        send_query(&mut client, "SELECT numeric_value FROM tagvalues WHERE tag_name = 'Tag_1'").await;
+        assert_eq!(&read_until(&mut client, b'D').await[6..], b"1.5");
+
+        // The username comes from the sub claim and GraphQL gets the JWT instead of a login
+        let connections = server.session_manager.get_connections().await;
+        assert_eq!(connections.iter().map(|conn| conn.username.as_deref()).collect::<Vec<_>>(), vec![Some("grafana")]);
+        let requests = requests.lock().unwrap().clone();
+        assert!(requests.iter().all(|(_, body)| !body.contains("mutation Login")));
+        assert!(requests.iter().any(|(authorization, _)| *authorization == format!("Bearer {}", token)));
+
+        let forged = crate::jwt::tests::sign_es256(&crate::jwt::tests::es256_key_pair().0, serde_json::json!({"sub": "admin", "exp": exp, "aud": "winccua"}));
+        let (_, tag, body) = login(forged).await;
+        assert_eq!(tag, b'E');
+        let error = String::from_utf8_lossy(&body).to_string();
+        assert!(error.contains("28P01") && error.contains("JWT signature is invalid"), "{}", error);
+        assert_eq!(server.session_manager.metrics().auth_failures_total.get(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_jwt_larger_than_one_read_is_accepted() {
+        let (key_pair, pem) = crate::jwt::tests::es256_key_pair();
+        let exp = chrono::Utc::now().timestamp() + 3600;
+        let groups: Vec<String> = (0..200).map(|i| format!("group-{:04}-of-the-identity-provider", i)).collect();
+        let token = crate::jwt::tests::sign_es256(&key_pair, serde_json::json!({"sub": "grafana", "exp": exp, "aud": "winccua", "groups": groups}));
+        assert!(token.len() > 4096, "{}", token.len());
+
+        let url = spawn_graphql_server(|_| None).await;
+        let validator = crate::jwt::JwtValidator::from_pem(pem.as_bytes(), Some("winccua".to_string())).unwrap();
+        let server = Arc::new(
+            PgProtocolServer::with_keep_alive(url, None, 600, 600)
+                .with_jwt_validator(Some(Arc::new(validator)))
+                .with_auth_mode(AuthMode::Jwt, None),
+        );
+        let (addr, _server_task) = spawn_server(server.clone()).await;
+
+        let mut stream = send_startup(addr, "ignored").await;
+        read_until(&mut stream, b'R').await;
+        let mut message = vec![b'p'];
+        message.extend_from_slice(&((token.len() + 5) as u32).to_be_bytes());
+        message.extend_from_slice(token.as_bytes());
+        message.push(0);
+        // The PasswordMessage arrives in two TCP segments
+        let (first, second) = message.split_at(1000);
+        stream.write_all(first).await.unwrap();
+        stream.flush().await.unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        stream.write_all(second).await.unwrap();
+
+        let (tag, body) = read_message(&mut stream).await.unwrap();
+        assert_eq!((tag, body), (b'R', 0u32.to_be_bytes().to_vec()), "AuthenticationOk expected");
+        read_until(&mut stream, b'Z').await;
+    }
+
+    #[tokio::test]
+    async fn test_trust_mode_logs_in_with_the_default_user() {
+        let url = spawn_login_only_graphql_server().await;
+        let server = Arc::new(
+            PgProtocolServer::with_keep_alive(url, None, 600, 600)
+                .with_auth_mode(AuthMode::Trust, Some(("grafana".to_string(), "password1".to_string()))),
+        );
+        let (addr, _server_task) = spawn_server(server.clone()).await;
+
+        // No password is requested, the first authentication message is AuthenticationOk
+        let mut client = send_startup(addr, "anyone").await;
+        let (tag, body) = read_message(&mut client).await.unwrap();
+        assert_eq!((tag, body), (b'R', 0u32.to_be_bytes().to_vec()), "AuthenticationOk expected");
+        read_until(&mut client, b'Z').await;
+        send_query(&mut client, "SELECT 42").await;
+        read_until(&mut client, b'D').await;
+
+        // The startup user is ignored, the session belongs to --default-user
+        let connections = server.session_manager.get_connections().await;
+        assert_eq!(connections.iter().map(|conn| conn.username.as_deref()).collect::<Vec<_>>(), vec![Some("grafana")]);
+    }
+
+    #[tokio::test]
+    async fn test_password_mode_requests_a_cleartext_password() {
+        let url = spawn_login_only_graphql_server().await;
+        let server = Arc::new(PgProtocolServer::with_keep_alive(url, None, 600, 600).with_auth_mode(AuthMode::Password, None));
+        let (addr, _server_task) = spawn_server(server).await;
+
+        let mut stream = send_startup(addr, "grafana").await;
+        let (tag, body) = read_message(&mut stream).await.unwrap();
+        assert_eq!((tag, body), (b'R', 3u32.to_be_bytes().to_vec()), "AuthenticationCleartextPassword expected");
+
+        let mut client = connect_client(addr, "grafana").await;
+        send_query(&mut client, "SELECT 42").await;
+        read_until(&mut client, b'D').await;
+    }
+
+    #[tokio::test]
+    async fn test_listen_notifies_tag_value_changes_until_unlisten() {
+        // Every poll sees a new value
+        let polls = Arc::new(std::sync::atomic::AtomicU32::new(0));
+        let counter = polls.clone();
+        let url = spawn_graphql_server(move |request| {
+            request.body.contains("tagValues").then(|| {
+                let value = counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                format!(
+                    r#"{{"data":{{"tagValues":[{{"name":"Tag_1","value":{{"value":{},"timestamp":"2025-01-01T10:00:00.000Z","quality":null}},"error":null}}]}}}}"#,
+                    value
+                )
+            })
+        })
+        .await;
+        let poll_interval = Duration::from_millis(500);
+        let server = Arc::new(PgProtocolServer::with_keep_alive(url, None, 600, 600).with_listen_poll_interval(poll_interval));
+        let (addr, _server_task) = spawn_server(server).await;
+        let mut client = connect_client(addr, "grafana").await;
+
+        send_query(&mut client, r#"LISTEN "Tag_1""#).await;
+        assert_eq!(read_until(&mut client, b'C').await, b"LISTEN\0");
+        read_until(&mut client, b'Z').await;
+
+        let notification = tokio::time::timeout(poll_interval * 2, read_until(&mut client, b'A'))
+            .await
+            .expect("notification within two poll intervals");
+        let fields: Vec<&[u8]> = notification[4..].split(|&b| b == 0).collect();
+        assert_eq!(fields[0], b"Tag_1");
+        let payload: serde_json::Value = serde_json::from_slice(fields[1]).unwrap();
+        assert_eq!(payload["tag_name"], "Tag_1");
+        assert_eq!(payload["timestamp"], "2025-01-01T10:00:00.000Z");
+        assert!(payload["value"].as_u64().unwrap() >= 1, "{}", payload);
+
+        send_query(&mut client, r#"UNLISTEN "Tag_1""#).await;
+        assert_eq!(read_until(&mut client, b'C').await, b"UNLISTEN\0");
+        read_until(&mut client, b'Z').await;
+        // A notification queued before UNLISTEN may still arrive, nothing after it
+        while tokio::time::timeout(Duration::from_millis(100), read_message(&mut client)).await.is_ok() {}
+        let polls_after_unlisten = polls.load(std::sync::atomic::Ordering::SeqCst);
+        assert!(tokio::time::timeout(poll_interval * 3, read_message(&mut client)).await.is_err());
+        assert_eq!(polls.load(std::sync::atomic::Ordering::SeqCst), polls_after_unlisten);
+    }
+
+    #[tokio::test]
+    async fn test_received_queries_are_appended_to_the_sql_log_file() {
+        let path = std::env::temp_dir().join(format!("sql_log_{}.log", uuid::Uuid::new_v4()));
+        let sql_log = crate::sql_log::SqlLog::open(&path, 0).await.unwrap();
+        let url = spawn_login_only_graphql_server().await;
+        let server = Arc::new(PgProtocolServer::with_keep_alive(url, None, 600, 600).with_sql_log(Some(Arc::new(sql_log))));
+        let (addr, _server_task) = spawn_server(server.clone()).await;
+        let mut client = connect_client(addr, "grafana").await;
+        let connection_id = server.session_manager.get_connections().await[0].connection_id;
+
+        let started = chrono::Utc::now();
+        for sql in ["SELECT 42", "SET password = 's3cret'", "SELECT 'a\tb'"] {
+            send_query(&mut client, sql).await;
+            read_until(&mut client, b'Z').await;
+        }
+
+        let log = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let lines: Vec<Vec<&str>> = log.lines().map(|line| line.split('\t').collect()).collect();
+        assert_eq!(lines.iter().map(|fields| fields[3]).collect::<Vec<_>>(), vec!["SELECT 42", "SET password = '***'", "SELECT 'a b'"]);
+        for fields in &lines {
+            assert_eq!(fields.len(), 4, "{:?}", fields);
+            let timestamp = chrono::DateTime::parse_from_rfc3339(fields[0]).unwrap();
+            assert!(timestamp >= started - chrono::Duration::milliseconds(1) && timestamp <= chrono::Utc::now(), "{}", fields[0]);
+            assert_eq!(fields[1], connection_id.to_string());
+            assert_eq!(fields[2], "grafana");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_row_description_reports_column_types() {
+        let url = spawn_graphql_server(|request| {
+            request.body.contains("tagValues").then(|| {
+                r#"{"data":{"tagValues":[
+                    {"name":"Tag_1","value":{"value":1.5,"timestamp":"2025-01-01T10:00:00.000Z","quality":{"quality":"GOOD"}},"error":null}
+                ]}}"#
+                    .to_string()
+            })
+        })
+        .await;
+        let server = Arc::new(PgProtocolServer::with_keep_alive(url, None, 600, 600));
+        let (addr, _server_task) = spawn_server(server).await;
+        let mut client = connect_client(addr, "grafana").await;
+
+        send_query(&mut client, "SELECT tag_name, numeric_value, timestamp FROM tagvalues WHERE tag_name = 'Tag_1'").await;
+        let body = read_until(&mut client, b'T').await;
+        let mut fields = Vec::new();
+        let mut pos = 2;
+        for _ in 0..u16::from_be_bytes([body[0], body[1]]) {
+            let name_end = pos + body[pos..].iter().position(|&b| b == 0).unwrap();
+            let name = String::from_utf8_lossy(&body[pos..name_end]).to_string();
+            let attrs = &body[name_end + 1..];
+            let type_oid = u32::from_be_bytes([attrs[6], attrs[7], attrs[8], attrs[9]]);
+            let type_size = i16::from_be_bytes([attrs[10], attrs[11]]);
+            fields.push((name, type_oid, type_size));
+            pos = name_end + 1 + 18;
+        }
+        assert_eq!(
+            fields,
+            vec![
+                ("tag_name".to_string(), 25, -1),
+                ("numeric_value".to_string(), 701, 8),
+                ("timestamp".to_string(), 1114, -1),
+            ]
+        );
+        assert_eq!(read_until(&mut client, b'C').await, b"SELECT 1\0");
+    }
+
+    #[tokio::test]
+    async fn test_copy_to_stdout_streams_csv_rows() {
+        let url = spawn_graphql_server(|request| {
+            request.body.contains("tagValues").then(|| {
+                r#"{"data":{"tagValues":[
+                    {"name":"Tag_1","value":{"value":1.5,"timestamp":"2025-01-01T10:00:00.000Z","quality":{"quality":"GOOD"}},"error":null},
+                    {"name":"Tag,2","value":{"value":"say \"hi\"","timestamp":"2025-01-01T10:00:00.000Z","quality":null},"error":null}
+                ]}}"#
+                    .to_string()
+            })
+        })
+        .await;
+        let server = Arc::new(PgProtocolServer::with_keep_alive(url, None, 600, 600));
+        let (addr, _server_task) = spawn_server(server).await;
+        let mut client = connect_client(addr, "grafana").await;
+
+        send_query(
+            &mut client,
+            "COPY (SELECT tag_name, numeric_value, string_value, quality FROM tagvalues \
+             WHERE tag_name IN ('Tag_1', 'Tag,2') ORDER BY tag_name) TO STDOUT WITH (FORMAT CSV, HEADER)",
+        )
+        .await;
+        let (tag, copy_out) = read_message(&mut client).await.unwrap();
+        assert_eq!(tag, b'H', "CopyOutResponse comes first");
+        assert_eq!(copy_out, [0, 0, 4, 0, 0, 0, 0, 0, 0, 0, 0]);
+        let mut csv = Vec::new();
+        loop {
+            match read_message(&mut client).await.unwrap() {
+                (b'd', data) => csv.extend_from_slice(&data),
+                (b'c', _) => break,
+                (other, _) => panic!("Unexpected message {}", other as char),
+            }
+        }
+        assert_eq!(read_until(&mut client, b'C').await, b"COPY 2\0");
+        read_until(&mut client, b'Z').await;
+
+        assert_eq!(
+            String::from_utf8(csv.clone()).unwrap(),
+            concat!(
+                "tag_name,numeric_value,string_value,quality\n",
+                "\"Tag,2\",\\N,\"say \"\"hi\"\"\",\\N\n",
+                "Tag_1,1.5,\\N,GOOD\n",
+            )
+        );
+
+        // The output reads back into a RecordBatch with the same rows
+        let schema = Arc::new(arrow::datatypes::Schema::new(vec![
+            arrow::datatypes::Field::new("tag_name", arrow::datatypes::DataType::Utf8, false),
+            arrow::datatypes::Field::new("numeric_value", arrow::datatypes::DataType::Float64, true),
+            arrow::datatypes::Field::new("string_value", arrow::datatypes::DataType::Utf8, true),
+            arrow::datatypes::Field::new("quality", arrow::datatypes::DataType::Utf8, true),
+        ]));
+        let mut reader = arrow::csv::ReaderBuilder::new(schema)
+            .with_header(true)
+            .with_null_regex(regex::Regex::new(r"^\\N$").unwrap())
+            .build(std::io::Cursor::new(csv))
+            .unwrap();
+        let batch = reader.next().unwrap().unwrap();
+        assert_eq!(batch.num_rows(), 2);
+        let tag_names = batch.column(0).as_any().downcast_ref::<arrow::array::StringArray>().unwrap();
+        assert_eq!(tag_names.value(0), "Tag,2");
+        let strings = batch.column(2).as_any().downcast_ref::<arrow::array::StringArray>().unwrap();
+        assert_eq!(strings.value(0), "say \"hi\"");
+        assert!(arrow::array::Array::is_null(strings, 1));
+        let numbers = batch.column(1).as_any().downcast_ref::<arrow::array::Float64Array>().unwrap();
+        assert_eq!(numbers.value(1), 1.5);
+
+        // Tables can be copied directly, in the text format by default
+        send_query(&mut client, "COPY pg_namespace (oid, nspname) TO STDOUT").await;
+        assert_eq!(read_until(&mut client, b'd').await, b"11\tpg_catalog\n");
+        assert_eq!(read_until(&mut client, b'd').await, b"2200\tpublic\n");
+        assert_eq!(read_until(&mut client, b'C').await, b"COPY 2\0");
+        read_until(&mut client, b'Z').await;
+    }
+
+    #[test]
+    fn test_nan_and_infinity_in_data_rows() {
+        let schema = Arc::new(arrow::datatypes::Schema::new(vec![
+            arrow::datatypes::Field::new("tag_name", arrow::datatypes::DataType::Utf8, false),
+            arrow::datatypes::Field::new("numeric_value", arrow::datatypes::DataType::Float64, true),
+        ]));
+        let batch = arrow::record_batch::RecordBatch::try_new(
+            schema,
+            vec![
+                Arc::new(arrow::array::StringArray::from(vec!["Sensor_1", "Sensor_2", "Sensor_3"])),
+                Arc::new(arrow::array::Float64Array::from(vec![f64::NAN, f64::INFINITY, f64::NEG_INFINITY])),
+            ],
+        )
+        .unwrap();
+        let data_rows = |batch: arrow::record_batch::RecordBatch| {
+            let result = crate::query_handler::QueryResult::from_record_batches(vec![batch]).unwrap();
+            let response = response::format_query_result_as_postgres_result(&result);
+            // DataRow: 'D', length, field count, then tag_name (length + 8 bytes) and numeric_value
+            let mut values = Vec::new();
+            let mut pos = 0;
+            while let Some(offset) = response[pos..].iter().position(|&b| b == b'D') {
+                let start = pos + offset + 1 + 4 + 2 + 4 + 8;
+                let length = i32::from_be_bytes(response[start..start + 4].try_into().unwrap());
+                let value = (length >= 0).then(|| response[start + 4..start + 4 + length as usize].to_vec());
+                values.push((length, value));
+                pos = start + 4 + length.max(0) as usize;
+            }
+            values
+        };
+
+        let values = data_rows(batch.clone());
+        assert_eq!(values[0], (3, Some(vec![0x4E, 0x61, 0x4E])));
+        assert_eq!(values[1], (8, Some(b"Infinity".to_vec())));
+        assert_eq!(values[2], (9, Some(b"-Infinity".to_vec())));
+
+        crate::NAN_AS_NULL.store(true, std::sync::atomic::Ordering::Relaxed);
+        let values = data_rows(batch);
+        crate::NAN_AS_NULL.store(false, std::sync::atomic::Ordering::Relaxed);
+        assert_eq!(values, vec![(-1, None), (-1, None), (-1, None)]);
+    }
+}
@@ -0,0 +1,172 @@
+use crate::auth::SessionManager;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio::time::{interval, MissedTickBehavior};
+use tracing::{debug, info, warn};
+
+use super::response::create_notification_response;
+
+#[derive(Debug, Clone, PartialEq)]
+pub(super) enum ListenCommand {
+    Listen(String),
+    Unlisten(Option<String>), // None for UNLISTEN *
+}
+
+/// Parse LISTEN channel / UNLISTEN channel / UNLISTEN *. Like PostgreSQL, unquoted channel
+/// names are folded to lower case, so tag names with upper case letters need double quotes.
+pub(super) fn parse_listen_command(query: &str) -> Option<ListenCommand> {
+    let query = query.trim().trim_end_matches(';').trim();
+    let (keyword, channel) = query.split_once(char::is_whitespace)?;
+    let channel = channel.trim();
+    let channel_name = || {
+        if channel.len() >= 2 && channel.starts_with('"') && channel.ends_with('"') {
+            Some(channel[1..channel.len() - 1].replace("\"\"", "\""))
+        } else if !channel.is_empty() && channel.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '$') {
+            Some(channel.to_lowercase())
+        } else {
+            None
+        }
+    };
+    match keyword.to_uppercase().as_str() {
+        "LISTEN" => channel_name().map(ListenCommand::Listen),
+        "UNLISTEN" if channel == "*" => Some(ListenCommand::Unlisten(None)),
+        "UNLISTEN" => channel_name().map(|channel| ListenCommand::Unlisten(Some(channel))),
+        _ => None,
+    }
+}
+
+/// Channels a connection listens on. Each channel is a tag name whose value is polled by its own
+/// task; changes are sent as NotificationResponse through the channel returned by `new`, which
+/// the connection loop writes to the client between queries.
+#[derive(Debug)]
+pub(super) struct Listeners {
+    tasks: HashMap<String, JoinHandle<()>>,
+    sender: mpsc::UnboundedSender<Vec<u8>>,
+}
+
+impl Listeners {
+    pub(super) fn new() -> (Self, mpsc::UnboundedReceiver<Vec<u8>>) {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        (Self { tasks: HashMap::new(), sender }, receiver)
+    }
+
+    /// Run LISTEN / UNLISTEN for the connection `connection_id` and return its command tag
+    pub(super) fn execute(
+        &mut self,
+        command: ListenCommand,
+        session_id: &str,
+        session_manager: Arc<SessionManager>,
+        connection_id: Option<u32>,
+    ) -> &'static str {
+        match command {
+            ListenCommand::Listen(channel) => {
+                // Listening twice on a channel is a no-op, like in PostgreSQL
+                if !self.tasks.contains_key(&channel) {
+                    info!("👂 LISTEN {} (polling every {:?})", channel, session_manager.listen_poll_interval());
+                    let task = tokio::spawn(poll_channel(
+                        channel.clone(),
+                        session_id.to_string(),
+                        session_manager,
+                        connection_id.unwrap_or(0),
+                        self.sender.clone(),
+                    ));
+                    self.tasks.insert(channel, task);
+                }
+                "LISTEN"
+            }
+            ListenCommand::Unlisten(Some(channel)) => {
+                if let Some(task) = self.tasks.remove(&channel) {
+                    info!("🙉 UNLISTEN {}", channel);
+                    task.abort();
+                }
+                "UNLISTEN"
+            }
+            ListenCommand::Unlisten(None) => {
+                debug!("🙉 UNLISTEN * stops {} channels", self.tasks.len());
+                for (_, task) in self.tasks.drain() {
+                    task.abort();
+                }
+                "UNLISTEN"
+            }
+        }
+    }
+}
+
+impl Drop for Listeners {
+    fn drop(&mut self) {
+        for task in self.tasks.values() {
+            task.abort();
+        }
+    }
+}
+
+/// Poll the value of the tag `channel` and send a notification whenever it differs from the
+/// previous poll. The first poll only records the value. Ends with the session.
+async fn poll_channel(
+    channel: String,
+    session_id: String,
+    session_manager: Arc<SessionManager>,
+    process_id: u32,
+    sender: mpsc::UnboundedSender<Vec<u8>>,
+) {
+    let mut timer = interval(session_manager.listen_poll_interval());
+    timer.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    let mut last_value = None;
+
+    loop {
+        timer.tick().await;
+        // Look the session up on every poll, the extension task replaces its token
+        let Some(session) = session_manager.get_session(&session_id).await else {
+            debug!("👂 Session {} ended, stopping LISTEN {}", session_id, channel);
+            return;
+        };
+        let request = session.client.get_tag_values(&session.token, vec![channel.clone()], false);
+        let results = match session_manager.circuit_breaker().call(session_manager.metrics().observe_graphql(request)).await {
+            Ok(results) => results,
+            Err(e) => {
+                warn!("⚠️  Polling tag {} for LISTEN failed: {}", channel, e);
+                continue;
+            }
+        };
+        let Some(value) = results.into_iter().find(|result| result.name == channel).and_then(|result| result.value) else {
+            debug!("👂 No value for tag {}", channel);
+            continue;
+        };
+
+        let current = value.value.clone().unwrap_or(serde_json::Value::Null);
+        if last_value.as_ref().is_some_and(|last| *last != current) {
+            let payload = serde_json::json!({"tag_name": channel, "value": current, "timestamp": value.timestamp});
+            debug!("🔔 NOTIFY {}: {}", channel, payload);
+            if sender.send(create_notification_response(process_id, &channel, &payload.to_string())).is_err() {
+                return; // Connection closed
+            }
+        }
+        last_value = Some(current);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_listen_command() {
+        assert_eq!(parse_listen_command("LISTEN tagvalues;"), Some(ListenCommand::Listen("tagvalues".to_string())));
+        assert_eq!(parse_listen_command("listen Tag_1"), Some(ListenCommand::Listen("tag_1".to_string())));
+        assert_eq!(
+            parse_listen_command(r#"LISTEN "HMI_Tag_1""#),
+            Some(ListenCommand::Listen("HMI_Tag_1".to_string()))
+        );
+        assert_eq!(
+            parse_listen_command(r#"UNLISTEN "HMI_Tag_1""#),
+            Some(ListenCommand::Unlisten(Some("HMI_Tag_1".to_string())))
+        );
+        assert_eq!(parse_listen_command("UNLISTEN *"), Some(ListenCommand::Unlisten(None)));
+        assert_eq!(parse_listen_command("LISTEN *"), None);
+        assert_eq!(parse_listen_command("LISTEN"), None);
+        assert_eq!(parse_listen_command("LISTEN a b"), None);
+        assert_eq!(parse_listen_command("NOTIFY tagvalues"), None);
+    }
+}
@@ -1,7 +1,9 @@
 use crate::auth::SessionManager;
-use anyhow::Result;
+use crate::query_handler::{QueryResult, QueryValue};
+use anyhow::{anyhow, Result};
+use std::collections::HashMap;
 use std::sync::Arc;
-use tracing::{debug, info};
+use tracing::{debug, info, warn};
 
 // Helper function to create a simple single-row QueryResult
 #[allow(dead_code)]
@@ -32,26 +34,13 @@ fn create_command_complete_wire_response(tag: &str) -> Vec<u8> {
     response
 }
 
-#[allow(dead_code)]
-fn create_command_complete_response_text(command_tag: &str) -> String {
-    // For Simple Query protocol, we return a text response that will be formatted later
-    // The actual PostgreSQL CommandComplete message will be created by format_as_postgres_result
-    format!("COMMAND_COMPLETE:{}", command_tag)
-}
-
-#[allow(dead_code)]
-fn create_empty_query_response() -> String {
-    // Return a special marker for empty query that will be handled in the response formatting
-    "EMPTY_QUERY_RESPONSE".to_string()
-}
-
 #[allow(dead_code)]
 pub(super) async fn handle_extended_query(
     query: &str,
     session: &crate::auth::AuthenticatedSession,
     session_manager: Arc<SessionManager>,
 ) -> Result<Vec<u8>> {
-    handle_extended_query_with_connection(query, session, session_manager, None).await
+    handle_extended_query_with_connection(query, session, session_manager, None, &[]).await
 }
 
 pub(super) async fn handle_extended_query_with_connection(
@@ -59,6 +48,7 @@ pub(super) async fn handle_extended_query_with_connection(
     session: &crate::auth::AuthenticatedSession,
     session_manager: Arc<SessionManager>,
     connection_id: Option<u32>,
+    result_formats: &[super::ColumnFormat],
 ) -> Result<Vec<u8>> {
     debug!("🔍 Processing extended query: {}", query.trim());
 
@@ -66,18 +56,23 @@ pub(super) async fn handle_extended_query_with_connection(
     let cleaned_query = query.trim().trim_end_matches(';').trim();
     if cleaned_query.is_empty() {
         debug!("⚪ Empty extended query received, returning CommandComplete");
-        return Ok(create_command_complete_wire_response(""));
+        return Ok(super::response::create_command_complete_response(""));
     }
 
     let trimmed_query = query.trim().to_uppercase();
 
-    // Handle transaction control statements that can be safely acknowledged
+    if is_replication_command(&trimmed_query) {
+        return Err(replication_not_supported(query));
+    }
+
+    // Handle transaction control statements that can be safely acknowledged.
+    // In the Extended Query Protocol, ReadyForQuery is only sent by the Sync handler.
     if is_transaction_control_statement(&trimmed_query) {
         debug!(
             "📋 Transaction control statement (acknowledged): {}",
             query.trim()
         );
-        return Ok(create_command_complete_wire_response(
+        return Ok(super::response::create_command_complete_response(
             &get_transaction_command_tag(&trimmed_query),
         ));
     }
@@ -92,20 +87,32 @@ pub(super) async fn handle_extended_query_with_connection(
                 "🔧 SET statement detected, routing to QueryHandler: {}",
                 query.trim()
             );
-            let result = crate::query_handler::QueryHandler::execute_query_with_connection(query, session, session_manager.clone(), connection_id).await?;
-            return Ok(super::response::format_query_result_as_extended_query_result(&result));
+            crate::query_handler::QueryHandler::execute_query_with_connection(query, session, session_manager.clone(), connection_id).await?;
+            // SET produces no RowDescription/DataRows, only CommandComplete("SET")
+            return Ok(super::response::create_command_complete_response("SET"));
+        }
+
+        if is_show_statement(&trimmed_query) {
+            let session_variables = connection_session_variables(&session_manager, connection_id).await;
+            let result = execute_show_statement(query, &session_variables)?;
+            return Ok(super::response::format_query_result_as_extended_query_result(&result, result_formats));
+        }
+
+        if is_reset_statement(&trimmed_query) {
+            execute_reset_statement(query, &session_manager, connection_id).await;
+            return Ok(super::response::create_command_complete_response("RESET"));
         }
 
 
         // For other utility statements, just acknowledge
-        return Ok(create_command_complete_wire_response(
+        return Ok(super::response::create_command_complete_response(
             &get_utility_command_tag(&trimmed_query),
         ));
     }
 
     // Use the new query handler for all SQL processing
     let result = crate::query_handler::QueryHandler::execute_query_with_connection(query, session, session_manager.clone(), connection_id).await?;
-    Ok(super::response::format_query_result_as_extended_query_result(&result))
+    Ok(super::response::format_query_result_as_extended_query_result(&result, result_formats))
 }
 
 pub(super) async fn handle_simple_query(
@@ -140,6 +147,10 @@ pub(super) async fn handle_simple_query_with_connection(
 
     let trimmed_query = query.trim().to_uppercase();
 
+    if is_replication_command(&trimmed_query) {
+        return Err(replication_not_supported(query));
+    }
+
     // Handle transaction control statements that can be safely acknowledged
     if is_transaction_control_statement(&trimmed_query) {
         debug!(
@@ -161,10 +172,21 @@ pub(super) async fn handle_simple_query_with_connection(
                 "🔧 SET statement detected, routing to QueryHandler: {}",
                 query.trim()
             );
-            let result = crate::query_handler::QueryHandler::execute_query_with_connection(query, session, session_manager.clone(), connection_id).await?;
+            crate::query_handler::QueryHandler::execute_query_with_connection(query, session, session_manager.clone(), connection_id).await?;
+            return Ok(create_command_complete_wire_response("SET"));
+        }
+
+        if is_show_statement(&trimmed_query) {
+            let session_variables = connection_session_variables(&session_manager, connection_id).await;
+            let result = execute_show_statement(query, &session_variables)?;
             return Ok(super::response::format_query_result_as_postgres_result(&result));
         }
 
+        if is_reset_statement(&trimmed_query) {
+            execute_reset_statement(query, &session_manager, connection_id).await;
+            return Ok(create_command_complete_wire_response("RESET"));
+        }
+
 
         // For other utility statements, just acknowledge
         return Ok(create_command_complete_wire_response(
@@ -178,6 +200,23 @@ pub(super) async fn handle_simple_query_with_connection(
     Ok(super::response::format_query_result_as_postgres_result(&result))
 }
 
+/// Message of the 0A000 error for replication connections and replication commands
+pub(super) const REPLICATION_NOT_SUPPORTED: &str = "logical replication is not supported by this server";
+
+/// Replication protocol commands and START TRANSACTION ... REPLICATION; unlike the other
+/// utility statements they are rejected instead of acknowledged
+pub(super) fn is_replication_command(query: &str) -> bool {
+    let replication_commands = ["START_REPLICATION", "CREATE_REPLICATION_SLOT", "DROP_REPLICATION_SLOT", "IDENTIFY_SYSTEM"];
+    replication_commands.iter().any(|command| query.starts_with(command))
+        || (query.starts_with("START ") && query.split(|c: char| !c.is_ascii_alphanumeric() && c != '_').any(|word| word == "REPLICATION"))
+}
+
+fn replication_not_supported(query: &str) -> anyhow::Error {
+    warn!("🚫 Rejecting replication command: {}", query.trim());
+    info!("💡 Query the WinCC tables with plain SQL (e.g. SELECT ... FROM tagvaluechanges) instead of logical replication");
+    crate::query_handler::SqlStateError::new("0A000", REPLICATION_NOT_SUPPORTED).into()
+}
+
 pub(super) fn is_transaction_control_statement(query: &str) -> bool {
     // Transaction control statements that can be safely ignored
     let transaction_keywords = [
@@ -230,6 +269,9 @@ pub(super) fn is_utility_statement(query: &str) -> bool {
         "DROP ROLE",
         "GRANT ",
         "REVOKE ",
+        // Replication protocol (rejected, see is_replication_command)
+        "START_REPLICATION",
+        "CREATE_REPLICATION_SLOT",
     ];
 
     for pattern in &utility_patterns {
@@ -241,6 +283,133 @@ pub(super) fn is_utility_statement(query: &str) -> bool {
     false
 }
 
+/// UPDATE writes tag values, so it must only run on Execute and never to describe its result
+pub(super) fn is_write_statement(query: &str) -> bool {
+    query.starts_with("UPDATE ")
+}
+
+/// COPY ... TO STDOUT answers with CopyData messages, so Describe reports no row description
+pub(super) fn is_copy_statement(query: &str) -> bool {
+    query.starts_with("COPY ")
+}
+
+/// DECLARE runs its query and CLOSE drops a cursor, so both only run on Execute
+pub(super) fn is_cursor_statement(query: &str) -> bool {
+    query.starts_with("DECLARE ") || query.starts_with("CLOSE ")
+}
+
+/// FETCH advances its cursor, so Describe reports the cursor's columns without fetching
+pub(super) fn is_fetch_statement(query: &str) -> bool {
+    query.starts_with("FETCH ")
+}
+
+/// tagvaluechanges remembers the values it returns per connection; a Describe runs the query
+/// without the connection so only Execute advances the change cache
+pub(super) fn is_change_detection_query(query: &str) -> bool {
+    query.contains("TAGVALUECHANGES")
+}
+
+pub(super) fn is_show_statement(query: &str) -> bool {
+    query.starts_with("SHOW ")
+}
+
+pub(super) fn is_reset_statement(query: &str) -> bool {
+    query.starts_with("RESET ")
+}
+
+/// `RESET <name>` drops the value assigned with SET on this connection, so SHOW reports the
+/// server setting again (e.g. `"$user", public` for search_path); `RESET ALL` drops all of them
+pub(super) async fn execute_reset_statement(query: &str, session_manager: &SessionManager, connection_id: Option<u32>) {
+    let name = query
+        .trim()
+        .trim_end_matches(';')
+        .get("RESET".len()..)
+        .unwrap_or_default()
+        .trim()
+        .trim_matches('"');
+    debug!("🔧 RESET {}", name);
+
+    let Some(conn_id) = connection_id else {
+        return;
+    };
+    let name = (!name.eq_ignore_ascii_case("ALL")).then_some(name);
+    session_manager.reset_session_variable(conn_id, name).await;
+}
+
+/// Variables assigned with SET on this connection, empty when the connection is unknown
+pub(super) async fn connection_session_variables(
+    session_manager: &SessionManager,
+    connection_id: Option<u32>,
+) -> HashMap<String, String> {
+    match connection_id {
+        Some(conn_id) => session_manager.session_variables(conn_id).await,
+        None => HashMap::new(),
+    }
+}
+
+/// Answer `SHOW <name>` and `SHOW ALL` from the connection's SET variables, falling back to
+/// the server settings cache
+pub(super) fn execute_show_statement(query: &str, session_variables: &HashMap<String, String>) -> Result<QueryResult> {
+    let statement = query.trim().trim_end_matches(';').trim();
+    let name = statement
+        .get("SHOW".len()..)
+        .unwrap_or_default()
+        .trim()
+        .trim_matches('"');
+
+    // Multi-word forms accepted by PostgreSQL for a few settings
+    let name = match name.to_uppercase().as_str() {
+        "TIME ZONE" => "TimeZone",
+        "TRANSACTION ISOLATION LEVEL" => "transaction_isolation",
+        "SESSION AUTHORIZATION" => "session_authorization",
+        _ => name,
+    };
+    debug!("🔧 SHOW {}", name);
+
+    if name.eq_ignore_ascii_case("ALL") {
+        let mut result = QueryResult::new(
+            vec!["name".to_string(), "setting".to_string(), "description".to_string()],
+            vec![25, 25, 25],
+        );
+        for setting in super::settings::GLOBAL_SETTINGS.iter() {
+            let value = session_variables
+                .get(&setting.name.to_lowercase())
+                .map(String::as_str)
+                .unwrap_or(setting.value);
+            result.add_row(vec![
+                QueryValue::Text(setting.name.to_string()),
+                QueryValue::Text(value.to_string()),
+                QueryValue::Text(setting.description.to_string()),
+            ]);
+        }
+        // Custom variables such as grafana.user that are not server settings
+        let mut custom: Vec<_> = session_variables
+            .iter()
+            .filter(|(name, _)| super::settings::get_postgresql_setting(name).is_none())
+            .collect();
+        custom.sort();
+        for (name, value) in custom {
+            result.add_row(vec![
+                QueryValue::Text(name.clone()),
+                QueryValue::Text(value.clone()),
+                QueryValue::Text(String::new()),
+            ]);
+        }
+        return Ok(result);
+    }
+
+    let setting = super::settings::get_postgresql_setting(name);
+    let value = match (session_variables.get(&name.to_lowercase()), setting) {
+        (Some(value), _) => value.clone(),
+        (None, Some(setting)) => setting.value.to_string(),
+        (None, None) => return Err(anyhow!("unrecognized configuration parameter \"{}\"", name)),
+    };
+    let column = setting.map(|setting| setting.name.to_string()).unwrap_or_else(|| name.to_lowercase());
+    let mut result = QueryResult::new(vec![column], vec![25]);
+    result.add_row(vec![QueryValue::Text(value)]);
+    Ok(result)
+}
+
 fn get_transaction_command_tag(query: &str) -> String {
     if query.starts_with("BEGIN") || query.starts_with("START TRANSACTION") {
         "BEGIN".to_string()
@@ -296,3 +465,25 @@ fn get_utility_command_tag(query: &str) -> String {
         "OK".to_string()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_show_server_version() {
+        let result = execute_show_statement("SHOW server_version", &HashMap::new()).unwrap();
+        assert_eq!(result.columns, vec!["server_version"]);
+        assert!(matches!(&result.rows[..], [row] if matches!(&row[..], [QueryValue::Text(v)] if v == "15.0")));
+
+        // Setting names are case-insensitive and may be quoted
+        let result = execute_show_statement("show \"TIMEZONE\";", &HashMap::new()).unwrap();
+        assert!(matches!(&result.rows[0][0], QueryValue::Text(v) if v == "UTC"));
+
+        let result = execute_show_statement("SHOW ALL", &HashMap::new()).unwrap();
+        assert_eq!(result.columns, vec!["name", "setting", "description"]);
+        assert!(result.rows.len() > 1);
+
+        assert!(execute_show_statement("SHOW no_such_setting", &HashMap::new()).is_err());
+    }
+}
@@ -1,17 +1,39 @@
+use crate::tables::{CopyFormat, CopyOptions};
+
 pub(super) fn create_postgres_error_response(code: &str, message: &str) -> Vec<u8> {
+    create_postgres_error_response_with_severity(b'E', "ERROR", code, message, &[])
+}
+
+/// ErrorResponse with optional fields after the message, e.g. (b'P', position) or (b'D', detail)
+pub(super) fn create_postgres_error_response_with_fields(code: &str, message: &str, extra_fields: &[(u8, String)]) -> Vec<u8> {
+    create_postgres_error_response_with_severity(b'E', "ERROR", code, message, extra_fields)
+}
+
+/// ErrorResponse with severity FATAL, sent right before the server closes the connection
+pub(super) fn create_postgres_fatal_response(code: &str, message: &str) -> Vec<u8> {
+    create_postgres_error_response_with_severity(b'E', "FATAL", code, message, &[])
+}
+
+/// NoticeResponse with severity WARNING; the statement still completes
+pub(super) fn create_postgres_warning_notice(message: &str) -> Vec<u8> {
+    create_postgres_error_response_with_severity(b'N', "WARNING", "01000", message, &[])
+}
+
+fn create_postgres_error_response_with_severity(message_type: u8, severity: &str, code: &str, message: &str, extra_fields: &[(u8, String)]) -> Vec<u8> {
     let mut response = Vec::new();
 
-    // Error message format:
+    // Error message format (NoticeResponse uses the same fields):
     // 'E' + length(4 bytes) + severity + code + message + null terminators
 
-    response.push(b'E'); // 'E' = ErrorResponse message type
+    response.push(message_type); // 'E' = ErrorResponse, 'N' = NoticeResponse
 
     // Build the error fields
     let mut fields = Vec::new();
 
     // Severity
     fields.push(b'S');
-    fields.extend_from_slice(b"ERROR\0");
+    fields.extend_from_slice(severity.as_bytes());
+    fields.push(0);
 
     // SQLSTATE code
     fields.push(b'C');
@@ -23,6 +45,13 @@ pub(super) fn create_postgres_error_response(code: &str, message: &str) -> Vec<u
     fields.extend_from_slice(message.as_bytes());
     fields.push(0);
 
+    // Detail, position, where...
+    for (field_type, value) in extra_fields {
+        fields.push(*field_type);
+        fields.extend_from_slice(value.as_bytes());
+        fields.push(0);
+    }
+
     // End of fields
     fields.push(0);
 
@@ -36,7 +65,7 @@ pub(super) fn create_postgres_error_response(code: &str, message: &str) -> Vec<u
     response
 }
 
-pub(super) fn create_postgres_auth_ok_response() -> Vec<u8> {
+pub(super) fn create_postgres_auth_ok_response(process_id: u32, secret_key: u32, parameters: &[(String, String)]) -> Vec<u8> {
     let mut response = Vec::new();
 
     // Authentication OK message
@@ -49,24 +78,11 @@ pub(super) fn create_postgres_auth_ok_response() -> Vec<u8> {
     // Message type 'K' (BackendKeyData) + length (4 bytes) + process_id (4 bytes) + secret_key (4 bytes)
     response.push(b'K'); // 'K' = BackendKeyData message
     response.extend_from_slice(&12u32.to_be_bytes()); // Length: 4 + 4 + 4 = 12
-    response.extend_from_slice(&12345u32.to_be_bytes()); // Dummy process ID
-    response.extend_from_slice(&67890u32.to_be_bytes()); // Dummy secret key
-
-    // Parameter status messages for required parameters
-    let params = [
-        ("server_version", "14.0"),
-        ("server_encoding", "UTF8"),
-        ("client_encoding", "UTF8"),
-        ("application_name", ""),
-        ("is_superuser", "off"),
-        ("session_authorization", "operator"),
-        ("DateStyle", "ISO"),
-        ("TimeZone", "UTC"),
-        ("standard_conforming_strings", "on"),
-        ("integer_datetimes", "on"),
-    ];
-
-    for (name, value) in params {
+    response.extend_from_slice(&process_id.to_be_bytes()); // Connection ID
+    response.extend_from_slice(&secret_key.to_be_bytes()); // Random per-connection key for CancelRequest
+
+    // Parameter status messages for the reported parameters
+    for (name, value) in parameters {
         // Parameter status message: 'S' (ParameterStatus) + length + name + null + value + null
         response.push(b'S'); // 'S' = ParameterStatus message
         let content = format!("{}\0{}\0", name, value);
@@ -83,260 +99,8 @@ pub(super) fn create_postgres_auth_ok_response() -> Vec<u8> {
     response
 }
 
-#[allow(dead_code)]
-pub(super) fn format_as_postgres_result(csv_data: &str) -> Vec<u8> {
-    let mut response = Vec::new();
-
-    // Check if this is a command complete response (for transaction control/utility statements)
-    if csv_data.starts_with("COMMAND_COMPLETE:") {
-        let command_tag = csv_data.strip_prefix("COMMAND_COMPLETE:").unwrap_or("OK");
-
-        // Command complete message: 'C' (CommandComplete) + length + tag
-        response.push(b'C'); // 'C' = CommandComplete message
-        let tag_length = 4 + command_tag.len() + 1; // 4 bytes for length + tag + null terminator
-        response.extend_from_slice(&(tag_length as u32).to_be_bytes());
-        response.extend_from_slice(command_tag.as_bytes());
-        response.push(0); // Null terminator
-
-        // Ready for query message: 'Z' (ReadyForQuery) + length + status
-        response.push(b'Z'); // 'Z' = ReadyForQuery message
-        response.extend_from_slice(&5u32.to_be_bytes()); // Length: 4 + 1 = 5
-        response.push(b'I'); // Status: 'I' = idle (not in transaction)
-
-        return response;
-    }
-
-    // Handle empty query response
-    if csv_data.trim() == "EMPTY_QUERY_RESPONSE" {
-        // For empty queries, send EmptyQueryResponse followed by ReadyForQuery
-        // EmptyQueryResponse message: 'I' (EmptyQueryResponse) + length (4 bytes only)
-        response.push(b'I'); // 'I' = EmptyQueryResponse message
-        response.extend_from_slice(&4u32.to_be_bytes()); // Length: 4 bytes (just the length field)
-
-        // Ready for query message: 'Z' (ReadyForQuery) + length + status
-        response.push(b'Z'); // 'Z' = ReadyForQuery message
-        response.extend_from_slice(&5u32.to_be_bytes()); // Length: 4 + 1 = 5
-        response.push(b'I'); // Status: 'I' = idle (not in transaction)
-
-        return response;
-    }
-
-    let lines: Vec<&str> = csv_data.trim().split('\n').collect();
-    if lines.is_empty() {
-        return response;
-    }
-
-    // Parse CSV header with potential type information, handling quoted fields
-    let headers = parse_csv_line(lines[0]);
-    tracing::debug!("CSV headers parsed: {:?}", headers);
-    let mut column_types: std::collections::HashMap<String, &str> =
-        std::collections::HashMap::new();
-    let mut clean_headers: Vec<String> = Vec::new();
-
-    for header in &headers {
-        if header.contains(':') {
-            // Header format: "column:TYPE"
-            let parts: Vec<&str> = header.splitn(2, ':').collect();
-            if parts.len() == 2 {
-                clean_headers.push(parts[0].to_string());
-                column_types.insert(parts[0].to_string(), parts[1]);
-            } else {
-                clean_headers.push(header.clone());
-            }
-        } else {
-            clean_headers.push(header.clone());
-        }
-    }
-
-    // RowDescription message: 'T' + length + field_count + fields
-    response.push(b'T');
-
-    let mut fields_data = Vec::new();
-    fields_data.extend_from_slice(&(clean_headers.len() as u16).to_be_bytes());
-
-    for (i, header) in clean_headers.iter().enumerate() {
-        fields_data.extend_from_slice(header.as_bytes());
-        fields_data.push(0); // Null terminator for name
-
-        // Add dummy table/column IDs
-        fields_data.extend_from_slice(&0u32.to_be_bytes()); // Table OID
-        fields_data.extend_from_slice(&(i as u16).to_be_bytes()); // Column index
-
-        // Determine data type OID based on column name/type hint
-        let type_oid: u32 = match column_types.get(header.as_str()).unwrap_or(&"TEXT") {
-            &"NUMERIC" => 1700, // NUMERIC
-            &"TIMESTAMP" => 1114, // TIMESTAMP
-            &"TEXT" => 25,      // TEXT
-            _ => 25,            // Default to TEXT
-        };
-
-        fields_data.extend_from_slice(&type_oid.to_be_bytes()); // Data type OID
-
-        // Add type size (-1 for variable size)
-        let type_size: i16 = -1;
-        fields_data.extend_from_slice(&type_size.to_be_bytes());
-
-        // Add type modifier (-1 for default)
-        let type_modifier: i32 = -1;
-        fields_data.extend_from_slice(&type_modifier.to_be_bytes());
-
-        // Add format code (0 for text)
-        let format_code: i16 = 0;
-        fields_data.extend_from_slice(&format_code.to_be_bytes());
-    }
-
-    let length = 4 + fields_data.len();
-    response.extend_from_slice(&(length as u32).to_be_bytes());
-    response.extend_from_slice(&fields_data);
-
-    // DataRow messages: 'D' + length + column_count + columns
-    let mut row_count = 0;
-    for (line_num, line) in lines.iter().skip(1).enumerate() {
-        if line.trim().is_empty() {
-            continue;
-        }
-        
-        // Debug log first data row
-        if line_num == 0 {
-            tracing::debug!("First data row: {}", line);
-        }
-
-        response.push(b'D');
-
-        let values = parse_csv_line(line);
-        
-        // Ensure the number of values matches the number of headers
-        if values.len() != clean_headers.len() {
-            tracing::warn!(
-                "Column count mismatch: headers={}, values={} in line: {}",
-                clean_headers.len(),
-                values.len(),
-                line
-            );
-        }
-        
-        let mut row_data = Vec::new();
-        // IMPORTANT: Always send the number of columns from the header, not the actual values
-        // This ensures consistency with the RowDescription
-        row_data.extend_from_slice(&(clean_headers.len() as u16).to_be_bytes());
-
-        // Process values, padding with NULL if we have fewer values than headers
-        for i in 0..clean_headers.len() {
-            if i < values.len() {
-                let value = &values[i];
-                if value == "NULL" {
-                    row_data.extend_from_slice(&(-1i32).to_be_bytes());
-                } else {
-                    row_data.extend_from_slice(&(value.len() as u32).to_be_bytes());
-                    row_data.extend_from_slice(value.as_bytes());
-                }
-            } else {
-                // Pad with NULL if we don't have enough values
-                row_data.extend_from_slice(&(-1i32).to_be_bytes());
-            }
-        }
-
-        let length = 4 + row_data.len();
-        response.extend_from_slice(&(length as u32).to_be_bytes());
-        response.extend_from_slice(&row_data);
-        row_count += 1;
-    }
-
-    // CommandComplete message: 'C' + length + tag
-    response.push(b'C');
-    let tag = format!("SELECT {}", row_count);
-    let tag_length = 4 + tag.len() + 1; // 4 bytes for length + tag + null terminator
-    response.extend_from_slice(&(tag_length as u32).to_be_bytes());
-    response.extend_from_slice(tag.as_bytes());
-    response.push(0); // Null terminator
-
-    // ReadyForQuery message: 'Z' + length + status
-    response.push(b'Z');
-    response.extend_from_slice(&5u32.to_be_bytes()); // Length: 4 + 1 = 5
-    response.push(b'I'); // Status: 'I' = idle
-
-    response
-}
-
-#[allow(dead_code)]
-pub(super) fn format_as_extended_query_result(csv_data: &str, query_info: &crate::tables::QueryInfo) -> Vec<u8> {
-    let mut response = Vec::new();
-
-    // Handle command complete for non-query statements
-    if csv_data.starts_with("COMMAND_COMPLETE:") {
-        let command_tag = csv_data.strip_prefix("COMMAND_COMPLETE:").unwrap_or("OK");
-        response.extend_from_slice(&create_command_complete_response(command_tag));
-        return response;
-    }
-
-    // Handle empty query response
-    if csv_data.trim() == "EMPTY_QUERY_RESPONSE" {
-        response.extend_from_slice(&create_command_complete_response(""));
-        return response;
-    }
-
-    let lines: Vec<&str> = csv_data.trim().split('\n').collect();
-    if lines.is_empty() {
-        response.extend_from_slice(&create_command_complete_response(""));
-        return response;
-    }
-
-    // --- 1. RowDescription ---
-    response.extend_from_slice(&create_row_description_response(query_info));
-
-    // --- 2. DataRows ---
-    let mut row_count = 0;
-    for line in lines.iter().skip(1) {
-        if line.trim().is_empty() {
-            continue;
-        }
-        response.push(b'D'); // DataRow message type
-        let values = parse_csv_line(line);
-        
-        // Check for column count mismatch
-        if values.len() != query_info.columns.len() {
-            tracing::warn!(
-                "Extended query column count mismatch: expected={}, actual={} in line: {}",
-                query_info.columns.len(),
-                values.len(),
-                line
-            );
-        }
-        
-        let mut row_data = Vec::new();
-        // Always use the column count from query_info for consistency
-        row_data.extend_from_slice(&(query_info.columns.len() as u16).to_be_bytes());
-        
-        // Process values, padding with NULL if needed
-        for i in 0..query_info.columns.len() {
-            if i < values.len() {
-                let value = &values[i];
-                if value == "NULL" {
-                    row_data.extend_from_slice(&(-1i32).to_be_bytes());
-                } else {
-                    row_data.extend_from_slice(&(value.len() as u32).to_be_bytes());
-                    row_data.extend_from_slice(value.as_bytes());
-                }
-            } else {
-                // Pad with NULL if we don't have enough values
-                row_data.extend_from_slice(&(-1i32).to_be_bytes());
-            }
-        }
-        let length = 4 + row_data.len();
-        response.extend_from_slice(&(length as u32).to_be_bytes());
-        response.extend_from_slice(&row_data);
-        row_count += 1;
-    }
-
-    // --- 3. CommandComplete ---
-    let tag = format!("SELECT {}", row_count);
-    response.extend_from_slice(&create_command_complete_response(&tag));
-
-    response
-}
-
 /// Create RowDescription using QueryResult with proper column types (preferred for Extended Query Protocol)
-pub(super) fn create_row_description_response_with_types(query_result: &crate::query_handler::QueryResult) -> Vec<u8> {
+pub(super) fn create_row_description_response_with_types(query_result: &crate::query_handler::QueryResult, result_formats: &[super::ColumnFormat]) -> Vec<u8> {
     let mut response = Vec::new();
     tracing::debug!("🚀 create_row_description_response_with_types() CALLED with {} columns", query_result.columns.len());
     tracing::debug!("🚀 Column types (OIDs): {:?}", query_result.column_types);
@@ -364,15 +128,15 @@ pub(super) fn create_row_description_response_with_types(query_result: &crate::q
         fields_data.extend_from_slice(&type_oid.to_be_bytes()); // Data type OID
 
         // Add type size (-1 for variable size)
-        let type_size: i16 = -1;
+        let type_size = postgres_type_size(type_oid);
         fields_data.extend_from_slice(&type_size.to_be_bytes());
 
-        // Add type modifier (-1 for default)
-        let type_modifier: i32 = -1;
+        // Add type modifier (-1 for default, precision and scale for numeric)
+        let type_modifier = query_result.column_type_modifier(i);
         fields_data.extend_from_slice(&type_modifier.to_be_bytes());
 
-        // Add format code (0 for text)
-        let format_code: i16 = 0;
+        // Add format code negotiated in Bind (0 for text, 1 for binary)
+        let format_code = super::ColumnFormat::for_column(result_formats, i) as i16;
         fields_data.extend_from_slice(&format_code.to_be_bytes());
     }
 
@@ -383,7 +147,7 @@ pub(super) fn create_row_description_response_with_types(query_result: &crate::q
 }
 
 /// Create RowDescription using QueryInfo (fallback for when QueryResult is not available)
-pub(super) fn create_row_description_response(query_info: &crate::tables::QueryInfo) -> Vec<u8> {
+pub(super) fn create_row_description_response(query_info: &crate::tables::QueryInfo, result_formats: &[super::ColumnFormat]) -> Vec<u8> {
     let mut response = Vec::new();
     tracing::debug!("🚀 create_row_description_response() CALLED with {} columns", query_info.columns.len());
     tracing::debug!("🚀 Query info columns: {:?}", query_info.columns);
@@ -415,15 +179,15 @@ pub(super) fn create_row_description_response(query_info: &crate::tables::QueryI
         fields_data.extend_from_slice(&type_oid.to_be_bytes()); // Data type OID
 
         // Add type size (-1 for variable size)
-        let type_size: i16 = -1;
+        let type_size = postgres_type_size(type_oid);
         fields_data.extend_from_slice(&type_size.to_be_bytes());
 
         // Add type modifier (-1 for default)
         let type_modifier: i32 = -1;
         fields_data.extend_from_slice(&type_modifier.to_be_bytes());
 
-        // Add format code (0 for text)
-        let format_code: i16 = 0;
+        // Add format code negotiated in Bind (0 for text, 1 for binary)
+        let format_code = super::ColumnFormat::for_column(result_formats, i) as i16;
         fields_data.extend_from_slice(&format_code.to_be_bytes());
     }
 
@@ -449,7 +213,6 @@ pub(super) fn create_ready_for_query_response() -> Vec<u8> {
     vec![b'Z', 0, 0, 0, 5, b'I']
 }
 
-#[allow(dead_code)]
 pub(super) fn create_command_complete_response(tag: &str) -> Vec<u8> {
     let mut response = vec![b'C'];
     let tag_bytes = tag.as_bytes();
@@ -460,6 +223,19 @@ pub(super) fn create_command_complete_response(tag: &str) -> Vec<u8> {
     response
 }
 
+/// NotificationResponse ('A') for LISTEN; process_id is the backend that sent the notification
+pub(super) fn create_notification_response(process_id: u32, channel: &str, payload: &str) -> Vec<u8> {
+    let mut response = vec![b'A'];
+    let length = 4 + 4 + channel.len() + 1 + payload.len() + 1;
+    response.extend_from_slice(&(length as u32).to_be_bytes());
+    response.extend_from_slice(&process_id.to_be_bytes());
+    response.extend_from_slice(channel.as_bytes());
+    response.push(0);
+    response.extend_from_slice(payload.as_bytes());
+    response.push(0);
+    response
+}
+
 pub(super) fn create_parameter_description_response(param_oids: &[u32]) -> Vec<u8> {
     let mut response = vec![b't'];
     let mut data = Vec::new();
@@ -477,8 +253,107 @@ pub(super) fn create_empty_row_description_response() -> Vec<u8> {
     vec![b'n', 0, 0, 0, 4]
 }
 
+fn create_notice_responses(notices: &[String]) -> Vec<u8> {
+    notices.iter().flat_map(|notice| create_postgres_warning_notice(notice)).collect()
+}
+
+/// COPY ... TO STDOUT: CopyOutResponse, one CopyData message per row, CopyDone and CommandComplete
+fn create_copy_out_response(result: &crate::query_handler::QueryResult, options: &CopyOptions) -> Vec<u8> {
+    let mut response = Vec::new();
+
+    // CopyOutResponse: 'H' + length + overall format (0 = text) + column count + format per column
+    response.push(b'H');
+    let column_count = result.columns.len();
+    response.extend_from_slice(&((4 + 1 + 2 + 2 * column_count) as u32).to_be_bytes());
+    response.push(0);
+    response.extend_from_slice(&(column_count as u16).to_be_bytes());
+    for _ in 0..column_count {
+        response.extend_from_slice(&0u16.to_be_bytes());
+    }
+
+    let mut lines = Vec::new();
+    if options.header {
+        let names: Vec<Option<String>> = result.columns.iter().map(|name| Some(name.clone())).collect();
+        lines.push(encode_copy_line(&names, options));
+    }
+    for row in &result.rows {
+        let fields: Vec<Option<String>> = row
+            .iter()
+            .map(|value| value.to_pg_text_format().map(|bytes| String::from_utf8_lossy(&bytes).into_owned()))
+            .collect();
+        lines.push(encode_copy_line(&fields, options));
+    }
+    for line in lines {
+        // CopyData: 'd' + length + one row
+        response.push(b'd');
+        response.extend_from_slice(&((4 + line.len()) as u32).to_be_bytes());
+        response.extend_from_slice(line.as_bytes());
+    }
+
+    response.extend_from_slice(&[b'c', 0, 0, 0, 4]); // CopyDone
+    response.extend_from_slice(&create_command_complete_response(&format!("COPY {}", result.rows.len())));
+    response
+}
+
+/// One row in the COPY text or CSV format, terminated by a newline
+fn encode_copy_line(fields: &[Option<String>], options: &CopyOptions) -> String {
+    let encoded: Vec<String> = fields
+        .iter()
+        .map(|field| match field {
+            None => options.null.clone(),
+            Some(value) => match options.format {
+                CopyFormat::Text => {
+                    let mut escaped = String::with_capacity(value.len());
+                    for c in value.chars() {
+                        match c {
+                            '\\' => escaped.push_str("\\\\"),
+                            '\n' => escaped.push_str("\\n"),
+                            '\r' => escaped.push_str("\\r"),
+                            '\t' => escaped.push_str("\\t"),
+                            c if c == options.delimiter => {
+                                escaped.push('\\');
+                                escaped.push(c);
+                            }
+                            c => escaped.push(c),
+                        }
+                    }
+                    escaped
+                }
+                CopyFormat::Csv => {
+                    // Quoted when it could be mistaken for a delimiter, a line break or NULL
+                    let needs_quotes = *value == options.null
+                        || value.contains(options.delimiter)
+                        || value.contains(options.quote)
+                        || value.contains(['\n', '\r']);
+                    if needs_quotes {
+                        let quote = options.quote.to_string();
+                        format!("{}{}{}", quote, value.replace(&quote, &quote.repeat(2)), quote)
+                    } else {
+                        value.clone()
+                    }
+                }
+            },
+        })
+        .collect();
+    let mut line = encoded.join(&options.delimiter.to_string());
+    line.push('\n');
+    line
+}
+
 /// Format QueryResult directly to PostgreSQL wire protocol
 pub(super) fn format_query_result_as_postgres_result(result: &crate::query_handler::QueryResult) -> Vec<u8> {
+    if let Some(options) = &result.copy_out {
+        let mut response = create_copy_out_response(result, options);
+        response.extend_from_slice(&[b'Z', 0, 0, 0, 5, b'I']);
+        return response;
+    }
+    if let (Some(tag), true) = (&result.command_tag, result.columns.is_empty()) {
+        let mut response = create_notice_responses(&result.notices);
+        response.extend_from_slice(&create_command_complete_response(tag));
+        response.extend_from_slice(&[b'Z', 0, 0, 0, 5, b'I']);
+        return response;
+    }
+
     let mut response = Vec::new();
     
     tracing::debug!("🚀 format_query_result_as_postgres_result() CALLED with {} columns, {} rows", result.columns.len(), result.rows.len());
@@ -511,11 +386,11 @@ pub(super) fn format_query_result_as_postgres_result(result: &crate::query_handl
         fields_data.extend_from_slice(&type_oid.to_be_bytes());
         
         // Add type size (-1 for variable size)
-        let type_size: i16 = -1;
+        let type_size = postgres_type_size(type_oid);
         fields_data.extend_from_slice(&type_size.to_be_bytes());
         
-        // Add type modifier (-1 for default)
-        let type_modifier: i32 = -1;
+        // Add type modifier (-1 for default, precision and scale for numeric)
+        let type_modifier = result.column_type_modifier(i);
         fields_data.extend_from_slice(&type_modifier.to_be_bytes());
         
         // Add format code (0 for text)
@@ -532,59 +407,14 @@ pub(super) fn format_query_result_as_postgres_result(result: &crate::query_handl
     
     // DataRow messages: 'D' (DataRow) + length + column_count + columns
     for row in &result.rows {
-        response.push(b'D'); // 'D' = DataRow message
-        
-        let mut row_data = Vec::new();
-        row_data.extend_from_slice(&(row.len() as u16).to_be_bytes());
-        
-        for (col_idx, value) in row.iter().enumerate() {
-            // Get the column type to determine format
-            let _type_oid = if col_idx < result.column_types.len() {
-                result.column_types[col_idx]
-            } else {
-                25 // TEXT
-            };
-            
-            match value {
-                crate::query_handler::QueryValue::Null => {
-                    row_data.extend_from_slice(&(-1i32).to_be_bytes());
-                }
-                crate::query_handler::QueryValue::Text(s) => {
-                    row_data.extend_from_slice(&(s.len() as u32).to_be_bytes());
-                    row_data.extend_from_slice(s.as_bytes());
-                }
-                crate::query_handler::QueryValue::Integer(i) => {
-                    let s = i.to_string();
-                    row_data.extend_from_slice(&(s.len() as u32).to_be_bytes());
-                    row_data.extend_from_slice(s.as_bytes());
-                }
-                crate::query_handler::QueryValue::Float(f) => {
-                    let s = f.to_string();
-                    row_data.extend_from_slice(&(s.len() as u32).to_be_bytes());
-                    row_data.extend_from_slice(s.as_bytes());
-                }
-                crate::query_handler::QueryValue::Timestamp(s) => {
-                    row_data.extend_from_slice(&(s.len() as u32).to_be_bytes());
-                    row_data.extend_from_slice(s.as_bytes());
-                }
-                crate::query_handler::QueryValue::Boolean(b) => {
-                    let s = if *b { "true" } else { "false" };
-                    row_data.extend_from_slice(&(s.len() as u32).to_be_bytes());
-                    row_data.extend_from_slice(s.as_bytes());
-                }
-            }
-        }
-        
-        let length = 4 + row_data.len();
-        response.extend_from_slice(&(length as u32).to_be_bytes());
-        response.extend_from_slice(&row_data);
+        append_data_row(&mut response, row, &result.column_types, &[]);
     }
     
     tracing::debug!("🔧 Added {} DataRow ('D') messages", result.rows.len());
     
     // CommandComplete message: 'C' (CommandComplete) + length + tag
     response.push(b'C'); // 'C' = CommandComplete message
-    let tag = format!("SELECT {}", result.rows.len());
+    let tag = result.command_tag.clone().unwrap_or_else(|| format!("SELECT {}", result.rows.len()));
     let tag_length = 4 + tag.len() + 1; // 4 bytes for length + tag + null terminator
     response.extend_from_slice(&(tag_length as u32).to_be_bytes());
     response.extend_from_slice(tag.as_bytes());
@@ -601,66 +431,30 @@ pub(super) fn format_query_result_as_postgres_result(result: &crate::query_handl
 }
 
 /// Format QueryResult for Extended Query protocol (DataRow + CommandComplete only, no RowDescription)
-pub(super) fn format_query_result_as_extended_query_result(result: &crate::query_handler::QueryResult) -> Vec<u8> {
+pub(super) fn format_query_result_as_extended_query_result(result: &crate::query_handler::QueryResult, result_formats: &[super::ColumnFormat]) -> Vec<u8> {
+    if let Some(options) = &result.copy_out {
+        return create_copy_out_response(result, options);
+    }
+    if let (Some(tag), true) = (&result.command_tag, result.columns.is_empty()) {
+        let mut response = create_notice_responses(&result.notices);
+        response.extend_from_slice(&create_command_complete_response(tag));
+        return response;
+    }
+
     let mut response = Vec::new();
     
     tracing::debug!("🚀 format_query_result_as_extended_query_result() CALLED: {} columns, {} rows", result.columns.len(), result.rows.len());
     
     // DataRow messages only (no RowDescription - that was sent by Describe)
     for row in &result.rows {
-        response.push(b'D'); // 'D' = DataRow message
-        
-        let mut row_data = Vec::new();
-        row_data.extend_from_slice(&(row.len() as u16).to_be_bytes());
-        
-        for (col_idx, value) in row.iter().enumerate() {
-            // Get the column type to determine format
-            let _type_oid = if col_idx < result.column_types.len() {
-                result.column_types[col_idx]
-            } else {
-                25 // TEXT
-            };
-            
-            match value {
-                crate::query_handler::QueryValue::Null => {
-                    row_data.extend_from_slice(&(-1i32).to_be_bytes());
-                }
-                crate::query_handler::QueryValue::Text(s) => {
-                    row_data.extend_from_slice(&(s.len() as u32).to_be_bytes());
-                    row_data.extend_from_slice(s.as_bytes());
-                }
-                crate::query_handler::QueryValue::Integer(i) => {
-                    let s = i.to_string();
-                    row_data.extend_from_slice(&(s.len() as u32).to_be_bytes());
-                    row_data.extend_from_slice(s.as_bytes());
-                }
-                crate::query_handler::QueryValue::Float(f) => {
-                    let s = f.to_string();
-                    row_data.extend_from_slice(&(s.len() as u32).to_be_bytes());
-                    row_data.extend_from_slice(s.as_bytes());
-                }
-                crate::query_handler::QueryValue::Timestamp(s) => {
-                    row_data.extend_from_slice(&(s.len() as u32).to_be_bytes());
-                    row_data.extend_from_slice(s.as_bytes());
-                }
-                crate::query_handler::QueryValue::Boolean(b) => {
-                    let s = if *b { "true" } else { "false" };
-                    row_data.extend_from_slice(&(s.len() as u32).to_be_bytes());
-                    row_data.extend_from_slice(s.as_bytes());
-                }
-            }
-        }
-        
-        let length = 4 + row_data.len();
-        response.extend_from_slice(&(length as u32).to_be_bytes());
-        response.extend_from_slice(&row_data);
+        append_data_row(&mut response, row, &result.column_types, result_formats);
     }
     
     tracing::debug!("🔧 Added {} DataRow ('D') messages for Extended Query", result.rows.len());
     
     // CommandComplete message: 'C' (CommandComplete) + length + tag
     response.push(b'C'); // 'C' = CommandComplete message
-    let tag = format!("SELECT {}", result.rows.len());
+    let tag = result.command_tag.clone().unwrap_or_else(|| format!("SELECT {}", result.rows.len()));
     let tag_length = 4 + tag.len() + 1; // 4 bytes for length + tag + null terminator
     response.extend_from_slice(&(tag_length as u32).to_be_bytes());
     response.extend_from_slice(tag.as_bytes());
@@ -671,6 +465,53 @@ pub(super) fn format_query_result_as_extended_query_result(result: &crate::query
     response
 }
 
+/// Append a DataRow message with each value encoded in the requested format (text or binary)
+fn append_data_row(
+    response: &mut Vec<u8>,
+    row: &[crate::query_handler::QueryValue],
+    column_types: &[u32],
+    result_formats: &[super::ColumnFormat],
+) {
+    response.push(b'D'); // 'D' = DataRow message
+
+    let mut row_data = Vec::new();
+    row_data.extend_from_slice(&(row.len() as u16).to_be_bytes());
+
+    for (col_idx, value) in row.iter().enumerate() {
+        let encoded = match super::ColumnFormat::for_column(result_formats, col_idx) {
+            super::ColumnFormat::Text => value.to_pg_text_format(),
+            super::ColumnFormat::Binary => {
+                let type_oid = column_types.get(col_idx).copied().unwrap_or(25);
+                value.to_pg_binary_format(type_oid)
+            }
+        };
+        match encoded {
+            Some(bytes) => {
+                row_data.extend_from_slice(&(bytes.len() as u32).to_be_bytes());
+                row_data.extend_from_slice(&bytes);
+            }
+            None => {
+                row_data.extend_from_slice(&(-1i32).to_be_bytes());
+            }
+        }
+    }
+
+    let length = 4 + row_data.len();
+    response.extend_from_slice(&(length as u32).to_be_bytes());
+    response.extend_from_slice(&row_data);
+}
+
+/// Fixed size of the type for RowDescription, -1 for variable length types
+fn postgres_type_size(oid: u32) -> i16 {
+    match oid {
+        16 => 1,
+        21 => 2,
+        23 | 700 => 4,
+        20 | 701 => 8,
+        _ => -1,
+    }
+}
+
 fn postgres_type_name(oid: u32) -> &'static str {
     match oid {
         16 => "bool",
@@ -681,49 +522,8 @@ fn postgres_type_name(oid: u32) -> &'static str {
         700 => "float4",
         701 => "float8", 
         1114 => "timestamp",
+        1184 => "timestamptz",
         1700 => "numeric",
         _ => "unknown",
     }
 }
-
-// Parse CSV line handling quoted fields properly
-pub(crate) fn parse_csv_line(line: &str) -> Vec<String> {
-    let mut fields = Vec::new();
-    let mut current_field = String::new();
-    let mut in_quotes = false;
-    let mut chars = line.chars().peekable();
-    
-    while let Some(ch) = chars.next() {
-        match ch {
-            '"' => {
-                if in_quotes {
-                    // Check if this is an escaped quote
-                    if chars.peek() == Some(&'"') {
-                        current_field.push('"');
-                        chars.next(); // consume the second quote
-                    } else {
-                        in_quotes = false;
-                    }
-                } else {
-                    in_quotes = true;
-                }
-            }
-            ',' => {
-                if in_quotes {
-                    current_field.push(',');
-                } else {
-                    fields.push(current_field.clone());
-                    current_field.clear();
-                }
-            }
-            _ => {
-                current_field.push(ch);
-            }
-        }
-    }
-    
-    // Don't forget the last field
-    fields.push(current_field);
-    
-    fields
-}
@@ -0,0 +1,85 @@
+use std::sync::LazyLock;
+
+/// A server configuration parameter as reported by ParameterStatus and SHOW
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct PgSetting {
+    pub name: &'static str,
+    pub value: &'static str,
+    pub description: &'static str,
+}
+
+/// Settings cache shared by all connections, loaded once on first access
+pub(super) static GLOBAL_SETTINGS: LazyLock<Vec<PgSetting>> = LazyLock::new(load_default_settings);
+
+// Parameters the server reports with ParameterStatus after authentication
+pub(super) const REPORTED_PARAMETERS: [&str; 10] = [
+    "server_version",
+    "server_encoding",
+    "client_encoding",
+    "application_name",
+    "is_superuser",
+    "session_authorization",
+    "DateStyle",
+    "TimeZone",
+    "standard_conforming_strings",
+    "integer_datetimes",
+];
+
+fn load_default_settings() -> Vec<PgSetting> {
+    let settings = [
+        ("server_version", "15.0", "Shows the server version."),
+        ("server_version_num", "150000", "Shows the server version as an integer."),
+        ("server_encoding", "UTF8", "Shows the server (database) character set encoding."),
+        ("client_encoding", "UTF8", "Sets the client's character set encoding."),
+        ("application_name", "", "Sets the application name to be reported in statistics and logs."),
+        ("is_superuser", "off", "Shows whether the current user is a superuser."),
+        ("session_authorization", "operator", "Sets the session user name."),
+        ("DateStyle", "ISO", "Sets the display format for date and time values."),
+        ("IntervalStyle", "postgres", "Sets the display format for interval values."),
+        ("TimeZone", "UTC", "Sets the time zone for displaying and interpreting time stamps."),
+        ("standard_conforming_strings", "on", "Causes '...' strings to treat backslashes literally."),
+        ("integer_datetimes", "on", "Shows whether datetimes are integer based."),
+        ("extra_float_digits", "1", "Sets the number of digits displayed for floating-point values."),
+        ("max_identifier_length", "63", "Shows the maximum identifier length."),
+        ("transaction_isolation", "read committed", "Sets the current transaction's isolation level."),
+        ("default_transaction_read_only", "on", "Sets the default read-only status of new transactions."),
+        ("search_path", "\"$user\", public", "Sets the schema search order for names that are not schema-qualified."),
+    ];
+
+    settings
+        .into_iter()
+        .map(|(name, value, description)| PgSetting { name, value, description })
+        .collect()
+}
+
+/// ParameterStatus values sent after authentication: the REPORTED_PARAMETERS from the settings
+/// cache with the --pg-param-status overrides applied; parameters not reported by default are added
+pub(crate) fn parameter_status_list(overrides: &[(String, String)]) -> Vec<(String, String)> {
+    let mut parameters: Vec<(String, String)> = REPORTED_PARAMETERS
+        .iter()
+        .filter_map(|name| get_postgresql_setting(name))
+        .map(|setting| (setting.name.to_string(), setting.value.to_string()))
+        .collect();
+    for (name, value) in overrides {
+        match parameters.iter_mut().find(|(reported, _)| reported.eq_ignore_ascii_case(name)) {
+            Some(parameter) => parameter.1 = value.clone(),
+            None => parameters.push((name.clone(), value.clone())),
+        }
+    }
+    parameters
+}
+
+/// Parse a `--pg-param-status name=value` argument; the value may be empty
+pub(crate) fn parse_parameter_status(arg: &str) -> Result<(String, String), String> {
+    match arg.split_once('=') {
+        Some((name, value)) if !name.trim().is_empty() => Ok((name.trim().to_string(), value.to_string())),
+        _ => Err(format!("expected name=value, got '{}'", arg)),
+    }
+}
+
+/// Look up a setting by name; PostgreSQL setting names are case-insensitive
+pub(crate) fn get_postgresql_setting(name: &str) -> Option<&'static PgSetting> {
+    GLOBAL_SETTINGS
+        .iter()
+        .find(|setting| setting.name.eq_ignore_ascii_case(name))
+}
@@ -1,37 +1,41 @@
-use crate::auth::SessionManager;
+use crate::auth::{ClientAddr, ConnectionLimitExceeded, SessionManager, DEFAULT_DATABASE_NAME};
+use crate::AuthMode;
 use crate::keep_alive::{send_keep_alive_probe, create_parameter_status_keepalive};
 use std::collections::HashMap;
-use std::net::SocketAddr;
 use std::sync::Arc;
 use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
-use tokio::net::TcpStream;
 use tokio::time::{interval, timeout, Duration};
 use tracing::{debug, error, info, warn};
 use anyhow::Result;
 
-use super::authentication::{create_postgres_md5_request, create_postgres_scram_sha256_request, parse_postgres_password, parse_sasl_initial_response, AuthContext, parse_scram_client_first, scram_sha256_server_first_message, create_postgres_sasl_continue_response, parse_sasl_response, parse_scram_client_final, scram_sha256_verify_client_proof, create_postgres_sasl_final_response, compute_postgres_md5_hash, verify_postgres_md5_auth};
+use super::authentication::{scram_channel_binding, SCRAM_SHA_256, SCRAM_SHA_256_PLUS, create_postgres_md5_request, create_postgres_cleartext_password_request, create_postgres_scram_sha256_request, parse_postgres_password, parse_sasl_initial_response, AuthContext, parse_scram_client_first, scram_sha256_server_first_message, create_postgres_sasl_continue_response, parse_sasl_response, parse_scram_client_final, scram_sha256_verify_client_proof, create_postgres_sasl_final_response, compute_postgres_md5_hash, verify_postgres_md5_auth};
+use super::connection_handler::MessageReader;
 use super::message_handler::handle_postgres_message;
-use super::response::{create_postgres_auth_ok_response, create_postgres_error_response};
+use super::notifications::Listeners;
+use super::response::{create_postgres_auth_ok_response, create_postgres_error_response, create_postgres_error_response_with_fields, create_postgres_fatal_response};
 use super::{ConnectionState, ScramStage};
 
 pub(super) async fn handle_postgres_startup(
-    socket: TcpStream,
+    socket: super::transport::Transport,
     session_manager: Arc<SessionManager>,
     data: &[u8],
-    peer_addr: SocketAddr,
+    peer_addr: ClientAddr,
     quiet_connections: bool,
     keep_alive_interval: u64,
 ) -> Result<()> {
-    handle_postgres_startup_stream(socket, session_manager, data, Some(peer_addr), quiet_connections, keep_alive_interval).await
+    handle_postgres_startup_stream(socket, session_manager, data, Some(peer_addr), quiet_connections, keep_alive_interval, None).await
 }
 
+/// `tls_server_end_point` is the channel binding data of the server certificate on TLS
+/// connections; it enables SCRAM-SHA-256-PLUS
 pub(super) async fn handle_postgres_startup_stream<T>(
     mut socket: T,
     session_manager: Arc<SessionManager>,
     data: &[u8],
-    socket_addr: Option<SocketAddr>,
+    socket_addr: Option<ClientAddr>,
     quiet_connections: bool,
     keep_alive_interval: u64,
+    tls_server_end_point: Option<Vec<u8>>,
 ) -> Result<()> 
 where
     T: AsyncRead + AsyncWrite + Unpin,
@@ -70,6 +74,12 @@ where
     let version =
         u32::from_be_bytes([complete_data[4], complete_data[5], complete_data[6], complete_data[7]]);
 
+    // A CancelRequest can also arrive after an SSL upgrade
+    if let Some((process_id, secret_key)) = super::connection_handler::parse_cancel_request(&complete_data) {
+        super::connection_handler::handle_cancel_request(&session_manager, process_id, secret_key, &peer_addr_str).await;
+        return Ok(());
+    }
+
     if !quiet_connections {
         info!(
             "📋 Startup message: length={}, version={} (0x{:08x})",
@@ -92,7 +102,7 @@ where
     // ASCII interpretation (printable characters only)
     let ascii_dump: String = complete_data
         .iter()
-        .map(|&b| if b >= 32 && b <= 126 { b as char } else { '.' })
+        .map(|&b| if (32..=126).contains(&b) { b as char } else { '.' })
         .collect();
     debug!("   📝 ASCII dump: {}", ascii_dump);
 
@@ -101,10 +111,13 @@ where
         // PostgreSQL 3.0 protocol
 
         // Initialize connection state for Extended Query Protocol and SCRAM authentication
+        let (listeners, mut notifications) = Listeners::new();
         let mut connection_state = ConnectionState {
             prepared_statements: HashMap::new(),
             portals: HashMap::new(),
             scram_context: None,
+            listeners,
+            pipeline_depth: 0,
         };
         debug!("✅ PostgreSQL 3.0 protocol detected");
 
@@ -140,8 +153,8 @@ where
             }
         }
 
-        // Extract username and application_name from startup parameters for authentication
-        let (username, application_name) = if complete_data.len() > 8 {
+        // Extract username, application_name and database from startup parameters for authentication
+        let (username, application_name, database_name, timezone, startup_options, replication) = if complete_data.len() > 8 {
             let params_data = &complete_data[8..];
             let params = parse_startup_parameters(params_data);
             debug!("🔍 All startup parameters: {:?}", params);
@@ -168,16 +181,33 @@ where
             }
 
             let app_name = params.get("application_name").cloned().unwrap_or_else(|| "unknown".to_string());
-            (user, app_name)
+            // Clients can join the server spans to their own trace by passing a W3C traceparent
+            if crate::telemetry::set_parent_from_application_name(&tracing::Span::current(), &app_name) {
+                debug!("🔗 Continuing trace from application_name traceparent");
+            }
+            let database = params.get("database").cloned().unwrap_or_else(|| DEFAULT_DATABASE_NAME.to_string());
+            let startup_options = params.get("options").map(|options| parse_startup_options(options)).unwrap_or_default();
+            let replication = params.get("replication").is_some_and(|value| is_replication_mode(value));
+            (user, app_name, database, params.get("TimeZone").cloned(), startup_options, replication)
         } else {
             warn!(
                 "⚠️  Startup message too short from {}: {} bytes",
                 peer_addr_str,
                 data.len()
             );
-            ("unknown".to_string(), "unknown".to_string())
+            ("unknown".to_string(), "unknown".to_string(), DEFAULT_DATABASE_NAME.to_string(), None, Vec::new(), false)
         };
 
+        // Replication clients (Debezium, pg_recvlogical) cannot be served, refuse them before authentication
+        if replication {
+            warn!("🚫 Rejecting replication connection from {} for user {}", peer_addr_str, username);
+            info!("💡 Query the WinCC tables with plain SQL (e.g. SELECT ... FROM tagvaluechanges) instead of logical replication");
+            socket
+                .write_all(&create_postgres_fatal_response("0A000", super::query_execution::REPLICATION_NOT_SUPPORTED))
+                .await?;
+            return Ok(());
+        }
+
         if !quiet_connections {
             info!(
                 "🔐 PostgreSQL client {} requesting authentication for user: {}",
@@ -185,76 +215,126 @@ where
             );
         }
 
+        // In drain mode (rolling restart) clients are sent to another instance
+        if session_manager.is_draining() {
+            warn!("🚰 Rejecting connection from {}: server is draining", peer_addr_str);
+            socket
+                .write_all(&create_postgres_fatal_response("57P03", "the database system is shutting down"))
+                .await?;
+            return Ok(());
+        }
 
-        // Normal authentication flow
-        // Choose authentication method:
-        // 1. Use MD5 by default for maximum compatibility (psycopg2, etc.)
-        // 2. SCRAM-SHA-256 available but not default due to limited client support
-        // Note: For SCRAM, username comes in SASL Initial Response, not startup message
-
-        let prefer_scram = false; // Use MD5 for better compatibility with Python clients
+        // Enforce the connection limits before authentication; the slot is freed when this function returns
+        let _connection_slot = match session_manager.try_acquire_connection_slot(&username) {
+            Ok(slot) => slot,
+            Err(limit) => {
+                let message = match limit {
+                    ConnectionLimitExceeded::Global => "sorry, too many clients already".to_string(),
+                    ConnectionLimitExceeded::PerUser => format!("too many connections for user \"{}\"", username),
+                };
+                warn!(
+                    "🚫 Rejecting connection from {}: {} ({} connections open)",
+                    peer_addr_str, message, session_manager.open_connection_count()
+                );
+                socket.write_all(&create_postgres_fatal_response("53300", &message)).await?;
+                return Ok(());
+            }
+        };
 
-        let (auth_request, auth_context) = if prefer_scram {
-            if !quiet_connections {
-                info!("🔐 Offering SCRAM-SHA-256 authentication (preferred method)");
-                if username == "unknown" {
-                    info!("   💡 Username will be provided in SASL Initial Response");
-                } else {
-                    info!("   👤 Startup username: {}", username);
+        // Choose the authentication method of --auth-mode:
+        // - md5 (default) for maximum compatibility (psycopg2, etc.)
+        // - scram-sha-256 against the passwords of --scram-password-file
+        // - password asks for a cleartext password, jwt for a cleartext password carrying a JWT
+        // - trust skips the exchange and logs in to GraphQL with --default-user
+        // Note: For SCRAM, the password is looked up for the startup message user, the SCRAM username is ignored
+        let (auth_request, auth_context) = match session_manager.auth_mode() {
+            AuthMode::Trust => {
+                if !quiet_connections {
+                    info!("🔓 Trust authentication, no password requested");
                 }
+                (Vec::new(), AuthContext::Trust)
             }
-            (
-                create_postgres_scram_sha256_request(),
-                AuthContext::Scram,
-            )
-        } else {
-            if !quiet_connections {
-                info!("🔐 Sending MD5 authentication request");
+            AuthMode::Jwt => {
+                if !quiet_connections {
+                    info!("🔐 Requesting a JWT as cleartext password");
+                }
+                (create_postgres_cleartext_password_request(), AuthContext::Jwt)
+            }
+            AuthMode::Password => {
+                if !quiet_connections {
+                    info!("🔐 Sending cleartext password request");
+                }
+                (create_postgres_cleartext_password_request(), AuthContext::Password)
+            }
+            AuthMode::ScramSha256 => {
+                if !quiet_connections {
+                    info!("🔐 Offering SCRAM-SHA-256 authentication");
+                    if username == "unknown" {
+                        info!("   💡 Username will be provided in SASL Initial Response");
+                    } else {
+                        info!("   👤 Startup username: {}", username);
+                    }
+                }
+                (
+                    create_postgres_scram_sha256_request(tls_server_end_point.is_some()),
+                    AuthContext::Scram,
+                )
+            }
+            AuthMode::Md5 => {
+                if !quiet_connections {
+                    info!("🔐 Sending MD5 authentication request");
+                }
+                let (auth_request, salt) = create_postgres_md5_request();
+                debug!(
+                    "🧂 Generated salt for MD5 auth: {:02x}{:02x}{:02x}{:02x}",
+                    salt[0], salt[1], salt[2], salt[3]
+                );
+                (auth_request, AuthContext::Md5(salt))
             }
-            let (auth_request, salt) = create_postgres_md5_request();
-            debug!(
-                "🧂 Generated salt for MD5 auth: {:02x}{:02x}{:02x}{:02x}",
-                salt[0], salt[1], salt[2], salt[3]
-            );
-            (auth_request, AuthContext::Md5(salt))
         };
 
-        debug!("📤 Sending password authentication request to {}", peer_addr_str);
-        if let Err(e) = socket.write_all(&auth_request).await {
-            error!("❌ Failed to send auth request to {}: {}", peer_addr_str, e);
-            return Ok(());
-        }
-
-        // Wait for authentication response (SASL or password)
-        if matches!(auth_context, AuthContext::Scram) {
-            debug!("📖 Waiting for SASL Initial Response from {}", peer_addr_str);
+        let auth_message = if matches!(auth_context, AuthContext::Trust) {
+            Vec::new()
         } else {
-            debug!("📖 Waiting for password response from {}", peer_addr_str);
-        }
+            debug!("📤 Sending password authentication request to {}", peer_addr_str);
+            if let Err(e) = socket.write_all(&auth_request).await {
+                error!("❌ Failed to send auth request to {}: {}", peer_addr_str, e);
+                return Ok(());
+            }
 
-        let mut auth_buffer = [0; 1024];
-        let auth_n = socket.read(&mut auth_buffer).await?;
-        if auth_n == 0 {
-            if !quiet_connections {
-                warn!("⚠️  Client {} disconnected during authentication", peer_addr_str);
+            // Wait for authentication response (SASL or password)
+            if matches!(auth_context, AuthContext::Scram) {
+                debug!("📖 Waiting for SASL Initial Response from {}", peer_addr_str);
+            } else {
+                debug!("📖 Waiting for password response from {}", peer_addr_str);
             }
-            return Ok(());
-        }
 
-        debug!(
-            "📊 Received {} bytes authentication response from {}",
-            auth_n, peer_addr_str
-        );
+            let Some(auth_message) = read_auth_message(&mut socket).await? else {
+                if !quiet_connections {
+                    warn!("⚠️  Client {} disconnected during authentication", peer_addr_str);
+                }
+                return Ok(());
+            };
+
+            debug!(
+                "📊 Received {} bytes authentication response from {}",
+                auth_message.len(), peer_addr_str
+            );
+            auth_message
+        };
+        let auth_n = auth_message.len();
 
         // Handle authentication based on the context
         let (username_final, password_final) = match &auth_context {
             AuthContext::Scram => {
                 // Handle SCRAM-SHA-256 authentication with full protocol implementation
-                if auth_n > 0 && auth_buffer[0] == b'p' {
+                if auth_n > 0 && auth_message[0] == b'p' {
                     // Parse SASL Initial Response
-                    match parse_sasl_initial_response(&auth_buffer[..auth_n]) {
+                    match parse_sasl_initial_response(&auth_message) {
                         Ok((mechanism, initial_response)) => {
-                            if mechanism != "SCRAM-SHA-256" {
+                            let mechanism_offered = mechanism == SCRAM_SHA_256
+                                || (mechanism == SCRAM_SHA_256_PLUS && tls_server_end_point.is_some());
+                            if !mechanism_offered {
                                 warn!("🔄 Client requested unsupported SASL mechanism '{}', falling back to MD5", mechanism);
 
                                 // Send MD5 auth request
@@ -263,9 +343,7 @@ where
                                 socket.write_all(&md5_request).await?;
 
                                 // Wait for password response
-                                let mut password_buffer = [0; 1024];
-                                let password_n = socket.read(&mut password_buffer).await?;
-                                if password_n == 0 {
+                                let Some(password_message) = read_auth_message(&mut socket).await? else {
                                     if !quiet_connections {
                                         warn!(
                                             "⚠️  Client {} disconnected during MD5 fallback",
@@ -273,9 +351,9 @@ where
                                         );
                                     }
                                     return Ok(());
-                                }
+                                };
 
-                                let password = parse_postgres_password(&password_buffer[..password_n]);
+                                let password = parse_postgres_password(&password_message);
                                 if password.is_none() {
                                     error!("❌ Invalid password format during MD5 fallback from {}", peer_addr_str);
                                     let error_response = create_postgres_error_response("28P01", "Invalid password format");
@@ -291,9 +369,9 @@ where
                                 debug!("📨 SCRAM Initial Response: {}", initial_response);
 
                                 // Parse client-first message
-                                let (scram_username, client_nonce) =
+                                let (scram_username, client_nonce, gs2_header, client_first_bare) =
                                     match parse_scram_client_first(&initial_response) {
-                                        Ok((u, n)) => (u, n),
+                                        Ok(client_first) => client_first,
                                         Err(e) => {
                                             error!("❌ Failed to parse SCRAM client-first from {}: {}", peer_addr_str, e);
                                             let error_response = create_postgres_error_response("28P01", &format!("Invalid SCRAM client-first: {}", e));
@@ -302,19 +380,37 @@ where
                                         }
                                     };
 
+                                // The password is looked up for the user of the startup message; libpq
+                                // leaves the SCRAM username empty and PostgreSQL ignores it as well
                                 info!(
-                                    "👤 SCRAM username: '{}', client nonce: '{}'",
-                                    scram_username, client_nonce
+                                    "👤 SCRAM user: '{}' (client-first username: '{}'), client nonce: '{}'",
+                                    username, scram_username, client_nonce
                                 );
 
+                                let channel_binding = match scram_channel_binding(&mechanism, &gs2_header, tls_server_end_point.as_deref()) {
+                                    Ok(data) => data,
+                                    Err(e) => {
+                                        error!("❌ SCRAM channel binding rejected for {}: {}", peer_addr_str, e);
+                                        session_manager.metrics().auth_failures_total.inc();
+                                        let error_response = create_postgres_error_response("28000", &e);
+                                        socket.write_all(&error_response).await?;
+                                        return Ok(());
+                                    }
+                                };
+                                if !channel_binding.is_empty() && !quiet_connections {
+                                    info!("🔗 SCRAM bound to the TLS channel (tls-server-end-point)");
+                                }
+
                                 // Generate server-first message
                                 let (server_first, mut scram_context) =
                                     scram_sha256_server_first_message(
                                         &client_nonce,
-                                        &scram_username,
+                                        &username,
                                     );
-                                scram_context.client_first_bare =
-                                    format!("n={},r={}", scram_username, client_nonce);
+                                // The client signs the client-first-bare it sent, so it is kept verbatim
+                                scram_context.client_first_bare = client_first_bare;
+                                scram_context.gs2_header = gs2_header;
+                                scram_context.channel_binding = channel_binding;
                                 scram_context.stage = ScramStage::Continue;
 
                                 // We'll handle SCRAM context storage after authentication completes
@@ -327,9 +423,7 @@ where
                                 socket.write_all(&continue_response).await?;
 
                                 // Wait for client-final message
-                                let mut client_final_buffer = [0; 1024];
-                                let client_final_n = socket.read(&mut client_final_buffer).await?;
-                                if client_final_n == 0 {
+                                let Some(client_final_message) = read_auth_message(&mut socket).await? else {
                                     if !quiet_connections {
                                         warn!(
                                             "⚠️  Client {} disconnected during SCRAM client-final",
@@ -337,16 +431,16 @@ where
                                         );
                                     }
                                     return Ok(());
-                                }
+                                };
 
                                 debug!(
                                     "📊 Received {} bytes SCRAM client-final from {}",
-                                    client_final_n, peer_addr_str
+                                    client_final_message.len(), peer_addr_str
                                 );
 
                                 // Parse SASL Response (client-final)
                                 let client_final_data =
-                                    match parse_sasl_response(&client_final_buffer[..client_final_n])
+                                    match parse_sasl_response(&client_final_message)
                                     {
                                         Ok(data) => data,
                                         Err(e) => {
@@ -371,19 +465,18 @@ where
                                         }
                                     };
 
-                                // Get known password for verification
-                                let known_password = match scram_username.as_str() {
-                                    "username1" => "password1",
-                                    "grafana" => "password1",
-                                    "testuser" => "password1",
-                                    _ => {
+                                // Verify against the password file, the same password logs in to GraphQL afterwards
+                                let known_password = match known_password(&session_manager, &username) {
+                                    Some(password) => password,
+                                    None => {
                                         warn!(
                                             "⚠️  Unknown user '{}' for SCRAM authentication",
-                                            scram_username
+                                            username
                                         );
+                                        session_manager.metrics().auth_failures_total.inc();
                                         let error_response = create_postgres_error_response(
-                                            "28000",
-                                            "Authentication failed",
+                                            "28P01",
+                                            &format!("password authentication failed for user \"{}\"", username),
                                         );
                                         socket.write_all(&error_response).await?;
                                         return Ok(());
@@ -396,11 +489,11 @@ where
                                     &scram_context,
                                     &client_final_without_proof,
                                     &client_proof,
-                                    known_password,
+                                    &known_password,
                                 ) {
                                     Ok(server_final) => {
                                         if !quiet_connections {
-                                            info!("✅ SCRAM-SHA-256 authentication successful for user '{}'", scram_username);
+                                            info!("✅ SCRAM-SHA-256 authentication successful for user '{}'", username);
                                         }
                                         debug!("📨 Sending SCRAM server-final: {}", server_final);
 
@@ -409,14 +502,15 @@ where
                                             create_postgres_sasl_final_response(&server_final);
                                         socket.write_all(&final_response).await?;
 
-                                        // Authentication successful - use the SCRAM username and a dummy password for GraphQL
-                                        (scram_username, known_password.to_string())
+                                        // Authentication successful - log in to GraphQL with the verified password
+                                        (username.clone(), known_password)
                                     }
                                     Err(e) => {
-                                        error!("❌ SCRAM-SHA-256 verification failed for user '{}' from {}: {}", scram_username, peer_addr_str, e);
+                                        error!("❌ SCRAM-SHA-256 verification failed for user '{}' from {}: {}", username, peer_addr_str, e);
+                                        session_manager.metrics().auth_failures_total.inc();
                                         let error_response = create_postgres_error_response(
                                             "28P01",
-                                            "Authentication failed",
+                                            &format!("password authentication failed for user \"{}\"", username),
                                         );
                                         socket.write_all(&error_response).await?;
                                         return Ok(());
@@ -431,7 +525,7 @@ where
                             );
                             error!(
                                 "🔍 SASL message hex dump: {}",
-                                hex::encode(&auth_buffer[..auth_n.min(64)])
+                                hex::encode(&auth_message[..auth_n.min(64)])
                             );
                             let error_response = create_postgres_error_response(
                                 "28P01",
@@ -448,7 +542,7 @@ where
                     );
                     error!(
                         "🔍 Received message hex dump: {}",
-                        hex::encode(&auth_buffer[..auth_n.min(64)])
+                        hex::encode(&auth_message[..auth_n.min(64)])
                     );
                     let error_response = create_postgres_error_response(
                         "28P01",
@@ -458,19 +552,37 @@ where
                     return Ok(());
                 }
             }
+            AuthContext::Trust => match session_manager.default_credentials() {
+                Some((default_user, default_password)) => (default_user.clone(), default_password.clone()),
+                None => {
+                    error!("❌ Trust authentication without --default-user for {}", peer_addr_str);
+                    let error_response = create_postgres_error_response("28000", "Trust authentication is not configured");
+                    socket.write_all(&error_response).await?;
+                    return Ok(());
+                }
+            },
+            AuthContext::Jwt | AuthContext::Password => match parse_postgres_password(&auth_message) {
+                Some(password) => (username.clone(), password),
+                None => {
+                    error!("❌ Invalid password format from {}", peer_addr_str);
+                    let error_response = create_postgres_error_response("28P01", "Invalid password format");
+                    socket.write_all(&error_response).await?;
+                    return Ok(());
+                }
+            },
             AuthContext::Md5(_salt) => {
                 // Parse password from response (handles both cleartext and MD5)
-                let password = parse_postgres_password(&auth_buffer[..auth_n]);
+                let password = parse_postgres_password(&auth_message);
                 if password.is_none() {
                     error!("❌ Invalid password format from {}", peer_addr_str);
                     error!(
                         "🔍 Password message hex dump: {}",
-                        hex::encode(&auth_buffer[..auth_n.min(64)])
+                        hex::encode(&auth_message[..auth_n.min(64)])
                     );
                     if auth_n > 0 {
                         error!(
                             "🔍 First byte: 0x{:02x} (expected 'p' = 0x70)",
-                            auth_buffer[0]
+                            auth_message[0]
                         );
                     }
                     let error_response =
@@ -484,72 +596,46 @@ where
 
         if !quiet_connections {
             info!(
-                "🔑 Authenticating user '{}' from {} via GraphQL",
-                username_final, peer_addr_str
+                "🔑 Authenticating user '{}' from {} via {}",
+                username_final, peer_addr_str,
+                if matches!(auth_context, AuthContext::Jwt) { "JWT" } else { "GraphQL" }
             );
         }
 
-        // Handle MD5 authentication
-        let (is_md5_valid, actual_password) = if password_final.starts_with("md5") {
+        // Handle MD5 authentication; after SCRAM the password is already verified
+        let md5_salt = match &auth_context {
+            AuthContext::Md5(salt) if password_final.starts_with("md5") => Some(salt),
+            _ => None,
+        };
+        let (is_md5_valid, actual_password) = if let Some(salt) = md5_salt {
             if !quiet_connections {
                 info!("🔐 Received MD5 password response from {}", peer_addr_str);
             }
             debug!("🔍 MD5 response: {}", password_final);
 
             // For MD5 verification, we need to know the original password
-            // In a real implementation, you'd store password hashes in a database
-            // For now, we'll hardcode known user credentials for testing
-            let known_password = match username_final.as_str() {
-                "username1" => "password1",
-                "grafana" => "password1", // Allow grafana user with same password
-                "testuser" => "password1",
-                _ => {
-                    warn!(
-                        "⚠️  Unknown user '{}' for MD5 authentication",
-                        username_final
-                    );
-                    ""
-                }
-            };
-
-            if known_password.is_empty() {
-                (false, String::new())
-            } else {
-                let is_valid = match &auth_context {
-                    AuthContext::Md5(salt) => {
-                        let expected_hash =
-                            compute_postgres_md5_hash(&username_final, known_password, salt);
-                        debug!("🔍 Expected MD5 hash: {}", expected_hash);
-                        debug!("🔍 Received MD5 hash: {}", password_final);
-                        let valid = verify_postgres_md5_auth(
-                            &username_final,
-                            known_password,
-                            salt,
-                            &password_final,
+            match known_password(&session_manager, &username_final) {
+                Some(known_password) => {
+                    let expected_hash = compute_postgres_md5_hash(&username_final, &known_password, salt);
+                    debug!("🔍 Expected MD5 hash: {}", expected_hash);
+                    debug!("🔍 Received MD5 hash: {}", password_final);
+                    let valid = verify_postgres_md5_auth(&username_final, &known_password, salt, &password_final);
+                    if !quiet_connections {
+                        info!(
+                            "🔍 MD5 verification for user '{}': {}",
+                            username_final,
+                            if valid { "✅ PASSED" } else { "❌ FAILED" }
                         );
-                        if !quiet_connections {
-                            info!(
-                                "🔍 MD5 verification for user '{}': {}",
-                                username_final,
-                                if valid { "✅ PASSED" } else { "❌ FAILED" }
-                            );
-                        }
-                        valid
                     }
-                    AuthContext::Scram => {
-                        if !quiet_connections {
-                            info!(
-                                "🔍 SCRAM-SHA-256 verification for user '{}' (not fully implemented)",
-                                username_final
-                            );
-                        }
-                        true // For now, accept SCRAM attempts
-                    }
-                };
-                (is_valid, known_password.to_string())
+                    (valid, known_password)
+                }
+                None => {
+                    warn!("⚠️  Unknown user '{}' for MD5 authentication", username_final);
+                    (false, String::new())
+                }
             }
         } else {
-            if !quiet_connections {
+            if matches!(auth_context, AuthContext::Md5(_) | AuthContext::Password) && !quiet_connections {
                 info!("🔐 Received cleartext password from {}", peer_addr_str);
             }
             (true, password_final)
@@ -560,31 +646,29 @@ where
                 "❌ Authentication failed for user '{}' from {}",
                 username_final, peer_addr_str
             );
+            session_manager.metrics().auth_failures_total.inc();
             let error_response =
                 create_postgres_error_response("28P01", "MD5 authentication failed");
             socket.write_all(&error_response).await?;
             return Ok(());
         }
 
-        // Authenticate with GraphQL using the actual password
-        let authenticated_session =
-            match session_manager.authenticate(&username_final, &actual_password).await {
+        // Authenticate with GraphQL using the actual password, or validate the JWT without a login
+        let authentication = if matches!(auth_context, AuthContext::Jwt) {
+            session_manager.authenticate_jwt(&actual_password).await
+        } else {
+            session_manager.authenticate(&username_final, &actual_password).await
+        };
+        let mut authenticated_session =
+            match authentication {
                 Ok(session) => {
                     if !quiet_connections {
                         info!(
                             "✅ Authentication successful for user '{}' from {}",
-                            username_final, peer_addr_str
+                            session.username, peer_addr_str
                         );
                     }
 
-                    // Send authentication OK response
-                    let auth_ok_response = create_postgres_auth_ok_response();
-                    debug!("📤 Sending authentication OK to {}", peer_addr_str);
-                    if let Err(e) = socket.write_all(&auth_ok_response).await {
-                        error!("❌ Failed to send auth OK to {}: {}", peer_addr_str, e);
-                        return Ok(());
-                    }
-
                     session
                 }
                 Err(e) => {
@@ -607,6 +691,8 @@ where
                 &authenticated_session.session_id,
                 addr,
                 application_name.clone(),
+                database_name,
+                timezone,
             ).await {
                 Ok(id) => Some(id),
                 Err(e) => {
@@ -618,15 +704,43 @@ where
             None
         };
 
+        // Settings of the options parameter (-c name=value) apply as if the client had run SET
+        if let Some(conn_id) = connection_id {
+            if !startup_options.is_empty() {
+                debug!("⚙️  Applying startup options from {}: {:?}", peer_addr_str, startup_options);
+            }
+            for (name, value) in &startup_options {
+                session_manager.set_session_variable(conn_id, name, value).await;
+            }
+        }
+
+        // Send authentication OK response with this connection's cancel key
+        let (process_id, secret_key) = match connection_id {
+            Some(conn_id) => (conn_id, session_manager.register_cancel_key(conn_id).await),
+            None => (0, 0),
+        };
+        let parameter_status = session_manager.connection_parameter_status(connection_id).await;
+        let auth_ok_response = create_postgres_auth_ok_response(process_id, secret_key, &parameter_status);
+        debug!("📤 Sending authentication OK to {}", peer_addr_str);
+        if let Err(e) = socket.write_all(&auth_ok_response).await {
+            error!("❌ Failed to send auth OK to {}: {}", peer_addr_str, e);
+            if let Some(conn_id) = connection_id {
+                session_manager.unregister_connection(conn_id).await;
+            }
+            return Ok(());
+        }
+
         // Main query processing loop
         if !quiet_connections {
             info!("🔄 Starting PostgreSQL query loop for {}", peer_addr_str);
         }
         let mut buffer = vec![0; 4096];
+        let mut message_reader = MessageReader::new();
         
         // Set up keep-alive interval
         let mut keep_alive_timer = interval(Duration::from_secs(keep_alive_interval));
         keep_alive_timer.tick().await; // Skip the immediate first tick
+        let shutdown = session_manager.shutdown_token();
 
         loop {
             debug!("📖 Waiting for PostgreSQL query from {}", peer_addr_str);
@@ -644,57 +758,41 @@ where
                                 break;
                             }
                             
-                            // Process the received data
-
+                            // Accumulate the received data; messages may span multiple reads
             debug!(
                 "📊 Received {} bytes from PostgreSQL client {}",
                 n, peer_addr_str
             );
+            message_reader.push(&buffer[..n]);
 
-            let mut pos = 0;
-            let mut response_buffer = Vec::new();
-            
-            // Log all incoming messages in this batch
-            debug!("📨 Processing batch of {} bytes from {}", n, peer_addr_str);
-            let mut temp_pos = 0;
-            while temp_pos < n && temp_pos + 5 <= n {
-                let msg_type = buffer[temp_pos] as char;
-                let msg_len = u32::from_be_bytes([
-                    buffer[temp_pos + 1],
-                    buffer[temp_pos + 2],
-                    buffer[temp_pos + 3],
-                    buffer[temp_pos + 4],
-                ]) as usize;
-                debug!("   Incoming message: type='{}' length={}", msg_type, msg_len);
-                temp_pos += 1 + msg_len;
-                if temp_pos > n {
-                    break;
+            // Log in again before the next query once the session is older than --session-max-lifetime
+            if session_manager.session_lifetime_exceeded(&authenticated_session) {
+                match session_manager.renew_session(connection_id, &authenticated_session, &actual_password).await {
+                    Ok(session) => authenticated_session = session,
+                    Err(e) => warn!("⚠️  Failed to renew the GraphQL session of {}: {}", peer_addr_str, e),
                 }
             }
-            
-            while pos < n {
-                let message_slice = &buffer[pos..n];
-                if message_slice.len() < 5 {
-                    // Not enough data for a full message header
-                    break;
-                }
 
-                let message_len = u32::from_be_bytes([
-                    message_slice[1],
-                    message_slice[2],
-                    message_slice[3],
-                    message_slice[4],
-                ]) as usize;
-                
-                let total_message_len = 1 + message_len;
+            let mut response_buffer = Vec::new();
 
-                if message_slice.len() < total_message_len {
-                    // Incomplete message in the buffer
-                    break;
-                }
+            loop {
+                let message = match message_reader.next_message() {
+                    Ok(Some(message)) => message,
+                    Ok(None) => break,
+                    Err(e) => {
+                        error!("❌ Protocol error from {}: {}", peer_addr_str, e);
+                        response_buffer.extend_from_slice(&create_postgres_error_response("08P01", &e.to_string()));
+                        socket.write_all(&response_buffer).await?;
+                        if let Some(conn_id) = connection_id {
+                            session_manager.unregister_connection(conn_id).await;
+                        }
+                        return Ok(());
+                    }
+                };
+                debug!("   Incoming message: type='{}' length={}", message[0] as char, message.len() - 1);
 
                 match handle_postgres_message(
-                    &message_slice[..total_message_len],
+                    &message,
                     &mut connection_state,
                     &authenticated_session,
                     session_manager.clone(),
@@ -707,7 +805,7 @@ where
                         if !response.is_empty() {
                             debug!("📤 Adding {} bytes to response buffer for message type '{}'", 
                                 response.len(), 
-                                message_slice[0] as char
+                                message[0] as char
                             );
                             response_buffer.extend_from_slice(&response);
                         }
@@ -724,16 +822,14 @@ where
                             return Ok(());
                         } else {
                             error!("❌ Error for {}: {}", peer_addr_str, e);
-                            let mut error_response = create_postgres_error_response(
-                                "42000",
-                                &format!("Query failed: {}", e),
-                            );
-                            error_response.extend_from_slice(&super::response::create_ready_for_query_response());
-                            response_buffer.extend_from_slice(&error_response);
+                            response_buffer.extend_from_slice(&query_error_response(&e));
                         }
                     }
                 }
-                pos += total_message_len;
+            }
+
+            if message_reader.buffered_len() > 0 {
+                debug!("🧩 {} bytes of a partial message buffered from {}", message_reader.buffered_len(), peer_addr_str);
             }
 
             if !response_buffer.is_empty() {
@@ -758,6 +854,25 @@ where
                     }
                 }
                 
+                // Value change of a LISTEN channel; like keep-alives only sent between queries
+                Some(notification) = notifications.recv() => {
+                    debug!("🔔 Sending notification to {}", peer_addr_str);
+                    socket.write_all(&notification).await?;
+                }
+
+                // Server shutdown; only reached between queries, so a running query completes first
+                _ = shutdown.cancelled() => {
+                    if !quiet_connections {
+                        info!("🛑 Closing connection to {} for server shutdown", peer_addr_str);
+                    }
+                    let notice = create_postgres_fatal_response("57P01", "terminating connection due to administrator command");
+                    if let Err(e) = socket.write_all(&notice).await {
+                        debug!("⚠️  Failed to send shutdown notice to {}: {}", peer_addr_str, e);
+                    }
+                    let _ = socket.shutdown().await;
+                    break;
+                }
+
                 // Keep-alive timer fired
                 _ = keep_alive_timer.tick() => {
                     debug!("💓 Keep-alive timer fired for {}", peer_addr_str);
@@ -817,6 +932,24 @@ where
     Ok(())
 }
 
+/// Password to verify SCRAM and MD5 responses against. Without a password file only the
+/// built-in test users are known.
+fn known_password(session_manager: &SessionManager, username: &str) -> Option<String> {
+    if session_manager.scram_enabled() {
+        return session_manager.scram_password(username).map(str::to_string);
+    }
+    match username {
+        "username1" | "grafana" | "testuser" => Some("password1".to_string()),
+        _ => None,
+    }
+}
+
+/// The replication startup parameter asks for a walsender connection: `database` for logical,
+/// a true boolean for physical replication
+fn is_replication_mode(value: &str) -> bool {
+    matches!(value.to_lowercase().as_str(), "database" | "true" | "on" | "yes" | "1")
+}
+
 fn parse_startup_parameters(data: &[u8]) -> std::collections::HashMap<String, String> {
     let mut params = std::collections::HashMap::new();
     let mut pos = 0;
@@ -858,6 +991,50 @@ fn parse_startup_parameters(data: &[u8]) -> std::collections::HashMap<String, St
     params
 }
 
+/// Settings of the `options` startup parameter as name/value pairs. Like the server's command
+/// line it holds `-c name=value`, `-cname=value` or `--name=value` switches separated by
+/// whitespace; a backslash escapes the next character, e.g. a space in a value.
+fn parse_startup_options(options: &str) -> Vec<(String, String)> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut chars = options.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => word.extend(chars.next()),
+            c if c.is_whitespace() => {
+                if !word.is_empty() {
+                    words.push(std::mem::take(&mut word));
+                }
+            }
+            c => word.push(c),
+        }
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+
+    let mut settings = Vec::new();
+    let mut words = words.into_iter();
+    while let Some(word) = words.next() {
+        let setting = if word == "-c" {
+            words.next()
+        } else if let Some(setting) = word.strip_prefix("--") {
+            // --search-path=x is the same as -c search_path=x
+            Some(match setting.split_once('=') {
+                Some((name, value)) => format!("{}={}", name.replace('-', "_"), value),
+                None => setting.to_string(),
+            })
+        } else {
+            word.strip_prefix("-c").map(str::to_string)
+        };
+        match setting.as_deref().and_then(|setting| setting.split_once('=')) {
+            Some((name, value)) if !name.is_empty() => settings.push((name.to_string(), value.to_string())),
+            _ => warn!("⚠️  Ignoring unsupported startup option: {}", setting.unwrap_or(word)),
+        }
+    }
+    settings
+}
+
 async fn read_complete_postgres_message_stream<T>(
     socket: &mut T,
     initial_data: &[u8],
@@ -892,3 +1069,157 @@ where
 
     Ok(complete_message)
 }
+
+/// Read one complete message during authentication; JWTs and SASL messages can be larger than a
+/// single read returns. `None` when the client disconnected.
+async fn read_auth_message<T>(socket: &mut T) -> Result<Option<Vec<u8>>>
+where
+    T: AsyncRead + Unpin,
+{
+    let mut message_reader = MessageReader::new();
+    let mut buffer = vec![0; 4096];
+    loop {
+        if let Some(message) = message_reader.next_message()? {
+            return Ok(Some(message));
+        }
+        let n = socket.read(&mut buffer).await?;
+        if n == 0 {
+            return Ok(None);
+        }
+        message_reader.push(&buffer[..n]);
+    }
+}
+
+/// ErrorResponse for a failed message followed by ReadyForQuery, so the connection stays usable
+pub(super) fn query_error_response(e: &anyhow::Error) -> Vec<u8> {
+    let mut error_response = if e.downcast_ref::<crate::query_handler::QueryCanceled>().is_some() {
+        create_postgres_error_response("57014", &e.to_string())
+    } else if let Some(sql_state) = e.downcast_ref::<crate::query_handler::SqlStateError>() {
+        let position: Vec<(u8, String)> = sql_state.position.map(|p| (b'P', p.to_string())).into_iter().collect();
+        create_postgres_error_response_with_fields(sql_state.code, &sql_state.message, &position)
+    } else {
+        let mut fields = Vec::new();
+        if let Some(graphql) = e.chain().find_map(|cause| cause.downcast_ref::<crate::graphql::client::GraphQLResponseError>()) {
+            fields.push((b'D', graphql.detail.clone()));
+        }
+        if let Some(datafusion) = e.chain().find_map(|cause| cause.downcast_ref::<datafusion::error::DataFusionError>()) {
+            fields.push((b'W', crate::datafusion_handler::failed_step(datafusion).to_string()));
+        }
+        create_postgres_error_response_with_fields(
+            "42000",
+            &format!("Query failed: {}", e),
+            &fields,
+        )
+    };
+    error_response.extend_from_slice(&super::response::create_ready_for_query_response());
+    error_response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::{AuthenticatedSession, SessionManager};
+    use crate::query_handler::QueryHandler;
+    use std::sync::Arc;
+
+    // GraphQL endpoint that accepts requests but never answers
+    async fn spawn_hanging_graphql_server() -> String {
+        crate::test_support::spawn_graphql_server(|_| crate::test_support::MockResponse::Hang).await
+    }
+
+    #[tokio::test]
+    async fn test_query_timeout_returns_57014_and_keeps_connection_usable() {
+        let url = spawn_hanging_graphql_server().await;
+        let session = AuthenticatedSession {
+            session_id: "test".to_string(),
+            username: "test".to_string(),
+            token: "token".to_string(),
+            expires: String::new(),
+            client: Arc::new(crate::graphql::client::GraphQLClient::new(url.clone())),
+            graphql_url: url.clone(),
+            datafusion_context: Default::default(),
+            external_token: false,
+            created_at: std::time::Instant::now(),
+        };
+        let session_manager = Arc::new(SessionManager::new(url).with_query_timeout_ms(1000));
+
+        let start = std::time::Instant::now();
+        let err = QueryHandler::execute_query_with_connection(
+            "SELECT * FROM tagvalues WHERE tag_name = 'Tag_1'",
+            &session,
+            session_manager.clone(),
+            None,
+        )
+        .await
+        .unwrap_err();
+        assert!(start.elapsed() < std::time::Duration::from_secs(3), "timeout took {:?}", start.elapsed());
+
+        let response = query_error_response(&err);
+        assert_eq!(response[0], b'E');
+        let text = String::from_utf8_lossy(&response);
+        assert!(text.contains("C57014"), "{}", text);
+        assert!(text.contains("canceling statement due to statement timeout"), "{}", text);
+        assert_eq!(&response[response.len() - 6..], &[b'Z', 0, 0, 0, 5, b'I']);
+
+        // The next query on the same session runs normally
+        let result = QueryHandler::execute_query_with_connection("SELECT 42 AS answer", &session, session_manager, None)
+            .await
+            .unwrap();
+        assert_eq!(result.rows.len(), 1);
+    }
+
+    // Value of a field of the ErrorResponse at the start of `response`
+    fn error_field(response: &[u8], field_type: u8) -> Option<String> {
+        assert_eq!(response[0], b'E');
+        let length = u32::from_be_bytes([response[1], response[2], response[3], response[4]]) as usize;
+        response[5..1 + length]
+            .split(|&b| b == 0)
+            .take_while(|field| !field.is_empty())
+            .find(|field| field[0] == field_type)
+            .map(|field| String::from_utf8_lossy(&field[1..]).to_string())
+    }
+
+    #[tokio::test]
+    async fn test_error_response_fields() {
+        // A select list may be empty, so the error is at the end of the input after WHERE
+        let sql = "SELECT FROM tagvalues WHERE";
+        let response = query_error_response(&crate::sql_handler::SqlHandler::parse_query(sql).unwrap_err());
+        assert_eq!(error_field(&response, b'C').as_deref(), Some("42601"));
+        assert_eq!(error_field(&response, b'P').as_deref(), Some("28"));
+
+        let sql = "SELECT *\nFORM tagvalues";
+        let response = query_error_response(&crate::sql_handler::SqlHandler::parse_query(sql).unwrap_err());
+        let position: usize = error_field(&response, b'P').unwrap().parse().unwrap();
+        assert!(sql.chars().skip(position - 1).collect::<String>().starts_with("FORM"), "position {}", position);
+        assert!(!error_field(&response, b'M').unwrap().contains("Line:"));
+
+        // DataFusion errors name the failed step
+        let schema = Arc::new(arrow::datatypes::Schema::new(vec![arrow::datatypes::Field::new("tag_name", arrow::datatypes::DataType::Utf8, true)]));
+        let batch = arrow::record_batch::RecordBatch::new_empty(schema);
+        let err = crate::datafusion_handler::execute_query("SELECT missing FROM t", batch, "t").await.unwrap_err();
+        let response = query_error_response(&err);
+        assert_eq!(error_field(&response, b'C').as_deref(), Some("42000"));
+        assert_eq!(error_field(&response, b'W').as_deref(), Some("DataFusion query planning"));
+
+        // GraphQL errors carry the server's message as detail
+        let err = anyhow::Error::from(crate::graphql::client::GraphQLResponseError::new("LoggedTagValues query errors", "Tag not found"));
+        let response = query_error_response(&err.context("Failed to read logged values"));
+        assert_eq!(error_field(&response, b'D').as_deref(), Some("Tag not found"));
+        assert_eq!(error_field(&response, b'W'), None);
+    }
+
+    #[test]
+    fn test_parse_startup_options() {
+        let settings = parse_startup_options;
+        assert_eq!(
+            settings("-c application_name=MyApp -c search_path=public"),
+            vec![("application_name".to_string(), "MyApp".to_string()), ("search_path".to_string(), "public".to_string())]
+        );
+        assert_eq!(settings("-csearch_path=myschema"), vec![("search_path".to_string(), "myschema".to_string())]);
+        assert_eq!(settings("--search-path=myschema"), vec![("search_path".to_string(), "myschema".to_string())]);
+        assert_eq!(settings(r"-c application_name=My\ App"), vec![("application_name".to_string(), "My App".to_string())]);
+        assert_eq!(settings("  -c  statement_timeout=0  "), vec![("statement_timeout".to_string(), "0".to_string())]);
+        assert!(settings("-v -c noequals").is_empty());
+        assert!(settings("").is_empty());
+    }
+}
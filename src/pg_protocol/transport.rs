@@ -0,0 +1,160 @@
+use crate::auth::ClientAddr;
+use anyhow::Result;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::net::TcpStream;
+#[cfg(unix)]
+use tokio::net::{UnixListener, UnixStream};
+#[cfg(unix)]
+use tracing::{info, warn};
+
+/// Client connection over TCP or a Unix domain socket
+pub(super) enum Transport {
+    Tcp(TcpStream),
+    #[cfg(unix)]
+    Unix(UnixStream),
+}
+
+impl AsyncRead for Transport {
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Transport::Tcp(stream) => Pin::new(stream).poll_read(cx, buf),
+            #[cfg(unix)]
+            Transport::Unix(stream) => Pin::new(stream).poll_read(cx, buf),
+        }
+    }
+}
+
+impl AsyncWrite for Transport {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        match self.get_mut() {
+            Transport::Tcp(stream) => Pin::new(stream).poll_write(cx, buf),
+            #[cfg(unix)]
+            Transport::Unix(stream) => Pin::new(stream).poll_write(cx, buf),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Transport::Tcp(stream) => Pin::new(stream).poll_flush(cx),
+            #[cfg(unix)]
+            Transport::Unix(stream) => Pin::new(stream).poll_flush(cx),
+        }
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Transport::Tcp(stream) => Pin::new(stream).poll_shutdown(cx),
+            #[cfg(unix)]
+            Transport::Unix(stream) => Pin::new(stream).poll_shutdown(cx),
+        }
+    }
+}
+
+/// Optional Unix domain socket listener next to the TCP listener. Without a configured
+/// path `accept` never completes, so it can always take part in the accept loop.
+pub(super) struct UnixSocketListener {
+    #[cfg(unix)]
+    listener: Option<(UnixListener, std::path::PathBuf)>,
+}
+
+impl UnixSocketListener {
+    /// Bind the socket. A directory gets the PostgreSQL socket file name `.s.PGSQL.<port>`,
+    /// so `psql -h <directory>` finds it.
+    #[cfg(unix)]
+    pub(super) fn bind(path: Option<&str>, port: u16) -> Result<Self> {
+        let Some(path) = path else {
+            return Ok(Self { listener: None });
+        };
+        let mut socket_path = std::path::PathBuf::from(path);
+        if socket_path.is_dir() {
+            socket_path.push(format!(".s.PGSQL.{}", port));
+        }
+        // A socket file left behind by an earlier run would make bind fail
+        if socket_path.exists() {
+            warn!("⚠️  Removing stale Unix socket {}", socket_path.display());
+            std::fs::remove_file(&socket_path)?;
+        }
+        let listener = UnixListener::bind(&socket_path)?;
+        info!("🐘 PostgreSQL-like server listening on Unix socket {}", socket_path.display());
+        Ok(Self { listener: Some((listener, socket_path)) })
+    }
+
+    #[cfg(not(unix))]
+    pub(super) fn bind(path: Option<&str>, _port: u16) -> Result<Self> {
+        match path {
+            Some(_) => Err(anyhow::anyhow!("Unix domain sockets are not supported on this platform")),
+            None => Ok(Self {}),
+        }
+    }
+
+    #[cfg(unix)]
+    pub(super) async fn accept(&self) -> Result<(Transport, ClientAddr)> {
+        use std::sync::atomic::{AtomicU64, Ordering};
+        static SOCKET_ID_COUNTER: AtomicU64 = AtomicU64::new(1);
+
+        let Some((listener, _)) = &self.listener else {
+            return std::future::pending().await;
+        };
+        let (stream, _) = listener.accept().await?;
+        let credentials = stream.peer_cred().ok();
+        let client_addr = ClientAddr::Unix {
+            pid: credentials.and_then(|c| c.pid()),
+            uid: credentials.map(|c| c.uid()),
+            socket_id: SOCKET_ID_COUNTER.fetch_add(1, Ordering::Relaxed),
+        };
+        Ok((Transport::Unix(stream), client_addr))
+    }
+
+    #[cfg(not(unix))]
+    pub(super) async fn accept(&self) -> Result<(Transport, ClientAddr)> {
+        std::future::pending().await
+    }
+}
+
+#[cfg(unix)]
+impl Drop for UnixSocketListener {
+    fn drop(&mut self) {
+        if let Some((_, socket_path)) = &self.listener {
+            let _ = std::fs::remove_file(socket_path);
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    #[tokio::test]
+    async fn test_unix_socket_reports_peer_credentials() {
+        let dir = std::env::temp_dir().join(format!("pgwire-unix-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let listener = UnixSocketListener::bind(dir.to_str(), 5432).unwrap();
+        let socket_path = dir.join(".s.PGSQL.5432");
+        assert!(socket_path.exists());
+
+        let mut client = UnixStream::connect(&socket_path).await.unwrap();
+        let (mut transport, client_addr) = listener.accept().await.unwrap();
+        match client_addr {
+            ClientAddr::Unix { pid, uid, .. } => {
+                assert_eq!(pid, Some(std::process::id() as i32));
+                assert!(uid.is_some());
+            }
+            other => panic!("expected a Unix peer, got {}", other),
+        }
+        assert_eq!(client_addr.host(), "local");
+        assert_eq!(client_addr.port(), -1);
+
+        client.write_all(b"ping").await.unwrap();
+        let mut buffer = [0u8; 4];
+        transport.read_exact(&mut buffer).await.unwrap();
+        assert_eq!(&buffer, b"ping");
+
+        drop(listener);
+        assert!(!socket_path.exists(), "socket file is removed on shutdown");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
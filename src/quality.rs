@@ -0,0 +1,117 @@
+// Main quality, the top byte of an OPC UA status code
+pub const QUALITY_GOOD: i32 = 0x00;
+pub const QUALITY_UNCERTAIN: i32 = 0x40;
+pub const QUALITY_BAD: i32 = 0x80;
+
+// WinCC sub-statuses and the 12-bit SubCode (bits 16-27) of the OPC UA status code they
+// correspond to. Sub-statuses without an OPC UA counterpart have no subcode.
+const SUB_STATUS_CODES: &[(&str, i32)] = &[
+    ("NON_SPECIFIC", 0x000),
+    ("NO_COMMUNICATION_NO_USABLE_VALUE", 0x031),  // BadNoCommunication
+    ("NO_COMMUNICATION_WITH_LAST_USABLE_VALUE", 0x08F), // UncertainNoCommunicationLastUsableValue
+    ("CONFIGURATION_ERROR", 0x089),               // BadConfigurationError
+    ("NOT_CONNECTED", 0x08A),                     // BadNotConnected
+    ("DEVICE_FAILURE", 0x08B),                    // BadDeviceFailure
+    ("SENSOR_FAILURE", 0x08C),                    // BadSensorFailure
+    ("OUT_OF_SERVICE", 0x08D),                    // BadOutOfService
+    ("LAST_USABLE_VALUE", 0x090),                 // UncertainLastUsableValue
+    ("SUBSTITUTE_VALUE", 0x091),                  // UncertainSubstituteValue
+    ("INITIAL_VALUE", 0x092),                     // UncertainInitialValue
+    ("SENSOR_CONVERSION", 0x093),                 // UncertainSensorNotAccurate
+    ("RANGE_VIOLATION", 0x094),                   // UncertainEngineeringUnitsExceeded
+    ("SUB_NORMAL", 0x095),                        // UncertainSubNormal
+    ("LOCAL_OVERRIDE", 0x096),                    // GoodLocalOverride
+    ("CONFIG_ERROR", 0x20F),                      // UncertainConfigurationError
+    ("INIT_ACKED", 0x401),                        // GoodCascadeInitializationAcknowledged
+    ("INITREQ", 0x402),                           // GoodCascadeInitializationRequest
+    ("NOT_INVITED", 0x403),                       // GoodCascadeNotInvited
+    ("DO_NOT_SELECT", 0x404),                     // GoodCascadeNotSelected
+    ("INIT_FAILSAFE", 0x408),                     // GoodInitiateFaultState
+];
+
+// Main qualities as sent by WinCC, longest first so GOOD_CASCADE is not taken for GOOD
+const MAIN_QUALITIES: &[(&str, i32)] = &[
+    ("GOOD_NON_CASCADE", QUALITY_GOOD),
+    ("GOOD_CASCADE", QUALITY_GOOD),
+    ("UNCERTAIN", QUALITY_UNCERTAIN),
+    ("GOOD", QUALITY_GOOD),
+    ("BAD", QUALITY_BAD),
+];
+
+/// Split a quality like GOOD_CASCADE, BAD_NOT_CONNECTED or GOOD_NON_SPECIFIC into its
+/// main quality code and the sub-status that follows it ("" if none)
+fn split_quality(quality: &str) -> Option<(i32, String)> {
+    let quality = quality.trim().to_uppercase();
+    MAIN_QUALITIES.iter().find_map(|(name, code)| {
+        let rest = quality.strip_prefix(name)?;
+        if rest.is_empty() || rest.starts_with('_') {
+            Some((*code, rest.trim_start_matches('_').to_string()))
+        } else {
+            None
+        }
+    })
+}
+
+/// quality_code: 0x00 for GOOD, 0x40 for UNCERTAIN and 0x80 for BAD, the severity byte of
+/// the OPC UA status code
+pub fn parse_quality_code(quality_str: &str) -> Option<i32> {
+    split_quality(quality_str).map(|(code, _)| code)
+}
+
+/// quality_subcode: the OPC UA SubCode of the sub-status, taken from `sub_status` or else
+/// from a sub-status appended to the quality. 0 if there is none, NULL if it has no SubCode.
+pub fn parse_quality_subcode(quality_str: &str, sub_status: Option<&str>) -> Option<i32> {
+    let (_, appended) = split_quality(quality_str)?;
+    let sub_status = sub_status.map(str::to_uppercase).unwrap_or(appended);
+    if sub_status.is_empty() {
+        return Some(0);
+    }
+    SUB_STATUS_CODES
+        .iter()
+        .find(|(name, _)| *name == sub_status)
+        .map(|(_, subcode)| *subcode)
+}
+
+/// Name of a quality_code, the main quality as shown in the quality column. Only the two
+/// severity bits are looked at, so the top byte of any OPC UA status code works as well.
+pub fn quality_description(code: i32) -> Option<&'static str> {
+    match code & 0xC0 {
+        QUALITY_GOOD => Some("GOOD"),
+        QUALITY_UNCERTAIN => Some("UNCERTAIN"),
+        QUALITY_BAD => Some("BAD"),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quality_codes() {
+        assert_eq!(parse_quality_code("GOOD_NON_SPECIFIC"), Some(QUALITY_GOOD));
+        assert_eq!(parse_quality_code("GOOD_CASCADE"), Some(QUALITY_GOOD));
+        assert_eq!(parse_quality_code("uncertain"), Some(QUALITY_UNCERTAIN));
+        assert_eq!(parse_quality_code("BAD_NOT_CONNECTED"), Some(QUALITY_BAD));
+        assert_eq!(parse_quality_code("GOODISH"), None);
+
+        assert_eq!(parse_quality_subcode("GOOD_NON_SPECIFIC", None), Some(0));
+        assert_eq!(parse_quality_subcode("GOOD_NON_CASCADE", None), Some(0));
+        assert_eq!(parse_quality_subcode("BAD_NOT_CONNECTED", None), Some(0x08A));
+        assert_eq!(parse_quality_subcode("UNCERTAIN", Some("SUBSTITUTE_VALUE")), Some(0x091));
+        assert_eq!(parse_quality_subcode("GOOD_CASCADE", Some("LOCAL_OVERRIDE")), Some(0x096));
+        assert_eq!(parse_quality_subcode("GOOD_NON_CASCADE", Some("UPDATE_EVENT")), None);
+        assert_eq!(parse_quality_subcode("UNKNOWN", None), None);
+    }
+
+    #[test]
+    fn test_quality_description_round_trips() {
+        for quality in ["GOOD", "UNCERTAIN", "BAD"] {
+            let code = parse_quality_code(quality).unwrap();
+            assert_eq!(quality_description(code), Some(quality));
+        }
+        // Top byte of UncertainConfigurationError (0x420F0000)
+        assert_eq!(quality_description(0x42), Some("UNCERTAIN"));
+        assert_eq!(quality_description(0xC0), None);
+    }
+}
@@ -1,14 +1,62 @@
-use crate::auth::AuthenticatedSession;
-use crate::query_handler::QueryHandler;
-use crate::tables::QueryInfo;
+use crate::auth::{AuthenticatedSession, SessionManager};
+use crate::graphql::types::{ActiveAlarm, AlarmIdentifierInput};
+use crate::query_handler::{QueryHandler, QueryResult, SqlStateError};
+use crate::tables::{QueryInfo, VirtualTable};
 use anyhow::Result;
+use std::sync::Arc;
 use std::time::Instant;
-use tracing::debug;
+use tracing::{debug, info, warn};
+
+/// Columns compared by activealarms_changes without --alarm-diff-columns
+pub const DEFAULT_ALARM_DIFF_COLUMNS: &[&str] = &["state", "priority"];
+
+/// Value parser of --alarm-diff-columns: an activealarms column other than the name and
+/// instance_id that identify an alarm
+pub(crate) fn parse_alarm_diff_column(arg: &str) -> Result<String, String> {
+    let column = arg.trim().to_lowercase();
+    let columns = VirtualTable::ActiveAlarms.metadata().map(|metadata| metadata.columns).unwrap_or_default();
+    if column != "name" && column != "instance_id" && columns.iter().any(|c| c.name == column) {
+        Ok(column)
+    } else {
+        Err(format!("'{}' is not a column of activealarms that can change", arg))
+    }
+}
+
+/// Columns of --alarm-diff-columns, or the default ones when it was not parsed (tests)
+pub fn alarm_diff_columns() -> Vec<String> {
+    match crate::ALARM_DIFF_COLUMNS.get() {
+        Some(columns) => columns.clone(),
+        None => DEFAULT_ALARM_DIFF_COLUMNS.iter().map(|column| column.to_string()).collect(),
+    }
+}
+
+// Value of an activealarms column before the alarm texts are reduced to one language
+fn alarm_column_value(alarm: &ActiveAlarm, column: &str) -> serde_json::Value {
+    match column {
+        "alarm_group_id" => serde_json::json!(alarm.alarm_group_id),
+        "raise_time" => serde_json::json!(alarm.raise_time),
+        "acknowledgment_time" => serde_json::json!(alarm.acknowledgment_time),
+        "clear_time" => serde_json::json!(alarm.clear_time),
+        "reset_time" => serde_json::json!(alarm.reset_time),
+        "modification_time" => serde_json::json!(alarm.modification_time),
+        "state" => serde_json::json!(alarm.state),
+        "priority" => serde_json::json!(alarm.priority),
+        "event_text" => serde_json::json!(alarm.event_text),
+        "info_text" => serde_json::json!(alarm.info_text),
+        "origin" => serde_json::json!(alarm.origin),
+        "area" => serde_json::json!(alarm.area),
+        "value" => serde_json::json!(alarm.value),
+        "host_name" => serde_json::json!(alarm.host_name),
+        "user_name" => serde_json::json!(alarm.user_name),
+        _ => serde_json::Value::Null,
+    }
+}
 
 impl QueryHandler {
     pub(super) async fn fetch_active_alarms_data(
         query_info: &QueryInfo,
         session: &AuthenticatedSession,
+        languages: &[String],
     ) -> Result<Vec<crate::graphql::types::ActiveAlarm>> {
         // Extract filter string if any
         let filter_string = Self::extract_alarm_filter_string(&query_info.filters).unwrap_or_default();
@@ -22,6 +70,7 @@ impl QueryHandler {
                 &session.token,
                 vec![], // system_names - empty for all systems
                 filter_string,
+                languages.to_vec(),
             )
             .await?;
         let graphql_elapsed_ms = graphql_start.elapsed().as_millis();
@@ -38,4 +87,126 @@ impl QueryHandler {
         Ok(filtered_results)
     }
 
-}
\ No newline at end of file
+    /// activealarms_changes: all active alarms, the WHERE clause is applied by DataFusion after
+    /// the comparison so that the snapshot of the connection always covers every alarm
+    pub(super) async fn fetch_active_alarm_changes_data(
+        session: &AuthenticatedSession,
+        languages: &[String],
+    ) -> Result<Vec<ActiveAlarm>> {
+        let graphql_start = Instant::now();
+        let alarms = session
+            .client
+            .get_active_alarms(&session.token, vec![], String::new(), languages.to_vec())
+            .await?;
+        debug!("🚀 GraphQL query for activealarms_changes returned {} alarms in {} ms", alarms.len(), graphql_start.elapsed().as_millis());
+        Ok(alarms)
+    }
+
+    /// activealarms_changes: compare the alarms with the ones last returned to this connection.
+    /// New alarms are ADDED, alarms that are gone are REMOVED with their last known values and
+    /// alarms with a different value in one of `diff_columns` are CHANGED. Without a connection
+    /// there is nothing to compare against, so every alarm is ADDED.
+    pub(super) async fn keep_changed_active_alarms(
+        alarms: Vec<ActiveAlarm>,
+        session_manager: &SessionManager,
+        connection_id: Option<u32>,
+        diff_columns: &[String],
+    ) -> Vec<(ActiveAlarm, &'static str)> {
+        let Some(conn_id) = connection_id else {
+            return alarms.into_iter().map(|alarm| (alarm, "ADDED")).collect();
+        };
+
+        let mut previous = session_manager.replace_active_alarm_snapshot(conn_id, &alarms).await;
+        let total = alarms.len();
+        let mut changes: Vec<(ActiveAlarm, &'static str)> = alarms
+            .into_iter()
+            .filter_map(|alarm| match previous.remove(&(alarm.name.clone(), alarm.instance_id)) {
+                None => Some((alarm, "ADDED")),
+                Some(old) => diff_columns
+                    .iter()
+                    .any(|column| alarm_column_value(&old, column) != alarm_column_value(&alarm, column))
+                    .then_some((alarm, "CHANGED")),
+            })
+            .collect();
+        let mut removed: Vec<ActiveAlarm> = previous.into_values().collect();
+        removed.sort_by(|a, b| (&a.name, a.instance_id).cmp(&(&b.name, b.instance_id)));
+        changes.extend(removed.into_iter().map(|alarm| (alarm, "REMOVED")));
+        debug!("🔁 activealarms_changes: {} changes for {} active alarms", changes.len(), total);
+        changes
+    }
+
+    /// Acknowledge alarms for UPDATE activealarms SET state = 'ACKNOWLEDGED'; only allowed with
+    /// --enable-alarm-writes. Alarms that fail are reported as warnings, the statement still succeeds.
+    pub(super) async fn acknowledge_alarms(
+        instance_ids: Vec<i32>,
+        session: &AuthenticatedSession,
+        session_manager: Arc<SessionManager>,
+    ) -> Result<QueryResult> {
+        if !crate::ENABLE_ALARM_WRITES.load(std::sync::atomic::Ordering::Relaxed) {
+            return Err(SqlStateError::new(
+                "42501",
+                "permission denied: alarm acknowledgment is disabled, start the server with --enable-alarm-writes",
+            )
+            .into());
+        }
+
+        info!("🔔 User {} acknowledging {} alarms: {:?}", session.username, instance_ids.len(), instance_ids);
+
+        // acknowledgeAlarms identifies alarms by name and instanceID, the names come from the active alarms
+        let graphql_start = Instant::now();
+        let active_alarms = session_manager
+            .circuit_breaker()
+            .call(session_manager.metrics().observe_graphql(session.client.get_active_alarms(
+                &session.token,
+                vec![],
+                String::new(),
+                vec![],
+            )))
+            .await?;
+        let mut failures = Vec::new();
+        let mut alarms = Vec::with_capacity(instance_ids.len());
+        for instance_id in instance_ids {
+            match active_alarms.iter().find(|alarm| alarm.instance_id == instance_id) {
+                Some(alarm) => alarms.push(AlarmIdentifierInput { name: alarm.name.clone(), instance_id }),
+                None => failures.push(format!("Alarm {} not acknowledged (no active alarm with this instance_id)", instance_id)),
+            }
+        }
+
+        let results = if alarms.is_empty() {
+            Vec::new()
+        } else {
+            session_manager
+                .circuit_breaker()
+                .call(session_manager.metrics().observe_graphql(session.client.acknowledge_alarms(&session.token, alarms)))
+                .await?
+        };
+
+        let failed_results: Vec<String> = results
+            .iter()
+            .filter_map(|result| {
+                let error = result.error.as_ref()?;
+                let code = error.code.as_deref().unwrap_or("1");
+                (code != "0").then(|| {
+                    format!(
+                        "Alarm {} ({}) not acknowledged (code {}: {})",
+                        result.instance_id.map(|id| id.to_string()).unwrap_or_else(|| "?".to_string()),
+                        result.alarm_name.as_deref().unwrap_or("?"),
+                        code,
+                        error.description.as_deref().unwrap_or("Unknown error")
+                    )
+                })
+            })
+            .collect();
+        let acknowledged = results.len() - failed_results.len();
+        failures.extend(failed_results);
+
+        // The command tag only counts acknowledged alarms, failures are sent as WARNING notices
+        if !failures.is_empty() {
+            warn!("⚠️  {}", failures.join(", "));
+        }
+        let mut result = QueryResult::command(format!("UPDATE {}", acknowledged));
+        result.notices = failures;
+        result.timings.graphql_time_ms = Some(graphql_start.elapsed().as_millis() as u64);
+        Ok(result)
+    }
+}
@@ -0,0 +1,187 @@
+use crate::auth::AuthenticatedSession;
+use crate::datafusion_handler;
+use crate::graphql::types::ActiveAlarm;
+use crate::query_handler::util::AlarmTextLocale;
+use crate::query_handler::QueryHandler;
+use crate::sql_handler::SqlHandler;
+use crate::tables::QueryInfo;
+use anyhow::Result;
+use arrow::array::Float64Array;
+use arrow::datatypes::{DataType, Field, Schema, TimeUnit};
+use arrow::record_batch::RecordBatch;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use tracing::debug;
+
+// Table name of the alarms inside the aggregation query
+const ALARMS_TABLE: &str = "alarms";
+
+type AggregationResult = Result<(Vec<RecordBatch>, u64)>;
+
+impl QueryHandler {
+    /// All active alarms with `duration_seconds` as the time since they were raised
+    pub(super) async fn fetch_alarm_statistics_data(
+        query_info: &QueryInfo,
+        session: &AuthenticatedSession,
+        text_locale: &AlarmTextLocale,
+    ) -> Result<RecordBatch> {
+        debug!("📊 Fetching alarm_statistics data");
+        let alarms = Self::fetch_active_alarms_data(query_info, session, &text_locale.languages).await?;
+        Self::create_active_alarm_durations_record_batch(alarms, chrono::Utc::now(), text_locale)
+    }
+
+    /// Logged alarms, which have `duration_seconds` parsed from their ISO 8601 duration
+    pub(super) async fn fetch_logged_alarm_statistics_data(
+        query_info: &QueryInfo,
+        session: &AuthenticatedSession,
+        text_locale: &AlarmTextLocale,
+    ) -> Result<RecordBatch> {
+        debug!("📊 Fetching logged_alarm_statistics data");
+        // LIMIT applies to the statistics rows, not to the alarms they are computed from
+        let alarms_query = QueryInfo { limit: None, offset: None, ..query_info.clone() };
+        let alarms = Self::fetch_logged_alarms_data(&alarms_query, session, &text_locale.languages).await?;
+        Self::create_logged_alarms_record_batch(alarms, text_locale)
+    }
+
+    fn create_active_alarm_durations_record_batch(
+        alarms: Vec<ActiveAlarm>,
+        now: chrono::DateTime<chrono::Utc>,
+        text_locale: &AlarmTextLocale,
+    ) -> Result<RecordBatch> {
+        let now_nanos = now.timestamp_nanos_opt().unwrap_or(0);
+        let durations: Vec<Option<f64>> = alarms
+            .iter()
+            .map(|alarm| {
+                Self::parse_string_timestamp_to_nanos(&alarm.raise_time)
+                    .map(|raise_nanos| (now_nanos - raise_nanos) as f64 / 1_000_000_000.0)
+            })
+            .collect();
+        Self::with_duration_seconds(Self::create_active_alarms_record_batch(alarms, text_locale)?, durations)
+    }
+
+    fn with_duration_seconds(batch: RecordBatch, durations: Vec<Option<f64>>) -> Result<RecordBatch> {
+        let mut fields: Vec<Field> = batch.schema().fields().iter().map(|field| field.as_ref().clone()).collect();
+        fields.push(Field::new("duration_seconds", DataType::Float64, true));
+        let mut columns = batch.columns().to_vec();
+        columns.push(Arc::new(Float64Array::from(durations)));
+        RecordBatch::try_new(Arc::new(Schema::new(fields)), columns).map_err(Into::into)
+    }
+
+    /// Group the alarms by (state, priority, area) with DataFusion. Conditions of the statistics
+    /// query on these columns are applied to the alarms before grouping.
+    pub(super) async fn aggregate_alarm_statistics(sql: &str, alarms: RecordBatch) -> Result<RecordBatch> {
+        let where_clause = SqlHandler::alarm_statistics_filter(sql)
+            .map(|filter| format!(" WHERE {}", filter))
+            .unwrap_or_default();
+        let aggregation_sql = format!(
+            "SELECT state, priority, area, COUNT(*) AS alarm_count, \
+             MIN(raise_time) AS oldest_raise_time, MAX(raise_time) AS newest_raise_time, \
+             AVG(duration_seconds) AS avg_duration_seconds \
+             FROM {ALARMS_TABLE}{where_clause} GROUP BY state, priority, area ORDER BY state, priority, area"
+        );
+        debug!("📊 Alarm statistics aggregation: {}", aggregation_sql);
+
+        // Type-erased, the nested DataFusion future is too deep for the Send check of the connection task
+        let aggregation: Pin<Box<dyn Future<Output = AggregationResult> + Send + '_>> =
+            Box::pin(datafusion_handler::execute_query(&aggregation_sql, alarms, ALARMS_TABLE));
+        let (batches, _) = aggregation.await?;
+        match batches.first() {
+            Some(batch) => Ok(arrow::compute::concat_batches(&batch.schema(), &batches)?),
+            None => Ok(RecordBatch::new_empty(Self::alarm_statistics_schema())),
+        }
+    }
+
+    fn alarm_statistics_schema() -> Arc<Schema> {
+        Arc::new(Schema::new(vec![
+            Field::new("state", DataType::Utf8, true),
+            Field::new("priority", DataType::Int64, true),
+            Field::new("area", DataType::Utf8, true),
+            Field::new("alarm_count", DataType::Int64, false),
+            Field::new("oldest_raise_time", DataType::Timestamp(TimeUnit::Nanosecond, None), true),
+            Field::new("newest_raise_time", DataType::Timestamp(TimeUnit::Nanosecond, None), true),
+            Field::new("avg_duration_seconds", DataType::Float64, true),
+        ]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::query_handler::{QueryResult, QueryValue};
+    use crate::tables::{SqlResult, VirtualTable};
+
+    fn active_alarm(instance_id: i32, state: &str, priority: i32, raise_time: &str) -> ActiveAlarm {
+        ActiveAlarm {
+            name: format!("Alarm_{}", instance_id),
+            instance_id,
+            alarm_group_id: None,
+            raise_time: raise_time.to_string(),
+            acknowledgment_time: None,
+            clear_time: None,
+            reset_time: None,
+            modification_time: raise_time.to_string(),
+            state: state.to_string(),
+            priority: Some(priority),
+            event_text: None,
+            info_text: None,
+            origin: None,
+            area: Some("Plant".to_string()),
+            value: None,
+            host_name: None,
+            user_name: None,
+        }
+    }
+
+    /// 6 raised alarms raised 1..=6 minutes and 4 acknowledged ones raised 10 minutes before now
+    fn ten_alarms() -> Vec<ActiveAlarm> {
+        (1..=10)
+            .map(|i| {
+                let (state, minutes) = if i <= 6 { ("RAISED", i) } else { ("ACKNOWLEDGED", 10) };
+                active_alarm(i, state, 5, &format!("2025-01-01T11:{:02}:00.000Z", 60 - minutes))
+            })
+            .collect()
+    }
+
+    async fn statistics(sql: &str) -> QueryResult {
+        let query_info = match SqlHandler::parse_query(sql).unwrap() {
+            SqlResult::Query(query_info) => query_info,
+            other => panic!("Unexpected parse result {:?}", other),
+        };
+        assert_eq!(query_info.table, VirtualTable::AlarmStatistics);
+
+        let now = chrono::DateTime::parse_from_rfc3339("2025-01-01T12:00:00.000Z").unwrap().to_utc();
+        let alarms = QueryHandler::create_active_alarm_durations_record_batch(ten_alarms(), now, &AlarmTextLocale::default()).unwrap();
+        let batch = QueryHandler::aggregate_alarm_statistics(sql, alarms).await.unwrap();
+        let (batches, _) = datafusion_handler::execute_query(sql, batch, "alarm_statistics").await.unwrap();
+        QueryResult::from_record_batches(batches).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_alarm_counts_by_state() {
+        let result = statistics("SELECT state, alarm_count, avg_duration_seconds FROM alarm_statistics ORDER BY state").await;
+        let rows: Vec<(String, i64, f64)> = result
+            .rows
+            .iter()
+            .map(|row| match (&row[0], &row[1], &row[2]) {
+                (QueryValue::Text(state), QueryValue::Integer(count), QueryValue::Float(avg)) => (state.clone(), *count, *avg),
+                other => panic!("Unexpected row {:?}", other),
+            })
+            .collect();
+        assert_eq!(rows, vec![("ACKNOWLEDGED".to_string(), 4, 600.0), ("RAISED".to_string(), 6, 210.0)]);
+
+        // The state condition filters the alarms before they are grouped
+        let sql = "SELECT sum(alarm_count) FROM alarm_statistics WHERE state = 'RAISED'";
+        assert_eq!(SqlHandler::alarm_statistics_filter(sql).as_deref(), Some("state = 'RAISED'"));
+        assert!(matches!(statistics(sql).await.rows[0][0], QueryValue::Integer(6)));
+        assert_eq!(SqlHandler::alarm_statistics_filter("SELECT * FROM alarm_statistics WHERE alarm_count > 1"), None);
+    }
+
+    #[test]
+    fn test_iso_durations() {
+        assert_eq!(QueryHandler::parse_iso_duration_to_seconds("PT1H30M45S"), Some(5445));
+        assert_eq!(QueryHandler::parse_iso_duration_to_seconds("P1DT2H"), Some(93600));
+        assert_eq!(QueryHandler::parse_iso_duration_to_seconds("PT0.5S"), Some(0));
+        assert_eq!(QueryHandler::parse_iso_duration_to_seconds("01:00:00"), None);
+    }
+}
@@ -0,0 +1,75 @@
+use crate::auth::{AuthenticatedSession, SessionManager};
+use crate::query_handler::{QueryHandler, QueryResult, SqlStateError};
+use crate::sql_handler::SqlHandler;
+use crate::tables::{CursorCommand, SqlResult};
+use anyhow::{anyhow, Result};
+use std::sync::Arc;
+use tracing::debug;
+
+impl QueryHandler {
+    /// DECLARE runs the query right away and keeps its rows on the connection, FETCH returns
+    /// them in pages and CLOSE drops them. Cursors stay open until CLOSE or the end of the
+    /// connection, as if declared WITH HOLD.
+    pub(super) async fn execute_cursor_command(
+        command: CursorCommand,
+        session: &AuthenticatedSession,
+        session_manager: Arc<SessionManager>,
+        connection_id: Option<u32>,
+    ) -> Result<QueryResult> {
+        let conn_id = connection_id.ok_or_else(|| anyhow!("Cursors are only available on a connection"))?;
+        match command {
+            CursorCommand::Declare { name, query } => {
+                // Checked before the query runs, and again when the cursor is stored
+                if session_manager.has_cursor(conn_id, &name).await {
+                    return Err(Self::duplicate_cursor(&name));
+                }
+                let mut result = match SqlHandler::parse_query(&query)? {
+                    SqlResult::Query(query_info) => {
+                        Self::execute_unified_datafusion_query(&query, &query_info, session, session_manager.clone(), connection_id).await?
+                    }
+                    SqlResult::UnionQuery(union_info) => {
+                        Self::execute_union_all_query(&query, &union_info, session, session_manager.clone(), connection_id).await?
+                    }
+                    _ => return Err(anyhow!("Only DECLARE ... CURSOR FOR SELECT is supported")),
+                };
+                result.mark_jsonb_columns(&SqlHandler::jsonb_cast_columns(&query));
+                debug!("🗂️  Declared cursor {} with {} rows on connection {}", name, result.rows.len(), conn_id);
+                if !session_manager.declare_cursor(conn_id, &name, result).await {
+                    return Err(Self::duplicate_cursor(&name));
+                }
+                Ok(QueryResult::command("DECLARE CURSOR"))
+            }
+            CursorCommand::Fetch { name, count } => {
+                let mut result = session_manager
+                    .fetch_from_cursor(conn_id, &name, count)
+                    .await
+                    .ok_or_else(|| Self::unknown_cursor(&name))?;
+                result.command_tag = Some(format!("FETCH {}", result.rows.len()));
+                Ok(result)
+            }
+            CursorCommand::Close { name } => {
+                if !session_manager.close_cursor(conn_id, name.as_deref()).await {
+                    return Err(Self::unknown_cursor(name.as_deref().unwrap_or_default()));
+                }
+                Ok(QueryResult::command("CLOSE CURSOR"))
+            }
+        }
+    }
+
+    /// Columns a FETCH returns, without advancing the cursor, for Describe
+    pub async fn describe_fetch(sql: &str, session_manager: &SessionManager, connection_id: Option<u32>) -> Result<QueryResult> {
+        let SqlResult::Cursor(CursorCommand::Fetch { name, .. }) = SqlHandler::parse_query(sql)? else {
+            return Err(anyhow!("Not a FETCH statement: {}", sql));
+        };
+        let conn_id = connection_id.ok_or_else(|| anyhow!("Cursors are only available on a connection"))?;
+        session_manager.cursor_columns(conn_id, &name).await.ok_or_else(|| Self::unknown_cursor(&name))
+    }
+
+    fn duplicate_cursor(name: &str) -> anyhow::Error {
+        SqlStateError::new("42P03", format!("cursor \"{}\" already exists", name)).into()
+    }
+
+    fn unknown_cursor(name: &str) -> anyhow::Error {
+        SqlStateError::new("34000", format!("cursor \"{}\" does not exist", name)).into()
+    }
+}
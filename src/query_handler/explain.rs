@@ -0,0 +1,339 @@
+use crate::auth::{AuthenticatedSession, SessionManager};
+use crate::query_handler::{QueryHandler, QueryResult, QueryTimings, QueryValue};
+use crate::tables::{ExplainInfo, QueryInfo, SqlResult, VirtualTable};
+use anyhow::{anyhow, Result};
+use std::sync::Arc;
+use std::time::Instant;
+use tracing::debug;
+
+// Default maxNumberOfValues of logged tag value requests without LIMIT
+const DEFAULT_LOGGED_VALUES_LIMIT: i64 = 1000;
+
+/// Step of the plan an EXPLAIN ANALYZE timing belongs to; detail rows have none
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum PlanStep {
+    Query,
+    GraphQL,
+    DataFusion,
+}
+
+type PlanRow = (Option<PlanStep>, String);
+
+impl QueryHandler {
+    pub(super) async fn execute_explain(
+        explain: &ExplainInfo,
+        session: &AuthenticatedSession,
+        session_manager: Arc<SessionManager>,
+        connection_id: Option<u32>,
+    ) -> Result<QueryResult> {
+        let mut plan = Self::explain_plan_rows(&explain.statement)?;
+
+        if explain.analyze {
+            debug!("📐 EXPLAIN ANALYZE executing: {}", explain.sql);
+            let start = Instant::now();
+            let result = match explain.statement.as_ref() {
+                SqlResult::Query(query_info) => {
+                    Self::execute_unified_datafusion_query(&explain.sql, query_info, session, session_manager, connection_id).await?
+                }
+                SqlResult::UnionQuery(union_info) => {
+                    Self::execute_union_all_query(&explain.sql, union_info, session, session_manager, connection_id).await?
+                }
+                _ => return Err(anyhow!("EXPLAIN is only supported for SELECT statements")),
+            };
+            let mut timings = result.timings;
+            timings.overall_time_ms = Some(start.elapsed().as_millis() as u64);
+            Self::annotate_plan_with_actuals(&mut plan, &timings, result.rows.len());
+        }
+
+        let mut query_result = QueryResult::new(vec!["QUERY PLAN".to_string()], vec![25]);
+        for (_, text) in plan {
+            query_result.add_row(vec![QueryValue::Text(text)]);
+        }
+        Ok(query_result)
+    }
+
+    /// Describe how a parsed query is translated into GraphQL requests without sending them
+    pub(super) fn explain_plan_rows(statement: &SqlResult) -> Result<Vec<PlanRow>> {
+        match statement {
+            SqlResult::Query(query_info) => Ok(Self::explain_query(query_info, "")),
+            SqlResult::UnionQuery(union_info) => {
+                let mut rows = vec![(Some(PlanStep::Query), format!("UNION ALL ({} legs)", union_info.legs.len()))];
+                for leg in &union_info.legs {
+                    rows.extend(Self::explain_query(&leg.query_info, "  ->  "));
+                }
+                if let Some(outer_sql) = &union_info.outer_sql {
+                    rows.push((Some(PlanStep::DataFusion), format!("  ->  DataFusion: {}", outer_sql)));
+                }
+                Ok(rows)
+            }
+            _ => Err(anyhow!("EXPLAIN is only supported for SELECT statements")),
+        }
+    }
+
+    fn explain_query(query_info: &QueryInfo, prefix: &str) -> Vec<PlanRow> {
+        let indent = " ".repeat(prefix.len());
+        let step = |text: String| format!("{}  ->  {}", indent, text);
+        let detail = |name: &str, value: String| (None, format!("{}        {}: {}", indent, name, value));
+
+        let mut rows = vec![(Some(PlanStep::Query), format!("{}Virtual table {}", prefix, query_info.table))];
+        let mut estimated_rows = query_info.fetch_limit().unwrap_or(0);
+
+        match query_info.table {
+            VirtualTable::TagValues | VirtualTable::TagValueChanges => {
+                rows.push((Some(PlanStep::GraphQL), step("GraphQL tagValues".to_string())));
+                let tag_names = query_info.get_tag_names();
+                if query_info.requires_browse() {
+                    rows.push(detail("names", format!("resolved by browse of LIKE patterns {:?}", Self::explain_like_patterns(query_info))));
+                } else if tag_names.is_empty() {
+                    rows.push(detail("names", "all tags (full scan via browse)".to_string()));
+                } else {
+                    estimated_rows = tag_names.len() as i64;
+                    rows.push(detail("names", format!("{:?}", tag_names)));
+                }
+                rows.push(detail("directRead", "false".to_string()));
+                if query_info.table == VirtualTable::TagValueChanges {
+                    let change_detection = if query_info.is_forced() {
+                        "bypassed (force = true)"
+                    } else {
+                        "only values changed since the previous query on this connection"
+                    };
+                    rows.push(detail("change detection", change_detection.to_string()));
+                }
+            }
+            VirtualTable::LoggedTagValues | VirtualTable::LoggedTagValuesIncremental => {
+                rows.push((Some(PlanStep::GraphQL), step("GraphQL loggedTagValues".to_string())));
+                if query_info.requires_browse() {
+                    rows.push(detail("names", format!("resolved by browse of LIKE patterns {:?}", Self::explain_like_patterns(query_info))));
+                } else {
+                    rows.push(detail("names", format!("{:?}", query_info.get_tag_names())));
+                }
+                let timestamp_filter = query_info.get_timestamp_filter();
+                let incremental = query_info.table == VirtualTable::LoggedTagValuesIncremental && timestamp_filter.is_none();
+                let (start_time, end_time) = timestamp_filter.unwrap_or((None, None));
+                let start_time = match start_time {
+                    _ if incremental => "cursor of this connection (initially now - 1 hour)".to_string(),
+                    Some(start_time) => start_time,
+                    None => "none".to_string(),
+                };
+                rows.push(detail("startTime", start_time));
+                rows.push(detail("endTime", end_time.unwrap_or_else(|| "now".to_string())));
+                if query_info.has_timestamp_exclusion() {
+                    rows.push(detail("timestamp NOT BETWEEN", "applied to the fetched values (full scan)".to_string()));
+                }
+                rows.push(detail(
+                    "maxNumberOfValues",
+                    query_info.fetch_limit().unwrap_or(DEFAULT_LOGGED_VALUES_LIMIT).to_string(),
+                ));
+                let sorting_mode = if incremental { "TIME_ASC" } else { query_info.sorting_mode() };
+                rows.push(detail("sortingMode", sorting_mode.to_string()));
+            }
+            VirtualTable::LoggedTagValuesResampled => {
+                rows.push((Some(PlanStep::GraphQL), step("GraphQL loggedTagValues".to_string())));
+                rows.push(detail("names", format!("{:?}", query_info.get_tag_names())));
+                let (start_time, end_time) = query_info.get_timestamp_filter().unwrap_or((None, None));
+                rows.push(detail("startTime", start_time.unwrap_or_else(|| "none".to_string())));
+                rows.push(detail("endTime", end_time.unwrap_or_else(|| "now".to_string())));
+                rows.push(detail("maxNumberOfValues", super::resample_handler::MAX_RESAMPLE_SAMPLES.to_string()));
+                rows.push(detail("sortingMode", "TIME_ASC".to_string()));
+                rows.push(detail(
+                    "resampled",
+                    format!(
+                        "{} over buckets of {}s",
+                        query_info.get_resample_method().unwrap_or("avg"),
+                        query_info.get_resample_interval().unwrap_or_default()
+                    ),
+                ));
+            }
+            VirtualTable::LoggedTagValuesSummary => {
+                rows.push((Some(PlanStep::GraphQL), step("GraphQL loggedTagValues".to_string())));
+                if query_info.requires_browse() {
+                    rows.push(detail("names", format!("resolved by browse of LIKE patterns {:?}", Self::explain_like_patterns(query_info))));
+                } else {
+                    rows.push(detail("names", format!("{:?}", query_info.get_tag_names())));
+                }
+                let (start_time, end_time) = query_info.get_window_range().unwrap_or((None, None));
+                rows.push(detail("startTime", start_time.unwrap_or_else(|| "none".to_string())));
+                rows.push(detail("endTime", end_time.unwrap_or_else(|| "now".to_string())));
+                rows.push(detail("maxNumberOfValues", super::summary_handler::MAX_SUMMARY_SAMPLES.to_string()));
+                rows.push(detail("sortingMode", "TIME_ASC".to_string()));
+                let window_size = query_info.get_window_size().unwrap_or(super::summary_handler::DEFAULT_SUMMARY_WINDOW_SIZE);
+                rows.push(detail("summarized", format!("min/max/avg over windows of {}", window_size)));
+            }
+            VirtualTable::ActiveAlarms => {
+                rows.push((Some(PlanStep::GraphQL), step("GraphQL activeAlarms".to_string())));
+                rows.push(detail("systemNames", "[]".to_string()));
+                let filter_string = Self::extract_alarm_filter_string(&query_info.filters).unwrap_or_default();
+                rows.push(detail("filterString", format!("{:?}", filter_string)));
+            }
+            VirtualTable::ActiveAlarmsChanges => {
+                rows.push((Some(PlanStep::GraphQL), step("GraphQL activeAlarms".to_string())));
+                rows.push(detail("systemNames", "[]".to_string()));
+                rows.push(detail("filterString", "\"\"".to_string()));
+                let diff_columns = super::active_alarms_handler::alarm_diff_columns().join(", ");
+                rows.push(detail("change detection", format!("alarms added, removed or changed in {} since the previous query on this connection", diff_columns)));
+            }
+            VirtualTable::LoggedAlarms => {
+                rows.push((Some(PlanStep::GraphQL), step("GraphQL loggedAlarms".to_string())));
+                let (start_time, end_time) = query_info
+                    .get_raise_time_filter()
+                    .or_else(|| query_info.get_modification_time_filter())
+                    .or_else(|| query_info.get_timestamp_filter())
+                    .unwrap_or((None, None));
+                rows.push(detail("systemNames", format!("{:?}", query_info.get_system_names())));
+                rows.push(detail("filterString", format!("{:?}", query_info.get_filter_string().unwrap_or_default())));
+                rows.push(detail("startTime", start_time.unwrap_or_else(|| "none".to_string())));
+                rows.push(detail("endTime", end_time.unwrap_or_else(|| "now".to_string())));
+                rows.push(detail(
+                    "maxNumberOfResults",
+                    query_info.fetch_limit().map_or("none".to_string(), |limit| limit.to_string()),
+                ));
+            }
+            VirtualTable::TagList => {
+                rows.push((Some(PlanStep::GraphQL), step("GraphQL browse".to_string())));
+                let name_filters: Vec<String> =
+                    query_info.get_name_filters().iter().map(|filter| filter.replace('%', "*")).collect();
+                rows.push(detail("nameFilters", format!("{:?}", name_filters)));
+                rows.push(detail("objectTypeFilters", format!("{:?}", query_info.get_object_type_filters())));
+            }
+            VirtualTable::TagHierarchy => {
+                rows.push((Some(PlanStep::GraphQL), step("GraphQL browse".to_string())));
+                rows.push(detail("nameFilters", format!("{:?}", query_info.get_hierarchy_name_filters())));
+            }
+            VirtualTable::TagStatistics => {
+                rows.push((Some(PlanStep::GraphQL), step("GraphQL tagValues + loggedTagValues".to_string())));
+                if query_info.requires_browse() {
+                    rows.push(detail("names", format!("resolved by browse of LIKE patterns {:?}", Self::explain_like_patterns(query_info))));
+                } else {
+                    rows.push(detail("names", format!("{:?}", query_info.get_tag_names())));
+                }
+                let lookback = query_info.get_lookback().unwrap_or(super::tag_statistics_handler::DEFAULT_TAG_STATISTICS_LOOKBACK);
+                rows.push(detail("lookback", lookback.to_string()));
+                rows.push(detail("maxNumberOfValues", super::tag_statistics_handler::MAX_TAG_STATISTICS_SAMPLES.to_string()));
+            }
+            VirtualTable::AlarmStatistics => {
+                rows.push((Some(PlanStep::GraphQL), step("GraphQL activeAlarms".to_string())));
+                rows.push(detail("systemNames", "[]".to_string()));
+                rows.push(detail("grouped by", "state, priority, area".to_string()));
+            }
+            VirtualTable::LoggedAlarmStatistics => {
+                rows.push((Some(PlanStep::GraphQL), step("GraphQL loggedAlarms".to_string())));
+                rows.push(detail("systemNames", "[]".to_string()));
+                rows.push(detail("grouped by", "state, priority, area".to_string()));
+            }
+            VirtualTable::FromLessQuery => {
+                rows.push((Some(PlanStep::DataFusion), step("DataFusion: expression evaluated locally, no GraphQL request".to_string())));
+                return rows;
+            }
+            _ => {
+                rows.push((Some(PlanStep::GraphQL), step("Local catalog table, no GraphQL request".to_string())));
+            }
+        }
+
+        rows.push(detail("estimated rows", estimated_rows.to_string()));
+        for derived in &query_info.derived_tables {
+            // Subqueries in FROM are fetched separately and joined in the DataFusion step below
+            let mut derived_rows = Self::explain_query(&derived.query_info, &format!("{}  ->  ", indent));
+            derived_rows.pop();
+            rows.extend(derived_rows);
+        }
+        rows.push((
+            Some(PlanStep::DataFusion),
+            step("DataFusion: original SQL evaluated on the fetched rows (filters, ORDER BY, LIMIT/OFFSET)".to_string()),
+        ));
+        rows
+    }
+
+    fn explain_like_patterns(query_info: &QueryInfo) -> Vec<String> {
+        let mut patterns = query_info.get_like_patterns();
+        patterns.extend(query_info.get_ilike_patterns());
+        patterns
+    }
+
+    /// Append the measured time of its step and the returned row count to every plan step
+    pub(super) fn annotate_plan_with_actuals(plan: &mut [PlanRow], timings: &QueryTimings, rows: usize) {
+        for (step, text) in plan.iter_mut() {
+            let time_ms = match step {
+                Some(PlanStep::Query) => timings.overall_time_ms,
+                Some(PlanStep::GraphQL) => timings.graphql_time_ms,
+                Some(PlanStep::DataFusion) => timings.datafusion_time_ms,
+                None => continue,
+            };
+            text.push_str(&format!(" (actual time={}ms rows={})", time_ms.unwrap_or(0), rows));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sql_handler::SqlHandler;
+
+    fn parse_explain(sql: &str) -> (ExplainInfo, Vec<PlanRow>) {
+        match SqlHandler::parse_query(sql).unwrap() {
+            SqlResult::ExplainPlan(explain) => {
+                let plan = QueryHandler::explain_plan_rows(&explain.statement).unwrap();
+                (explain, plan)
+            }
+            other => panic!("expected an EXPLAIN plan, got {:?}", other),
+        }
+    }
+
+    fn plan_text(plan: &[PlanRow]) -> String {
+        plan.iter().map(|(_, text)| text.as_str()).collect::<Vec<_>>().join("\n")
+    }
+
+    #[test]
+    fn test_explain_shows_graphql_parameters() {
+        let (explain, plan) = parse_explain(
+            "EXPLAIN SELECT * FROM loggedtagvalues WHERE tag_name IN ('HMI_Tag_1', 'HMI_Tag_2') \
+             AND timestamp >= '2025-01-01T00:00:00Z' ORDER BY timestamp DESC LIMIT 10",
+        );
+        assert!(!explain.analyze);
+        assert!(explain.sql.starts_with("SELECT * FROM loggedtagvalues"));
+
+        let text = plan_text(&plan);
+        assert!(text.contains("Virtual table loggedtagvalues"), "{}", text);
+        assert!(text.contains("GraphQL loggedTagValues"), "{}", text);
+        assert!(text.contains(r#"names: ["HMI_Tag_1", "HMI_Tag_2"]"#), "{}", text);
+        assert!(text.contains("startTime: 2025-01-01"), "{}", text);
+        assert!(text.contains("endTime: now"), "{}", text);
+        assert!(text.contains("maxNumberOfValues: 10"), "{}", text);
+        assert!(text.contains("sortingMode: TIME_DESC"), "{}", text);
+        assert!(text.contains("DataFusion"), "{}", text);
+
+        let (_, plan) = parse_explain("EXPLAIN SELECT tag_name, numeric_value FROM tagvalues WHERE tag_name LIKE 'HMI_%'");
+        let text = plan_text(&plan);
+        assert!(text.contains("Virtual table tagvalues"), "{}", text);
+        assert!(text.contains(r#"resolved by browse of LIKE patterns ["HMI_%"]"#), "{}", text);
+        assert!(text.contains("estimated rows: 0"), "{}", text);
+
+        let (_, plan) = parse_explain("EXPLAIN SELECT * FROM loggedalarms WHERE filterString = 'Motor' LIMIT 5");
+        let text = plan_text(&plan);
+        assert!(text.contains("GraphQL loggedAlarms"), "{}", text);
+        assert!(text.contains("maxNumberOfResults: 5"), "{}", text);
+        assert!(text.contains(r#"filterString: "Motor""#), "{}", text);
+
+        assert!(SqlHandler::parse_query("EXPLAIN SET datestyle = 'ISO'").is_err());
+    }
+
+    #[test]
+    fn test_explain_analyze_appends_actuals_to_plan_steps() {
+        let (explain, mut plan) = parse_explain("EXPLAIN ANALYZE SELECT * FROM tagvalues WHERE tag_name = 'HMI_Tag_1'");
+        assert!(explain.analyze);
+
+        let timings = QueryTimings {
+            graphql_time_ms: Some(12),
+            datafusion_time_ms: Some(3),
+            overall_time_ms: Some(20),
+            retry_count: 0,
+        };
+        QueryHandler::annotate_plan_with_actuals(&mut plan, &timings, 1);
+
+        let text = plan_text(&plan);
+        assert!(text.contains("Virtual table tagvalues (actual time=20ms rows=1)"), "{}", text);
+        assert!(text.contains("GraphQL tagValues (actual time=12ms rows=1)"), "{}", text);
+        assert!(text.contains("(actual time=3ms rows=1)"), "{}", text);
+        // Parameter rows are details of the GraphQL step and carry no timings
+        assert!(text.contains(r#"names: ["HMI_Tag_1"]"#) && !text.contains(r#"["HMI_Tag_1"] (actual"#), "{}", text);
+    }
+}
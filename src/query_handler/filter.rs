@@ -1,87 +1,64 @@
 use crate::query_handler::QueryHandler;
 use crate::tables::{ColumnFilter, FilterOperator, FilterValue};
 use anyhow::Result;
+use arrow::array::{Array, BooleanArray, Float64Array, StringArray};
+use arrow::compute::kernels::boolean::{and, not, or};
+use arrow::compute::kernels::cmp::{eq, gt, gt_eq, lt, lt_eq, neq};
+use arrow::compute::kernels::comparison::{ilike, like, nlike};
+use arrow::compute::unary;
+use std::cell::LazyCell;
 
 impl QueryHandler {
     pub(super) fn apply_filters(
         results: Vec<crate::graphql::types::TagValueResult>,
         filters: &[ColumnFilter],
     ) -> Result<Vec<crate::graphql::types::TagValueResult>> {
-        let mut filtered = Vec::new();
-
-        for result in results {
-            let mut include = true;
+        fn value(result: &crate::graphql::types::TagValueResult) -> Option<&serde_json::Value> {
+            result.value.as_ref().and_then(|value| value.value.as_ref())
+        }
+        let numeric_values = LazyCell::new(|| Float64Array::from_iter(results.iter().map(|result| value(result).and_then(|v| v.as_f64()))));
+        let string_values = LazyCell::new(|| StringArray::from_iter(results.iter().map(|result| value(result).and_then(|v| v.as_str()))));
+        let qualities = LazyCell::new(|| {
+            StringArray::from_iter(results.iter().map(|result| {
+                result.value.as_ref().and_then(|value| value.quality.as_ref()).map(|quality| quality.quality.as_str())
+            }))
+        });
+        let has_value = LazyCell::new(|| BooleanArray::from_iter(results.iter().map(|result| Some(result.value.is_some()))));
 
-            // Check if this result passes all filters
-            for filter in filters {
-                match filter.column.as_str() {
-                    "tag_name" => {
-                        // tag_name filters are already applied in the GraphQL query
-                        continue;
-                    }
-                    "numeric_value" => {
-                        if let Some(value) = &result.value {
-                            if let Some(numeric_val) = value.value.as_ref().and_then(|v| v.as_f64())
-                            {
-                                if !Self::check_numeric_filter(
-                                    numeric_val,
-                                    &filter.operator,
-                                    &filter.value,
-                                ) {
-                                    include = false;
-                                    break;
-                                }
-                            }
-                        }
-                    }
-                    "string_value" => {
-                        if let Some(value) = &result.value {
-                            if let Some(string_val) = value.value.as_ref().and_then(|v| v.as_str())
-                            {
-                                if !Self::check_string_filter(
-                                    string_val,
-                                    &filter.operator,
-                                    &filter.value,
-                                ) {
-                                    include = false;
-                                    break;
-                                }
-                            }
-                        }
-                    }
-                    "quality" => {
-                        if let Some(value) = &result.value {
-                            if let Some(quality) = &value.quality {
-                                if !Self::check_string_filter(
-                                    &quality.quality,
-                                    &filter.operator,
-                                    &filter.value,
-                                ) {
-                                    include = false;
-                                    break;
-                                }
-                            } else {
-                                // No quality available, check if filter expects NULL
-                                if !Self::check_null_filter(&filter.operator, &filter.value) {
-                                    include = false;
-                                    break;
-                                }
-                            }
-                        }
-                    }
-                    _ => {
-                        // Unknown filter column, skip
-                        continue;
-                    }
-                }
+        let mut masks = Vec::new();
+        for filter in filters {
+            if filter.is_null_check() {
+                continue;
             }
-
-            if include {
-                filtered.push(result);
+            match filter.column.as_str() {
+                "tag_name" => {
+                    // tag_name filters are already applied in the GraphQL query
+                    continue;
+                }
+                "numeric_value" => {
+                    // Rows without a numeric value are kept
+                    let mask = Self::numeric_filter_mask(&numeric_values, &filter.operator, &filter.value)?;
+                    masks.push(Self::fill_null_rows(&mask, &*numeric_values, true)?);
+                }
+                "string_value" => {
+                    let mask = Self::string_filter_mask(&string_values, &filter.operator, &filter.value)?;
+                    masks.push(Self::fill_null_rows(&mask, &*string_values, true)?);
+                }
+                "quality" => {
+                    // A missing quality matches only `= 'NULL'`, rows without a value are kept
+                    let mask = Self::string_filter_mask(&qualities, &filter.operator, &filter.value)?;
+                    let null_matches = Self::check_null_filter(&filter.operator, &filter.value);
+                    let mask = Self::fill_null_rows(&mask, &*qualities, null_matches)?;
+                    masks.push(or(&mask, &not(&has_value)?)?);
+                }
+                _ => {
+                    // Unknown filter column, skip
+                    continue;
+                }
             }
         }
 
-        Ok(filtered)
+        Self::select_rows(results, masks)
     }
 
     pub(super) fn apply_browse_filters(
@@ -95,7 +72,17 @@ impl QueryHandler {
 
             // Check if this result passes all filters
             for filter in filters {
+                if filter.is_null_check() {
+                    continue;
+                }
                 match filter.column.as_str() {
+                    "tag_name" if matches!(filter.operator, FilterOperator::ILike) => {
+                        // The browse pattern was upper-cased, re-check case-insensitively
+                        if !Self::check_string_filter(&result.name, &filter.operator, &filter.value) {
+                            include = false;
+                            break;
+                        }
+                    }
                     "tag_name" | "object_type" => {
                         // These filters are already applied in the GraphQL query
                         continue;
@@ -139,110 +126,73 @@ impl QueryHandler {
         results: Vec<crate::graphql::types::LoggedTagValue>,
         filters: &[ColumnFilter],
     ) -> Result<Vec<crate::graphql::types::LoggedTagValue>> {
-        // Similar to apply_filters but for logged tag values
-        let mut filtered = Vec::new();
-
-        for result in results {
-            let mut include = true;
+        // Same columns as apply_filters, the quality belongs to the value itself
+        let numeric_values = LazyCell::new(|| Float64Array::from_iter(results.iter().map(|result| result.value.as_ref().and_then(|v| v.as_f64()))));
+        let string_values = LazyCell::new(|| StringArray::from_iter(results.iter().map(|result| result.value.as_ref().and_then(|v| v.as_str()))));
+        let qualities = LazyCell::new(|| StringArray::from_iter(results.iter().map(|result| result.quality.as_ref().map(|quality| quality.quality.as_str()))));
 
-            for filter in filters {
-                match filter.column.as_str() {
-                    "tag_name" | "timestamp" => {
-                        // These are handled by the GraphQL query
-                        continue;
-                    }
-                    "numeric_value" => {
-                        if let Some(numeric_val) = result.value.as_ref().and_then(|v| v.as_f64()) {
-                            if !Self::check_numeric_filter(
-                                numeric_val,
-                                &filter.operator,
-                                &filter.value,
-                            ) {
-                                include = false;
-                                break;
-                            }
-                        }
-                    }
-                    "string_value" => {
-                        if let Some(string_val) = result.value.as_ref().and_then(|v| v.as_str()) {
-                            if !Self::check_string_filter(
-                                string_val,
-                                &filter.operator,
-                                &filter.value,
-                            ) {
-                                include = false;
-                                break;
-                            }
-                        }
-                    }
-                    "quality" => {
-                        if let Some(quality) = &result.quality {
-                            if !Self::check_string_filter(
-                                &quality.quality,
-                                &filter.operator,
-                                &filter.value,
-                            ) {
-                                include = false;
-                                break;
-                            }
-                        } else {
-                            // No quality available, check if filter expects NULL
-                            if !Self::check_null_filter(&filter.operator, &filter.value) {
-                                include = false;
-                                break;
-                            }
-                        }
-                    }
-                    _ => continue,
-                }
+        let mut masks = Vec::new();
+        for filter in filters {
+            if filter.is_null_check() {
+                continue;
             }
-
-            if include {
-                filtered.push(result);
+            match filter.column.as_str() {
+                "timestamp" if matches!(filter.operator, FilterOperator::NotBetween) => {
+                    // GraphQL only takes a single time range, the exclusion is applied here
+                    masks.push(BooleanArray::from_iter(
+                        results.iter().map(|result| Some(Self::check_timestamp_outside_range(&result.timestamp, &filter.value))),
+                    ));
+                }
+                "tag_name" | "timestamp" => {
+                    // These are handled by the GraphQL query
+                    continue;
+                }
+                "numeric_value" => {
+                    let mask = Self::numeric_filter_mask(&numeric_values, &filter.operator, &filter.value)?;
+                    masks.push(Self::fill_null_rows(&mask, &*numeric_values, true)?);
+                }
+                "string_value" => {
+                    let mask = Self::string_filter_mask(&string_values, &filter.operator, &filter.value)?;
+                    masks.push(Self::fill_null_rows(&mask, &*string_values, true)?);
+                }
+                "quality" => {
+                    let mask = Self::string_filter_mask(&qualities, &filter.operator, &filter.value)?;
+                    let null_matches = Self::check_null_filter(&filter.operator, &filter.value);
+                    masks.push(Self::fill_null_rows(&mask, &*qualities, null_matches)?);
+                }
+                _ => continue,
             }
         }
 
-        Ok(filtered)
+        Self::select_rows(results, masks)
     }
 
     pub(super) fn apply_alarm_filters(
         results: Vec<crate::graphql::types::ActiveAlarm>,
         filters: &[ColumnFilter],
     ) -> Result<Vec<crate::graphql::types::ActiveAlarm>> {
-        let mut filtered = Vec::new();
+        let priorities = LazyCell::new(|| Float64Array::from_iter_values(results.iter().map(|result| result.priority.unwrap_or(0) as f64)));
 
-        for result in results {
-            let mut include = true;
-
-            for filter in filters {
-                match filter.column.as_str() {
-                    "priority" => {
-                        if let Some(priority_val) = filter.value.as_integer() {
-                            let alarm_priority = result.priority.unwrap_or(0) as i64;
-                            if !Self::check_numeric_filter(
-                                alarm_priority as f64,
-                                &filter.operator,
-                                &FilterValue::Integer(priority_val),
-                            ) {
-                                include = false;
-                                break;
-                            }
-                        }
-                    }
-                    "name" | "event_text" | "info_text" => {
-                        // These are handled by the filter_string in GraphQL
-                        continue;
+        let mut masks = Vec::new();
+        for filter in filters {
+            if filter.is_null_check() {
+                continue;
+            }
+            match filter.column.as_str() {
+                "priority" => {
+                    if let Some(priority_val) = Self::priority_filter_value(&filter.value) {
+                        masks.push(Self::numeric_filter_mask(&priorities, &filter.operator, &priority_val)?);
                     }
-                    _ => continue,
                 }
-            }
-
-            if include {
-                filtered.push(result);
+                "name" | "event_text" | "info_text" => {
+                    // These are handled by the filter_string in GraphQL
+                    continue;
+                }
+                _ => continue,
             }
         }
 
-        Ok(filtered)
+        Self::select_rows(results, masks)
     }
 
     pub(super) fn apply_logged_alarm_filters(
@@ -250,96 +200,196 @@ impl QueryHandler {
         filters: &[ColumnFilter],
     ) -> Result<Vec<crate::graphql::types::LoggedAlarm>> {
         // Similar logic to apply_alarm_filters but for logged alarms
-        let mut filtered = Vec::new();
-
-        for result in results {
-            let mut include = true;
+        let priorities = LazyCell::new(|| Float64Array::from_iter_values(results.iter().map(|result| result.priority.unwrap_or(0) as f64)));
 
-            for filter in filters {
-                match filter.column.as_str() {
-                    "priority" => {
-                        if let Some(priority_val) = filter.value.as_integer() {
-                            let alarm_priority = result.priority.unwrap_or(0) as i64;
-                            if !Self::check_numeric_filter(
-                                alarm_priority as f64,
-                                &filter.operator,
-                                &FilterValue::Integer(priority_val),
-                            ) {
-                                include = false;
-                                break;
-                            }
-                        }
-                    }
-                    "timestamp" | "modification_time" => {
-                        // Handled by GraphQL query
-                        continue;
-                    }
-                    "filterString" | "system_name" | "filter_language" => {
-                        // Virtual columns - handled by GraphQL query, skip in post-processing
-                        continue;
+        let mut masks = Vec::new();
+        for filter in filters {
+            if filter.is_null_check() {
+                continue;
+            }
+            match filter.column.as_str() {
+                "priority" => {
+                    if let Some(priority_val) = Self::priority_filter_value(&filter.value) {
+                        masks.push(Self::numeric_filter_mask(&priorities, &filter.operator, &priority_val)?);
                     }
-                    _ => continue,
                 }
+                "timestamp" | "modification_time" => {
+                    // Handled by GraphQL query
+                    continue;
+                }
+                "filterString" | "system_name" | "filter_language" => {
+                    // Virtual columns - handled by GraphQL query, skip in post-processing
+                    continue;
+                }
+                _ => continue,
             }
+        }
 
-            if include {
-                filtered.push(result);
-            }
+        Self::select_rows(results, masks)
+    }
+
+    /// Keep the rows selected by all masks. The masks are ANDed into one before the rows are taken.
+    fn select_rows<T>(results: Vec<T>, masks: Vec<BooleanArray>) -> Result<Vec<T>> {
+        let mut masks = masks.into_iter();
+        let Some(mut mask) = masks.next() else {
+            return Ok(results);
+        };
+        for other in masks {
+            mask = and(&mask, &other)?;
         }
+        Ok(results
+            .into_iter()
+            .zip(mask.values().iter())
+            .filter_map(|(result, keep)| keep.then_some(result))
+            .collect())
+    }
 
-        Ok(filtered)
+    /// Replace the result of the rows where `values` is NULL by `null_result`
+    fn fill_null_rows(mask: &BooleanArray, values: &dyn Array, null_result: bool) -> Result<BooleanArray> {
+        let mask = BooleanArray::new(mask.values().clone(), None);
+        let Some(nulls) = values.nulls() else {
+            return Ok(mask);
+        };
+        let valid = BooleanArray::new(nulls.inner().clone(), None);
+        let present = and(&mask, &valid)?;
+        if null_result {
+            Ok(or(&present, &not(&valid)?)?)
+        } else {
+            Ok(present)
+        }
     }
 
-    pub(super) fn check_numeric_filter(
-        value: f64,
-        operator: &FilterOperator,
-        filter_value: &FilterValue,
-    ) -> bool {
-        match operator {
-            FilterOperator::Equal => {
-                if let Some(target) = filter_value.as_number() {
-                    (value - target).abs() < f64::EPSILON
-                } else {
-                    false
+    /// Comparison of `values` with a numeric filter, equality within f64::EPSILON. NULL where `values` is NULL
+    fn numeric_filter_mask(values: &Float64Array, operator: &FilterOperator, filter_value: &FilterValue) -> Result<BooleanArray> {
+        let constant = |result: bool| BooleanArray::from(vec![result; values.len()]);
+        let equal = |target: f64| -> Result<BooleanArray> {
+            let distance: Float64Array = unary(values, |value| (value - target).abs());
+            Ok(lt(&distance, &Float64Array::new_scalar(f64::EPSILON))?)
+        };
+        let mask = match operator {
+            FilterOperator::Equal => match filter_value.as_number() {
+                Some(target) => equal(target)?,
+                None => constant(false),
+            },
+            FilterOperator::NotEqual => match filter_value.as_number() {
+                Some(target) => not(&equal(target)?)?,
+                None => constant(true),
+            },
+            FilterOperator::GreaterThan
+            | FilterOperator::LessThan
+            | FilterOperator::GreaterThanOrEqual
+            | FilterOperator::LessThanOrEqual => {
+                let Some(target) = filter_value.as_number() else {
+                    return Ok(constant(false));
+                };
+                let target = Float64Array::new_scalar(target);
+                match operator {
+                    FilterOperator::GreaterThan => gt(values, &target)?,
+                    FilterOperator::LessThan => lt(values, &target)?,
+                    FilterOperator::GreaterThanOrEqual => gt_eq(values, &target)?,
+                    _ => lt_eq(values, &target)?,
                 }
             }
-            FilterOperator::NotEqual => {
-                if let Some(target) = filter_value.as_number() {
-                    (value - target).abs() >= f64::EPSILON
-                } else {
-                    true
+            FilterOperator::In | FilterOperator::NotIn => {
+                let mut contained = constant(false);
+                for target in filter_value.as_list().into_iter().flatten().filter_map(|item| item.parse::<f64>().ok()) {
+                    contained = or(&contained, &equal(target)?)?;
                 }
+                if matches!(operator, FilterOperator::In) { contained } else { not(&contained)? }
             }
-            FilterOperator::GreaterThan => {
-                if let Some(target) = filter_value.as_number() {
-                    value > target
-                } else {
-                    false
-                }
-            }
-            FilterOperator::LessThan => {
-                if let Some(target) = filter_value.as_number() {
-                    value < target
-                } else {
-                    false
-                }
+            FilterOperator::Between | FilterOperator::NotBetween => {
+                let FilterValue::Range(low, high) = filter_value else {
+                    return Ok(constant(false));
+                };
+                let (Some(low), Some(high)) = (low.as_number(), high.as_number()) else {
+                    return Ok(constant(false));
+                };
+                let within = and(
+                    &gt_eq(values, &Float64Array::new_scalar(low))?,
+                    &lt_eq(values, &Float64Array::new_scalar(high))?,
+                )?;
+                if matches!(operator, FilterOperator::Between) { within } else { not(&within)? }
             }
-            FilterOperator::GreaterThanOrEqual => {
-                if let Some(target) = filter_value.as_number() {
-                    value >= target
-                } else {
-                    false
+            _ => constant(false), // Other operators not applicable to numeric values
+        };
+        Ok(mask)
+    }
+
+    /// Arrow kernel version of `check_string_filter`, NULL where `values` is NULL
+    fn string_filter_mask(values: &StringArray, operator: &FilterOperator, filter_value: &FilterValue) -> Result<BooleanArray> {
+        let constant = |result: bool| BooleanArray::from(vec![result; values.len()]);
+        let mask = match operator {
+            FilterOperator::Equal
+            | FilterOperator::NotEqual
+            | FilterOperator::Like
+            | FilterOperator::ILike
+            | FilterOperator::NotLike => {
+                let Some(target) = filter_value.as_string() else {
+                    // Without a comparable value only the negations match
+                    return Ok(constant(matches!(operator, FilterOperator::NotEqual | FilterOperator::NotLike)));
+                };
+                let target = StringArray::new_scalar(target);
+                match operator {
+                    FilterOperator::Equal => eq(values, &target)?,
+                    FilterOperator::NotEqual => neq(values, &target)?,
+                    FilterOperator::Like => like(values, &target)?,
+                    FilterOperator::ILike => ilike(values, &target)?,
+                    _ => nlike(values, &target)?,
                 }
             }
-            FilterOperator::LessThanOrEqual => {
-                if let Some(target) = filter_value.as_number() {
-                    value <= target
-                } else {
-                    false
+            FilterOperator::In | FilterOperator::NotIn => {
+                let mut contained = constant(false);
+                for item in filter_value.as_list().into_iter().flatten() {
+                    contained = or(&contained, &eq(values, &StringArray::new_scalar(item.as_str()))?)?;
                 }
+                if matches!(operator, FilterOperator::In) { contained } else { not(&contained)? }
             }
-            _ => false, // Other operators not applicable to numeric values
+            _ => constant(false), // Other operators not applicable to string values
+        };
+        Ok(mask)
+    }
+
+    /// Priorities are compared as integers, BETWEEN bounds included
+    fn priority_filter_value(filter_value: &FilterValue) -> Option<FilterValue> {
+        match filter_value {
+            FilterValue::Range(low, high) => Some(FilterValue::Range(
+                Box::new(FilterValue::Integer(low.as_integer()?)),
+                Box::new(FilterValue::Integer(high.as_integer()?)),
+            )),
+            value => value.as_integer().map(FilterValue::Integer),
+        }
+    }
+
+    /// True when `timestamp` lies before the start or after the end of a NOT BETWEEN range.
+    /// Values that cannot be parsed are kept, DataFusion evaluates the WHERE clause again.
+    pub(super) fn check_timestamp_outside_range(timestamp: &str, filter_value: &FilterValue) -> bool {
+        let FilterValue::Range(low, high) = filter_value else {
+            return true;
+        };
+        let bound = |value: &FilterValue| match value {
+            FilterValue::Timestamp(ts) | FilterValue::String(ts) => Self::parse_filter_timestamp(ts),
+            _ => None,
+        };
+        match (Self::parse_filter_timestamp(timestamp), bound(low), bound(high)) {
+            (Some(ts), Some(low), Some(high)) => ts < low || ts > high,
+            _ => true,
+        }
+    }
+
+    /// Parse an RFC 3339 timestamp or a date/time literal without offset, taken as UTC
+    fn parse_filter_timestamp(value: &str) -> Option<chrono::DateTime<chrono::Utc>> {
+        if let Ok(dt) = chrono::DateTime::parse_from_rfc3339(value) {
+            return Some(dt.with_timezone(&chrono::Utc));
         }
+        ["%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%d %H:%M:%S%.f"]
+            .iter()
+            .find_map(|format| chrono::NaiveDateTime::parse_from_str(value, format).ok())
+            .or_else(|| {
+                chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                    .ok()
+                    .and_then(|date| date.and_hms_opt(0, 0, 0))
+            })
+            .map(|naive| naive.and_utc())
     }
 
     pub(super) fn check_string_filter(
@@ -369,10 +419,55 @@ impl QueryHandler {
                     false
                 }
             }
+            FilterOperator::ILike => {
+                if let Some(pattern) = filter_value.as_string() {
+                    Self::matches_like_pattern(&value.to_lowercase(), &pattern.to_lowercase())
+                } else {
+                    false
+                }
+            }
+            FilterOperator::NotLike => {
+                if let Some(pattern) = filter_value.as_string() {
+                    !Self::matches_like_pattern(value, pattern)
+                } else {
+                    true
+                }
+            }
+            FilterOperator::In | FilterOperator::NotIn => {
+                let contained = filter_value
+                    .as_list()
+                    .is_some_and(|list| list.iter().any(|item| item == value));
+                contained == matches!(operator, FilterOperator::In)
+            }
             _ => false, // Other operators not applicable to string values
         }
     }
 
+    /// Removes tag names excluded by `tag_name NOT IN (...)` or `tag_name NOT LIKE ...`.
+    /// These cannot be pushed down to GraphQL, so they are applied to the resolved name list.
+    pub(super) fn exclude_tag_names(tag_names: Vec<String>, filters: &[ColumnFilter]) -> Vec<String> {
+        let exclusions: Vec<&ColumnFilter> = filters
+            .iter()
+            .filter(|f| {
+                f.column == "tag_name"
+                    && matches!(f.operator, FilterOperator::NotIn | FilterOperator::NotLike)
+            })
+            .collect();
+
+        if exclusions.is_empty() {
+            return tag_names;
+        }
+
+        tag_names
+            .into_iter()
+            .filter(|name| {
+                exclusions
+                    .iter()
+                    .all(|f| Self::check_string_filter(name, &f.operator, &f.value))
+            })
+            .collect()
+    }
+
     pub(super) fn matches_like_pattern(value: &str, pattern: &str) -> bool {
         // Simple LIKE pattern matching (% = any characters)
         let regex_pattern = pattern.replace('%', ".*");
@@ -407,3 +502,4 @@ impl QueryHandler {
         }
     }
 }
+
@@ -0,0 +1,157 @@
+use crate::query_handler::QueryHandler;
+use crate::tables::{QueryInfo, VirtualTable};
+use anyhow::Result;
+use arrow::array::{Int32Array, StringArray};
+use arrow::datatypes::{DataType, Field, Schema};
+use arrow::record_batch::RecordBatch;
+use pgwire::api::Type;
+use std::sync::Arc;
+
+/// Type columns of information_schema.columns
+pub(super) struct ColumnTypeInfo {
+    pub data_type: &'static str,
+    pub udt_name: &'static str,
+    pub numeric_precision: Option<i64>,
+    pub numeric_precision_radix: Option<i64>,
+    pub numeric_scale: Option<i64>,
+    pub datetime_precision: Option<i64>,
+}
+
+impl QueryHandler {
+    /// One row per primary key column, see `VirtualTable::primary_key_columns`
+    pub(super) fn create_key_column_usage_record_batch() -> Result<RecordBatch> {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("constraint_catalog", DataType::Utf8, false),
+            Field::new("constraint_schema", DataType::Utf8, false),
+            Field::new("constraint_name", DataType::Utf8, false),
+            Field::new("table_catalog", DataType::Utf8, false),
+            Field::new("table_schema", DataType::Utf8, false),
+            Field::new("table_name", DataType::Utf8, false),
+            Field::new("column_name", DataType::Utf8, false),
+            Field::new("ordinal_position", DataType::Int32, false),
+            Field::new("position_in_unique_constraint", DataType::Int32, true),
+        ]));
+
+        let mut table_names = Vec::new();
+        let mut column_names = Vec::new();
+        let mut ordinal_positions = Vec::new();
+        for table in Self::primary_key_tables() {
+            for (i, column_name) in table.primary_key_columns().iter().enumerate() {
+                table_names.push(table.to_string());
+                column_names.push(*column_name);
+                ordinal_positions.push(i as i32 + 1);
+            }
+        }
+
+        let count = table_names.len();
+        let constraint_names: Vec<String> = table_names.iter().map(|table_name| format!("{}_pkey", table_name)).collect();
+        RecordBatch::try_new(
+            schema,
+            vec![
+                Arc::new(StringArray::from(vec!["winccua"; count])),
+                Arc::new(StringArray::from(vec!["public"; count])),
+                Arc::new(StringArray::from(constraint_names)),
+                Arc::new(StringArray::from(vec!["winccua"; count])),
+                Arc::new(StringArray::from(vec!["public"; count])),
+                Arc::new(StringArray::from(table_names)),
+                Arc::new(StringArray::from(column_names)),
+                Arc::new(Int32Array::from(ordinal_positions)),
+                Arc::new(Int32Array::from(vec![None; count])), // Only set for foreign keys
+            ],
+        ).map_err(Into::into)
+    }
+
+    /// One PRIMARY KEY constraint per WinCC table
+    pub(super) fn create_table_constraints_record_batch() -> Result<RecordBatch> {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("constraint_catalog", DataType::Utf8, false),
+            Field::new("constraint_schema", DataType::Utf8, false),
+            Field::new("constraint_name", DataType::Utf8, false),
+            Field::new("table_catalog", DataType::Utf8, false),
+            Field::new("table_schema", DataType::Utf8, false),
+            Field::new("table_name", DataType::Utf8, false),
+            Field::new("constraint_type", DataType::Utf8, false),
+            Field::new("is_deferrable", DataType::Utf8, false),
+            Field::new("initially_deferred", DataType::Utf8, false),
+            Field::new("enforced", DataType::Utf8, false),
+        ]));
+
+        let table_names: Vec<String> = Self::primary_key_tables().iter().map(|table| table.to_string()).collect();
+        let count = table_names.len();
+        let constraint_names: Vec<String> = table_names.iter().map(|table_name| format!("{}_pkey", table_name)).collect();
+        RecordBatch::try_new(
+            schema,
+            vec![
+                Arc::new(StringArray::from(vec!["winccua"; count])),
+                Arc::new(StringArray::from(vec!["public"; count])),
+                Arc::new(StringArray::from(constraint_names)),
+                Arc::new(StringArray::from(vec!["winccua"; count])),
+                Arc::new(StringArray::from(vec!["public"; count])),
+                Arc::new(StringArray::from(table_names)),
+                Arc::new(StringArray::from(vec!["PRIMARY KEY"; count])),
+                Arc::new(StringArray::from(vec!["NO"; count])),
+                Arc::new(StringArray::from(vec!["NO"; count])),
+                Arc::new(StringArray::from(vec!["YES"; count])),
+            ],
+        ).map_err(Into::into)
+    }
+
+    /// data_type, udt_name and the precision columns of information_schema.columns for a column type
+    pub(super) fn information_schema_column_type(column_type: &Type) -> ColumnTypeInfo {
+        let (data_type, udt_name) = match *column_type {
+            Type::BOOL => ("boolean", "bool"),
+            Type::INT2 => ("smallint", "int2"),
+            Type::INT4 => ("integer", "int4"),
+            Type::INT8 => ("bigint", "int8"),
+            Type::FLOAT4 => ("real", "float4"),
+            Type::FLOAT8 => ("double precision", "float8"),
+            Type::NUMERIC => ("numeric", "numeric"),
+            Type::TIMESTAMP => ("timestamp without time zone", "timestamp"),
+            Type::JSON => ("json", "json"),
+            _ => ("text", "text"),
+        };
+        // Binary precision in bits for integers and floats, as PostgreSQL reports it
+        let (numeric_precision, numeric_precision_radix, numeric_scale) = match *column_type {
+            Type::INT2 => (Some(16), Some(2), Some(0)),
+            Type::INT4 => (Some(32), Some(2), Some(0)),
+            Type::INT8 => (Some(64), Some(2), Some(0)),
+            Type::FLOAT4 => (Some(24), Some(2), None),
+            Type::FLOAT8 => (Some(53), Some(2), None),
+            Type::NUMERIC => (None, Some(10), None),
+            _ => (None, None, None),
+        };
+        let datetime_precision = matches!(*column_type, Type::TIMESTAMP).then_some(6);
+        ColumnTypeInfo { data_type, udt_name, numeric_precision, numeric_precision_radix, numeric_scale, datetime_precision }
+    }
+
+    fn primary_key_tables() -> Vec<VirtualTable> {
+        VirtualTable::pg_class_relations()
+            .into_iter()
+            .filter(|table| !table.primary_key_columns().is_empty())
+            .collect()
+    }
+
+    /// Record batches of all information_schema tables except the queried one, keyed by table name
+    pub(super) fn other_information_schema_record_batches(query_info: &QueryInfo) -> Result<Vec<(String, RecordBatch)>> {
+        let mut batches = Vec::new();
+        if query_info.table != VirtualTable::InformationSchemaTables {
+            batches.push((
+                VirtualTable::InformationSchemaTables.to_string(),
+                Self::create_information_schema_tables_record_batch(query_info)?,
+            ));
+        }
+        if query_info.table != VirtualTable::InformationSchemaColumns {
+            batches.push((
+                VirtualTable::InformationSchemaColumns.to_string(),
+                Self::create_information_schema_columns_record_batch(query_info)?,
+            ));
+        }
+        if query_info.table != VirtualTable::InformationSchemaKeyColumnUsage {
+            batches.push((VirtualTable::InformationSchemaKeyColumnUsage.to_string(), Self::create_key_column_usage_record_batch()?));
+        }
+        if query_info.table != VirtualTable::InformationSchemaTableConstraints {
+            batches.push((VirtualTable::InformationSchemaTableConstraints.to_string(), Self::create_table_constraints_record_batch()?));
+        }
+        Ok(batches)
+    }
+}
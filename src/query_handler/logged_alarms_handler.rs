@@ -9,6 +9,7 @@ impl QueryHandler {
     pub(super) async fn fetch_logged_alarms_data(
         query_info: &QueryInfo,
         session: &AuthenticatedSession,
+        languages: &[String],
     ) -> Result<Vec<crate::graphql::types::LoggedAlarm>> {
         info!("📚 Fetching LoggedAlarms data");
 
@@ -34,7 +35,7 @@ impl QueryHandler {
         let filter_language = query_info.get_filter_language();
 
         // Get limit for maxNumberOfResults
-        let limit = query_info.limit.map(|l| l as i32);
+        let limit = query_info.fetch_limit().map(|l| l as i32);
 
         // Debug GraphQL query parameters
         debug!("🔧 GraphQL query parameters:");
@@ -57,6 +58,7 @@ impl QueryHandler {
                 end_time,
                 limit,
                 filter_language,
+                languages.to_vec(),
             )
             .await?;
         let graphql_elapsed_ms = graphql_start.elapsed().as_millis();
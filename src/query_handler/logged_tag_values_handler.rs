@@ -1,6 +1,6 @@
-use crate::auth::AuthenticatedSession;
+use crate::auth::{AuthenticatedSession, SessionManager};
 use crate::query_handler::QueryHandler;
-use crate::tables::QueryInfo;
+use crate::tables::{QueryInfo, VirtualTable};
 use anyhow::{anyhow, Result};
 use chrono::Utc;
 use std::time::Instant;
@@ -10,13 +10,35 @@ impl QueryHandler {
     pub(super) async fn fetch_logged_tag_values_data(
         query_info: &QueryInfo,
         session: &AuthenticatedSession,
+        session_manager: &SessionManager,
+    ) -> Result<Vec<crate::graphql::types::LoggedTagValue>> {
+        if query_info.has_timestamp_exclusion() {
+            warn!("⚠️  timestamp NOT BETWEEN cannot be pushed down to GraphQL, fetching logged values without that time restriction (full scan)");
+        }
+
+        // Get timestamp range
+        let (start_time, end_time) = query_info.get_timestamp_filter().unwrap_or((None, None));
+
+        // Determine sorting mode based on the first ORDER BY column, the full ordering is applied by DataFusion
+        let sorting_mode = query_info.sorting_mode();
+        Self::fetch_logged_tag_values_in_range(query_info, session, session_manager, start_time, end_time, sorting_mode).await
+    }
+
+    /// Logged values of the tags selected by `query_info` between `start_time` and `end_time`
+    pub(super) async fn fetch_logged_tag_values_in_range(
+        query_info: &QueryInfo,
+        session: &AuthenticatedSession,
+        session_manager: &SessionManager,
+        start_time: Option<String>,
+        mut end_time: Option<String>,
+        sorting_mode: &str,
     ) -> Result<Vec<crate::graphql::types::LoggedTagValue>> {
         debug!("📈 Fetching LoggedTagValues data");
 
         // Get tag names - handle LIKE patterns via browse if needed
         let tag_names = if query_info.requires_browse() {
             debug!("🔍 LoggedTagValues query contains LIKE patterns, using browse to resolve tag names");
-            Self::resolve_like_patterns(&query_info, session).await?
+            Self::resolve_like_patterns(query_info, session, session_manager.browse_cache()).await?
         } else {
             let tag_names = query_info.get_tag_names();
             if tag_names.is_empty() {
@@ -28,14 +50,13 @@ impl QueryHandler {
             tag_names
         };
 
+        let tag_names = Self::exclude_tag_names(tag_names, &query_info.filters);
+
         if tag_names.is_empty() {
             info!("📭 No tags found matching the LIKE criteria");
             return Ok(Vec::new());
         }
 
-        // Get timestamp range
-        let (start_time, mut end_time) = query_info.get_timestamp_filter().unwrap_or((None, None));
-
         // If endtime is not specified, use current UTC time
         if end_time.is_none() {
             let now = Utc::now().format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string();
@@ -45,40 +66,33 @@ impl QueryHandler {
 
         debug!("⏰ Time range: {:?} to {:?}", start_time, end_time);
 
-        // Get limit
-        let limit = query_info.limit.unwrap_or(1000); // Default limit for historical data
+        // Get limit; resampling and summaries aggregate the whole range, their LIMIT applies to the buckets
+        let limit = match query_info.table {
+            VirtualTable::LoggedTagValuesResampled => super::resample_handler::MAX_RESAMPLE_SAMPLES as i64,
+            VirtualTable::LoggedTagValuesSummary => super::summary_handler::MAX_SUMMARY_SAMPLES as i64,
+            _ => query_info.fetch_limit().unwrap_or(1000), // Default limit for historical data
+        };
         debug!("📏 Limit: {}", limit);
 
-        // Determine sorting mode based on ORDER BY clause
-        let sorting_mode = if let Some(order_by) = &query_info.order_by {
-            if order_by.column == "timestamp" {
-                if order_by.ascending {
-                    Some("TIME_ASC".to_string())
-                } else {
-                    Some("TIME_DESC".to_string())
-                }
-            } else {
-                // Default to TIME_ASC for non-timestamp ordering
-                Some("TIME_ASC".to_string())
-            }
-        } else {
-            // Default to TIME_ASC when no ordering is specified
-            Some("TIME_ASC".to_string())
-        };
+        let sorting_mode = Some(sorting_mode.to_string());
         debug!("🔄 Using GraphQL sortingMode: {:?}", sorting_mode);
 
         let graphql_start = Instant::now();
-        let logged_results_response = session
-            .client
-            .get_logged_tag_values(
-                &session.token,
-                tag_names,
-                start_time,
-                end_time,
-                Some(limit as i32),
-                sorting_mode,
-            )
-            .await?;
+        let client = session.client.clone();
+        let token = session.token.clone();
+        let logged_results_response = Self::fetch_in_batches(tag_names, session_manager.graphql_batch_size(), |batch| {
+            let client = client.clone();
+            let token = token.clone();
+            let start_time = start_time.clone();
+            let end_time = end_time.clone();
+            let sorting_mode = sorting_mode.clone();
+            async move {
+                client
+                    .get_logged_tag_values(&token, batch, start_time, end_time, Some(limit as i32), sorting_mode)
+                    .await
+            }
+        })
+        .await?;
         let graphql_elapsed_ms = graphql_start.elapsed().as_millis();
 
         // Convert LoggedTagValuesResult to LoggedTagValue format
@@ -0,0 +1,89 @@
+use crate::auth::{AuthenticatedSession, SessionManager};
+use crate::graphql::types::LoggedTagValue;
+use crate::query_handler::QueryHandler;
+use crate::tables::QueryInfo;
+use anyhow::Result;
+use arrow::array::TimestampNanosecondArray;
+use arrow::datatypes::{DataType, Field, Schema, TimeUnit};
+use arrow::record_batch::RecordBatch;
+use chrono::{DateTime, Utc};
+use std::sync::Arc;
+use tracing::debug;
+
+// Window fetched by the first query of a connection, before it has a cursor
+const INITIAL_LOOKBACK_SECS: i64 = 3600;
+
+impl QueryHandler {
+    /// loggedtagvalues_incr: logged values newer than the cursor of this connection and tag_name
+    /// filter, which then advances to the newest returned timestamp. Returns the values and the
+    /// cursor used as startTime. With a timestamp filter the query runs like loggedtagvalues.
+    pub(super) async fn fetch_logged_tag_values_incremental_data(
+        query_info: &QueryInfo,
+        session: &AuthenticatedSession,
+        session_manager: &SessionManager,
+        connection_id: Option<u32>,
+    ) -> Result<(Vec<LoggedTagValue>, Option<DateTime<Utc>>)> {
+        if query_info.get_timestamp_filter().is_some() {
+            debug!("⏩ loggedtagvalues_incr with a timestamp filter, not using the cursor");
+            let values = Self::fetch_logged_tag_values_data(query_info, session, session_manager).await?;
+            return Ok((values, None));
+        }
+
+        let key = Self::logged_value_cursor_key(query_info);
+        let stored_cursor = match connection_id {
+            Some(conn_id) => session_manager.logged_value_cursor(conn_id, &key).await,
+            None => None,
+        };
+        let cursor = stored_cursor.unwrap_or_else(|| Utc::now() - chrono::Duration::seconds(INITIAL_LOOKBACK_SECS));
+        debug!("⏩ loggedtagvalues_incr cursor for {}: {} (stored: {})", key, cursor, stored_cursor.is_some());
+
+        // Ascending, so a LIMIT keeps the oldest new values and the cursor does not skip any
+        let start_time = cursor.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string();
+        let values = Self::fetch_logged_tag_values_in_range(query_info, session, session_manager, Some(start_time), None, "TIME_ASC").await?;
+
+        // startTime is inclusive, the value at the cursor was returned by the previous query
+        let cursor_nanos = cursor.timestamp_nanos_opt().unwrap_or(0);
+        let values: Vec<LoggedTagValue> = values
+            .into_iter()
+            .filter(|value| Self::parse_string_timestamp_to_nanos(&value.timestamp).is_some_and(|nanos| nanos > cursor_nanos))
+            .collect();
+
+        if let Some(conn_id) = connection_id {
+            // Without new values the cursor stays, a first query pins the start of its window
+            let newest = values
+                .iter()
+                .filter_map(|value| Self::parse_string_timestamp_to_nanos(&value.timestamp))
+                .max()
+                .map(DateTime::from_timestamp_nanos)
+                .unwrap_or(cursor);
+            session_manager.advance_logged_value_cursor(conn_id, &key, newest).await;
+        }
+        debug!("⏩ loggedtagvalues_incr returned {} values newer than {}", values.len(), cursor);
+        Ok((values, Some(cursor)))
+    }
+
+    /// Cursors are kept per tag_name filter, so panels of the same connection don't share one
+    fn logged_value_cursor_key(query_info: &QueryInfo) -> String {
+        query_info
+            .filters
+            .iter()
+            .filter(|filter| filter.column == "tag_name")
+            .map(|filter| format!("{:?} {:?}", filter.operator, filter.value))
+            .collect::<Vec<_>>()
+            .join(" AND ")
+    }
+
+    pub(super) fn create_logged_tag_values_incremental_record_batch(
+        values: Vec<LoggedTagValue>,
+        cursor: Option<DateTime<Utc>>,
+    ) -> Result<RecordBatch> {
+        let batch = Self::create_logged_tag_values_record_batch(values)?;
+        let cursor_nanos = cursor.and_then(|cursor| cursor.timestamp_nanos_opt());
+
+        let mut fields: Vec<Field> = batch.schema().fields().iter().map(|field| field.as_ref().clone()).collect();
+        fields.push(Field::new("cursor", DataType::Timestamp(TimeUnit::Nanosecond, None), true));
+        let mut columns = batch.columns().to_vec();
+        columns.push(Arc::new(TimestampNanosecondArray::from(vec![cursor_nanos; batch.num_rows()])));
+        RecordBatch::try_new(Arc::new(Schema::new(fields)), columns).map_err(Into::into)
+    }
+}
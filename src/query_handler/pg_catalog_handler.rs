@@ -0,0 +1,560 @@
+use crate::datafusion_handler::WINCCUA_FUNCTIONS;
+use crate::query_handler::QueryHandler;
+use crate::tables::{VirtualTable, PG_CATALOG_NAMESPACE_OID, PUBLIC_NAMESPACE_OID};
+use pgwire::api::Type;
+use anyhow::Result;
+use arrow::array::{BooleanArray, Float32Array, Int16Array, Int32Array, Int64Array, StringArray, TimestampNanosecondArray};
+use arrow::datatypes::{DataType, Field, Schema, TimeUnit};
+use arrow::record_batch::RecordBatch;
+use std::sync::Arc;
+
+// OIDs are fixed rather than generated, so they are the same on every connection and after a
+// restart, and clients can cache them or look objects up with WHERE oid = ...:
+// - built-in objects (namespaces, catalog tables, types, functions) keep their PostgreSQL OIDs
+// - WinCC objects are numbered from 16384, the first OID of user objects in PostgreSQL:
+//   tables 16384.. (VirtualTable::relation_oid), their primary key indexes 1000 higher,
+//   functions 16400.. and the session role 16500
+
+// OID of the bootstrap superuser that owns the built-in namespaces
+const BOOTSTRAP_SUPERUSER_OID: i32 = 10;
+
+// pg_database OID of the single database, the OID of `postgres` in a fresh cluster
+const DATABASE_OID: i32 = 5;
+// pg_database.encoding of UTF8
+const UTF8_ENCODING: i32 = 6;
+
+// Role OID of the connected user, after the OIDs of the WinCC tables and functions
+const SESSION_ROLE_OID: i32 = 16500;
+
+// pg_proc OIDs of the WinCC functions, after the relation OIDs of the WinCC tables
+const WINCCUA_FUNCTION_OID_START: i32 = 16400;
+
+// Built-in functions clients look up in pg_proc as (oid, name, return type, argument types)
+const BUILTIN_FUNCTIONS: &[(i32, &str, Type, &[Type])] = &[
+    (89, "version", Type::TEXT, &[]),
+    (861, "current_database", Type::NAME, &[]),
+    (1402, "current_schema", Type::NAME, &[]),
+    (745, "current_user", Type::NAME, &[]),
+    (2077, "current_setting", Type::TEXT, &[Type::TEXT]),
+    (1299, "now", Type::TIMESTAMPTZ, &[]),
+    (2026, "pg_backend_pid", Type::INT4, &[]),
+];
+
+// Types listed in pg_type: every type a column or function of this server can have, plus the
+// types clients commonly look up, each with its array type
+const PG_TYPES: &[(Type, Type)] = &[
+    (Type::BOOL, Type::BOOL_ARRAY),
+    (Type::BYTEA, Type::BYTEA_ARRAY),
+    (Type::CHAR, Type::CHAR_ARRAY),
+    (Type::NAME, Type::NAME_ARRAY),
+    (Type::INT8, Type::INT8_ARRAY),
+    (Type::INT2, Type::INT2_ARRAY),
+    (Type::INT4, Type::INT4_ARRAY),
+    (Type::TEXT, Type::TEXT_ARRAY),
+    (Type::OID, Type::OID_ARRAY),
+    (Type::JSON, Type::JSON_ARRAY),
+    (Type::FLOAT4, Type::FLOAT4_ARRAY),
+    (Type::FLOAT8, Type::FLOAT8_ARRAY),
+    (Type::VARCHAR, Type::VARCHAR_ARRAY),
+    (Type::DATE, Type::DATE_ARRAY),
+    (Type::TIME, Type::TIME_ARRAY),
+    (Type::TIMESTAMP, Type::TIMESTAMP_ARRAY),
+    (Type::TIMESTAMPTZ, Type::TIMESTAMPTZ_ARRAY),
+    (Type::INTERVAL, Type::INTERVAL_ARRAY),
+    (Type::NUMERIC, Type::NUMERIC_ARRAY),
+    (Type::UUID, Type::UUID_ARRAY),
+    (Type::JSONB, Type::JSONB_ARRAY),
+];
+
+type CreateBatchFn = fn() -> Result<RecordBatch>;
+
+/// A row of pg_proc
+struct PgProcEntry {
+    oid: i32,
+    name: &'static str,
+    namespace_oid: i32,
+    return_type: Type,
+    arg_types: Vec<Type>,
+    volatility: &'static str, // 'i' immutable, 's' stable, 'v' volatile
+}
+
+impl QueryHandler {
+    pub(super) fn create_pg_class_record_batch() -> Result<RecordBatch> {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("oid", DataType::Int32, false),
+            Field::new("relname", DataType::Utf8, false),
+            Field::new("relnamespace", DataType::Int32, false),
+            Field::new("relkind", DataType::Utf8, false),
+            Field::new("reltype", DataType::Int32, false),
+            Field::new("relam", DataType::Int32, false),
+            Field::new("relpages", DataType::Int32, false),
+            Field::new("reltuples", DataType::Float32, false),
+            Field::new("relhasindex", DataType::Boolean, false),
+            Field::new("relisshared", DataType::Boolean, false),
+        ]));
+
+        let relations = VirtualTable::pg_class_relations();
+        let mut oids = Vec::with_capacity(relations.len());
+        let mut relnames = Vec::with_capacity(relations.len());
+        let mut relnamespaces = Vec::with_capacity(relations.len());
+        let mut relkinds = Vec::with_capacity(relations.len());
+        let mut relhasindexes = Vec::with_capacity(relations.len());
+
+        for relation in &relations {
+            let (name, namespace_oid) = relation.relation_name_and_namespace();
+            oids.push(relation.relation_oid().unwrap_or_default());
+            relnames.push(name);
+            relnamespaces.push(namespace_oid);
+            // WinCC tables are reported as views in information_schema.tables as well
+            relkinds.push(if namespace_oid == PG_CATALOG_NAMESPACE_OID { "r" } else { "v" });
+            relhasindexes.push(relation.primary_key_index_oid().is_some());
+        }
+        // The primary key indexes listed in pg_index, named like the constraints in information_schema
+        for relation in &relations {
+            let Some(index_oid) = relation.primary_key_index_oid() else {
+                continue;
+            };
+            let (name, namespace_oid) = relation.relation_name_and_namespace();
+            oids.push(index_oid);
+            relnames.push(format!("{}_pkey", name));
+            relnamespaces.push(namespace_oid);
+            relkinds.push("i");
+            relhasindexes.push(false);
+        }
+
+        let count = oids.len();
+        RecordBatch::try_new(
+            schema,
+            vec![
+                Arc::new(Int32Array::from(oids)),
+                Arc::new(StringArray::from(relnames)),
+                Arc::new(Int32Array::from(relnamespaces)),
+                Arc::new(StringArray::from(relkinds)),
+                Arc::new(Int32Array::from(vec![0; count])),
+                Arc::new(Int32Array::from(vec![0; count])),
+                Arc::new(Int32Array::from(vec![0; count])),
+                Arc::new(Float32Array::from(vec![-1.0; count])), // -1 = never analyzed
+                Arc::new(BooleanArray::from(relhasindexes)),
+                Arc::new(BooleanArray::from(vec![false; count])),
+            ],
+        ).map_err(Into::into)
+    }
+
+    /// One unique primary key index per table with a primary key, see `VirtualTable::primary_key_columns`
+    pub(super) fn create_pg_index_record_batch() -> Result<RecordBatch> {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("indexrelid", DataType::Int32, false),
+            Field::new("indrelid", DataType::Int32, false),
+            Field::new("indnatts", DataType::Int16, false),
+            Field::new("indisunique", DataType::Boolean, false),
+            Field::new("indisprimary", DataType::Boolean, false),
+            Field::new("indisexclusion", DataType::Boolean, false),
+            Field::new("indimmediate", DataType::Boolean, false),
+            Field::new("indisclustered", DataType::Boolean, false),
+            Field::new("indisvalid", DataType::Boolean, false),
+            Field::new("indkey", DataType::Utf8, false),
+            Field::new("indcollation", DataType::Utf8, false),
+            Field::new("indclass", DataType::Utf8, false),
+            Field::new("indoption", DataType::Utf8, false),
+            Field::new("indexprs", DataType::Utf8, true),
+            Field::new("indpred", DataType::Utf8, true),
+        ]));
+
+        let mut indexrelids = Vec::new();
+        let mut indrelids = Vec::new();
+        let mut indnatts = Vec::new();
+        let mut indkeys = Vec::new();
+        let mut zero_vectors = Vec::new();
+
+        for relation in VirtualTable::pg_class_relations() {
+            let (Some(index_oid), Some(relation_oid)) = (relation.primary_key_index_oid(), relation.relation_oid()) else {
+                continue;
+            };
+            // attnums as in pg_attribute
+            let column_names = relation.catalog_column_names();
+            let attnums: Vec<String> = relation
+                .primary_key_columns()
+                .iter()
+                .filter_map(|key| column_names.iter().position(|name| name == key))
+                .map(|position| (position + 1).to_string())
+                .collect();
+            indexrelids.push(index_oid);
+            indrelids.push(relation_oid);
+            indnatts.push(attnums.len() as i16);
+            zero_vectors.push(vec!["0"; attnums.len()].join(" "));
+            indkeys.push(attnums.join(" "));
+        }
+
+        let count = indexrelids.len();
+        RecordBatch::try_new(
+            schema,
+            vec![
+                Arc::new(Int32Array::from(indexrelids)),
+                Arc::new(Int32Array::from(indrelids)),
+                Arc::new(Int16Array::from(indnatts)),
+                Arc::new(BooleanArray::from(vec![true; count])),
+                Arc::new(BooleanArray::from(vec![true; count])),
+                Arc::new(BooleanArray::from(vec![false; count])),
+                Arc::new(BooleanArray::from(vec![true; count])),
+                Arc::new(BooleanArray::from(vec![false; count])),
+                Arc::new(BooleanArray::from(vec![true; count])),
+                Arc::new(StringArray::from(indkeys)),
+                Arc::new(StringArray::from(zero_vectors.clone())),
+                Arc::new(StringArray::from(zero_vectors.clone())),
+                Arc::new(StringArray::from(zero_vectors)),
+                Arc::new(StringArray::from(vec![None::<&str>; count])),
+                Arc::new(StringArray::from(vec![None::<&str>; count])),
+            ],
+        ).map_err(Into::into)
+    }
+
+    pub(super) fn create_pg_namespace_record_batch() -> Result<RecordBatch> {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("oid", DataType::Int32, false),
+            Field::new("nspname", DataType::Utf8, false),
+            Field::new("nspowner", DataType::Int32, false),
+        ]));
+
+        RecordBatch::try_new(
+            schema,
+            vec![
+                Arc::new(Int32Array::from(vec![PG_CATALOG_NAMESPACE_OID, PUBLIC_NAMESPACE_OID])),
+                Arc::new(StringArray::from(vec!["pg_catalog", "public"])),
+                Arc::new(Int32Array::from(vec![BOOTSTRAP_SUPERUSER_OID; 2])),
+            ],
+        ).map_err(Into::into)
+    }
+
+    pub(super) fn create_pg_attribute_record_batch() -> Result<RecordBatch> {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("attrelid", DataType::Int32, false),
+            Field::new("attname", DataType::Utf8, false),
+            Field::new("atttypid", DataType::Int32, false),
+            Field::new("attlen", DataType::Int16, false),
+            Field::new("attnum", DataType::Int16, false),
+            Field::new("attnotnull", DataType::Boolean, false),
+            Field::new("atthasdef", DataType::Boolean, false),
+            Field::new("attisdropped", DataType::Boolean, false),
+        ]));
+
+        let mut attrelids = Vec::new();
+        let mut attnames = Vec::new();
+        let mut atttypids = Vec::new();
+        let mut attlens = Vec::new();
+        let mut attnums = Vec::new();
+        let mut attnotnulls = Vec::new();
+
+        // attrelid values match the OIDs emitted in pg_class
+        for relation in VirtualTable::pg_class_relations() {
+            let Some(relation_oid) = relation.relation_oid() else {
+                continue;
+            };
+            for (i, column_name) in relation.catalog_column_names().into_iter().enumerate() {
+                let column_type = relation.get_column_type(column_name).unwrap_or(Type::TEXT);
+                attrelids.push(relation_oid);
+                attnames.push(column_name);
+                atttypids.push(column_type.oid() as i32);
+                attlens.push(Self::pg_type_length(&column_type));
+                attnums.push(i as i16 + 1);
+                let metadata_column = relation.metadata().and_then(|metadata| metadata.columns.iter().find(|column| column.name == column_name));
+                attnotnulls.push(metadata_column.is_some_and(|column| !column.is_nullable));
+            }
+        }
+
+        let count = attrelids.len();
+        RecordBatch::try_new(
+            schema,
+            vec![
+                Arc::new(Int32Array::from(attrelids)),
+                Arc::new(StringArray::from(attnames)),
+                Arc::new(Int32Array::from(atttypids)),
+                Arc::new(Int16Array::from(attlens)),
+                Arc::new(Int16Array::from(attnums)),
+                Arc::new(BooleanArray::from(attnotnulls)),
+                Arc::new(BooleanArray::from(vec![false; count])),
+                Arc::new(BooleanArray::from(vec![false; count])),
+            ],
+        ).map_err(Into::into)
+    }
+
+    pub(super) fn create_pg_proc_record_batch() -> Result<RecordBatch> {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("oid", DataType::Int32, false),
+            Field::new("proname", DataType::Utf8, false),
+            Field::new("pronamespace", DataType::Int32, false),
+            Field::new("prorettype", DataType::Int32, false),
+            Field::new("proargtypes", DataType::Utf8, false),
+            Field::new("pronargs", DataType::Int16, false),
+            Field::new("provolatile", DataType::Utf8, false),
+            Field::new("prokind", DataType::Utf8, false),
+            Field::new("prosrc", DataType::Utf8, false),
+        ]));
+
+        let mut functions: Vec<PgProcEntry> = BUILTIN_FUNCTIONS
+            .iter()
+            .map(|(oid, name, return_type, arg_types)| PgProcEntry {
+                oid: *oid,
+                name,
+                namespace_oid: PG_CATALOG_NAMESPACE_OID,
+                return_type: return_type.clone(),
+                arg_types: arg_types.to_vec(),
+                volatility: "s",
+            })
+            .collect();
+        for (i, (name, parameter_type, return_type, _)) in WINCCUA_FUNCTIONS.iter().enumerate() {
+            functions.push(PgProcEntry {
+                oid: WINCCUA_FUNCTION_OID_START + i as i32,
+                name,
+                namespace_oid: PUBLIC_NAMESPACE_OID,
+                return_type: Self::pg_type_by_name(return_type),
+                arg_types: vec![Self::pg_type_by_name(parameter_type)],
+                volatility: "i",
+            });
+        }
+
+        let count = functions.len();
+        RecordBatch::try_new(
+            schema,
+            vec![
+                Arc::new(Int32Array::from_iter_values(functions.iter().map(|f| f.oid))),
+                Arc::new(StringArray::from_iter_values(functions.iter().map(|f| f.name))),
+                Arc::new(Int32Array::from_iter_values(functions.iter().map(|f| f.namespace_oid))),
+                Arc::new(Int32Array::from_iter_values(functions.iter().map(|f| f.return_type.oid() as i32))),
+                // oidvector output: space-separated argument type OIDs
+                Arc::new(StringArray::from_iter_values(functions.iter().map(|f| {
+                    f.arg_types.iter().map(|t| t.oid().to_string()).collect::<Vec<_>>().join(" ")
+                }))),
+                Arc::new(Int16Array::from_iter_values(functions.iter().map(|f| f.arg_types.len() as i16))),
+                Arc::new(StringArray::from_iter_values(functions.iter().map(|f| f.volatility))),
+                Arc::new(StringArray::from(vec!["f"; count])),
+                Arc::new(StringArray::from_iter_values(functions.iter().map(|f| f.name))),
+            ],
+        ).map_err(Into::into)
+    }
+
+    /// Built-in base types of `PG_TYPES` and their array types, with the PostgreSQL OIDs
+    pub(super) fn create_pg_type_record_batch() -> Result<RecordBatch> {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("oid", DataType::Int32, false),
+            Field::new("typname", DataType::Utf8, false),
+            Field::new("typnamespace", DataType::Int32, false),
+            Field::new("typowner", DataType::Int32, false),
+            Field::new("typlen", DataType::Int16, false),
+            Field::new("typbyval", DataType::Boolean, false),
+            Field::new("typtype", DataType::Utf8, false),
+            Field::new("typcategory", DataType::Utf8, false),
+            Field::new("typrelid", DataType::Int32, false),
+            Field::new("typelem", DataType::Int32, false),
+            Field::new("typarray", DataType::Int32, false),
+            Field::new("typbasetype", DataType::Int32, false),
+            Field::new("typnotnull", DataType::Boolean, false),
+            Field::new("typtypmod", DataType::Int32, false),
+        ]));
+
+        // (type, element type of an array type, array type of a base type)
+        let types: Vec<(&Type, Option<&Type>, Option<&Type>)> = PG_TYPES
+            .iter()
+            .map(|(base, array)| (base, None, Some(array)))
+            .chain(PG_TYPES.iter().map(|(base, array)| (array, Some(base), None)))
+            .collect();
+        let lengths: Vec<i16> = types.iter().map(|(pg_type, _, _)| Self::pg_type_length(pg_type)).collect();
+
+        let count = types.len();
+        RecordBatch::try_new(
+            schema,
+            vec![
+                Arc::new(Int32Array::from_iter_values(types.iter().map(|(pg_type, _, _)| pg_type.oid() as i32))),
+                Arc::new(StringArray::from_iter_values(types.iter().map(|(pg_type, _, _)| pg_type.name()))),
+                Arc::new(Int32Array::from(vec![PG_CATALOG_NAMESPACE_OID; count])),
+                Arc::new(Int32Array::from(vec![BOOTSTRAP_SUPERUSER_OID; count])),
+                Arc::new(Int16Array::from(lengths.clone())),
+                // Fixed-size values of up to 8 bytes are passed by value
+                Arc::new(BooleanArray::from_iter(lengths.iter().map(|&length| Some((1..=8).contains(&length))))),
+                Arc::new(StringArray::from(vec!["b"; count])), // b = base type
+                Arc::new(StringArray::from_iter_values(types.iter().map(|(pg_type, element, _)| match element {
+                    Some(_) => "A",
+                    None => Self::pg_type_category(pg_type),
+                }))),
+                Arc::new(Int32Array::from(vec![0; count])),
+                Arc::new(Int32Array::from_iter_values(types.iter().map(|(_, element, _)| element.map_or(0, |t| t.oid() as i32)))),
+                Arc::new(Int32Array::from_iter_values(types.iter().map(|(_, _, array)| array.map_or(0, |t| t.oid() as i32)))),
+                Arc::new(Int32Array::from(vec![0; count])),
+                Arc::new(BooleanArray::from(vec![false; count])),
+                Arc::new(Int32Array::from(vec![-1; count])), // -1 = no type modifier
+            ],
+        ).map_err(Into::into)
+    }
+
+    /// The single database, named as in the connection's startup message
+    pub(super) fn create_pg_database_record_batch(database_name: &str) -> Result<RecordBatch> {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("oid", DataType::Int32, false),
+            Field::new("datname", DataType::Utf8, false),
+            Field::new("datdba", DataType::Int32, false),
+            Field::new("encoding", DataType::Int32, false),
+            Field::new("datcollate", DataType::Utf8, false),
+            Field::new("datctype", DataType::Utf8, false),
+            Field::new("datistemplate", DataType::Boolean, false),
+            Field::new("datallowconn", DataType::Boolean, false),
+            Field::new("datconnlimit", DataType::Int32, false),
+        ]));
+
+        RecordBatch::try_new(
+            schema,
+            vec![
+                Arc::new(Int32Array::from(vec![DATABASE_OID])),
+                Arc::new(StringArray::from(vec![database_name])),
+                Arc::new(Int32Array::from(vec![BOOTSTRAP_SUPERUSER_OID])),
+                Arc::new(Int32Array::from(vec![UTF8_ENCODING])),
+                Arc::new(StringArray::from(vec!["en_US.UTF-8"])),
+                Arc::new(StringArray::from(vec!["en_US.UTF-8"])),
+                Arc::new(BooleanArray::from(vec![false])),
+                Arc::new(BooleanArray::from(vec![true])),
+                Arc::new(Int32Array::from(vec![-1])), // -1 = no limit
+            ],
+        ).map_err(Into::into)
+    }
+
+    /// Statistics row of the database; only the number of connections is tracked
+    pub(super) fn create_pg_stat_database_record_batch(database_name: &str, numbackends: usize) -> Result<RecordBatch> {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("datid", DataType::Int32, false),
+            Field::new("datname", DataType::Utf8, false),
+            Field::new("numbackends", DataType::Int32, false),
+            Field::new("xact_commit", DataType::Int64, false),
+            Field::new("xact_rollback", DataType::Int64, false),
+            Field::new("blks_read", DataType::Int64, false),
+            Field::new("blks_hit", DataType::Int64, false),
+        ]));
+
+        RecordBatch::try_new(
+            schema,
+            vec![
+                Arc::new(Int32Array::from(vec![DATABASE_OID])),
+                Arc::new(StringArray::from(vec![database_name])),
+                Arc::new(Int32Array::from(vec![numbackends as i32])),
+                Arc::new(Int64Array::from(vec![0])),
+                Arc::new(Int64Array::from(vec![0])),
+                Arc::new(Int64Array::from(vec![0])),
+                Arc::new(Int64Array::from(vec![0])),
+            ],
+        ).map_err(Into::into)
+    }
+
+    /// The connected user as an ordinary login role; other WinCC users are not listed
+    pub(super) fn create_pg_roles_record_batch(username: &str) -> Result<RecordBatch> {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("oid", DataType::Int32, false),
+            Field::new("rolname", DataType::Utf8, false),
+            Field::new("rolsuper", DataType::Boolean, false),
+            Field::new("rolinherit", DataType::Boolean, false),
+            Field::new("rolcreaterole", DataType::Boolean, false),
+            Field::new("rolcreatedb", DataType::Boolean, false),
+            Field::new("rolcanlogin", DataType::Boolean, false),
+            Field::new("rolreplication", DataType::Boolean, false),
+            Field::new("rolconnlimit", DataType::Int32, false),
+            Field::new("rolvaliduntil", DataType::Timestamp(TimeUnit::Nanosecond, None), true),
+            Field::new("rolbypassrls", DataType::Boolean, false),
+        ]));
+
+        RecordBatch::try_new(
+            schema,
+            vec![
+                Arc::new(Int32Array::from(vec![SESSION_ROLE_OID])),
+                Arc::new(StringArray::from(vec![username])),
+                Arc::new(BooleanArray::from(vec![false])),
+                Arc::new(BooleanArray::from(vec![true])),
+                Arc::new(BooleanArray::from(vec![false])),
+                Arc::new(BooleanArray::from(vec![false])),
+                Arc::new(BooleanArray::from(vec![true])),
+                Arc::new(BooleanArray::from(vec![false])),
+                Arc::new(Int32Array::from(vec![-1])), // -1 = no limit
+                Arc::new(TimestampNanosecondArray::from(vec![None::<i64>])),
+                Arc::new(BooleanArray::from(vec![false])),
+            ],
+        ).map_err(Into::into)
+    }
+
+    /// pg_user, the older view of the login roles in pg_roles
+    pub(super) fn create_pg_user_record_batch(username: &str) -> Result<RecordBatch> {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("usename", DataType::Utf8, false),
+            Field::new("usesysid", DataType::Int32, false),
+            Field::new("usecreatedb", DataType::Boolean, false),
+            Field::new("usesuper", DataType::Boolean, false),
+            Field::new("userepl", DataType::Boolean, false),
+            Field::new("usebypassrls", DataType::Boolean, false),
+            Field::new("passwd", DataType::Utf8, false),
+            Field::new("valuntil", DataType::Timestamp(TimeUnit::Nanosecond, None), true),
+        ]));
+
+        RecordBatch::try_new(
+            schema,
+            vec![
+                Arc::new(StringArray::from(vec![username])),
+                Arc::new(Int32Array::from(vec![SESSION_ROLE_OID])),
+                Arc::new(BooleanArray::from(vec![false])),
+                Arc::new(BooleanArray::from(vec![false])),
+                Arc::new(BooleanArray::from(vec![false])),
+                Arc::new(BooleanArray::from(vec![false])),
+                Arc::new(StringArray::from(vec!["********"])), // As shown by PostgreSQL
+                Arc::new(TimestampNanosecondArray::from(vec![None::<i64>])),
+            ],
+        ).map_err(Into::into)
+    }
+
+    /// PostgreSQL type for a SQL type name as listed in `WINCCUA_FUNCTIONS`
+    fn pg_type_by_name(type_name: &str) -> Type {
+        match type_name {
+            "boolean" => Type::BOOL,
+            "integer" => Type::INT4,
+            "bigint" => Type::INT8,
+            "double precision" => Type::FLOAT8,
+            _ => Type::TEXT,
+        }
+    }
+
+    /// pg_type.typlen: fixed size in bytes, -1 for variable-length types
+    fn pg_type_length(column_type: &Type) -> i16 {
+        match *column_type {
+            Type::BOOL | Type::CHAR => 1,
+            Type::INT2 => 2,
+            Type::INT4 | Type::FLOAT4 | Type::OID | Type::DATE => 4,
+            Type::INT8 | Type::FLOAT8 | Type::TIME | Type::TIMESTAMP | Type::TIMESTAMPTZ => 8,
+            Type::INTERVAL | Type::UUID => 16,
+            Type::NAME => 64,
+            _ => -1,
+        }
+    }
+
+    /// pg_type.typcategory of a base type
+    fn pg_type_category(pg_type: &Type) -> &'static str {
+        match *pg_type {
+            Type::BOOL => "B",
+            Type::INT2 | Type::INT4 | Type::INT8 | Type::OID | Type::FLOAT4 | Type::FLOAT8 | Type::NUMERIC => "N",
+            Type::CHAR => "Z", // Internal-use type
+            Type::NAME | Type::TEXT | Type::VARCHAR => "S",
+            Type::DATE | Type::TIME | Type::TIMESTAMP | Type::TIMESTAMPTZ => "D",
+            Type::INTERVAL => "T",
+            _ => "U", // bytea, json, jsonb and uuid are user-defined types
+        }
+    }
+
+    /// Record batches of all catalog tables except `table`, keyed by table name
+    pub(super) fn other_pg_catalog_record_batches(table: &VirtualTable) -> Result<Vec<(String, RecordBatch)>> {
+        let catalog_tables: [(VirtualTable, CreateBatchFn); 6] = [
+            (VirtualTable::PgClass, Self::create_pg_class_record_batch),
+            (VirtualTable::PgNamespace, Self::create_pg_namespace_record_batch),
+            (VirtualTable::PgAttribute, Self::create_pg_attribute_record_batch),
+            (VirtualTable::PgProc, Self::create_pg_proc_record_batch),
+            (VirtualTable::PgType, Self::create_pg_type_record_batch),
+            (VirtualTable::PgIndex, Self::create_pg_index_record_batch),
+        ];
+
+        catalog_tables
+            .into_iter()
+            .filter(|(catalog_table, _)| catalog_table != table)
+            .map(|(catalog_table, create_batch)| Ok((catalog_table.to_string(), create_batch()?)))
+            .collect()
+    }
+}
@@ -0,0 +1,218 @@
+use crate::auth::{AuthenticatedSession, SessionManager};
+use crate::graphql::types::LoggedTagValue;
+use crate::query_handler::summary_handler::SummaryWindow;
+use crate::query_handler::QueryHandler;
+use crate::tables::QueryInfo;
+use anyhow::{anyhow, Result};
+use arrow::array::{Array, Float64Array, Int64Array, StringArray, TimestampNanosecondArray};
+use arrow::compute;
+use arrow::datatypes::{DataType, Field, Schema, TimeUnit};
+use arrow::record_batch::RecordBatch;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use tracing::{debug, warn};
+
+/// Aggregations of the values within a bucket of loggedtagvalues_resampled
+pub const RESAMPLE_METHODS: &[&str] = &["avg", "min", "max", "first", "last"];
+/// Upper bound of logged values fetched for resampling, a day of 1-second samples fits
+pub const MAX_RESAMPLE_SAMPLES: i32 = 100_000;
+
+// Numeric values of one tag within one bucket, with the timestamps of the first and last one
+struct Bucket {
+    sample_count: i64,
+    sum: f64,
+    min: f64,
+    max: f64,
+    first: (i64, f64),
+    last: (i64, f64),
+}
+
+impl Bucket {
+    fn new(timestamp: i64, value: f64) -> Self {
+        Self { sample_count: 1, sum: value, min: value, max: value, first: (timestamp, value), last: (timestamp, value) }
+    }
+
+    fn add(&mut self, timestamp: i64, value: f64) {
+        self.sample_count += 1;
+        self.sum += value;
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+        if timestamp < self.first.0 {
+            self.first = (timestamp, value);
+        }
+        if timestamp >= self.last.0 {
+            self.last = (timestamp, value);
+        }
+    }
+
+    fn value(&self, method: &str) -> f64 {
+        match method {
+            "min" => self.min,
+            "max" => self.max,
+            "first" => self.first.1,
+            "last" => self.last.1,
+            _ => self.sum / self.sample_count as f64,
+        }
+    }
+}
+
+impl QueryHandler {
+    /// Logged values of the tag and time range given as table function arguments
+    pub(super) async fn fetch_logged_tag_values_resampled_data(
+        query_info: &QueryInfo,
+        session: &AuthenticatedSession,
+        session_manager: &SessionManager,
+    ) -> Result<Vec<LoggedTagValue>> {
+        let (start_time, end_time) = query_info.get_timestamp_filter().unwrap_or((None, None));
+        let logged_values =
+            Self::fetch_logged_tag_values_in_range(query_info, session, session_manager, start_time, end_time, "TIME_ASC").await?;
+        if logged_values.len() >= MAX_RESAMPLE_SAMPLES as usize {
+            warn!("⚠️  More than {} logged values in the resampled range, the last buckets are incomplete", MAX_RESAMPLE_SAMPLES);
+        }
+        Ok(logged_values)
+    }
+
+    /// One row per tag and bucket of `interval_seconds`. Buckets are aligned to the Unix epoch
+    /// like date_bin, values without a numeric value are not counted.
+    pub(super) fn create_logged_tag_values_resampled_record_batch(
+        logged_values: Vec<LoggedTagValue>,
+        interval_seconds: i64,
+        method: &str,
+    ) -> Result<RecordBatch> {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("tag_name", DataType::Utf8, false),
+            Field::new("bucket_start", DataType::Timestamp(TimeUnit::Nanosecond, None), true),
+            Field::new("value", DataType::Float64, true),
+            Field::new("sample_count", DataType::Int64, false),
+        ]));
+
+        let logged = Self::create_logged_tag_values_record_batch(logged_values)?;
+        let tag_names = logged
+            .column(0)
+            .as_any()
+            .downcast_ref::<StringArray>()
+            .ok_or_else(|| anyhow!("Unexpected tag_name column type in logged values"))?;
+        let numeric_values = logged
+            .column(3)
+            .as_any()
+            .downcast_ref::<Float64Array>()
+            .ok_or_else(|| anyhow!("Unexpected numeric_value column type in logged values"))?;
+
+        // Buckets are fixed windows like the ones of loggedtagvalues_summary
+        let window = interval_seconds
+            .checked_mul(1_000_000_000)
+            .filter(|nanos| *nanos > 0)
+            .map(SummaryWindow::Fixed)
+            .ok_or_else(|| anyhow!("interval_seconds {} is out of range", interval_seconds))?;
+        let timestamps = compute::cast(logged.column(1), &DataType::Int64)?;
+        let timestamps = timestamps
+            .as_any()
+            .downcast_ref::<Int64Array>()
+            .ok_or_else(|| anyhow!("Unexpected timestamp column type in logged values"))?;
+
+        let mut buckets: BTreeMap<(&str, i64), Bucket> = BTreeMap::new();
+        for row in 0..logged.num_rows() {
+            if tag_names.is_null(row) || timestamps.is_null(row) || numeric_values.is_null(row) {
+                continue;
+            }
+            let (timestamp, value) = (timestamps.value(row), numeric_values.value(row));
+            buckets
+                .entry((tag_names.value(row), window.start(timestamp)))
+                .and_modify(|bucket| bucket.add(timestamp, value))
+                .or_insert_with(|| Bucket::new(timestamp, value));
+        }
+        debug!("🪣 Resampled {} logged values into {} buckets of {}s ({})", logged.num_rows(), buckets.len(), interval_seconds, method);
+
+        RecordBatch::try_new(
+            schema,
+            vec![
+                Arc::new(StringArray::from_iter_values(buckets.keys().map(|(tag_name, _)| *tag_name))),
+                Arc::new(TimestampNanosecondArray::from_iter_values(buckets.keys().map(|(_, bucket_start)| *bucket_start))),
+                Arc::new(Float64Array::from_iter_values(buckets.values().map(|bucket| bucket.value(method)))),
+                Arc::new(Int64Array::from_iter_values(buckets.values().map(|bucket| bucket.sample_count))),
+            ],
+        )
+        .map_err(Into::into)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::query_handler::{QueryResult, QueryValue};
+    use crate::sql_handler::SqlHandler;
+    use crate::tables::{SqlResult, VirtualTable};
+
+    /// 300 values one second apart, starting at 2024-01-01 00:00:00 with the value 0
+    fn second_samples() -> Vec<LoggedTagValue> {
+        (0..300)
+            .map(|i| LoggedTagValue {
+                tag_name: "Motor:Speed:PV".to_string(),
+                timestamp: format!("2024-01-01T00:{:02}:{:02}.000Z", i / 60, i % 60),
+                value: Some(serde_json::json!(i as f64)),
+                quality: None,
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_resample_seconds_into_minute_buckets() {
+        let sql = "SELECT bucket_start, value, sample_count \
+                   FROM loggedtagvalues_resampled('Motor:Speed:PV', '2024-01-01', '2024-01-02', 60, 'avg') ORDER BY bucket_start";
+        let query_info = match SqlHandler::parse_query(sql).unwrap() {
+            SqlResult::Query(query_info) => query_info,
+            other => panic!("Unexpected parse result {:?}", other),
+        };
+        assert_eq!(query_info.table, VirtualTable::LoggedTagValuesResampled);
+        assert_eq!(query_info.get_tag_names(), vec!["Motor:Speed:PV".to_string()]);
+        assert_eq!(query_info.get_timestamp_filter(), Some((Some("2024-01-01".to_string()), Some("2024-01-02".to_string()))));
+        assert_eq!((query_info.get_resample_interval(), query_info.get_resample_method()), (Some(60), Some("avg")));
+
+        let batch = QueryHandler::create_logged_tag_values_resampled_record_batch(second_samples(), 60, "avg").unwrap();
+        let sql = SqlHandler::strip_table_function_arguments(sql).unwrap();
+        let (batches, _) = crate::datafusion_handler::execute_query(&sql, batch, "loggedtagvalues_resampled").await.unwrap();
+        let result = QueryResult::from_record_batches(batches).unwrap();
+
+        assert_eq!(result.rows.len(), 5);
+        for (minute, row) in result.rows.iter().enumerate() {
+            assert!(matches!(&row[0], QueryValue::Timestamp(ts) if ts.starts_with(&format!("2024-01-01 00:{:02}:00", minute))), "{:?}", row[0]);
+            assert!(matches!(row[1], QueryValue::Float(avg) if avg == minute as f64 * 60.0 + 29.5), "{:?}", row[1]);
+            assert!(matches!(row[2], QueryValue::Integer(60)), "{:?}", row[2]);
+        }
+
+        let last = QueryHandler::create_logged_tag_values_resampled_record_batch(second_samples(), 60, "last").unwrap();
+        let values = last.column(2).as_any().downcast_ref::<Float64Array>().unwrap();
+        assert_eq!(values.values().to_vec(), vec![59.0, 119.0, 179.0, 239.0, 299.0]);
+
+        assert!(SqlHandler::parse_query("SELECT * FROM loggedtagvalues_resampled('Motor:Speed:PV', '2024-01-01', '2024-01-02', 60, 'median')").is_err());
+        assert!(SqlHandler::parse_query("SELECT * FROM loggedtagvalues_resampled").is_err());
+    }
+
+    #[test]
+    fn test_resample_buckets_before_1970() {
+        let sample = |timestamp: &str, value: f64| LoggedTagValue {
+            tag_name: "Motor:Speed:PV".to_string(),
+            timestamp: timestamp.to_string(),
+            value: Some(serde_json::json!(value)),
+            quality: None,
+        };
+        let samples = vec![
+            sample("1969-12-31T23:30:00.000Z", 1.0),
+            sample("1969-12-31T00:30:00.000Z", 2.0),
+            sample("1970-01-01T00:30:00.000Z", 3.0),
+        ];
+        let batch = QueryHandler::create_logged_tag_values_resampled_record_batch(samples, 86_400, "avg").unwrap();
+        let bucket_starts = batch.column(1).as_any().downcast_ref::<TimestampNanosecondArray>().unwrap();
+        let bucket_starts: Vec<String> = bucket_starts
+            .values()
+            .iter()
+            .map(|nanos| chrono::DateTime::from_timestamp_nanos(*nanos).format("%Y-%m-%d %H:%M").to_string())
+            .collect();
+        assert_eq!(bucket_starts, vec!["1969-12-31 00:00", "1970-01-01 00:00"]);
+        let counts = batch.column(3).as_any().downcast_ref::<Int64Array>().unwrap();
+        assert_eq!((counts.value(0), counts.value(1)), (2, 1));
+
+        let error = QueryHandler::create_logged_tag_values_resampled_record_batch(Vec::new(), 10_000_000_000, "avg").unwrap_err();
+        assert!(error.to_string().contains("out of range"), "{}", error);
+    }
+}
@@ -0,0 +1,292 @@
+use crate::auth::{AuthenticatedSession, SessionManager};
+use crate::graphql::types::LoggedTagValue;
+use crate::query_handler::QueryHandler;
+use crate::sql_handler::SqlHandler;
+use crate::tables::QueryInfo;
+use anyhow::{anyhow, Result};
+use arrow::array::{Array, ArrayRef, Float64Array, Int64Array, StringArray, TimestampNanosecondArray};
+use arrow::compute;
+use arrow::compute::SortColumn;
+use arrow::datatypes::{DataType, Field, Schema, TimeUnit};
+use arrow::record_batch::RecordBatch;
+use chrono::Datelike;
+use std::sync::Arc;
+use tracing::{debug, warn};
+
+/// Window width used when the query has no `window_size = '...'` filter
+pub const DEFAULT_SUMMARY_WINDOW_SIZE: &str = "1 day";
+/// Upper bound of logged values fetched for a summary, two years of hourly values of 50 tags fit
+pub const MAX_SUMMARY_SAMPLES: i32 = 1_000_000;
+
+/// Width of the summary windows: a fixed duration aligned to the Unix epoch like date_bin, or
+/// a number of calendar months (years are 12 months) starting on the first of a month, UTC
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum SummaryWindow {
+    Fixed(i64),
+    Months(i32),
+}
+
+impl SummaryWindow {
+    pub(crate) fn parse(window_size: &str) -> Result<Self> {
+        let invalid = || anyhow!("Invalid loggedtagvalues_summary window_size '{}', expected e.g. '1 day' or '1 month'", window_size);
+        let parts: Vec<&str> = window_size.split_whitespace().collect();
+        let months = match parts.as_slice() {
+            [value, unit] => match unit.to_lowercase().as_str() {
+                "month" | "months" => value.parse::<i32>().ok(),
+                "year" | "years" => value.parse::<i32>().ok().and_then(|years| years.checked_mul(12)),
+                _ => None,
+            },
+            _ => None,
+        };
+        if let Some(months) = months {
+            return (months > 0).then_some(SummaryWindow::Months(months)).ok_or_else(invalid);
+        }
+        SqlHandler::parse_interval_string(window_size)
+            .ok()
+            .and_then(|window| window.num_nanoseconds())
+            .filter(|nanos| *nanos > 0)
+            .map(SummaryWindow::Fixed)
+            .ok_or_else(invalid)
+    }
+
+    /// Start of the window containing `timestamp` (nanoseconds since the epoch)
+    pub(crate) fn start(&self, timestamp: i64) -> i64 {
+        match *self {
+            // rem_euclid keeps timestamps before 1970 in the window below them
+            SummaryWindow::Fixed(nanos) => timestamp - timestamp.rem_euclid(nanos),
+            SummaryWindow::Months(months) => {
+                let date = chrono::DateTime::from_timestamp_nanos(timestamp).date_naive();
+                let month_index = (date.year() - 1970) * 12 + date.month0() as i32;
+                month_start(month_index - month_index.rem_euclid(months))
+            }
+        }
+    }
+
+    /// End of the window starting at `start`
+    fn end(&self, start: i64) -> i64 {
+        match *self {
+            SummaryWindow::Fixed(nanos) => start.saturating_add(nanos),
+            SummaryWindow::Months(months) => {
+                let date = chrono::DateTime::from_timestamp_nanos(start).date_naive();
+                month_start((date.year() - 1970) * 12 + date.month0() as i32 + months)
+            }
+        }
+    }
+}
+
+// Midnight UTC of the first day of the month `month_index` months after January 1970
+fn month_start(month_index: i32) -> i64 {
+    chrono::NaiveDate::from_ymd_opt(1970 + month_index.div_euclid(12), month_index.rem_euclid(12) as u32 + 1, 1)
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .and_then(|midnight| midnight.and_utc().timestamp_nanos_opt())
+        .unwrap_or(i64::MAX)
+}
+
+impl QueryHandler {
+    /// Logged values of the requested tags within the window_start / window_end range
+    pub(super) async fn fetch_logged_tag_values_summary_data(
+        query_info: &QueryInfo,
+        session: &AuthenticatedSession,
+        session_manager: &SessionManager,
+    ) -> Result<Vec<LoggedTagValue>> {
+        // GraphQL has no aggregation of logged values, the raw values are summarized here
+        let (start_time, end_time) = query_info.get_window_range().unwrap_or((None, None));
+        let logged_values =
+            Self::fetch_logged_tag_values_in_range(query_info, session, session_manager, start_time, end_time, "TIME_ASC").await?;
+        if logged_values.len() >= MAX_SUMMARY_SAMPLES as usize {
+            warn!("⚠️  More than {} logged values in the summary range, the last windows are incomplete", MAX_SUMMARY_SAMPLES);
+        }
+        Ok(logged_values)
+    }
+
+    /// One row per tag and window of `window_size`, with min/max/avg of the numeric values computed
+    /// by Arrow kernels. Windows are aligned to the Unix epoch like date_bin, so day windows start
+    /// at midnight UTC; month and year windows follow the calendar, see `SummaryWindow`.
+    pub(super) fn create_logged_tag_values_summary_record_batch(logged_values: Vec<LoggedTagValue>, window_size: &str) -> Result<RecordBatch> {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("tag_name", DataType::Utf8, false),
+            Field::new("window_start", DataType::Timestamp(TimeUnit::Nanosecond, None), false),
+            Field::new("window_end", DataType::Timestamp(TimeUnit::Nanosecond, None), false),
+            Field::new("min_value", DataType::Float64, true),
+            Field::new("max_value", DataType::Float64, true),
+            Field::new("avg_value", DataType::Float64, true),
+            Field::new("sample_count", DataType::Int64, false),
+            Field::new("window_size", DataType::Utf8, false),
+        ]));
+        let window = SummaryWindow::parse(window_size)?;
+
+        // Values without a timestamp or numeric value are not counted
+        let logged = Self::create_logged_tag_values_record_batch(logged_values)?;
+        let counted = compute::and(&compute::is_not_null(logged.column(1))?, &compute::is_not_null(logged.column(3))?)?;
+        let logged = compute::filter_record_batch(&logged, &counted)?;
+
+        // Truncate the timestamps to the start of their window and sort by tag and window
+        let timestamps = compute::cast(logged.column(1), &DataType::Int64)?;
+        let timestamps = timestamps
+            .as_any()
+            .downcast_ref::<Int64Array>()
+            .ok_or_else(|| anyhow!("Unexpected timestamp column type in logged values"))?;
+        let window_starts: ArrayRef = Arc::new(timestamps.unary::<_, arrow::datatypes::Int64Type>(|timestamp| window.start(timestamp)));
+        let indices = compute::lexsort_to_indices(
+            &[
+                SortColumn { values: logged.column(0).clone(), options: None },
+                SortColumn { values: window_starts.clone(), options: None },
+            ],
+            None,
+        )?;
+        let tag_names: ArrayRef = compute::take(logged.column(0).as_ref(), &indices, None)?;
+        let window_starts: ArrayRef = compute::take(window_starts.as_ref(), &indices, None)?;
+        let numeric_values = compute::take(logged.column(3).as_ref(), &indices, None)?;
+        let numeric_values = numeric_values
+            .as_any()
+            .downcast_ref::<Float64Array>()
+            .ok_or_else(|| anyhow!("Unexpected numeric_value column type in logged values"))?;
+
+        // Every run of equal tag and window start is one window
+        let windows = compute::partition(&[tag_names.clone(), window_starts.clone()])?.ranges();
+        let starts: Vec<usize> = windows.iter().map(|window| window.start).collect();
+        let start_indices = arrow::array::UInt64Array::from_iter_values(starts.iter().map(|start| *start as u64));
+        let window_tag_names = compute::take(tag_names.as_ref(), &start_indices, None)?;
+        let window_start_values = compute::take(window_starts.as_ref(), &start_indices, None)?;
+        let window_start_values = window_start_values
+            .as_any()
+            .downcast_ref::<Int64Array>()
+            .ok_or_else(|| anyhow!("Unexpected window_start type"))?;
+
+        let mut min_values = Vec::with_capacity(windows.len());
+        let mut max_values = Vec::with_capacity(windows.len());
+        let mut avg_values = Vec::with_capacity(windows.len());
+        let mut sample_counts = Vec::with_capacity(windows.len());
+        for window in &windows {
+            let values = numeric_values.slice(window.start, window.len());
+            min_values.push(compute::min(&values));
+            max_values.push(compute::max(&values));
+            avg_values.push(compute::sum(&values).map(|sum| sum / values.len() as f64));
+            sample_counts.push(values.len() as i64);
+        }
+        debug!("🗜️  Summarized {} logged values into {} windows of {}", logged.num_rows(), windows.len(), window_size);
+
+        let window_start_values: Vec<i64> = window_start_values.values().to_vec();
+        RecordBatch::try_new(
+            schema,
+            vec![
+                window_tag_names,
+                Arc::new(TimestampNanosecondArray::from(window_start_values.clone())),
+                Arc::new(TimestampNanosecondArray::from_iter_values(window_start_values.iter().map(|start| window.end(*start)))),
+                Arc::new(Float64Array::from(min_values)),
+                Arc::new(Float64Array::from(max_values)),
+                Arc::new(Float64Array::from(avg_values)),
+                Arc::new(Int64Array::from(sample_counts)),
+                Arc::new(StringArray::from(vec![window_size; windows.len()])),
+            ],
+        )
+        .map_err(Into::into)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::query_handler::{QueryResult, QueryValue};
+    use crate::tables::{SqlResult, VirtualTable};
+
+    /// Hourly values for 730 days from 2024-01-01, the value is the hour of the day
+    fn hourly_samples() -> Vec<LoggedTagValue> {
+        let start = chrono::DateTime::parse_from_rfc3339("2024-01-01T00:00:00Z").unwrap();
+        (0..730 * 24)
+            .map(|hour| LoggedTagValue {
+                tag_name: "Motor:Speed:PV".to_string(),
+                timestamp: (start + chrono::Duration::hours(hour)).format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string(),
+                value: Some(serde_json::json!((hour % 24) as f64)),
+                quality: None,
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_summary_of_hourly_values_into_day_windows() {
+        let sql = "SELECT * FROM loggedtagvalues_summary WHERE tag_name = 'Motor:Speed:PV' \
+                   AND window_start >= '2024-01-01' AND window_end <= '2026-01-01' AND window_size = '1 day' ORDER BY window_start";
+        let query_info = match SqlHandler::parse_query(sql).unwrap() {
+            SqlResult::Query(query_info) => query_info,
+            other => panic!("Unexpected parse result {:?}", other),
+        };
+        assert_eq!(query_info.table, VirtualTable::LoggedTagValuesSummary);
+        assert_eq!(query_info.get_window_size(), Some("1 day"));
+        let (start_time, end_time) = query_info.get_window_range().unwrap();
+        assert!(start_time.is_some_and(|start| start.starts_with("2024-01-01")));
+        assert!(end_time.is_some_and(|end| end.starts_with("2026-01-01")));
+
+        let batch = QueryHandler::create_logged_tag_values_summary_record_batch(hourly_samples(), "1 day").unwrap();
+        let (batches, _) = crate::datafusion_handler::execute_query(sql, batch, "loggedtagvalues_summary").await.unwrap();
+        let result = QueryResult::from_record_batches(batches).unwrap();
+
+        assert_eq!(result.rows.len(), 730);
+        for (day, row) in result.rows.iter().enumerate() {
+            let midnight = (chrono::NaiveDate::from_ymd_opt(2024, 1, 1).unwrap() + chrono::Duration::days(day as i64)).format("%Y-%m-%d 00:00:00");
+            assert!(matches!(&row[1], QueryValue::Timestamp(ts) if ts.starts_with(&midnight.to_string())), "{:?}", row);
+            assert!(matches!(row[3], QueryValue::Float(min) if min == 0.0), "{:?}", row);
+            assert!(matches!(row[4], QueryValue::Float(max) if max == 23.0), "{:?}", row);
+            assert!(matches!(row[5], QueryValue::Float(avg) if avg == 11.5), "{:?}", row);
+            assert!(matches!(row[6], QueryValue::Integer(24)), "{:?}", row);
+        }
+
+        assert!(QueryHandler::create_logged_tag_values_summary_record_batch(hourly_samples(), "1 fortnight").is_err());
+        assert!(QueryHandler::create_logged_tag_values_summary_record_batch(hourly_samples(), "0 months").is_err());
+        assert!(SqlHandler::parse_query("SELECT * FROM loggedtagvalues_summary WHERE window_size = '1 day'").is_err());
+    }
+
+    fn window_bounds(batch: &RecordBatch) -> Vec<(String, String)> {
+        let format = |column: usize, row: usize| {
+            let timestamps = batch.column(column).as_any().downcast_ref::<TimestampNanosecondArray>().unwrap();
+            chrono::DateTime::from_timestamp_nanos(timestamps.value(row)).format("%Y-%m-%d %H:%M").to_string()
+        };
+        (0..batch.num_rows()).map(|row| (format(1, row), format(2, row))).collect()
+    }
+
+    #[test]
+    fn test_summary_month_windows_follow_the_calendar() {
+        // Hourly values from 2024-01-01 for 730 days: month windows have 28 to 31 days of values
+        let batch = QueryHandler::create_logged_tag_values_summary_record_batch(hourly_samples(), "1 month").unwrap();
+        assert_eq!(batch.num_rows(), 24);
+        let bounds = window_bounds(&batch);
+        assert_eq!(bounds[0], ("2024-01-01 00:00".to_string(), "2024-02-01 00:00".to_string()));
+        assert_eq!(bounds[1], ("2024-02-01 00:00".to_string(), "2024-03-01 00:00".to_string()));
+        let counts = batch.column(6).as_any().downcast_ref::<Int64Array>().unwrap();
+        assert_eq!((counts.value(0), counts.value(1), counts.value(13)), (31 * 24, 29 * 24, 28 * 24));
+
+        // Quarters start in January, April, July and October; years on January 1st
+        let batch = QueryHandler::create_logged_tag_values_summary_record_batch(hourly_samples(), "3 months").unwrap();
+        assert_eq!(window_bounds(&batch)[1], ("2024-04-01 00:00".to_string(), "2024-07-01 00:00".to_string()));
+        let batch = QueryHandler::create_logged_tag_values_summary_record_batch(hourly_samples(), "1 year").unwrap();
+        assert_eq!(window_bounds(&batch)[1], ("2025-01-01 00:00".to_string(), "2026-01-01 00:00".to_string()));
+    }
+
+    #[test]
+    fn test_summary_windows_before_1970() {
+        let sample = |timestamp: &str, value: f64| LoggedTagValue {
+            tag_name: "Motor:Speed:PV".to_string(),
+            timestamp: timestamp.to_string(),
+            value: Some(serde_json::json!(value)),
+            quality: None,
+        };
+        let samples = vec![
+            sample("1969-12-31T23:30:00.000Z", 1.0),
+            sample("1969-12-31T00:30:00.000Z", 2.0),
+            sample("1970-01-01T00:30:00.000Z", 3.0),
+        ];
+        let batch = QueryHandler::create_logged_tag_values_summary_record_batch(samples.clone(), "1 day").unwrap();
+        assert_eq!(
+            window_bounds(&batch),
+            vec![
+                ("1969-12-31 00:00".to_string(), "1970-01-01 00:00".to_string()),
+                ("1970-01-01 00:00".to_string(), "1970-01-02 00:00".to_string()),
+            ]
+        );
+        let counts = batch.column(6).as_any().downcast_ref::<Int64Array>().unwrap();
+        assert_eq!((counts.value(0), counts.value(1)), (2, 1));
+
+        let batch = QueryHandler::create_logged_tag_values_summary_record_batch(samples, "1 month").unwrap();
+        assert_eq!(window_bounds(&batch)[0], ("1969-12-01 00:00".to_string(), "1970-01-01 00:00".to_string()));
+    }
+}
@@ -0,0 +1,157 @@
+use crate::auth::AuthenticatedSession;
+use crate::graphql::types::BrowseResult;
+use crate::query_handler::QueryHandler;
+use crate::tables::{QueryInfo, TAG_PATH_SEPARATOR};
+use anyhow::Result;
+use std::collections::BTreeMap;
+use tracing::debug;
+
+/// A node of the tag name hierarchy; intermediate levels are objects, tags are variables
+#[derive(Debug, Clone, PartialEq)]
+pub struct TagHierarchyNode {
+    pub node_id: String,
+    pub parent_id: Option<String>,
+    pub display_name: String,
+    pub node_class: &'static str,
+    pub data_type: Option<String>,
+}
+
+impl QueryHandler {
+    pub(super) async fn fetch_tag_hierarchy_data(
+        query_info: &QueryInfo,
+        session: &AuthenticatedSession,
+    ) -> Result<Vec<TagHierarchyNode>> {
+        debug!("🌳 Fetching tag_hierarchy data");
+
+        let name_filters = query_info.get_hierarchy_name_filters();
+        debug!("🔍 Hierarchy browse name filters: {:?}", name_filters);
+
+        let browse_results = session.client.browse_tags(&session.token, name_filters, None, Self::browse_paginator()).await?;
+        debug!("✅ GraphQL browse returned {} tags", browse_results.len());
+
+        let nodes = Self::build_tag_hierarchy(browse_results);
+        debug!("🌳 Built {} hierarchy nodes", nodes.len());
+        Ok(nodes)
+    }
+
+    /// Split the browsed tag names at `::` into one node per level, sorted by node_id
+    pub(super) fn build_tag_hierarchy(results: Vec<BrowseResult>) -> Vec<TagHierarchyNode> {
+        let mut nodes: BTreeMap<String, TagHierarchyNode> = BTreeMap::new();
+
+        for result in results {
+            let levels: Vec<&str> = result.name.split(TAG_PATH_SEPARATOR).collect();
+            for depth in 1..=levels.len() {
+                let node_id = levels[..depth].join(TAG_PATH_SEPARATOR);
+                let parent_id = (depth > 1).then(|| levels[..depth - 1].join(TAG_PATH_SEPARATOR));
+                let segment = levels[depth - 1].to_string();
+
+                if depth == levels.len() {
+                    // The tag itself; replaces an object node created for a deeper tag of the same name
+                    nodes.insert(
+                        node_id.clone(),
+                        TagHierarchyNode {
+                            node_id,
+                            parent_id,
+                            display_name: result.display_name.clone().filter(|name| !name.is_empty()).unwrap_or(segment),
+                            node_class: "Variable",
+                            data_type: result.data_type.clone(),
+                        },
+                    );
+                } else {
+                    nodes.entry(node_id.clone()).or_insert(TagHierarchyNode {
+                        node_id,
+                        parent_id,
+                        display_name: segment,
+                        node_class: "Object",
+                        data_type: None,
+                    });
+                }
+            }
+        }
+
+        nodes.into_values().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::query_handler::QueryValue;
+    use crate::sql_handler::SqlHandler;
+    use crate::tables::{SqlResult, VirtualTable};
+
+    fn browse_result(name: &str, data_type: &str) -> BrowseResult {
+        BrowseResult {
+            name: name.to_string(),
+            display_name: None,
+            object_type: Some("TAG".to_string()),
+            data_type: Some(data_type.to_string()),
+        }
+    }
+
+    fn two_level_hierarchy() -> Vec<BrowseResult> {
+        vec![
+            browse_result("Server::Pump1::Speed", "Float"),
+            browse_result("Server::Pump1::Running", "Bool"),
+            browse_result("Server::Pump2::Speed", "Float"),
+        ]
+    }
+
+    #[test]
+    fn test_parent_id_is_computed_from_separator() {
+        let nodes = QueryHandler::build_tag_hierarchy(two_level_hierarchy());
+        let summary: Vec<(&str, Option<&str>, &str)> = nodes
+            .iter()
+            .map(|node| (node.node_id.as_str(), node.parent_id.as_deref(), node.node_class))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("Server", None, "Object"),
+                ("Server::Pump1", Some("Server"), "Object"),
+                ("Server::Pump1::Running", Some("Server::Pump1"), "Variable"),
+                ("Server::Pump1::Speed", Some("Server::Pump1"), "Variable"),
+                ("Server::Pump2", Some("Server"), "Object"),
+                ("Server::Pump2::Speed", Some("Server::Pump2"), "Variable"),
+            ]
+        );
+        assert_eq!(nodes[3].display_name, "Speed");
+        assert_eq!(nodes[3].data_type.as_deref(), Some("Float"));
+        assert_eq!(nodes[1].data_type, None);
+    }
+
+    #[tokio::test]
+    async fn test_children_of_parent_via_datafusion() {
+        let parse = |sql: &str| match SqlHandler::parse_query(sql).unwrap() {
+            SqlResult::Query(query_info) => query_info,
+            other => panic!("Unexpected parse result {:?}", other),
+        };
+
+        let sql = "SELECT node_id, display_name FROM winccua.tag_hierarchy WHERE parent_id = 'Server' ORDER BY node_id";
+        let query_info = parse(sql);
+        assert_eq!(query_info.table, VirtualTable::TagHierarchy);
+        assert_eq!(query_info.get_hierarchy_name_filters(), vec!["Server::*"]);
+
+        let batch = QueryHandler::create_tag_hierarchy_record_batch(QueryHandler::build_tag_hierarchy(two_level_hierarchy())).unwrap();
+        let tables = vec![
+            ("tag_hierarchy".to_string(), batch.clone()),
+            ("winccua.tag_hierarchy".to_string(), batch),
+        ];
+        let (results, _) = crate::datafusion_handler::execute_query_with_tables(sql, tables).await.unwrap();
+        let result = crate::query_handler::QueryResult::from_record_batches(results).unwrap();
+        let node_ids: Vec<String> = result
+            .rows
+            .iter()
+            .map(|row| match &row[0] {
+                QueryValue::Text(node_id) => node_id.clone(),
+                other => panic!("Unexpected value {:?}", other),
+            })
+            .collect();
+        assert_eq!(node_ids, vec!["Server::Pump1", "Server::Pump2"]);
+
+        let query_info = parse("SELECT * FROM tag_hierarchy WHERE node_id LIKE 'Server::Pump1::%'");
+        assert_eq!(query_info.get_hierarchy_name_filters(), vec!["Server::Pump1::*"]);
+        let query_info = parse("SELECT * FROM tag_hierarchy");
+        assert_eq!(query_info.get_hierarchy_name_filters(), vec!["*"]);
+    }
+}
@@ -29,23 +29,12 @@ impl QueryHandler {
             .unwrap_or_else(|| "en-US".to_string());
         debug!("🌐 Language filter: {}", language);
 
-        // Call GraphQL browse with filters
-        let browse_results = if object_type_filters.is_empty() {
-            session
-                .client
-                .browse_tags(&session.token, name_filters)
-                .await?
-        } else {
-            session
-                .client
-                .browse_tags_with_object_type(
-                    &session.token,
-                    name_filters,
-                    object_type_filters,
-                    language,
-                )
-                .await?
-        };
+        // Call GraphQL browse with filters, object types are filtered by GraphQL
+        let object_type_filter = (!object_type_filters.is_empty()).then_some(object_type_filters.as_slice());
+        let browse_results = session
+            .client
+            .browse_tags_in_language(&session.token, name_filters, object_type_filter, &language, Self::browse_paginator())
+            .await?;
 
         debug!("✅ GraphQL browse returned {} results", browse_results.len());
 
@@ -0,0 +1,249 @@
+use crate::auth::{AuthenticatedSession, SessionManager};
+use crate::graphql::types::{LoggedTagValue, TagValueResult};
+use crate::query_handler::QueryHandler;
+use crate::sql_handler::SqlHandler;
+use crate::tables::QueryInfo;
+use anyhow::{anyhow, Result};
+use arrow::array::{Array, BooleanArray, Float64Array, Int64Array, StringArray, TimestampNanosecondArray};
+use arrow::compute;
+use arrow::datatypes::{DataType, Field, Schema, TimeUnit};
+use arrow::record_batch::RecordBatch;
+use chrono::Utc;
+use std::sync::Arc;
+use tracing::{debug, warn};
+
+/// Lookback window used when the query has no `lookback = '...'` filter
+pub const DEFAULT_TAG_STATISTICS_LOOKBACK: &str = "24 hours";
+/// Upper bound of logged values fetched per tag for the statistics
+pub const MAX_TAG_STATISTICS_SAMPLES: i32 = 10_000;
+
+impl QueryHandler {
+    /// Current values of the requested tags and their logged values within the lookback window
+    pub(super) async fn fetch_tag_statistics_data(
+        query_info: &QueryInfo,
+        session: &AuthenticatedSession,
+        session_manager: &SessionManager,
+    ) -> Result<(Vec<TagValueResult>, Vec<LoggedTagValue>)> {
+        debug!("📐 Fetching tag_statistics data");
+
+        // Resolves LIKE patterns and exclusions, the logged values are fetched for the same tags
+        let current_values = Self::fetch_tag_values_data(query_info, session, session_manager).await?;
+        let tag_names: Vec<String> = current_values.iter().map(|result| result.name.clone()).collect();
+        if tag_names.is_empty() {
+            return Ok((current_values, Vec::new()));
+        }
+
+        let lookback = query_info.get_lookback().unwrap_or(DEFAULT_TAG_STATISTICS_LOOKBACK);
+        let lookback_duration = SqlHandler::parse_interval_string(lookback)
+            .map_err(|e| anyhow!("Invalid tag_statistics lookback '{}': {}", lookback, e))?;
+        let end = Utc::now();
+        let start_time = Some((end - lookback_duration).format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string());
+        let end_time = Some(end.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string());
+        debug!("⏰ Statistics window: {:?} to {:?}", start_time, end_time);
+
+        let client = session.client.clone();
+        let token = session.token.clone();
+        let logged_results = Self::fetch_in_batches(tag_names, session_manager.graphql_batch_size(), |batch| {
+            let client = client.clone();
+            let token = token.clone();
+            let start_time = start_time.clone();
+            let end_time = end_time.clone();
+            async move {
+                client
+                    .get_logged_tag_values(&token, batch, start_time, end_time, Some(MAX_TAG_STATISTICS_SAMPLES), Some("TIME_DESC".to_string()))
+                    .await
+            }
+        })
+        .await?;
+
+        let mut logged_values = Vec::new();
+        for result in logged_results {
+            if let Some(error) = &result.error {
+                let error_code = error.code.as_deref().unwrap_or("1");
+                if error_code != "0" {
+                    warn!(
+                        "⚠️  Error for logged tag {} (code {}): {}",
+                        result.logging_tag_name,
+                        error_code,
+                        error.description.as_deref().unwrap_or("Unknown error")
+                    );
+                    continue;
+                }
+            }
+            if result.values.len() as i32 >= MAX_TAG_STATISTICS_SAMPLES {
+                warn!("⚠️  Logged tag {} has more than {} values in the lookback window, statistics cover the most recent ones", result.logging_tag_name, MAX_TAG_STATISTICS_SAMPLES);
+            }
+            logged_values.extend(result.values.into_iter().map(|entry| LoggedTagValue {
+                tag_name: result.logging_tag_name.clone(),
+                timestamp: entry.value.timestamp,
+                value: entry.value.value,
+                quality: entry.value.quality,
+            }));
+        }
+
+        debug!("📈 Fetched {} logged values for {} tags", logged_values.len(), current_values.len());
+        Ok((current_values, logged_values))
+    }
+
+    /// One row per tag: min/max/avg over the logged numeric values, computed with Arrow kernels on
+    /// the logged values batch, plus the current value. Logging tags `Tag:LoggingTag` count for `Tag`.
+    pub(super) fn create_tag_statistics_record_batch(
+        current_values: Vec<TagValueResult>,
+        logged_values: Vec<LoggedTagValue>,
+        lookback: &str,
+    ) -> Result<RecordBatch> {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("tag_name", DataType::Utf8, false),
+            Field::new("sample_count", DataType::Int64, false),
+            Field::new("min_value", DataType::Float64, true),
+            Field::new("max_value", DataType::Float64, true),
+            Field::new("avg_value", DataType::Float64, true),
+            Field::new("last_value", DataType::Float64, true),
+            Field::new("last_timestamp", DataType::Timestamp(TimeUnit::Nanosecond, None), true),
+            Field::new("last_quality", DataType::Utf8, true),
+            Field::new("lookback", DataType::Utf8, false),
+        ]));
+
+        let logged = Self::create_logged_tag_values_record_batch(logged_values)?;
+        let logged_names = logged
+            .column(0)
+            .as_any()
+            .downcast_ref::<StringArray>()
+            .ok_or_else(|| anyhow!("Unexpected tag_name column type in logged values"))?;
+
+        let row_count = current_values.len();
+        let mut tag_names = Vec::with_capacity(row_count);
+        let mut sample_counts = Vec::with_capacity(row_count);
+        let mut min_values = Vec::with_capacity(row_count);
+        let mut max_values = Vec::with_capacity(row_count);
+        let mut avg_values = Vec::with_capacity(row_count);
+        let mut last_values = Vec::with_capacity(row_count);
+        let mut last_timestamps = Vec::with_capacity(row_count);
+        let mut last_qualities = Vec::with_capacity(row_count);
+
+        for current in current_values {
+            let logging_tag_prefix = format!("{}:", current.name);
+            let mask: BooleanArray = logged_names
+                .iter()
+                .map(|name| Some(name.is_some_and(|name| name == current.name || name.starts_with(&logging_tag_prefix))))
+                .collect();
+            let numeric = compute::filter(logged.column(3).as_ref(), &mask)?;
+            let numeric = numeric
+                .as_any()
+                .downcast_ref::<Float64Array>()
+                .ok_or_else(|| anyhow!("Unexpected numeric_value column type in logged values"))?;
+
+            let sample_count = (numeric.len() - numeric.null_count()) as i64;
+            let sum = compute::sum(numeric);
+            sample_counts.push(sample_count);
+            min_values.push(compute::min(numeric));
+            max_values.push(compute::max(numeric));
+            avg_values.push(sum.map(|sum| sum / sample_count as f64));
+
+            match current.value {
+                Some(value) => {
+                    last_values.push(value.value.as_ref().and_then(|v| v.as_f64()));
+                    last_timestamps.push(
+                        chrono::DateTime::parse_from_rfc3339(&value.timestamp)
+                            .ok()
+                            .and_then(|dt| dt.timestamp_nanos_opt()),
+                    );
+                    last_qualities.push(value.quality.map(|q| q.quality));
+                }
+                None => {
+                    last_values.push(None);
+                    last_timestamps.push(None);
+                    last_qualities.push(None);
+                }
+            }
+            tag_names.push(current.name);
+        }
+
+        RecordBatch::try_new(
+            schema,
+            vec![
+                Arc::new(StringArray::from(tag_names)),
+                Arc::new(Int64Array::from(sample_counts)),
+                Arc::new(Float64Array::from(min_values)),
+                Arc::new(Float64Array::from(max_values)),
+                Arc::new(Float64Array::from(avg_values)),
+                Arc::new(Float64Array::from(last_values)),
+                Arc::new(TimestampNanosecondArray::from(last_timestamps)),
+                Arc::new(StringArray::from(last_qualities)),
+                Arc::new(StringArray::from(vec![lookback; row_count])),
+            ],
+        )
+        .map_err(Into::into)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphql::types::{Quality, Value};
+    use crate::query_handler::{QueryResult, QueryValue};
+    use crate::tables::{SqlResult, VirtualTable};
+
+    fn current_value(name: &str, value: f64) -> TagValueResult {
+        TagValueResult {
+            name: name.to_string(),
+            value: Some(Value {
+                value: Some(serde_json::json!(value)),
+                timestamp: "2025-01-01T12:00:00.000Z".to_string(),
+                quality: Some(Quality { quality: "GOOD".to_string(), sub_status: None }),
+            }),
+            error: None,
+            warning: None,
+        }
+    }
+
+    /// 100 logged values 1..=100 for Tag_A and 101..=200 for Tag_B
+    fn logged_values() -> Vec<LoggedTagValue> {
+        [("Tag_A:LoggingTag_1", 0.0), ("Tag_B:LoggingTag_1", 100.0)]
+            .into_iter()
+            .flat_map(|(logging_tag, offset)| {
+                (1..=100).map(move |i| LoggedTagValue {
+                    tag_name: logging_tag.to_string(),
+                    timestamp: format!("2025-01-01T10:{:02}:{:02}.000Z", i / 60, i % 60),
+                    value: Some(serde_json::json!(offset + i as f64)),
+                    quality: None,
+                })
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_statistics_from_logged_values() {
+        let sql = "SELECT tag_name, sample_count, min_value, max_value, avg_value, last_value, last_quality \
+                   FROM tag_statistics WHERE tag_name IN ('Tag_A', 'Tag_B', 'Tag_C') AND lookback = '1 hour' ORDER BY tag_name";
+        let query_info = match SqlHandler::parse_query(sql).unwrap() {
+            SqlResult::Query(query_info) => query_info,
+            other => panic!("Unexpected parse result {:?}", other),
+        };
+        assert_eq!(query_info.table, VirtualTable::TagStatistics);
+        assert_eq!(query_info.get_lookback(), Some("1 hour"));
+
+        let current = vec![current_value("Tag_A", 42.0), current_value("Tag_B", 7.0), current_value("Tag_C", 1.0)];
+        let batch = QueryHandler::create_tag_statistics_record_batch(current, logged_values(), "1 hour").unwrap();
+        let (batches, _) = crate::datafusion_handler::execute_query(sql, batch, "tag_statistics").await.unwrap();
+        let result = QueryResult::from_record_batches(batches).unwrap();
+
+        let number = |value: &QueryValue| match value {
+            QueryValue::Integer(i) => Some(*i as f64),
+            QueryValue::Float(f) => Some(*f),
+            QueryValue::Null => None,
+            other => panic!("Unexpected value {:?}", other),
+        };
+        let rows: Vec<Vec<Option<f64>>> = result.rows.iter().map(|row| row[1..6].iter().map(number).collect()).collect();
+        assert_eq!(
+            rows,
+            vec![
+                vec![Some(100.0), Some(1.0), Some(100.0), Some(50.5), Some(42.0)],
+                vec![Some(100.0), Some(101.0), Some(200.0), Some(150.5), Some(7.0)],
+                // No logged values in the window
+                vec![Some(0.0), None, None, None, Some(1.0)],
+            ]
+        );
+        assert!(matches!(&result.rows[0][6], QueryValue::Text(quality) if quality == "GOOD"));
+    }
+}
@@ -1,24 +1,37 @@
-use crate::auth::AuthenticatedSession;
-use crate::query_handler::QueryHandler;
-use crate::tables::QueryInfo;
+use crate::auth::{AuthenticatedSession, SessionManager};
+use crate::metrics::Metrics;
+use crate::graphql::client::BrowsePaginator;
+use crate::graphql::types::{TagValueInput, TagValueResult};
+use crate::query_handler::{QueryHandler, QueryResult, SqlStateError};
+use crate::tables::{QueryInfo, TagWrite};
 use anyhow::{anyhow, Result};
+use std::sync::Arc;
 use std::time::Instant;
-use tracing::{debug, info};
+use tracing::{debug, info, warn};
+
+// Candidates named in the __warning of an ambiguous --tag-name-autocomplete
+const AUTOCOMPLETE_LISTED_TAGS: usize = 10;
 
 impl QueryHandler {
     pub(super) async fn fetch_tag_values_data(
         query_info: &QueryInfo,
         session: &AuthenticatedSession,
+        session_manager: &SessionManager,
     ) -> Result<Vec<crate::graphql::types::TagValueResult>> {
         debug!("📊 Fetching TagValues data");
 
         // Get tag names from the WHERE clause
         let tag_names = query_info.get_tag_names();
+        let exact_tag_names = !query_info.requires_browse() && !tag_names.is_empty();
 
         // Check if we need to use browse for LIKE patterns
         let final_tag_names = if query_info.requires_browse() {
             debug!("🔍 Query contains LIKE patterns, using browse to resolve tag names");
-            Self::resolve_like_patterns(&query_info, session).await?
+            Self::resolve_like_patterns(query_info, session, session_manager.browse_cache()).await?
+        } else if tag_names.is_empty()
+            && crate::ALLOW_FULL_TAGVALUES_SCAN.load(std::sync::atomic::Ordering::Relaxed)
+        {
+            Self::browse_all_tag_names(session).await?
         } else {
             // For non-LIKE queries, we must have explicit tag names
             if tag_names.is_empty() {
@@ -30,6 +43,15 @@ impl QueryHandler {
             tag_names
         };
 
+        let final_tag_names = if query_info.has_tag_name_exclusions() {
+            let before = final_tag_names.len();
+            let remaining = Self::exclude_tag_names(final_tag_names, &query_info.filters);
+            debug!("🚫 Excluded {} tag names via NOT IN / NOT LIKE", before - remaining.len());
+            remaining
+        } else {
+            final_tag_names
+        };
+
         if final_tag_names.is_empty() {
             info!("📭 No tags found matching the criteria");
             return Ok(Vec::new());
@@ -37,19 +59,268 @@ impl QueryHandler {
 
         debug!("🎯 Final tag names to query: {:?}", final_tag_names);
 
-        // Call GraphQL
+        // Call GraphQL in concurrent batches to keep individual requests small
         let graphql_start = Instant::now();
-        let tag_results = session
-            .client
-            .get_tag_values(&session.token, final_tag_names, false)
-            .await?;
+        let client = session.client.clone();
+        let token = session.token.clone();
+        let tag_results = Self::fetch_in_batches(final_tag_names, session_manager.graphql_batch_size(), |batch| {
+            let client = client.clone();
+            let token = token.clone();
+            async move { client.get_tag_values(&token, batch, false).await }
+        })
+        .await?;
         let graphql_elapsed_ms = graphql_start.elapsed().as_millis();
         debug!("🚀 GraphQL query for TagValues completed in {} ms with {} results", graphql_elapsed_ms, tag_results.len());
 
+        let tag_results = if crate::TAG_NAME_FALLBACK_BROWSE.load(std::sync::atomic::Ordering::Relaxed)
+            && crate::TagNameCaseFold::current() == crate::TagNameCaseFold::None
+        {
+            Self::retry_unknown_tags_case_insensitive(tag_results, session, &session_manager.metrics()).await?
+        } else {
+            tag_results
+        };
+
+        let tag_results = if exact_tag_names && crate::TAG_NAME_AUTOCOMPLETE.load(std::sync::atomic::Ordering::Relaxed) {
+            Self::autocomplete_unknown_tags(tag_results, session).await?
+        } else {
+            tag_results
+        };
+
         // Filter and format results
         let filtered_results = Self::apply_filters(tag_results, &query_info.filters)?;
         debug!("✂️  After filtering: {} results", filtered_results.len());
 
         Ok(filtered_results)
     }
+
+    /// tagvaluechanges: keep the tags whose value differs from the one last returned to this
+    /// connection. Without a connection there is nothing to compare against, so every row is kept.
+    pub(super) async fn keep_changed_tag_values(
+        results: Vec<TagValueResult>,
+        session_manager: &SessionManager,
+        connection_id: Option<u32>,
+        force: bool,
+    ) -> Vec<TagValueResult> {
+        let Some(conn_id) = connection_id else {
+            return results;
+        };
+
+        let values: Vec<(String, Option<f64>, Option<String>)> = results
+            .iter()
+            .map(|result| {
+                let (numeric_value, string_value) = Self::tag_value_fields(result);
+                (result.name.clone(), numeric_value, string_value)
+            })
+            .collect();
+        let changed = session_manager.detect_tag_value_changes(conn_id, &values).await;
+        if force {
+            debug!("🔁 tagvaluechanges forced, returning all {} rows", results.len());
+            return results;
+        }
+
+        let total = results.len();
+        let changes: Vec<TagValueResult> = results
+            .into_iter()
+            .zip(changed)
+            .filter_map(|(result, changed)| changed.then_some(result))
+            .collect();
+        debug!("🔁 tagvaluechanges: {} of {} tags changed", changes.len(), total);
+        changes
+    }
+
+    /// Error of a tag GraphQL could not read, as shown in __error and winccua_last_errors().
+    /// Error code 0 means success.
+    pub(super) fn tag_error_message(result: &TagValueResult) -> Option<String> {
+        let error = result.error.as_ref()?;
+        let code = error.code.as_deref().unwrap_or("1");
+        (code != "0").then(|| {
+            let description = error.description.as_deref().or(error.message.as_deref()).unwrap_or("Unknown error");
+            format!("code {}: {}", code, description)
+        })
+    }
+
+    /// Remember the errors of the tags of a tagvalues query for winccua_last_errors()
+    pub(super) async fn record_tag_errors(session_manager: &SessionManager, connection_id: Option<u32>, results: &[TagValueResult]) {
+        let Some(conn_id) = connection_id else {
+            return;
+        };
+        let errors = results
+            .iter()
+            .filter_map(|result| Self::tag_error_message(result).map(|error| (result.name.clone(), error)))
+            .collect();
+        session_manager.set_last_tag_errors(conn_id, errors).await;
+    }
+
+    /// numeric_value and string_value of a tag as shown in tagvalues
+    fn tag_value_fields(result: &TagValueResult) -> (Option<f64>, Option<String>) {
+        match result.value.as_ref().and_then(|value| value.value.as_ref()) {
+            Some(value) => match (value.as_f64(), value.as_str()) {
+                (Some(number), _) => (Some(number), None),
+                (None, Some(text)) => (None, Some(text.to_string())),
+                (None, None) => (None, Some(value.to_string())),
+            },
+            None => (None, None),
+        }
+    }
+
+    /// Write tag values for UPDATE tagvalues SET ...; only allowed with --enable-writes
+    pub(super) async fn write_tag_values(
+        writes: Vec<TagWrite>,
+        session: &AuthenticatedSession,
+        session_manager: Arc<SessionManager>,
+    ) -> Result<QueryResult> {
+        if !crate::ENABLE_WRITES.load(std::sync::atomic::Ordering::Relaxed) {
+            return Err(SqlStateError::new(
+                "42501",
+                "permission denied: tag writes are disabled, start the server with --enable-writes",
+            )
+            .into());
+        }
+
+        let count = writes.len();
+        info!(
+            "✏️  User {} writing {} tag values: {:?}",
+            session.username,
+            count,
+            writes.iter().map(|write| write.tag_name.as_str()).collect::<Vec<_>>()
+        );
+        let input = writes
+            .into_iter()
+            .map(|write| TagValueInput { name: write.tag_name, value: write.value })
+            .collect();
+
+        let graphql_start = Instant::now();
+        let results = session_manager
+            .circuit_breaker()
+            .call(session_manager.metrics().observe_graphql(session.client.write_tag_values(&session.token, input)))
+            .await?;
+
+        let failures: Vec<String> = results
+            .iter()
+            .filter_map(|result| {
+                let error = result.error.as_ref()?;
+                let code = error.code.as_deref().unwrap_or("1");
+                (code != "0").then(|| {
+                    format!(
+                        "{} (code {}: {})",
+                        result.name.as_deref().unwrap_or("?"),
+                        code,
+                        error.description.as_deref().unwrap_or("Unknown error")
+                    )
+                })
+            })
+            .collect();
+        if !failures.is_empty() {
+            warn!("⚠️  Tag write failed for {}", failures.join(", "));
+            return Err(anyhow!("Failed to write tag values: {}", failures.join(", ")));
+        }
+
+        let mut result = QueryResult::command(format!("UPDATE {}", count));
+        result.timings.graphql_time_ms = Some(graphql_start.elapsed().as_millis() as u64);
+        Ok(result)
+    }
+
+    /// --tag-name-fallback-browse: look up tags that were not found with a case-insensitive browse
+    /// and fetch them under their actual name. The rows keep the requested name, so the WHERE
+    /// clause evaluated by DataFusion still matches them.
+    async fn retry_unknown_tags_case_insensitive(
+        mut results: Vec<TagValueResult>,
+        session: &AuthenticatedSession,
+        metrics: &Metrics,
+    ) -> Result<Vec<TagValueResult>> {
+        for result in results.iter_mut().filter(|result| result.error.is_some()) {
+            let requested = result.name.to_lowercase();
+            let browse_results = session.client.browse_tags(&session.token, vec![result.name.to_uppercase()], None, Self::browse_paginator()).await?;
+            let Some(actual_name) = browse_results
+                .into_iter()
+                .map(|browse_result| browse_result.name)
+                .find(|name| name.to_lowercase() == requested && *name != result.name)
+            else {
+                continue;
+            };
+
+            let retried = session.client.get_tag_values(&session.token, vec![actual_name.clone()], false).await?;
+            if let Some(retried) = retried.into_iter().next().filter(|retried| retried.error.is_none()) {
+                info!("🔤 Tag '{}' not found, using '{}' from a case-insensitive browse", result.name, actual_name);
+                metrics.tag_name_case_folds_total.inc();
+                result.value = retried.value;
+                result.error = None;
+            }
+        }
+        Ok(results)
+    }
+
+    /// --tag-name-autocomplete: browse for `name*` when a tag of a tag_name = or IN filter was not
+    /// found. A single match is fetched and returned under the requested name, like with the
+    /// fallback browse; several matches are listed in __warning and the row keeps its error.
+    async fn autocomplete_unknown_tags(mut results: Vec<TagValueResult>, session: &AuthenticatedSession) -> Result<Vec<TagValueResult>> {
+        for result in results.iter_mut().filter(|result| Self::tag_error_message(result).is_some()) {
+            let pattern = format!("{}*", result.name);
+            let candidates: Vec<String> = session
+                .client
+                .browse_tags(&session.token, vec![pattern], None, Self::browse_paginator())
+                .await?
+                .into_iter()
+                .map(|browse_result| browse_result.name)
+                .filter(|name| *name != result.name)
+                .collect();
+
+            match candidates.as_slice() {
+                [] => {}
+                [completed] => {
+                    let retried = session.client.get_tag_values(&session.token, vec![completed.clone()], false).await?;
+                    if let Some(retried) = retried.into_iter().next().filter(|retried| Self::tag_error_message(retried).is_none()) {
+                        info!("🔮 Tag '{}' not found, completed to '{}'", result.name, completed);
+                        result.value = retried.value;
+                        result.error = None;
+                        result.warning = Some(format!("completed to '{}'", completed));
+                    }
+                }
+                _ => {
+                    debug!("🔮 Tag '{}' not found, {} tags start with it", result.name, candidates.len());
+                    let mut listed = candidates.iter().take(AUTOCOMPLETE_LISTED_TAGS).cloned().collect::<Vec<_>>().join(", ");
+                    if candidates.len() > AUTOCOMPLETE_LISTED_TAGS {
+                        listed.push_str(", ...");
+                    }
+                    result.warning = Some(format!("tag '{}' is ambiguous, {} tags start with it: {}", result.name, candidates.len(), listed));
+                }
+            }
+        }
+        Ok(results)
+    }
+
+    /// All tag names for a TagValues query without tag_name filter (--allow-full-tagvalues-scan),
+    /// cut to --full-scan-limit
+    pub(super) async fn browse_all_tag_names(session: &AuthenticatedSession) -> Result<Vec<String>> {
+        let limit = crate::FULL_SCAN_LIMIT.load(std::sync::atomic::Ordering::Relaxed);
+        warn!(
+            "⚠️  TagValues query without tag_name filter - browsing all tags (max {}). This can be slow on large systems",
+            limit
+        );
+
+        // No need to fetch more tags than the full scan limit can use; the paginator warns when
+        // the tags are cut at the limit
+        let browse_max_tags = crate::BROWSE_MAX_TAGS.load(std::sync::atomic::Ordering::Relaxed);
+        let mut paginator = BrowsePaginator::new(
+            crate::BROWSE_PAGE_SIZE.load(std::sync::atomic::Ordering::Relaxed),
+            crate::BROWSE_MAX_PAGES.load(std::sync::atomic::Ordering::Relaxed),
+            browse_max_tags.min(limit),
+        );
+        if limit < browse_max_tags {
+            paginator = paginator.with_max_tags_option("--full-scan-limit");
+        }
+
+        let graphql_start = Instant::now();
+        let browse_results = session
+            .client
+            .browse_tags(&session.token, vec!["*".to_string()], None, paginator)
+            .await?;
+        debug!(
+            "🌐 Full scan browse returned {} tags in {} ms",
+            browse_results.len(),
+            graphql_start.elapsed().as_millis()
+        );
+
+        Ok(browse_results.into_iter().map(|result| result.name).collect())
+    }
 }
@@ -1,24 +1,233 @@
-use crate::auth::AuthenticatedSession;
-use crate::query_handler::QueryHandler;
-use crate::tables::{ColumnFilter, FilterOperator, QueryInfo};
-use anyhow::Result;
+use crate::auth::{AuthenticatedSession, SessionManager};
+use crate::cache::BrowseCache;
+use crate::graphql::client::BrowsePaginator;
+use crate::query_handler::{QueryHandler, QueryValue};
+use crate::tables::{ColumnFilter, FilterOperator, FilterValue, QueryInfo};
+use anyhow::{anyhow, Result};
+use arrow::array::timezone::Tz;
+use std::future::Future;
+use std::sync::atomic::Ordering;
 use std::time::Instant;
-use tracing::{debug};
+use tokio::task::JoinSet;
+use tracing::{debug, warn, Instrument};
+
+// Session variable selecting the language of alarm texts for a connection
+pub(super) const ALARM_LOCALE_VARIABLE: &str = "winccua.alarm_locale";
+// Language requested for alarm texts without --alarm-languages
+pub(super) const DEFAULT_ALARM_LANGUAGE: &str = "en-US";
+// Session variable of `SET TimeZone`, or the TimeZone startup parameter
+const TIMEZONE_VARIABLE: &str = "timezone";
+
+impl QueryValue {
+    /// Float value of a result column; NaN and Infinity become NULL with --nan-as-null
+    pub fn from_float(f: f64) -> Self {
+        if !f.is_finite() && crate::NAN_AS_NULL.load(Ordering::Relaxed) {
+            QueryValue::Null
+        } else {
+            QueryValue::Float(f)
+        }
+    }
+
+    /// PostgreSQL text output of a float: NaN, Infinity and -Infinity instead of Rust's NaN, inf and -inf
+    pub fn pg_float_text(f: f64) -> String {
+        if f.is_infinite() {
+            if f > 0.0 { "Infinity" } else { "-Infinity" }.to_string()
+        } else {
+            // Display for f64 emits the shortest representation that round-trips exactly
+            f.to_string()
+        }
+    }
+
+    /// Encode the value in PostgreSQL text protocol format for a DataRow field.
+    /// Returns `None` for NULL, which is sent as a field length of -1.
+    pub fn to_pg_text_format(&self) -> Option<Vec<u8>> {
+        match self {
+            QueryValue::Null => None,
+            QueryValue::Text(s) => Some(s.as_bytes().to_vec()),
+            QueryValue::Integer(i) => Some(i.to_string().into_bytes()),
+            QueryValue::Float(f) => Some(Self::pg_float_text(*f).into_bytes()),
+            // Timestamps are already formatted as YYYY-MM-DD HH:MM:SS.ffffff, timestamptz with its offset
+            QueryValue::Timestamp(s) => Some(s.as_bytes().to_vec()),
+            QueryValue::Boolean(b) => Some(if *b { b"t".to_vec() } else { b"f".to_vec() }),
+            QueryValue::Json(s) => Some(s.as_bytes().to_vec()),
+        }
+    }
+
+    /// Encode the value in PostgreSQL binary format for the given column type OID.
+    /// Fixed-width types are big-endian; types without a binary encoding here fall back
+    /// to their text bytes, which is the binary representation of text/varchar.
+    /// Returns `None` for NULL.
+    pub fn to_pg_binary_format(&self, type_oid: u32) -> Option<Vec<u8>> {
+        if matches!(self, QueryValue::Null) {
+            return None;
+        }
+
+        let encoded = match type_oid {
+            16 => self.as_bool().map(|b| vec![b as u8]),
+            21 => self.as_i64().map(|i| (i as i16).to_be_bytes().to_vec()),
+            23 => self.as_i64().map(|i| (i as i32).to_be_bytes().to_vec()),
+            20 => self.as_i64().map(|i| i.to_be_bytes().to_vec()),
+            700 => self.as_f64().map(|f| (f as f32).to_be_bytes().to_vec()),
+            701 => self.as_f64().map(|f| f.to_be_bytes().to_vec()),
+            1114 | 1184 => self.as_pg_timestamp_micros().map(|us| us.to_be_bytes().to_vec()),
+            1700 => self.to_pg_text_format().and_then(|text| Self::pg_numeric_binary(&String::from_utf8_lossy(&text))),
+            // jsonb is its text prefixed with the format version 1
+            3802 => self.to_pg_text_format().map(|text| [&[1u8][..], &text].concat()),
+            _ => None,
+        };
+
+        encoded.or_else(|| self.to_pg_text_format())
+    }
+
+    /// NUMERIC binary format of a decimal like -1234.50: ndigits, weight (of the first digit group),
+    /// sign and display scale as int16, then the digits in base 10000 groups of four decimal digits
+    /// aligned at the decimal point. None for text that is not a plain decimal number.
+    fn pg_numeric_binary(text: &str) -> Option<Vec<u8>> {
+        const NUMERIC_POS: u16 = 0x0000;
+        const NUMERIC_NEG: u16 = 0x4000;
+        const NUMERIC_NAN: u16 = 0xC000;
+
+        let text = text.trim();
+        if text.eq_ignore_ascii_case("nan") {
+            return Some([0i16.to_be_bytes(), 0i16.to_be_bytes(), NUMERIC_NAN.to_be_bytes(), 0u16.to_be_bytes()].concat());
+        }
+        let (sign, unsigned) = match text.strip_prefix('-') {
+            Some(rest) => (NUMERIC_NEG, rest),
+            None => (NUMERIC_POS, text.strip_prefix('+').unwrap_or(text)),
+        };
+        let (integer, fraction) = unsigned.split_once('.').unwrap_or((unsigned, ""));
+        if integer.is_empty() && fraction.is_empty()
+            || !integer.bytes().chain(fraction.bytes()).all(|b| b.is_ascii_digit())
+        {
+            return None;
+        }
+
+        // Pad both parts to whole groups of four digits around the decimal point
+        let integer_groups = integer.len().div_ceil(4);
+        let padded = format!(
+            "{:0>int_width$}{:0<frac_width$}",
+            integer,
+            fraction,
+            int_width = integer_groups * 4,
+            frac_width = fraction.len().div_ceil(4) * 4
+        );
+        let mut digits: Vec<i16> = padded
+            .as_bytes()
+            .chunks(4)
+            .map(|group| group.iter().fold(0i16, |acc, b| acc * 10 + (b - b'0') as i16))
+            .collect();
+        let mut weight = integer_groups as i16 - 1;
+        let leading_zeros = digits.iter().take_while(|&&digit| digit == 0).count();
+        digits.drain(..leading_zeros);
+        weight -= leading_zeros as i16;
+        while digits.last() == Some(&0) {
+            digits.pop();
+        }
+        let (weight, sign) = if digits.is_empty() { (0, NUMERIC_POS) } else { (weight, sign) };
+
+        let mut encoded = Vec::with_capacity(8 + digits.len() * 2);
+        encoded.extend_from_slice(&(digits.len() as i16).to_be_bytes());
+        encoded.extend_from_slice(&weight.to_be_bytes());
+        encoded.extend_from_slice(&sign.to_be_bytes());
+        encoded.extend_from_slice(&(fraction.len() as u16).to_be_bytes());
+        for digit in digits {
+            encoded.extend_from_slice(&digit.to_be_bytes());
+        }
+        Some(encoded)
+    }
+
+    fn as_bool(&self) -> Option<bool> {
+        match self {
+            QueryValue::Boolean(b) => Some(*b),
+            QueryValue::Integer(i) => Some(*i != 0),
+            QueryValue::Text(s) => match s.to_lowercase().as_str() {
+                "t" | "true" | "1" => Some(true),
+                "f" | "false" | "0" => Some(false),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    fn as_i64(&self) -> Option<i64> {
+        match self {
+            QueryValue::Integer(i) => Some(*i),
+            QueryValue::Float(f) => Some(*f as i64),
+            QueryValue::Boolean(b) => Some(*b as i64),
+            QueryValue::Text(s) => s.trim().parse().ok(),
+            _ => None,
+        }
+    }
+
+    fn as_f64(&self) -> Option<f64> {
+        match self {
+            QueryValue::Float(f) => Some(*f),
+            QueryValue::Integer(i) => Some(*i as f64),
+            QueryValue::Text(s) => s.trim().parse().ok(),
+            _ => None,
+        }
+    }
+
+    /// Microseconds since the PostgreSQL epoch (2000-01-01 00:00:00)
+    fn as_pg_timestamp_micros(&self) -> Option<i64> {
+        let text = match self {
+            QueryValue::Timestamp(s) | QueryValue::Text(s) => s,
+            _ => return None,
+        };
+        let timestamp = chrono::NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M:%S%.f")
+            .ok()
+            .or_else(|| chrono::DateTime::parse_from_str(text, "%Y-%m-%d %H:%M:%S%.f%:z").ok().map(|dt| dt.naive_utc()))
+            .or_else(|| chrono::DateTime::parse_from_rfc3339(text).ok().map(|dt| dt.naive_utc()))?;
+        let pg_epoch = chrono::NaiveDate::from_ymd_opt(2000, 1, 1)?.and_hms_opt(0, 0, 0)?;
+        (timestamp - pg_epoch).num_microseconds()
+    }
+}
+
+/// Languages requested for alarm texts and the locale shown in event_text and info_text. The
+/// GraphQL server returns eventText and infoText in the order of the requested languages.
+#[derive(Debug, Clone)]
+pub(super) struct AlarmTextLocale {
+    pub languages: Vec<String>,
+    pub locale: String,
+}
+
+impl Default for AlarmTextLocale {
+    fn default() -> Self {
+        Self { languages: vec![DEFAULT_ALARM_LANGUAGE.to_string()], locale: String::new() }
+    }
+}
+
+impl AlarmTextLocale {
+    pub(super) fn select(&self, texts: Option<Vec<String>>) -> Option<String> {
+        texts.and_then(|texts| QueryHandler::select_localized_text(&texts, &self.languages, &self.locale))
+    }
+}
 
 impl QueryHandler {
     pub(super) async fn resolve_like_patterns(
         query_info: &QueryInfo,
         session: &AuthenticatedSession,
+        browse_cache: &BrowseCache,
     ) -> Result<Vec<String>> {
-        let patterns = query_info.get_like_patterns();
+        // ILIKE patterns are upper-cased for browse and matched case-insensitively afterwards
+        let patterns = query_info
+            .get_like_patterns()
+            .into_iter()
+            .map(|pattern| (pattern, false))
+            .chain(
+                query_info
+                    .get_ilike_patterns()
+                    .into_iter()
+                    .map(|pattern| (pattern.to_uppercase(), true)),
+            );
         let mut resolved_names = Vec::new();
 
-        for pattern in patterns {
+        for (pattern, case_insensitive) in patterns {
             debug!("🔍 Resolving LIKE pattern: '{}'", pattern);
 
             // For LoggedTagValues, auto-append ":*" if pattern doesn't contain ":"
             let processed_pattern =
-                if matches!(query_info.table, crate::tables::VirtualTable::LoggedTagValues) {
+                if matches!(query_info.table, crate::tables::VirtualTable::LoggedTagValues | crate::tables::VirtualTable::LoggedTagValuesIncremental) {
                     if !pattern.contains(':') {
                         let new_pattern = format!("{}:*", pattern);
                         debug!(
@@ -40,21 +249,26 @@ impl QueryHandler {
                 processed_pattern, browse_pattern
             );
 
-            // Call appropriate GraphQL browse function based on table type
+            // Call appropriate GraphQL browse function based on table type, unless the
+            // results of the same browse are still cached
             let graphql_start = Instant::now();
             let browse_results = match query_info.table {
-                crate::tables::VirtualTable::LoggedTagValues => {
+                crate::tables::VirtualTable::LoggedTagValues | crate::tables::VirtualTable::LoggedTagValuesIncremental => {
                     debug!("🗂️  Using browse_logging_tags for LoggedTagValues with objectTypeFilters=LOGGINGTAG");
-                    session
-                        .client
-                        .browse_logging_tags(&session.token, vec![browse_pattern.clone()])
+                    browse_cache
+                        .get_or_fetch(
+                            &format!("LOGGINGTAG:{}", browse_pattern),
+                            session.client.browse_logging_tags(&session.token, vec![browse_pattern.clone()], None, Self::browse_paginator()),
+                        )
                         .await?
                 }
                 _ => {
                     debug!("🗂️  Using standard browse_tags for non-LoggedTagValues table");
-                    session
-                        .client
-                        .browse_tags(&session.token, vec![browse_pattern.clone()])
+                    browse_cache
+                        .get_or_fetch(
+                            &browse_pattern,
+                            session.client.browse_tags(&session.token, vec![browse_pattern.clone()], None, Self::browse_paginator()),
+                        )
                         .await?
                 }
             };
@@ -62,7 +276,18 @@ impl QueryHandler {
             debug!("📋 GraphQL browse for LIKE pattern '{}' completed in {} ms returned {} tags", pattern, graphql_elapsed_ms, browse_results.len());
 
             // Extract just the names from BrowseResult
-            let tag_names: Vec<String> = browse_results.into_iter().map(|br| br.name).collect();
+            let tag_names: Vec<String> = browse_results
+                .into_iter()
+                .map(|br| br.name)
+                .filter(|name| {
+                    !case_insensitive
+                        || Self::check_string_filter(
+                            name,
+                            &FilterOperator::ILike,
+                            &FilterValue::String(browse_pattern.replace('*', "%")),
+                        )
+                })
+                .collect();
             if !tag_names.is_empty() {
                 debug!("🏷️  Found matching tags: {:?}", tag_names);
             } else {
@@ -78,6 +303,48 @@ impl QueryHandler {
         Ok(resolved_names)
     }
 
+    /// Split tag names into chunks of `batch_size` and run `fetch` for all chunks concurrently.
+    /// Results are returned in the order of the input names.
+    pub(super) async fn fetch_in_batches<T, F, Fut>(
+        tag_names: Vec<String>,
+        batch_size: usize,
+        fetch: F,
+    ) -> Result<Vec<T>>
+    where
+        T: Send + 'static,
+        F: Fn(Vec<String>) -> Fut,
+        Fut: Future<Output = Result<Vec<T>>> + Send + 'static,
+    {
+        let chunks: Vec<Vec<String>> = tag_names.chunks(batch_size.max(1)).map(|c| c.to_vec()).collect();
+        debug!("📦 Fetching {} tags in {} concurrent GraphQL batches", tag_names.len(), chunks.len());
+
+        let retry_counter = crate::graphql::client::retry_counter();
+        let mut tasks = JoinSet::new();
+        for (index, chunk) in chunks.into_iter().enumerate() {
+            let request = crate::graphql::client::with_retry_counter(retry_counter.clone(), fetch(chunk));
+            // Spawned tasks don't inherit the current span; keep the GraphQL spans under the query
+            tasks.spawn(async move { (index, request.await) }.in_current_span());
+        }
+
+        let mut batches = Vec::with_capacity(tasks.len());
+        while let Some(joined) = tasks.join_next().await {
+            let (index, result) = joined.map_err(|e| anyhow!("GraphQL batch task failed: {}", e))?;
+            batches.push((index, result?));
+        }
+        batches.sort_by_key(|(index, _)| *index);
+
+        Ok(batches.into_iter().flat_map(|(_, results)| results).collect())
+    }
+
+    /// Paginator for a browse with the --browse-page-size, --browse-max-pages and --browse-max-tags settings
+    pub(super) fn browse_paginator() -> BrowsePaginator {
+        BrowsePaginator::new(
+            crate::BROWSE_PAGE_SIZE.load(Ordering::Relaxed),
+            crate::BROWSE_MAX_PAGES.load(Ordering::Relaxed),
+            crate::BROWSE_MAX_TAGS.load(Ordering::Relaxed),
+        )
+    }
+
     pub(super) fn convert_like_to_browse_pattern(sql_pattern: &str) -> String {
         // Convert SQL LIKE pattern to GraphQL browse pattern
         // SQL LIKE: % = any characters, _ = single character
@@ -111,6 +378,51 @@ impl QueryHandler {
         }
     }
 
+    /// Languages of --alarm-languages and the locale set with `SET winccua.alarm_locale`,
+    /// falling back to --alarm-locale
+    pub(super) async fn alarm_text_locale(session_manager: &SessionManager, connection_id: Option<u32>) -> AlarmTextLocale {
+        let session_locale = match connection_id {
+            Some(conn_id) => session_manager.session_variables(conn_id).await.remove(ALARM_LOCALE_VARIABLE),
+            None => None,
+        };
+        AlarmTextLocale {
+            languages: crate::ALARM_LANGUAGES
+                .get()
+                .filter(|languages| !languages.is_empty())
+                .cloned()
+                .unwrap_or_else(|| vec![DEFAULT_ALARM_LANGUAGE.to_string()]),
+            locale: session_locale.or_else(|| crate::ALARM_LOCALE.get().cloned()).unwrap_or_default(),
+        }
+    }
+
+    /// Time zone of `SET TimeZone` or the TimeZone startup parameter, None for UTC. Unknown
+    /// names are logged and leave timestamps in UTC.
+    pub(super) async fn session_timezone(session_manager: &SessionManager, connection_id: Option<u32>) -> Option<Tz> {
+        let name = session_manager.session_variables(connection_id?).await.remove(TIMEZONE_VARIABLE)?;
+        if name.eq_ignore_ascii_case("UTC") {
+            return None;
+        }
+        name.parse::<Tz>()
+            .inspect_err(|e| warn!("⚠️  Unknown time zone '{}', showing timestamps in UTC: {}", name, e))
+            .ok()
+    }
+
+    /// Text for `locale` from alarm texts in the order of `languages`: the exact language first,
+    /// then the first one of the same language ("de" matches "de-DE"), otherwise the first text.
+    /// An empty locale selects the first text.
+    pub(super) fn select_localized_text(texts: &[String], languages: &[String], locale: &str) -> Option<String> {
+        let language_of = |tag: &str| tag.split(['-', '_']).next().unwrap_or_default().to_ascii_lowercase();
+        let position = (!locale.is_empty())
+            .then(|| {
+                languages
+                    .iter()
+                    .position(|language| language.eq_ignore_ascii_case(locale))
+                    .or_else(|| languages.iter().position(|language| language_of(language) == language_of(locale)))
+            })
+            .flatten();
+        position.and_then(|index| texts.get(index)).or_else(|| texts.first()).cloned()
+    }
+
     pub(super) fn extract_alarm_filter_string(filters: &[ColumnFilter]) -> Option<String> {
         // Look for text-based filters that can be used as alarm filter strings
         for filter in filters {
@@ -128,4 +440,40 @@ impl QueryHandler {
         None
     }
 
+    /// Seconds of an ISO 8601 duration like `PT3H15M` or `P1DT2H`, as returned for alarm durations.
+    /// Fractional seconds are truncated; years and months are not supported.
+    pub(crate) fn parse_iso_duration_to_seconds(duration: &str) -> Option<i64> {
+        let rest = duration.trim().strip_prefix('P')?;
+        let (date_part, time_part) = match rest.split_once('T') {
+            Some((date, time)) => (date, Some(time)),
+            None => (rest, None),
+        };
+
+        let mut seconds = 0.0;
+        let mut parse_part = |part: &str, units: &[(char, f64)]| -> Option<()> {
+            let mut number = String::new();
+            for c in part.chars() {
+                if c.is_ascii_digit() || c == '.' || c == ',' {
+                    number.push(if c == ',' { '.' } else { c });
+                } else {
+                    let factor = units.iter().find(|(unit, _)| *unit == c)?.1;
+                    seconds += number.parse::<f64>().ok()? * factor;
+                    number.clear();
+                }
+            }
+            number.is_empty().then_some(())
+        };
+        parse_part(date_part, &[('W', 604_800.0), ('D', 86_400.0)])?;
+        if let Some(time_part) = time_part {
+            if time_part.is_empty() {
+                return None;
+            }
+            parse_part(time_part, &[('H', 3_600.0), ('M', 60.0), ('S', 1.0)])?;
+        }
+        if date_part.is_empty() && time_part.is_none() {
+            return None;
+        }
+        Some(seconds as i64)
+    }
+
 }
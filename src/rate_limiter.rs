@@ -0,0 +1,104 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Instant;
+
+// Defaults unless overridden with --rate-limit-queries-per-second / --rate-limit-burst
+pub const DEFAULT_RATE_LIMIT_QUERIES_PER_SECOND: u32 = 0;
+pub const DEFAULT_RATE_LIMIT_BURST: u32 = 10;
+
+#[derive(Debug)]
+struct TokenBucket {
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+/// Token bucket per username, so one dashboard cannot flood the GraphQL server. A bucket holds
+/// up to `burst` queries and refills at `queries_per_second`.
+#[derive(Debug)]
+pub struct RateLimiter {
+    queries_per_second: u32, // 0 disables the limit
+    burst: u32,
+    buckets: Mutex<HashMap<String, TokenBucket>>,
+}
+
+impl RateLimiter {
+    pub fn new(queries_per_second: u32, burst: u32) -> Self {
+        Self {
+            queries_per_second,
+            burst: burst.max(1),
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn is_enabled(&self) -> bool {
+        self.queries_per_second > 0
+    }
+
+    /// Give a newly authenticated user a full bucket, unless it already has one
+    pub fn add_user(&self, username: &str) {
+        if !self.is_enabled() {
+            return;
+        }
+        self.buckets.lock().unwrap().entry(username.to_string()).or_insert_with(|| self.full_bucket());
+    }
+
+    /// Drop the bucket once the user has no session left
+    pub fn remove_user(&self, username: &str) {
+        self.buckets.lock().unwrap().remove(username);
+    }
+
+    /// Take a token for one query of `username`, false if its bucket is empty
+    pub fn try_acquire(&self, username: &str) -> bool {
+        if !self.is_enabled() {
+            return true;
+        }
+        let mut buckets = self.buckets.lock().unwrap();
+        let bucket = buckets.entry(username.to_string()).or_insert_with(|| self.full_bucket());
+        let now = Instant::now();
+        let refill = now.duration_since(bucket.refilled_at).as_secs_f64() * self.queries_per_second as f64;
+        bucket.tokens = (bucket.tokens + refill).min(self.burst as f64);
+        bucket.refilled_at = now;
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn full_bucket(&self) -> TokenBucket {
+        TokenBucket {
+            tokens: self.burst as f64,
+            refilled_at: Instant::now(),
+        }
+    }
+}
+
+impl Default for RateLimiter {
+    fn default() -> Self {
+        Self::new(DEFAULT_RATE_LIMIT_QUERIES_PER_SECOND, DEFAULT_RATE_LIMIT_BURST)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_buckets_allow_a_burst_and_refill_over_time() {
+        let limiter = RateLimiter::new(20, 3);
+        limiter.add_user("grafana");
+        assert_eq!((0..5).filter(|_| limiter.try_acquire("grafana")).count(), 3);
+        // Other users have their own bucket
+        assert!(limiter.try_acquire("operator"));
+
+        std::thread::sleep(std::time::Duration::from_millis(120));
+        assert!(limiter.try_acquire("grafana"), "a token is refilled every 50 ms");
+
+        limiter.remove_user("grafana");
+        assert!(!limiter.buckets.lock().unwrap().contains_key("grafana"));
+
+        let disabled = RateLimiter::default();
+        assert!((0..100).all(|_| disabled.try_acquire("grafana")));
+    }
+}
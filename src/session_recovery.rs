@@ -0,0 +1,171 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+// Maximum age of a GraphQL session unless overridden with --session-max-lifetime (0 = unlimited)
+pub const DEFAULT_SESSION_MAX_LIFETIME_SECS: u64 = 3600;
+
+/// A GraphQL session as listed in the recovery file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecoverableSession {
+    pub token: String,
+    pub username: String,
+    pub expires: String,
+    pub graphql_url: String,
+}
+
+/// Tokens of the open GraphQL sessions (--session-recovery-file), rewritten whenever a session
+/// is created, extended or removed. Sessions still listed when the server starts were left
+/// open by a crash and are logged out, so they stop holding WinCC licenses.
+#[derive(Debug)]
+pub struct SessionRecoveryFile {
+    path: PathBuf,
+    write_lock: Mutex<()>,
+}
+
+impl SessionRecoveryFile {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            write_lock: Mutex::new(()),
+        }
+    }
+
+    pub fn path(&self) -> &std::path::Path {
+        &self.path
+    }
+
+    /// Replace the listed sessions. The list is written to `<path>.tmp` and renamed, so a crash
+    /// while writing keeps the previous list. The file holds bearer tokens and is only readable
+    /// by the owner on unix.
+    pub fn save(&self, sessions: &[RecoverableSession]) -> Result<()> {
+        let _guard = self.write_lock.lock().unwrap();
+        let mut temp_path = self.path.clone().into_os_string();
+        temp_path.push(".tmp");
+        let temp_path = PathBuf::from(temp_path);
+
+        // The mode only applies to new files, so a temp file left by a crash is removed first
+        if let Err(e) = std::fs::remove_file(&temp_path) {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                return Err(e).with_context(|| format!("Failed to remove {}", temp_path.display()));
+            }
+        }
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let file = options.open(&temp_path).with_context(|| format!("Failed to create {}", temp_path.display()))?;
+        let mut writer = BufWriter::new(file);
+        serde_json::to_writer(&mut writer, sessions)?;
+        writer.flush()?;
+        std::fs::rename(&temp_path, &self.path).with_context(|| format!("Failed to replace {}", self.path.display()))?;
+        Ok(())
+    }
+
+    /// Sessions listed by the previous run, none if the file does not exist
+    pub fn load(&self) -> Result<Vec<RecoverableSession>> {
+        let content = match std::fs::read_to_string(&self.path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {}", self.path.display())),
+        };
+        if content.trim().is_empty() {
+            return Ok(Vec::new());
+        }
+        serde_json::from_str(&content).with_context(|| format!("Invalid session recovery file {}", self.path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::SessionManager;
+    use crate::test_support::{spawn_graphql_server, MockResponse};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    // GraphQL server answering login with a new token per request; the bearer tokens of logout
+    // requests are collected in `logouts`
+    async fn spawn_login_server(logouts: Arc<Mutex<Vec<String>>>) -> String {
+        let logins = AtomicUsize::new(0);
+        spawn_graphql_server(move |request| {
+            if request.body.contains("logout") {
+                let token = request.header("authorization").and_then(|value| value.strip_prefix("Bearer ")).unwrap_or_default();
+                logouts.lock().unwrap().push(token.to_string());
+                return MockResponse::json(serde_json::json!({ "data": { "logout": true } }));
+            }
+            let token = format!("token-{}", logins.fetch_add(1, Ordering::SeqCst) + 1);
+            MockResponse::json(serde_json::json!({ "data": { "login": { "token": token, "expires": "2030-01-01T00:00:00Z", "user": null, "error": { "code": "0", "description": "Success" } } } }))
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn test_sessions_left_by_a_crash_are_logged_out_on_restart() {
+        let logouts = Arc::new(Mutex::new(Vec::new()));
+        let url = spawn_login_server(logouts.clone()).await;
+        let dir = std::env::temp_dir().join(format!("session_recovery_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("sessions.json");
+
+        // The server authenticates a client and crashes without closing the session
+        let (authenticated_tx, authenticated_rx) = tokio::sync::oneshot::channel();
+        let crashed_server = tokio::spawn({
+            let (url, path) = (url.clone(), path.clone());
+            async move {
+                let session_manager = SessionManager::new(url).with_session_recovery_file(Some(Arc::new(SessionRecoveryFile::new(path))));
+                let session = session_manager.authenticate("operator", "secret").await.unwrap();
+                authenticated_tx.send(session.token.clone()).unwrap();
+                std::future::pending::<()>().await;
+            }
+        });
+        let token = authenticated_rx.await.unwrap();
+        crashed_server.abort();
+        let _ = crashed_server.await;
+
+        let recovery_file = Arc::new(SessionRecoveryFile::new(&path));
+        let listed = recovery_file.load().unwrap();
+        assert_eq!(listed.len(), 1);
+        assert_eq!((listed[0].token.as_str(), listed[0].username.as_str(), listed[0].graphql_url.as_str()), (token.as_str(), "operator", url.as_str()));
+
+        // The restarted server logs the orphaned session out and empties the file
+        let session_manager = SessionManager::new(url.clone()).with_session_recovery_file(Some(recovery_file.clone()));
+        assert_eq!(session_manager.recover_orphaned_sessions().await, 1);
+        assert_eq!(*logouts.lock().unwrap(), vec![token]);
+        assert!(recovery_file.load().unwrap().is_empty());
+
+        // An unreadable file is reported and replaced, not a reason to fail the startup
+        std::fs::write(&path, "not json").unwrap();
+        assert_eq!(session_manager.recover_orphaned_sessions().await, 0);
+        assert!(recovery_file.load().unwrap().is_empty());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_recovery_file_is_only_readable_by_the_owner() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = std::env::temp_dir().join(format!("session_recovery_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("sessions.json");
+        // Neither an existing world-readable file nor a stale temp file keeps its permissions
+        std::fs::write(&path, "[]").unwrap();
+        std::fs::write(dir.join("sessions.json.tmp"), "").unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644)).unwrap();
+
+        let session = RecoverableSession {
+            token: "secret-token".to_string(),
+            username: "operator".to_string(),
+            expires: "2030-01-01T00:00:00Z".to_string(),
+            graphql_url: "http://localhost:4000/graphql".to_string(),
+        };
+        let recovery_file = SessionRecoveryFile::new(&path);
+        recovery_file.save(std::slice::from_ref(&session)).unwrap();
+        assert_eq!(std::fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+        assert_eq!(recovery_file.load().unwrap(), vec![session]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,10 +1,21 @@
+use crate::query_handler::SqlStateError;
 use crate::tables::*;
 use anyhow::{anyhow, Result};
-use datafusion::sql::sqlparser::ast::{BinaryOperator, Expr, OrderByExpr, Query, Select, SelectItem, SetExpr, Statement, Value, ValueWithSpan};
+use datafusion::sql::sqlparser::ast::{Assignment, AssignmentTarget, BinaryOperator, CloseCursor, CopyLegacyCsvOption, CopyLegacyOption, CopyOption, CopySource, CopyTarget, DataType, Declare, DeclareType, Distinct, Expr, FetchDirection, Function, FunctionArg, FunctionArgExpr, FunctionArguments, GroupByExpr, Ident, JoinConstraint, JoinOperator, OrderByExpr, OrderByKind, Query, Select, SelectItem, SetExpr, SetOperator, SetQuantifier, Statement, TableFactor, UnaryOperator, Value, ValueWithSpan};
+use datafusion::sql::sqlparser::ast::{visit_expressions, visit_expressions_mut, Visit};
 use datafusion::sql::sqlparser::dialect::GenericDialect;
-use datafusion::sql::sqlparser::parser::Parser;
+use datafusion::sql::sqlparser::parser::{Parser, ParserError};
 use tracing::{debug, warn};
 use chrono::{Duration, Local, DateTime};
+use std::ops::ControlFlow;
+
+// OFFSET above which a warning about deep pagination is logged
+const DEEP_PAGINATION_OFFSET: i64 = 1000;
+// Columns a decorrelated LATERAL subquery adds for the join: its correlation keys and row numbers
+const LATERAL_KEY_COLUMN: &str = "__lateral_key_";
+const LATERAL_ROW_COLUMN: &str = "__lateral_row";
+// (inner, outer) column pairs of the `=` conditions that correlate a LATERAL subquery
+type CorrelatedColumns = Vec<(Expr, Expr)>;
 
 pub struct SqlHandler;
 
@@ -20,11 +31,179 @@ impl SqlHandler {
             _ => Err(anyhow!("Expected string value")),
         }
     }
+    /// Output names of the top-level SELECT items cast with `::jsonb`, which DataFusion plans
+    /// as json; a cast column keeps the name of the casted column unless aliased
+    pub fn jsonb_cast_columns(sql: &str) -> Vec<String> {
+        if !sql.to_lowercase().contains("jsonb") {
+            return Vec::new();
+        }
+        let Ok(ast) = Parser::parse_sql(&GenericDialect {}, sql) else {
+            return Vec::new();
+        };
+        let Some(Statement::Query(query)) = ast.first() else {
+            return Vec::new();
+        };
+        let SetExpr::Select(select) = query.body.as_ref() else {
+            return Vec::new();
+        };
+
+        let is_jsonb_cast = |expr: &Expr| matches!(expr, Expr::Cast { data_type: DataType::JSONB, .. });
+        select
+            .projection
+            .iter()
+            .filter_map(|item| match item {
+                SelectItem::ExprWithAlias { expr, alias } if is_jsonb_cast(expr) => Some(alias.value.clone()),
+                SelectItem::UnnamedExpr(Expr::Cast { data_type: DataType::JSONB, expr, .. }) => match expr.as_ref() {
+                    Expr::Identifier(ident) => Some(ident.value.clone()),
+                    Expr::CompoundIdentifier(parts) => parts.last().map(|ident| ident.value.clone()),
+                    _ => None,
+                },
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Trim and case-fold the string literals compared with tag_name, so the GraphQL request and
+    /// the DataFusion WHERE clause use the same names. Returns the rewritten SQL and the number of
+    /// case-folded names, or None if nothing changed.
+    pub fn normalize_tag_names(sql: &str, case_fold: crate::TagNameCaseFold) -> Option<(String, u64)> {
+        let mut statements = Parser::parse_sql(&GenericDialect {}, sql).ok()?;
+        let mut changed = false;
+        let mut case_folds = 0;
+
+        let _ = visit_expressions_mut(&mut statements, |expr| {
+            let literals: Vec<&mut Expr> = match expr {
+                Expr::BinaryOp { left, right, .. } if Self::is_tag_name_column(left) => vec![right.as_mut()],
+                Expr::BinaryOp { left, right, .. } if Self::is_tag_name_column(right) => vec![left.as_mut()],
+                Expr::InList { expr, list, .. } if Self::is_tag_name_column(expr) => list.iter_mut().collect(),
+                Expr::Like { expr, pattern, .. } | Expr::ILike { expr, pattern, .. } if Self::is_tag_name_column(expr) => {
+                    vec![pattern.as_mut()]
+                }
+                _ => Vec::new(),
+            };
+            for literal in literals {
+                if let Expr::Value(ValueWithSpan { value: Value::SingleQuotedString(tag_name), .. }) = literal {
+                    let normalized = normalize_tag_name(tag_name, case_fold);
+                    if normalized != *tag_name {
+                        if normalized != tag_name.trim() {
+                            case_folds += 1;
+                        }
+                        *tag_name = normalized;
+                        changed = true;
+                    }
+                }
+            }
+            ControlFlow::<()>::Continue(())
+        });
+
+        if !changed {
+            return None;
+        }
+        let rewritten = statements.iter().map(ToString::to_string).collect::<Vec<_>>().join("; ");
+        debug!("🔤 Normalized tag names: {}", rewritten);
+        Some((rewritten, case_folds))
+    }
+
+    fn is_tag_name_column(expr: &Expr) -> bool {
+        match expr {
+            Expr::Identifier(ident) => ident.value.eq_ignore_ascii_case("tag_name"),
+            Expr::CompoundIdentifier(parts) => parts.last().is_some_and(|ident| ident.value.eq_ignore_ascii_case("tag_name")),
+            _ => false,
+        }
+    }
+
+    /// WHERE clause of an alarm statistics query that only references the group columns, so it
+    /// can filter the alarms before they are aggregated. Other conditions are applied afterwards.
+    pub fn alarm_statistics_filter(sql: &str) -> Option<String> {
+        let statements = Parser::parse_sql(&GenericDialect {}, sql).ok()?;
+        let Some(Statement::Query(query)) = statements.first() else {
+            return None;
+        };
+        let SetExpr::Select(select) = query.body.as_ref() else {
+            return None;
+        };
+        let selection = select.selection.as_ref()?;
+
+        let only_group_columns = visit_expressions(selection, |expr| {
+            let ident = match expr {
+                Expr::Identifier(ident) => Some(ident),
+                Expr::CompoundIdentifier(parts) => parts.last(),
+                Expr::Subquery(_) | Expr::Exists { .. } | Expr::InSubquery { .. } => return ControlFlow::Break(()),
+                _ => None,
+            };
+            match ident {
+                Some(ident) if !["state", "priority", "area"].contains(&ident.value.to_lowercase().as_str()) => ControlFlow::Break(()),
+                _ => ControlFlow::Continue(()),
+            }
+        });
+        if only_group_columns.is_break() {
+            return None;
+        }
+        // Qualified names like s.state refer to the statistics table, the alarms are unqualified
+        let mut selection = selection.clone();
+        let _ = visit_expressions_mut(&mut selection, |expr| {
+            if let Expr::CompoundIdentifier(parts) = expr {
+                if let Some(ident) = parts.pop() {
+                    *expr = Expr::Identifier(ident);
+                }
+            }
+            ControlFlow::<()>::Continue(())
+        });
+        Some(selection.to_string())
+    }
+
+    /// Split a Simple Query message into its statements at `;`, ignoring semicolons inside
+    /// quoted strings, quoted identifiers and comments. Statements that are empty or only
+    /// comments are dropped.
+    pub fn split_statements(sql: &str) -> Vec<&str> {
+        let bytes = sql.as_bytes();
+        let mut statements = Vec::new();
+        let (mut start, mut has_code) = (0, false);
+        let mut i = 0;
+        while i < bytes.len() {
+            match bytes[i] {
+                b'-' if bytes.get(i + 1) == Some(&b'-') => {
+                    while i + 1 < bytes.len() && bytes[i + 1] != b'\n' {
+                        i += 1;
+                    }
+                }
+                b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                    i += 2;
+                    while i + 1 < bytes.len() && !(bytes[i] == b'*' && bytes[i + 1] == b'/') {
+                        i += 1;
+                    }
+                    i += 1;
+                }
+                b';' => {
+                    if has_code {
+                        statements.push(sql[start..i].trim());
+                    }
+                    (start, has_code) = (i + 1, false);
+                }
+                quote @ (b'\'' | b'"') => {
+                    has_code = true;
+                    // A doubled quote inside the literal is an escaped quote
+                    i += 1;
+                    while i < bytes.len() && !(bytes[i] == quote && bytes.get(i + 1) != Some(&quote)) {
+                        i += if bytes[i] == quote { 2 } else { 1 };
+                    }
+                }
+                byte if !byte.is_ascii_whitespace() => has_code = true,
+                _ => {}
+            }
+            i += 1;
+        }
+        if has_code {
+            statements.push(sql[start.min(sql.len())..].trim());
+        }
+        statements
+    }
+
     pub fn parse_query(sql: &str) -> Result<SqlResult> {
         debug!("Parsing SQL: {}", sql);
 
         let dialect = GenericDialect {};
-        let ast = Parser::parse_sql(&dialect, sql)?;
+        let ast = Parser::parse_sql(&dialect, sql).map_err(|e| Self::syntax_error(sql, e))?;
 
         if ast.len() != 1 {
             return Err(anyhow!("Expected exactly one SQL statement"));
@@ -32,6 +211,9 @@ impl SqlHandler {
 
         let statement = &ast[0];
         match statement {
+            Statement::Query(query) if matches!(&*query.body, SetExpr::SetOperation { .. }) => {
+                Self::parse_union_query(query)
+            }
             Statement::Query(query) => {
                 let query_info = Self::parse_select_query(query)?;
                 Ok(SqlResult::Query(query_info))
@@ -40,8 +222,171 @@ impl SqlHandler {
                 // Handle SET statements by returning a special success indicator
                 Self::handle_set_statement(statement)
             }
-            _ => Err(anyhow!("Only SELECT and SET statements are supported")),
+            Statement::Explain { analyze, statement, .. } => {
+                let sql = statement.to_string();
+                let explained = Self::parse_query(&sql)?;
+                if !matches!(explained, SqlResult::Query(_) | SqlResult::UnionQuery(_)) {
+                    return Err(anyhow!("EXPLAIN is only supported for SELECT statements"));
+                }
+                Ok(SqlResult::ExplainPlan(ExplainInfo { sql, analyze: *analyze, statement: Box::new(explained) }))
+            }
+            Statement::Update { table, assignments, from, selection, returning, .. } => {
+                if from.is_some() || returning.is_some() {
+                    return Err(anyhow!("UPDATE with FROM or RETURNING is not supported"));
+                }
+                let table_name = match &table.relation {
+                    TableFactor::Table { name, .. } if table.joins.is_empty() => name.to_string(),
+                    _ => return Err(anyhow!("UPDATE supports a single table only")),
+                };
+                match VirtualTable::from_name(&table_name) {
+                    Some(VirtualTable::TagValues) => Self::parse_tag_write(assignments, selection.as_ref()),
+                    Some(VirtualTable::ActiveAlarms) => Self::parse_alarm_acknowledgment(assignments, selection.as_ref()),
+                    _ => Err(anyhow!("UPDATE is only supported on tagvalues and activealarms, got {}", table_name)),
+                }
+            }
+            Statement::Copy { source, to, target, options, legacy_options, .. } => {
+                if !*to || *target != CopyTarget::Stdout {
+                    return Err(anyhow!("Only COPY ... TO STDOUT is supported"));
+                }
+                Self::parse_copy_to(source, options, legacy_options)
+            }
+            Statement::Declare { stmts } => Self::parse_declare_cursor(stmts),
+            Statement::Fetch { name, direction, into } => {
+                if into.is_some() {
+                    return Err(anyhow!("FETCH ... INTO is not supported"));
+                }
+                let count = match direction {
+                    FetchDirection::Next => Some(1),
+                    FetchDirection::Count { limit } | FetchDirection::Forward { limit: Some(limit) } => Some(Self::fetch_count(limit)?),
+                    FetchDirection::Forward { limit: None } => Some(1),
+                    FetchDirection::All | FetchDirection::ForwardAll => None,
+                    _ => return Err(anyhow!("Cursors can only fetch forward, {} is not supported", direction)),
+                };
+                Ok(SqlResult::Cursor(CursorCommand::Fetch { name: Self::cursor_name(name), count }))
+            }
+            Statement::Close { cursor } => {
+                let name = match cursor {
+                    CloseCursor::All => None,
+                    CloseCursor::Specific { name } => Some(Self::cursor_name(name)),
+                };
+                Ok(SqlResult::Cursor(CursorCommand::Close { name }))
+            }
+            _ => Err(anyhow!("Only SELECT, SET, UPDATE, COPY TO STDOUT, EXPLAIN and cursor statements are supported")),
+        }
+    }
+
+    /// `DECLARE name [NO SCROLL] CURSOR [WITH HOLD] FOR query`: only SELECT queries, which are
+    /// executed right away
+    fn parse_declare_cursor(stmts: &[Declare]) -> Result<SqlResult> {
+        let [declare] = stmts else {
+            return Err(anyhow!("DECLARE supports a single cursor"));
+        };
+        let (Some(DeclareType::Cursor), [name], Some(query)) = (&declare.declare_type, declare.names.as_slice(), &declare.for_query) else {
+            return Err(anyhow!("Only DECLARE ... CURSOR FOR SELECT is supported"));
+        };
+        if declare.scroll == Some(true) {
+            return Err(anyhow!("SCROLL cursors are not supported, cursors can only fetch forward"));
+        }
+        let query = query.to_string();
+        if !matches!(Self::parse_query(&query)?, SqlResult::Query(_) | SqlResult::UnionQuery(_)) {
+            return Err(anyhow!("Only DECLARE ... CURSOR FOR SELECT is supported"));
+        }
+        Ok(SqlResult::Cursor(CursorCommand::Declare { name: Self::cursor_name(name), query }))
+    }
+
+    // Unquoted names are case-insensitive like in PostgreSQL
+    fn cursor_name(name: &Ident) -> String {
+        match name.quote_style {
+            Some(_) => name.value.clone(),
+            None => name.value.to_lowercase(),
+        }
+    }
+
+    fn fetch_count(limit: &Value) -> Result<usize> {
+        match limit {
+            Value::Number(count, _) => count.parse().map_err(|_| anyhow!("Invalid FETCH count {}", count)),
+            _ => Err(anyhow!("Invalid FETCH count {}", limit)),
+        }
+    }
+
+    /// Parse `COPY table [(columns)] TO STDOUT` or `COPY (SELECT ...) TO STDOUT` with the
+    /// PostgreSQL (`WITH (FORMAT CSV, HEADER)`) or legacy (`WITH CSV HEADER`) options
+    fn parse_copy_to(source: &CopySource, options: &[CopyOption], legacy_options: &[CopyLegacyOption]) -> Result<SqlResult> {
+        let query = match source {
+            CopySource::Table { table_name, columns } if columns.is_empty() => format!("SELECT * FROM {}", table_name),
+            CopySource::Table { table_name, columns } => format!(
+                "SELECT {} FROM {}",
+                columns.iter().map(|column| column.to_string()).collect::<Vec<_>>().join(", "),
+                table_name
+            ),
+            CopySource::Query(query) => query.to_string(),
+        };
+        if !matches!(Self::parse_query(&query)?, SqlResult::Query(_) | SqlResult::UnionQuery(_)) {
+            return Err(anyhow!("COPY is only supported for tables and SELECT queries"));
+        }
+
+        let mut copy_options = CopyOptions::default();
+        let mut delimiter = None;
+        for option in options {
+            match option {
+                CopyOption::Format(format) => {
+                    copy_options.format = match format.value.to_lowercase().as_str() {
+                        "text" => CopyFormat::Text,
+                        "csv" => CopyFormat::Csv,
+                        other => return Err(anyhow!("COPY format \"{}\" is not supported", other)),
+                    }
+                }
+                CopyOption::Delimiter(c) => delimiter = Some(*c),
+                CopyOption::Null(null) => copy_options.null = null.clone(),
+                CopyOption::Header(header) => copy_options.header = *header,
+                CopyOption::Quote(c) => copy_options.quote = *c,
+                other => return Err(anyhow!("COPY option {} is not supported", other)),
+            }
         }
+        for option in legacy_options {
+            match option {
+                CopyLegacyOption::Delimiter(c) => delimiter = Some(*c),
+                CopyLegacyOption::Null(null) => copy_options.null = null.clone(),
+                CopyLegacyOption::Csv(csv_options) => {
+                    copy_options.format = CopyFormat::Csv;
+                    for csv_option in csv_options {
+                        match csv_option {
+                            CopyLegacyCsvOption::Header => copy_options.header = true,
+                            CopyLegacyCsvOption::Quote(c) => copy_options.quote = *c,
+                            other => return Err(anyhow!("COPY option {} is not supported", other)),
+                        }
+                    }
+                }
+                CopyLegacyOption::Binary => return Err(anyhow!("COPY format \"binary\" is not supported")),
+            }
+        }
+        copy_options.delimiter = delimiter.unwrap_or(match copy_options.format {
+            CopyFormat::Text => '\t',
+            CopyFormat::Csv => ',',
+        });
+
+        debug!("✅ COPY TO STDOUT of '{}' with {:?}", query, copy_options);
+        Ok(SqlResult::CopyTo(CopyToInfo { query, options: copy_options }))
+    }
+
+    /// SQLSTATE 42601 with the position of the offending token, so clients can point at it
+    fn syntax_error(sql: &str, error: ParserError) -> anyhow::Error {
+        let message = error.to_string();
+        // The parser appends the location as " at Line: 2, Column: 6" (both 1-based),
+        // errors at the end of the input have none
+        let (message, position) = match message.rsplit_once(" at Line: ") {
+            Some((text, location)) => {
+                let position = location.split_once(", Column: ").and_then(|(line, column)| {
+                    let (line, column) = (line.parse::<usize>().ok()?, column.parse::<usize>().ok()?);
+                    let preceding: usize = sql.split('\n').take(line.checked_sub(1)?).map(|l| l.chars().count() + 1).sum();
+                    Some(preceding + column)
+                });
+                (text.to_string(), position)
+            }
+            None => (message, None),
+        };
+        let position = position.unwrap_or_else(|| sql.trim_end().chars().count() + 1);
+        SqlStateError::new("42601", message).with_position(position).into()
     }
 
     fn parse_select_query(query: &Query) -> Result<QueryInfo> {
@@ -51,13 +396,52 @@ impl SqlHandler {
                 if select.from.is_empty() {
                     return Self::handle_from_less_query(select, query);
                 }
+                // UNNEST only expands its array arguments, no WinCC table is read
+                if matches!(select.from[0].relation, TableFactor::UNNEST { .. }) && select.from[0].joins.is_empty() {
+                    return Self::handle_from_less_query(select, query);
+                }
+                // Catalog queries keep their subqueries, DataFusion runs them on the catalog batches
+                let is_catalog_query = matches!(select.from[0].relation, TableFactor::Table { .. })
+                    && Self::extract_table(select).is_ok_and(|table| table.is_catalog());
+                if !is_catalog_query && Self::has_subquery_in_from(select) {
+                    return Self::parse_subquery_select(select, query);
+                }
                 
                 let table = Self::extract_table(select)?;
                 let (columns, column_mappings) = Self::extract_columns(select, &table)?;
-                let filters = Self::extract_filters(select, &table)?;
-                let limit = query.limit.as_ref().and_then(|l| Self::extract_limit(l));
-                // OrderBy structure changed in newer sqlparser - skip for now
-                let order_by = None;
+                // The arguments of the table function come first, so they win over WHERE conditions
+                let mut filters = match table {
+                    VirtualTable::LoggedTagValuesResampled => Self::extract_resample_arguments(select)?,
+                    _ => Vec::new(),
+                };
+                // Catalog queries often join and use qualified columns; DataFusion evaluates
+                // their WHERE clause on the in-memory batches, so no filters are extracted
+                if !table.is_catalog() {
+                    filters.extend(Self::extract_filters(select, &table)?);
+                }
+                let limit = query.limit.as_ref().and_then(Self::extract_limit);
+                let offset = query.offset.as_ref().and_then(|offset| Self::extract_limit(&offset.value));
+                if let Some(offset) = offset.filter(|offset| *offset > DEEP_PAGINATION_OFFSET) {
+                    warn!(
+                        "⚠️  OFFSET {} fetches all skipped rows from GraphQL, prefer a timestamp range for deep pagination",
+                        offset
+                    );
+                }
+                let mut order_by = Self::extract_order_by(query);
+                if let Some(Distinct::On(exprs)) = &select.distinct {
+                    // DISTINCT ON keeps the first row of every value of its columns, which row that is
+                    // decides the ORDER BY after them, e.g. `timestamp DESC` for the latest value per tag
+                    let distinct_columns: Vec<String> = exprs.iter().map(Self::order_by_column).collect();
+                    let partition_len = order_by.iter().take_while(|order_by| distinct_columns.contains(&order_by.column)).count();
+                    order_by.drain(..partition_len);
+                }
+                // Aggregation and DISTINCT run in DataFusion on the full batch of the table
+                let has_group_by = select.distinct.is_some()
+                    || match &select.group_by {
+                        GroupByExpr::All(_) => true,
+                        GroupByExpr::Expressions(exprs, _) => !exprs.is_empty(),
+                    };
+                let has_window_function = Self::has_window_function(select);
                 
                 let query_info = QueryInfo {
                     table,
@@ -65,7 +449,11 @@ impl SqlHandler {
                     column_mappings,
                     filters,
                     limit,
+                    offset,
                     order_by,
+                    has_group_by,
+                    has_window_function,
+                    derived_tables: Vec::new(),
                 };
 
                 Self::validate_query(&query_info)?;
@@ -75,6 +463,459 @@ impl SqlHandler {
         }
     }
 
+    fn parse_union_query(query: &Query) -> Result<SqlResult> {
+        let mut leg_sqls = Vec::new();
+        Self::collect_union_all_legs(&query.body, &mut leg_sqls)?;
+
+        let legs = leg_sqls
+            .into_iter()
+            .map(|sql| match Self::parse_query(&sql)? {
+                SqlResult::Query(query_info) => Ok(UnionLeg { sql, query_info }),
+                _ => Err(anyhow!("Unsupported UNION ALL leg: {}", sql)),
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        // ORDER BY / LIMIT / OFFSET after the last leg apply to the combined result
+        let mut outer_clauses = Vec::new();
+        if let Some(order_by) = &query.order_by {
+            outer_clauses.push(order_by.to_string());
+        }
+        if let Some(limit) = &query.limit {
+            outer_clauses.push(format!("LIMIT {}", limit));
+        }
+        if let Some(offset) = &query.offset {
+            outer_clauses.push(offset.to_string());
+        }
+        let outer_sql = (!outer_clauses.is_empty())
+            .then(|| format!("SELECT * FROM {} {}", UNION_RESULT_TABLE, outer_clauses.join(" ")));
+
+        debug!("🔗 UNION ALL query with {} legs", legs.len());
+        Ok(SqlResult::UnionQuery(UnionQueryInfo { legs, outer_sql }))
+    }
+
+    fn collect_union_all_legs(body: &SetExpr, legs: &mut Vec<String>) -> Result<()> {
+        match body {
+            SetExpr::SetOperation { op: SetOperator::Union, set_quantifier: SetQuantifier::All, left, right } => {
+                Self::collect_union_all_legs(left, legs)?;
+                Self::collect_union_all_legs(right, legs)
+            }
+            SetExpr::SetOperation { op, set_quantifier, .. } => {
+                Err(anyhow!("Only UNION ALL is supported, got {} {}", op, set_quantifier))
+            }
+            SetExpr::Query(query) => {
+                legs.push(query.to_string());
+                Ok(())
+            }
+            other => {
+                legs.push(other.to_string());
+                Ok(())
+            }
+        }
+    }
+
+    /// True if FROM reads a subquery, as main relation or joined like `JOIN LATERAL (SELECT ...) m`
+    fn has_subquery_in_from(select: &Select) -> bool {
+        select.from.iter().any(|table| {
+            std::iter::once(&table.relation)
+                .chain(table.joins.iter().map(|join| &join.relation))
+                .any(|relation| matches!(relation, TableFactor::Derived { .. }))
+        })
+    }
+
+    /// SELECT with subqueries in FROM, e.g. `tagvalues t JOIN LATERAL (SELECT ... FROM taglist ...) m`.
+    /// The main relation is fetched like a plain query, every joined subquery reads its table in a
+    /// separate fetch. DataFusion evaluates the joins and the WHERE clause on the fetched batches.
+    fn parse_subquery_select(select: &Select, query: &Query) -> Result<QueryInfo> {
+        if select.from.len() != 1 {
+            return Err(anyhow!("Expected exactly one table in FROM clause"));
+        }
+        let from = &select.from[0];
+        let mut query_info = match &from.relation {
+            TableFactor::Derived { subquery, .. } => {
+                let mut query_info = Self::parse_select_query(subquery)?;
+                query_info.columns = Self::projection_names(select, &query_info.columns);
+                query_info
+            }
+            relation => {
+                let table = Self::extract_table(select)?;
+                let mut filters = match table {
+                    VirtualTable::LoggedTagValuesResampled => Self::extract_resample_arguments(select)?,
+                    _ => Vec::new(),
+                };
+                // Only the conditions on the main relation select what is fetched from GraphQL
+                let mut conjuncts = Vec::new();
+                if let Some(selection) = &select.selection {
+                    Self::split_conjuncts(selection.clone(), &mut conjuncts);
+                }
+                if let Some(selection) = Self::relation_selection(conjuncts, &Self::relation_qualifiers(relation), &table) {
+                    Self::extract_filters_from_expr(&selection, &table, &mut filters)?;
+                }
+                let columns: Vec<String> = table.get_column_names().iter().map(|column| column.to_string()).collect();
+                let query_info = QueryInfo {
+                    columns: Self::projection_names(select, &columns),
+                    table,
+                    column_mappings: std::collections::HashMap::new(),
+                    filters,
+                    // The joins may drop rows, LIMIT and OFFSET apply to the joined result only
+                    limit: None,
+                    offset: None,
+                    order_by: Self::extract_order_by(query),
+                    has_group_by: false,
+                    has_window_function: false,
+                    derived_tables: Vec::new(),
+                };
+                Self::validate_query(&query_info)?;
+                query_info
+            }
+        };
+
+        let correlations = Self::decorrelate_lateral_joins(&mut select.clone())?;
+        for (join, keys) in from.joins.iter().zip(correlations) {
+            if let TableFactor::Derived { subquery, .. } = &join.relation {
+                let derived = Self::derived_table(subquery, &keys, select.selection.as_ref())?;
+                debug!("🔗 Subquery in FROM reads {} ({} correlated columns)", derived.query_info.table, keys.len());
+                query_info.derived_tables.push(derived);
+            }
+        }
+        Ok(query_info)
+    }
+
+    /// The WinCC table read by a subquery in FROM, filtered by the subquery's own conditions and by
+    /// the outer conditions on its correlated columns: with `tag_name = t.tag_name`, the outer
+    /// `t.tag_name LIKE '%Motor%'` also narrows the subquery to `tag_name LIKE '%Motor%'`
+    fn derived_table(subquery: &Query, keys: &[(Expr, Expr)], outer_selection: Option<&Expr>) -> Result<DerivedTable> {
+        let SetExpr::Select(inner) = subquery.body.as_ref() else {
+            return Err(anyhow!("Subqueries in FROM must be a simple SELECT"));
+        };
+        let relation = match inner.from.as_slice() {
+            [table] if table.joins.is_empty() && matches!(table.relation, TableFactor::Table { .. }) => &table.relation,
+            _ => return Err(anyhow!("Subqueries in FROM must read a single table")),
+        };
+        let table = Self::extract_table(inner)?;
+
+        let mut conjuncts = Vec::new();
+        if let Some(selection) = &inner.selection {
+            Self::split_conjuncts(selection.clone(), &mut conjuncts);
+        }
+        let mut outer_conjuncts = Vec::new();
+        if let Some(selection) = outer_selection {
+            Self::split_conjuncts(selection.clone(), &mut outer_conjuncts);
+        }
+        for (inner_column, outer_column) in keys {
+            for conjunct in outer_conjuncts.iter().filter(|conjunct| Self::only_references(conjunct, outer_column)) {
+                let mut pushed = conjunct.clone();
+                let _ = visit_expressions_mut(&mut pushed, |expr| {
+                    if expr.to_string() == outer_column.to_string() {
+                        *expr = inner_column.clone();
+                    }
+                    ControlFlow::<()>::Continue(())
+                });
+                conjuncts.push(pushed);
+            }
+        }
+
+        // Correlated conditions reference the outer relations and are left to the join
+        let selection = Self::relation_selection(conjuncts, &Self::relation_qualifiers(relation), &table);
+        let mut relation = relation.clone();
+        if let TableFactor::Table { alias, .. } = &mut relation {
+            *alias = None;
+        }
+        let sql = match selection {
+            Some(selection) => format!("SELECT * FROM {} WHERE {}", relation, selection),
+            None => format!("SELECT * FROM {}", relation),
+        };
+        match Self::parse_query(&sql)? {
+            SqlResult::Query(query_info) => Ok(DerivedTable { sql, query_info }),
+            _ => Err(anyhow!("Unsupported subquery in FROM: {}", subquery)),
+        }
+    }
+
+    /// The query with its LATERAL subqueries decorrelated, see `decorrelate_lateral_joins`
+    pub fn rewrite_lateral_joins(sql: &str) -> Result<String> {
+        let mut statements = Parser::parse_sql(&GenericDialect {}, sql)?;
+        if let Some(Statement::Query(query)) = statements.first_mut() {
+            if let SetExpr::Select(select) = query.body.as_mut() {
+                Self::decorrelate_lateral_joins(select)?;
+            }
+        }
+        Ok(statements.iter().map(ToString::to_string).collect::<Vec<_>>().join("; "))
+    }
+
+    /// DataFusion cannot plan correlated subqueries in FROM, so
+    /// `JOIN LATERAL (SELECT display_name FROM taglist WHERE tag_name = t.tag_name LIMIT 1) m ON true`
+    /// becomes a join on the correlation columns, which the subquery returns as `__lateral_key_<i>`.
+    /// A LIMIT is applied per outer row by numbering the rows of every key:
+    /// `JOIN (SELECT display_name, tag_name AS __lateral_key_0, ROW_NUMBER() OVER (PARTITION BY tag_name)
+    /// AS __lateral_row FROM taglist) m ON m.__lateral_key_0 = t.tag_name AND m.__lateral_row <= 1`.
+    /// Returns the (inner, outer) column pairs of every join.
+    fn decorrelate_lateral_joins(select: &mut Select) -> Result<Vec<CorrelatedColumns>> {
+        let Some(from) = select.from.first_mut() else {
+            return Ok(Vec::new());
+        };
+        let mut outer_qualifiers = Self::relation_qualifiers(&from.relation);
+        let mut correlations = Vec::new();
+        for join in &mut from.joins {
+            let keys = match &mut join.relation {
+                TableFactor::Derived { lateral, subquery, alias } if *lateral => {
+                    let alias = alias
+                        .as_ref()
+                        .map(|alias| alias.name.clone())
+                        .ok_or_else(|| anyhow!("subquery in FROM must have an alias"))?;
+                    let (keys, mut conditions) = Self::decorrelate_lateral_subquery(subquery, &alias, &outer_qualifiers)?;
+                    if !keys.is_empty() {
+                        let is_left_join = matches!(
+                            join.join_operator,
+                            JoinOperator::Left(_) | JoinOperator::LeftOuter(_) | JoinOperator::OuterApply
+                        );
+                        let constraint = match &mut join.join_operator {
+                            JoinOperator::Join(constraint)
+                            | JoinOperator::Inner(constraint)
+                            | JoinOperator::Left(constraint)
+                            | JoinOperator::LeftOuter(constraint) => std::mem::replace(constraint, JoinConstraint::None),
+                            JoinOperator::CrossJoin | JoinOperator::CrossApply | JoinOperator::OuterApply => JoinConstraint::None,
+                            _ => return Err(Self::lateral_not_supported("only INNER, LEFT and CROSS joins are supported with LATERAL subqueries")),
+                        };
+                        match constraint {
+                            JoinConstraint::None | JoinConstraint::On(Expr::Value(ValueWithSpan { value: Value::Boolean(true), .. })) => {}
+                            JoinConstraint::On(condition) => conditions.push(condition),
+                            _ => return Err(Self::lateral_not_supported("USING and NATURAL are not supported with LATERAL subqueries")),
+                        }
+                        let on = JoinConstraint::On(Self::join_conjuncts(conditions).unwrap_or_else(|| Expr::value(Value::Boolean(true))));
+                        join.join_operator = if is_left_join { JoinOperator::Left(on) } else { JoinOperator::Inner(on) };
+                    }
+                    *lateral = false;
+                    keys
+                }
+                _ => Vec::new(),
+            };
+            outer_qualifiers.extend(Self::relation_qualifiers(&join.relation));
+            correlations.push(keys);
+        }
+        Ok(correlations)
+    }
+
+    /// Move the `inner = outer.column` conditions of a LATERAL subquery into its SELECT list and
+    /// replace its LIMIT by row numbers per key. Returns the key pairs and the join conditions.
+    fn decorrelate_lateral_subquery(subquery: &mut Query, alias: &Ident, outer_qualifiers: &[String]) -> Result<(CorrelatedColumns, Vec<Expr>)> {
+        let SetExpr::Select(inner) = subquery.body.as_mut() else {
+            return Err(Self::lateral_not_supported("LATERAL subqueries must be a simple SELECT"));
+        };
+        // Relations of the subquery hide outer relations of the same name
+        let inner_qualifiers: Vec<String> = inner
+            .from
+            .iter()
+            .flat_map(|table| std::iter::once(&table.relation).chain(table.joins.iter().map(|join| &join.relation)))
+            .flat_map(Self::relation_qualifiers)
+            .collect();
+        let outer: Vec<String> = outer_qualifiers.iter().filter(|qualifier| !inner_qualifiers.contains(qualifier)).cloned().collect();
+
+        let mut conjuncts = Vec::new();
+        if let Some(selection) = inner.selection.take() {
+            Self::split_conjuncts(selection, &mut conjuncts);
+        }
+        let mut keys = Vec::new();
+        let mut uncorrelated = Vec::new();
+        for conjunct in conjuncts {
+            if !Self::references_qualifier(&conjunct, &outer) {
+                uncorrelated.push(conjunct);
+                continue;
+            }
+            match conjunct {
+                Expr::BinaryOp { left, op: BinaryOperator::Eq, right }
+                    if Self::is_qualified_by(&right, &outer) && !Self::references_qualifier(left.as_ref(), &outer) =>
+                {
+                    keys.push((*left, *right))
+                }
+                Expr::BinaryOp { left, op: BinaryOperator::Eq, right }
+                    if Self::is_qualified_by(&left, &outer) && !Self::references_qualifier(right.as_ref(), &outer) =>
+                {
+                    keys.push((*right, *left))
+                }
+                other => {
+                    return Err(Self::lateral_not_supported(format!(
+                        "LATERAL subqueries can only reference outer columns in equality conditions, got {}",
+                        other
+                    )))
+                }
+            }
+        }
+        inner.selection = Self::join_conjuncts(uncorrelated);
+        if keys.is_empty() {
+            return Ok((keys, Vec::new()));
+        }
+
+        for (i, (inner_column, _)) in keys.iter().enumerate() {
+            inner.projection.push(SelectItem::ExprWithAlias {
+                expr: inner_column.clone(),
+                alias: Ident::new(format!("{}{}", LATERAL_KEY_COLUMN, i)),
+            });
+        }
+        // Aggregates are computed per outer row, that is per key
+        let has_aggregate = Self::has_aggregate_function(inner);
+        if let GroupByExpr::Expressions(exprs, _) = &mut inner.group_by {
+            if !exprs.is_empty() || has_aggregate {
+                exprs.extend(keys.iter().map(|(inner_column, _)| inner_column.clone()));
+            }
+        }
+
+        let mut conditions: Vec<Expr> = (0..keys.len())
+            .map(|i| format!("{}.{}{} = {}", alias, LATERAL_KEY_COLUMN, i, keys[i].1))
+            .map(|condition| Self::parse_expr_fragment(&condition))
+            .collect::<Result<_>>()?;
+        let limit = subquery.limit.take();
+        let offset = subquery.offset.take();
+        if limit.is_some() || offset.is_some() {
+            let partition_by = keys.iter().map(|(inner_column, _)| inner_column.to_string()).collect::<Vec<_>>().join(", ");
+            let order_by = subquery.order_by.take().map(|order_by| format!(" {}", order_by)).unwrap_or_default();
+            let row_number = format!("ROW_NUMBER() OVER (PARTITION BY {}{}) AS {}", partition_by, order_by, LATERAL_ROW_COLUMN);
+            let dialect = GenericDialect {};
+            inner.projection.push(Parser::new(&dialect).try_with_sql(&row_number)?.parse_select_item()?);
+
+            let constant = |expr: &Expr| {
+                Self::extract_limit(expr).ok_or_else(|| Self::lateral_not_supported("LIMIT and OFFSET of LATERAL subqueries must be constants"))
+            };
+            let offset = offset.map(|offset| constant(&offset.value)).transpose()?.unwrap_or(0);
+            if offset > 0 {
+                conditions.push(Self::parse_expr_fragment(&format!("{}.{} > {}", alias, LATERAL_ROW_COLUMN, offset))?);
+            }
+            if let Some(limit) = limit {
+                let limit = constant(&limit)?;
+                conditions.push(Self::parse_expr_fragment(&format!("{}.{} <= {}", alias, LATERAL_ROW_COLUMN, offset + limit))?);
+            }
+        }
+
+        if Self::references_qualifier(&*subquery, &outer) {
+            return Err(Self::lateral_not_supported("LATERAL subqueries can only reference outer columns in WHERE conditions"));
+        }
+        Ok((keys, conditions))
+    }
+
+    fn lateral_not_supported(message: impl Into<String>) -> anyhow::Error {
+        SqlStateError::new("0A000", message).into()
+    }
+
+    fn parse_expr_fragment(sql: &str) -> Result<Expr> {
+        let dialect = GenericDialect {};
+        Ok(Parser::new(&dialect).try_with_sql(sql)?.parse_expr()?)
+    }
+
+    /// True if the SELECT list calls an aggregate function like `MAX(x)` outside a window
+    fn has_aggregate_function(select: &Select) -> bool {
+        const AGGREGATES: &[&str] = &["count", "sum", "avg", "min", "max", "array_agg", "string_agg", "bool_and", "bool_or", "stddev", "variance", "median"];
+        visit_expressions(&select.projection, |expr| match expr {
+            Expr::Function(Function { name, over: None, .. }) if AGGREGATES.contains(&name.to_string().to_lowercase().as_str()) => {
+                ControlFlow::Break(())
+            }
+            _ => ControlFlow::Continue(()),
+        })
+        .is_break()
+    }
+
+    /// Names a relation is referenced by in qualified columns: its alias, or else its table name
+    fn relation_qualifiers(relation: &TableFactor) -> Vec<String> {
+        match relation {
+            TableFactor::Table { alias: Some(alias), .. }
+            | TableFactor::Derived { alias: Some(alias), .. }
+            | TableFactor::UNNEST { alias: Some(alias), .. } => vec![alias.name.value.to_lowercase()],
+            TableFactor::Table { name, .. } => name.0.last().and_then(|part| part.as_ident()).map(|ident| ident.value.to_lowercase()).into_iter().collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// True for a column like `t.tag_name` whose qualifier is one of `qualifiers`
+    fn is_qualified_by(expr: &Expr, qualifiers: &[String]) -> bool {
+        match expr {
+            Expr::CompoundIdentifier(parts) if parts.len() >= 2 => qualifiers.contains(&parts[parts.len() - 2].value.to_lowercase()),
+            _ => false,
+        }
+    }
+
+    fn references_qualifier<V: Visit>(node: &V, qualifiers: &[String]) -> bool {
+        visit_expressions(node, |expr| match Self::is_qualified_by(expr, qualifiers) {
+            true => ControlFlow::Break(()),
+            false => ControlFlow::Continue(()),
+        })
+        .is_break()
+    }
+
+    /// True if every column of the condition is `column`
+    fn only_references(condition: &Expr, column: &Expr) -> bool {
+        let column = column.to_string();
+        visit_expressions(condition, |expr| match expr {
+            Expr::Identifier(_) | Expr::CompoundIdentifier(_) if expr.to_string() != column => ControlFlow::Break(()),
+            _ => ControlFlow::Continue(()),
+        })
+        .is_continue()
+    }
+
+    /// The conditions that only reference columns of one relation, without its qualifier
+    /// (`t.tag_name` becomes `tag_name`), so they can be extracted as filters of its table
+    fn relation_selection(conjuncts: Vec<Expr>, qualifiers: &[String], table: &VirtualTable) -> Option<Expr> {
+        let own_conjuncts = conjuncts
+            .into_iter()
+            .filter(|conjunct| {
+                visit_expressions(conjunct, |expr| match expr {
+                    Expr::Identifier(ident) if !table.has_column(&ident.value.to_lowercase()) => ControlFlow::Break(()),
+                    Expr::CompoundIdentifier(parts) if parts.len() != 2 || !Self::is_qualified_by(expr, qualifiers) => ControlFlow::Break(()),
+                    // Subqueries have their own scope
+                    Expr::Subquery(_) | Expr::InSubquery { .. } | Expr::Exists { .. } => ControlFlow::Break(()),
+                    _ => ControlFlow::Continue(()),
+                })
+                .is_continue()
+            })
+            .map(|mut conjunct| {
+                let _ = visit_expressions_mut(&mut conjunct, |expr| {
+                    if let Expr::CompoundIdentifier(parts) = expr {
+                        if let Some(column) = parts.pop() {
+                            *expr = Expr::Identifier(column);
+                        }
+                    }
+                    ControlFlow::<()>::Continue(())
+                });
+                conjunct
+            })
+            .collect();
+        Self::join_conjuncts(own_conjuncts)
+    }
+
+    fn split_conjuncts(expr: Expr, conjuncts: &mut Vec<Expr>) {
+        match expr {
+            Expr::BinaryOp { left, op: BinaryOperator::And, right } => {
+                Self::split_conjuncts(*left, conjuncts);
+                Self::split_conjuncts(*right, conjuncts);
+            }
+            Expr::Nested(inner) if matches!(*inner, Expr::BinaryOp { op: BinaryOperator::And, .. }) => {
+                Self::split_conjuncts(*inner, conjuncts)
+            }
+            other => conjuncts.push(other),
+        }
+    }
+
+    fn join_conjuncts(conjuncts: Vec<Expr>) -> Option<Expr> {
+        conjuncts
+            .into_iter()
+            .map(|conjunct| match conjunct {
+                Expr::BinaryOp { op: BinaryOperator::Or, .. } => Expr::Nested(Box::new(conjunct)),
+                conjunct => conjunct,
+            })
+            .reduce(|left, right| Expr::BinaryOp { left: Box::new(left), op: BinaryOperator::And, right: Box::new(right) })
+    }
+
+    /// Output names of the SELECT list, `*` stands for `columns`
+    fn projection_names(select: &Select, columns: &[String]) -> Vec<String> {
+        select
+            .projection
+            .iter()
+            .flat_map(|item| match item {
+                SelectItem::Wildcard(_) | SelectItem::QualifiedWildcard(..) => columns.to_vec(),
+                SelectItem::ExprWithAlias { alias, .. } => vec![alias.value.clone()],
+                SelectItem::UnnamedExpr(Expr::Identifier(ident)) => vec![ident.value.clone()],
+                SelectItem::UnnamedExpr(Expr::CompoundIdentifier(parts)) => parts.iter().last().map(|part| part.value.clone()).into_iter().collect(),
+                SelectItem::UnnamedExpr(expr) => vec![expr.to_string()],
+            })
+            .collect()
+    }
+
     fn handle_from_less_query(select: &Select, query: &Query) -> Result<QueryInfo> {
         // For FROM-less queries like SELECT 1, SELECT VERSION(), etc.
         // Extract column names from the SELECT expressions
@@ -90,9 +931,15 @@ impl SqlHandler {
                 SelectItem::ExprWithAlias { expr: _, alias } => {
                     columns.push(alias.value.clone());
                 }
-                SelectItem::Wildcard(_) => {
-                    return Err(anyhow!("SELECT * is not supported in FROM-less queries"));
-                }
+                SelectItem::Wildcard(_) => match &select.from.first().map(|table| &table.relation) {
+                    Some(TableFactor::UNNEST { alias, .. }) => match alias {
+                        Some(alias) if !alias.columns.is_empty() => {
+                            columns.extend(alias.columns.iter().map(|column| column.name.value.clone()))
+                        }
+                        _ => columns.push("unnest".to_string()),
+                    },
+                    _ => return Err(anyhow!("SELECT * is not supported in FROM-less queries")),
+                },
                 SelectItem::QualifiedWildcard(_, _) => {
                     return Err(anyhow!("Qualified wildcards are not supported in FROM-less queries"));
                 }
@@ -101,8 +948,8 @@ impl SqlHandler {
         
         // FROM-less queries don't have filters, ordering, or limits in our simple implementation
         let filters = vec![];
-        let limit = query.limit.as_ref().and_then(|l| Self::extract_limit(l));
-        let order_by = None; // FROM-less queries typically don't need ordering
+        let limit = query.limit.as_ref().and_then(Self::extract_limit);
+        let order_by = Vec::new(); // FROM-less queries typically don't need ordering
         
         Ok(QueryInfo {
             table: VirtualTable::FromLessQuery,
@@ -110,7 +957,11 @@ impl SqlHandler {
             column_mappings,
             filters,
             limit,
+            offset: None,
             order_by,
+            has_group_by: false,
+            has_window_function: false,
+            derived_tables: Vec::new(),
         })
     }
     
@@ -135,8 +986,8 @@ impl SqlHandler {
             return Err(anyhow!("Expected exactly one table in FROM clause"));
         }
 
-        let table_name = match &select.from[0].relation {
-            datafusion::sql::sqlparser::ast::TableFactor::Table { name, .. } => {
+        let (table_name, is_function_call) = match &select.from[0].relation {
+            datafusion::sql::sqlparser::ast::TableFactor::Table { name, args, .. } => {
                 // Extract the actual identifier value without quotes
                 // ObjectNamePart has a to_string() that includes quotes, but we need the raw value
                 let parts: Vec<String> = name.0.iter().map(|part| {
@@ -149,19 +1000,96 @@ impl SqlHandler {
                         part_str
                     }
                 }).collect();
-                parts.join(".")
+                (parts.join("."), args.is_some())
             }
             _ => return Err(anyhow!("Only simple table names are supported")),
         };
 
-        VirtualTable::from_name(&table_name)
-            .ok_or_else(|| anyhow!("Unknown table: {}", table_name))
+        let table = VirtualTable::from_name(&table_name)
+            .ok_or_else(|| anyhow!("Unknown table: {}", table_name))?;
+        match (&table, is_function_call) {
+            (VirtualTable::LoggedTagValuesResampled, false) => Err(anyhow!(
+                "loggedtagvalues_resampled is a table function, use loggedtagvalues_resampled(tag_name, start_time, end_time, interval_seconds, method)"
+            )),
+            (VirtualTable::LoggedTagValuesResampled, true) | (_, false) => Ok(table),
+            (_, true) => Err(anyhow!("{} is not a table function", table_name)),
+        }
+    }
+
+    /// Arguments of `loggedtagvalues_resampled('Motor:Speed:PV', '2024-01-01', '2024-01-02', 60, 'avg')`
+    /// as filters: the tag name and time range select the logged values like a WHERE clause on
+    /// loggedtagvalues, interval_seconds and method are read by the resampling.
+    fn extract_resample_arguments(select: &Select) -> Result<Vec<ColumnFilter>> {
+        let args = match &select.from[0].relation {
+            TableFactor::Table { args: Some(args), .. } => &args.args,
+            _ => return Err(anyhow!("loggedtagvalues_resampled requires arguments")),
+        };
+        let args = args
+            .iter()
+            .map(|arg| match arg {
+                FunctionArg::Unnamed(FunctionArgExpr::Expr(expr)) => Ok(expr),
+                _ => Err(anyhow!("Unsupported loggedtagvalues_resampled argument: {}", arg)),
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let [tag_name, start_time, end_time, interval_seconds, method] = args.as_slice() else {
+            return Err(anyhow!(
+                "loggedtagvalues_resampled expects 5 arguments (tag_name, start_time, end_time, interval_seconds, method), got {}",
+                args.len()
+            ));
+        };
+
+        let logged_values = VirtualTable::LoggedTagValues;
+        let tag_name = Self::extract_filter_value_for_column(tag_name, "tag_name", &logged_values)?;
+        if tag_name.as_string().is_none_or(str::is_empty) {
+            return Err(anyhow!("loggedtagvalues_resampled expects the tag name as first argument"));
+        }
+        let timestamp = |expr: &Expr| match Self::extract_filter_value_for_column(expr, "timestamp", &logged_values)? {
+            FilterValue::Timestamp(timestamp) => Ok(FilterValue::Timestamp(timestamp)),
+            other => Err(anyhow!("Expected a timestamp for loggedtagvalues_resampled, got {:?}", other)),
+        };
+        let interval_seconds = match Self::extract_filter_value_for_column(interval_seconds, "interval_seconds", &logged_values)? {
+            FilterValue::Integer(seconds) if seconds > 0 => seconds,
+            other => return Err(anyhow!("interval_seconds must be a positive integer, got {:?}", other)),
+        };
+        let method = Self::extract_string_value(method)?.to_lowercase();
+        if !crate::query_handler::resample_handler::RESAMPLE_METHODS.contains(&method.as_str()) {
+            return Err(anyhow!(
+                "Unknown resample method '{}', expected one of {}",
+                method,
+                crate::query_handler::resample_handler::RESAMPLE_METHODS.join(", ")
+            ));
+        }
+
+        let filter = |column: &str, operator: FilterOperator, value: FilterValue| ColumnFilter { column: column.to_string(), operator, value };
+        Ok(vec![
+            filter("tag_name", FilterOperator::Equal, tag_name),
+            filter("timestamp", FilterOperator::GreaterThanOrEqual, timestamp(start_time)?),
+            filter("timestamp", FilterOperator::LessThanOrEqual, timestamp(end_time)?),
+            filter("interval_seconds", FilterOperator::Equal, FilterValue::Integer(interval_seconds)),
+            filter("method", FilterOperator::Equal, FilterValue::String(method)),
+        ])
+    }
+
+    /// The query with `loggedtagvalues_resampled(...)` in FROM replaced by the plain table name,
+    /// under which the resampled batch is registered in DataFusion
+    pub fn strip_table_function_arguments(sql: &str) -> Result<String> {
+        let mut statements = Parser::parse_sql(&GenericDialect {}, sql)?;
+        if let Some(Statement::Query(query)) = statements.first_mut() {
+            if let SetExpr::Select(select) = query.body.as_mut() {
+                for table in &mut select.from {
+                    if let TableFactor::Table { args, .. } = &mut table.relation {
+                        *args = None;
+                    }
+                }
+            }
+        }
+        Ok(statements.iter().map(ToString::to_string).collect::<Vec<_>>().join("; "))
     }
 
     fn extract_columns(select: &Select, table: &VirtualTable) -> Result<(Vec<String>, std::collections::HashMap<String, String>)> {
         let mut columns = Vec::new();
         let mut column_mappings = std::collections::HashMap::new();
-        let is_datafusion_table = matches!(table, VirtualTable::TagValues | VirtualTable::TagList | VirtualTable::LoggedTagValues | VirtualTable::ActiveAlarms | VirtualTable::LoggedAlarms | VirtualTable::PgStatActivity);
+        let is_datafusion_table = matches!(table, VirtualTable::TagValues | VirtualTable::TagValueChanges | VirtualTable::TagList | VirtualTable::TagHierarchy | VirtualTable::TagStatistics | VirtualTable::AlarmStatistics | VirtualTable::LoggedAlarmStatistics | VirtualTable::WinccuaFunctions | VirtualTable::CacheStats | VirtualTable::ConnectionStats | VirtualTable::WinccuaConfig | VirtualTable::WinccuaVersion | VirtualTable::LoggedTagValues | VirtualTable::LoggedTagValuesIncremental | VirtualTable::LoggedTagValuesResampled | VirtualTable::LoggedTagValuesSummary | VirtualTable::ActiveAlarms | VirtualTable::ActiveAlarmsChanges | VirtualTable::LoggedAlarms | VirtualTable::PgStatActivity) || table.is_catalog();
 
         for item in &select.projection {
             match item {
@@ -170,7 +1098,9 @@ impl SqlHandler {
                 }
                 SelectItem::UnnamedExpr(expr) => {
                     if is_datafusion_table {
-                        // For datafusion, we don't validate here. Just pass the expression string.
+                        // DataFusion evaluates the expression (CASE, functions, arithmetic); only
+                        // the referenced columns are checked. Just pass the expression string.
+                        Self::validate_expression_columns(expr, table)?;
                         columns.push(expr.to_string());
                     } else {
                         // For non-datafusion, maintain strict validation.
@@ -190,6 +1120,7 @@ impl SqlHandler {
                 }
                 SelectItem::ExprWithAlias { expr, alias } => {
                     if is_datafusion_table {
+                        Self::validate_expression_columns(expr, table)?;
                         let alias_name = alias.value.clone();
                         columns.push(alias_name.clone());
                         column_mappings.insert(alias_name, expr.to_string());
@@ -221,6 +1152,53 @@ impl SqlHandler {
         Ok((columns, column_mappings))
     }
 
+    /// True if the SELECT list uses a window function such as `AVG(x) OVER (...)`
+    fn has_window_function(select: &Select) -> bool {
+        visit_expressions(&select.projection, |expr| match expr {
+            Expr::Function(Function { over: Some(_), .. }) => ControlFlow::Break(()),
+            _ => ControlFlow::Continue(()),
+        })
+        .is_break()
+    }
+
+    /// Check that every column referenced by a projection expression exists in the table's
+    /// RecordBatch, so a typo fails with "Unknown column" instead of a DataFusion planning error
+    fn validate_expression_columns(expr: &Expr, table: &VirtualTable) -> Result<()> {
+        // Catalog and pg_stat_activity queries may join and qualify columns of other tables
+        if table.is_catalog() || matches!(table, VirtualTable::PgStatActivity) {
+            return Ok(());
+        }
+
+        let mut unknown_column = None;
+        let _ = visit_expressions(expr, |expr| {
+            let ident = match expr {
+                Expr::Identifier(ident) => Some(ident),
+                Expr::CompoundIdentifier(parts) => parts.last(),
+                _ => None,
+            };
+            if let Some(ident) = ident {
+                // Unquoted identifiers are case-insensitive, as in DataFusion
+                let column = match ident.quote_style {
+                    Some(_) => ident.value.clone(),
+                    None => ident.value.to_lowercase(),
+                };
+                if !table.is_selectable_column(&column) {
+                    unknown_column = Some(column);
+                    return ControlFlow::Break(());
+                }
+            }
+            ControlFlow::Continue(())
+        });
+
+        match unknown_column {
+            Some(column) if table.is_virtual_column(&column) => {
+                Err(anyhow!("Column '{}' cannot be selected (virtual column)", column))
+            }
+            Some(column) => Err(anyhow!("Unknown column: {}", column)),
+            None => Ok(()),
+        }
+    }
+
     fn extract_filters(select: &Select, table: &VirtualTable) -> Result<Vec<ColumnFilter>> {
         let mut filters = Vec::new();
 
@@ -231,6 +1209,44 @@ impl SqlHandler {
         Ok(filters)
     }
 
+    /// Merge the `=` and `IN` filters of OR branches on the same column into one `IN` filter,
+    /// so `tag_name = 'A' OR tag_name = 'B'` becomes a single GraphQL call for both tags
+    fn coalesce_or_filters(branch_filters: Vec<ColumnFilter>) -> Vec<ColumnFilter> {
+        let string_values = |filter: &ColumnFilter| match (&filter.operator, &filter.value) {
+            (FilterOperator::Equal, FilterValue::String(value)) => Some(vec![value.clone()]),
+            (FilterOperator::In, FilterValue::List(values)) => Some(values.clone()),
+            _ => None,
+        };
+        let mut coalesced: Vec<ColumnFilter> = Vec::new();
+
+        for filter in branch_filters {
+            let Some(values) = string_values(&filter) else {
+                coalesced.push(filter);
+                continue;
+            };
+            let existing = coalesced
+                .iter_mut()
+                .find_map(|existing| match string_values(existing) {
+                    Some(merged) if existing.column == filter.column => Some((existing, merged)),
+                    _ => None,
+                });
+            let Some((existing, mut merged)) = existing else {
+                coalesced.push(filter);
+                continue;
+            };
+
+            for value in values {
+                if !merged.contains(&value) {
+                    merged.push(value);
+                }
+            }
+            existing.operator = FilterOperator::In;
+            existing.value = FilterValue::List(merged);
+        }
+
+        coalesced
+    }
+
     fn extract_filters_from_expr(
         expr: &Expr,
         table: &VirtualTable,
@@ -244,9 +1260,14 @@ impl SqlHandler {
                         Self::extract_filters_from_expr(right, table, filters)?;
                     }
                     BinaryOperator::Or => {
-                        warn!("OR conditions are not optimally supported and may result in multiple GraphQL calls");
-                        Self::extract_filters_from_expr(left, table, filters)?;
-                        Self::extract_filters_from_expr(right, table, filters)?;
+                        let mut branch_filters = Vec::new();
+                        Self::extract_filters_from_expr(left, table, &mut branch_filters)?;
+                        Self::extract_filters_from_expr(right, table, &mut branch_filters)?;
+                        let branch_filters = Self::coalesce_or_filters(branch_filters);
+                        if branch_filters.len() > 1 {
+                            warn!("OR conditions are not optimally supported and may result in multiple GraphQL calls");
+                        }
+                        filters.extend(branch_filters);
                     }
                     _ => {
                         if let (Expr::Identifier(column), value_expr) = (left.as_ref(), right.as_ref()) {
@@ -264,18 +1285,15 @@ impl SqlHandler {
                 }
             }
             Expr::InList { expr, list, negated } => {
-                if *negated {
-                    return Err(anyhow!("NOT IN is not supported"));
-                }
                 if let Expr::Identifier(column) = expr.as_ref() {
                     let values = list
                         .iter()
-                        .map(|v| Self::extract_string_value(v))
+                        .map(Self::extract_string_value)
                         .collect::<Result<Vec<_>>>()?;
                     
                     let filter = ColumnFilter {
                         column: column.value.clone(),
-                        operator: FilterOperator::In,
+                        operator: if *negated { FilterOperator::NotIn } else { FilterOperator::In },
                         value: FilterValue::List(values),
                     };
                     filters.push(filter);
@@ -284,14 +1302,11 @@ impl SqlHandler {
                 }
             }
             Expr::Like { expr, pattern, negated, .. } => {
-                if *negated {
-                    return Err(anyhow!("NOT LIKE is not supported"));
-                }
                 if let Expr::Identifier(column) = expr.as_ref() {
                     let pattern_str = Self::extract_string_value(pattern)?;
                     let filter = ColumnFilter {
                         column: column.value.clone(),
-                        operator: FilterOperator::Like,
+                        operator: if *negated { FilterOperator::NotLike } else { FilterOperator::Like },
                         value: FilterValue::String(pattern_str),
                     };
                     filters.push(filter);
@@ -299,16 +1314,29 @@ impl SqlHandler {
                     return Err(anyhow!("Complex LIKE expressions are not supported"));
                 }
             }
-            Expr::Between { expr, negated, low, high } => {
+            Expr::ILike { expr, pattern, negated, .. } => {
                 if *negated {
-                    return Err(anyhow!("NOT BETWEEN is not supported"));
+                    return Err(anyhow!("NOT ILIKE is not supported"));
                 }
+                if let Expr::Identifier(column) = expr.as_ref() {
+                    let pattern_str = Self::extract_string_value(pattern)?;
+                    let filter = ColumnFilter {
+                        column: column.value.clone(),
+                        operator: FilterOperator::ILike,
+                        value: FilterValue::String(pattern_str),
+                    };
+                    filters.push(filter);
+                } else {
+                    return Err(anyhow!("Complex ILIKE expressions are not supported"));
+                }
+            }
+            Expr::Between { expr, negated, low, high } => {
                 if let Expr::Identifier(column) = expr.as_ref() {
                     let low_val = Self::extract_filter_value_for_column(low, &column.value, table)?;
                     let high_val = Self::extract_filter_value_for_column(high, &column.value, table)?;
                     let filter = ColumnFilter {
                         column: column.value.clone(),
-                        operator: FilterOperator::Between,
+                        operator: if *negated { FilterOperator::NotBetween } else { FilterOperator::Between },
                         value: FilterValue::Range(Box::new(low_val), Box::new(high_val)),
                     };
                     filters.push(filter);
@@ -340,6 +1368,7 @@ impl SqlHandler {
                     return Err(anyhow!("Complex IS NOT NULL expressions are not supported"));
                 }
             }
+            Expr::Nested(inner) => Self::extract_filters_from_expr(inner, table, filters)?,
             _ => return Err(anyhow!("Unsupported WHERE expression")),
         }
 
@@ -390,9 +1419,14 @@ impl SqlHandler {
         
         match expr {
             Expr::Value(value_span) => match Self::extract_value_from_span(value_span) {
+                Value::SingleQuotedString(s) | Value::DoubleQuotedString(s) if column == "tag_name" => {
+                    // Bound parameters may arrive padded with whitespace
+                    Ok(FilterValue::String(s.trim().to_string()))
+                }
                 Value::SingleQuotedString(s) | Value::DoubleQuotedString(s) => {
                     Ok(FilterValue::from_string_value_for_column(s, column_type))
                 }
+                Value::Boolean(b) => Ok(FilterValue::String(b.to_string())),
                 Value::Number(n, _) => {
                     if let Ok(i) = n.parse::<i64>() {
                         Ok(FilterValue::Integer(i))
@@ -437,6 +1471,7 @@ impl SqlHandler {
                         let now = Local::now().format("%Y-%m-%dT%H:%M:%S%.3f").to_string();
                         Ok(FilterValue::Timestamp(now))
                     }
+                    "DATE_TRUNC" => Self::evaluate_date_trunc(func, column, table),
                     _ => Err(anyhow!("Unsupported function: {}", func.name)),
                 }
             }
@@ -449,6 +1484,54 @@ impl SqlHandler {
     }
 
 
+    /// Evaluate DATE_TRUNC(unit, timestamp) at parse time, e.g. DATE_TRUNC('hour', NOW())
+    fn evaluate_date_trunc(func: &Function, column: &str, table: &VirtualTable) -> Result<FilterValue> {
+        let args = match &func.args {
+            FunctionArguments::List(list) => list
+                .args
+                .iter()
+                .map(|arg| match arg {
+                    FunctionArg::Unnamed(FunctionArgExpr::Expr(expr)) => Ok(expr),
+                    _ => Err(anyhow!("Unsupported DATE_TRUNC argument: {}", arg)),
+                })
+                .collect::<Result<Vec<_>>>()?,
+            _ => Vec::new(),
+        };
+        let [unit, timestamp] = args.as_slice() else {
+            return Err(anyhow!("DATE_TRUNC expects a unit and a timestamp"));
+        };
+
+        let unit = Self::extract_string_value(unit)?;
+        let FilterValue::Timestamp(timestamp) = Self::extract_filter_value_for_column(timestamp, column, table)? else {
+            return Err(anyhow!("Expected timestamp value for DATE_TRUNC"));
+        };
+        let truncated = Self::truncate_timestamp(Self::parse_timestamp(&timestamp)?, &unit)?;
+        Ok(FilterValue::Timestamp(truncated.format("%Y-%m-%dT%H:%M:%S%.3f").to_string()))
+    }
+
+    fn truncate_timestamp(dt: DateTime<Local>, unit: &str) -> Result<DateTime<Local>> {
+        use chrono::{Datelike, DurationRound, NaiveDate, TimeZone};
+
+        let start_of_day = |date: NaiveDate| {
+            Local
+                .from_local_datetime(&date.and_hms_opt(0, 0, 0).unwrap())
+                .earliest()
+                .ok_or_else(|| anyhow!("Invalid local time for {}", date))
+        };
+        let date = dt.date_naive();
+        match unit.to_lowercase().as_str() {
+            "second" => Ok(dt.duration_trunc(Duration::seconds(1))?),
+            "minute" => Ok(dt.duration_trunc(Duration::minutes(1))?),
+            "hour" => Ok(dt.duration_trunc(Duration::hours(1))?),
+            "day" => start_of_day(date),
+            // Weeks start on Monday, as in PostgreSQL
+            "week" => start_of_day(date - Duration::days(date.weekday().num_days_from_monday() as i64)),
+            "month" => start_of_day(date.with_day(1).unwrap()),
+            "year" => start_of_day(NaiveDate::from_ymd_opt(date.year(), 1, 1).unwrap()),
+            other => Err(anyhow!("Unsupported DATE_TRUNC unit: {}", other)),
+        }
+    }
+
     fn extract_string_value(expr: &Expr) -> Result<String> {
         match expr {
             Expr::Value(value_span) => Self::extract_string_from_span(value_span),
@@ -468,36 +1551,57 @@ impl SqlHandler {
         }
     }
 
-    #[allow(dead_code)]
-    fn extract_order_by(order_expr: &OrderByExpr) -> OrderBy {
-        let column = match &order_expr.expr {
-            Expr::Identifier(ident) => ident.to_string(),
-            _ => "timestamp".to_string(), // Default fallback
+    fn extract_order_by(query: &Query) -> Vec<OrderBy> {
+        let Some(order_by) = &query.order_by else {
+            return Vec::new();
         };
+        match &order_by.kind {
+            OrderByKind::Expressions(exprs) => exprs.iter().map(Self::extract_order_by_expr).collect(),
+            OrderByKind::All(_) => Vec::new(),
+        }
+    }
 
-        let ascending = true; // Simplified for compatibility
+    fn extract_order_by_expr(order_expr: &OrderByExpr) -> OrderBy {
+        let column = Self::order_by_column(&order_expr.expr);
+        let ascending = order_expr.options.asc.unwrap_or(true);
 
         OrderBy { column, ascending }
     }
 
+    fn order_by_column(expr: &Expr) -> String {
+        match expr {
+            Expr::Identifier(ident) => ident.value.to_lowercase(),
+            Expr::CompoundIdentifier(parts) => parts.last().map(|p| p.value.to_lowercase()).unwrap_or_default(),
+            other => other.to_string(),
+        }
+    }
 
     fn validate_query(query: &QueryInfo) -> Result<()> {
-        // Validate that tag-based tables have required filters
-        if matches!(query.table, VirtualTable::TagValues | VirtualTable::LoggedTagValues) {
-            if !query.has_required_tag_filter() {
-                return Err(anyhow!(
-                    "TagValues and LoggedTagValues queries must include a WHERE clause on tag_name"
-                ));
-            }
+        // Validate that tag-based tables have required filters.
+        // TagValues may skip the filter when full scans are enabled via --allow-full-tagvalues-scan.
+        let full_scan_allowed = matches!(query.table, VirtualTable::TagValues | VirtualTable::TagValueChanges)
+            && crate::ALLOW_FULL_TAGVALUES_SCAN.load(std::sync::atomic::Ordering::Relaxed);
+        if matches!(query.table, VirtualTable::TagValues | VirtualTable::TagValueChanges | VirtualTable::LoggedTagValues | VirtualTable::LoggedTagValuesIncremental | VirtualTable::LoggedTagValuesResampled | VirtualTable::LoggedTagValuesSummary | VirtualTable::TagStatistics)
+            && !query.has_required_tag_filter()
+            && !full_scan_allowed
+        {
+            return Err(anyhow!(
+                "TagValues and LoggedTagValues queries must include a WHERE clause on tag_name"
+            ));
+        }
+
+        if let Some(window_size) = query.get_window_size().filter(|_| query.table == VirtualTable::LoggedTagValuesSummary) {
+            crate::query_handler::summary_handler::SummaryWindow::parse(window_size)?;
         }
 
         // Validate that LoggedTagValues has timestamp constraints when using LIMIT
-        if matches!(query.table, VirtualTable::LoggedTagValues) {
-            if query.limit.is_some() && query.get_timestamp_filter().is_none() {
-                return Err(anyhow!(
-                    "LoggedTagValues queries with LIMIT must include timestamp constraints"
-                ));
-            }
+        if matches!(query.table, VirtualTable::LoggedTagValues)
+            && query.fetch_limit().is_some()
+            && query.get_timestamp_filter().is_none()
+        {
+            return Err(anyhow!(
+                "LoggedTagValues queries with LIMIT must include timestamp constraints"
+            ));
         }
 
         Ok(())
@@ -572,7 +1676,7 @@ impl SqlHandler {
             // If leading_field is specified, use it; otherwise parse from the string
             if let Some(leading_field) = &interval.leading_field {
                 // Parse the numeric value from the string
-                let parts: Vec<&str> = interval_str.trim().split_whitespace().collect();
+                let parts: Vec<&str> = interval_str.split_whitespace().collect();
                 if parts.is_empty() {
                     return Err(anyhow!("Empty interval string"));
                 }
@@ -628,11 +1732,11 @@ impl SqlHandler {
         }
     }
     
-    fn parse_interval_string(interval_str: &str) -> Result<Duration> {
+    pub(crate) fn parse_interval_string(interval_str: &str) -> Result<Duration> {
         debug!("Parsing interval string: '{}'", interval_str);
         
         // Parse strings like "1 hour", "3 minutes", "7 days", etc.
-        let parts: Vec<&str> = interval_str.trim().split_whitespace().collect();
+        let parts: Vec<&str> = interval_str.split_whitespace().collect();
         if parts.len() != 2 {
             return Err(anyhow!("Invalid interval format: expected 'NUMBER UNIT', got '{}'", interval_str));
         }
@@ -681,27 +1785,184 @@ impl SqlHandler {
         Err(anyhow!("Could not parse timestamp: {}", ts_str))
     }
 
+    /// Parse `UPDATE tagvalues SET numeric_value|string_value = <literal> WHERE tag_name = '...'`
+    /// (or `tag_name IN (...)`) into one write per tag
+    fn parse_tag_write(assignments: &[Assignment], selection: Option<&Expr>) -> Result<SqlResult> {
+        let [Assignment { target: AssignmentTarget::ColumnName(column), value }] = assignments else {
+            return Err(anyhow!("UPDATE tagvalues must set exactly one column"));
+        };
+        let column = column.to_string().to_lowercase();
+        let value = match (column.as_str(), value) {
+            ("numeric_value", expr) => Self::tag_write_number(expr),
+            ("string_value", Expr::Value(value_span)) => Self::extract_string_from_span(value_span).ok().map(serde_json::Value::String),
+            ("string_value", _) => None,
+            _ => return Err(anyhow!("UPDATE tagvalues can only set numeric_value or string_value, got {}", column)),
+        }
+        .ok_or_else(|| anyhow!("UPDATE tagvalues SET {} requires a literal value", column))?;
+
+        let mut tag_names = Vec::new();
+        match selection {
+            Some(expr) => Self::collect_tag_write_names(expr, &mut tag_names)?,
+            None => return Err(anyhow!("UPDATE tagvalues requires WHERE tag_name = '...' or tag_name IN (...)")),
+        }
+
+        debug!("✏️  Tag write of {} = {} to {} tags", column, value, tag_names.len());
+        Ok(SqlResult::WriteTagValues(
+            tag_names
+                .into_iter()
+                .map(|tag_name| TagWrite { tag_name, value: value.clone() })
+                .collect(),
+        ))
+    }
+
+    /// Parse `UPDATE activealarms SET state = 'ACKNOWLEDGED' WHERE instance_id = 12345`
+    /// (or `instance_id IN (...)`) into the instance IDs to acknowledge
+    fn parse_alarm_acknowledgment(assignments: &[Assignment], selection: Option<&Expr>) -> Result<SqlResult> {
+        let [Assignment { target: AssignmentTarget::ColumnName(column), value }] = assignments else {
+            return Err(anyhow!("UPDATE activealarms must set exactly one column"));
+        };
+        let column = column.to_string().to_lowercase();
+        if column != "state" {
+            return Err(anyhow!("UPDATE activealarms can only set state, got {}", column));
+        }
+        let state = match value {
+            Expr::Value(value_span) => Self::extract_string_from_span(value_span)?,
+            _ => return Err(anyhow!("UPDATE activealarms SET state requires a string literal")),
+        };
+        if !state.eq_ignore_ascii_case("ACKNOWLEDGED") {
+            return Err(anyhow!("Alarms can only be set to state 'ACKNOWLEDGED', got '{}'", state));
+        }
+
+        let mut instance_ids = Vec::new();
+        match selection {
+            Some(expr) => Self::collect_alarm_instance_ids(expr, &mut instance_ids)?,
+            None => return Err(anyhow!("UPDATE activealarms requires WHERE instance_id = ... or instance_id IN (...)")),
+        }
+
+        debug!("✅ Alarm acknowledgment of {} instances: {:?}", instance_ids.len(), instance_ids);
+        Ok(SqlResult::AcknowledgeAlarms(instance_ids))
+    }
+
+    fn collect_alarm_instance_ids(expr: &Expr, instance_ids: &mut Vec<i32>) -> Result<()> {
+        let is_instance_id = |expr: &Expr| matches!(expr, Expr::Identifier(ident) if ident.value.eq_ignore_ascii_case("instance_id"));
+        let instance_id = |expr: &Expr| match expr {
+            Expr::Value(value_span) => match Self::extract_value_from_span(value_span) {
+                Value::Number(n, _) => n.parse::<i32>().map_err(|_| anyhow!("Invalid instance_id: {}", n)),
+                _ => Err(anyhow!("instance_id must be compared to an integer literal")),
+            },
+            _ => Err(anyhow!("instance_id must be compared to an integer literal")),
+        };
+        match expr {
+            Expr::Nested(inner) => Self::collect_alarm_instance_ids(inner, instance_ids),
+            Expr::BinaryOp { left, op: BinaryOperator::Eq, right } if is_instance_id(left) => {
+                instance_ids.push(instance_id(right)?);
+                Ok(())
+            }
+            Expr::InList { expr, list, negated: false } if is_instance_id(expr) => {
+                for item in list {
+                    instance_ids.push(instance_id(item)?);
+                }
+                Ok(())
+            }
+            _ => Err(anyhow!("UPDATE activealarms only supports WHERE instance_id = ... or instance_id IN (...)")),
+        }
+    }
+
+    fn tag_write_number(expr: &Expr) -> Option<serde_json::Value> {
+        let (number, negative) = match expr {
+            Expr::UnaryOp { op: UnaryOperator::Minus, expr } => (&**expr, true),
+            expr => (expr, false),
+        };
+        match number {
+            Expr::Value(value_span) => match Self::extract_value_from_span(value_span) {
+                Value::Number(n, _) => {
+                    let n = if negative { format!("-{}", n) } else { n.clone() };
+                    n.parse::<i64>()
+                        .map(serde_json::Value::from)
+                        .ok()
+                        .or_else(|| n.parse::<f64>().ok().map(serde_json::Value::from))
+                }
+                Value::Boolean(b) if !negative => Some(serde_json::Value::Bool(*b)),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    fn collect_tag_write_names(expr: &Expr, tag_names: &mut Vec<String>) -> Result<()> {
+        let is_tag_name = |expr: &Expr| matches!(expr, Expr::Identifier(ident) if ident.value.eq_ignore_ascii_case("tag_name"));
+        match expr {
+            Expr::Nested(inner) => Self::collect_tag_write_names(inner, tag_names),
+            Expr::BinaryOp { left, op: BinaryOperator::Eq, right } if is_tag_name(left) => match &**right {
+                Expr::Value(value_span) => {
+                    tag_names.push(Self::extract_string_from_span(value_span)?);
+                    Ok(())
+                }
+                _ => Err(anyhow!("tag_name must be compared to a string literal")),
+            },
+            Expr::InList { expr, list, negated: false } if is_tag_name(expr) => {
+                for item in list {
+                    match item {
+                        Expr::Value(value_span) => tag_names.push(Self::extract_string_from_span(value_span)?),
+                        _ => return Err(anyhow!("tag_name IN (...) must only contain string literals")),
+                    }
+                }
+                Ok(())
+            }
+            _ => Err(anyhow!("UPDATE tagvalues only supports WHERE tag_name = '...' or tag_name IN (...)")),
+        }
+    }
+
     fn handle_set_statement(statement: &Statement) -> Result<SqlResult> {
         debug!("Handling SET statement: {:?}", statement);
         
-        let set_command = match statement {
-            Statement::SetVariable { variables, .. } => {
-                // For now, just return a simple success message
-                // TODO: Extract actual variable names and values when the structure is clear
-                debug!("Variables structure: {:?}", variables);
-                format!("SET (variables: {})", variables.len())
+        let variables = match statement {
+            Statement::SetVariable { variables, value, .. } => {
+                let names: Vec<String> = variables
+                    .iter()
+                    .map(|name| name.to_string().replace('"', "").to_lowercase())
+                    .collect();
+                let values: Vec<String> = value.iter().map(Self::set_value_to_string).collect();
+                if names.len() == 1 {
+                    // SET search_path = a, b keeps all values as one comma-separated setting
+                    vec![(names[0].clone(), values.join(", "))]
+                } else {
+                    // SET (a, b) = (1, 2)
+                    names.into_iter().zip(values).collect()
+                }
             }
             Statement::SetNames { charset_name, .. } => {
-                format!("SET NAMES {}", charset_name)
+                vec![("client_encoding".to_string(), charset_name.clone())]
             }
             Statement::SetTimeZone { value, .. } => {
-                format!("SET TIME ZONE {}", value)
+                vec![("timezone".to_string(), Self::set_value_to_string(value))]
             }
-            _ => "SET (unknown)".to_string(),
+            _ => Vec::new(),
+        };
+
+        let command = match variables.as_slice() {
+            [] => "SET (unknown)".to_string(),
+            assignments => format!(
+                "SET {}",
+                assignments
+                    .iter()
+                    .map(|(name, value)| format!("{} = '{}'", name, value))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
         };
         
-        debug!("Successfully handled SET statement: {}", set_command);
-        Ok(SqlResult::SetStatement(set_command))
+        debug!("Successfully handled SET statement: {}", command);
+        Ok(SqlResult::SetStatement(SetCommand { command, variables }))
+    }
+
+    /// Value of a SET assignment as stored for SHOW: string literals unquoted, identifiers as written
+    fn set_value_to_string(expr: &Expr) -> String {
+        match expr {
+            Expr::Value(value_span) => Self::extract_string_from_span(value_span).unwrap_or_else(|_| value_span.value.to_string()),
+            Expr::Identifier(ident) => ident.value.clone(),
+            other => other.to_string(),
+        }
     }
 }
 
@@ -709,6 +1970,110 @@ impl SqlHandler {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_tagvalues_without_where_requires_full_scan_flag() {
+        // --allow-full-tagvalues-scan is off by default, so a tag_name filter is still required
+        let result = SqlHandler::parse_query("SELECT COUNT(*) FROM tagvalues");
+        assert!(result.is_err(), "tagvalues without WHERE should fail when full scan is disabled");
+
+        let result = SqlHandler::parse_query("SELECT * FROM loggedtagvalues");
+        assert!(result.is_err(), "loggedtagvalues without WHERE should always fail");
+    }
+
+    #[test]
+    fn test_split_statements_respects_quotes_and_comments() {
+        assert_eq!(
+            SqlHandler::split_statements("BEGIN; SELECT * FROM tagvalues WHERE tag_name = 'X;Y'; COMMIT"),
+            vec!["BEGIN", "SELECT * FROM tagvalues WHERE tag_name = 'X;Y'", "COMMIT"]
+        );
+        assert_eq!(
+            SqlHandler::split_statements("SELECT 'it''s;' AS \"a;b\" -- c;d\n; /* e;f */ SELECT 2;; -- done"),
+            vec!["SELECT 'it''s;' AS \"a;b\" -- c;d", "/* e;f */ SELECT 2"]
+        );
+        assert_eq!(SqlHandler::split_statements("SELECT 1;"), vec!["SELECT 1"]);
+        assert!(SqlHandler::split_statements(" ; -- nothing").is_empty());
+    }
+
+    #[test]
+    fn test_not_in_string_list() {
+        let sql = "SELECT * FROM taglist WHERE data_type NOT IN ('Bool', 'String')";
+        match SqlHandler::parse_query(sql) {
+            Ok(SqlResult::Query(query_info)) => {
+                let filter = query_info
+                    .filters
+                    .iter()
+                    .find(|f| f.column == "data_type")
+                    .expect("data_type filter should be present");
+                assert!(matches!(filter.operator, FilterOperator::NotIn));
+                assert_eq!(
+                    filter.value.as_list(),
+                    Some(&vec!["Bool".to_string(), "String".to_string()])
+                );
+            }
+            other => panic!("Unexpected parse result for '{}': {:?}", sql, other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn test_not_like_with_percent_prefix() {
+        let sql = "SELECT * FROM tagvalues WHERE tag_name LIKE 'HMI_%' AND tag_name NOT LIKE '%_Test'";
+        match SqlHandler::parse_query(sql) {
+            Ok(SqlResult::Query(query_info)) => {
+                let filter = query_info
+                    .filters
+                    .iter()
+                    .find(|f| matches!(f.operator, FilterOperator::NotLike))
+                    .expect("NOT LIKE filter should be present");
+                assert_eq!(filter.column, "tag_name");
+                assert_eq!(filter.value.as_string(), Some("%_Test"));
+                assert!(query_info.has_tag_name_exclusions());
+                // The positive LIKE pattern is still resolved via browse
+                assert_eq!(query_info.get_like_patterns(), vec!["HMI_%".to_string()]);
+            }
+            other => panic!("Unexpected parse result for '{}': {:?}", sql, other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn test_ilike_tag_name_pattern() {
+        let sql = "SELECT * FROM tagvalues WHERE tag_name ILIKE '%motor%'";
+        match SqlHandler::parse_query(sql) {
+            Ok(SqlResult::Query(query_info)) => {
+                assert!(matches!(query_info.filters[0].operator, FilterOperator::ILike));
+                assert!(query_info.has_required_tag_filter());
+                assert!(query_info.requires_browse());
+                assert_eq!(query_info.get_ilike_patterns(), vec!["%motor%".to_string()]);
+                assert!(query_info.get_like_patterns().is_empty());
+            }
+            other => panic!("Unexpected parse result for '{}': {:?}", sql, other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn test_union_all_of_current_and_logged_values() {
+        let sql = "SELECT tag_name, timestamp, numeric_value FROM tagvalues WHERE tag_name = 'X' \
+                   UNION ALL SELECT tag_name, timestamp, numeric_value FROM loggedtagvalues \
+                   WHERE tag_name = 'X' AND timestamp > NOW() - INTERVAL '1 hour' ORDER BY timestamp LIMIT 10";
+        match SqlHandler::parse_query(sql) {
+            Ok(SqlResult::UnionQuery(union_info)) => {
+                assert_eq!(union_info.legs.len(), 2);
+                assert_eq!(union_info.legs[0].query_info.table, VirtualTable::TagValues);
+                assert_eq!(union_info.legs[1].query_info.table, VirtualTable::LoggedTagValues);
+                assert!(!union_info.legs[1].sql.contains("LIMIT"));
+                assert_eq!(
+                    union_info.outer_sql.as_deref(),
+                    Some("SELECT * FROM union_all_result ORDER BY timestamp LIMIT 10")
+                );
+            }
+            other => panic!("Unexpected parse result for '{}': {:?}", sql, other.map(|_| ())),
+        }
+
+        let result = SqlHandler::parse_query(
+            "SELECT tag_name FROM tagvalues WHERE tag_name = 'X' UNION SELECT tag_name FROM tagvalues WHERE tag_name = 'Y'",
+        );
+        assert!(result.is_err(), "UNION without ALL is not supported");
+    }
+
     #[test]
     fn test_is_null_expressions() {
         let test_cases = [
@@ -730,6 +2095,12 @@ mod tests {
                     });
                     assert!(has_null_filter, "Query should contain IS NULL or IS NOT NULL filter");
                 }
+                Ok(SqlResult::UnionQuery(_)) => panic!("Unexpected UNION ALL result"),
+                Ok(SqlResult::ExplainPlan(_)) => panic!("Unexpected EXPLAIN result"),
+                Ok(SqlResult::WriteTagValues(_)) => panic!("Unexpected UPDATE result"),
+                Ok(SqlResult::AcknowledgeAlarms(_)) => panic!("Unexpected UPDATE result"),
+                Ok(SqlResult::CopyTo(_)) => panic!("Unexpected COPY result"),
+                Ok(SqlResult::Cursor(_)) => panic!("Unexpected cursor result"),
                 Ok(SqlResult::SetStatement(_)) => {
                     panic!("Query incorrectly identified as SET statement: {}", sql);
                 }
@@ -768,6 +2139,12 @@ mod tests {
                     }
                 }
             }
+            SqlResult::UnionQuery(_) => panic!("Unexpected UNION ALL result"),
+            SqlResult::ExplainPlan(_) => panic!("Unexpected EXPLAIN result"),
+            SqlResult::WriteTagValues(_) => panic!("Unexpected UPDATE result"),
+            SqlResult::AcknowledgeAlarms(_) => panic!("Unexpected UPDATE result"),
+            SqlResult::CopyTo(_) => panic!("Unexpected COPY result"),
+            SqlResult::Cursor(_) => panic!("Unexpected cursor result"),
             SqlResult::SetStatement(_) => {
                 panic!("Expected Query result, got SetStatement");
             }
@@ -813,8 +2190,15 @@ mod tests {
             assert!(result.is_ok(), "Failed to parse SET statement: {}: {:?}", sql, result.err());
             
             match result.unwrap() {
+                SqlResult::UnionQuery(_) => panic!("Unexpected UNION ALL result"),
+                SqlResult::ExplainPlan(_) => panic!("Unexpected EXPLAIN result"),
+                SqlResult::WriteTagValues(_) => panic!("Unexpected UPDATE result"),
+                SqlResult::AcknowledgeAlarms(_) => panic!("Unexpected UPDATE result"),
+                SqlResult::CopyTo(_) => panic!("Unexpected COPY result"),
+                SqlResult::Cursor(_) => panic!("Unexpected cursor result"),
                 SqlResult::SetStatement(set_command) => {
-                    assert!(set_command.starts_with("SET"), "SET command should start with 'SET': {}", set_command);
+                    assert!(set_command.command.starts_with("SET"), "SET command should start with 'SET': {}", set_command.command);
+                    assert_eq!(set_command.variables.len(), 1, "SET should assign one variable: {}", sql);
                 }
                 SqlResult::Query(_) => {
                     panic!("Expected SetStatement result for '{}', got Query", sql);
@@ -838,6 +2222,12 @@ mod tests {
             assert!(result.is_ok(), "Failed to parse statement: {}: {:?}", sql, result.err());
             
             match result.unwrap() {
+                SqlResult::UnionQuery(_) => panic!("Unexpected UNION ALL result"),
+                SqlResult::ExplainPlan(_) => panic!("Unexpected EXPLAIN result"),
+                SqlResult::WriteTagValues(_) => panic!("Unexpected UPDATE result"),
+                SqlResult::AcknowledgeAlarms(_) => panic!("Unexpected UPDATE result"),
+                SqlResult::CopyTo(_) => panic!("Unexpected COPY result"),
+                SqlResult::Cursor(_) => panic!("Unexpected cursor result"),
                 SqlResult::SetStatement(_) => {
                     assert!(is_set, "Expected Query result for '{}', got SetStatement", sql);
                 }
@@ -867,9 +2257,15 @@ mod tests {
             
             // Verify it's recognized as a SET statement
             match result.unwrap() {
+                SqlResult::UnionQuery(_) => panic!("Unexpected UNION ALL result"),
+                SqlResult::ExplainPlan(_) => panic!("Unexpected EXPLAIN result"),
+                SqlResult::WriteTagValues(_) => panic!("Unexpected UPDATE result"),
+                SqlResult::AcknowledgeAlarms(_) => panic!("Unexpected UPDATE result"),
+                SqlResult::CopyTo(_) => panic!("Unexpected COPY result"),
+                SqlResult::Cursor(_) => panic!("Unexpected cursor result"),
                 SqlResult::SetStatement(set_command) => {
-                    assert!(set_command.starts_with("SET"), "Expected SET command, got: {}", set_command);
-                    println!("✅ Parse request for '{}' -> SetStatement('{}')", sql, set_command);
+                    assert!(set_command.command.starts_with("SET"), "Expected SET command, got: {}", set_command.command);
+                    println!("✅ Parse request for '{}' -> SetStatement('{}')", sql, set_command.command);
                 }
                 SqlResult::Query(_) => {
                     panic!("Parse request for SET statement '{}' incorrectly returned Query result", sql);
@@ -940,6 +2336,12 @@ mod tests {
                     assert!(!query_info.columns.is_empty(), "Should have columns specified");
                     println!("✅ TagList query parsed: '{}' -> {} columns", sql, query_info.columns.len());
                 }
+                SqlResult::UnionQuery(_) => panic!("Unexpected UNION ALL result"),
+                SqlResult::ExplainPlan(_) => panic!("Unexpected EXPLAIN result"),
+                SqlResult::WriteTagValues(_) => panic!("Unexpected UPDATE result"),
+                SqlResult::AcknowledgeAlarms(_) => panic!("Unexpected UPDATE result"),
+                SqlResult::CopyTo(_) => panic!("Unexpected COPY result"),
+                SqlResult::Cursor(_) => panic!("Unexpected cursor result"),
                 SqlResult::SetStatement(_) => {
                     panic!("TagList query incorrectly identified as SET statement: {}", sql);
                 }
@@ -980,6 +2382,12 @@ mod tests {
                         println!("✅ TagList display_name filter parsed: '{}' -> {:?}", sql, filter.operator);
                     }
                 }
+                SqlResult::UnionQuery(_) => panic!("Unexpected UNION ALL result"),
+                SqlResult::ExplainPlan(_) => panic!("Unexpected EXPLAIN result"),
+                SqlResult::WriteTagValues(_) => panic!("Unexpected UPDATE result"),
+                SqlResult::AcknowledgeAlarms(_) => panic!("Unexpected UPDATE result"),
+                SqlResult::CopyTo(_) => panic!("Unexpected COPY result"),
+                SqlResult::Cursor(_) => panic!("Unexpected cursor result"),
                 SqlResult::SetStatement(_) => {
                     panic!("TagList query incorrectly identified as SET statement: {}", sql);
                 }
@@ -1011,6 +2419,12 @@ mod tests {
                     
                     println!("✅ TagList data_type filter parsed: '{}'", sql);
                 }
+                SqlResult::UnionQuery(_) => panic!("Unexpected UNION ALL result"),
+                SqlResult::ExplainPlan(_) => panic!("Unexpected EXPLAIN result"),
+                SqlResult::WriteTagValues(_) => panic!("Unexpected UPDATE result"),
+                SqlResult::AcknowledgeAlarms(_) => panic!("Unexpected UPDATE result"),
+                SqlResult::CopyTo(_) => panic!("Unexpected COPY result"),
+                SqlResult::Cursor(_) => panic!("Unexpected cursor result"),
                 SqlResult::SetStatement(_) => {
                     panic!("TagList query incorrectly identified as SET statement: {}", sql);
                 }
@@ -1052,6 +2466,12 @@ mod tests {
                         println!("✅ LoggedAlarms {} filter parsed: '{}' -> {:?}", expected_column, sql, filter.operator);
                     }
                 }
+                SqlResult::UnionQuery(_) => panic!("Unexpected UNION ALL result"),
+                SqlResult::ExplainPlan(_) => panic!("Unexpected EXPLAIN result"),
+                SqlResult::WriteTagValues(_) => panic!("Unexpected UPDATE result"),
+                SqlResult::AcknowledgeAlarms(_) => panic!("Unexpected UPDATE result"),
+                SqlResult::CopyTo(_) => panic!("Unexpected COPY result"),
+                SqlResult::Cursor(_) => panic!("Unexpected cursor result"),
                 SqlResult::SetStatement(_) => {
                     panic!("LoggedAlarms query incorrectly identified as SET statement: {}", sql);
                 }
@@ -1078,6 +2498,12 @@ mod tests {
                 
                 println!("✅ LoggedAlarms complex query parsed successfully with LIMIT and virtual columns");
             }
+            SqlResult::UnionQuery(_) => panic!("Unexpected UNION ALL result"),
+            SqlResult::ExplainPlan(_) => panic!("Unexpected EXPLAIN result"),
+            SqlResult::WriteTagValues(_) => panic!("Unexpected UPDATE result"),
+            SqlResult::AcknowledgeAlarms(_) => panic!("Unexpected UPDATE result"),
+            SqlResult::CopyTo(_) => panic!("Unexpected COPY result"),
+            SqlResult::Cursor(_) => panic!("Unexpected cursor result"),
             SqlResult::SetStatement(_) => {
                 panic!("LoggedAlarms query incorrectly identified as SET statement");
             }
@@ -1136,6 +2562,12 @@ mod tests {
                     
                     println!("✅ LoggedAlarms optional parameters test passed for: '{}'", sql);
                 }
+                SqlResult::UnionQuery(_) => panic!("Unexpected UNION ALL result"),
+                SqlResult::ExplainPlan(_) => panic!("Unexpected EXPLAIN result"),
+                SqlResult::WriteTagValues(_) => panic!("Unexpected UPDATE result"),
+                SqlResult::AcknowledgeAlarms(_) => panic!("Unexpected UPDATE result"),
+                SqlResult::CopyTo(_) => panic!("Unexpected COPY result"),
+                SqlResult::Cursor(_) => panic!("Unexpected cursor result"),
                 SqlResult::SetStatement(_) => {
                     panic!("LoggedAlarms query incorrectly identified as SET statement: {}", sql);
                 }
@@ -1171,6 +2603,12 @@ mod tests {
                     
                     println!("✅ Quality column test passed for: '{}'", sql);
                 }
+                SqlResult::UnionQuery(_) => panic!("Unexpected UNION ALL result"),
+                SqlResult::ExplainPlan(_) => panic!("Unexpected EXPLAIN result"),
+                SqlResult::WriteTagValues(_) => panic!("Unexpected UPDATE result"),
+                SqlResult::AcknowledgeAlarms(_) => panic!("Unexpected UPDATE result"),
+                SqlResult::CopyTo(_) => panic!("Unexpected COPY result"),
+                SqlResult::Cursor(_) => panic!("Unexpected cursor result"),
                 SqlResult::SetStatement(_) => {
                     panic!("Tag query incorrectly identified as SET statement: {}", sql);
                 }
@@ -1206,6 +2644,12 @@ mod tests {
                     
                     println!("✅ Quality filtering test passed for: '{}'", sql);
                 }
+                SqlResult::UnionQuery(_) => panic!("Unexpected UNION ALL result"),
+                SqlResult::ExplainPlan(_) => panic!("Unexpected EXPLAIN result"),
+                SqlResult::WriteTagValues(_) => panic!("Unexpected UPDATE result"),
+                SqlResult::AcknowledgeAlarms(_) => panic!("Unexpected UPDATE result"),
+                SqlResult::CopyTo(_) => panic!("Unexpected COPY result"),
+                SqlResult::Cursor(_) => panic!("Unexpected cursor result"),
                 SqlResult::SetStatement(_) => {
                     panic!("Tag query incorrectly identified as SET statement: {}", sql);
                 }
@@ -1247,6 +2691,12 @@ mod tests {
                         println!("✅ Auto-suffix test (should NOT append): '{}'", sql);
                     }
                 }
+                SqlResult::UnionQuery(_) => panic!("Unexpected UNION ALL result"),
+                SqlResult::ExplainPlan(_) => panic!("Unexpected EXPLAIN result"),
+                SqlResult::WriteTagValues(_) => panic!("Unexpected UPDATE result"),
+                SqlResult::AcknowledgeAlarms(_) => panic!("Unexpected UPDATE result"),
+                SqlResult::CopyTo(_) => panic!("Unexpected COPY result"),
+                SqlResult::Cursor(_) => panic!("Unexpected cursor result"),
                 SqlResult::SetStatement(_) => {
                     panic!("Tag query incorrectly identified as SET statement: {}", sql);
                 }
@@ -1304,6 +2754,12 @@ mod tests {
                     }
                     println!("✅ Quoted table name test passed: '{}'", sql);
                 }
+                SqlResult::UnionQuery(_) => panic!("Unexpected UNION ALL result"),
+                SqlResult::ExplainPlan(_) => panic!("Unexpected EXPLAIN result"),
+                SqlResult::WriteTagValues(_) => panic!("Unexpected UPDATE result"),
+                SqlResult::AcknowledgeAlarms(_) => panic!("Unexpected UPDATE result"),
+                SqlResult::CopyTo(_) => panic!("Unexpected COPY result"),
+                SqlResult::Cursor(_) => panic!("Unexpected cursor result"),
                 SqlResult::SetStatement(_) => {
                     panic!("Query incorrectly identified as SET statement: {}", sql);
                 }
@@ -1325,9 +2781,11 @@ mod tests {
                 timestamp: "2024-01-01T00:00:00Z".to_string(),
                 quality: Some(Quality {
                     quality: "GOOD".to_string(),
+                    sub_status: None,
                 }),
             }),
             error: None,
+            warning: None,
         };
         
         // Test SQL parsing and filter creation
@@ -1348,14 +2806,20 @@ mod tests {
                     println!("🔍 Quality filter found: {:?}", filter);
                     
                     // Test the filtering logic directly
-                    let _filters = vec![filter.clone()];
-                    let _test_results = vec![mock_result];
+                    let _filters = [filter.clone()];
+                    let _test_results = [mock_result];
                     
                     // This should work but let's see what happens
                     // Note: We can't easily test apply_filters here since it's private
                     println!("✅ Quality filter runtime test setup complete");
                 }
             }
+            SqlResult::UnionQuery(_) => panic!("Unexpected UNION ALL result"),
+            SqlResult::ExplainPlan(_) => panic!("Unexpected EXPLAIN result"),
+            SqlResult::WriteTagValues(_) => panic!("Unexpected UPDATE result"),
+            SqlResult::AcknowledgeAlarms(_) => panic!("Unexpected UPDATE result"),
+            SqlResult::CopyTo(_) => panic!("Unexpected COPY result"),
+            SqlResult::Cursor(_) => panic!("Unexpected cursor result"),
             SqlResult::SetStatement(_) => {
                 panic!("Query incorrectly identified as SET statement");
             }
@@ -1378,6 +2842,12 @@ mod tests {
                               "Should parse as pg_stat_activity table");
                     println!("✅ Successfully parsed pg_stat_activity query: {}", sql);
                 }
+                Ok(SqlResult::UnionQuery(_)) => panic!("Unexpected UNION ALL result"),
+                Ok(SqlResult::ExplainPlan(_)) => panic!("Unexpected EXPLAIN result"),
+                Ok(SqlResult::WriteTagValues(_)) => panic!("Unexpected UPDATE result"),
+                Ok(SqlResult::AcknowledgeAlarms(_)) => panic!("Unexpected UPDATE result"),
+                Ok(SqlResult::CopyTo(_)) => panic!("Unexpected COPY result"),
+                Ok(SqlResult::Cursor(_)) => panic!("Unexpected cursor result"),
                 Ok(SqlResult::SetStatement(_)) => {
                     panic!("Query incorrectly identified as SET statement: {}", sql);
                 }
@@ -1387,4 +2857,303 @@ mod tests {
             }
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_order_by_columns_and_sorting_mode() {
+        let parse = |sql: &str| match SqlHandler::parse_query(sql) {
+            Ok(SqlResult::Query(query_info)) => query_info,
+            other => panic!("Unexpected parse result for '{}': {:?}", sql, other),
+        };
+        let columns = |query_info: &QueryInfo| {
+            query_info
+                .order_by
+                .iter()
+                .map(|o| (o.column.clone(), o.ascending))
+                .collect::<Vec<_>>()
+        };
+        let base = "SELECT * FROM loggedtagvalues WHERE tag_name = 'Tag_1' AND timestamp > '2025-01-01T00:00:00Z'";
+
+        let query_info = parse(&format!("{} ORDER BY timestamp DESC", base));
+        assert_eq!(columns(&query_info), vec![("timestamp".to_string(), false)]);
+        assert_eq!(query_info.sorting_mode(), "TIME_DESC");
+
+        let query_info = parse(&format!("{} ORDER BY numeric_value ASC", base));
+        assert_eq!(columns(&query_info), vec![("numeric_value".to_string(), true)]);
+        assert_eq!(query_info.sorting_mode(), "TIME_ASC");
+
+        // Only the first column decides the GraphQL sort, DataFusion applies the full ordering
+        let query_info = parse(&format!("{} ORDER BY tag_name, timestamp DESC", base));
+        assert_eq!(
+            columns(&query_info),
+            vec![("tag_name".to_string(), true), ("timestamp".to_string(), false)]
+        );
+        assert_eq!(query_info.sorting_mode(), "TIME_ASC");
+
+        assert!(parse(base).order_by.is_empty());
+        assert_eq!(parse(base).sorting_mode(), "TIME_ASC");
+    }
+
+    #[test]
+    fn test_date_trunc_units() {
+        use chrono::TimeZone;
+        // Thursday
+        let dt = Local.with_ymd_and_hms(2025, 3, 13, 14, 35, 27).unwrap() + Duration::milliseconds(123);
+        let truncate = |unit: &str| {
+            SqlHandler::truncate_timestamp(dt, unit)
+                .unwrap()
+                .format("%Y-%m-%d %H:%M:%S%.3f")
+                .to_string()
+        };
+        assert_eq!(truncate("second"), "2025-03-13 14:35:27.000");
+        assert_eq!(truncate("minute"), "2025-03-13 14:35:00.000");
+        assert_eq!(truncate("HOUR"), "2025-03-13 14:00:00.000");
+        assert_eq!(truncate("day"), "2025-03-13 00:00:00.000");
+        assert_eq!(truncate("week"), "2025-03-10 00:00:00.000");
+        assert_eq!(truncate("month"), "2025-03-01 00:00:00.000");
+        assert_eq!(truncate("year"), "2025-01-01 00:00:00.000");
+        assert!(SqlHandler::truncate_timestamp(dt, "fortnight").is_err());
+    }
+
+    #[test]
+    fn test_date_trunc_in_where_clause() {
+        let parse = |sql: &str| match SqlHandler::parse_query(sql) {
+            Ok(SqlResult::Query(query_info)) => query_info,
+            other => panic!("Unexpected parse result for '{}': {:?}", sql, other),
+        };
+
+        let query_info = parse("SELECT * FROM loggedtagvalues WHERE tag_name = 'A' AND timestamp >= DATE_TRUNC('hour', NOW())");
+        let (start, _) = query_info.get_timestamp_filter().unwrap();
+        let start = start.unwrap();
+        assert!(start.ends_with(":00:00.000"), "{}", start);
+
+        let query_info = parse(
+            "SELECT * FROM loggedtagvalues WHERE tag_name = 'A' \
+             AND timestamp >= DATE_TRUNC('day', '2025-03-13T14:35:27') - INTERVAL '1 day'",
+        );
+        let (start, _) = query_info.get_timestamp_filter().unwrap();
+        assert_eq!(start.unwrap(), "2025-03-12T00:00:00.000");
+
+        assert!(SqlHandler::parse_query(
+            "SELECT * FROM loggedtagvalues WHERE tag_name = 'A' AND timestamp >= DATE_TRUNC('decade', NOW())"
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_update_tagvalues_parses_into_tag_writes() {
+        let writes = |sql: &str| match SqlHandler::parse_query(sql).unwrap() {
+            SqlResult::WriteTagValues(writes) => writes,
+            other => panic!("Unexpected parse result {:?}", other),
+        };
+
+        assert_eq!(
+            writes("UPDATE tagvalues SET numeric_value = 21.5 WHERE tag_name = 'Setpoint:SP'"),
+            vec![TagWrite { tag_name: "Setpoint:SP".to_string(), value: serde_json::json!(21.5) }]
+        );
+        let negative = writes("UPDATE tagvalues SET numeric_value = -5 WHERE tag_name IN ('A', 'B')");
+        assert_eq!(negative.len(), 2);
+        assert_eq!(negative[1], TagWrite { tag_name: "B".to_string(), value: serde_json::json!(-5) });
+        assert_eq!(
+            writes("UPDATE tagvalues SET string_value = 'auto' WHERE tag_name = 'Mode'")[0].value,
+            serde_json::json!("auto")
+        );
+
+        for sql in [
+            "UPDATE tagvalues SET numeric_value = 1",
+            "UPDATE loggedtagvalues SET numeric_value = 1 WHERE tag_name = 'A'",
+            "UPDATE tagvalues SET numeric_value = 1, string_value = 'x' WHERE tag_name = 'A'",
+            "UPDATE tagvalues SET quality = 'GOOD' WHERE tag_name = 'A'",
+            "UPDATE tagvalues SET numeric_value = numeric_value + 1 WHERE tag_name = 'A'",
+            "UPDATE tagvalues SET numeric_value = 1 WHERE tag_name LIKE 'A%'",
+            "UPDATE tagvalues SET numeric_value = 1 WHERE tag_name = 'A' OR tag_name = 'B'",
+        ] {
+            assert!(SqlHandler::parse_query(sql).is_err(), "{} should be rejected", sql);
+        }
+    }
+
+    #[test]
+    fn test_update_activealarms_parses_into_acknowledgment() {
+        let instance_ids = |sql: &str| match SqlHandler::parse_query(sql).unwrap() {
+            SqlResult::AcknowledgeAlarms(instance_ids) => instance_ids,
+            other => panic!("Unexpected parse result {:?}", other),
+        };
+
+        assert_eq!(instance_ids("UPDATE activealarms SET state = 'ACKNOWLEDGED' WHERE instance_id = 12345"), vec![12345]);
+        assert_eq!(
+            instance_ids("UPDATE activealarms SET state = 'acknowledged' WHERE instance_id IN (1, 2, 3)"),
+            vec![1, 2, 3]
+        );
+
+        for sql in [
+            "UPDATE activealarms SET state = 'ACKNOWLEDGED'",
+            "UPDATE activealarms SET state = 'CLEARED' WHERE instance_id = 1",
+            "UPDATE activealarms SET priority = 1 WHERE instance_id = 1",
+            "UPDATE activealarms SET state = 'ACKNOWLEDGED' WHERE name = 'Alarm_1'",
+            "UPDATE activealarms SET state = 'ACKNOWLEDGED' WHERE instance_id > 1",
+            "UPDATE activealarms SET state = 'ACKNOWLEDGED' WHERE instance_id NOT IN (1)",
+            "UPDATE loggedalarms SET state = 'ACKNOWLEDGED' WHERE instance_id = 1",
+        ] {
+            assert!(SqlHandler::parse_query(sql).is_err(), "{} should be rejected", sql);
+        }
+    }
+
+    #[test]
+    fn test_copy_to_stdout_parses_into_query_and_options() {
+        let copy = |sql: &str| match SqlHandler::parse_query(sql).unwrap() {
+            SqlResult::CopyTo(copy) => copy,
+            other => panic!("Unexpected parse result {:?}", other),
+        };
+
+        let legacy = copy("COPY activealarms TO STDOUT WITH CSV HEADER");
+        assert_eq!(legacy.query, "SELECT * FROM activealarms");
+        assert_eq!(legacy.options.format, CopyFormat::Csv);
+        assert!(legacy.options.header);
+        assert_eq!(legacy.options.delimiter, ',');
+        assert_eq!(legacy.options.null, "\\N");
+
+        let columns = copy("COPY activealarms (name, priority) TO STDOUT");
+        assert_eq!(columns.query, "SELECT name, priority FROM activealarms");
+        assert_eq!(columns.options, CopyOptions::default());
+
+        let query = copy("COPY (SELECT tag_name FROM tagvalues WHERE tag_name = 'A') TO STDOUT WITH (FORMAT csv, DELIMITER ';', NULL '')");
+        assert_eq!(query.query, "SELECT tag_name FROM tagvalues WHERE tag_name = 'A'");
+        assert_eq!(query.options.delimiter, ';');
+        assert_eq!(query.options.null, "");
+        assert!(!query.options.header);
+
+        for sql in [
+            "COPY activealarms TO '/tmp/activealarms.csv'",
+            "COPY activealarms FROM STDIN",
+            "COPY activealarms TO STDOUT WITH (FORMAT binary)",
+            // The copied query is validated like a SELECT
+            "COPY tagvalues TO STDOUT WITH CSV HEADER",
+        ] {
+            assert!(SqlHandler::parse_query(sql).is_err(), "{} should be rejected", sql);
+        }
+    }
+
+    #[test]
+    fn test_cursor_statements_parse_into_commands() {
+        let cursor = |sql: &str| match SqlHandler::parse_query(sql).unwrap() {
+            SqlResult::Cursor(command) => command,
+            other => panic!("Unexpected parse result {:?}", other),
+        };
+        let fetch = |name: &str, count: Option<usize>| CursorCommand::Fetch { name: name.to_string(), count };
+
+        assert_eq!(
+            cursor("DECLARE C1 NO SCROLL CURSOR WITH HOLD FOR SELECT name FROM loggedalarms"),
+            CursorCommand::Declare { name: "c1".to_string(), query: "SELECT name FROM loggedalarms".to_string() }
+        );
+        assert_eq!(cursor("FETCH 100 FROM c1"), fetch("c1", Some(100)));
+        assert_eq!(cursor("FETCH NEXT IN \"Report\""), fetch("Report", Some(1)));
+        assert_eq!(cursor("FETCH FORWARD ALL FROM c1"), fetch("c1", None));
+        assert_eq!(cursor("CLOSE c1"), CursorCommand::Close { name: Some("c1".to_string()) });
+        assert_eq!(cursor("CLOSE ALL"), CursorCommand::Close { name: None });
+
+        for sql in [
+            "DECLARE c1 SCROLL CURSOR FOR SELECT name FROM loggedalarms",
+            "DECLARE c1 CURSOR FOR SELECT * FROM tagvalues",
+            "FETCH PRIOR FROM c1",
+            "FETCH BACKWARD 10 FROM c1",
+        ] {
+            assert!(SqlHandler::parse_query(sql).is_err(), "{} should be rejected", sql);
+        }
+    }
+
+    #[test]
+    fn test_or_tag_name_equalities_coalesce_into_in() {
+        let filters = |sql: &str| match SqlHandler::parse_query(sql).unwrap() {
+            SqlResult::Query(query_info) => query_info.filters,
+            other => panic!("Unexpected parse result {:?}", other),
+        };
+        let in_values = |filter: &ColumnFilter| match (&filter.operator, &filter.value) {
+            (FilterOperator::In, FilterValue::List(values)) => values.clone(),
+            other => panic!("Expected IN filter, got {:?}", other),
+        };
+
+        let or_filters = filters("SELECT * FROM tagvalues WHERE tag_name = 'A' OR tag_name = 'B'");
+        assert_eq!(or_filters.len(), 1);
+        assert_eq!(or_filters[0].column, "tag_name");
+        assert_eq!(in_values(&or_filters[0]), vec!["A", "B"]);
+
+        let nested = filters("SELECT * FROM tagvalues WHERE (tag_name = 'A' OR tag_name IN ('B', 'A')) OR tag_name = 'C'");
+        assert_eq!(nested.len(), 1);
+        assert_eq!(in_values(&nested[0]), vec!["A", "B", "C"]);
+
+        // AND keeps its filters apart; only the OR branches are merged
+        let mixed = filters("SELECT * FROM tagvalues WHERE (tag_name = 'A' OR tag_name = 'B') AND quality = 'GOOD'");
+        assert_eq!(mixed.len(), 2);
+        assert_eq!(in_values(&mixed[0]), vec!["A", "B"]);
+        assert!(matches!(mixed[1].operator, FilterOperator::Equal));
+
+        let query_info = match SqlHandler::parse_query("SELECT * FROM tagvalues WHERE tag_name = 'A' OR tag_name = 'B'").unwrap() {
+            SqlResult::Query(query_info) => query_info,
+            other => panic!("Unexpected parse result {:?}", other),
+        };
+        assert_eq!(query_info.get_tag_names(), vec!["A", "B"]);
+    }
+
+    #[test]
+    fn test_tag_name_literals_are_trimmed_and_case_folded() {
+        use crate::TagNameCaseFold;
+
+        let query_info = match SqlHandler::parse_query("SELECT * FROM tagvalues WHERE tag_name = '  motor:pv  '").unwrap() {
+            SqlResult::Query(query_info) => query_info,
+            other => panic!("Unexpected parse result {:?}", other),
+        };
+        assert_eq!(query_info.get_tag_names(), vec!["motor:pv"]);
+
+        let (sql, case_folds) =
+            SqlHandler::normalize_tag_names("SELECT * FROM tagvalues WHERE tag_name = '  motor:pv  '", TagNameCaseFold::None).unwrap();
+        assert_eq!(sql, "SELECT * FROM tagvalues WHERE tag_name = 'motor:pv'");
+        assert_eq!(case_folds, 0);
+
+        let (sql, case_folds) = SqlHandler::normalize_tag_names(
+            "SELECT * FROM tagvalues WHERE tag_name IN ('Motor:PV', 'pump:pv') AND string_value = 'On'",
+            TagNameCaseFold::Lower,
+        )
+        .unwrap();
+        assert_eq!(sql, "SELECT * FROM tagvalues WHERE tag_name IN ('motor:pv', 'pump:pv') AND string_value = 'On'");
+        assert_eq!(case_folds, 1);
+
+        let (sql, _) =
+            SqlHandler::normalize_tag_names("SELECT * FROM tagvalues t WHERE 'motor:pv' = t.tag_name", TagNameCaseFold::Upper).unwrap();
+        assert_eq!(sql, "SELECT * FROM tagvalues AS t WHERE 'MOTOR:PV' = t.tag_name");
+
+        // Queries without anything to normalize are passed on unchanged
+        assert!(SqlHandler::normalize_tag_names("SELECT * FROM tagvalues WHERE tag_name = 'motor:pv'", TagNameCaseFold::Lower).is_none());
+    }
+
+    #[test]
+    fn test_subqueries_and_unnest_in_from() {
+        let query_info = |sql: &str| match SqlHandler::parse_query(sql).unwrap() {
+            SqlResult::Query(query_info) => query_info,
+            other => panic!("Unexpected parse result {:?}", other),
+        };
+
+        // A subquery as main relation is fetched with its own filters
+        let derived = query_info("SELECT v.tag_name FROM (SELECT tag_name, numeric_value FROM tagvalues WHERE tag_name = 'Motor:PV') v");
+        assert_eq!(derived.table, VirtualTable::TagValues);
+        assert_eq!(derived.get_tag_names(), vec!["Motor:PV".to_string()]);
+        assert_eq!(derived.columns, vec!["tag_name"]);
+        assert!(derived.derived_tables.is_empty());
+        assert!(SqlHandler::parse_query("SELECT * FROM (SELECT * FROM tagvalues) v").is_err());
+
+        let unnest = query_info("SELECT * FROM UNNEST(ARRAY['Motor:PV', 'Pump:PV']) AS u(tag_name)");
+        assert_eq!(unnest.table, VirtualTable::FromLessQuery);
+        assert_eq!(unnest.columns, vec!["tag_name"]);
+
+        // The LIMIT of a LATERAL subquery applies per outer row
+        let sql = SqlHandler::rewrite_lateral_joins(
+            "SELECT t.tag_name, m.display_name FROM tagvalues t \
+             LEFT JOIN LATERAL (SELECT display_name FROM taglist l WHERE l.tag_name = t.tag_name ORDER BY display_name LIMIT 2) m ON true",
+        )
+        .unwrap();
+        assert_eq!(
+            sql,
+            "SELECT t.tag_name, m.display_name FROM tagvalues AS t LEFT JOIN (SELECT display_name, l.tag_name AS __lateral_key_0, \
+             ROW_NUMBER() OVER (PARTITION BY l.tag_name ORDER BY display_name) AS __lateral_row FROM taglist AS l) AS m \
+             ON m.__lateral_key_0 = t.tag_name AND m.__lateral_row <= 2"
+        );
+    }
+}
@@ -0,0 +1,126 @@
+use anyhow::{Context, Result};
+use chrono::Utc;
+use regex::Regex;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+use tokio::fs::{File, OpenOptions};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::Mutex;
+use tracing::{info, warn};
+
+// SET password = '...' / SET app.password TO secret
+static SET_PASSWORD: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"(?i)^(\s*SET\s+(?:SESSION\s+|LOCAL\s+)?[\w.]*password[\w.]*\s*(?:=|\bTO\b)\s*)('(?:[^']|'')*'|"(?:[^"]|"")*"|[^\s;]+)"#).unwrap()
+});
+// ALTER ROLE ... PASSWORD '...'
+static PASSWORD_LITERAL: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?i)(\bPASSWORD\s+)'(?:[^']|'')*'").unwrap());
+
+#[derive(Debug)]
+struct OpenFile {
+    file: File,
+    size: u64,
+}
+
+/// Append-only log of the received SQL for replay (--sql-log-file), one query per line:
+/// `timestamp\tconnection_id\tusername\tsql`. Passwords in SET statements are masked.
+#[derive(Debug)]
+pub struct SqlLog {
+    path: PathBuf,
+    rotate_size: u64, // 0 disables the rotation
+    file: Mutex<OpenFile>,
+}
+
+impl SqlLog {
+    pub async fn open(path: impl Into<PathBuf>, rotate_size: u64) -> Result<Self> {
+        let path = path.into();
+        let file = open_append(&path).await?;
+        info!("📝 Logging received SQL to {}", path.display());
+        Ok(Self {
+            path,
+            rotate_size,
+            file: Mutex::new(file),
+        })
+    }
+
+    /// Append a query; errors are logged, a failing log file never fails the query
+    pub async fn write(&self, connection_id: Option<u32>, username: &str, sql: &str) {
+        let line = format!(
+            "{}\t{}\t{}\t{}\n",
+            Utc::now().format("%Y-%m-%dT%H:%M:%S%.3fZ"),
+            connection_id.map(|id| id.to_string()).unwrap_or_default(),
+            username,
+            single_line(&mask_passwords(sql.trim()))
+        );
+        if let Err(e) = self.append(line.as_bytes()).await {
+            warn!("⚠️  Failed to write to SQL log {}: {}", self.path.display(), e);
+        }
+    }
+
+    async fn append(&self, line: &[u8]) -> Result<()> {
+        let mut open_file = self.file.lock().await;
+        if self.rotate_size > 0 && open_file.size > 0 && open_file.size + line.len() as u64 > self.rotate_size {
+            let rotated = PathBuf::from(format!("{}.1", self.path.display()));
+            open_file.file.flush().await?;
+            tokio::fs::rename(&self.path, &rotated).await?;
+            *open_file = open_append(&self.path).await?;
+            info!("📝 Rotated SQL log to {}", rotated.display());
+        }
+        open_file.file.write_all(line).await?;
+        open_file.file.flush().await?;
+        open_file.size += line.len() as u64;
+        Ok(())
+    }
+}
+
+async fn open_append(path: &Path) -> Result<OpenFile> {
+    let file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .await
+        .with_context(|| format!("Failed to open SQL log file {}", path.display()))?;
+    let size = file.metadata().await?.len();
+    Ok(OpenFile { file, size })
+}
+
+fn mask_passwords(sql: &str) -> String {
+    let sql = SET_PASSWORD.replace(sql, "${1}'***'");
+    PASSWORD_LITERAL.replace_all(&sql, "${1}'***'").into_owned()
+}
+
+// Line breaks and tabs would split the record
+fn single_line(sql: &str) -> String {
+    sql.replace("\r\n", " ").replace(['\n', '\r', '\t'], " ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_passwords_are_masked() {
+        assert_eq!(mask_passwords("SET password = 's3cret'"), "SET password = '***'");
+        assert_eq!(mask_passwords("set SESSION my.password TO hunter2;"), "set SESSION my.password TO '***';");
+        assert_eq!(mask_passwords("ALTER ROLE grafana PASSWORD 'it''s'"), "ALTER ROLE grafana PASSWORD '***'");
+        assert_eq!(mask_passwords("SET TimeZone = 'UTC'"), "SET TimeZone = 'UTC'");
+        assert_eq!(single_line("SELECT 1\r\nFROM\ttagvalues"), "SELECT 1 FROM tagvalues");
+    }
+
+    #[tokio::test]
+    async fn test_log_is_rotated_at_the_size_limit() {
+        let dir = std::env::temp_dir().join(format!("sql_log_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("queries.log");
+        let log = SqlLog::open(&path, 100).await.unwrap();
+        for index in 0..3 {
+            log.write(Some(7), "grafana", &format!("SELECT * FROM tagvalues WHERE tag_name = 'Tag_{}'", index)).await;
+        }
+
+        // Each line is about 90 bytes, so every write starts a new file
+        let current = std::fs::read_to_string(&path).unwrap();
+        let rotated = std::fs::read_to_string(dir.join("queries.log.1")).unwrap();
+        assert!(current.ends_with("\t7\tgrafana\tSELECT * FROM tagvalues WHERE tag_name = 'Tag_2'\n"), "{}", current);
+        assert!(rotated.contains("'Tag_1'") && !rotated.contains("'Tag_0'"), "{}", rotated);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,118 +1,456 @@
 use pgwire::api::Type;
 
+// Namespace OIDs as assigned by PostgreSQL
+pub const PG_CATALOG_NAMESPACE_OID: i32 = 11;
+pub const PUBLIC_NAMESPACE_OID: i32 = 2200;
+
+// Column of tagvalues and tagvaluechanges with the error of a tag, with --include-errors-column
+pub const TAG_ERROR_COLUMN: &str = "__error";
+// Column of tagvalues and tagvaluechanges with the result of --tag-name-autocomplete
+pub const TAG_WARNING_COLUMN: &str = "__warning";
+
+// Type OIDs of the WinCC table columns
+const BOOL_OID: u32 = 16;
+const INT8_OID: u32 = 20;
+const INT4_OID: u32 = 23;
+const TEXT_OID: u32 = 25;
+const JSON_OID: u32 = 114;
+const FLOAT8_OID: u32 = 701;
+const TIMESTAMP_OID: u32 = 1114;
+
+/// Column of a WinCC table as listed in information_schema.columns and pg_attribute
+#[derive(Debug)]
+pub struct ColumnMetadata {
+    pub name: &'static str,
+    pub pg_type_oid: u32,
+    pub is_nullable: bool,
+}
+
+impl ColumnMetadata {
+    const fn new(name: &'static str, pg_type_oid: u32) -> Self {
+        Self { name, pg_type_oid, is_nullable: true }
+    }
+
+    // Primary key columns, every row has them
+    const fn not_null(name: &'static str, pg_type_oid: u32) -> Self {
+        Self { name, pg_type_oid, is_nullable: false }
+    }
+
+    pub fn pg_type(&self) -> Type {
+        Type::from_oid(self.pg_type_oid).unwrap_or(Type::TEXT)
+    }
+}
+
+/// Name and columns of a WinCC table in the public schema
+#[derive(Debug)]
+pub struct TableMetadata {
+    pub table_name: &'static str,
+    pub columns: &'static [ColumnMetadata],
+}
+
+const TAG_VALUE_COLUMNS: &[ColumnMetadata] = &[
+    ColumnMetadata::not_null("tag_name", TEXT_OID),
+    ColumnMetadata::new("timestamp", TIMESTAMP_OID),
+    ColumnMetadata::new("timestamp_ms", INT8_OID),
+    ColumnMetadata::new("numeric_value", FLOAT8_OID),
+    ColumnMetadata::new("string_value", TEXT_OID),
+    ColumnMetadata::new("quality", TEXT_OID),
+    ColumnMetadata::new("quality_code", INT4_OID),
+    ColumnMetadata::new("quality_subcode", INT4_OID),
+];
+
+const TAG_VALUE_CHANGE_COLUMNS: &[ColumnMetadata] = &[
+    ColumnMetadata::not_null("tag_name", TEXT_OID),
+    ColumnMetadata::new("timestamp", TIMESTAMP_OID),
+    ColumnMetadata::new("timestamp_ms", INT8_OID),
+    ColumnMetadata::new("numeric_value", FLOAT8_OID),
+    ColumnMetadata::new("string_value", TEXT_OID),
+    ColumnMetadata::new("quality", TEXT_OID),
+    ColumnMetadata::new("quality_code", INT4_OID),
+    ColumnMetadata::new("quality_subcode", INT4_OID),
+    ColumnMetadata::new("force", BOOL_OID), // WHERE force = true returns all rows, not only changes
+];
+
+const LOGGED_TAG_VALUE_COLUMNS: &[ColumnMetadata] = &[
+    ColumnMetadata::not_null("tag_name", TEXT_OID),
+    ColumnMetadata::new("timestamp", TIMESTAMP_OID),
+    ColumnMetadata::new("timestamp_ms", INT8_OID),
+    ColumnMetadata::new("numeric_value", FLOAT8_OID),
+    ColumnMetadata::new("string_value", TEXT_OID),
+    ColumnMetadata::new("json_value", JSON_OID), // string_value as JSON, plain strings become JSON strings
+    ColumnMetadata::new("quality", TEXT_OID),
+    ColumnMetadata::new("quality_code", INT4_OID),    // 0x00 GOOD, 0x40 UNCERTAIN, 0x80 BAD
+    ColumnMetadata::new("quality_subcode", INT4_OID), // OPC UA SubCode of the sub-status
+];
+
+const LOGGED_TAG_VALUE_INCREMENTAL_COLUMNS: &[ColumnMetadata] = &[
+    ColumnMetadata::not_null("tag_name", TEXT_OID),
+    ColumnMetadata::new("timestamp", TIMESTAMP_OID),
+    ColumnMetadata::new("timestamp_ms", INT8_OID),
+    ColumnMetadata::new("numeric_value", FLOAT8_OID),
+    ColumnMetadata::new("string_value", TEXT_OID),
+    ColumnMetadata::new("json_value", JSON_OID),
+    ColumnMetadata::new("quality", TEXT_OID),
+    ColumnMetadata::new("quality_code", INT4_OID),
+    ColumnMetadata::new("quality_subcode", INT4_OID),
+    ColumnMetadata::new("cursor", TIMESTAMP_OID), // Start of the request, the newest timestamp this connection has seen
+];
+
+const LOGGED_TAG_VALUE_RESAMPLED_COLUMNS: &[ColumnMetadata] = &[
+    ColumnMetadata::not_null("tag_name", TEXT_OID),
+    ColumnMetadata::new("bucket_start", TIMESTAMP_OID), // Start of the interval_seconds wide bucket
+    ColumnMetadata::new("value", FLOAT8_OID),           // Numeric values of the bucket aggregated with the method
+    ColumnMetadata::new("sample_count", INT8_OID),
+];
+
+const LOGGED_TAG_VALUE_SUMMARY_COLUMNS: &[ColumnMetadata] = &[
+    ColumnMetadata::not_null("tag_name", TEXT_OID),
+    ColumnMetadata::not_null("window_start", TIMESTAMP_OID),
+    ColumnMetadata::not_null("window_end", TIMESTAMP_OID),
+    ColumnMetadata::new("min_value", FLOAT8_OID),
+    ColumnMetadata::new("max_value", FLOAT8_OID),
+    ColumnMetadata::new("avg_value", FLOAT8_OID),
+    ColumnMetadata::not_null("sample_count", INT8_OID),
+    ColumnMetadata::not_null("window_size", TEXT_OID), // WHERE window_size = '1 day' sets the width of the windows
+];
+
+const ACTIVE_ALARM_COLUMNS: &[ColumnMetadata] = &[
+    ColumnMetadata::not_null("name", TEXT_OID),
+    ColumnMetadata::not_null("instance_id", INT4_OID),
+    ColumnMetadata::new("alarm_group_id", INT4_OID),
+    ColumnMetadata::new("raise_time", TIMESTAMP_OID),
+    ColumnMetadata::new("acknowledgment_time", TIMESTAMP_OID),
+    ColumnMetadata::new("clear_time", TIMESTAMP_OID),
+    ColumnMetadata::new("reset_time", TIMESTAMP_OID),
+    ColumnMetadata::new("modification_time", TIMESTAMP_OID),
+    ColumnMetadata::new("state", TEXT_OID),
+    ColumnMetadata::new("priority", INT4_OID),
+    ColumnMetadata::new("event_text", TEXT_OID),
+    ColumnMetadata::new("info_text", TEXT_OID),
+    ColumnMetadata::new("origin", TEXT_OID),
+    ColumnMetadata::new("area", TEXT_OID),
+    ColumnMetadata::new("value", TEXT_OID),
+    ColumnMetadata::new("host_name", TEXT_OID),
+    ColumnMetadata::new("user_name", TEXT_OID),
+];
+
+const ACTIVE_ALARM_CHANGE_COLUMNS: &[ColumnMetadata] = &[
+    ColumnMetadata::not_null("name", TEXT_OID),
+    ColumnMetadata::not_null("instance_id", INT4_OID),
+    ColumnMetadata::new("alarm_group_id", INT4_OID),
+    ColumnMetadata::new("raise_time", TIMESTAMP_OID),
+    ColumnMetadata::new("acknowledgment_time", TIMESTAMP_OID),
+    ColumnMetadata::new("clear_time", TIMESTAMP_OID),
+    ColumnMetadata::new("reset_time", TIMESTAMP_OID),
+    ColumnMetadata::new("modification_time", TIMESTAMP_OID),
+    ColumnMetadata::new("state", TEXT_OID),
+    ColumnMetadata::new("priority", INT4_OID),
+    ColumnMetadata::new("event_text", TEXT_OID),
+    ColumnMetadata::new("info_text", TEXT_OID),
+    ColumnMetadata::new("origin", TEXT_OID),
+    ColumnMetadata::new("area", TEXT_OID),
+    ColumnMetadata::new("value", TEXT_OID),
+    ColumnMetadata::new("host_name", TEXT_OID),
+    ColumnMetadata::new("user_name", TEXT_OID),
+    ColumnMetadata::not_null("change_type", TEXT_OID), // ADDED, REMOVED or CHANGED since the previous query
+];
+
+const LOGGED_ALARM_COLUMNS: &[ColumnMetadata] = &[
+    ColumnMetadata::not_null("name", TEXT_OID),
+    ColumnMetadata::not_null("instance_id", INT4_OID),
+    ColumnMetadata::new("alarm_group_id", INT4_OID),
+    ColumnMetadata::new("raise_time", TIMESTAMP_OID),
+    ColumnMetadata::new("acknowledgment_time", TIMESTAMP_OID),
+    ColumnMetadata::new("clear_time", TIMESTAMP_OID),
+    ColumnMetadata::new("reset_time", TIMESTAMP_OID),
+    ColumnMetadata::new("modification_time", TIMESTAMP_OID),
+    ColumnMetadata::new("state", TEXT_OID),
+    ColumnMetadata::new("priority", INT4_OID),
+    ColumnMetadata::new("event_text", TEXT_OID),
+    ColumnMetadata::new("info_text", TEXT_OID),
+    ColumnMetadata::new("origin", TEXT_OID),
+    ColumnMetadata::new("area", TEXT_OID),
+    ColumnMetadata::new("value", TEXT_OID),
+    ColumnMetadata::new("host_name", TEXT_OID),
+    ColumnMetadata::new("user_name", TEXT_OID),
+    ColumnMetadata::new("duration", TEXT_OID),
+    ColumnMetadata::new("duration_seconds", INT8_OID),
+];
+
+const ALARM_STATISTICS_COLUMNS: &[ColumnMetadata] = &[
+    ColumnMetadata::new("state", TEXT_OID),
+    ColumnMetadata::new("priority", INT8_OID),
+    ColumnMetadata::new("area", TEXT_OID),
+    ColumnMetadata::new("alarm_count", INT8_OID),
+    ColumnMetadata::new("oldest_raise_time", TIMESTAMP_OID),
+    ColumnMetadata::new("newest_raise_time", TIMESTAMP_OID),
+    ColumnMetadata::new("avg_duration_seconds", FLOAT8_OID),
+];
+
+static TAG_VALUES_METADATA: TableMetadata = TableMetadata { table_name: "tagvalues", columns: TAG_VALUE_COLUMNS };
+static TAG_VALUE_CHANGES_METADATA: TableMetadata = TableMetadata { table_name: "tagvaluechanges", columns: TAG_VALUE_CHANGE_COLUMNS };
+static LOGGED_TAG_VALUES_METADATA: TableMetadata = TableMetadata { table_name: "loggedtagvalues", columns: LOGGED_TAG_VALUE_COLUMNS };
+static LOGGED_TAG_VALUES_INCREMENTAL_METADATA: TableMetadata = TableMetadata {
+    table_name: "loggedtagvalues_incr",
+    columns: LOGGED_TAG_VALUE_INCREMENTAL_COLUMNS,
+};
+static LOGGED_TAG_VALUES_RESAMPLED_METADATA: TableMetadata = TableMetadata {
+    table_name: "loggedtagvalues_resampled",
+    columns: LOGGED_TAG_VALUE_RESAMPLED_COLUMNS,
+};
+static LOGGED_TAG_VALUES_SUMMARY_METADATA: TableMetadata = TableMetadata {
+    table_name: "loggedtagvalues_summary",
+    columns: LOGGED_TAG_VALUE_SUMMARY_COLUMNS,
+};
+static ACTIVE_ALARMS_METADATA: TableMetadata = TableMetadata { table_name: "activealarms", columns: ACTIVE_ALARM_COLUMNS };
+static ACTIVE_ALARM_CHANGES_METADATA: TableMetadata = TableMetadata {
+    table_name: "activealarms_changes",
+    columns: ACTIVE_ALARM_CHANGE_COLUMNS,
+};
+static LOGGED_ALARMS_METADATA: TableMetadata = TableMetadata { table_name: "loggedalarms", columns: LOGGED_ALARM_COLUMNS };
+static TAG_LIST_METADATA: TableMetadata = TableMetadata {
+    table_name: "taglist",
+    columns: &[
+        ColumnMetadata::not_null("tag_name", TEXT_OID),
+        ColumnMetadata::new("display_name", TEXT_OID),
+        ColumnMetadata::new("object_type", TEXT_OID),
+        ColumnMetadata::new("data_type", TEXT_OID),
+    ],
+};
+static TAG_HIERARCHY_METADATA: TableMetadata = TableMetadata {
+    table_name: "tag_hierarchy",
+    columns: &[
+        ColumnMetadata::not_null("node_id", TEXT_OID),
+        ColumnMetadata::new("parent_id", TEXT_OID),
+        ColumnMetadata::new("display_name", TEXT_OID),
+        ColumnMetadata::new("node_class", TEXT_OID),
+        ColumnMetadata::new("data_type", TEXT_OID),
+    ],
+};
+static TAG_STATISTICS_METADATA: TableMetadata = TableMetadata {
+    table_name: "tag_statistics",
+    columns: &[
+        ColumnMetadata::not_null("tag_name", TEXT_OID),
+        ColumnMetadata::new("sample_count", INT8_OID),
+        ColumnMetadata::new("min_value", FLOAT8_OID),
+        ColumnMetadata::new("max_value", FLOAT8_OID),
+        ColumnMetadata::new("avg_value", FLOAT8_OID),
+        ColumnMetadata::new("last_value", FLOAT8_OID),
+        ColumnMetadata::new("last_timestamp", TIMESTAMP_OID),
+        ColumnMetadata::new("last_quality", TEXT_OID),
+        ColumnMetadata::new("lookback", TEXT_OID), // WHERE lookback = '1 hour' sets the statistics window
+    ],
+};
+static ALARM_STATISTICS_METADATA: TableMetadata = TableMetadata { table_name: "alarm_statistics", columns: ALARM_STATISTICS_COLUMNS };
+static LOGGED_ALARM_STATISTICS_METADATA: TableMetadata = TableMetadata {
+    table_name: "logged_alarm_statistics",
+    columns: ALARM_STATISTICS_COLUMNS,
+};
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum VirtualTable {
     TagValues,
+    TagValueChanges,
     LoggedTagValues,
+    LoggedTagValuesIncremental,
+    LoggedTagValuesResampled, // Table function loggedtagvalues_resampled(tag_name, start_time, end_time, interval_seconds, method)
+    LoggedTagValuesSummary,
     ActiveAlarms,
+    ActiveAlarmsChanges,
     LoggedAlarms,
     TagList,
+    TagHierarchy,
+    TagStatistics,
+    AlarmStatistics,
+    LoggedAlarmStatistics,
+    WinccuaFunctions,
+    CacheStats,
+    ConnectionStats,
+    WinccuaConfig,
+    WinccuaVersion,
     InformationSchemaTables,
     InformationSchemaColumns,
+    InformationSchemaKeyColumnUsage,
+    InformationSchemaTableConstraints,
     PgStatActivity,
+    PgClass,
+    PgNamespace,
+    PgAttribute,
+    PgProc,
+    PgType,
+    PgIndex,
+    PgDatabase,
+    PgStatDatabase,
+    PgRoles,
+    PgUser,
     FromLessQuery, // For queries without FROM clause like SELECT 1, SELECT VERSION(), etc.
 }
 
-impl ToString for VirtualTable {
-    fn to_string(&self) -> String {
-        match self {
-            VirtualTable::TagValues => "tagvalues".to_string(),
-            VirtualTable::LoggedTagValues => "loggedtagvalues".to_string(),
-            VirtualTable::ActiveAlarms => "activealarms".to_string(),
-            VirtualTable::LoggedAlarms => "loggedalarms".to_string(),
-            VirtualTable::TagList => "taglist".to_string(),
-            VirtualTable::InformationSchemaTables => "information_schema.tables".to_string(),
-            VirtualTable::InformationSchemaColumns => "information_schema.columns".to_string(),
-            VirtualTable::PgStatActivity => "pg_stat_activity".to_string(),
-            VirtualTable::FromLessQuery => "dual".to_string(), // Use Oracle-style "dual" table name
-        }
+impl std::fmt::Display for VirtualTable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            VirtualTable::TagValues => "tagvalues",
+            VirtualTable::TagValueChanges => "tagvaluechanges",
+            VirtualTable::LoggedTagValues => "loggedtagvalues",
+            VirtualTable::LoggedTagValuesIncremental => "loggedtagvalues_incr",
+            VirtualTable::LoggedTagValuesResampled => "loggedtagvalues_resampled",
+            VirtualTable::LoggedTagValuesSummary => "loggedtagvalues_summary",
+            VirtualTable::ActiveAlarms => "activealarms",
+            VirtualTable::ActiveAlarmsChanges => "activealarms_changes",
+            VirtualTable::LoggedAlarms => "loggedalarms",
+            VirtualTable::TagList => "taglist",
+            VirtualTable::TagHierarchy => "tag_hierarchy",
+            VirtualTable::TagStatistics => "tag_statistics",
+            VirtualTable::AlarmStatistics => "alarm_statistics",
+            VirtualTable::LoggedAlarmStatistics => "logged_alarm_statistics",
+            VirtualTable::WinccuaFunctions => "winccua_functions",
+            VirtualTable::CacheStats => "winccua_cache_stats",
+            VirtualTable::ConnectionStats => "winccua_connection_stats",
+            VirtualTable::WinccuaConfig => "winccua_config",
+            VirtualTable::WinccuaVersion => "winccua_version",
+            VirtualTable::InformationSchemaTables => "information_schema.tables",
+            VirtualTable::InformationSchemaColumns => "information_schema.columns",
+            VirtualTable::InformationSchemaKeyColumnUsage => "information_schema.key_column_usage",
+            VirtualTable::InformationSchemaTableConstraints => "information_schema.table_constraints",
+            VirtualTable::PgStatActivity => "pg_stat_activity",
+            VirtualTable::PgClass => "pg_catalog.pg_class",
+            VirtualTable::PgNamespace => "pg_catalog.pg_namespace",
+            VirtualTable::PgAttribute => "pg_catalog.pg_attribute",
+            VirtualTable::PgProc => "pg_catalog.pg_proc",
+            VirtualTable::PgType => "pg_catalog.pg_type",
+            VirtualTable::PgIndex => "pg_catalog.pg_index",
+            VirtualTable::PgDatabase => "pg_catalog.pg_database",
+            VirtualTable::PgStatDatabase => "pg_catalog.pg_stat_database",
+            VirtualTable::PgRoles => "pg_catalog.pg_roles",
+            VirtualTable::PgUser => "pg_catalog.pg_user",
+            VirtualTable::FromLessQuery => "dual", // Use Oracle-style "dual" table name
+        };
+        write!(f, "{}", name)
     }
 }
 
 impl VirtualTable {
     pub fn from_name(name: &str) -> Option<Self> {
         let lower_name = name.to_lowercase();
-        if lower_name.starts_with("information_schema.") {
+        if let Some(catalog_name) = lower_name.strip_prefix("pg_catalog.") {
+            match catalog_name {
+                "pg_class" => Some(Self::PgClass),
+                "pg_namespace" => Some(Self::PgNamespace),
+                "pg_attribute" => Some(Self::PgAttribute),
+                "pg_proc" => Some(Self::PgProc),
+                "pg_type" => Some(Self::PgType),
+                "pg_index" => Some(Self::PgIndex),
+                "pg_database" => Some(Self::PgDatabase),
+                "pg_stat_database" => Some(Self::PgStatDatabase),
+                "pg_roles" => Some(Self::PgRoles),
+                "pg_user" => Some(Self::PgUser),
+                _ => None,
+            }
+        } else if lower_name.starts_with("information_schema.") {
             match lower_name.strip_prefix("information_schema.") {
                 Some("tables") => Some(Self::InformationSchemaTables),
                 Some("columns") => Some(Self::InformationSchemaColumns),
+                Some("key_column_usage") => Some(Self::InformationSchemaKeyColumnUsage),
+                Some("table_constraints") => Some(Self::InformationSchemaTableConstraints),
                 _ => None,
             }
         } else {
             match lower_name.as_str() {
                 "tagvalues" => Some(Self::TagValues),
+                "tagvaluechanges" => Some(Self::TagValueChanges),
                 "loggedtagvalues" => Some(Self::LoggedTagValues),
+                "loggedtagvalues_incr" => Some(Self::LoggedTagValuesIncremental),
+                "loggedtagvalues_resampled" => Some(Self::LoggedTagValuesResampled),
+                "loggedtagvalues_summary" => Some(Self::LoggedTagValuesSummary),
                 "activealarms" => Some(Self::ActiveAlarms),
+                "activealarms_changes" => Some(Self::ActiveAlarmsChanges),
                 "loggedalarms" => Some(Self::LoggedAlarms),
                 "taglist" => Some(Self::TagList),
+                "tag_hierarchy" | "winccua.tag_hierarchy" => Some(Self::TagHierarchy),
+                "tag_statistics" => Some(Self::TagStatistics),
+                "alarm_statistics" => Some(Self::AlarmStatistics),
+                "logged_alarm_statistics" => Some(Self::LoggedAlarmStatistics),
+                "winccua_functions" => Some(Self::WinccuaFunctions),
+                "winccua_cache_stats" => Some(Self::CacheStats),
+                "winccua_connection_stats" => Some(Self::ConnectionStats),
+                "winccua_config" => Some(Self::WinccuaConfig),
+                "winccua_version" => Some(Self::WinccuaVersion),
                 "pg_stat_activity" => Some(Self::PgStatActivity),
+                "pg_class" => Some(Self::PgClass),
+                "pg_namespace" => Some(Self::PgNamespace),
+                "pg_attribute" => Some(Self::PgAttribute),
+                "pg_proc" => Some(Self::PgProc),
+                "pg_type" => Some(Self::PgType),
+                "pg_index" => Some(Self::PgIndex),
+                "pg_database" => Some(Self::PgDatabase),
+                "pg_stat_database" => Some(Self::PgStatDatabase),
+                "pg_roles" => Some(Self::PgRoles),
+                "pg_user" => Some(Self::PgUser),
+                "key_column_usage" => Some(Self::InformationSchemaKeyColumnUsage),
+                "table_constraints" => Some(Self::InformationSchemaTableConstraints),
                 _ => None,
             }
         }
     }
 
     pub fn get_schema(&self) -> Vec<(&'static str, Type)> {
+        let mut schema = match self.metadata() {
+            Some(metadata) => metadata.columns.iter().map(|column| (column.name, column.pg_type())).collect(),
+            None => self.system_table_schema(),
+        };
+        // --include-errors-column: error of tags GraphQL could not read, after the value columns
+        if matches!(self, Self::TagValues | Self::TagValueChanges) && crate::INCLUDE_ERRORS_COLUMN.load(std::sync::atomic::Ordering::Relaxed) {
+            let position = schema.iter().position(|(name, _)| *name == "force").unwrap_or(schema.len());
+            schema.insert(position, (TAG_ERROR_COLUMN, Type::TEXT));
+        }
+        if matches!(self, Self::TagValues | Self::TagValueChanges) && crate::TAG_NAME_AUTOCOMPLETE.load(std::sync::atomic::Ordering::Relaxed) {
+            let position = schema.iter().position(|(name, _)| *name == "force").unwrap_or(schema.len());
+            schema.insert(position, (TAG_WARNING_COLUMN, Type::TEXT));
+        }
+        schema
+    }
+
+    // Columns of the catalog and system tables
+    fn system_table_schema(&self) -> Vec<(&'static str, Type)> {
         match self {
-            Self::TagValues => vec![
-                ("tag_name", Type::TEXT),
-                ("timestamp", Type::TIMESTAMP),
-                ("timestamp_ms", Type::INT8),
-                ("numeric_value", Type::NUMERIC),
-                ("string_value", Type::TEXT),
-                ("quality", Type::TEXT),
+            Self::WinccuaFunctions => vec![
+                ("routine_catalog", Type::TEXT),
+                ("routine_schema", Type::TEXT),
+                ("routine_name", Type::TEXT),
+                ("routine_type", Type::TEXT),
+                ("parameter_types", Type::TEXT),
+                ("data_type", Type::TEXT),
+                ("description", Type::TEXT),
             ],
-            Self::LoggedTagValues => vec![
-                ("tag_name", Type::TEXT),
-                ("timestamp", Type::TIMESTAMP),
-                ("timestamp_ms", Type::INT8),
-                ("numeric_value", Type::NUMERIC),
-                ("string_value", Type::TEXT),
-                ("quality", Type::TEXT),
+            Self::CacheStats => vec![
+                ("hit_count", Type::INT8),
+                ("miss_count", Type::INT8),
+                ("eviction_count", Type::INT8),
+                ("entry_count", Type::INT8),
             ],
-            Self::ActiveAlarms => vec![
-                ("name", Type::TEXT),
-                ("instance_id", Type::INT4),
-                ("alarm_group_id", Type::INT4),
-                ("raise_time", Type::TIMESTAMP),
-                ("acknowledgment_time", Type::TIMESTAMP),
-                ("clear_time", Type::TIMESTAMP),
-                ("reset_time", Type::TIMESTAMP),
-                ("modification_time", Type::TIMESTAMP),
-                ("state", Type::TEXT),
-                ("priority", Type::INT4),
-                ("event_text", Type::TEXT),
-                ("info_text", Type::TEXT),
-                ("origin", Type::TEXT),
-                ("area", Type::TEXT),
-                ("value", Type::TEXT),
-                ("host_name", Type::TEXT),
-                ("user_name", Type::TEXT),
+            Self::ConnectionStats => vec![
+                ("connection_id", Type::INT8),
+                ("username", Type::TEXT),
+                ("connected_since", Type::TIMESTAMP),
+                ("total_queries", Type::INT8),
+                ("total_duration_ms", Type::INT8),
+                ("avg_duration_ms", Type::FLOAT8),
+                ("max_duration_ms", Type::INT8),
+                ("graphql_errors", Type::INT8),
+                ("cache_hits", Type::INT8),
+                ("slow_queries", Type::INT8),
             ],
-            Self::LoggedAlarms => vec![
+            Self::WinccuaConfig => vec![
                 ("name", Type::TEXT),
-                ("instance_id", Type::INT4),
-                ("alarm_group_id", Type::INT4),
-                ("raise_time", Type::TIMESTAMP),
-                ("acknowledgment_time", Type::TIMESTAMP),
-                ("clear_time", Type::TIMESTAMP),
-                ("reset_time", Type::TIMESTAMP),
-                ("modification_time", Type::TIMESTAMP),
-                ("state", Type::TEXT),
-                ("priority", Type::INT4),
-                ("event_text", Type::TEXT),
-                ("info_text", Type::TEXT),
-                ("origin", Type::TEXT),
-                ("area", Type::TEXT),
                 ("value", Type::TEXT),
-                ("host_name", Type::TEXT),
-                ("user_name", Type::TEXT),
-                ("duration", Type::TEXT),
+                ("type", Type::TEXT),
+                ("description", Type::TEXT),
             ],
-            Self::TagList => vec![
-                ("tag_name", Type::TEXT),
-                ("display_name", Type::TEXT),
-                ("object_type", Type::TEXT),
-                ("data_type", Type::TEXT),
+            Self::WinccuaVersion => vec![
+                ("server_version", Type::TEXT),
+                ("build_timestamp", Type::TEXT),
+                ("graphql_url", Type::TEXT),
+                ("uptime_seconds", Type::INT8),
             ],
             Self::InformationSchemaTables => vec![
                 ("table_catalog", Type::TEXT),
@@ -174,10 +512,34 @@ impl VirtualTable {
                 ("generation_expression", Type::TEXT),
                 ("is_updatable", Type::TEXT),
             ],
+            Self::InformationSchemaKeyColumnUsage => vec![
+                ("constraint_catalog", Type::TEXT),
+                ("constraint_schema", Type::TEXT),
+                ("constraint_name", Type::TEXT),
+                ("table_catalog", Type::TEXT),
+                ("table_schema", Type::TEXT),
+                ("table_name", Type::TEXT),
+                ("column_name", Type::TEXT),
+                ("ordinal_position", Type::INT4),
+                ("position_in_unique_constraint", Type::INT4),
+            ],
+            Self::InformationSchemaTableConstraints => vec![
+                ("constraint_catalog", Type::TEXT),
+                ("constraint_schema", Type::TEXT),
+                ("constraint_name", Type::TEXT),
+                ("table_catalog", Type::TEXT),
+                ("table_schema", Type::TEXT),
+                ("table_name", Type::TEXT),
+                ("constraint_type", Type::TEXT),
+                ("is_deferrable", Type::TEXT),
+                ("initially_deferred", Type::TEXT),
+                ("enforced", Type::TEXT),
+            ],
             Self::PgStatActivity => vec![
                 ("datid", Type::INT4),           // OID of database (always 0 for now)
                 ("datname", Type::TEXT),         // Database name
                 ("pid", Type::INT4),             // Process ID (connection ID)
+                ("leader_pid", Type::INT4),      // Parallel group leader, always NULL
                 ("usename", Type::TEXT),         // Username
                 ("application_name", Type::TEXT), // Client application name
                 ("client_addr", Type::TEXT),     // Client IP address
@@ -186,17 +548,309 @@ impl VirtualTable {
                 ("backend_start", Type::TIMESTAMP), // Connection start time
                 ("query_start", Type::TIMESTAMP),   // Query start time
                 ("query_stop", Type::TIMESTAMP),    // Query completion time
+                ("wait_event_type", Type::TEXT), // Client while idle, Activity while waiting for GraphQL
+                ("wait_event", Type::TEXT),      // ClientRead or DataFileRead
                 ("state", Type::TEXT),           // Connection state
+                ("query_id", Type::INT8),        // Hash of the current/last query
                 ("query", Type::TEXT),           // Current/last query
+                ("backend_type", Type::TEXT),    // Always "client backend"
                 ("graphql_time", Type::INT8),    // GraphQL execution time in ms
                 ("datafusion_time", Type::INT8), // DataFusion execution time in ms
                 ("overall_time", Type::INT8),    // Overall query execution time in ms
+                ("retry_count", Type::INT4),     // GraphQL requests retried by the last query
                 ("last_alive_sent", Type::TIMESTAMP), // Last time keep-alive was sent
             ],
+            Self::PgClass => vec![
+                ("oid", Type::INT4),
+                ("relname", Type::TEXT),
+                ("relnamespace", Type::INT4),
+                ("relkind", Type::CHAR),
+                ("reltype", Type::INT4),
+                ("relam", Type::INT4),
+                ("relpages", Type::INT4),
+                ("reltuples", Type::FLOAT4),
+                ("relhasindex", Type::BOOL),
+                ("relisshared", Type::BOOL),
+            ],
+            Self::PgNamespace => vec![
+                ("oid", Type::INT4),
+                ("nspname", Type::TEXT),
+                ("nspowner", Type::INT4),
+            ],
+            Self::PgAttribute => vec![
+                ("attrelid", Type::INT4),
+                ("attname", Type::TEXT),
+                ("atttypid", Type::INT4),
+                ("attlen", Type::INT2),
+                ("attnum", Type::INT2),
+                ("attnotnull", Type::BOOL),
+                ("atthasdef", Type::BOOL),
+                ("attisdropped", Type::BOOL),
+            ],
+            Self::PgProc => vec![
+                ("oid", Type::INT4),
+                ("proname", Type::TEXT),
+                ("pronamespace", Type::INT4),
+                ("prorettype", Type::INT4),
+                ("proargtypes", Type::TEXT), // Space-separated argument type OIDs, like oidvector
+                ("pronargs", Type::INT2),
+                ("provolatile", Type::CHAR),
+                ("prokind", Type::CHAR),
+                ("prosrc", Type::TEXT),
+            ],
+            Self::PgType => vec![
+                ("oid", Type::INT4),
+                ("typname", Type::TEXT),
+                ("typnamespace", Type::INT4),
+                ("typowner", Type::INT4),
+                ("typlen", Type::INT2),
+                ("typbyval", Type::BOOL),
+                ("typtype", Type::CHAR),
+                ("typcategory", Type::CHAR),
+                ("typrelid", Type::INT4),
+                ("typelem", Type::INT4),
+                ("typarray", Type::INT4),
+                ("typbasetype", Type::INT4),
+                ("typnotnull", Type::BOOL),
+                ("typtypmod", Type::INT4),
+            ],
+            Self::PgIndex => vec![
+                ("indexrelid", Type::INT4),
+                ("indrelid", Type::INT4),
+                ("indnatts", Type::INT2),
+                ("indisunique", Type::BOOL),
+                ("indisprimary", Type::BOOL),
+                ("indisexclusion", Type::BOOL),
+                ("indimmediate", Type::BOOL),
+                ("indisclustered", Type::BOOL),
+                ("indisvalid", Type::BOOL),
+                ("indkey", Type::TEXT), // Space-separated attnums, like int2vector
+                ("indcollation", Type::TEXT),
+                ("indclass", Type::TEXT),
+                ("indoption", Type::TEXT),
+                ("indexprs", Type::TEXT),
+                ("indpred", Type::TEXT),
+            ],
+            Self::PgDatabase => vec![
+                ("oid", Type::INT4),
+                ("datname", Type::TEXT),
+                ("datdba", Type::INT4),
+                ("encoding", Type::INT4),
+                ("datcollate", Type::TEXT),
+                ("datctype", Type::TEXT),
+                ("datistemplate", Type::BOOL),
+                ("datallowconn", Type::BOOL),
+                ("datconnlimit", Type::INT4),
+            ],
+            Self::PgStatDatabase => vec![
+                ("datid", Type::INT4),
+                ("datname", Type::TEXT),
+                ("numbackends", Type::INT4),
+                ("xact_commit", Type::INT8),
+                ("xact_rollback", Type::INT8),
+                ("blks_read", Type::INT8),
+                ("blks_hit", Type::INT8),
+            ],
+            Self::PgRoles => vec![
+                ("oid", Type::INT4),
+                ("rolname", Type::TEXT),
+                ("rolsuper", Type::BOOL),
+                ("rolinherit", Type::BOOL),
+                ("rolcreaterole", Type::BOOL),
+                ("rolcreatedb", Type::BOOL),
+                ("rolcanlogin", Type::BOOL),
+                ("rolreplication", Type::BOOL),
+                ("rolconnlimit", Type::INT4),
+                ("rolvaliduntil", Type::TIMESTAMP),
+                ("rolbypassrls", Type::BOOL),
+            ],
+            Self::PgUser => vec![
+                ("usename", Type::TEXT),
+                ("usesysid", Type::INT4),
+                ("usecreatedb", Type::BOOL),
+                ("usesuper", Type::BOOL),
+                ("userepl", Type::BOOL),
+                ("usebypassrls", Type::BOOL),
+                ("passwd", Type::TEXT),
+                ("valuntil", Type::TIMESTAMP),
+            ],
             Self::FromLessQuery => vec![
                 // Empty schema - FROM-less queries don't have predefined columns
                 // The actual columns will be determined by the SELECT expressions
             ],
+            // WinCC tables, see metadata()
+            _ => Vec::new(),
+        }
+    }
+
+    /// WinCC tables of the public schema, in the order of their relation OIDs
+    pub fn public_tables() -> Vec<Self> {
+        vec![
+            Self::TagValues,
+            Self::LoggedTagValues,
+            Self::ActiveAlarms,
+            Self::LoggedAlarms,
+            Self::TagList,
+            Self::TagHierarchy,
+            Self::TagValueChanges,
+            Self::TagStatistics,
+            Self::AlarmStatistics,
+            Self::LoggedAlarmStatistics,
+            Self::LoggedTagValuesIncremental,
+            Self::ActiveAlarmsChanges,
+            Self::LoggedTagValuesSummary,
+        ]
+    }
+
+    /// Name and columns of the WinCC tables, None for catalog and system tables
+    pub fn metadata(&self) -> Option<&'static TableMetadata> {
+        match self {
+            Self::TagValues => Some(&TAG_VALUES_METADATA),
+            Self::TagValueChanges => Some(&TAG_VALUE_CHANGES_METADATA),
+            Self::LoggedTagValues => Some(&LOGGED_TAG_VALUES_METADATA),
+            Self::LoggedTagValuesIncremental => Some(&LOGGED_TAG_VALUES_INCREMENTAL_METADATA),
+            Self::LoggedTagValuesResampled => Some(&LOGGED_TAG_VALUES_RESAMPLED_METADATA),
+            Self::LoggedTagValuesSummary => Some(&LOGGED_TAG_VALUES_SUMMARY_METADATA),
+            Self::ActiveAlarms => Some(&ACTIVE_ALARMS_METADATA),
+            Self::ActiveAlarmsChanges => Some(&ACTIVE_ALARM_CHANGES_METADATA),
+            Self::LoggedAlarms => Some(&LOGGED_ALARMS_METADATA),
+            Self::TagList => Some(&TAG_LIST_METADATA),
+            Self::TagHierarchy => Some(&TAG_HIERARCHY_METADATA),
+            Self::TagStatistics => Some(&TAG_STATISTICS_METADATA),
+            Self::AlarmStatistics => Some(&ALARM_STATISTICS_METADATA),
+            Self::LoggedAlarmStatistics => Some(&LOGGED_ALARM_STATISTICS_METADATA),
+            _ => None,
+        }
+    }
+
+    /// Relations listed in pg_catalog.pg_class
+    pub fn pg_class_relations() -> Vec<Self> {
+        let mut relations = Self::public_tables();
+        relations.extend([
+            Self::PgClass,
+            Self::PgNamespace,
+            Self::PgAttribute,
+            Self::PgProc,
+            Self::PgType,
+            Self::PgIndex,
+            Self::PgDatabase,
+        ]);
+        relations
+    }
+
+    /// pg_class OID of the relation. Catalog tables use their PostgreSQL OIDs,
+    /// WinCC tables start at 16384 like user tables in PostgreSQL (see pg_catalog_handler).
+    pub fn relation_oid(&self) -> Option<i32> {
+        match self {
+            Self::TagValues => Some(16384),
+            Self::LoggedTagValues => Some(16385),
+            Self::ActiveAlarms => Some(16386),
+            Self::LoggedAlarms => Some(16387),
+            Self::TagList => Some(16388),
+            Self::TagHierarchy => Some(16389),
+            Self::TagValueChanges => Some(16390),
+            Self::TagStatistics => Some(16391),
+            Self::AlarmStatistics => Some(16392),
+            Self::LoggedAlarmStatistics => Some(16393),
+            Self::LoggedTagValuesIncremental => Some(16394),
+            Self::ActiveAlarmsChanges => Some(16395),
+            Self::LoggedTagValuesSummary => Some(16396),
+            Self::PgClass => Some(1259),
+            Self::PgNamespace => Some(2615),
+            Self::PgAttribute => Some(1249),
+            Self::PgProc => Some(1255),
+            Self::PgType => Some(1247),
+            Self::PgIndex => Some(2610),
+            Self::PgDatabase => Some(1262),
+            _ => None,
+        }
+    }
+
+    /// Unqualified relation name and namespace OID as shown in pg_class
+    pub fn relation_name_and_namespace(&self) -> (String, i32) {
+        let name = self.to_string();
+        match name.strip_prefix("pg_catalog.") {
+            Some(catalog_name) => (catalog_name.to_string(), PG_CATALOG_NAMESPACE_OID),
+            None => (name, PUBLIC_NAMESPACE_OID),
+        }
+    }
+
+    pub fn is_pg_catalog(&self) -> bool {
+        matches!(
+            self,
+            Self::PgClass
+                | Self::PgNamespace
+                | Self::PgAttribute
+                | Self::PgProc
+                | Self::PgType
+                | Self::PgIndex
+                | Self::PgDatabase
+                | Self::PgStatDatabase
+                | Self::PgRoles
+                | Self::PgUser
+        )
+    }
+
+    pub fn is_information_schema(&self) -> bool {
+        matches!(
+            self,
+            Self::InformationSchemaTables
+                | Self::InformationSchemaColumns
+                | Self::InformationSchemaKeyColumnUsage
+                | Self::InformationSchemaTableConstraints
+        )
+    }
+
+    /// pg_catalog and information_schema tables, built locally and often joined with each other
+    pub fn is_catalog(&self) -> bool {
+        self.is_pg_catalog() || self.is_information_schema()
+    }
+
+    /// Tables whose rows are fetched from the GraphQL server
+    pub fn uses_graphql(&self) -> bool {
+        matches!(
+            self,
+            Self::TagValues
+                | Self::TagValueChanges
+                | Self::LoggedTagValues
+                | Self::LoggedTagValuesIncremental
+                | Self::LoggedTagValuesResampled
+                | Self::LoggedTagValuesSummary
+                | Self::ActiveAlarms
+                | Self::ActiveAlarmsChanges
+                | Self::LoggedAlarms
+                | Self::TagList
+                | Self::TagHierarchy
+                | Self::TagStatistics
+                | Self::AlarmStatistics
+                | Self::LoggedAlarmStatistics
+        )
+    }
+
+    /// Primary key declared in information_schema for ORM reflection; WinCC does not enforce it
+    pub fn primary_key_columns(&self) -> &'static [&'static str] {
+        match self {
+            Self::TagValues | Self::TagValueChanges | Self::LoggedTagValues | Self::LoggedTagValuesIncremental | Self::TagList | Self::TagStatistics => &["tag_name"],
+            Self::ActiveAlarms | Self::ActiveAlarmsChanges | Self::LoggedAlarms => &["name", "instance_id"],
+            Self::TagHierarchy => &["node_id"],
+            Self::LoggedTagValuesSummary => &["tag_name", "window_start"],
+            _ => &[],
+        }
+    }
+
+    /// pg_class OID of the index behind the primary key, 1000 after the OID of its table
+    pub fn primary_key_index_oid(&self) -> Option<i32> {
+        if self.primary_key_columns().is_empty() {
+            return None;
+        }
+        self.relation_oid().map(|oid| oid + 1000)
+    }
+
+    /// Columns exposed through information_schema.columns and pg_attribute
+    pub fn catalog_column_names(&self) -> Vec<&'static str> {
+        match self.metadata() {
+            Some(metadata) => metadata.columns.iter().map(|column| column.name).collect(),
+            None => self.get_column_names(),
         }
     }
 
@@ -240,17 +894,29 @@ pub struct ColumnFilter {
     pub value: FilterValue,
 }
 
+impl ColumnFilter {
+    /// IS NULL / IS NOT NULL are left to DataFusion, which sees the Arrow RecordBatch
+    /// where fields missing from the GraphQL response have become real nulls.
+    pub fn is_null_check(&self) -> bool {
+        matches!(self.operator, FilterOperator::IsNull | FilterOperator::IsNotNull)
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum FilterOperator {
     Equal,
     NotEqual,
     Like,
+    NotLike,
+    ILike,
     In,
+    NotIn,
     GreaterThan,
     LessThan,
     GreaterThanOrEqual,
     LessThanOrEqual,
     Between,
+    NotBetween,
     IsNull,
     IsNotNull,
 }
@@ -334,9 +1000,97 @@ impl FilterValue {
 #[derive(Debug, Clone)]
 pub enum SqlResult {
     Query(QueryInfo),
-    SetStatement(String), // Contains the SET command that was executed
+    UnionQuery(UnionQueryInfo),
+    SetStatement(SetCommand),
+    ExplainPlan(ExplainInfo),
+    WriteTagValues(Vec<TagWrite>), // UPDATE tagvalues SET ...
+    AcknowledgeAlarms(Vec<i32>),   // UPDATE activealarms SET state = 'ACKNOWLEDGED', by instance_id
+    CopyTo(CopyToInfo),            // COPY ... TO STDOUT
+    Cursor(CursorCommand),         // DECLARE ... CURSOR FOR, FETCH, CLOSE
+}
+
+/// Statements on the cursors of a connection
+#[derive(Debug, Clone, PartialEq)]
+pub enum CursorCommand {
+    Declare { name: String, query: String },
+    Fetch { name: String, count: Option<usize> }, // None for FETCH ALL
+    Close { name: Option<String> },               // None for CLOSE ALL
+}
+
+/// `COPY table_or_query TO STDOUT`: the SELECT producing the rows and how they are encoded
+#[derive(Debug, Clone, PartialEq)]
+pub struct CopyToInfo {
+    pub query: String,
+    pub options: CopyOptions,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CopyFormat {
+    Text,
+    Csv,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CopyOptions {
+    pub format: CopyFormat,
+    pub header: bool,
+    pub delimiter: char,
+    pub null: String,
+    pub quote: char, // CSV only
+}
+
+impl Default for CopyOptions {
+    fn default() -> Self {
+        Self { format: CopyFormat::Text, header: false, delimiter: '\t', null: "\\N".to_string(), quote: '"' }
+    }
+}
+
+/// A SET statement and the session variables it assigns
+#[derive(Debug, Clone, PartialEq)]
+pub struct SetCommand {
+    pub command: String,                  // Normalized SET command, for logging
+    pub variables: Vec<(String, String)>, // (lowercase name, unquoted value)
 }
 
+/// New value for one tag from an UPDATE on tagvalues
+#[derive(Debug, Clone, PartialEq)]
+pub struct TagWrite {
+    pub tag_name: String,
+    pub value: serde_json::Value,
+}
+
+/// EXPLAIN [ANALYZE] of a SELECT or UNION ALL query
+#[derive(Debug, Clone)]
+pub struct ExplainInfo {
+    pub sql: String, // The explained statement without the EXPLAIN keyword
+    pub analyze: bool,
+    pub statement: Box<SqlResult>,
+}
+
+/// A UNION ALL query; every leg is fetched and executed on its own and the results are concatenated
+#[derive(Debug, Clone)]
+pub struct UnionQueryInfo {
+    pub legs: Vec<UnionLeg>,
+    pub outer_sql: Option<String>, // ORDER BY / LIMIT applied to the combined result
+}
+
+#[derive(Debug, Clone)]
+pub struct UnionLeg {
+    pub sql: String,
+    pub query_info: QueryInfo,
+}
+
+// Separator between the levels of a tag name, e.g. System1::Pump1::Speed
+pub const TAG_PATH_SEPARATOR: &str = "::";
+
+/// Trim a tag name filter value and apply the `--tag-name-case-fold` mode
+pub fn normalize_tag_name(tag_name: &str, case_fold: crate::TagNameCaseFold) -> String {
+    case_fold.apply(tag_name.trim())
+}
+
+// Table name the combined UNION ALL result is registered under for outer ORDER BY / LIMIT
+pub const UNION_RESULT_TABLE: &str = "union_all_result";
+
 #[derive(Debug, Clone)]
 pub struct QueryInfo {
     pub table: VirtualTable,
@@ -345,7 +1099,19 @@ pub struct QueryInfo {
     pub column_mappings: std::collections::HashMap<String, String>, // alias -> original_column
     pub filters: Vec<ColumnFilter>,
     pub limit: Option<i64>,
-    pub order_by: Option<OrderBy>,
+    pub offset: Option<i64>,
+    pub order_by: Vec<OrderBy>, // ORDER BY columns in query order
+    pub has_group_by: bool, // GROUP BY or DISTINCT: LIMIT applies to groups, so it must not limit the fetched rows
+    pub has_window_function: bool, // Window frames need the rows the LIMIT cuts off
+    pub derived_tables: Vec<DerivedTable>, // WinCC tables read by subqueries joined in FROM
+}
+
+/// WinCC table read by a subquery in FROM, e.g. `JOIN LATERAL (SELECT ... FROM taglist ...)`.
+/// It is fetched on its own and registered next to the main table.
+#[derive(Debug, Clone)]
+pub struct DerivedTable {
+    pub sql: String,
+    pub query_info: QueryInfo,
 }
 
 #[derive(Debug, Clone)]
@@ -355,13 +1121,38 @@ pub struct OrderBy {
 }
 
 impl QueryInfo {
+    /// GraphQL sortingMode for logged values, derived from the first ORDER BY column
+    pub fn sorting_mode(&self) -> &'static str {
+        match self.order_by.first() {
+            Some(order_by) if order_by.column == "timestamp" && !order_by.ascending => "TIME_DESC",
+            _ => "TIME_ASC",
+        }
+    }
+
+    /// LIMIT that can be pushed down to GraphQL; with GROUP BY or DISTINCT it limits groups, not rows,
+    /// and window functions like a moving average are computed over all fetched rows.
+    /// GraphQL has no offset, so the skipped rows are fetched too and DataFusion applies the OFFSET.
+    pub fn fetch_limit(&self) -> Option<i64> {
+        if self.has_group_by || self.has_window_function {
+            None
+        } else {
+            self.limit.map(|limit| limit + self.offset.unwrap_or(0))
+        }
+    }
+
     pub fn has_required_tag_filter(&self) -> bool {
         match self.table {
-            VirtualTable::TagValues | VirtualTable::LoggedTagValues => {
+            VirtualTable::TagValues
+            | VirtualTable::TagValueChanges
+            | VirtualTable::LoggedTagValues
+            | VirtualTable::LoggedTagValuesIncremental
+            | VirtualTable::LoggedTagValuesResampled
+            | VirtualTable::LoggedTagValuesSummary
+            | VirtualTable::TagStatistics => {
                 self.filters.iter().any(|f| {
                     f.column == "tag_name" && matches!(
                         f.operator, 
-                        FilterOperator::Equal | FilterOperator::In | FilterOperator::Like
+                        FilterOperator::Equal | FilterOperator::In | FilterOperator::Like | FilterOperator::ILike
                     )
                 })
             }
@@ -383,7 +1174,7 @@ impl QueryInfo {
                             return names.clone();
                         }
                     }
-                    FilterOperator::Like => {
+                    FilterOperator::Like | FilterOperator::ILike => {
                         // LIKE patterns will be resolved via browse function
                         // Return empty here since resolve_like_patterns handles this
                         return vec![];
@@ -395,6 +1186,13 @@ impl QueryInfo {
         vec![]
     }
 
+    /// `timestamp NOT BETWEEN` has no GraphQL equivalent and is applied to the fetched values
+    pub fn has_timestamp_exclusion(&self) -> bool {
+        self.filters
+            .iter()
+            .any(|f| f.column == "timestamp" && matches!(f.operator, FilterOperator::NotBetween))
+    }
+
     pub fn get_timestamp_filter(&self) -> Option<(Option<String>, Option<String>)> {
         let mut start_time = None;
         let mut end_time = None;
@@ -436,7 +1234,87 @@ impl QueryInfo {
 
     pub fn requires_browse(&self) -> bool {
         self.filters.iter().any(|f| {
-            f.column == "tag_name" && matches!(f.operator, FilterOperator::Like)
+            f.column == "tag_name" && matches!(f.operator, FilterOperator::Like | FilterOperator::ILike)
+        })
+    }
+
+    /// tagvaluechanges: `WHERE force = true` bypasses the change cache
+    pub fn is_forced(&self) -> bool {
+        self.filters.iter().any(|f| {
+            f.column == "force"
+                && matches!(f.operator, FilterOperator::Equal)
+                && f.value.as_string().is_some_and(|value| value.eq_ignore_ascii_case("true"))
+        })
+    }
+
+    /// tag_statistics: `WHERE lookback = '1 hour'` sets the window of logged values
+    pub fn get_lookback(&self) -> Option<&str> {
+        self.filters
+            .iter()
+            .find(|f| f.column == "lookback" && matches!(f.operator, FilterOperator::Equal))
+            .and_then(|f| f.value.as_string())
+    }
+
+    /// loggedtagvalues_summary: `WHERE window_size = '1 day'` sets the width of the windows
+    pub fn get_window_size(&self) -> Option<&str> {
+        self.filters
+            .iter()
+            .find(|f| f.column == "window_size" && matches!(f.operator, FilterOperator::Equal))
+            .and_then(|f| f.value.as_string())
+    }
+
+    /// loggedtagvalues_summary: time range of the logged values, from the lower bound of
+    /// window_start and the upper bound of window_end (or window_start)
+    pub fn get_window_range(&self) -> Option<(Option<String>, Option<String>)> {
+        let mut start_time = None;
+        let mut end_time = None;
+
+        for filter in self.filters.iter().filter(|f| f.column == "window_start" || f.column == "window_end") {
+            match (&filter.operator, &filter.value) {
+                (FilterOperator::GreaterThan | FilterOperator::GreaterThanOrEqual, FilterValue::Timestamp(ts)) if filter.column == "window_start" => {
+                    start_time = Some(ts.clone());
+                }
+                (FilterOperator::LessThan | FilterOperator::LessThanOrEqual, FilterValue::Timestamp(ts)) => {
+                    end_time = Some(ts.clone());
+                }
+                (FilterOperator::Between, FilterValue::Range(start, end)) => {
+                    if let (FilterValue::Timestamp(ts), "window_start") = (start.as_ref(), filter.column.as_str()) {
+                        start_time = Some(ts.clone());
+                    }
+                    if let FilterValue::Timestamp(ts) = end.as_ref() {
+                        end_time = Some(ts.clone());
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if start_time.is_some() || end_time.is_some() {
+            Some((start_time, end_time))
+        } else {
+            None
+        }
+    }
+
+    /// loggedtagvalues_resampled: width of the buckets in seconds, from the table function arguments
+    pub fn get_resample_interval(&self) -> Option<i64> {
+        self.filters
+            .iter()
+            .find(|f| f.column == "interval_seconds" && matches!(f.operator, FilterOperator::Equal))
+            .and_then(|f| f.value.as_integer())
+    }
+
+    /// loggedtagvalues_resampled: aggregation of the values within a bucket (avg, min, max, first, last)
+    pub fn get_resample_method(&self) -> Option<&str> {
+        self.filters
+            .iter()
+            .find(|f| f.column == "method" && matches!(f.operator, FilterOperator::Equal))
+            .and_then(|f| f.value.as_string())
+    }
+
+    pub fn has_tag_name_exclusions(&self) -> bool {
+        self.filters.iter().any(|f| {
+            f.column == "tag_name" && matches!(f.operator, FilterOperator::NotIn | FilterOperator::NotLike)
         })
     }
 
@@ -453,6 +1331,19 @@ impl QueryInfo {
             .collect()
     }
 
+    pub fn get_ilike_patterns(&self) -> Vec<String> {
+        self.filters
+            .iter()
+            .filter_map(|f| {
+                if f.column == "tag_name" && matches!(f.operator, FilterOperator::ILike) {
+                    f.value.as_string().map(|s| s.to_string())
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
     pub fn get_name_filters(&self) -> Vec<String> {
         for filter in &self.filters {
             if filter.column == "tag_name" {
@@ -472,6 +1363,13 @@ impl QueryInfo {
                             return vec![pattern.to_string()];
                         }
                     }
+                    FilterOperator::ILike => {
+                        // Tag names are case-sensitive on the wire; results are re-checked
+                        // case-insensitively in apply_browse_filters
+                        if let Some(pattern) = filter.value.as_string() {
+                            return vec![pattern.to_uppercase()];
+                        }
+                    }
                     _ => {}
                 }
             }
@@ -479,6 +1377,36 @@ impl QueryInfo {
         vec!["*".to_string()] // Default wildcard
     }
 
+    /// Browse name filters for tag_hierarchy. A parent_id selects the nodes below it, a node_id
+    /// the node itself and its descendants; DataFusion re-applies the exact WHERE clause.
+    pub fn get_hierarchy_name_filters(&self) -> Vec<String> {
+        let mut name_filters = Vec::new();
+        for filter in &self.filters {
+            let values: Vec<&str> = match (&filter.operator, &filter.value) {
+                (FilterOperator::Equal | FilterOperator::Like, FilterValue::String(value)) => vec![value.as_str()],
+                (FilterOperator::In, FilterValue::List(values)) => values.iter().map(String::as_str).collect(),
+                _ => continue,
+            };
+            for value in values {
+                match (filter.column.as_str(), &filter.operator) {
+                    ("parent_id", FilterOperator::Equal | FilterOperator::In) => {
+                        name_filters.push(format!("{}{}*", value, TAG_PATH_SEPARATOR));
+                    }
+                    ("node_id", FilterOperator::Equal | FilterOperator::In) => {
+                        name_filters.push(value.to_string());
+                        name_filters.push(format!("{}{}*", value, TAG_PATH_SEPARATOR));
+                    }
+                    ("node_id", FilterOperator::Like) => name_filters.push(value.replace('%', "*")),
+                    _ => {}
+                }
+            }
+        }
+        if name_filters.is_empty() {
+            name_filters.push("*".to_string());
+        }
+        name_filters
+    }
+
     pub fn get_object_type_filters(&self) -> Vec<String> {
         for filter in &self.filters {
             if filter.column == "object_type" {
@@ -0,0 +1,60 @@
+use anyhow::Result;
+use opentelemetry::propagation::TextMapPropagator;
+use opentelemetry::trace::{TraceContextExt, TracerProvider as _};
+use opentelemetry::Context;
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::SdkTracerProvider;
+use opentelemetry_sdk::Resource;
+use std::collections::HashMap;
+use tracing::Subscriber;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Layer;
+
+const SERVICE_NAME: &str = env!("CARGO_PKG_NAME");
+
+/// Create a tracer provider that exports spans in batches to an OTLP gRPC collector
+pub fn init_tracer_provider(endpoint: &str) -> Result<SdkTracerProvider> {
+    let exporter = opentelemetry_otlp::SpanExporter::builder()
+        .with_tonic()
+        .with_endpoint(endpoint)
+        .build()?;
+
+    Ok(SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(Resource::builder().with_service_name(SERVICE_NAME).build())
+        .build())
+}
+
+/// tracing layer forwarding spans to the given provider
+pub fn layer<S>(provider: &SdkTracerProvider) -> impl Layer<S>
+where
+    S: Subscriber + for<'span> LookupSpan<'span>,
+{
+    tracing_opentelemetry::layer()
+        .with_tracer(provider.tracer(SERVICE_NAME))
+        // The pg.connection span is entered before the startup message is read; without
+        // activation on enter its parent can still be taken from application_name
+        .with_context_activation(false)
+}
+
+/// Extract a W3C trace context from application_name, either the plain traceparent value
+/// or a `traceparent=<value>` token among other whitespace separated words
+pub fn parent_context_from_application_name(application_name: &str) -> Option<Context> {
+    let propagator = TraceContextPropagator::new();
+    application_name.split_whitespace().find_map(|token| {
+        let value = token.strip_prefix("traceparent=").unwrap_or(token);
+        let carrier = HashMap::from([("traceparent".to_string(), value.to_string())]);
+        let context = propagator.extract(&carrier);
+        context.span().span_context().is_valid().then_some(context)
+    })
+}
+
+/// Attach the span to a remote parent found in application_name; returns whether a parent was set
+pub fn set_parent_from_application_name(span: &tracing::Span, application_name: &str) -> bool {
+    match parent_context_from_application_name(application_name) {
+        Some(context) => span.set_parent(context).is_ok(),
+        None => false,
+    }
+}
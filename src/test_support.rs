@@ -0,0 +1,124 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+/// One HTTP request received by the mock server
+pub struct MockRequest {
+    pub headers: String,
+    pub body: String,
+}
+
+impl MockRequest {
+    /// The body parsed as a GraphQL request ({"query": ..., "variables": ...})
+    pub fn json(&self) -> serde_json::Value {
+        serde_json::from_str(&self.body).unwrap()
+    }
+
+    /// Text of the GraphQL query or mutation
+    pub fn query(&self) -> String {
+        self.json()["query"].as_str().unwrap_or_default().to_string()
+    }
+
+    /// Value of a header, the name is compared case-insensitively
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.lines().find_map(|line| {
+            let (key, value) = line.split_once(':')?;
+            key.eq_ignore_ascii_case(name).then(|| value.trim())
+        })
+    }
+}
+
+/// Answer of the mock server to one request
+pub enum MockResponse {
+    /// 200 OK with a JSON body
+    Json(String),
+    /// The same after a delay, for slow servers and timeouts
+    Delayed(Duration, String),
+    /// An error status line like "503 Service Unavailable" without body
+    Status(&'static str),
+    /// Never answer, the connection stays open
+    Hang,
+}
+
+impl MockResponse {
+    pub fn json(value: serde_json::Value) -> Self {
+        MockResponse::Json(value.to_string())
+    }
+}
+
+/// Successful login response with a fixed token
+pub fn login_response() -> MockResponse {
+    MockResponse::Json(r#"{"data":{"login":{"token":"token","expires":"2099-01-01T00:00:00Z","error":{"code":"0"}}}}"#.to_string())
+}
+
+/// Mock GraphQL endpoint for tests: a minimal HTTP/1.1 server on a free port that answers every
+/// request with what `respond` returns. Connections are kept alive; returns the GraphQL URL.
+pub async fn spawn_graphql_server(respond: impl Fn(&MockRequest) -> MockResponse + Send + Sync + 'static) -> String {
+    spawn_counting_graphql_server(respond).await.0
+}
+
+/// Same as `spawn_graphql_server`, also returning the number of accepted TCP connections
+pub async fn spawn_counting_graphql_server(
+    respond: impl Fn(&MockRequest) -> MockResponse + Send + Sync + 'static,
+) -> (String, Arc<AtomicUsize>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let connections = Arc::new(AtomicUsize::new(0));
+    let accepted = connections.clone();
+    let respond = Arc::new(respond);
+    tokio::spawn(async move {
+        while let Ok((mut socket, _)) = listener.accept().await {
+            accepted.fetch_add(1, Ordering::SeqCst);
+            let respond = respond.clone();
+            tokio::spawn(async move {
+                let mut received = Vec::new();
+                while let Some(request) = read_http_request(&mut socket, &mut received).await {
+                    let response = match respond(&request) {
+                        MockResponse::Json(body) => json_response(&body),
+                        MockResponse::Delayed(delay, body) => {
+                            tokio::time::sleep(delay).await;
+                            json_response(&body)
+                        }
+                        MockResponse::Status(status) => format!("HTTP/1.1 {}\r\nContent-Length: 0\r\n\r\n", status),
+                        MockResponse::Hang => return std::future::pending().await,
+                    };
+                    if socket.write_all(response.as_bytes()).await.is_err() {
+                        return;
+                    }
+                }
+            });
+        }
+    });
+    (format!("http://{}/graphql", addr), connections)
+}
+
+fn json_response(body: &str) -> String {
+    format!("HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}", body.len(), body)
+}
+
+// Read the next request of a keep-alive connection; bytes of a following request stay in
+// `received`. None when the client closed the connection.
+async fn read_http_request(socket: &mut TcpStream, received: &mut Vec<u8>) -> Option<MockRequest> {
+    let mut buffer = [0u8; 4096];
+    loop {
+        if let Some(header_end) = received.windows(4).position(|window| window == b"\r\n\r\n") {
+            let headers = String::from_utf8_lossy(&received[..header_end]).to_string();
+            let content_length = headers
+                .lines()
+                .find_map(|line| line.to_lowercase().strip_prefix("content-length:").map(|v| v.trim().parse::<usize>().unwrap()))
+                .unwrap_or(0);
+            let request_end = header_end + 4 + content_length;
+            if received.len() >= request_end {
+                let body = String::from_utf8_lossy(&received[header_end + 4..request_end]).to_string();
+                received.drain(..request_end);
+                return Some(MockRequest { headers, body });
+            }
+        }
+        match socket.read(&mut buffer).await {
+            Ok(n) if n > 0 => received.extend_from_slice(&buffer[..n]),
+            _ => return None,
+        }
+    }
+}
@@ -2,6 +2,7 @@ use anyhow::{anyhow, Result};
 use rustls::pki_types::CertificateDer;
 use rustls::{ServerConfig, RootCertStore};
 use rustls_pemfile::{certs, private_key};
+use sha2::{Digest, Sha256, Sha384, Sha512};
 use std::fs::File;
 use std::io::BufReader;
 use std::sync::Arc;
@@ -45,17 +46,7 @@ pub fn create_server_config(tls_config: &TlsConfig) -> Result<Arc<ServerConfig>>
     debug!("   🔑 Private key: {}", tls_config.key_path);
 
     // Load certificate chain
-    let cert_file = File::open(&tls_config.cert_path)
-        .map_err(|e| anyhow!("Failed to open certificate file '{}': {}", tls_config.cert_path, e))?;
-    let mut cert_reader = BufReader::new(cert_file);
-    let cert_chain: Vec<CertificateDer> = certs(&mut cert_reader)
-        .collect::<Result<Vec<_>, _>>()
-        .map_err(|e| anyhow!("Failed to parse certificate file: {}", e))?;
-
-    if cert_chain.is_empty() {
-        return Err(anyhow!("No certificates found in file '{}'", tls_config.cert_path));
-    }
-
+    let cert_chain = load_cert_chain(&tls_config.cert_path)?;
     info!("✅ Loaded {} certificate(s)", cert_chain.len());
 
     // Load private key
@@ -116,3 +107,127 @@ pub fn create_server_config(tls_config: &TlsConfig) -> Result<Arc<ServerConfig>>
     Ok(Arc::new(server_config))
 }
 
+
+fn load_cert_chain(cert_path: &str) -> Result<Vec<CertificateDer<'static>>> {
+    let cert_file = File::open(cert_path)
+        .map_err(|e| anyhow!("Failed to open certificate file '{}': {}", cert_path, e))?;
+    let mut cert_reader = BufReader::new(cert_file);
+    let cert_chain: Vec<CertificateDer> = certs(&mut cert_reader)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| anyhow!("Failed to parse certificate file: {}", e))?;
+
+    if cert_chain.is_empty() {
+        return Err(anyhow!("No certificates found in file '{}'", cert_path));
+    }
+    Ok(cert_chain)
+}
+
+/// Channel binding data of the configured server certificate for SCRAM-SHA-256-PLUS
+pub fn load_server_end_point(tls_config: &TlsConfig) -> Result<Vec<u8>> {
+    let cert_chain = load_cert_chain(&tls_config.cert_path)?;
+    Ok(tls_server_end_point(&cert_chain[0]))
+}
+
+/// RFC 5929 tls-server-end-point: the certificate hashed with the hash function of its
+/// signature algorithm, where MD5, SHA-1 and unrecognized algorithms use SHA-256
+pub fn tls_server_end_point(cert: &CertificateDer) -> Vec<u8> {
+    // OIDs of sha384/sha512WithRSAEncryption and ecdsa-with-SHA384/SHA512
+    const SHA384_OIDS: [&[u8]; 2] = [
+        &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x0c],
+        &[0x2a, 0x86, 0x48, 0xce, 0x3d, 0x04, 0x03, 0x03],
+    ];
+    const SHA512_OIDS: [&[u8]; 2] = [
+        &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x0d],
+        &[0x2a, 0x86, 0x48, 0xce, 0x3d, 0x04, 0x03, 0x04],
+    ];
+
+    match signature_algorithm_oid(cert) {
+        Some(oid) if SHA384_OIDS.contains(&oid) => Sha384::digest(cert).to_vec(),
+        Some(oid) if SHA512_OIDS.contains(&oid) => Sha512::digest(cert).to_vec(),
+        _ => Sha256::digest(cert).to_vec(),
+    }
+}
+
+// Certificate ::= SEQUENCE { tbsCertificate, signatureAlgorithm SEQUENCE { OID, ... }, signature }
+fn signature_algorithm_oid(cert: &[u8]) -> Option<&[u8]> {
+    let (_, header_len, _) = der_header(cert)?;
+    let certificate = cert.get(header_len..)?;
+    let (_, tbs_header_len, tbs_len) = der_header(certificate)?;
+    let algorithm = certificate.get(tbs_header_len + tbs_len..)?;
+    let (_, algorithm_header_len, _) = der_header(algorithm)?;
+    let oid = algorithm.get(algorithm_header_len..)?;
+    let (tag, oid_header_len, oid_len) = der_header(oid)?;
+    if tag != 0x06 {
+        return None;
+    }
+    oid.get(oid_header_len..oid_header_len + oid_len)
+}
+
+// Tag, header length and content length of a DER element
+fn der_header(data: &[u8]) -> Option<(u8, usize, usize)> {
+    let tag = *data.first()?;
+    let first_length_byte = *data.get(1)? as usize;
+    if first_length_byte < 0x80 {
+        return Some((tag, 2, first_length_byte));
+    }
+    let length_bytes = first_length_byte & 0x7f;
+    if length_bytes == 0 || length_bytes > 4 {
+        return None;
+    }
+    let length = data
+        .get(2..2 + length_bytes)?
+        .iter()
+        .fold(0usize, |length, byte| (length << 8) | *byte as usize);
+    Some((tag, 2 + length_bytes, length))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    // Self-signed P-256 certificate signed with ecdsa-with-SHA256
+    pub(crate) const SHA256_SIGNED_CERT: &str = "-----BEGIN CERTIFICATE-----
+MIIBfzCCASWgAwIBAgIUBgarKB9z1BmFLdw7EeXAsX1BPLIwCgYIKoZIzj0EAwIw
+FDESMBAGA1UEAwwJbG9jYWxob3N0MCAXDTI2MTAxNTA0NTAxMFoYDzIxMjYwOTIx
+MDQ1MDEwWjAUMRIwEAYDVQQDDAlsb2NhbGhvc3QwWTATBgcqhkjOPQIBBggqhkjO
+PQMBBwNCAATkflSgZlZqfclDfvgk1eTnGIiLvzFL+/Hg+/8pr7poIwi00tcNWZyE
+Ec2yYBoU1AMuOoZZ9zpnT8zCvhpyLDf8o1MwUTAdBgNVHQ4EFgQUwqO/IEmApp3D
+NzYvuGaRontVSlcwHwYDVR0jBBgwFoAUwqO/IEmApp3DNzYvuGaRontVSlcwDwYD
+VR0TAQH/BAUwAwEB/zAKBggqhkjOPQQDAgNIADBFAiBqzPNvphSXlPkCMJty09NU
+wwZ5gq66a5RIwxi+6SseKwIhAIrS4I6lSbzyMID7oNj3b8KiatK8ubeb9dyU97jO
+Fpqb
+-----END CERTIFICATE-----
+";
+
+    // Self-signed P-384 certificate signed with ecdsa-with-SHA384
+    const SHA384_SIGNED_CERT: &str = "-----BEGIN CERTIFICATE-----
+MIIBvDCCAUKgAwIBAgIUB5R1FrxgncGFFJvsk8i5mAS3Q/IwCgYIKoZIzj0EAwMw
+FDESMBAGA1UEAwwJbG9jYWxob3N0MCAXDTI2MTAxNTA0NTAxNFoYDzIxMjYwOTIx
+MDQ1MDE0WjAUMRIwEAYDVQQDDAlsb2NhbGhvc3QwdjAQBgcqhkjOPQIBBgUrgQQA
+IgNiAARkA4lWIAVRXzgXnQ3HW4vCqFEJ+I36SqxpUnGP0PBb1t+c7dGiS18vqAas
+7ZXEcMCn5TViE2vKb+PtJhfBskErnXaE4oLg6530V686FJxvlJAxB0NEQjjN0fPK
+DlOOkmijUzBRMB0GA1UdDgQWBBQLmM/3gH5W9WOHkzY+3XAnBbH/BjAfBgNVHSME
+GDAWgBQLmM/3gH5W9WOHkzY+3XAnBbH/BjAPBgNVHRMBAf8EBTADAQH/MAoGCCqG
+SM49BAMDA2gAMGUCMQD49Hz37AGGJ+t2MtJK4ho43I+8xMH9vCYMdYhhlvpoiYW/
+ZIDTWmkucqm3htMTU+sCMFrHW64gGAF6yn5vII+jlsHCyS6ZBXIFnmcmsmfSFb7b
++78RAnzNv1Z7iCYFYaejIQ==
+-----END CERTIFICATE-----
+";
+
+    pub(crate) fn parse_cert(pem: &str) -> CertificateDer<'static> {
+        certs(&mut pem.as_bytes()).next().unwrap().unwrap()
+    }
+
+    #[test]
+    fn test_tls_server_end_point_uses_signature_hash() {
+        // Expected values from `openssl x509 -outform der | sha256sum` / `sha384sum`
+        assert_eq!(
+            hex::encode(tls_server_end_point(&parse_cert(SHA256_SIGNED_CERT))),
+            "a64f90b18b033e5bd15699e3e2494e0e3adeeb7ad9372665bad7525ebd2eb146"
+        );
+        assert_eq!(
+            hex::encode(tls_server_end_point(&parse_cert(SHA384_SIGNED_CERT))),
+            "d42d6f12f16b4f61fecf966bcf5a0ea610de821acbbd714797733b1b560b8df4fc030a340ade03be8c5cd4192811a65c"
+        );
+    }
+}
@@ -9,6 +9,7 @@ import argparse
 import time
 import os
 import traceback
+from decimal import Decimal
 from typing import List, Tuple, Optional
 
 try:
@@ -267,6 +268,37 @@ class QueryTester:
             if self.verbose:
                 print(f"{Colors.DIM}{traceback.format_exc()}{Colors.RESET}")
     
+    def check_column_types(self) -> bool:
+        """Check that numeric columns arrive typed as float8 and decimals as numeric, not as text"""
+        print(f"{Colors.HEADER}🔎 Checking column types of tagvalues...{Colors.RESET}")
+        try:
+            cursor = self.connection.cursor()
+            cursor.execute("SELECT numeric_value FROM tagvalues WHERE tag_name LIKE '%HMI_Tag_%'")
+            type_code = cursor.description[0].type_code
+            row = cursor.fetchone()
+            cursor.close()
+            if type_code != 701:
+                print(f"{Colors.ERROR}❌ numeric_value has type OID {type_code}, expected 701 (float8){Colors.RESET}\n")
+                return False
+            if row is not None and row[0] is not None and not isinstance(row[0], float):
+                print(f"{Colors.ERROR}❌ numeric_value arrived as {type(row[0]).__name__}, expected float{Colors.RESET}\n")
+                return False
+            print(f"{Colors.SUCCESS}✅ numeric_value is float8{Colors.RESET}\n")
+
+            cursor = self.connection.cursor()
+            cursor.execute("SELECT CAST(12.5 AS DECIMAL(10,2)) AS amount")
+            type_code = cursor.description[0].type_code
+            row = cursor.fetchone()
+            cursor.close()
+            if type_code != 1700 or row[0] != Decimal("12.50"):
+                print(f"{Colors.ERROR}❌ DECIMAL arrived as {row[0]!r} with type OID {type_code}, expected Decimal('12.50') (numeric){Colors.RESET}\n")
+                return False
+            print(f"{Colors.SUCCESS}✅ DECIMAL is numeric{Colors.RESET}\n")
+            return True
+        except psycopg2.Error as e:
+            print(f"{Colors.ERROR}❌ Column type check failed: {e}{Colors.RESET}\n")
+            return False
+    
     def run_tests(self, queries_file: str, query_filter: Optional[int] = None) -> None:
         """Run all queries from the file or a specific query"""
         if not self.connect():
@@ -293,6 +325,12 @@ class QueryTester:
             successful = 0
             failed = 0
             
+            if not query_filter:
+                if self.check_column_types():
+                    successful += 1
+                else:
+                    failed += 1
+            
             for query_num, description, query in queries:
                 try:
                     self.execute_query(query_num, description, query)